@@ -0,0 +1,142 @@
+//! `usml export access` で書き出すアクセス行列の CSV・HTML
+//!
+//! セルは参照するカラムに `R`、値をレスポンスに返す場合はそのフィールドを添える。
+//! `[governance] pii` に一致するカラムには見出しに `PII` を付ける
+
+use std::fmt::Write;
+
+use usml_core::export::{AccessCell, AccessColumn, AccessMatrix};
+
+use super::csv_field;
+
+pub fn csv(matrix: &AccessMatrix) -> String {
+    let mut header = vec![
+        "file".to_string(),
+        "usecase".to_string(),
+        "endpoint".to_string(),
+    ];
+    header.extend(matrix.columns.iter().map(|c| csv_field(&column_label(c))));
+    let mut out = header.join(",") + "\n";
+    for row in &matrix.usecases {
+        let mut fields = vec![
+            csv_field(&row.file),
+            csv_field(&row.usecase),
+            csv_field(row.endpoint.as_deref().unwrap_or_default()),
+        ];
+        fields.extend(
+            matrix
+                .columns
+                .iter()
+                .map(|c| csv_field(&row.cells.get(&key(c)).map(marker).unwrap_or_default())),
+        );
+        out.push_str(&fields.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+pub fn html(matrix: &AccessMatrix) -> String {
+    let mut out = String::from(
+        r#"<!DOCTYPE html>
+<html lang="ja">
+<head>
+<meta charset="utf-8">
+<title>カラムアクセス行列</title>
+<style>
+body { font-family: sans-serif; margin: 24px; color: #1f2937; }
+table { border-collapse: collapse; font-size: 13px; }
+th, td { border: 1px solid #d1d5db; padding: 4px 8px; text-align: center; }
+thead th { background: #e0e7ff; writing-mode: vertical-rl; white-space: nowrap; }
+thead th.pii { background: #fecaca; }
+tbody th { text-align: left; white-space: nowrap; }
+td.exposed { background: #eef2ff; }
+td.exposed.pii { background: #fca5a5; font-weight: bold; }
+td small { display: block; color: #4b5563; font-weight: normal; }
+</style>
+</head>
+<body>
+<h1>カラムアクセス行列</h1>
+<p>R: 参照するカラム（下は値を返すレスポンスフィールド）。PII: 個人情報を含むカラム</p>
+<table>
+<thead>
+<tr><th>usecase</th><th>エンドポイント</th>"#,
+    );
+    for column in &matrix.columns {
+        let class = if column.pii { " class=\"pii\"" } else { "" };
+        let _ = write!(
+            out,
+            "<th{}>{}</th>",
+            class,
+            escape_html(&column_label(column))
+        );
+    }
+    out.push_str("</tr>\n</thead>\n<tbody>\n");
+    for row in &matrix.usecases {
+        let _ = write!(
+            out,
+            "<tr><th title=\"{}\">{}</th><td>{}</td>",
+            escape_html(&row.file),
+            escape_html(&row.usecase),
+            escape_html(row.endpoint.as_deref().unwrap_or_default())
+        );
+        for column in &matrix.columns {
+            let Some(cell) = row.cells.get(&key(column)) else {
+                out.push_str("<td></td>");
+                continue;
+            };
+            let mut classes = Vec::new();
+            if !cell.fields.is_empty() {
+                classes.push("exposed");
+                if column.pii {
+                    classes.push("pii");
+                }
+            }
+            let class = if classes.is_empty() {
+                String::new()
+            } else {
+                format!(" class=\"{}\"", classes.join(" "))
+            };
+            let _ = write!(out, "<td{}>R", class);
+            if !cell.fields.is_empty() {
+                let _ = write!(
+                    out,
+                    "<small>{}</small>",
+                    escape_html(&cell.fields.join(", "))
+                );
+            }
+            out.push_str("</td>");
+        }
+        out.push_str("</tr>\n");
+    }
+    out.push_str("</tbody>\n</table>\n</body>\n</html>\n");
+    out
+}
+
+fn key(column: &AccessColumn) -> String {
+    format!("{}.{}", column.table, column.column)
+}
+
+fn column_label(column: &AccessColumn) -> String {
+    if column.pii {
+        format!("{} (PII)", key(column))
+    } else {
+        key(column)
+    }
+}
+
+/// `R`、値を返す場合は `R (フィールド, ...)`
+fn marker(cell: &AccessCell) -> String {
+    if cell.fields.is_empty() {
+        "R".to_string()
+    } else {
+        format!("R ({})", cell.fields.join(", "))
+    }
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,391 @@
+//! `usml add` の対話モード
+//!
+//! import 先の DBML・OpenAPI からテーブル・カラム・フィールド・パラメータの選択肢を作り、
+//! 選んだ内容を [`Editor`] でドキュメントに書き足す。スキーマが解決できない場合は名前を入力する
+
+use dialoguer::{Input, MultiSelect, Select};
+use usml_core::ast::UsmlDocument;
+use usml_core::edit::{EditError, Editor, Value};
+use usml_core::model::{FieldKind, Model};
+use usml_core::resolver::OpenapiProperty;
+use usml_core::validator::ResolveContext;
+
+/// 一覧に無い名前を入力するための選択肢
+const OTHER: &str = "（一覧に無い名前を入力する）";
+
+const OPERATORS: &[&str] = &["=", "!=", ">", ">=", "<", "<=", "LIKE"];
+
+/// 対話で組み立てた、ドキュメントに書き足す要素
+pub enum Addition {
+    Field {
+        /// 追加先の配列フィールド。None ならトップレベル
+        parent: Option<String>,
+        entries: Vec<(&'static str, Value)>,
+    },
+    Filter(Vec<(&'static str, Value)>),
+    Transform(Vec<(&'static str, Value)>),
+}
+
+impl Addition {
+    pub fn apply(&self, editor: &mut Editor) -> Result<(), EditError> {
+        match self {
+            Addition::Field { parent, entries } => editor.add_field(parent.as_deref(), entries),
+            Addition::Filter(entries) => editor.add_filter(entries),
+            Addition::Transform(entries) => editor.add_transform(entries),
+        }
+    }
+}
+
+/// 選択肢の元になるドキュメントと解決済みのスキーマ
+pub struct Prompter<'a> {
+    doc: &'a UsmlDocument,
+    model: Model,
+    context: &'a ResolveContext,
+}
+
+impl<'a> Prompter<'a> {
+    pub fn new(doc: &'a UsmlDocument, context: &'a ResolveContext) -> Self {
+        Prompter {
+            doc,
+            model: Model::build(doc),
+            context,
+        }
+    }
+
+    /// レスポンスフィールドを 1 つ組み立てる
+    pub fn field(&self) -> dialoguer::Result<Addition> {
+        let arrays: Vec<&str> = self
+            .model
+            .fields
+            .iter()
+            .filter(|f| f.kind == FieldKind::Array)
+            .map(|f| f.path.as_str())
+            .collect();
+        let parent = if arrays.is_empty() {
+            None
+        } else {
+            let items: Vec<&str> = std::iter::once("（トップレベル）")
+                .chain(arrays.iter().copied())
+                .collect();
+            let index = Select::new()
+                .with_prompt("追加先")
+                .items(&items)
+                .default(0)
+                .interact()?;
+            index.checked_sub(1).map(|i| arrays[i].to_string())
+        };
+
+        // OpenAPI のプロパティのうち、まだ response_mapping に無いもの
+        let mapped: Vec<&str> = self
+            .model
+            .fields
+            .iter()
+            .filter(|f| f.parent.map(|i| self.model.fields[i].path.as_str()) == parent.as_deref())
+            .map(|f| f.name.as_str())
+            .collect();
+        let properties: &[OpenapiProperty] = match (&self.context.openapi, &parent) {
+            (Some(openapi), Some(parent)) => openapi
+                .property(parent)
+                .map_or(&[], |p| p.properties.as_slice()),
+            (Some(openapi), None) => &openapi.properties,
+            (None, _) => &[],
+        };
+        let candidates: Vec<(String, String)> = properties
+            .iter()
+            .filter(|p| !mapped.contains(&p.name.as_str()))
+            .map(|p| (p.name.clone(), property_label(p)))
+            .collect();
+        let name = choose("レスポンスフィールド", &candidates)?;
+
+        let source = self.column("source")?;
+        let mut entries = vec![
+            ("field", Value::from(name)),
+            ("source", source.clone().into()),
+        ];
+
+        let table = source.split_once('.').map_or("", |(table, _)| table);
+        if self.needs_join(table) {
+            let on: String = Input::new()
+                .with_prompt(format!(
+                    "{} との結合条件（空欄なら join を書かない）",
+                    table
+                ))
+                .allow_empty(true)
+                .interact_text()?;
+            if !on.is_empty() {
+                entries.push((
+                    "join",
+                    Value::Mapping(vec![("table", table.into()), ("on", on.into())]),
+                ));
+            }
+        }
+        Ok(Addition::Field { parent, entries })
+    }
+
+    /// フィルタを 1 つ組み立てる
+    pub fn filter(&self) -> dialoguer::Result<Addition> {
+        let used: Vec<&str> = self
+            .doc
+            .usecase
+            .filters
+            .iter()
+            .flat_map(|f| std::iter::once(f.param.as_str()).chain(f.limit_param.as_deref()))
+            .collect();
+        let parameters: Vec<_> = self
+            .context
+            .openapi
+            .iter()
+            .flat_map(|openapi| &openapi.parameter_details)
+            .filter(|p| !used.contains(&p.name.as_str()))
+            .collect();
+        let candidates: Vec<(String, String)> = parameters
+            .iter()
+            .map(|p| {
+                let location = p.location.as_deref().unwrap_or("query");
+                let label = match &p.schema_type {
+                    Some(ty) => format!("{} ({}, {})", p.name, location, ty),
+                    None => format!("{} ({})", p.name, location),
+                };
+                (p.name.clone(), label)
+            })
+            .collect();
+        let param = choose("リクエストパラメータ", &candidates)?;
+        let required = parameters.iter().any(|p| p.name == param && p.required);
+
+        let kinds = ["WHERE", "ORDER_BY", "PAGINATION"];
+        let maps_to = kinds[Select::new()
+            .with_prompt("maps_to")
+            .items(kinds)
+            .default(0)
+            .interact()?];
+        let mut entries = vec![
+            ("param", Value::from(param.as_str())),
+            ("maps_to", maps_to.into()),
+        ];
+        match maps_to {
+            "WHERE" => {
+                let column = self.column("条件のカラム")?;
+                let operator = OPERATORS[Select::new()
+                    .with_prompt("演算子")
+                    .items(OPERATORS)
+                    .default(0)
+                    .interact()?];
+                entries.push((
+                    "condition",
+                    format!("{} {} :{}", column, operator, param).into(),
+                ));
+                if required {
+                    entries.push(("required", "true".into()));
+                }
+            }
+            "ORDER_BY" => {
+                let columns = self.columns("ソートを許すカラム")?;
+                let default_column = match columns.as_slice() {
+                    [] => self.column("default_column")?,
+                    [column] => column.clone(),
+                    _ => columns[Select::new()
+                        .with_prompt("default_column")
+                        .items(&columns)
+                        .default(0)
+                        .interact()?]
+                    .clone(),
+                };
+                let directions = ["DESC", "ASC"];
+                let direction = directions[Select::new()
+                    .with_prompt("default_direction")
+                    .items(directions)
+                    .default(0)
+                    .interact()?];
+                entries.push(("default_column", default_column.into()));
+                entries.push(("default_direction", direction.into()));
+                if !columns.is_empty() {
+                    entries.push(("allowed_columns", Value::Sequence(columns)));
+                }
+                entries.push((
+                    "allowed_directions",
+                    Value::Sequence(vec!["ASC".to_string(), "DESC".to_string()]),
+                ));
+            }
+            _ => {
+                let strategies = ["offset", "cursor"];
+                let strategy = strategies[Select::new()
+                    .with_prompt("strategy")
+                    .items(strategies)
+                    .default(0)
+                    .interact()?];
+                entries.push(("strategy", strategy.into()));
+                if strategy == "cursor" {
+                    let column = self.column("cursor_field")?;
+                    let name = column.rsplit_once('.').map_or(column.as_str(), |(_, c)| c);
+                    entries.push(("cursor_field", name.into()));
+                }
+                let page_size: u32 = Input::new()
+                    .with_prompt("page_size")
+                    .default(20)
+                    .interact_text()?;
+                entries.push(("page_size", page_size.to_string().into()));
+            }
+        }
+        Ok(Addition::Filter(entries))
+    }
+
+    /// transform を 1 つ組み立てる
+    pub fn transform(&self) -> dialoguer::Result<Addition> {
+        let targeted: Vec<&str> = self
+            .doc
+            .usecase
+            .transforms
+            .iter()
+            .map(|t| t.target.as_str())
+            .collect();
+        let fields: Vec<_> = self
+            .model
+            .fields
+            .iter()
+            .filter(|f| f.kind == FieldKind::Scalar && !targeted.contains(&f.path.as_str()))
+            .collect();
+        let candidates: Vec<(String, String)> = fields
+            .iter()
+            .map(|f| (f.path.clone(), f.path.clone()))
+            .collect();
+        let target = choose("target", &candidates)?;
+
+        let kinds = ["COALESCE", "CONCAT", "MASK"];
+        let kind = kinds[Select::new()
+            .with_prompt("type")
+            .items(kinds)
+            .default(0)
+            .interact()?];
+        let mut entries = vec![
+            ("target", Value::from(target.as_str())),
+            ("type", kind.into()),
+        ];
+        match kind {
+            "MASK" => {
+                // 既に source のあるフィールドならそのカラムを隠す
+                let source = match fields
+                    .iter()
+                    .find(|f| f.path == target)
+                    .and_then(|f| f.source.as_ref())
+                {
+                    Some(source) => format!("{}.{}", source.table, source.column),
+                    None => self.column("source")?,
+                };
+                let pattern: String = Input::new()
+                    .with_prompt("mask_pattern（例: ***-****-$4）")
+                    .interact_text()?;
+                entries.push(("source", source.into()));
+                entries.push(("mask_pattern", pattern.into()));
+            }
+            _ => {
+                let mut sources = self.columns("sources（評価・連結する順に選ぶ）")?;
+                if sources.is_empty() {
+                    sources.push(self.column("sources")?);
+                }
+                entries.push(("sources", Value::Sequence(sources)));
+                let (key, prompt, default) = if kind == "CONCAT" {
+                    ("separator", "区切り文字", " ")
+                } else {
+                    ("fallback", "すべて NULL の場合の値（空欄なら書かない）", "")
+                };
+                let value: String = Input::new()
+                    .with_prompt(prompt)
+                    .default(default.to_string())
+                    .allow_empty(true)
+                    .interact_text()?;
+                if !value.is_empty() {
+                    entries.push((key, value.into()));
+                }
+            }
+        }
+        Ok(Addition::Transform(entries))
+    }
+
+    /// DBML のテーブルとカラムを順に選ぶ。DBML が無ければ `テーブル.カラム` を入力する
+    fn column(&self, prompt: &str) -> dialoguer::Result<String> {
+        let tables = &self.context.dbml_tables;
+        if tables.is_empty() {
+            return Input::new()
+                .with_prompt(format!("{}（テーブル.カラム）", prompt))
+                .interact_text();
+        }
+        let table = &tables[Select::new()
+            .with_prompt(format!("{} のテーブル", prompt))
+            .items(tables.iter().map(|t| &t.name))
+            .default(0)
+            .interact()?];
+        let labels: Vec<String> = table
+            .columns
+            .iter()
+            .map(|name| match table.column(name) {
+                Some(column) if !column.data_type.is_empty() => {
+                    format!("{} ({})", name, column.data_type)
+                }
+                _ => name.clone(),
+            })
+            .collect();
+        let column = &table.columns[Select::new()
+            .with_prompt(format!("{} のカラム", prompt))
+            .items(&labels)
+            .default(0)
+            .interact()?];
+        Ok(format!("{}.{}", table.name, column))
+    }
+
+    /// DBML の全テーブルのカラムから複数選ぶ（選んだ順ではなく一覧の順）。DBML が無ければ空
+    fn columns(&self, prompt: &str) -> dialoguer::Result<Vec<String>> {
+        let columns: Vec<String> = self
+            .context
+            .dbml_tables
+            .iter()
+            .flat_map(|t| t.columns.iter().map(move |c| format!("{}.{}", t.name, c)))
+            .collect();
+        if columns.is_empty() {
+            return Ok(Vec::new());
+        }
+        let selected = MultiSelect::new()
+            .with_prompt(format!("{}（スペースで選択、Enter で確定）", prompt))
+            .items(&columns)
+            .interact()?;
+        Ok(selected.into_iter().map(|i| columns[i].clone()).collect())
+    }
+
+    /// まだどのフィールドからも参照されていないテーブルか（最初のフィールドのテーブルは結合しない）
+    fn needs_join(&self, table: &str) -> bool {
+        let mut referenced = self
+            .model
+            .tables
+            .iter()
+            .filter(|t| !t.columns.is_empty())
+            .peekable();
+        referenced.peek().is_some()
+            && !referenced.any(|t| t.name == table || t.aliases.iter().any(|a| a == table))
+    }
+}
+
+/// 候補（値・表示名）から選ぶ。候補が無いか一覧に無い名前を選んだ場合は入力する
+fn choose(prompt: &str, candidates: &[(String, String)]) -> dialoguer::Result<String> {
+    if !candidates.is_empty() {
+        let labels: Vec<&str> = candidates
+            .iter()
+            .map(|(_, label)| label.as_str())
+            .chain([OTHER])
+            .collect();
+        let index = Select::new()
+            .with_prompt(prompt)
+            .items(&labels)
+            .default(0)
+            .interact()?;
+        if let Some((value, _)) = candidates.get(index) {
+            return Ok(value.clone());
+        }
+    }
+    Input::new().with_prompt(prompt).interact_text()
+}
+
+fn property_label(property: &OpenapiProperty) -> String {
+    match &property.schema_type {
+        Some(ty) => format!("{} ({})", property.name, ty),
+        None => property.name.clone(),
+    }
+}
@@ -0,0 +1,86 @@
+//! usecase の承認の記録（USML ファイルの横の `<名前>.approval.json`）
+//!
+//! `usml sign` が承認した人と、その時点の意味の指紋（`fingerprint::semantic`）を記録する。
+//! `usml verify` は今の指紋が承認したときのどれかと一致するかを確かめる。
+//! 書き方だけの変更では指紋が変わらないため、承認し直す必要は無い
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Approvals {
+    pub approvals: Vec<Approval>,
+}
+
+/// 1 人分の承認
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Approval {
+    /// 承認した人
+    pub by: String,
+    /// 承認した usecase 名
+    pub usecase: String,
+    /// 承認したときの意味の指紋
+    pub fingerprint: String,
+}
+
+impl Approvals {
+    /// `users-list.usml.yaml` なら同じディレクトリの `users-list.approval.json`
+    pub fn path(file: &str) -> PathBuf {
+        let path = Path::new(file);
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let stem = [".usml.yaml", ".usml.yml", ".yaml", ".yml"]
+            .iter()
+            .find_map(|suffix| name.strip_suffix(suffix))
+            .unwrap_or(&name);
+        path.with_file_name(format!("{}.approval.json", stem))
+    }
+
+    /// 承認の記録。無ければ None
+    pub fn load(file: &str) -> Result<Option<Self>, String> {
+        let path = Self::path(file);
+        match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)
+                .map(Some)
+                .map_err(|e| format!("承認の記録の読み込みエラー '{}': {}", path.display(), e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!(
+                "承認の記録の読み込みエラー '{}': {}",
+                path.display(),
+                e
+            )),
+        }
+    }
+
+    pub fn save(&self, file: &str) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(Self::path(file), json + "\n")
+    }
+
+    /// 承認を加える。同じ人が同じ指紋をすでに承認していれば false
+    pub fn sign(&mut self, approval: Approval) -> bool {
+        if self
+            .approvals
+            .iter()
+            .any(|a| a.by == approval.by && a.fingerprint == approval.fingerprint)
+        {
+            return false;
+        }
+        self.approvals.push(approval);
+        true
+    }
+
+    /// `fingerprint` を承認した人
+    pub fn approvers(&self, fingerprint: &str) -> Vec<&str> {
+        self.approvals
+            .iter()
+            .filter(|a| a.fingerprint == fingerprint)
+            .map(|a| a.by.as_str())
+            .collect()
+    }
+}
@@ -0,0 +1,53 @@
+//! `usml visualize --archive` で書き出す zip
+//!
+//! レポートの HTML と、元の USML・import 先のファイルを 1 つにまとめる。
+//! import 先は USML からの相対パスのまま `spec/` の下に置くので、展開しても参照が切れない
+
+use std::fs::File;
+use std::io::Write;
+use std::path::{Component, Path};
+
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+/// zip に入れる 1 ファイル
+pub struct Entry {
+    /// zip 内のパス（`/` 区切り）
+    pub name: String,
+    pub content: Vec<u8>,
+}
+
+pub fn write(path: &str, entries: &[Entry]) -> zip::result::ZipResult<()> {
+    let mut zip = ZipWriter::new(File::create(path)?);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    for entry in entries {
+        zip.start_file(entry.name.as_str(), options)?;
+        zip.write_all(&entry.content)?;
+    }
+    zip.finish()?;
+    Ok(())
+}
+
+/// USML からの相対パスを `spec/` 以下のパスにする
+///
+/// 親ディレクトリを遡るパスや絶対パスは zip の外を指すので、ファイル名だけにする
+pub fn spec_path(relative: &Path) -> String {
+    let inside = relative
+        .components()
+        .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+    let parts: Vec<String> = if inside {
+        relative
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(part) => Some(part.to_string_lossy().to_string()),
+                _ => None,
+            })
+            .collect()
+    } else {
+        relative
+            .file_name()
+            .map(|name| vec![name.to_string_lossy().to_string()])
+            .unwrap_or_default()
+    };
+    format!("spec/{}", parts.join("/"))
+}
@@ -0,0 +1,208 @@
+//! 複数ファイルを処理するコマンド（`validate`・`check`・`visualize`）の進捗表示と集計
+//!
+//! 進捗バーは標準エラー出力が端末のときだけ描画する。CI のログでは `--no-progress` で消せる。
+//! `--format ndjson` のイベントは main.rs で出力する
+
+use std::cmp::Reverse;
+use std::time::{Duration, Instant};
+
+use indicatif::{ProgressBar, ProgressStyle};
+use usml_core::diagnostic::{Diagnostic, Severity};
+
+/// 集計に出す「時間のかかったファイル」の数
+const SLOWEST: usize = 3;
+
+/// 1 ファイルの処理結果
+pub struct FileResult {
+    pub path: String,
+    pub diagnostics: Vec<Diagnostic>,
+    /// ファイル全体の処理時間
+    pub elapsed: Duration,
+    /// import の解決にかかった時間（`--resolve` で検証したときだけ）
+    pub resolve: Option<Duration>,
+    /// 前回の診断をキャッシュから使ったか（`--cache-dir` のときだけ）
+    pub cached: Option<bool>,
+}
+
+impl FileResult {
+    pub fn has_error(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|diag| diag.severity == Severity::Error)
+    }
+
+    /// エラー・警告があるか（情報・ヒントだけなら false）
+    pub fn has_problem(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|diag| diag.severity.is_problem())
+    }
+}
+
+/// 全体の集計
+pub struct Totals {
+    pub files: usize,
+    /// エラー・警告のあったファイルの数
+    pub failed: usize,
+    pub errors: usize,
+    pub warnings: usize,
+    pub infos: usize,
+    pub hints: usize,
+    /// キャッシュの診断を使ったファイルの数（`--cache-dir` のときだけ）
+    pub cached: Option<usize>,
+    pub elapsed: Duration,
+}
+
+pub struct Batch {
+    bar: ProgressBar,
+    started: Instant,
+    file_started: Instant,
+    pub results: Vec<FileResult>,
+}
+
+impl Batch {
+    /// `total` ファイルを処理する。`progress` が false なら進捗バーを出さない
+    pub fn new(total: usize, progress: bool) -> Self {
+        let bar = if progress {
+            ProgressBar::new(total as u64)
+        } else {
+            ProgressBar::hidden()
+        };
+        bar.set_style(
+            ProgressStyle::with_template("{bar:30} {pos}/{len} {elapsed} {wide_msg}")
+                .expect("進捗バーのテンプレート"),
+        );
+        let now = Instant::now();
+        Self {
+            bar,
+            started: now,
+            file_started: now,
+            results: Vec::new(),
+        }
+    }
+
+    /// `path` の処理を始める
+    pub fn start(&mut self, path: &str) {
+        self.bar.set_message(path.to_string());
+        self.file_started = Instant::now();
+    }
+
+    /// `start` からの経過時間
+    pub fn elapsed(&self) -> Duration {
+        self.file_started.elapsed()
+    }
+
+    /// 進捗バーを消してから `f` を実行する（出力が進捗バーと混ざらないようにする）
+    pub fn suspend<R>(&self, f: impl FnOnce() -> R) -> R {
+        self.bar.suspend(f)
+    }
+
+    pub fn finish(&mut self, result: FileResult) {
+        self.results.push(result);
+        self.bar.inc(1);
+    }
+
+    pub fn has_error(&self) -> bool {
+        self.results.iter().any(FileResult::has_error)
+    }
+
+    pub fn totals(&self) -> Totals {
+        let count = |severity: Severity| {
+            self.results
+                .iter()
+                .flat_map(|result| &result.diagnostics)
+                .filter(|diag| diag.severity == severity)
+                .count()
+        };
+        Totals {
+            files: self.results.len(),
+            failed: self
+                .results
+                .iter()
+                .filter(|result| result.has_problem())
+                .count(),
+            errors: count(Severity::Error),
+            warnings: count(Severity::Warning),
+            infos: count(Severity::Info),
+            hints: count(Severity::Hint),
+            cached: self
+                .results
+                .iter()
+                .any(|result| result.cached.is_some())
+                .then(|| {
+                    self.results
+                        .iter()
+                        .filter(|result| result.cached == Some(true))
+                        .count()
+                }),
+            elapsed: self.started.elapsed(),
+        }
+    }
+
+    /// 進捗バーを消して、ファイル数・エラー数・警告数・合計時間と、時間のかかったファイルを出力する
+    pub fn print_summary(&self) {
+        self.bar.finish_and_clear();
+        let totals = self.totals();
+        // 情報・ヒントは出たときだけ数を出す
+        let notices = if totals.infos + totals.hints > 0 {
+            format!("、情報 {} 件、ヒント {} 件", totals.infos, totals.hints)
+        } else {
+            String::new()
+        };
+        println!(
+            "集計: {} ファイル（問題のあるファイル {}）、エラー {} 件、警告 {} 件{}、合計 {}",
+            totals.files,
+            totals.failed,
+            totals.errors,
+            totals.warnings,
+            notices,
+            format_duration(totals.elapsed)
+        );
+        if let Some(cached) = totals.cached {
+            println!(
+                "キャッシュ: {} ファイルは前回の結果を使い、{} ファイルを検証しました",
+                cached,
+                totals.files - cached
+            );
+        }
+
+        // --resolve のときは import の解決、それ以外はファイル全体の時間で並べる
+        let resolving = self.results.iter().any(|result| result.resolve.is_some());
+        let mut slowest: Vec<(&str, Duration)> = self
+            .results
+            .iter()
+            .map(|result| {
+                let time = if resolving {
+                    result.resolve.unwrap_or_default()
+                } else {
+                    result.elapsed
+                };
+                (result.path.as_str(), time)
+            })
+            .collect();
+        if slowest.len() < 2 {
+            return;
+        }
+        slowest.sort_by_key(|(_, time)| Reverse(*time));
+        println!(
+            "{}:",
+            if resolving {
+                "import の解決に時間のかかったファイル"
+            } else {
+                "時間のかかったファイル"
+            }
+        );
+        for (path, time) in slowest.into_iter().take(SLOWEST) {
+            println!("  {:>8}  {}", format_duration(time), path);
+        }
+    }
+}
+
+/// 1 秒未満はミリ秒、それ以上は秒（小数 2 桁）
+pub fn format_duration(duration: Duration) -> String {
+    if duration < Duration::from_secs(1) {
+        format!("{}ms", duration.as_millis())
+    } else {
+        format!("{:.2}s", duration.as_secs_f64())
+    }
+}
@@ -0,0 +1,112 @@
+//! `--cache-dir` に置く検証結果のキャッシュ（`<キャッシュディレクトリ>/validate.json`）
+//!
+//! ファイルごとに、検証に使ったもの（`--resolve` の有無・`--allow-paths`・設定・library を展開した
+//! ドキュメント・`--resolve` なら import 先のファイルの内容）から作ったキーと診断を記録する。
+//! キーが同じなら import の解決と検証を省いて記録した診断を使う。workspace 全体にまたがる検査は毎回行う。
+//! 別のビルド（[`build_id`]）が書いたキャッシュは読み込まない
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use usml_core::diagnostic::Diagnostic;
+use usml_core::{fingerprint, rules};
+
+/// キャッシュディレクトリに置くファイル名
+pub const FILE_NAME: &str = "validate.json";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheFile {
+    /// 書き出した usml のビルド（[`build_id`]）。違えば読み込まない
+    build: String,
+    files: BTreeMap<String, Entry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    key: String,
+    diagnostics: Vec<Diagnostic>,
+}
+
+pub struct Cache {
+    dir: PathBuf,
+    files: BTreeMap<String, Entry>,
+}
+
+impl Cache {
+    /// キャッシュを読み込む。無い・壊れている・別のビルドが書いたものは空として扱う
+    pub fn load(dir: &str) -> Self {
+        let dir = PathBuf::from(dir);
+        let files = fs::read_to_string(dir.join(FILE_NAME))
+            .ok()
+            .and_then(|content| serde_json::from_str::<CacheFile>(&content).ok())
+            .filter(|cache| cache.build == build_id())
+            .map(|cache| cache.files)
+            .unwrap_or_default();
+        Cache { dir, files }
+    }
+
+    /// `path` の前回の診断。キーが変わっていれば None
+    pub fn get(&self, path: &str, key: &str) -> Option<Vec<Diagnostic>> {
+        self.files
+            .get(path)
+            .filter(|entry| entry.key == key)
+            .map(|entry| entry.diagnostics.clone())
+    }
+
+    pub fn insert(&mut self, path: &str, key: String, diagnostics: Vec<Diagnostic>) {
+        self.files
+            .insert(path.to_string(), Entry { key, diagnostics });
+    }
+
+    pub fn save(self) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let cache = CacheFile {
+            build: build_id(),
+            files: self.files,
+        };
+        let json = serde_json::to_string(&cache).map_err(io::Error::other)?;
+        fs::write(Path::new(&self.dir).join(FILE_NAME), json + "\n")
+    }
+}
+
+/// usml のビルドの識別子（バージョンと規則の一覧のハッシュ）
+///
+/// バージョンを上げずに規則を足したり重大度を変えたりしたビルドでも、前の診断を使わないようにする
+pub fn build_id() -> String {
+    let rules: Vec<String> = rules::RULES
+        .iter()
+        .map(|rule| format!("{:?}", rule))
+        .collect();
+    fingerprint::hash(
+        std::iter::once(env!("CARGO_PKG_VERSION")).chain(rules.iter().map(String::as_str)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ignores_cache_from_another_build() {
+        let dir = std::env::temp_dir().join("usml_test_cache_build");
+        let _ = fs::remove_dir_all(&dir);
+        let dir = dir.to_string_lossy().to_string();
+
+        let mut cache = Cache::load(&dir);
+        cache.insert("users.usml.yaml", "key".to_string(), Vec::new());
+        cache.save().unwrap();
+        assert_eq!(
+            Cache::load(&dir).get("users.usml.yaml", "key"),
+            Some(Vec::new())
+        );
+
+        // バージョンだけが同じ別のビルドが書いたもの
+        let path = Path::new(&dir).join(FILE_NAME);
+        let content = fs::read_to_string(&path).unwrap();
+        fs::write(&path, content.replace(&build_id(), "0000000000000000")).unwrap();
+        assert_eq!(Cache::load(&dir).get("users.usml.yaml", "key"), None);
+    }
+}
@@ -0,0 +1,61 @@
+//! `usml examples` で配布するサンプル仕様
+//!
+//! リポジトリの `examples/` をバイナリに埋め込む。各サンプルは共通の
+//! `api.yaml`・`schema.dbml` を import するので、init ではそれらも一緒に書き出す
+
+/// サンプル 1 件
+pub struct Example {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// 書き出すときのファイル名
+    pub file: &'static str,
+    pub source: &'static str,
+}
+
+/// サンプルが import する OpenAPI 定義
+pub const OPENAPI: (&str, &str) = ("api.yaml", include_str!("../../examples/api.yaml"));
+/// サンプルが import する DBML 定義
+pub const DBML: (&str, &str) = ("schema.dbml", include_str!("../../examples/schema.dbml"));
+
+pub const EXAMPLES: &[Example] = &[
+    Example {
+        name: "simple-read",
+        description: "1 テーブルから 1 件を読む最小構成",
+        file: "user-detail.usml.yaml",
+        source: include_str!("../../examples/user-detail.usml.yaml"),
+    },
+    Example {
+        name: "offset-pagination",
+        description: "JOIN・COALESCE とオフセット方式のページネーション",
+        file: "users-list.usml.yaml",
+        source: include_str!("../../examples/users-list.usml.yaml"),
+    },
+    Example {
+        name: "nested-array",
+        description: "多段結合を含む配列フィールドの入れ子",
+        file: "posts-detail.usml.yaml",
+        source: include_str!("../../examples/posts-detail.usml.yaml"),
+    },
+    Example {
+        name: "aggregate",
+        description: "LEFT JOIN と COUNT による集計",
+        file: "user-stats.usml.yaml",
+        source: include_str!("../../examples/user-stats.usml.yaml"),
+    },
+    Example {
+        name: "cursor-pagination",
+        description: "items・next_cursor の一覧レスポンスとカーソル方式のページネーション",
+        file: "posts-feed.usml.yaml",
+        source: include_str!("../../examples/posts-feed.usml.yaml"),
+    },
+    Example {
+        name: "write",
+        description: "登録した行を返す書き込み系ユースケース",
+        file: "create-post.usml.yaml",
+        source: include_str!("../../examples/create-post.usml.yaml"),
+    },
+];
+
+pub fn find(name: &str) -> Option<&'static Example> {
+    EXAMPLES.iter().find(|e| e.name == name)
+}
@@ -1,20 +1,118 @@
-use clap::{Arg, ArgAction, Command};
+use clap::{Arg, ArgAction, ArgGroup, ArgMatches, Command};
 use std::fs;
+use std::io::IsTerminal;
+use std::path::{Component, Path, PathBuf};
 use std::process;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
 
-use usml_core::{parser, validator, visualizer};
+mod access;
+mod add;
+mod approval;
+mod archive;
+mod batch;
+mod cache;
+mod examples;
+mod manifest;
+mod report;
+mod xlsx;
+
+use batch::{Batch, FileResult};
+use cache::Cache;
+use report::Collector;
+use usml_core::ast::UsmlDocument;
+use usml_core::config::{CONFIG_FILE, Config, FmtConfig, SizeConfig, SynthConfig};
+use usml_core::diagnostic::{Diagnostic, Location, Severity};
+use usml_core::edit::Editor;
+use usml_core::error::UsmlError;
+use usml_core::generated::{self, CommentStyle};
+use usml_core::model::Model;
+use usml_core::normalize::{FieldOrder, KeywordCase};
+use usml_core::resolver::{
+    self, DbmlTable, FsResolver, Limits, OpenapiResponse, Resolver, ResolverError, SandboxResolver,
+};
+use usml_core::rules::Rule;
+use usml_core::size::{self, ResponseSize};
+use usml_core::stats::WorkspaceStats;
+use usml_core::validator::Validator;
+use usml_core::workspace::{Workspace, WorkspaceDocument};
+use usml_core::{
+    api, codegen, export, fingerprint, fix, impact, locate, normalize, output, parser, rule_docs,
+    schema, validator, visualizer, workspace,
+};
+
+/// `--allow-paths`（CI では既定でカレントディレクトリ）。None なら import の読み込み先を制限しない
+static ALLOWED_PATHS: OnceLock<Option<Vec<String>>> = OnceLock::new();
 
 fn main() {
     let matches = Command::new("usml")
         .about("Usecase Markup Language - API と DB のデータフローを声明的に定義する")
         .version("0.1.0")
+        .arg(
+            Arg::new("allow-paths")
+                .help("import で読み込めるディレクトリ（カンマ区切り・複数指定可）。環境変数 CI がある場合の既定はカレントディレクトリ。'/' で制限しない")
+                .long("allow-paths")
+                .value_name("DIR")
+                .value_delimiter(',')
+                .num_args(1)
+                .action(ArgAction::Append)
+                .global(true),
+        )
         .subcommand(
             Command::new("validate")
                 .about("USML ファイルのバリデーションを実行する")
+                .arg(no_progress_arg())
                 .arg(
                     Arg::new("file")
-                        .help("検証対象の .usml.yaml ファイルパス")
+                        .help("検証対象の .usml.yaml ファイルパス・ディレクトリ・glob（'specs/**/*.usml.yaml'）。複数ファイルでは進捗と集計を表示する")
                         .required(true)
+                        .num_args(1..)
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("json")
+                        .help("JSON形式で結果を出力する")
+                        .long("json")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(report_format_arg())
+                .arg(cache_dir_arg())
+                .arg(report_out_arg())
+                .arg(
+                    Arg::new("tag")
+                        .help("指定したタグの付いた usecase だけを検証する")
+                        .long("tag")
+                        .value_name("TAG"),
+                )
+                .arg(
+                    Arg::new("resolve")
+                        .help("import された OpenAPI / DBML ファイルを読み込んで照合する")
+                        .long("resolve")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("config")
+                        .help("設定ファイルのパス（デフォルト: 対象ファイルのディレクトリから遡って usml.toml を探す）")
+                        .long("config")
+                        .value_name("FILE"),
+                )
+                .arg(
+                    Arg::new("workspace")
+                        .help("source_usecase の参照先と共有ライブラリを探すディレクトリ（以下の .usml.yaml をすべて読み込む）")
+                        .long("workspace")
+                        .value_name("DIR"),
+                )
+                .arg(env_arg()),
+        )
+        .subcommand(
+            Command::new("lint")
+                .about("usml.toml の [lint] の規則の選択と重大度の上書きを適用して USML ファイルを検査する")
+                .arg(no_progress_arg())
+                .arg(
+                    Arg::new("file")
+                        .help("検査する .usml.yaml ファイルパス・ディレクトリ・glob（デフォルト: カレントディレクトリ）")
+                        .num_args(1..)
+                        .default_value(".")
                         .index(1),
                 )
                 .arg(
@@ -22,8 +120,571 @@ fn main() {
                         .help("JSON形式で結果を出力する")
                         .long("json")
                         .action(ArgAction::SetTrue),
+                )
+                .arg(report_format_arg())
+                .arg(
+                    Arg::new("deny-warnings")
+                        .help("警告もエラーとして報告する（[lint] warnings_as_errors と同じ）")
+                        .long("deny-warnings")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("resolve")
+                        .help("import された OpenAPI / DBML ファイルを読み込んで照合する")
+                        .long("resolve")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("config")
+                        .help("設定ファイルのパス（デフォルト: 対象ファイルのディレクトリから遡って usml.toml を探す）")
+                        .long("config")
+                        .value_name("FILE"),
+                )
+                .arg(
+                    Arg::new("workspace")
+                        .help("source_usecase の参照先と共有ライブラリを探すディレクトリ（以下の .usml.yaml をすべて読み込む）")
+                        .long("workspace")
+                        .value_name("DIR"),
+                )
+                .arg(env_arg()),
+        )
+        .subcommand(
+            Command::new("analyze")
+                .about("AST・診断・リネージ・テーブル利用状況を JSON で出力する")
+                .arg(
+                    Arg::new("file")
+                        .help("解析対象の .usml.yaml ファイルパス")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("resolve")
+                        .help("import された OpenAPI / DBML ファイルを読み込んで照合する")
+                        .long("resolve")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("workspace")
+                        .help("source_usecase の参照先と共有ライブラリを探すディレクトリ（以下の .usml.yaml をすべて読み込む）")
+                        .long("workspace")
+                        .value_name("DIR"),
+                )
+                .arg(env_arg()),
+        )
+        .subcommand(
+            Command::new("check")
+                .about("ディレクトリ以下の USML ファイルをまとめてバリデーションする")
+                .arg(no_progress_arg())
+                .arg(report_format_arg())
+                .arg(cache_dir_arg())
+                .arg(report_out_arg())
+                .arg(
+                    Arg::new("dir")
+                        .help("検証するディレクトリ（以下の .usml.yaml をすべて読み込む）")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("tag")
+                        .help("指定したタグの付いた usecase だけを検証する")
+                        .long("tag")
+                        .value_name("TAG"),
+                )
+                .arg(
+                    Arg::new("json")
+                        .help("JSON形式で出力する")
+                        .long("json")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("resolve")
+                        .help("import された OpenAPI / DBML ファイルを読み込んで照合する")
+                        .long("resolve")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("config")
+                        .help("設定ファイルのパス（デフォルト: ディレクトリから遡って usml.toml を探す）")
+                        .long("config")
+                        .value_name("FILE"),
+                ),
+        )
+        .subcommand(
+            Command::new("validate-schema")
+                .about("import 先の OpenAPI / DBML ファイル単体の構造を検査する")
+                .arg(
+                    Arg::new("files")
+                        .help("検査する OpenAPI（.yaml / .yml / .json）・DBML（.dbml）ファイル。.usml.yaml を指定すると import 先を検査する")
+                        .required(true)
+                        .num_args(1..)
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("json")
+                        .help("JSON形式で出力する")
+                        .long("json")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("catalog")
+                .about("ディレクトリ以下の usecase をタグごとに一覧表示する")
+                .arg(
+                    Arg::new("dir")
+                        .help("一覧にするディレクトリ（以下の .usml.yaml をすべて読み込む）")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("tag")
+                        .help("指定したタグの usecase だけを表示する")
+                        .long("tag")
+                        .value_name("TAG"),
+                )
+                .arg(
+                    Arg::new("json")
+                        .help("JSON形式で出力する")
+                        .long("json")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("stats")
+                .about("ディレクトリ以下の USML ファイルの規模・複雑さの指標を出力する")
+                .arg(
+                    Arg::new("dir")
+                        .help("集計するディレクトリ（以下の .usml.yaml をすべて読み込む）")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("tag")
+                        .help("指定したタグの付いた usecase だけを集計する")
+                        .long("tag")
+                        .value_name("TAG"),
+                )
+                .arg(
+                    Arg::new("json")
+                        .help("JSON形式で出力する")
+                        .long("json")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("csv"),
+                )
+                .arg(
+                    Arg::new("csv")
+                        .help("ファイルごとの指標を CSV で出力する")
+                        .long("csv")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("config")
+                        .help("設定ファイルのパス（デフォルト: ディレクトリから遡って usml.toml を探す）")
+                        .long("config")
+                        .value_name("FILE"),
+                ),
+        )
+        .subcommand(
+            Command::new("size")
+                .about("カラムの型とページサイズから usecase のレスポンスのサイズを見積もり、予算を超えるものを報告する")
+                .arg(
+                    Arg::new("file")
+                        .help("見積もる .usml.yaml ファイルパス・ディレクトリ・glob（デフォルト: カレントディレクトリ）")
+                        .num_args(1..)
+                        .default_value(".")
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("budget")
+                        .help("最悪の場合のサイズの上限（バイト。[size] budget より優先する）")
+                        .long("budget")
+                        .value_name("BYTES")
+                        .value_parser(clap::value_parser!(u64)),
+                )
+                .arg(
+                    Arg::new("json")
+                        .help("JSON形式で出力する")
+                        .long("json")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("resolve")
+                        .help("import された OpenAPI / DBML ファイルを読み込んで型を決める")
+                        .long("resolve")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("config")
+                        .help("設定ファイルのパス（デフォルト: 対象ファイルのディレクトリから遡って usml.toml を探す）")
+                        .long("config")
+                        .value_name("FILE"),
+                )
+                .arg(
+                    Arg::new("workspace")
+                        .help("source_usecase の参照先と共有ライブラリを探すディレクトリ（以下の .usml.yaml をすべて読み込む）")
+                        .long("workspace")
+                        .value_name("DIR"),
+                )
+                .arg(env_arg()),
+        )
+        .subcommand(
+            Command::new("impact")
+                .about("カラムを削除・改名すると壊れるフィールド・filter・transform・usecase を一覧表示する")
+                .arg(
+                    Arg::new("dir")
+                        .help("調べるディレクトリ（以下の .usml.yaml をすべて読み込む）")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("column")
+                        .help("調べるカラム（テーブル.カラム。複数回指定できる）")
+                        .long("column")
+                        .value_name("TABLE.COLUMN")
+                        .action(ArgAction::Append),
+                )
+                .arg(
+                    Arg::new("schema-diff")
+                        .help("2 つの版の DBML を比べ、削除・改名・型の変更があったカラムごとに影響を表示する")
+                        .long("schema-diff")
+                        .num_args(2)
+                        .value_names(["OLD", "NEW"]),
+                )
+                .group(
+                    ArgGroup::new("target")
+                        .args(["column", "schema-diff"])
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("json")
+                        .help("JSON形式で出力する")
+                        .long("json")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("export")
+                .about("マッピングを他の形式で書き出す")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("xlsx")
+                        .about("usecase ごとのマッピング表とテーブル利用状況を Excel ブックに書き出す")
+                        .arg(
+                            Arg::new("path")
+                                .help(".usml.yaml ファイル、またはディレクトリ（以下の .usml.yaml をすべて読み込む）")
+                                .required(true)
+                                .index(1),
+                        )
+                        .arg(
+                            Arg::new("tag")
+                                .help("ディレクトリの場合、指定したタグの付いた usecase だけを書き出す")
+                                .long("tag")
+                                .value_name("TAG"),
+                        )
+                        .arg(
+                            Arg::new("output")
+                                .help("出力先ファイルパス（デフォルト: ./output/mappings.xlsx）")
+                                .short('o')
+                                .long("output")
+                                .value_name("FILE"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("dbt")
+                        .about("usecase ごとに、依存するテーブル・カラムと owner を dbt の exposures に書き出す")
+                        .arg(force_arg())
+                        .arg(check_arg())
+                        .arg(
+                            Arg::new("path")
+                                .help(".usml.yaml ファイル、またはディレクトリ（以下の .usml.yaml をすべて読み込む）")
+                                .required(true)
+                                .index(1),
+                        )
+                        .arg(
+                            Arg::new("tag")
+                                .help("ディレクトリの場合、指定したタグの付いた usecase だけを書き出す")
+                                .long("tag")
+                                .value_name("TAG"),
+                        )
+                        .arg(
+                            Arg::new("source")
+                                .help("依存先を ref() ではなく、この名前の source() で書く")
+                                .long("source")
+                                .value_name("NAME"),
+                        )
+                        .arg(
+                            Arg::new("output")
+                                .help("出力先ファイルパス（デフォルト: ./output/usml_exposures.yml）")
+                                .short('o')
+                                .long("output")
+                                .value_name("FILE"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("access")
+                        .about("usecase × テーブル・カラムのアクセス行列を、個人情報の印付きで書き出す")
+                        .arg(force_arg())
+                        .arg(check_arg())
+                        .arg(
+                            Arg::new("path")
+                                .help(".usml.yaml ファイル、またはディレクトリ（以下の .usml.yaml をすべて読み込む）")
+                                .required(true)
+                                .index(1),
+                        )
+                        .arg(
+                            Arg::new("tag")
+                                .help("ディレクトリの場合、指定したタグの付いた usecase だけを書き出す")
+                                .long("tag")
+                                .value_name("TAG"),
+                        )
+                        .arg(
+                            Arg::new("format")
+                                .help("出力形式")
+                                .long("format")
+                                .value_parser(["json", "csv", "html"])
+                                .default_value("html"),
+                        )
+                        .arg(
+                            Arg::new("config")
+                                .help("[governance] pii を読む設定ファイル（省略時はパスから遡って usml.toml を探す）")
+                                .long("config")
+                                .value_name("FILE"),
+                        )
+                        .arg(
+                            Arg::new("output")
+                                .help("出力先ファイルパス（デフォルト: ./output/usml_access.<形式>）")
+                                .short('o')
+                                .long("output")
+                                .value_name("FILE"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("dbml")
+                        .about("usecase が参照するテーブル・カラム・結合だけを含む DBML を書き出す")
+                        .arg(force_arg())
+                        .arg(check_arg())
+                        .arg(
+                            Arg::new("path")
+                                .help(".usml.yaml ファイル、またはディレクトリ（以下の .usml.yaml をすべて読み込む）")
+                                .required(true)
+                                .index(1),
+                        )
+                        .arg(
+                            Arg::new("tag")
+                                .help("ディレクトリの場合、指定したタグの付いた usecase だけを書き出す")
+                                .long("tag")
+                                .value_name("TAG"),
+                        )
+                        .arg(
+                            Arg::new("resolve")
+                                .help("import された DBML ファイルを読み込み、カラムの型・制約を書き出す")
+                                .long("resolve")
+                                .action(ArgAction::SetTrue),
+                        )
+                        .arg(
+                            Arg::new("output")
+                                .help("出力先ファイルパス（デフォルト: ./output/usml_usage.dbml）")
+                                .short('o')
+                                .long("output")
+                                .value_name("FILE"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("datahub")
+                        .about("usecase をカラム単位のリネージ付きで DataHub に取り込む MetadataChangeProposal を書き出す")
+                        .arg(force_arg())
+                        .arg(check_arg())
+                        .arg(
+                            Arg::new("path")
+                                .help(".usml.yaml ファイル、またはディレクトリ（以下の .usml.yaml をすべて読み込む）")
+                                .required(true)
+                                .index(1),
+                        )
+                        .arg(
+                            Arg::new("tag")
+                                .help("ディレクトリの場合、指定したタグの付いた usecase だけを書き出す")
+                                .long("tag")
+                                .value_name("TAG"),
+                        )
+                        .arg(
+                            Arg::new("platform")
+                                .help("テーブルのデータプラットフォーム")
+                                .long("platform")
+                                .value_name("PLATFORM")
+                                .default_value("postgres"),
+                        )
+                        .arg(
+                            Arg::new("database")
+                                .help("テーブル名の前に付けるデータベース・スキーマ名（例: app.public）")
+                                .long("database")
+                                .value_name("NAME"),
+                        )
+                        .arg(
+                            Arg::new("env")
+                                .help("DataHub の環境")
+                                .long("env")
+                                .value_name("ENV")
+                                .default_value("PROD"),
+                        )
+                        .arg(
+                            Arg::new("output")
+                                .help("出力先ファイルパス（デフォルト: ./output/usml_datahub.json）")
+                                .short('o')
+                                .long("output")
+                                .value_name("FILE"),
+                        ),
                 ),
         )
+        .subcommand(
+            Command::new("generate")
+                .about("解析結果をテンプレート（Jinja2 / Tera 互換）か組み込みの生成器（kotlin・graphql・sql）に渡してコードを生成する")
+                .arg(force_arg())
+                .arg(check_arg().requires("output"))
+                .args_conflicts_with_subcommands(true)
+                .subcommand_negates_reqs(true)
+                .subcommand(
+                    Command::new("tests")
+                        .about("SQL・フィクスチャ・パラメータの値と、結果の列が response_mapping と一致するかのテストを生成する")
+                        .arg(force_arg())
+                        .arg(check_arg().requires("output"))
+                        .arg(
+                            Arg::new("file")
+                                .help("生成元の .usml.yaml ファイルパス")
+                                .required(true)
+                                .index(1),
+                        )
+                        .arg(
+                            Arg::new("format")
+                                .help("出力形式（rust: #[sqlx::test] のテストモジュール、json: テストベクター）")
+                                .long("format")
+                                .value_parser(["rust", "json"])
+                                .default_value("rust"),
+                        )
+                        .arg(
+                            Arg::new("output")
+                                .help("出力先ファイルパス（省略時は標準出力）")
+                                .short('o')
+                                .long("output")
+                                .value_name("FILE"),
+                        )
+                        .arg(
+                            Arg::new("resolve")
+                                .help("import された DBML ファイルを読み込み、カラムの型と必須のカラムをフィクスチャに使う")
+                                .long("resolve")
+                                .action(ArgAction::SetTrue),
+                        )
+                        .arg(
+                            Arg::new("workspace")
+                                .help("共有ライブラリを探すディレクトリ（以下の .usml.yaml をすべて読み込む）")
+                                .long("workspace")
+                                .value_name("DIR"),
+                        )
+                        .arg(env_arg()),
+                )
+                .subcommand(
+                    Command::new("mock")
+                        .about("レスポンスのモックデータ（JSON）を生成する。OpenAPI の example と OpenAPI・DBML の enum の値を優先する")
+                        .arg(force_arg())
+                        .arg(check_arg().requires("output"))
+                        .arg(
+                            Arg::new("file")
+                                .help("生成元の .usml.yaml ファイルパス")
+                                .required(true)
+                                .index(1),
+                        )
+                        .arg(
+                            Arg::new("seed")
+                                .help("値を選ぶ乱数の種（同じ種なら何度生成しても同じデータになる）")
+                                .long("seed")
+                                .value_name("N")
+                                .value_parser(clap::value_parser!(u64))
+                                .default_value("0"),
+                        )
+                        .arg(
+                            Arg::new("items")
+                                .help("配列の要素数")
+                                .long("items")
+                                .value_name("N")
+                                .value_parser(clap::value_parser!(usize))
+                                .default_value("2"),
+                        )
+                        .arg(
+                            Arg::new("output")
+                                .help("出力先ファイルパス（省略時は標準出力）")
+                                .short('o')
+                                .long("output")
+                                .value_name("FILE"),
+                        )
+                        .arg(
+                            Arg::new("resolve")
+                                .help("import された OpenAPI / DBML ファイルを読み込み、example・enum・カラムの型を値に使う")
+                                .long("resolve")
+                                .action(ArgAction::SetTrue),
+                        )
+                        .arg(
+                            Arg::new("workspace")
+                                .help("共有ライブラリを探すディレクトリ（以下の .usml.yaml をすべて読み込む）")
+                                .long("workspace")
+                                .value_name("DIR"),
+                        )
+                        .arg(env_arg()),
+                )
+                .arg(
+                    Arg::new("file")
+                        .help("生成元の .usml.yaml ファイルパス")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("template")
+                        .help("テンプレートファイルのパス")
+                        .long("template")
+                        .short('t')
+                        .value_name("FILE"),
+                )
+                .arg(
+                    Arg::new("backend")
+                        .help(
+                            "組み込みの生成器（kotlin: data class と jOOQ のクエリ、\
+                             graphql: async-graphql のリゾルバ、graphql-sdl: GraphQL の SDL、\
+                             sql: 条件を断片に分けた SQL、\
+                             sql-prepared: $1..$n のプレースホルダーの SQL と束縛するパラメータの JSON）",
+                        )
+                        .long("backend")
+                        .value_name("BACKEND")
+                        .value_parser(["kotlin", "graphql", "graphql-sdl", "sql", "sql-prepared"]),
+                )
+                .group(
+                    ArgGroup::new("generator")
+                        .args(["template", "backend"])
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("package")
+                        .help("--backend kotlin で出力する package 名")
+                        .long("package")
+                        .value_name("NAME"),
+                )
+                .arg(
+                    Arg::new("output")
+                        .help("出力先ファイルパス（省略時は標準出力）")
+                        .short('o')
+                        .long("output")
+                        .value_name("FILE"),
+                )
+                .arg(
+                    Arg::new("resolve")
+                        .help("import された OpenAPI / DBML ファイルを読み込み、schemas に入れる")
+                        .long("resolve")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("workspace")
+                        .help("source_usecase の参照先と共有ライブラリを探すディレクトリ（以下の .usml.yaml をすべて読み込む）")
+                        .long("workspace")
+                        .value_name("DIR"),
+                )
+                .arg(env_arg()),
+        )
         .subcommand(
             Command::new("parse")
                 .about("USML ファイルをパースしてAST情報を出力する")
@@ -37,10 +698,14 @@ fn main() {
         .subcommand(
             Command::new("visualize")
                 .about("USML ドキュメントからHTMLデータフロー図を生成する")
+                .arg(force_arg())
+                .arg(check_arg().conflicts_with("archive"))
+                .arg(no_progress_arg())
                 .arg(
                     Arg::new("file")
-                        .help("可視化対象の .usml.yaml ファイルパス")
+                        .help("可視化対象の .usml.yaml ファイルパス（複数指定できる）")
                         .required(true)
+                        .num_args(1..)
                         .index(1),
                 )
                 .arg(
@@ -49,242 +714,3527 @@ fn main() {
                         .short('o')
                         .long("output")
                         .value_name("FILE"),
+                )
+                .arg(
+                    Arg::new("output-dir")
+                        .help("出力先ディレクトリ（デフォルト: usml.toml の [visualize] output_dir、無ければ ./output）")
+                        .long("output-dir")
+                        .value_name("DIR"),
+                )
+                .arg(
+                    Arg::new("output-template")
+                        .help("出力先ディレクトリからのパスのテンプレート（例: \"{method}/{path}/{name}.html\"。変数: name, file, dir, method, path, status, tag, owner）")
+                        .long("output-template")
+                        .value_name("TEMPLATE")
+                        .conflicts_with("output"),
+                )
+                .arg(
+                    Arg::new("config")
+                        .help("[visualize] を読む設定ファイル（省略時は USML ファイルのディレクトリから遡って usml.toml を探す）")
+                        .long("config")
+                        .value_name("FILE"),
+                )
+                .arg(
+                    Arg::new("workspace")
+                        .help("共有ライブラリ（library.usml.yaml）を探すディレクトリ")
+                        .long("workspace")
+                        .value_name("DIR"),
+                )
+                .arg(
+                    Arg::new("archive")
+                        .help("HTML と元の USML・import 先のファイルを 1 つの zip にまとめて出力する")
+                        .long("archive")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("merge")
+                        .help("指定した usecase を 1 つの HTML にまとめる（usecase ごとのタブと共有のテーブル一覧。デフォルトの出力先: <出力ディレクトリ>/usml_merged.html）")
+                        .long("merge")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with_all(["archive", "output-template"]),
                 ),
         )
-        .get_matches();
+        .subcommand(
+            Command::new("clean")
+                .about("生成元の usecase が改名・削除されて古くなった生成物を削除する")
+                .arg(
+                    Arg::new("dir")
+                        .help("マニフェスト（.usml-manifest.json）のある出力ディレクトリ（デフォルト: output）")
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("dry-run")
+                        .help("削除せずに削除対象を表示する")
+                        .long("dry-run")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("fix")
+                .about("直し方のある診断（import.dbml の不足・alias・group_by・filters の param の宣言）をコメントを保ったまま直す")
+                .arg(
+                    Arg::new("file")
+                        .help("直す .usml.yaml ファイルパス（複数指定できる）")
+                        .required(true)
+                        .num_args(1..)
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("config")
+                        .help("設定ファイルのパス（デフォルト: 対象ファイルのディレクトリから遡って usml.toml を探す）")
+                        .long("config")
+                        .value_name("FILE"),
+                )
+                .arg(
+                    Arg::new("dry-run")
+                        .help("書き込まずに直し方を表示する")
+                        .long("dry-run")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("fmt")
+                .about("import の並び・フィールドの順・join.type の表記・既定値の省略を usml.toml の [fmt] に従ってコメントを保ったまま正規化する")
+                .arg(
+                    Arg::new("file")
+                        .help("整形する .usml.yaml ファイルパス（複数指定できる）")
+                        .required(true)
+                        .num_args(1..)
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("config")
+                        .help("設定ファイルのパス（デフォルト: 対象ファイルのディレクトリから遡って usml.toml を探す）")
+                        .long("config")
+                        .value_name("FILE"),
+                )
+                .arg(
+                    Arg::new("sort-imports")
+                        .help("import.dbml を参照の文字列順に並べる（[fmt] sort_imports）")
+                        .long("sort-imports")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("order-fields")
+                        .help("response_mapping を OpenAPI のプロパティの順に並べる（[fmt] order_fields）")
+                        .long("order-fields")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("join-type")
+                        .help("join.type の表記をそろえる（[fmt] join_type）")
+                        .long("join-type")
+                        .value_name("CASE")
+                        .value_parser(["upper", "lower"]),
+                )
+                .arg(
+                    Arg::new("collapse-defaults")
+                        .help("既定値と同じ値（join.type: LEFT・collection: false など）を消す（[fmt] collapse_defaults）")
+                        .long("collapse-defaults")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("check")
+                        .help("書き込まずに、整形が必要なファイルがあれば終了コード 1 で終わる（CI 向け）")
+                        .long("check")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("sort")
+                .about("response_mapping（入れ子の fields も）を OpenAPI のプロパティの順か名前順にコメントを保ったまま並べ替える")
+                .arg(
+                    Arg::new("file")
+                        .help("並べ替える .usml.yaml ファイルパス（複数指定できる）")
+                        .required(true)
+                        .num_args(1..)
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("by-openapi")
+                        .help("import.openapi のレスポンスのプロパティが書かれた順に並べる。OpenAPI に無いフィールドは末尾に残して一覧を出力する")
+                        .long("by-openapi")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("alphabetical")
+                        .help("フィールド名の文字列順に並べる")
+                        .long("alphabetical")
+                        .action(ArgAction::SetTrue),
+                )
+                .group(
+                    ArgGroup::new("order")
+                        .args(["by-openapi", "alphabetical"])
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("config")
+                        .help("設定ファイルのパス（import の解決の上限 [limits] に使う）")
+                        .long("config")
+                        .value_name("FILE"),
+                )
+                .arg(
+                    Arg::new("check")
+                        .help("書き込まずに、並べ替えが必要なファイルがあれば終了コード 1 で終わる（CI 向け）")
+                        .long("check")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("sign")
+                .about("usecase の今の意味を承認し、ファイルの横の <名前>.approval.json に記録する")
+                .arg(
+                    Arg::new("file")
+                        .help("承認する .usml.yaml ファイルパス（複数指定できる）")
+                        .required(true)
+                        .num_args(1..)
+                        .index(1),
+                )
+                .arg(
+                    Arg::new("by")
+                        .help("承認する人の名前")
+                        .long("by")
+                        .value_name("NAME")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("verify")
+                .about("承認した usecase の意味が承認後に変わっていないかを確かめる（変わっていれば終了コード 1。CI 向け）")
+                .arg(
+                    Arg::new("file")
+                        .help("確かめる .usml.yaml ファイルパス（複数指定できる）")
+                        .required(true)
+                        .num_args(1..)
+                        .index(1),
+                ),
+        )
+        .subcommand(
+            Command::new("rules")
+                .about("バリデーション規則の一覧と説明を表示する")
+                .arg(
+                    Arg::new("explain")
+                        .help("指定したコードの規則を詳しく説明する（例: U007）")
+                        .long("explain")
+                        .value_name("CODE"),
+                )
+                .arg(
+                    Arg::new("doc")
+                        .help("指定したコードの規則のドキュメント（説明・違反例・URL）を表示する")
+                        .long("doc")
+                        .value_name("CODE")
+                        .conflicts_with_all(["explain", "json"]),
+                )
+                .arg(
+                    Arg::new("markdown")
+                        .help("全規則のドキュメント（docs/rules.md）を Markdown で出力する")
+                        .long("markdown")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with_all(["explain", "doc", "json"]),
+                )
+                .arg(
+                    Arg::new("json")
+                        .help("JSON形式で出力する")
+                        .long("json")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("config")
+                        .help("設定ファイルのパス（デフォルト: カレントディレクトリから遡って usml.toml を探す）")
+                        .long("config")
+                        .value_name("FILE"),
+                ),
+        )
+        .subcommand(
+            Command::new("examples")
+                .about("同梱のサンプル仕様を一覧・表示・展開する")
+                .subcommand_required(true)
+                .subcommand(Command::new("list").about("サンプルの一覧を表示する"))
+                .subcommand(
+                    Command::new("show")
+                        .about("サンプルの USML を表示する")
+                        .arg(
+                            Arg::new("name")
+                                .help("サンプル名（usml examples list で確認）")
+                                .required(true)
+                                .index(1),
+                        ),
+                )
+                .subcommand(
+                    Command::new("init")
+                        .about("サンプルと import 先の api.yaml・schema.dbml を書き出す")
+                        .arg(
+                            Arg::new("name")
+                                .help("サンプル名（usml examples list で確認）")
+                                .required(true)
+                                .index(1),
+                        )
+                        .arg(
+                            Arg::new("dir")
+                                .help("書き出し先ディレクトリ（デフォルト: カレントディレクトリ）")
+                                .long("dir")
+                                .value_name("DIR"),
+                        )
+                        .arg(
+                            Arg::new("force")
+                                .help("既存のファイルを上書きする")
+                                .long("force")
+                                .action(ArgAction::SetTrue),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("add")
+                .about("DBML・OpenAPI から選んでフィールド・フィルタ・transform を対話的に追加する")
+                .subcommand_required(true)
+                .subcommand(add_command(
+                    "field",
+                    "response_mapping にフィールドを追加する",
+                ))
+                .subcommand(add_command("filter", "filters にフィルタを追加する"))
+                .subcommand(add_command(
+                    "transform",
+                    "transforms に transform を追加する",
+                )),
+        )
+        .get_matches();
+
+    let allowed = match matches.get_many::<String>("allow-paths") {
+        Some(dirs) => Some(dirs.cloned().collect()),
+        None if is_ci() => Some(vec![".".to_string()]),
+        None => None,
+    };
+    ALLOWED_PATHS.set(allowed).unwrap();
+
+    match matches.subcommand() {
+        Some(("validate", sub_matches)) => {
+            let inputs: Vec<&String> = sub_matches.get_many::<String>("file").unwrap().collect();
+            let expanded = expand_inputs(&inputs);
+            // ディレクトリ・glob は 1 ファイルに一致しても複数ファイルと同じ集計を出す
+            let single = expanded.as_slice() == [inputs[0].as_str()];
+            let env = sub_matches.get_one::<String>("env");
+            let files: Vec<&String> = match sub_matches.get_one::<String>("tag") {
+                Some(tag) => select_tagged_files(&expanded, tag, env),
+                None => expanded.iter().collect(),
+            };
+            let format = ReportFormat::from_args(sub_matches);
+            let resolve = sub_matches.get_flag("resolve");
+            let config = sub_matches.get_one::<String>("config");
+            let workspace = sub_matches.get_one::<String>("workspace");
+            let options = BatchOptions::from_args(sub_matches);
+            match files.as_slice() {
+                [file_path]
+                    if single
+                        && matches!(format, ReportFormat::Text | ReportFormat::Json)
+                        && options.cache_dir.is_none()
+                        && options.report_out.is_none() =>
+                {
+                    cmd_validate(
+                        file_path,
+                        format == ReportFormat::Json,
+                        resolve,
+                        config,
+                        workspace,
+                        env,
+                    )
+                }
+                _ => cmd_validate_files(&files, resolve, config, workspace, env, options),
+            }
+        }
+        Some(("analyze", sub_matches)) => {
+            let file_path = sub_matches.get_one::<String>("file").unwrap();
+            let resolve = sub_matches.get_flag("resolve");
+            let workspace = sub_matches.get_one::<String>("workspace");
+            let env = sub_matches.get_one::<String>("env");
+            cmd_analyze(file_path, resolve, workspace, env);
+        }
+        Some(("lint", sub_matches)) => {
+            let inputs: Vec<&String> = sub_matches.get_many::<String>("file").unwrap().collect();
+            let expanded = expand_inputs(&inputs);
+            let files: Vec<&String> = expanded.iter().collect();
+            let config = sub_matches.get_one::<String>("config");
+            // プロジェクトの規約が無ければ validate と変わらないため、設定ファイルを必須にする
+            if config.is_none() && Config::find(Path::new(&base_dir(files[0]))).is_none() {
+                eprintln!(
+                    "{} が見つかりません（--config で設定ファイルを指定できます）",
+                    CONFIG_FILE
+                );
+                process::exit(1);
+            }
+            let options = BatchOptions {
+                format: ReportFormat::from_args(sub_matches),
+                cache_dir: None,
+                report_out: None,
+                no_progress: sub_matches.get_flag("no-progress"),
+                warnings_as_errors: sub_matches.get_flag("deny-warnings"),
+            };
+            cmd_validate_files(
+                &files,
+                sub_matches.get_flag("resolve"),
+                config,
+                sub_matches.get_one::<String>("workspace"),
+                sub_matches.get_one::<String>("env"),
+                options,
+            );
+        }
+        Some(("check", sub_matches)) => {
+            let dir = sub_matches.get_one::<String>("dir").unwrap();
+            let tag = sub_matches.get_one::<String>("tag");
+            let resolve = sub_matches.get_flag("resolve");
+            let config = sub_matches.get_one::<String>("config");
+            cmd_check(
+                dir,
+                tag,
+                resolve,
+                config,
+                BatchOptions::from_args(sub_matches),
+            );
+        }
+        Some(("validate-schema", sub_matches)) => {
+            let files: Vec<&String> = sub_matches.get_many::<String>("files").unwrap().collect();
+            cmd_validate_schema(&files, sub_matches.get_flag("json"));
+        }
+        Some(("catalog", sub_matches)) => {
+            let dir = sub_matches.get_one::<String>("dir").unwrap();
+            let tag = sub_matches.get_one::<String>("tag");
+            cmd_catalog(dir, tag, sub_matches.get_flag("json"));
+        }
+        Some(("stats", sub_matches)) => {
+            let dir = sub_matches.get_one::<String>("dir").unwrap();
+            let tag = sub_matches.get_one::<String>("tag");
+            let format = if sub_matches.get_flag("json") {
+                "json"
+            } else if sub_matches.get_flag("csv") {
+                "csv"
+            } else {
+                "text"
+            };
+            let config = sub_matches.get_one::<String>("config");
+            cmd_stats(dir, tag, format, config);
+        }
+        Some(("size", sub_matches)) => {
+            let inputs: Vec<&String> = sub_matches.get_many::<String>("file").unwrap().collect();
+            let files = expand_inputs(&inputs);
+            let config = load_config(
+                Path::new(&base_dir(&files[0])),
+                sub_matches.get_one::<String>("config"),
+            )
+            .map(|config| config.size)
+            .unwrap_or_default();
+            let config = SizeConfig {
+                budget: sub_matches
+                    .get_one::<u64>("budget")
+                    .copied()
+                    .or(config.budget),
+                ..config
+            };
+            cmd_size(
+                &files,
+                sub_matches.get_flag("resolve"),
+                sub_matches.get_one::<String>("workspace"),
+                sub_matches.get_one::<String>("env"),
+                sub_matches.get_flag("json"),
+                &config,
+            );
+        }
+        Some(("impact", sub_matches)) => {
+            let dir = sub_matches.get_one::<String>("dir").unwrap();
+            let json_output = sub_matches.get_flag("json");
+            if let Some(files) = sub_matches.get_many::<String>("schema-diff") {
+                let files: Vec<&String> = files.collect();
+                cmd_impact_schema_diff(dir, files[0], files[1], json_output);
+            } else {
+                let columns: Vec<&String> =
+                    sub_matches.get_many::<String>("column").unwrap().collect();
+                cmd_impact(dir, &columns, json_output);
+            }
+        }
+        Some(("export", sub_matches)) => match sub_matches.subcommand() {
+            Some(("xlsx", args)) => cmd_export_xlsx(
+                args.get_one::<String>("path").unwrap(),
+                args.get_one::<String>("tag"),
+                args.get_one::<String>("output"),
+            ),
+            Some(("dbt", args)) => cmd_export_dbt(
+                args.get_one::<String>("path").unwrap(),
+                args.get_one::<String>("tag"),
+                args.get_one::<String>("source"),
+                args.get_one::<String>("output"),
+                WriteMode::from_args(args),
+            ),
+            Some(("access", args)) => cmd_export_access(
+                args.get_one::<String>("path").unwrap(),
+                args.get_one::<String>("tag"),
+                args.get_one::<String>("format").unwrap(),
+                args.get_one::<String>("config"),
+                args.get_one::<String>("output"),
+                WriteMode::from_args(args),
+            ),
+            Some(("dbml", args)) => cmd_export_dbml(
+                args.get_one::<String>("path").unwrap(),
+                args.get_one::<String>("tag"),
+                args.get_flag("resolve"),
+                args.get_one::<String>("output"),
+                WriteMode::from_args(args),
+            ),
+            Some(("datahub", args)) => cmd_export_datahub(
+                args.get_one::<String>("path").unwrap(),
+                args.get_one::<String>("tag"),
+                export::DatahubOptions {
+                    platform: args.get_one::<String>("platform").unwrap().clone(),
+                    database: args.get_one::<String>("database").cloned(),
+                    env: args.get_one::<String>("env").unwrap().clone(),
+                },
+                args.get_one::<String>("output"),
+                WriteMode::from_args(args),
+            ),
+            _ => {}
+        },
+        Some(("generate", sub_matches)) => match sub_matches.subcommand() {
+            Some(("tests", args)) => cmd_generate_tests(
+                args.get_one::<String>("file").unwrap(),
+                args.get_one::<String>("format").unwrap(),
+                args.get_one::<String>("output"),
+                args.get_flag("resolve"),
+                args.get_one::<String>("workspace"),
+                args.get_one::<String>("env"),
+                WriteMode::from_args(args),
+            ),
+            Some(("mock", args)) => cmd_generate_mock(
+                args.get_one::<String>("file").unwrap(),
+                codegen::mock::MockOptions {
+                    seed: *args.get_one::<u64>("seed").unwrap(),
+                    items: *args.get_one::<usize>("items").unwrap(),
+                    synth: synth_config(args.get_one::<String>("file").unwrap()),
+                },
+                args.get_one::<String>("output"),
+                args.get_flag("resolve"),
+                args.get_one::<String>("workspace"),
+                args.get_one::<String>("env"),
+                WriteMode::from_args(args),
+            ),
+            _ => {
+                let generator = match sub_matches.get_one::<String>("template") {
+                    Some(template) => Generator::Template(template),
+                    None => match sub_matches.get_one::<String>("backend").unwrap().as_str() {
+                        "graphql" => Generator::Graphql,
+                        "graphql-sdl" => Generator::GraphqlSdl,
+                        "sql" => Generator::Sql,
+                        "sql-prepared" => Generator::SqlPrepared,
+                        _ => Generator::Kotlin {
+                            package: sub_matches.get_one::<String>("package"),
+                        },
+                    },
+                };
+                cmd_generate(
+                    sub_matches.get_one::<String>("file").unwrap(),
+                    generator,
+                    sub_matches.get_one::<String>("output"),
+                    sub_matches.get_flag("resolve"),
+                    sub_matches.get_one::<String>("workspace"),
+                    sub_matches.get_one::<String>("env"),
+                    WriteMode::from_args(sub_matches),
+                );
+            }
+        },
+        Some(("parse", sub_matches)) => {
+            let file_path = sub_matches.get_one::<String>("file").unwrap();
+            cmd_parse(file_path);
+        }
+        Some(("visualize", sub_matches)) => {
+            let files: Vec<&String> = sub_matches.get_many::<String>("file").unwrap().collect();
+            let output = sub_matches.get_one::<String>("output");
+            let workspace = sub_matches.get_one::<String>("workspace");
+            let archive = sub_matches.get_flag("archive");
+            let output = VisualizeOutput {
+                path: output,
+                dir: sub_matches.get_one::<String>("output-dir"),
+                template: sub_matches.get_one::<String>("output-template"),
+                config: sub_matches.get_one::<String>("config"),
+                mode: WriteMode::from_args(sub_matches),
+            };
+            if sub_matches.get_flag("merge") {
+                cmd_visualize_merged(&files, output, workspace);
+            } else if let [file_path] = files.as_slice() {
+                cmd_visualize(file_path, output, workspace, archive);
+            } else {
+                let progress = !sub_matches.get_flag("no-progress");
+                cmd_visualize_files(&files, output, workspace, archive, progress);
+            }
+        }
+        Some(("clean", sub_matches)) => cmd_clean(
+            sub_matches
+                .get_one::<String>("dir")
+                .map_or(OUTPUT_DIR, String::as_str),
+            sub_matches.get_flag("dry-run"),
+        ),
+        Some(("fix", sub_matches)) => {
+            let files: Vec<&String> = sub_matches.get_many::<String>("file").unwrap().collect();
+            cmd_fix(
+                &files,
+                sub_matches.get_one::<String>("config"),
+                sub_matches.get_flag("dry-run"),
+            );
+        }
+        Some(("fmt", sub_matches)) => {
+            let files: Vec<&String> = sub_matches.get_many::<String>("file").unwrap().collect();
+            let options = FmtConfig {
+                sort_imports: sub_matches.get_flag("sort-imports"),
+                order_fields: sub_matches.get_flag("order-fields"),
+                join_type: sub_matches.get_one::<String>("join-type").map(|case| {
+                    match case.as_str() {
+                        "lower" => KeywordCase::Lower,
+                        _ => KeywordCase::Upper,
+                    }
+                }),
+                collapse_defaults: sub_matches.get_flag("collapse-defaults"),
+            };
+            cmd_fmt(
+                &files,
+                sub_matches.get_one::<String>("config"),
+                &options,
+                sub_matches.get_flag("check"),
+            );
+        }
+        Some(("sort", sub_matches)) => {
+            let files: Vec<&String> = sub_matches.get_many::<String>("file").unwrap().collect();
+            cmd_sort(
+                &files,
+                sub_matches.get_one::<String>("config"),
+                sub_matches.get_flag("by-openapi"),
+                sub_matches.get_flag("check"),
+            );
+        }
+        Some(("sign", sub_matches)) => {
+            let files: Vec<&String> = sub_matches.get_many::<String>("file").unwrap().collect();
+            cmd_sign(&files, sub_matches.get_one::<String>("by").unwrap());
+        }
+        Some(("verify", sub_matches)) => {
+            let files: Vec<&String> = sub_matches.get_many::<String>("file").unwrap().collect();
+            cmd_verify(&files);
+        }
+        Some(("rules", sub_matches)) => {
+            let explain = sub_matches.get_one::<String>("explain");
+            let doc = sub_matches.get_one::<String>("doc");
+            let config = sub_matches.get_one::<String>("config");
+            let format = if sub_matches.get_flag("json") {
+                RulesFormat::Json
+            } else if sub_matches.get_flag("markdown") {
+                RulesFormat::Markdown
+            } else if doc.is_some() {
+                RulesFormat::Doc
+            } else {
+                RulesFormat::Text
+            };
+            cmd_rules(explain.or(doc).map(String::as_str), format, config);
+        }
+        Some(("examples", sub_matches)) => match sub_matches.subcommand() {
+            Some(("show", args)) => cmd_examples_show(args.get_one::<String>("name").unwrap()),
+            Some(("init", args)) => cmd_examples_init(
+                args.get_one::<String>("name").unwrap(),
+                args.get_one::<String>("dir").map_or(".", String::as_str),
+                args.get_flag("force"),
+            ),
+            _ => cmd_examples_list(),
+        },
+        Some(("add", sub_matches)) => {
+            if let Some((kind, args)) = sub_matches.subcommand() {
+                cmd_add(kind, args.get_one::<String>("file").unwrap());
+            }
+        }
+        _ => {
+            // サブコマンド未指定の場合はヘルプを表示
+            Command::new("usml")
+                .about("Usecase Markup Language - API と DB のデータフローを声明的に定義する")
+                .version("0.1.0")
+                .subcommand(
+                    Command::new("validate").about("USML ファイルのバリデーションを実行する"),
+                )
+                .subcommand(Command::new("lint").about(
+                    "usml.toml の [lint] の規則の選択と重大度の上書きを適用して USML ファイルを検査する",
+                ))
+                .subcommand(
+                    Command::new("analyze")
+                        .about("AST・診断・リネージ・テーブル利用状況を JSON で出力する"),
+                )
+                .subcommand(
+                    Command::new("check")
+                        .about("ディレクトリ以下の USML ファイルをまとめてバリデーションする"),
+                )
+                .subcommand(
+                    Command::new("validate-schema")
+                        .about("import 先の OpenAPI / DBML ファイル単体の構造を検査する"),
+                )
+                .subcommand(
+                    Command::new("catalog")
+                        .about("ディレクトリ以下の usecase をタグごとに一覧表示する"),
+                )
+                .subcommand(
+                    Command::new("stats")
+                        .about("ディレクトリ以下の USML ファイルの規模・複雑さの指標を出力する"),
+                )
+                .subcommand(Command::new("size").about(
+                    "カラムの型とページサイズから usecase のレスポンスのサイズを見積もり、予算を超えるものを報告する",
+                ))
+                .subcommand(Command::new("impact").about(
+                    "カラムを削除・改名すると壊れるフィールド・filter・transform・usecase を一覧表示する",
+                ))
+                .subcommand(Command::new("export").about("マッピングを他の形式で書き出す"))
+                .subcommand(
+                    Command::new("generate").about(
+                        "解析結果をテンプレート（Jinja2 / Tera 互換）か組み込みの生成器（kotlin・graphql・sql）に渡してコードを生成する",
+                    ),
+                )
+                .subcommand(
+                    Command::new("parse").about("USML ファイルをパースしてAST情報を出力する"),
+                )
+                .subcommand(
+                    Command::new("visualize")
+                        .about("USML ドキュメントからHTMLデータフロー図を生成する"),
+                )
+                .subcommand(
+                    Command::new("clean")
+                        .about("生成元の usecase が改名・削除されて古くなった生成物を削除する"),
+                )
+                .subcommand(
+                    Command::new("fix").about("直し方のある診断をコメントを保ったまま直す"),
+                )
+                .subcommand(
+                    Command::new("fmt")
+                        .about("import・フィールドの順や表記をコメントを保ったまま正規化する"),
+                )
+                .subcommand(
+                    Command::new("sort")
+                        .about("response_mapping を OpenAPI のプロパティの順か名前順に並べ替える"),
+                )
+                .subcommand(
+                    Command::new("sign").about("usecase の今の意味を承認して記録する"),
+                )
+                .subcommand(
+                    Command::new("verify")
+                        .about("承認した usecase の意味が承認後に変わっていないかを確かめる"),
+                )
+                .subcommand(Command::new("rules").about("バリデーション規則の一覧と説明を表示する"))
+                .subcommand(
+                    Command::new("examples").about("同梱のサンプル仕様を一覧・表示・展開する"),
+                )
+                .subcommand(Command::new("add").about(
+                    "DBML・OpenAPI から選んでフィールド・フィルタ・transform を対話的に追加する",
+                ))
+                .print_help()
+                .unwrap();
+        }
+    }
+}
+
+fn cmd_validate(
+    file_path: &str,
+    json_output: bool,
+    resolve: bool,
+    config: Option<&String>,
+    workspace: Option<&String>,
+    env: Option<&String>,
+) {
+    let mut doc = match parse_file_in(file_path, env) {
+        Ok(doc) => doc,
+        Err(e) => {
+            if json_output {
+                let (rule, message) = match &e {
+                    UsmlError::Parse(inner) => ("parse", inner.to_string()),
+                    UsmlError::Io { .. } => ("io", e.to_string()),
+                    UsmlError::Environment(inner) => ("environment", inner.to_string()),
+                    _ => ("error", e.to_string()),
+                };
+                println!(
+                    r#"{{"file":"{}","status":"error","diagnostics":[{{"severity":"error","rule":"{}","message":"{}"}}]}}"#,
+                    escape_json_string(file_path),
+                    rule,
+                    escape_json_string(&message)
+                );
+            } else {
+                eprintln!("{}", e);
+            }
+            process::exit(1);
+        }
+    };
+
+    // 共有ライブラリの transform・filter を展開してから検証する
+    let validator = load_validator(Path::new(&base_dir(file_path)), config);
+    let workspace = workspace.map(|dir| load_workspace_with_limits(dir, validator.limits()));
+    if let Some(workspace) = &workspace {
+        workspace.library().expand(&mut doc);
+    }
+    let mut errors: Vec<Diagnostic> = if resolve {
+        validator.validate_with_resolver(&doc, &base_dir(file_path), &import_resolver())
+    } else {
+        validator.validate(&doc)
+    };
+    if let Some(workspace) = &workspace {
+        errors.extend(workspace.check(&doc).into_iter().map(Diagnostic::from));
+    }
+
+    if json_output {
+        let diagnostics: Vec<String> = located(file_path, &errors)
+            .iter()
+            .map(diagnostic_json)
+            .collect();
+        let has_rule_error = errors.iter().any(|err| err.severity == Severity::Error);
+        let status = if has_rule_error { "error" } else { "ok" };
+        println!(
+            r#"{{"file":"{}","status":"{}","diagnostics":[{}]}}"#,
+            escape_json_string(file_path),
+            status,
+            diagnostics.join(",")
+        );
+        if has_rule_error {
+            process::exit(1);
+        }
+    } else if !errors.iter().any(|err| err.severity.is_problem()) {
+        // 情報・ヒントだけなら成功として、内容だけを出す
+        println!("✓ バリデーション成功: '{}'", file_path);
+        for (i, err) in errors.iter().enumerate() {
+            print_diagnostic(i + 1, err);
+        }
+    } else {
+        eprintln!(
+            "✗ バリデーションエラー: '{}' ({} 件)",
+            file_path,
+            errors.len()
+        );
+        for (i, err) in errors.iter().enumerate() {
+            print_diagnostic(i + 1, err);
+        }
+        process::exit(1);
+    }
+}
+
+/// 複数ファイルのバリデーション。読めないファイルがあっても残りを検証し、最後に集計を出す
+fn cmd_validate_files(
+    files: &[&String],
+    resolve: bool,
+    config: Option<&String>,
+    workspace: Option<&String>,
+    env: Option<&String>,
+    options: BatchOptions,
+) {
+    let format = options.format;
+    let mut cache = options.cache_dir.map(|dir| Cache::load(dir));
+    let mut collector = options.report_out.map(|_| Collector::new());
+    let workspace = workspace.map(|dir| {
+        let limits = load_config(Path::new(dir), config)
+            .map(|config| config.limits)
+            .unwrap_or_default();
+        load_workspace_with_limits(dir, &limits)
+    });
+    let mut batch = Batch::new(files.len(), options.progress());
+    for file_path in files {
+        start_file(&mut batch, file_path, format);
+        let (diagnostics, resolve, cached) = match parse_file_in(file_path, env) {
+            Ok(mut doc) => {
+                if let Some(workspace) = &workspace {
+                    workspace.library().expand(&mut doc);
+                }
+                let dir = base_dir(file_path);
+                let mut validator = load_validator(Path::new(&dir), config);
+                if options.warnings_as_errors {
+                    validator = validator.with_warnings_as_errors();
+                }
+                let validation = Validation {
+                    validator: &validator,
+                    config: &config_key(Path::new(&dir), config),
+                    resolve,
+                };
+                let (mut diagnostics, resolve, cached) =
+                    validation.run(cache.as_mut(), &doc, file_path);
+                if let Some(workspace) = &workspace {
+                    diagnostics.extend(workspace.check(&doc).into_iter().map(Diagnostic::from));
+                }
+                if let Some(collector) = &mut collector {
+                    collector.add(file_path, &doc, &diagnostics);
+                }
+                (diagnostics, resolve, cached)
+            }
+            Err(e) => {
+                let rule = match &e {
+                    UsmlError::Parse(_) => "parse",
+                    UsmlError::Io { .. } => "io",
+                    UsmlError::Environment(_) => "environment",
+                    _ => "error",
+                };
+                // YAML の構文エラーは行・列まで指す
+                let location = match &e {
+                    UsmlError::Parse(parser::ParseError::YamlError(yaml)) => yaml
+                        .location()
+                        .map(|at| Location::at(file_path.as_str(), at.line(), at.column())),
+                    UsmlError::Parse(parser::ParseError::TooDeep { line, column, .. }) => {
+                        Some(Location::at(file_path.as_str(), *line, *column))
+                    }
+                    _ => None,
+                };
+                let diagnostic = Diagnostic::error(rule, e.to_string()).with_location(Some(
+                    location.unwrap_or_else(|| Location::file(file_path.as_str())),
+                ));
+                (vec![diagnostic], None, cache.as_ref().map(|_| false))
+            }
+        };
+        let result = FileResult {
+            path: file_path.to_string(),
+            diagnostics,
+            elapsed: batch.elapsed(),
+            resolve,
+            cached,
+        };
+        report_file(&mut batch, result, format);
+    }
+    save_cache(cache);
+    write_report(collector, "validate", &batch, &options);
+    finish_report(&batch, format);
+}
+
+/// `--config` のファイル、なければ `dir` から遡って見つけた usml.toml の規則を登録する
+/// 設定ファイルが無い場合は組み込み規則だけのバリデータを返す
+fn load_validator(dir: &Path, config: Option<&String>) -> Validator {
+    let Some(config) = load_config(dir, config) else {
+        return Validator::new();
+    };
+    Validator::from_config(&config).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    })
+}
+
+/// `--config`、無ければ `dir` から遡って見つけた usml.toml を読み込む
+fn load_config(dir: &Path, config: Option<&String>) -> Option<Config> {
+    let path = match config {
+        Some(path) => PathBuf::from(path),
+        None => Config::find(dir)?,
+    };
+    Some(Config::load(&path).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    }))
+}
+
+/// 環境変数 CI があるか（GitHub Actions・GitLab CI などが設定する。空と `false` は除く）
+fn is_ci() -> bool {
+    std::env::var("CI").is_ok_and(|value| !value.is_empty() && value != "false")
+}
+
+/// import を読み込む Resolver。`--allow-paths` があれば読み込めるディレクトリを制限する
+fn import_resolver() -> ImportResolver {
+    match ALLOWED_PATHS.get().cloned().flatten() {
+        Some(dirs) => ImportResolver::Sandboxed(SandboxResolver::new(FsResolver, dirs)),
+        None => ImportResolver::Any(FsResolver),
+    }
+}
+
+enum ImportResolver {
+    Any(FsResolver),
+    Sandboxed(SandboxResolver<FsResolver>),
+}
+
+impl Resolver for ImportResolver {
+    fn load(&self, path: &str) -> Result<String, ResolverError> {
+        match self {
+            ImportResolver::Any(resolver) => resolver.load(path),
+            ImportResolver::Sandboxed(resolver) => resolver.load(path),
+        }
+    }
+
+    fn size(&self, path: &str) -> Option<u64> {
+        match self {
+            ImportResolver::Any(resolver) => resolver.size(path),
+            ImportResolver::Sandboxed(resolver) => resolver.size(path),
+        }
+    }
+}
+
+/// import 参照の基準ディレクトリ（USML ファイルの親ディレクトリ）
+/// コマンドラインのパスを検証するファイルに展開する
+///
+/// ディレクトリは以下の `*.usml.yaml`（共有ライブラリを除く）、`*`・`?`・`[` を含むパスは glob
+/// （`**` は任意の段数のディレクトリ）として一致するファイルをパス順に、それ以外はそのまま返す。重複は除く
+fn expand_inputs(inputs: &[&String]) -> Vec<String> {
+    let mut files: Vec<String> = Vec::new();
+    for input in inputs {
+        let matched = if Path::new(input.as_str()).is_dir() {
+            workspace::find_documents(Path::new(input.as_str())).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(1);
+            })
+        } else if input.contains(['*', '?', '[']) {
+            glob_files(input)
+        } else {
+            vec![input.to_string()]
+        };
+        if matched.is_empty() {
+            eprintln!("一致する .usml.yaml ファイルがありません: '{}'", input);
+            process::exit(1);
+        }
+        for file in matched {
+            if !files.contains(&file) {
+                files.push(file);
+            }
+        }
+    }
+    files
+}
+
+/// glob に一致するファイル。`/` は `*` に一致しない
+fn glob_files(pattern: &str) -> Vec<String> {
+    let matcher = globset::GlobBuilder::new(pattern)
+        .literal_separator(true)
+        .build()
+        .unwrap_or_else(|e| {
+            eprintln!("glob の形式が不正です '{}': {}", pattern, e);
+            process::exit(1);
+        })
+        .compile_matcher();
+    // 特殊文字を含まない先頭のディレクトリから辿る
+    let root: PathBuf = Path::new(pattern)
+        .components()
+        .take_while(|c| !c.as_os_str().to_string_lossy().contains(['*', '?', '[']))
+        .collect();
+    let mut files = Vec::new();
+    let mut dirs = vec![if root.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        root
+    }];
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                dirs.push(path);
+                continue;
+            }
+            // `./` を付けずに書いたパターンには `./` を除いたパスで照合する
+            let path = path.strip_prefix(".").unwrap_or(&path).to_path_buf();
+            if matcher.is_match(&path) {
+                files.push(path.to_string_lossy().to_string());
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+fn base_dir(file_path: &str) -> String {
+    Path::new(file_path)
+        .parent()
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn cmd_analyze(file_path: &str, resolve: bool, workspace: Option<&String>, env: Option<&String>) {
+    let analysis = load_analysis(file_path, resolve, workspace, env);
+    match serde_json::to_string_pretty(&analysis) {
+        Ok(json) => println!("{}", json),
+        Err(e) => {
+            eprintln!("JSON 出力エラー: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+/// `usml generate` で使う生成器
+enum Generator<'a> {
+    /// テンプレートファイルのパス
+    Template(&'a str),
+    Kotlin {
+        package: Option<&'a String>,
+    },
+    Graphql,
+    GraphqlSdl,
+    Sql,
+    SqlPrepared,
+}
+
+impl Generator<'_> {
+    /// マニフェストに記録するコマンド
+    fn command(&self) -> String {
+        match self {
+            Generator::Template(path) => format!("generate --template {}", path),
+            Generator::Kotlin { .. } => "generate --backend kotlin".to_string(),
+            Generator::Graphql => "generate --backend graphql".to_string(),
+            Generator::GraphqlSdl => "generate --backend graphql-sdl".to_string(),
+            Generator::Sql => "generate --backend sql".to_string(),
+            Generator::SqlPrepared => "generate --backend sql-prepared".to_string(),
+        }
+    }
+}
+
+fn cmd_generate(
+    file_path: &str,
+    generator: Generator,
+    output: Option<&String>,
+    resolve: bool,
+    workspace: Option<&String>,
+    env: Option<&String>,
+    mode: WriteMode,
+) {
+    let command = generator.command();
+    let analysis = load_analysis(file_path, resolve, workspace, env);
+    refuse_invalid(file_path, &analysis);
+    let generated = match generator {
+        Generator::Template(template_path) => {
+            let template = match fs::read_to_string(template_path) {
+                Ok(template) => template,
+                Err(e) => {
+                    eprintln!("ファイル読み込みエラー '{}': {}", template_path, e);
+                    process::exit(1);
+                }
+            };
+            codegen::Generator::new(template_path, &template)
+                .and_then(|generator| generator.render(file_path, &analysis))
+                .map_err(|e| e.to_string())
+        }
+        Generator::Kotlin { package } => {
+            codegen::kotlin::generate(file_path, &analysis, package.map(String::as_str))
+                .map_err(|e| e.to_string())
+        }
+        Generator::Graphql => {
+            codegen::graphql::resolver(file_path, &analysis).map_err(|e| e.to_string())
+        }
+        Generator::GraphqlSdl => {
+            codegen::graphql::sdl(file_path, &analysis).map_err(|e| e.to_string())
+        }
+        Generator::Sql => codegen::sql::generate(file_path, &analysis).map_err(|e| e.to_string()),
+        Generator::SqlPrepared => codegen::sql::prepared(file_path, &analysis)
+            .map_err(|e| e.to_string())
+            .and_then(|prepared| {
+                serde_json::to_string_pretty(&prepared)
+                    .map(|json| json + "\n")
+                    .map_err(|e| format!("JSON シリアライズエラー: {}", e))
+            }),
+    };
+    let generated = match generated {
+        Ok(generated) => generated,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
+    match output {
+        Some(output_path) => {
+            if write_generated(output_path, &generated, mode) {
+                record_artifact(
+                    &parent_dir(output_path),
+                    output_path,
+                    command,
+                    file_path,
+                    Some(&analysis.document.usecase.name),
+                );
+                println!("✓ コードを生成しました: '{}'", output_path);
+            }
+        }
+        None => print!("{}", generated),
+    }
+}
+
+/// バリデーションエラーのあるドキュメントからは生成しない（不正なクエリを生成しないため）
+fn refuse_invalid(file_path: &str, analysis: &api::Analysis) {
+    let errors: Vec<Diagnostic> = analysis
+        .diagnostics
+        .iter()
+        .filter(|diag| diag.severity == Severity::Error)
+        .cloned()
+        .collect();
+    if errors.is_empty() {
+        return;
+    }
+    eprintln!(
+        "✗ バリデーションエラーがあるため生成しません: '{}' ({} 件)",
+        file_path,
+        errors.len()
+    );
+    for (i, diag) in located(file_path, &errors).iter().enumerate() {
+        print_diagnostic(i + 1, diag);
+    }
+    process::exit(1);
+}
+
+fn cmd_generate_tests(
+    file_path: &str,
+    format: &str,
+    output: Option<&String>,
+    resolve: bool,
+    workspace: Option<&String>,
+    env: Option<&String>,
+    mode: WriteMode,
+) {
+    let analysis = load_analysis(file_path, resolve, workspace, env);
+    let case = match codegen::testcase::test_case(file_path, &analysis, &synth_config(file_path)) {
+        Ok(case) => case,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
+    let generated = match format {
+        "json" => match serde_json::to_string_pretty(&case) {
+            Ok(json) => json + "\n",
+            Err(e) => {
+                eprintln!("JSON シリアライズエラー: {}", e);
+                process::exit(1);
+            }
+        },
+        _ => case.to_rust(),
+    };
+    match output {
+        Some(output_path) => {
+            if write_generated(output_path, &generated, mode) {
+                record_artifact(
+                    &parent_dir(output_path),
+                    output_path,
+                    format!("generate tests --format {}", format),
+                    file_path,
+                    Some(&analysis.document.usecase.name),
+                );
+                println!("✓ テストを生成しました: '{}'", output_path);
+            }
+        }
+        None => print!("{}", generated),
+    }
+}
+
+fn cmd_generate_mock(
+    file_path: &str,
+    options: codegen::mock::MockOptions,
+    output: Option<&String>,
+    resolve: bool,
+    workspace: Option<&String>,
+    env: Option<&String>,
+    mode: WriteMode,
+) {
+    let analysis = load_analysis(file_path, resolve, workspace, env);
+    let payload = codegen::mock::mock(&analysis, &options);
+    let generated = match serde_json::to_string_pretty(&payload) {
+        Ok(json) => json + "\n",
+        Err(e) => {
+            eprintln!("JSON シリアライズエラー: {}", e);
+            process::exit(1);
+        }
+    };
+    match output {
+        Some(output_path) => {
+            if write_generated(output_path, &generated, mode) {
+                record_artifact(
+                    &parent_dir(output_path),
+                    output_path,
+                    format!("generate mock --seed {}", options.seed),
+                    file_path,
+                    Some(&analysis.document.usecase.name),
+                );
+                println!("✓ モックデータを生成しました: '{}'", output_path);
+            }
+        }
+        None => print!("{}", generated),
+    }
+}
+
+/// ファイルのディレクトリから遡って見つけた usml.toml の `[synth]`（モック・フィクスチャの値の作り方）
+fn synth_config(file_path: &str) -> SynthConfig {
+    load_config(Path::new(&base_dir(file_path)), None)
+        .map(|config| config.synth)
+        .unwrap_or_default()
+}
+
+/// ファイルを解析する。`workspace` を指定すると共有ライブラリを展開し、参照先の usecase を含めたリネージにする
+/// `env` を指定すると environments のその環境の import を適用する
+fn load_analysis(
+    file_path: &str,
+    resolve: bool,
+    workspace: Option<&String>,
+    env: Option<&String>,
+) -> api::Analysis {
+    let mut doc = parse_file_in(file_path, env).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+    let workspace = workspace.map(|dir| load_workspace(dir));
+    if let Some(workspace) = &workspace {
+        workspace.library().expand(&mut doc);
+    }
+
+    let mut analysis = if resolve {
+        api::analyze_with_resolver(doc, &base_dir(file_path), &import_resolver())
+    } else {
+        api::analyze(doc)
+    };
+    // 参照先の usecase を含めたリネージに置き換える
+    if let Some(workspace) = &workspace {
+        analysis.diagnostics.extend(
+            workspace
+                .check(&analysis.document)
+                .into_iter()
+                .map(Diagnostic::from),
+        );
+        analysis.lineage = workspace.lineage(&analysis.document);
+    }
+    analysis
+}
+
+fn cmd_check(
+    dir: &str,
+    tag: Option<&String>,
+    resolve: bool,
+    config: Option<&String>,
+    options: BatchOptions,
+) {
+    let format = options.format;
+    let validator = load_validator(Path::new(dir), config);
+    let workspace = load_workspace_with_limits(dir, validator.limits());
+    let entries = select_documents(&workspace, tag);
+    let mut cache = options.cache_dir.map(|dir| Cache::load(dir));
+    let mut collector = options.report_out.map(|_| Collector::new());
+    let validation = Validation {
+        validator: &validator,
+        config: &config_key(Path::new(dir), config),
+        resolve,
+    };
+
+    let mut batch = Batch::new(entries.len(), options.progress());
+    for entry in entries {
+        start_file(&mut batch, &entry.path, format);
+        let (mut diagnostics, resolve, cached) =
+            validation.run(cache.as_mut(), &entry.document, &entry.path);
+        diagnostics.extend(workspace.validate_document(entry));
+        if let Some(collector) = &mut collector {
+            collector.add(&entry.path, &entry.document, &diagnostics);
+        }
+        let result = FileResult {
+            path: entry.path.clone(),
+            diagnostics,
+            elapsed: batch.elapsed(),
+            resolve,
+            cached,
+        };
+        report_file(&mut batch, result, format);
+    }
+    save_cache(cache);
+    write_report(collector, "check", &batch, &options);
+    finish_report(&batch, format);
+}
+
+/// `validate`・`check` で 1 ファイルを検証する設定
+struct Validation<'a> {
+    validator: &'a Validator,
+    /// 設定ファイルの内容（キャッシュのキーに混ぜる）
+    config: &'a str,
+    resolve: bool,
+}
+
+impl Validation<'_> {
+    /// `doc` を検証する。`cache` があればキーが同じ前回の診断を使い、無ければ検証して記録する
+    ///
+    /// import の解決にかかった時間（`--resolve` で検証したときだけ）と、キャッシュを使ったか（`cache` があるときだけ）も返す
+    fn run(
+        &self,
+        cache: Option<&mut Cache>,
+        doc: &UsmlDocument,
+        file_path: &str,
+    ) -> (Vec<Diagnostic>, Option<Duration>, Option<bool>) {
+        let Some(cache) = cache else {
+            let (diagnostics, resolve) =
+                validate_document(self.validator, doc, file_path, self.resolve);
+            return (diagnostics, resolve, None);
+        };
+        let key = self.cache_key(doc, file_path);
+        if let Some(diagnostics) = cache.get(file_path, &key) {
+            return (diagnostics, None, Some(true));
+        }
+        let (diagnostics, resolve) =
+            validate_document(self.validator, doc, file_path, self.resolve);
+        cache.insert(file_path, key, diagnostics.clone());
+        (diagnostics, resolve, Some(false))
+    }
+
+    /// 検証結果を左右するもの（`--allow-paths`・設定・ドキュメント・`--resolve` なら import 先の内容）から作るキー
+    fn cache_key(&self, doc: &UsmlDocument, file_path: &str) -> String {
+        let mut parts = vec![
+            if self.resolve { "resolve" } else { "static" }.to_string(),
+            sandbox_key(),
+            self.config.to_string(),
+            serde_json::to_string(doc).unwrap_or_default(),
+        ];
+        if self.resolve {
+            let plan = validator::plan_imports(doc, &base_dir(file_path));
+            let resolver = import_resolver();
+            for file in plan
+                .openapi
+                .iter()
+                .map(|(file, ..)| file)
+                .chain(&plan.dbml_files)
+            {
+                parts.push(file.clone());
+                parts.push(resolver.load(file).unwrap_or_else(|e| e.to_string()));
+            }
+        }
+        fingerprint::hash(parts.iter().map(String::as_str))
+    }
+}
+
+/// キャッシュのキーに混ぜる `--allow-paths`。相対パスはカレントディレクトリで意味が変わるため絶対パスにする
+fn sandbox_key() -> String {
+    match ALLOWED_PATHS.get().cloned().flatten() {
+        Some(dirs) => dirs
+            .iter()
+            .map(|dir| {
+                fs::canonicalize(dir)
+                    .map(|path| path.to_string_lossy().to_string())
+                    .unwrap_or_else(|_| dir.clone())
+            })
+            .collect::<Vec<_>>()
+            .join(","),
+        None => "unrestricted".to_string(),
+    }
+}
+
+/// キャッシュのキーに混ぜる設定（`--config`、無ければ `dir` から遡って見つけた usml.toml）
+fn config_key(dir: &Path, config: Option<&String>) -> String {
+    load_config(dir, config)
+        .map(|config| format!("{:?}", config))
+        .unwrap_or_default()
+}
+
+/// `--report-out` があれば実行レポートを書き出す
+fn write_report(
+    collector: Option<Collector>,
+    command: &'static str,
+    batch: &Batch,
+    options: &BatchOptions,
+) {
+    let (Some(collector), Some(path)) = (collector, options.report_out) else {
+        return;
+    };
+    match collector.finish(command, batch).write(path) {
+        Ok(()) if options.format == ReportFormat::Text => {
+            batch.suspend(|| println!("✓ レポートを書き出しました: {}", path))
+        }
+        Ok(()) => {}
+        Err(e) => eprintln!("⚠ レポートの書き込みエラー: {}", e),
+    }
+}
+
+fn save_cache(cache: Option<Cache>) {
+    if let Some(cache) = cache
+        && let Err(e) = cache.save()
+    {
+        eprintln!("⚠ キャッシュの書き込みエラー: {}", e);
+    }
+}
+
+/// `doc` を検証する。`resolve` のときは import の解決にかかった時間も返す
+fn validate_document(
+    validator: &Validator,
+    doc: &UsmlDocument,
+    file_path: &str,
+    resolve: bool,
+) -> (Vec<Diagnostic>, Option<Duration>) {
+    if !resolve {
+        return (validator.validate(doc), None);
+    }
+    let started = Instant::now();
+    let (ctx, resolve_errors) = validator::resolve_imports_with_limits(
+        doc,
+        &base_dir(file_path),
+        &import_resolver(),
+        validator.limits(),
+    );
+    let elapsed = started.elapsed();
+    (
+        validator.validate_with_context(doc, &ctx, resolve_errors),
+        Some(elapsed),
+    )
+}
+
+/// 1 ファイルの結果を処理時間付きで出力する
+fn print_file_result(result: &FileResult) {
+    let elapsed = batch::format_duration(result.elapsed);
+    let elapsed = if result.cached == Some(true) {
+        format!("{}, キャッシュ", elapsed)
+    } else {
+        elapsed
+    };
+    if !result.has_problem() {
+        println!("✓ {} ({})", result.path, elapsed);
+    } else {
+        eprintln!(
+            "✗ {} ({} 件, {})",
+            result.path,
+            result.diagnostics.len(),
+            elapsed
+        );
+    }
+    for (i, diag) in result.diagnostics.iter().enumerate() {
+        print_diagnostic(i + 1, diag);
+    }
+}
+
+/// 複数ファイルの検証結果の出力形式（`--json`・`--format`）
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ReportFormat {
+    Text,
+    /// 最後にまとめて 1 つの JSON
+    Json,
+    /// 検証しながら 1 行 1 イベントの JSON（file-start・diagnostic・file-end・summary）
+    Ndjson,
+    /// 1 行 1 診断の `ファイル:行:列: 重大度[規則]: メッセージ`（エディタの problem matcher・grep 向け）
+    Compact,
+}
+
+impl ReportFormat {
+    fn from_args(args: &ArgMatches) -> Self {
+        if args.get_flag("json") {
+            return Self::Json;
+        }
+        match args.get_one::<String>("output").map(String::as_str) {
+            Some("json") => Self::Json,
+            Some("ndjson") => Self::Ndjson,
+            Some("compact") => Self::Compact,
+            _ => Self::Text,
+        }
+    }
+}
+
+/// 複数ファイルを検証するコマンドの出力に関する指定
+struct BatchOptions<'a> {
+    format: ReportFormat,
+    cache_dir: Option<&'a String>,
+    /// 実行レポートの書き出し先（`--report-out`）
+    report_out: Option<&'a String>,
+    no_progress: bool,
+    /// 警告をエラーとして報告する（`usml lint --deny-warnings`）
+    warnings_as_errors: bool,
+}
+
+impl<'a> BatchOptions<'a> {
+    fn from_args(args: &'a ArgMatches) -> Self {
+        Self {
+            format: ReportFormat::from_args(args),
+            cache_dir: args.get_one::<String>("cache-dir"),
+            report_out: args.get_one::<String>("report-out"),
+            no_progress: args.get_flag("no-progress"),
+            warnings_as_errors: false,
+        }
+    }
+
+    /// 進捗バーを出すか（テキスト出力で `--no-progress` が無いときだけ）
+    fn progress(&self) -> bool {
+        !self.no_progress && self.format == ReportFormat::Text
+    }
+}
+
+fn report_format_arg() -> Arg {
+    Arg::new("output")
+        .help("出力形式（ndjson は検証しながら file-start・diagnostic・file-end・summary のイベントを、compact は `ファイル:行:列: 重大度[規則]: メッセージ` を 1 行ずつ出力する）")
+        .long("format")
+        .visible_alias("output")
+        .value_name("FORMAT")
+        .value_parser(["text", "json", "ndjson", "compact"])
+        .conflicts_with("json")
+}
+
+/// ファイルの処理を始める（ndjson では file-start を出力する）
+fn start_file(batch: &mut Batch, path: &str, format: ReportFormat) {
+    batch.start(path);
+    if format == ReportFormat::Ndjson {
+        println!(
+            r#"{{"event":"file-start","file":"{}"}}"#,
+            escape_json_string(path)
+        );
+    }
+}
+
+/// 1 ファイルの結果を出力して記録する（json は最後にまとめて出力する）
+fn report_file(batch: &mut Batch, result: FileResult, format: ReportFormat) {
+    match format {
+        ReportFormat::Text => batch.suspend(|| print_file_result(&result)),
+        ReportFormat::Json => {}
+        ReportFormat::Compact => print_compact(&result),
+        ReportFormat::Ndjson => {
+            let file = escape_json_string(&result.path);
+            for diag in &located(&result.path, &result.diagnostics) {
+                println!(
+                    r#"{{"event":"diagnostic","file":"{}","diagnostic":{}}}"#,
+                    file,
+                    diagnostic_json(diag)
+                );
+            }
+            let resolve_ms = result
+                .resolve
+                .map(|time| format!(r#","resolve_ms":{}"#, time.as_millis()))
+                .unwrap_or_default();
+            let cached = result
+                .cached
+                .map(|cached| format!(r#","cached":{}"#, cached))
+                .unwrap_or_default();
+            println!(
+                r#"{{"event":"file-end","file":"{}","status":"{}","diagnostics":{},"elapsed_ms":{}{}{}}}"#,
+                file,
+                if result.has_error() { "error" } else { "ok" },
+                result.diagnostics.len(),
+                result.elapsed.as_millis(),
+                resolve_ms,
+                cached
+            );
+        }
+    }
+    batch.finish(result);
+}
+
+/// 集計を出力し、エラーがあれば終了コード 1 で終わる
+fn finish_report(batch: &Batch, format: ReportFormat) {
+    match format {
+        ReportFormat::Text => batch.print_summary(),
+        ReportFormat::Json => print_batch_json(batch),
+        ReportFormat::Compact => {}
+        ReportFormat::Ndjson => {
+            let totals = batch.totals();
+            let cached = totals
+                .cached
+                .map(|cached| format!(r#","cached":{}"#, cached))
+                .unwrap_or_default();
+            println!(
+                r#"{{"event":"summary","status":"{}","files":{},"failed":{},"errors":{},"warnings":{},"infos":{},"hints":{},"elapsed_ms":{}{}}}"#,
+                if batch.has_error() { "error" } else { "ok" },
+                totals.files,
+                totals.failed,
+                totals.errors,
+                totals.warnings,
+                totals.infos,
+                totals.hints,
+                totals.elapsed.as_millis(),
+                cached
+            );
+        }
+    }
+    if batch.has_error() {
+        process::exit(1);
+    }
+}
+
+/// 診断を `ファイル:行:列: 重大度[規則]: メッセージ` で 1 行ずつ出力する
+///
+/// 規則の診断には記録した JSON Pointer のソース上の位置を付ける。位置が分からない診断は 1 行 1 列を指す
+fn print_compact(result: &FileResult) {
+    for diag in &located(&result.path, &result.diagnostics) {
+        let (file, line, column) = match &diag.location {
+            Some(location) => (
+                location.file.as_str(),
+                location.line.unwrap_or(1),
+                location.column.unwrap_or(1),
+            ),
+            None => (result.path.as_str(), 1, 1),
+        };
+        let code = diag.code.as_deref().unwrap_or(&diag.rule);
+        println!(
+            "{}:{}:{}: {}[{}]: {}",
+            file,
+            line,
+            column,
+            diag.severity.as_str(),
+            code,
+            diag.message.replace('\n', " ")
+        );
+        // 添えたヒントは同じ位置の hint の行にする
+        for hint in &diag.hints {
+            println!(
+                "{}:{}:{}: hint[{}]: {}",
+                file,
+                line,
+                column,
+                code,
+                hint.replace('\n', " ")
+            );
+        }
+    }
+}
+
+/// 規則の診断に JSON Pointer のソース上の位置を付ける（`locate::locate`）。ファイルが読めなければそのまま
+fn located(path: &str, diagnostics: &[Diagnostic]) -> Vec<Diagnostic> {
+    let mut diagnostics = diagnostics.to_vec();
+    if let Ok(source) = fs::read_to_string(path) {
+        locate::locate(&source, path, &mut diagnostics);
+    }
+    diagnostics
+}
+
+fn print_batch_json(batch: &Batch) {
+    let files: Vec<String> = batch
+        .results
+        .iter()
+        .map(|result| {
+            let diagnostics: Vec<String> = located(&result.path, &result.diagnostics)
+                .iter()
+                .map(diagnostic_json)
+                .collect();
+            format!(
+                r#"{{"file":"{}","status":"{}","diagnostics":[{}]}}"#,
+                escape_json_string(&result.path),
+                if result.has_error() { "error" } else { "ok" },
+                diagnostics.join(",")
+            )
+        })
+        .collect();
+    println!(
+        r#"{{"status":"{}","files":[{}]}}"#,
+        if batch.has_error() { "error" } else { "ok" },
+        files.join(",")
+    );
+}
+
+fn cmd_validate_schema(files: &[&String], json_output: bool) {
+    // .usml.yaml は import 先のファイルに置き換える（複数の usecase が同じファイルを参照しても 1 回だけ検査する）
+    let mut targets: Vec<(String, schema::SchemaKind)> = Vec::new();
+    for file in files {
+        let file = file.as_str();
+        let mut found = Vec::new();
+        if file.ends_with(".usml.yaml") {
+            let doc = load_document(file);
+            let dir = base_dir(file);
+            if let Some((path, ..)) = doc
+                .import
+                .openapi
+                .as_deref()
+                .and_then(resolver::openapi::parse_openapi_ref)
+            {
+                found.push((
+                    resolver::join_import_path(&dir, path),
+                    schema::SchemaKind::Openapi,
+                ));
+            }
+            for reference in doc.import.dbml.iter().flatten() {
+                if let Some((path, _)) = resolver::dbml::parse_dbml_ref(reference) {
+                    found.push((
+                        resolver::join_import_path(&dir, path),
+                        schema::SchemaKind::Dbml,
+                    ));
+                }
+            }
+        } else {
+            match schema::SchemaKind::for_path(file) {
+                Some(kind) => found.push((file.to_string(), kind)),
+                None => {
+                    eprintln!(
+                        "'{}' は OpenAPI（.yaml / .yml / .json）・DBML（.dbml）・USML（.usml.yaml）のいずれでもありません",
+                        file
+                    );
+                    process::exit(1);
+                }
+            }
+        }
+        for target in found {
+            if !targets.contains(&target) {
+                targets.push(target);
+            }
+        }
+    }
+
+    let results: Vec<(String, Vec<Diagnostic>)> = targets
+        .into_iter()
+        .map(|(path, kind)| {
+            let diagnostics = schema::check_file(&path, kind, &import_resolver());
+            (path, diagnostics)
+        })
+        .collect();
+    let has_error = results
+        .iter()
+        .any(|(_, errors)| errors.iter().any(|err| err.severity == Severity::Error));
+
+    if json_output {
+        let files: Vec<String> = results
+            .iter()
+            .map(|(path, errors)| {
+                let status = if errors.iter().any(|err| err.severity == Severity::Error) {
+                    "error"
+                } else {
+                    "ok"
+                };
+                let diagnostics: Vec<String> = errors.iter().map(diagnostic_json).collect();
+                format!(
+                    r#"{{"file":"{}","status":"{}","diagnostics":[{}]}}"#,
+                    escape_json_string(path),
+                    status,
+                    diagnostics.join(",")
+                )
+            })
+            .collect();
+        println!(
+            r#"{{"status":"{}","files":[{}]}}"#,
+            if has_error { "error" } else { "ok" },
+            files.join(",")
+        );
+    } else {
+        for (path, errors) in &results {
+            if errors.is_empty() {
+                println!("✓ {}", path);
+                continue;
+            }
+            eprintln!("✗ {} ({} 件)", path, errors.len());
+            for (i, err) in errors.iter().enumerate() {
+                print_diagnostic(i + 1, err);
+            }
+        }
+    }
+    if has_error {
+        process::exit(1);
+    }
+}
+
+fn cmd_impact(dir: &str, columns: &[&String], json_output: bool) {
+    let workspace = load_workspace(dir);
+    let mut results = Vec::new();
+    for column in columns {
+        let Some((table, name)) = column.split_once('.') else {
+            eprintln!(
+                "--column は テーブル.カラム の形式で指定してください: '{}'",
+                column
+            );
+            process::exit(1);
+        };
+        results.push((
+            column.as_str(),
+            impact::column_impact(&workspace, table, name),
+        ));
+    }
+
+    if json_output {
+        let columns: Vec<serde_json::Value> = results
+            .iter()
+            .map(|(column, impacts)| {
+                serde_json::json!({
+                    "column": column,
+                    "usecases": affected_usecases(impacts),
+                    "impacts": impacts,
+                })
+            })
+            .collect();
+        match serde_json::to_string_pretty(&serde_json::json!({ "columns": columns })) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("JSON 出力エラー: {}", e);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    for (column, impacts) in &results {
+        if impacts.is_empty() {
+            println!("{}: 影響を受ける箇所はありません", column);
+            continue;
+        }
+        println!(
+            "{}: {} usecase・{} 箇所",
+            column,
+            affected_usecases(impacts),
+            impacts.len()
+        );
+        print_impacts(impacts.iter().map(|impact| (impact, None)));
+    }
+}
+
+/// 影響を受ける usecase の数
+fn affected_usecases(impacts: &[impact::Impact]) -> usize {
+    let mut files: Vec<&str> = impacts.iter().map(|i| i.file.as_str()).collect();
+    files.dedup();
+    files.len()
+}
+
+/// 参照箇所を usecase ごとにまとめて表示する
+fn print_impacts<'a>(
+    impacts: impl IntoIterator<Item = (&'a impact::Impact, Option<impact::ImpactSeverity>)>,
+) {
+    let mut file = None;
+    for (impact, severity) in impacts {
+        if file != Some(&impact.file) {
+            println!("  {}（{}）", impact.file, impact.usecase);
+            file = Some(&impact.file);
+        }
+        let via = impact
+            .via
+            .as_ref()
+            .map(|via| format!("（{} 経由）", via))
+            .unwrap_or_default();
+        let severity = severity
+            .map(|s| format!("{:<7}", s.as_str()))
+            .unwrap_or_default();
+        println!(
+            "    {}{:<14} {:<24} {}{}",
+            severity,
+            impact.kind.as_str(),
+            impact.target,
+            impact.detail,
+            via
+        );
+    }
+}
+
+fn cmd_impact_schema_diff(dir: &str, old: &str, new: &str, json_output: bool) {
+    let load = |path: &str| match resolver::dbml::resolve_dbml(path) {
+        Ok(tables) => tables,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
+    let changes = impact::schema_diff(&load(old), &load(new));
+    let workspace = load_workspace(dir);
+    let results = impact::schema_impact(&workspace, changes);
+
+    if json_output {
+        match serde_json::to_string_pretty(&serde_json::json!({ "changes": results })) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("JSON 出力エラー: {}", e);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if results.is_empty() {
+        println!("削除・改名・型の変更があったカラムはありません");
+        return;
+    }
+    for result in &results {
+        let change = &result.change;
+        let description = match &change.change {
+            impact::ColumnChange::Removed => "削除".to_string(),
+            impact::ColumnChange::Renamed { to } => format!("{} に改名", to),
+            impact::ColumnChange::Retyped { from, to } => {
+                format!("型を {} から {} に変更", from, to)
+            }
+        };
+        let Some(severity) = result.severity else {
+            println!(
+                "{}.{}: {}・影響を受ける箇所はありません",
+                change.table, change.column, description
+            );
+            continue;
+        };
+        println!(
+            "{}.{}: {}・{} usecase・{} 箇所（{}）",
+            change.table,
+            change.column,
+            description,
+            result.usecases,
+            result.impacts.len(),
+            severity.as_str()
+        );
+        print_impacts(result.impacts.iter().map(|i| (&i.impact, Some(i.severity))));
+    }
+}
+
+fn cmd_catalog(dir: &str, tag: Option<&String>, json_output: bool) {
+    let workspace = load_workspace(dir);
+    let tags = workspace.tags();
+    let mut groups: Vec<(&str, Vec<&WorkspaceDocument>)> = match tag {
+        Some(tag) => vec![(tag.as_str(), select_documents(&workspace, Some(tag)))],
+        None => tags.into_iter().collect(),
+    };
+    let untagged: Vec<&WorkspaceDocument> = workspace
+        .documents()
+        .iter()
+        .filter(|d| d.document.usecase.tags.is_empty())
+        .collect();
+
+    if json_output {
+        let entry_json = |entry: &WorkspaceDocument| {
+            serde_json::json!({
+                "name": entry.document.usecase.name,
+                "summary": entry.document.usecase.summary,
+                "file": entry.path,
+                "tags": entry.document.usecase.tags,
+                "owner": entry.document.usecase.owner,
+                "reviewers": entry.document.usecase.reviewers,
+            })
+        };
+        let tags: serde_json::Map<String, serde_json::Value> = groups
+            .iter()
+            .map(|(tag, entries)| {
+                let entries = entries.iter().map(|e| entry_json(e)).collect();
+                (tag.to_string(), serde_json::Value::Array(entries))
+            })
+            .collect();
+        let mut catalog = serde_json::json!({ "tags": tags });
+        if tag.is_none() {
+            catalog["untagged"] = untagged.iter().map(|e| entry_json(e)).collect();
+        }
+        match serde_json::to_string_pretty(&catalog) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("JSON 出力エラー: {}", e);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if tag.is_none() && !untagged.is_empty() {
+        groups.push(("(タグなし)", untagged));
+    }
+    for (tag, entries) in groups {
+        println!("{} ({})", tag, entries.len());
+        for entry in entries {
+            let usecase = &entry.document.usecase;
+            match &usecase.owner {
+                Some(owner) => println!("  {}  {}  (owner: {})", usecase.name, entry.path, owner),
+                None => println!("  {}  {}", usecase.name, entry.path),
+            }
+        }
+    }
+}
+
+fn cmd_stats(dir: &str, tag: Option<&String>, format: &str, config: Option<&String>) {
+    let workspace = load_workspace(dir);
+    let validator = load_validator(Path::new(dir), config);
+    let mut stats = WorkspaceStats::new();
+    for entry in select_documents(&workspace, tag) {
+        let mut diagnostics = validator.validate(&entry.document);
+        diagnostics.extend(workspace.validate_document(entry));
+        stats.add(&entry.path, &entry.document, &diagnostics);
+    }
+
+    match format {
+        "json" => match serde_json::to_string_pretty(&stats) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("JSON 出力エラー: {}", e);
+                process::exit(1);
+            }
+        },
+        "csv" => {
+            println!("file,usecase,fields,joins,max_join_depth,transforms,tables,errors,warnings");
+            for file in &stats.files {
+                println!(
+                    "{},{},{},{},{},{},{},{},{}",
+                    csv_field(&file.file),
+                    csv_field(&file.usecase),
+                    file.fields,
+                    file.joins,
+                    file.max_join_depth,
+                    file.transforms,
+                    file.tables.len(),
+                    file.errors,
+                    file.warnings
+                );
+            }
+        }
+        _ => {
+            for file in &stats.files {
+                println!(
+                    "{}  {}: フィールド {} / 結合 {}（最大 {} 段） / transform {} / テーブル {} / エラー {} / 警告 {}",
+                    file.file,
+                    file.usecase,
+                    file.fields,
+                    file.joins,
+                    file.max_join_depth,
+                    file.transforms,
+                    file.tables.len(),
+                    file.errors,
+                    file.warnings
+                );
+            }
+            println!();
+            println!(
+                "合計: {} ファイル / フィールド {} / エラー {} / 警告 {}",
+                stats.files.len(),
+                stats.fields,
+                stats.errors,
+                stats.warnings
+            );
+            println!("結合の段数ごとのフィールド数:");
+            for (depth, count) in &stats.join_depths {
+                println!("  {} 段: {}", depth, count);
+            }
+            println!("transform の種類:");
+            for (kind, count) in &stats.transform_usage {
+                println!("  {}: {}", kind, count);
+            }
+            println!("テーブルを参照する usecase の数:");
+            for (table, count) in &stats.table_fan_in {
+                println!("  {}: {}", table, count);
+            }
+        }
+    }
+}
+
+fn cmd_size(
+    files: &[String],
+    resolve: bool,
+    workspace: Option<&String>,
+    env: Option<&String>,
+    json_output: bool,
+    config: &SizeConfig,
+) {
+    let sizes: Vec<ResponseSize> = files
+        .iter()
+        .map(|file| {
+            let analysis = load_analysis(file, resolve, workspace, env);
+            size::estimate(file, &analysis, config)
+        })
+        .collect();
+
+    if json_output {
+        match serde_json::to_string_pretty(&sizes) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("JSON 出力エラー: {}", e);
+                process::exit(1);
+            }
+        }
+    } else {
+        for size in &sizes {
+            let page = size
+                .page
+                .map(|page| format!(" / ページ {}〜{} 件", page.typical, page.worst))
+                .unwrap_or_default();
+            println!(
+                "{}{}  {}: 典型 {} / 最悪 {}{}",
+                if size.over_budget { "✗ " } else { "" },
+                size.file,
+                size.usecase,
+                human_bytes(size.bytes.typical),
+                human_bytes(size.bytes.worst),
+                page
+            );
+            for field in size.fields.iter().take(3) {
+                println!(
+                    "    {}: 典型 {} / 最悪 {}",
+                    field.path,
+                    human_bytes(field.bytes.typical),
+                    human_bytes(field.bytes.worst)
+                );
+            }
+        }
+        if let Some(budget) = config.budget {
+            let over = sizes.iter().filter(|s| s.over_budget).count();
+            println!();
+            println!(
+                "予算 {} を超える usecase: {} / {}",
+                human_bytes(budget),
+                over,
+                sizes.len()
+            );
+        }
+    }
+    if sizes.iter().any(|s| s.over_budget) {
+        process::exit(1);
+    }
+}
+
+/// バイト数を KiB・MiB で表す
+fn human_bytes(bytes: u64) -> String {
+    const KIB: f64 = 1024.0;
+    let value = bytes as f64;
+    if value >= KIB * KIB {
+        format!("{:.1} MiB", value / KIB / KIB)
+    } else if value >= KIB {
+        format!("{:.1} KiB", value / KIB)
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
+/// カンマ・ダブルクォート・改行を含む値をダブルクォートで囲む
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// `tag` の付いたドキュメント。`tag` が無ければすべて。該当が無い場合は終了する
+fn select_documents<'a>(
+    workspace: &'a Workspace,
+    tag: Option<&'a String>,
+) -> Vec<&'a WorkspaceDocument> {
+    let Some(tag) = tag else {
+        return workspace.documents().iter().collect();
+    };
+    let entries: Vec<_> = workspace.tagged(tag).collect();
+    if entries.is_empty() {
+        eprintln!("タグ '{}' の付いた usecase がありません", tag);
+        process::exit(1);
+    }
+    entries
+}
+
+/// `tag` の付いた usecase のファイル。読めないファイルはエラーを報告するため残す。該当が無い場合は終了する
+fn select_tagged_files<'a>(
+    files: &'a [String],
+    tag: &str,
+    env: Option<&String>,
+) -> Vec<&'a String> {
+    let selected: Vec<&String> = files
+        .iter()
+        .filter(|file_path| {
+            parse_file_in(file_path, env)
+                .map_or(true, |doc| doc.usecase.tags.iter().any(|t| t == tag))
+        })
+        .collect();
+    if selected.is_empty() {
+        eprintln!("タグ '{}' の付いた usecase がありません", tag);
+        process::exit(1);
+    }
+    selected
+}
+
+/// 診断を番号・位置・規則のドキュメントの URL 付きで標準エラーに出す
+fn print_diagnostic(number: usize, diag: &Diagnostic) {
+    eprintln!("  [{}] {}", number, diag);
+    if let Some(location) = &diag.location {
+        eprintln!("      --> {}", location);
+    }
+    for hint in &diag.hints {
+        eprintln!("      = ヒント: {}", hint);
+    }
+    if let Some(url) = diag.doc_url() {
+        eprintln!("      = 詳細: {}", url);
+    }
+}
+
+fn diagnostic_json(diag: &Diagnostic) -> String {
+    let location = match &diag.location {
+        Some(loc) => {
+            let mut fields = vec![format!(r#""file":"{}""#, escape_json_string(&loc.file))];
+            if let Some(line) = loc.line {
+                fields.push(format!(r#""line":{}"#, line));
+            }
+            if let Some(column) = loc.column {
+                fields.push(format!(r#""column":{}"#, column));
+            }
+            format!(r#","location":{{{}}}"#, fields.join(","))
+        }
+        None => String::new(),
+    };
+    let code = diag
+        .code
+        .as_ref()
+        .map(|code| format!(r#""code":"{}","#, escape_json_string(code)))
+        .unwrap_or_default();
+    let pointer = diag
+        .pointer
+        .as_ref()
+        .map(|pointer| format!(r#","pointer":"{}""#, escape_json_string(pointer)))
+        .unwrap_or_default();
+    let hints = if diag.hints.is_empty() {
+        String::new()
+    } else {
+        let hints: Vec<String> = diag
+            .hints
+            .iter()
+            .map(|hint| format!(r#""{}""#, escape_json_string(hint)))
+            .collect();
+        format!(r#","hints":[{}]"#, hints.join(","))
+    };
+    let doc_url = diag
+        .doc_url()
+        .map(|url| format!(r#","doc_url":"{}""#, escape_json_string(&url)))
+        .unwrap_or_default();
+    format!(
+        r#"{{"severity":"{}",{}"rule":"{}","message":"{}"{}{}{}{}}}"#,
+        diag.severity.as_str(),
+        code,
+        escape_json_string(&diag.rule),
+        escape_json_string(&diag.message),
+        location,
+        pointer,
+        hints,
+        doc_url
+    )
+}
+
+fn escape_json_string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for ch in value.chars() {
+        match ch {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+fn cmd_parse(file_path: &str) {
+    let doc = load_document(file_path);
+
+    println!("ドキュメント: {}", doc.usecase.name);
+    println!("バージョン: {}", doc.version);
+    if let Some(summary) = &doc.usecase.summary {
+        println!("サマリー: {}", summary);
+    }
+    println!(
+        "レスポンスマッピング: {} フィールド",
+        doc.usecase.response_mapping.len()
+    );
+    println!("フィルタ: {} 件", doc.usecase.filters.len());
+    println!("トランスフォーム: {} 件", doc.usecase.transforms.len());
+
+    println!("\n--- レスポンスマッピング ---");
+    print_mappings(&doc.usecase.response_mapping, 0);
+}
+
+fn print_mappings(mappings: &[usml_core::ast::ResponseMapping], indent: usize) {
+    let prefix = "  ".repeat(indent);
+    for mapping in mappings {
+        let source_str = mapping.source.as_deref().unwrap_or("-");
+        let type_str = mapping
+            .r#type
+            .as_ref()
+            .map(|t| format!(" [{}]", t))
+            .unwrap_or_default();
+        println!("{}{}: {} {}", prefix, mapping.field, source_str, type_str);
+
+        if let Some(join) = &mapping.join {
+            let alias_str = join
+                .alias
+                .as_ref()
+                .map(|a| format!(" (alias: {})", a))
+                .unwrap_or_default();
+            println!(
+                "{}  └─ JOIN {} ON {}{}",
+                prefix, join.table, join.on, alias_str
+            );
+        }
+
+        if let Some(agg) = &mapping.aggregate {
+            println!("{}  └─ {}", prefix, agg.r#type);
+        }
+
+        if let Some(sub_fields) = &mapping.fields {
+            print_mappings(sub_fields, indent + 2);
+        }
+    }
+}
+
+/// USML ファイルを読み込んでパースする。失敗した場合はエラーを表示して終了する
+/// ファイルをパースし、`env` を指定していれば environments のその環境の import を適用する
+fn parse_file_in(file_path: &str, env: Option<&String>) -> Result<UsmlDocument, UsmlError> {
+    let mut doc = parser::parse_file(file_path)?;
+    if let Some(env) = env {
+        doc.apply_environment(env)?;
+    }
+    Ok(doc)
+}
+
+fn load_document(file_path: &str) -> UsmlDocument {
+    parser::parse_file(file_path).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    })
+}
+
+/// `load_workspace` と同じ。source_usecase の参照は `limits` の段数までたどる
+fn load_workspace_with_limits(dir: &str, limits: &Limits) -> Workspace {
+    let mut workspace = load_workspace(dir);
+    workspace.set_limits(limits.clone());
+    workspace
+}
+
+fn load_workspace(dir: &str) -> Workspace {
+    Workspace::load(Path::new(dir)).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    })
+}
+
+/// `usml visualize` の出力先の指定
+#[derive(Clone, Copy)]
+struct VisualizeOutput<'a> {
+    /// `-o`
+    path: Option<&'a String>,
+    /// `--output-dir`
+    dir: Option<&'a String>,
+    /// `--output-template`
+    template: Option<&'a String>,
+    /// `[visualize]` を読む設定ファイル
+    config: Option<&'a String>,
+    mode: WriteMode,
+}
+
+/// `usml visualize` の出力先パスとマニフェストを置くディレクトリ
+///
+/// -o > USML ファイルの output > テンプレートの順に決める。マニフェストは出力ディレクトリに置く
+fn visualize_output_path(
+    file_path: &str,
+    doc: &UsmlDocument,
+    output: VisualizeOutput,
+) -> (String, String) {
+    if let Some(path) = output.path {
+        return (path.clone(), parent_dir(path));
+    }
+    let settings = load_config(Path::new(&base_dir(file_path)), output.config)
+        .map(|config| config.visualize)
+        .unwrap_or_default();
+    let output_dir = output
+        .dir
+        .or(settings.output_dir.as_ref())
+        .map_or(OUTPUT_DIR, String::as_str);
+    let relative = match (&doc.usecase.output, output.template) {
+        (Some(output_name), None) => {
+            if let Err(reason) = output::check_output_name(output_name) {
+                eprintln!("output '{}' は使えません: {}", output_name, reason);
+                process::exit(1);
+            }
+            output_name.clone()
+        }
+        (_, template) => {
+            let template = template
+                .or(settings.output_template.as_ref())
+                .map_or(output::DEFAULT_TEMPLATE, String::as_str);
+            output::render(template, doc, file_path).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(1);
+            })
+        }
+    };
+    let path = Path::new(output_dir).join(relative);
+    (path.to_string_lossy().to_string(), output_dir.to_string())
+}
+
+fn cmd_visualize(
+    file_path: &str,
+    output: VisualizeOutput,
+    workspace: Option<&String>,
+    archive: bool,
+) {
+    let mut doc = load_document(file_path);
+    if let Some(dir) = workspace {
+        load_workspace(dir).library().expand(&mut doc);
+    }
+
+    let html = visualizer::generate_html(&doc);
+
+    let (output_path, manifest_dir) = visualize_output_path(file_path, &doc, output);
+    if output.path.is_none()
+        && let Some(parent) = Path::new(&output_path).parent()
+        && let Err(e) = fs::create_dir_all(parent)
+    {
+        eprintln!("ディレクトリ作成エラー '{}': {}", parent.display(), e);
+        process::exit(1);
+    }
+
+    if archive {
+        let output_path = match output.path {
+            Some(path) => path.clone(),
+            None => Path::new(&output_path)
+                .with_extension("zip")
+                .to_string_lossy()
+                .to_string(),
+        };
+        write_archive(file_path, &doc, html, &output_path);
+        record_artifact(
+            &manifest_dir,
+            &output_path,
+            "visualize --archive".to_string(),
+            file_path,
+            Some(&doc.usecase.name),
+        );
+        return;
+    }
+
+    if write_generated(&output_path, &html, output.mode) {
+        record_artifact(
+            &manifest_dir,
+            &output_path,
+            "visualize".to_string(),
+            file_path,
+            Some(&doc.usecase.name),
+        );
+        println!("✓ HTML を出力しました: '{}'", output_path);
+    }
+}
+
+/// 複数ファイルの可視化。`-o` は 1 ファイルのときだけ使える
+fn cmd_visualize_files(
+    files: &[&String],
+    output: VisualizeOutput,
+    workspace: Option<&String>,
+    archive: bool,
+    progress: bool,
+) {
+    if output.path.is_some() {
+        eprintln!(
+            "-o（--output）は 1 ファイルのときだけ指定できます。--output-dir か --output-template を使ってください"
+        );
+        process::exit(1);
+    }
+    // 別の usecase が同じ出力先に解決されると上書きになるため、書き出す前に確かめる
+    let mut paths = output::OutputPaths::new();
+    for file_path in files {
+        let doc = load_document(file_path);
+        let (output_path, _) = visualize_output_path(file_path, &doc, output);
+        if let Err(previous) = paths.claim(Path::new(&output_path), file_path) {
+            eprintln!(
+                "'{}' と '{}' の出力先がどちらも '{}' です。usecase.output か --output-template で出力先を分けてください",
+                previous, file_path, output_path
+            );
+            process::exit(1);
+        }
+    }
+    let mut batch = Batch::new(files.len(), progress);
+    for file_path in files {
+        batch.start(file_path);
+        batch.suspend(|| cmd_visualize(file_path, output, workspace, archive));
+        let result = FileResult {
+            path: file_path.to_string(),
+            diagnostics: Vec::new(),
+            elapsed: batch.elapsed(),
+            resolve: None,
+            cached: None,
+        };
+        batch.finish(result);
+    }
+    batch.print_summary();
+}
+
+/// 複数の usecase を 1 つの HTML にまとめる。生成元が複数のためマニフェストには記録しない
+fn cmd_visualize_merged(files: &[&String], output: VisualizeOutput, workspace: Option<&String>) {
+    let library = workspace.map(|dir| load_workspace(dir));
+    let docs: Vec<UsmlDocument> = files
+        .iter()
+        .map(|file_path| {
+            let mut doc = load_document(file_path);
+            if let Some(workspace) = &library {
+                workspace.library().expand(&mut doc);
+            }
+            doc
+        })
+        .collect();
+    let html = visualizer::generate_merged_html(&docs.iter().collect::<Vec<_>>());
+
+    let output_path = match output.path {
+        Some(path) => path.clone(),
+        None => {
+            let settings = load_config(Path::new(&base_dir(files[0])), output.config)
+                .map(|config| config.visualize)
+                .unwrap_or_default();
+            let output_dir = output
+                .dir
+                .or(settings.output_dir.as_ref())
+                .map_or(OUTPUT_DIR, String::as_str);
+            if let Err(e) = fs::create_dir_all(output_dir) {
+                eprintln!("ディレクトリ作成エラー '{}': {}", output_dir, e);
+                process::exit(1);
+            }
+            Path::new(output_dir)
+                .join("usml_merged.html")
+                .to_string_lossy()
+                .to_string()
+        }
+    };
+    if write_generated(&output_path, &html, output.mode) {
+        println!(
+            "✓ HTML を出力しました: '{}' ({} usecase)",
+            output_path,
+            docs.len()
+        );
+    }
+}
+
+fn cmd_export_xlsx(path: &str, tag: Option<&String>, output: Option<&String>) {
+    let workspace = load_export_workspace(path);
+    let docs: Vec<&UsmlDocument> = select_documents(&workspace, tag)
+        .into_iter()
+        .map(|entry| &entry.document)
+        .collect();
+
+    let output_path = export_output_path(output, "mappings.xlsx");
+    if let Err(e) = xlsx::write(&output_path, &docs) {
+        eprintln!("ファイル書き込みエラー '{}': {}", output_path, e);
+        process::exit(1);
+    }
+    record_artifact(
+        &parent_dir(&output_path),
+        &output_path,
+        "export xlsx".to_string(),
+        path,
+        None,
+    );
+    println!(
+        "✓ Excel ブックを出力しました: '{}' ({} usecase)",
+        output_path,
+        docs.len()
+    );
+}
+
+fn cmd_export_dbt(
+    path: &str,
+    tag: Option<&String>,
+    source: Option<&String>,
+    output: Option<&String>,
+    mode: WriteMode,
+) {
+    let workspace = load_export_workspace(path);
+    let entries = select_documents(&workspace, tag);
+    let exposures = export::dbt_exposures(
+        entries
+            .iter()
+            .map(|entry| (entry.path.as_str(), &entry.document)),
+        source.map(String::as_str),
+    );
+
+    let output_path = export_output_path(output, "usml_exposures.yml");
+    if write_generated(&output_path, &exposures.to_yaml(), mode) {
+        record_artifact(
+            &parent_dir(&output_path),
+            &output_path,
+            "export dbt".to_string(),
+            path,
+            None,
+        );
+        println!(
+            "✓ dbt exposures を出力しました: '{}' ({} usecase)",
+            output_path,
+            entries.len()
+        );
+    }
+}
+
+fn cmd_export_access(
+    path: &str,
+    tag: Option<&String>,
+    format: &str,
+    config: Option<&String>,
+    output: Option<&String>,
+    mode: WriteMode,
+) {
+    let workspace = load_export_workspace(path);
+    let entries = select_documents(&workspace, tag);
+    let dir = if Path::new(path).is_dir() {
+        PathBuf::from(path)
+    } else {
+        PathBuf::from(base_dir(path))
+    };
+    let governance = load_config(&dir, config)
+        .map(|config| config.governance)
+        .unwrap_or_default();
+    let matrix = export::access_matrix(
+        entries
+            .iter()
+            .map(|entry| (entry.path.as_str(), &entry.document)),
+        &governance,
+    );
+    let content = match format {
+        "json" => match serde_json::to_string_pretty(&matrix) {
+            Ok(json) => json + "\n",
+            Err(e) => {
+                eprintln!("JSON シリアライズエラー: {}", e);
+                process::exit(1);
+            }
+        },
+        "csv" => access::csv(&matrix),
+        _ => access::html(&matrix),
+    };
+
+    let output_path = export_output_path(output, &format!("usml_access.{}", format));
+    if !write_generated(&output_path, &content, mode) {
+        return;
+    }
+    record_artifact(
+        &parent_dir(&output_path),
+        &output_path,
+        format!("export access --format {}", format),
+        path,
+        None,
+    );
+    let pii = matrix.columns.iter().filter(|c| c.pii).count();
+    println!(
+        "✓ アクセス行列を出力しました: '{}' ({} usecase / {} カラム、うち PII {})",
+        output_path,
+        entries.len(),
+        matrix.columns.len(),
+        pii
+    );
+}
+
+fn cmd_export_dbml(
+    path: &str,
+    tag: Option<&String>,
+    resolve: bool,
+    output: Option<&String>,
+    mode: WriteMode,
+) {
+    let workspace = load_export_workspace(path);
+    let entries = select_documents(&workspace, tag);
+    let mut tables: Vec<DbmlTable> = Vec::new();
+    if resolve {
+        for entry in &entries {
+            let (context, diagnostics) = validator::resolve_imports(
+                &entry.document,
+                &base_dir(&entry.path),
+                &import_resolver(),
+            );
+            for diagnostic in diagnostics {
+                eprintln!("⚠ {}: {}", entry.path, diagnostic.message);
+            }
+            for table in context.dbml_tables {
+                if !tables.iter().any(|t| t.name == table.name) {
+                    tables.push(table);
+                }
+            }
+        }
+    }
+    let dbml = export::usage_dbml(entries.iter().map(|entry| &entry.document), &tables);
+
+    let output_path = export_output_path(output, "usml_usage.dbml");
+    if write_generated(&output_path, &dbml, mode) {
+        record_artifact(
+            &parent_dir(&output_path),
+            &output_path,
+            "export dbml".to_string(),
+            path,
+            None,
+        );
+        println!(
+            "✓ DBML を出力しました: '{}' ({} usecase)",
+            output_path,
+            entries.len()
+        );
+    }
+}
+
+fn cmd_export_datahub(
+    path: &str,
+    tag: Option<&String>,
+    options: export::DatahubOptions,
+    output: Option<&String>,
+    mode: WriteMode,
+) {
+    let workspace = load_export_workspace(path);
+    let entries = select_documents(&workspace, tag);
+    let proposals = export::datahub_proposals(
+        entries.iter().map(|entry| {
+            (
+                entry.path.as_str(),
+                &entry.document,
+                workspace.lineage(&entry.document),
+            )
+        }),
+        &options,
+    );
+
+    let output_path = export_output_path(output, "usml_datahub.json");
+    let json = match serde_json::to_string_pretty(&proposals) {
+        Ok(json) => json,
+        Err(e) => {
+            eprintln!("JSON シリアライズエラー: {}", e);
+            process::exit(1);
+        }
+    };
+    if write_generated(&output_path, &json, mode) {
+        record_artifact(
+            &parent_dir(&output_path),
+            &output_path,
+            "export datahub".to_string(),
+            path,
+            None,
+        );
+        println!(
+            "✓ DataHub の MetadataChangeProposal を出力しました: '{}' ({} usecase, {} 件)",
+            output_path,
+            entries.len(),
+            proposals.len()
+        );
+    }
+}
+
+/// ディレクトリならワークスペースとして、ファイルならそのファイルだけを読み込む
+fn load_export_workspace(path: &str) -> Workspace {
+    if Path::new(path).is_dir() {
+        load_workspace(path)
+    } else {
+        let mut workspace = Workspace::new();
+        workspace.add(path, load_document(path));
+        workspace
+    }
+}
+
+/// `-o` が無ければ ./output/<file_name> に書き出す
+fn export_output_path(output: Option<&String>, file_name: &str) -> String {
+    if let Some(path) = output {
+        return path.clone();
+    }
+    let output_dir = OUTPUT_DIR;
+    if let Err(e) = fs::create_dir_all(output_dir) {
+        eprintln!("ディレクトリ作成エラー '{}': {}", output_dir, e);
+        process::exit(1);
+    }
+    format!("{}/{}", output_dir, file_name)
+}
+
+/// 出力先を指定しない場合のディレクトリ。生成物のマニフェストも置く
+const OUTPUT_DIR: &str = "output";
+
+/// 生成物をマニフェストに記録する。記録に失敗しても生成は成功として警告だけ出す
+fn record_artifact(
+    manifest_dir: &str,
+    path: &str,
+    command: String,
+    source: &str,
+    usecase: Option<&str>,
+) {
+    let result = manifest::Manifest::load(manifest_dir).and_then(|mut manifest| {
+        manifest.record(path, command, source, usecase);
+        manifest
+            .save()
+            .map_err(|e| format!("マニフェストの書き込みエラー '{}': {}", manifest_dir, e))
+    });
+    if let Err(e) = result {
+        eprintln!("⚠ {}", e);
+    }
+}
+
+/// `-o` などで指定した出力先のディレクトリ。マニフェストはここに置く
+fn parent_dir(path: &str) -> String {
+    match Path::new(path).parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_string_lossy().to_string(),
+        _ => ".".to_string(),
+    }
+}
+
+/// 生成物の書き込み方（`--force` / `--check`）
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum WriteMode {
+    /// 生成の印が無い既存ファイルは上書きしない
+    Safe,
+    /// 印が無くても上書きする
+    Force,
+    /// 書き込まずに、出力先が生成結果と一致するかを確かめる
+    Check,
+}
+
+impl WriteMode {
+    fn from_args(args: &ArgMatches) -> Self {
+        if args.get_flag("check") {
+            Self::Check
+        } else if args.get_flag("force") {
+            Self::Force
+        } else {
+            Self::Safe
+        }
+    }
+}
+
+fn force_arg() -> Arg {
+    Arg::new("force")
+        .help("生成の印（@generated by usml）が無い既存のファイルも上書きする")
+        .long("force")
+        .action(ArgAction::SetTrue)
+}
+
+fn cache_dir_arg() -> Arg {
+    Arg::new("cache-dir")
+        .help("検証結果をキャッシュするディレクトリ（ドキュメント・設定・import 先が前回と同じファイルは検証を省く）")
+        .long("cache-dir")
+        .value_name("DIR")
+}
+
+fn report_out_arg() -> Arg {
+    Arg::new("report-out")
+        .help("規則ごとの診断数・処理時間・記述の充足率・複雑さの指標を JSON で書き出すファイル（CI で推移を追う用）")
+        .long("report-out")
+        .value_name("FILE")
+}
+
+fn env_arg() -> Arg {
+    Arg::new("env")
+        .help("environments に宣言した環境（dev・stg・prod など）の import を適用する")
+        .long("env")
+        .value_name("NAME")
+}
+
+fn no_progress_arg() -> Arg {
+    Arg::new("no-progress")
+        .help("進捗バーを表示しない（CI のログ向け）")
+        .long("no-progress")
+        .action(ArgAction::SetTrue)
+}
+
+fn check_arg() -> Arg {
+    Arg::new("check")
+        .help("書き込まずに、出力先が生成結果と一致するかを確かめる（一致しなければ終了コード 1。CI 向け）")
+        .long("check")
+        .action(ArgAction::SetTrue)
+        .conflicts_with("force")
+}
+
+/// 生成物に印を付けて書き込む。書き込んだら true
+///
+/// 出力先に印の無いファイルがあれば（`--force` を除き）上書きせずに終了する。
+/// `--check` では書き込まずに一致を報告して false を返し、一致しなければ終了する。
+/// JSON・CSV などコメントを書けない形式は印を付けられないため、上書きの判定をしない
+fn write_generated(path: &str, content: &str, mode: WriteMode) -> bool {
+    let style = CommentStyle::for_path(path);
+    let content = match style {
+        Some(style) => generated::mark(content, style),
+        None => content.to_string(),
+    };
+    let existing = fs::read(path).ok();
+    if mode == WriteMode::Check {
+        match existing {
+            Some(existing) if existing == content.as_bytes() => {
+                println!("✓ 最新です: '{}'", path);
+                return false;
+            }
+            Some(_) => eprintln!("✗ 生成結果と一致しません: '{}'", path),
+            None => eprintln!("✗ ファイルがありません: '{}'", path),
+        }
+        process::exit(1);
+    }
+    if mode == WriteMode::Safe
+        && style.is_some()
+        && let Some(existing) = &existing
+        && !generated::is_generated(&String::from_utf8_lossy(existing))
+    {
+        eprintln!(
+            "'{}' には生成の印（{}）が無いため上書きしません。手で書いた内容を捨ててよければ --force を指定してください",
+            path,
+            generated::MARKER
+        );
+        process::exit(1);
+    }
+    if let Err(e) = fs::write(path, content) {
+        eprintln!("ファイル書き込みエラー '{}': {}", path, e);
+        process::exit(1);
+    }
+    true
+}
 
-    match matches.subcommand() {
-        Some(("validate", sub_matches)) => {
-            let file_path = sub_matches.get_one::<String>("file").unwrap();
-            let json_output = sub_matches.get_flag("json");
-            cmd_validate(file_path, json_output);
+/// レポートと元の USML・import 先のファイルを zip に書き出す
+fn write_archive(file_path: &str, doc: &UsmlDocument, html: String, output_path: &str) {
+    let stem = Path::new(output_path)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "report".to_string());
+    let mut entries = vec![archive::Entry {
+        name: format!("{}.html", stem),
+        content: html.into_bytes(),
+    }];
+
+    let base = base_dir(file_path);
+    let plan = validator::plan_imports(doc, &base);
+    let imports = plan
+        .openapi
+        .map(|(file, ..)| file)
+        .into_iter()
+        .chain(plan.dbml_files)
+        .filter(|file| !file.contains("://"));
+    let sources = std::iter::once(file_path.to_string()).chain(imports);
+    for source in sources {
+        let path = Path::new(&source);
+        let relative = path.strip_prefix(&base).unwrap_or(path);
+        match fs::read(path) {
+            Ok(content) => entries.push(archive::Entry {
+                name: archive::spec_path(relative),
+                content,
+            }),
+            Err(e) => eprintln!("警告: '{}' を読み込めないため含めません: {}", source, e),
         }
-        Some(("parse", sub_matches)) => {
-            let file_path = sub_matches.get_one::<String>("file").unwrap();
-            cmd_parse(file_path);
+    }
+
+    if let Err(e) = archive::write(output_path, &entries) {
+        eprintln!("ファイル書き込みエラー '{}': {}", output_path, e);
+        process::exit(1);
+    }
+    println!(
+        "✓ アーカイブを出力しました: '{}' ({} ファイル)",
+        output_path,
+        entries.len()
+    );
+}
+
+/// 直し方のある診断がなくなるまで、直し方を 1 つずつ適用して検証し直す
+///
+/// 直しても診断が消えない直し方を繰り返さないよう、同じ直し方は 1 回だけ適用する
+fn cmd_fix(files: &[&String], config: Option<&String>, dry_run: bool) {
+    for file_path in files {
+        let mut source = fs::read_to_string(file_path).unwrap_or_else(|e| {
+            eprintln!("ファイル読み込みエラー '{}': {}", file_path, e);
+            process::exit(1);
+        });
+        let validator = load_validator(Path::new(&base_dir(file_path)), config);
+        let mut applied: Vec<String> = Vec::new();
+        loop {
+            let doc = parser::parse(&source).unwrap_or_else(|e| {
+                eprintln!("{}: {}", file_path, e);
+                process::exit(1);
+            });
+            let diagnostics = validator.validate(&doc);
+            let Some(action) = fix::code_actions(&source, &diagnostics)
+                .into_iter()
+                .find(|action| !applied.contains(&action.title))
+            else {
+                break;
+            };
+            source = action.apply(&source);
+            applied.push(action.title);
         }
-        Some(("visualize", sub_matches)) => {
-            let file_path = sub_matches.get_one::<String>("file").unwrap();
-            let output = sub_matches.get_one::<String>("output");
-            cmd_visualize(file_path, output);
+        if applied.is_empty() {
+            println!("- {}: 直し方のある診断はありません", file_path);
+            continue;
         }
-        _ => {
-            // サブコマンド未指定の場合はヘルプを表示
-            Command::new("usml")
-                .about("Usecase Markup Language - API と DB のデータフローを声明的に定義する")
-                .version("0.1.0")
-                .subcommand(
-                    Command::new("validate").about("USML ファイルのバリデーションを実行する"),
-                )
-                .subcommand(
-                    Command::new("parse").about("USML ファイルをパースしてAST情報を出力する"),
-                )
-                .subcommand(
-                    Command::new("visualize")
-                        .about("USML ドキュメントからHTMLデータフロー図を生成する"),
-                )
-                .print_help()
-                .unwrap();
+        for title in &applied {
+            println!(
+                "{} {}: {}",
+                if dry_run { "-" } else { "✓" },
+                file_path,
+                title
+            );
+        }
+        if !dry_run && let Err(e) = fs::write(file_path, &source) {
+            eprintln!("ファイル書き込みエラー '{}': {}", file_path, e);
+            process::exit(1);
         }
     }
 }
 
-fn cmd_validate(file_path: &str, json_output: bool) {
-    let input = read_file(file_path);
-    let doc = match parser::parse(&input) {
-        Ok(doc) => doc,
-        Err(e) => {
-            if json_output {
-                println!(
-                    r#"{{"file":"{}","status":"error","diagnostics":[{{"severity":"error","rule":"parse","message":"{}"}}]}}"#,
-                    escape_json_string(file_path),
-                    escape_json_string(&e.to_string())
-                );
-            } else {
-                eprintln!("パースエラー: {}", e);
-            }
+/// usml.toml の `[fmt]` とコマンドラインで指定した正規化（`options`）をファイルごとに適用する
+fn cmd_fmt(files: &[&String], config: Option<&String>, options: &FmtConfig, check: bool) {
+    let mut unformatted = 0;
+    for file_path in files {
+        let source = fs::read_to_string(file_path).unwrap_or_else(|e| {
+            eprintln!("ファイル読み込みエラー '{}': {}", file_path, e);
+            process::exit(1);
+        });
+        let dir = base_dir(file_path);
+        let loaded = load_config(Path::new(&dir), config).unwrap_or_default();
+        let fmt = FmtConfig {
+            sort_imports: options.sort_imports || loaded.fmt.sort_imports,
+            order_fields: options.order_fields || loaded.fmt.order_fields,
+            join_type: options.join_type.or(loaded.fmt.join_type),
+            collapse_defaults: options.collapse_defaults || loaded.fmt.collapse_defaults,
+        };
+        if fmt.is_empty() {
+            eprintln!(
+                "正規化が指定されていません（usml.toml の [fmt] か --sort-imports などで指定してください）"
+            );
             process::exit(1);
         }
-    };
+        let doc = parser::parse(&source).unwrap_or_else(|e| {
+            eprintln!("{}: {}", file_path, e);
+            process::exit(1);
+        });
 
-    let errors = validator::validate(&doc);
+        // フィールドの順は import した OpenAPI から取る
+        let openapi = if fmt.order_fields {
+            let openapi = resolve_openapi(&doc, &dir, &loaded.limits);
+            if openapi.is_none() {
+                eprintln!(
+                    "⚠ {}: OpenAPI を解決できないため、フィールドは並べ替えません",
+                    file_path
+                );
+            }
+            openapi
+        } else {
+            None
+        };
 
-    if json_output {
-        let diagnostics: Vec<String> = errors
-            .iter()
-            .map(|err| match err {
-                validator::ValidationError::Rule(rule, msg) => format!(
-                    r#"{{"severity":"error","rule":"{}","message":"{}"}}"#,
-                    escape_json_string(rule),
-                    escape_json_string(msg)
-                ),
-                validator::ValidationError::Warning(rule, msg) => format!(
-                    r#"{{"severity":"warning","rule":"{}","message":"{}"}}"#,
-                    escape_json_string(rule),
-                    escape_json_string(msg)
-                ),
-            })
-            .collect();
-        let has_rule_error = errors
-            .iter()
-            .any(|err| matches!(err, validator::ValidationError::Rule(..)));
-        let status = if has_rule_error { "error" } else { "ok" };
-        println!(
-            r#"{{"file":"{}","status":"{}","diagnostics":[{}]}}"#,
-            escape_json_string(file_path),
-            status,
-            diagnostics.join(",")
-        );
-        if has_rule_error {
+        let formatted = normalize::normalize(&source, &fmt, openapi.as_ref()).unwrap_or_else(|e| {
+            eprintln!("{}: {}", file_path, e);
             process::exit(1);
+        });
+        if formatted == source {
+            println!("- {}: 変更はありません", file_path);
+            continue;
         }
-    } else if errors.is_empty() {
-        println!("✓ バリデーション成功: '{}'", file_path);
-    } else {
-        eprintln!(
-            "✗ バリデーションエラー: '{}' ({} 件)",
-            file_path,
-            errors.len()
-        );
-        for (i, err) in errors.iter().enumerate() {
-            eprintln!("  [{}] {}", i + 1, err);
+        if parser::parse(&formatted).is_err() {
+            eprintln!(
+                "✗ {}: 整形すると USML として読めなくなるため書き込みません",
+                file_path
+            );
+            process::exit(1);
         }
+        if check {
+            println!("✗ {}: 整形が必要です", file_path);
+            unformatted += 1;
+            continue;
+        }
+        if let Err(e) = fs::write(file_path, &formatted) {
+            eprintln!("ファイル書き込みエラー '{}': {}", file_path, e);
+            process::exit(1);
+        }
+        println!("✓ {}: 整形しました", file_path);
+    }
+    if unformatted > 0 {
         process::exit(1);
     }
 }
 
-fn escape_json_string(value: &str) -> String {
-    let mut escaped = String::with_capacity(value.len());
-    for ch in value.chars() {
-        match ch {
-            '"' => escaped.push_str("\\\""),
-            '\\' => escaped.push_str("\\\\"),
-            '\n' => escaped.push_str("\\n"),
-            '\r' => escaped.push_str("\\r"),
-            '\t' => escaped.push_str("\\t"),
-            _ => escaped.push(ch),
+/// import.openapi を解決したレスポンス。import が無い・解決できない場合は None
+fn resolve_openapi(doc: &UsmlDocument, dir: &str, limits: &Limits) -> Option<OpenapiResponse> {
+    validator::resolve_imports_with_limits(doc, dir, &import_resolver(), limits)
+        .0
+        .openapi
+}
+
+/// response_mapping を OpenAPI のプロパティの順か名前順に並べ替える
+fn cmd_sort(files: &[&String], config: Option<&String>, by_openapi: bool, check: bool) {
+    let mut unsorted = 0;
+    for file_path in files {
+        let source = fs::read_to_string(file_path).unwrap_or_else(|e| {
+            eprintln!("ファイル読み込みエラー '{}': {}", file_path, e);
+            process::exit(1);
+        });
+        let doc = parser::parse(&source).unwrap_or_else(|e| {
+            eprintln!("{}: {}", file_path, e);
+            process::exit(1);
+        });
+        let dir = base_dir(file_path);
+        let openapi = if by_openapi {
+            let limits = load_config(Path::new(&dir), config)
+                .map(|config| config.limits)
+                .unwrap_or_default();
+            let Some(openapi) = resolve_openapi(&doc, &dir, &limits) else {
+                eprintln!("✗ {}: import.openapi を解決できません", file_path);
+                process::exit(1);
+            };
+            Some(openapi)
+        } else {
+            None
+        };
+        let order = match &openapi {
+            Some(openapi) => FieldOrder::Openapi(&openapi.field_order),
+            None => FieldOrder::Alphabetical,
+        };
+
+        let sorted = normalize::sort_fields(&source, order).unwrap_or_else(|e| {
+            eprintln!("{}: {}", file_path, e);
+            process::exit(1);
+        });
+        // OpenAPI に無いフィールドは末尾に残るため、対応の抜けとして示す
+        if let Some(openapi) = &openapi {
+            let model = Model::build(&doc);
+            let missing: Vec<&str> = model
+                .fields
+                .iter()
+                .filter(|f| !openapi.field_order.contains(&f.path))
+                .map(|f| f.path.as_str())
+                .collect();
+            if !missing.is_empty() {
+                println!(
+                    "- {}: OpenAPI に無いフィールド（各階層の末尾）: {}",
+                    file_path,
+                    missing.join(", ")
+                );
+            }
+        }
+        if sorted == source {
+            println!("- {}: 並べ替えはありません", file_path);
+            continue;
+        }
+        if check {
+            println!("✗ {}: 並べ替えが必要です", file_path);
+            unsorted += 1;
+            continue;
+        }
+        if let Err(e) = fs::write(file_path, &sorted) {
+            eprintln!("ファイル書き込みエラー '{}': {}", file_path, e);
+            process::exit(1);
         }
+        println!("✓ {}: 並べ替えました", file_path);
+    }
+    if unsorted > 0 {
+        process::exit(1);
     }
-    escaped
 }
 
-fn cmd_parse(file_path: &str) {
-    let input = read_file(file_path);
-    let doc = match parser::parse(&input) {
-        Ok(doc) => doc,
-        Err(e) => {
-            eprintln!("パースエラー: {}", e);
+/// ファイルごとに今の意味の指紋を承認の記録に加える
+fn cmd_sign(files: &[&String], by: &str) {
+    for file_path in files {
+        let doc = load_document(file_path);
+        let mut approvals = approval::Approvals::load(file_path)
+            .unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(1);
+            })
+            .unwrap_or_default();
+        let fingerprint = fingerprint::semantic(&doc);
+        let added = approvals.sign(approval::Approval {
+            by: by.to_string(),
+            usecase: doc.usecase.name,
+            fingerprint: fingerprint.clone(),
+        });
+        if !added {
+            println!(
+                "- '{}' は {} が承認済みです（{}）",
+                file_path, by, fingerprint
+            );
+            continue;
+        }
+        if let Err(e) = approvals.save(file_path) {
+            eprintln!(
+                "ファイル書き込みエラー '{}': {}",
+                approval::Approvals::path(file_path).display(),
+                e
+            );
             process::exit(1);
         }
-    };
+        println!(
+            "✓ 承認を記録しました: '{}'（{}、{}）",
+            approval::Approvals::path(file_path).display(),
+            by,
+            fingerprint
+        );
+    }
+}
 
-    println!("ドキュメント: {}", doc.usecase.name);
-    println!("バージョン: {}", doc.version);
-    if let Some(summary) = &doc.usecase.summary {
-        println!("サマリー: {}", summary);
+/// 承認の記録があるファイルの今の指紋が、承認したときのどれかと一致するかを確かめる
+/// 承認の記録が無いファイルは数えるだけで失敗にしない
+fn cmd_verify(files: &[&String]) {
+    let mut approved = 0;
+    let mut changed = 0;
+    let mut unapproved = 0;
+    for file_path in files {
+        let doc = load_document(file_path);
+        let approvals = match approval::Approvals::load(file_path) {
+            Ok(Some(approvals)) => approvals,
+            Ok(None) => {
+                println!("- {}: 承認の記録がありません", file_path);
+                unapproved += 1;
+                continue;
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        };
+        let fingerprint = fingerprint::semantic(&doc);
+        let approvers = approvals.approvers(&fingerprint);
+        if approvers.is_empty() {
+            let last = approvals
+                .approvals
+                .last()
+                .map_or("-", |approval| approval.by.as_str());
+            eprintln!(
+                "✗ {}: 承認後に usecase の意味が変わっています（最後の承認: {}）。確認して usml sign で承認し直してください",
+                file_path, last
+            );
+            changed += 1;
+        } else {
+            println!("✓ {}: 承認済み（{}）", file_path, approvers.join("・"));
+            approved += 1;
+        }
     }
     println!(
-        "レスポンスマッピング: {} フィールド",
-        doc.usecase.response_mapping.len()
+        "集計: {} ファイル（承認済み {}、承認後の変更 {}、承認の記録なし {}）",
+        files.len(),
+        approved,
+        changed,
+        unapproved
     );
-    println!("フィルタ: {} 件", doc.usecase.filters.len());
-    println!("トランスフォーム: {} 件", doc.usecase.transforms.len());
-
-    println!("\n--- レスポンスマッピング ---");
-    print_mappings(&doc.usecase.response_mapping, 0);
+    if changed > 0 {
+        process::exit(1);
+    }
 }
 
-fn print_mappings(mappings: &[usml_core::ast::ResponseMapping], indent: usize) {
-    let prefix = "  ".repeat(indent);
-    for mapping in mappings {
-        let source_str = mapping.source.as_deref().unwrap_or("-");
-        let type_str = mapping
-            .r#type
-            .as_ref()
-            .map(|t| format!(" [{}]", t))
-            .unwrap_or_default();
-        println!("{}{}: {} {}", prefix, mapping.field, source_str, type_str);
+fn cmd_clean(dir: &str, dry_run: bool) {
+    let mut manifest = manifest::Manifest::load(dir).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+    // 手で消された生成物は記録からも外す
+    manifest.forget_missing();
 
-        if let Some(join) = &mapping.join {
-            let alias_str = join
-                .alias
-                .as_ref()
-                .map(|a| format!(" (alias: {})", a))
-                .unwrap_or_default();
-            println!(
-                "{}  └─ JOIN {} ON {}{}",
-                prefix, join.table, join.on, alias_str
+    let mut removed = Vec::new();
+    let mut kept = 0;
+    for index in 0..manifest.artifacts.len() {
+        let Some(reason) = manifest.stale_reason(index) else {
+            continue;
+        };
+        let path = manifest
+            .artifact_path(&manifest.artifacts[index].path)
+            .unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(1);
+            });
+        // 印を付けられる形式で印が消えているものは手で編集されたとみなして残す
+        let edited = CommentStyle::for_path(&path.to_string_lossy()).is_some()
+            && fs::read_to_string(&path).is_ok_and(|content| !generated::is_generated(&content));
+        if edited {
+            eprintln!(
+                "⚠ '{}' は古くなっていますが、@generated の印が無いため削除しません（{}）",
+                path.display(),
+                reason
             );
+            kept += 1;
+            continue;
+        }
+        println!("削除: {}（{}）", path.display(), reason);
+        if !dry_run {
+            if let Err(e) = fs::remove_file(&path) {
+                eprintln!("ファイル削除エラー '{}': {}", path.display(), e);
+                process::exit(1);
+            }
+            remove_empty_dirs(&path, Path::new(dir));
         }
+        removed.push(index);
+    }
 
-        if let Some(agg) = &mapping.aggregate {
-            println!("{}  └─ {}", prefix, agg.r#type);
+    if dry_run {
+        println!(
+            "
+{} 件の生成物が古くなっています（--dry-run のため削除していません）",
+            removed.len()
+        );
+        return;
+    }
+    let mut index = 0;
+    manifest.artifacts.retain(|_| {
+        index += 1;
+        !removed.contains(&(index - 1))
+    });
+    if let Err(e) = manifest.save() {
+        eprintln!("マニフェストの書き込みエラー '{}': {}", dir, e);
+        process::exit(1);
+    }
+    if removed.is_empty() && kept == 0 {
+        println!("✓ 古い生成物はありません");
+    } else {
+        println!(
+            "
+✓ {} 件の古い生成物を削除しました",
+            removed.len()
+        );
+    }
+}
+
+/// 削除したファイルの親ディレクトリを、空であれば `root` の手前まで削除する
+fn remove_empty_dirs(path: &Path, root: &Path) {
+    // `..` で root の外に出る生成物の親は消さない
+    if path.strip_prefix(root).map_or(true, |relative| {
+        relative.components().any(|c| c == Component::ParentDir)
+    }) {
+        return;
+    }
+    let mut dir = path.parent();
+    while let Some(current) = dir {
+        if current == root || !current.starts_with(root) || fs::remove_dir(current).is_err() {
+            break;
+        }
+        dir = current.parent();
+    }
+}
+
+/// `usml rules` の出力形式
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum RulesFormat {
+    Text,
+    Json,
+    /// `--doc`: 規則 1 つのドキュメント
+    Doc,
+    /// `--markdown`: 全規則のドキュメント
+    Markdown,
+}
+
+fn cmd_rules(explain: Option<&str>, format: RulesFormat, config: Option<&String>) {
+    let validator = load_validator(Path::new("."), config);
+    let all: Vec<&Rule> = validator.rules().collect();
+    if format == RulesFormat::Markdown {
+        print!("{}", rule_docs::markdown(&all));
+        return;
+    }
+    let rule = explain.map(|code| {
+        all.iter()
+            .copied()
+            .find(|rule| rule.code.eq_ignore_ascii_case(code))
+            .unwrap_or_else(|| {
+                eprintln!("規則 '{}' は存在しません", code);
+                process::exit(1);
+            })
+    });
+
+    if format == RulesFormat::Json {
+        let json = match rule {
+            Some(rule) => serde_json::to_string_pretty(rule),
+            None => serde_json::to_string_pretty(&all),
+        };
+        match json {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("JSON 出力エラー: {}", e);
+                process::exit(1);
+            }
         }
+        return;
+    }
 
-        if let Some(sub_fields) = &mapping.fields {
-            print_mappings(sub_fields, indent + 2);
+    match rule {
+        Some(rule) if format == RulesFormat::Doc => {
+            print!("{}", rule_docs::section(rule));
+            if let Some(url) = rule.doc_url() {
+                println!("\n詳細: {}", url);
+            }
+        }
+        Some(rule) => {
+            println!("{}: {}", rule.code, rule.summary);
+            println!();
+            println!("  名前:     {}", rule.name);
+            println!("  分類:     {}", rule.category.as_str());
+            println!("  重大度:   {}", rule.severity.as_str());
+            println!();
+            println!("{}", rule.explanation);
+            if let Some(url) = rule.doc_url() {
+                println!();
+                println!("詳細: {}", url);
+            }
+        }
+        None => {
+            let width = all.iter().map(|rule| rule.code.len()).max().unwrap_or(0);
+            for rule in &all {
+                println!(
+                    "{:<width$}  {:<7}  {:<9}  {:<26}  {}",
+                    rule.code,
+                    rule.severity.as_str(),
+                    rule.category.as_str(),
+                    rule.name,
+                    rule.summary
+                );
+            }
+            println!(
+                "\n`usml rules --explain <CODE>` で規則の詳しい説明、`--doc <CODE>` で違反例を表示します"
+            );
         }
     }
 }
 
-fn read_file(path: &str) -> String {
-    fs::read_to_string(path).unwrap_or_else(|e| {
-        eprintln!("ファイル読み込みエラー '{}': {}", path, e);
+fn cmd_examples_list() {
+    let width = examples::EXAMPLES
+        .iter()
+        .map(|e| e.name.len())
+        .max()
+        .unwrap_or(0);
+    for example in examples::EXAMPLES {
+        println!("{:width$}  {}", example.name, example.description);
+    }
+    println!("\n`usml examples init <name>` でカレントディレクトリに展開できます");
+}
+
+/// サンプル名からサンプルを探す。見つからない場合は一覧を表示して終了する
+fn find_example(name: &str) -> &'static examples::Example {
+    examples::find(name).unwrap_or_else(|| {
+        eprintln!("サンプル '{}' は存在しません", name);
+        let names: Vec<&str> = examples::EXAMPLES.iter().map(|e| e.name).collect();
+        eprintln!("利用できるサンプル: {}", names.join(", "));
         process::exit(1);
     })
 }
 
-fn cmd_visualize(file_path: &str, output: Option<&String>) {
-    let input = read_file(file_path);
-    let doc = match parser::parse(&input) {
-        Ok(doc) => doc,
-        Err(e) => {
-            eprintln!("パースエラー: {}", e);
-            process::exit(1);
-        }
-    };
+fn cmd_examples_show(name: &str) {
+    print!("{}", find_example(name).source);
+}
 
-    let html = visualizer::generate_html(&doc);
+fn cmd_examples_init(name: &str, dir: &str, force: bool) {
+    let example = find_example(name);
+    let files = [
+        (example.file, example.source),
+        examples::OPENAPI,
+        examples::DBML,
+    ];
 
-    // 出力先パスを決定
-    let output_path = if let Some(path) = output {
-        // -o オプションが指定されている場合はそれを優先
-        path.clone()
-    } else if let Some(output_name) = &doc.usecase.output {
-        // USMLファイル内のoutputパラメータが指定されている場合
-        let output_dir = "output";
-        if let Err(e) = fs::create_dir_all(output_dir) {
-            eprintln!("ディレクトリ作成エラー '{}': {}", output_dir, e);
+    if !force {
+        let existing: Vec<String> = files
+            .iter()
+            .map(|(file, _)| Path::new(dir).join(file))
+            .filter(|path| path.exists())
+            .map(|path| path.to_string_lossy().to_string())
+            .collect();
+        if !existing.is_empty() {
+            eprintln!("ファイルが既に存在します: {}", existing.join(", "));
+            eprintln!("上書きする場合は --force を指定してください");
             process::exit(1);
         }
-        format!("{}/{}", output_dir, output_name)
-    } else {
-        // デフォルト: ./output/<usecase-name>.html
-        let output_dir = "output";
-        if let Err(e) = fs::create_dir_all(output_dir) {
-            eprintln!("ディレクトリ作成エラー '{}': {}", output_dir, e);
+    }
+
+    if let Err(e) = fs::create_dir_all(dir) {
+        eprintln!("ディレクトリ作成エラー '{}': {}", dir, e);
+        process::exit(1);
+    }
+    for (file, contents) in files {
+        let path = Path::new(dir).join(file);
+        if let Err(e) = fs::write(&path, contents) {
+            eprintln!("ファイル書き込みエラー '{}': {}", path.display(), e);
             process::exit(1);
         }
+        println!("✓ 書き出しました: '{}'", path.display());
+    }
+    println!(
+        "\n次のコマンドで検証できます: usml validate --resolve {}",
+        Path::new(dir).join(example.file).display()
+    );
+}
 
-        // ユースケース名からファイル名を生成（スペースや特殊文字を置換）
-        let safe_name = doc
-            .usecase
-            .name
-            .chars()
-            .map(|c| {
-                if c.is_alphanumeric() || c == '-' || c == '_' {
-                    c
-                } else {
-                    '-'
-                }
-            })
-            .collect::<String>();
-        format!("{}/{}.html", output_dir, safe_name)
-    };
+/// `usml add` のサブコマンド（引数は対象の USML ファイルだけ）
+fn add_command(name: &'static str, about: &'static str) -> Command {
+    Command::new(name).about(about).arg(
+        Arg::new("file")
+            .help("追加先の USML ファイル")
+            .required(true)
+            .index(1),
+    )
+}
 
-    if let Err(e) = fs::write(&output_path, html) {
-        eprintln!("ファイル書き込みエラー '{}': {}", output_path, e);
+fn cmd_add(kind: &str, file_path: &str) {
+    if !std::io::stdin().is_terminal() {
+        eprintln!("usml add は対話モードのため端末から実行してください");
         process::exit(1);
     }
-    println!("✓ HTML を出力しました: '{}'", output_path);
+    let source = fs::read_to_string(file_path).unwrap_or_else(|e| {
+        eprintln!("ファイル読み込みエラー '{}': {}", file_path, e);
+        process::exit(1);
+    });
+    let doc = parser::parse(&source).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+    let (context, diagnostics) =
+        validator::resolve_imports(&doc, &base_dir(file_path), &import_resolver());
+    for diagnostic in diagnostics {
+        eprintln!("⚠ {}", diagnostic.message);
+    }
+
+    let prompter = add::Prompter::new(&doc, &context);
+    let addition = match kind {
+        "field" => prompter.field(),
+        "filter" => prompter.filter(),
+        _ => prompter.transform(),
+    }
+    .unwrap_or_else(|e| {
+        eprintln!("入力エラー: {}", e);
+        process::exit(1);
+    });
+
+    let mut editor = Editor::new(source).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+    if let Err(e) = addition.apply(&mut editor) {
+        eprintln!("編集エラー: {}", e);
+        process::exit(1);
+    }
+    if let Err(e) = parser::parse(editor.as_str()) {
+        eprintln!("追加後のドキュメントを読めません: {}", e);
+        process::exit(1);
+    }
+    if let Err(e) = fs::write(file_path, editor.as_str()) {
+        eprintln!("ファイル書き込みエラー '{}': {}", file_path, e);
+        process::exit(1);
+    }
+    println!("✓ 追加しました: '{}'", file_path);
+    println!(
+        "\n次のコマンドで検証できます: usml validate --resolve {}",
+        file_path
+    );
 }
@@ -0,0 +1,338 @@
+//! 生成物の記録（`<出力ディレクトリ>/.usml-manifest.json`）と、`usml clean` で消す古い生成物の判定
+//!
+//! パスはマニフェストのあるディレクトリからの相対パスで記録し、どのディレクトリで実行しても同じ生成物を指す。
+//! 後から記録したものほど新しく、同じパスを記録し直すと末尾に移る。
+//! 生成物のパスはマニフェストのあるディレクトリの中だけを指せる（`usml clean` が外のファイルを消さないように）
+
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use usml_core::parser;
+
+/// 出力ディレクトリに置くマニフェストのファイル名
+pub const FILE_NAME: &str = ".usml-manifest.json";
+
+/// マニフェストの形式の版。0（`version` 無し）はパスを実行したディレクトリからの相対パスで記録していた
+const VERSION: u32 = 1;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    #[serde(default)]
+    pub version: u32,
+    pub artifacts: Vec<Artifact>,
+    /// マニフェストのあるディレクトリ
+    #[serde(skip)]
+    dir: PathBuf,
+}
+
+/// 1 つの生成物
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Artifact {
+    /// 生成物のパス（マニフェストのあるディレクトリから）
+    pub path: String,
+    /// 生成したコマンド（`visualize`、`generate --backend kotlin`、`export dbml` など）
+    pub command: String,
+    /// 生成元の USML ファイル、またはディレクトリ（マニフェストのあるディレクトリから）
+    pub source: String,
+    /// 生成元の usecase 名（ファイル 1 つから生成したものだけ）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usecase: Option<String>,
+}
+
+impl Manifest {
+    pub fn path(dir: &str) -> PathBuf {
+        Path::new(dir).join(FILE_NAME)
+    }
+
+    /// マニフェストが無ければ空のものを返す。古い形式のパスはマニフェストのあるディレクトリからのパスに直す
+    pub fn load(dir: &str) -> Result<Self, String> {
+        let path = Self::path(dir);
+        let mut manifest: Self = match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)
+                .map_err(|e| format!("マニフェストの読み込みエラー '{}': {}", path.display(), e))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Self {
+                version: VERSION,
+                ..Self::default()
+            },
+            Err(e) => {
+                return Err(format!(
+                    "マニフェストの読み込みエラー '{}': {}",
+                    path.display(),
+                    e
+                ));
+            }
+        };
+        manifest.dir = PathBuf::from(dir);
+        if manifest.version < VERSION {
+            for artifact in &mut manifest.artifacts {
+                artifact.path = relative(&manifest.dir, &artifact.path);
+                artifact.source = relative(&manifest.dir, &artifact.source);
+            }
+            manifest.version = VERSION;
+        }
+        if let Some(artifact) = manifest
+            .artifacts
+            .iter()
+            .find(|a| manifest.artifact_path(&a.path).is_err())
+        {
+            return Err(format!(
+                "マニフェストの読み込みエラー '{}': {}",
+                path.display(),
+                manifest.artifact_path(&artifact.path).unwrap_err()
+            ));
+        }
+        Ok(manifest)
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(Self::path(&self.dir.to_string_lossy()), json + "\n")
+    }
+
+    /// 生成物を記録する。`path`・`source` は実行したディレクトリからのパス
+    ///
+    /// 同じパスの記録を置き換え、末尾（最新）に置く
+    pub fn record(&mut self, path: &str, command: String, source: &str, usecase: Option<&str>) {
+        let path = relative(&self.dir, path);
+        self.artifacts.retain(|a| a.path != path);
+        self.artifacts.push(Artifact {
+            path,
+            command,
+            source: relative(&self.dir, source),
+            usecase: usecase.map(str::to_string),
+        });
+    }
+
+    /// 記録したパスを実行したディレクトリからのパスにする
+    pub fn resolve(&self, path: &str) -> PathBuf {
+        self.dir.join(path)
+    }
+
+    /// 記録した生成物のパスを実行したディレクトリからのパスにする
+    ///
+    /// 絶対パス・`..` を含むパス・（シンボリックリンクをたどって）マニフェストのあるディレクトリの外を指すパスはエラー
+    pub fn artifact_path(&self, path: &str) -> Result<PathBuf, String> {
+        let outside = || {
+            format!(
+                "生成物のパス '{}' は '{}' の外を指しています",
+                path,
+                self.dir.display()
+            )
+        };
+        if !Path::new(path)
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+        {
+            return Err(outside());
+        }
+        let resolved = self.dir.join(path);
+        if let (Ok(dir), Ok(target)) = (fs::canonicalize(&self.dir), fs::canonicalize(&resolved))
+            && !target.starts_with(dir)
+        {
+            return Err(outside());
+        }
+        Ok(resolved)
+    }
+
+    /// 手で消された生成物を記録から外す
+    pub fn forget_missing(&mut self) {
+        let artifacts = std::mem::take(&mut self.artifacts);
+        self.artifacts = artifacts
+            .into_iter()
+            .filter(|a| self.artifact_path(&a.path).is_ok_and(|p| p.exists()))
+            .collect();
+    }
+
+    /// `index` の生成物が古くなっていれば理由を返す
+    ///
+    /// - 生成元のファイル・ディレクトリが無くなった
+    /// - 同じコマンド・生成元から後で別のパスに生成し、そちらが生成元の今の usecase 名と一致する
+    ///   （usecase 名の変更や出力先のテンプレートの変更で置き換わった）
+    pub fn stale_reason(&self, index: usize) -> Option<String> {
+        let artifact = &self.artifacts[index];
+        let source = self.resolve(&artifact.source);
+        if !source.exists() {
+            return Some(format!("生成元 '{}' がありません", source.display()));
+        }
+        let current = if source.is_file() {
+            // 読めない・パースできない場合は判断せずに残す
+            let content = fs::read_to_string(&source).ok()?;
+            Some(parser::parse(&content).ok()?.usecase.name)
+        } else {
+            None
+        };
+        let newer = self.artifacts[index + 1..].iter().find(|a| {
+            a.command == artifact.command
+                && a.source == artifact.source
+                && a.path != artifact.path
+                && a.usecase == current
+        })?;
+        Some(match (&artifact.usecase, &current) {
+            (Some(old), Some(new)) if old != new => format!(
+                "usecase 名が '{}' から '{}' に変わり、'{}' に生成し直されています",
+                old,
+                new,
+                self.resolve(&newer.path).display()
+            ),
+            _ => format!(
+                "'{}' に生成し直されています",
+                self.resolve(&newer.path).display()
+            ),
+        })
+    }
+}
+
+/// 実行したディレクトリからのパス `path` を、`dir` からの相対パスにする
+fn relative(dir: &Path, path: &str) -> String {
+    let (Ok(dir), Ok(target)) = (std::path::absolute(dir), std::path::absolute(path)) else {
+        return path.to_string();
+    };
+    let (dir, target) = (normalize(&dir), normalize(&target));
+    let common = dir
+        .components()
+        .zip(target.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut relative = PathBuf::new();
+    for _ in dir.components().skip(common) {
+        relative.push("..");
+    }
+    for component in target.components().skip(common) {
+        relative.push(component);
+    }
+    relative.to_string_lossy().to_string()
+}
+
+/// `.`・`..` を字句的に畳む
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::CurDir => {}
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 一時ディレクトリに `out/` を作り、その中のマニフェストを読む
+    fn setup(name: &str, manifest: Option<&str>) -> (PathBuf, Result<Manifest, String>) {
+        let root = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("out")).unwrap();
+        if let Some(json) = manifest {
+            fs::write(root.join("out").join(FILE_NAME), json).unwrap();
+        }
+        let dir = root.join("out").to_string_lossy().to_string();
+        (root, Manifest::load(&dir))
+    }
+
+    fn usecase(root: &Path, name: &str) -> String {
+        let path = root.join("users.usml.yaml");
+        fs::write(
+            &path,
+            format!(
+                "version: \"0.1\"\nimport: {{}}\nusecase:\n  name: {}\n  response_mapping: []\n",
+                name
+            ),
+        )
+        .unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn test_stale_after_usecase_rename() {
+        let (root, manifest) = setup("usml_test_manifest_stale", None);
+        let mut manifest = manifest.unwrap();
+        let source = usecase(&root, "UserList");
+        let old = root.join("out/user-list.html");
+        fs::write(&old, "").unwrap();
+        manifest.record(
+            &old.to_string_lossy(),
+            "visualize".to_string(),
+            &source,
+            Some("UserList"),
+        );
+        assert_eq!(manifest.artifacts[0].path, "user-list.html");
+        assert_eq!(manifest.stale_reason(0), None);
+
+        let source = usecase(&root, "MemberList");
+        let new = root.join("out/member-list.html");
+        fs::write(&new, "").unwrap();
+        manifest.record(
+            &new.to_string_lossy(),
+            "visualize".to_string(),
+            &source,
+            Some("MemberList"),
+        );
+        let reason = manifest.stale_reason(0).unwrap();
+        assert!(
+            reason.contains("'UserList' から 'MemberList' に変わり"),
+            "{}",
+            reason
+        );
+        assert_eq!(manifest.stale_reason(1), None);
+
+        // 手で消した生成物は記録から外す
+        fs::remove_file(&old).unwrap();
+        manifest.forget_missing();
+        let paths: Vec<&str> = manifest.artifacts.iter().map(|a| a.path.as_str()).collect();
+        assert_eq!(paths, ["member-list.html"]);
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_rejects_paths_outside_the_manifest_dir() {
+        let artifact = |path: &str| {
+            format!(
+                r#"{{"version":1,"artifacts":[{{"path":{},"command":"visualize","source":"../users.usml.yaml"}}]}}"#,
+                serde_json::to_string(path).unwrap()
+            )
+        };
+        let absolute = std::env::temp_dir().join("victim.txt");
+        for path in [
+            "../victim.txt",
+            "nested/../../victim.txt",
+            &absolute.to_string_lossy(),
+        ] {
+            let (_, manifest) = setup("usml_test_manifest_escape", Some(&artifact(path)));
+            let error = manifest.unwrap_err();
+            assert!(error.contains("の外を指しています"), "{}: {}", path, error);
+        }
+
+        // 中を指すパスは読める
+        let (root, manifest) = setup(
+            "usml_test_manifest_escape",
+            Some(&artifact("./nested/users.html")),
+        );
+        assert_eq!(
+            manifest
+                .unwrap()
+                .artifact_path("./nested/users.html")
+                .unwrap(),
+            root.join("out").join("./nested/users.html")
+        );
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_rejects_symlinks_outside_the_manifest_dir() {
+        let (root, manifest) = setup("usml_test_manifest_symlink", None);
+        let manifest = manifest.unwrap();
+        fs::write(root.join("victim.txt"), "").unwrap();
+        std::os::unix::fs::symlink(&root, root.join("out/link")).unwrap();
+        assert!(manifest.artifact_path("link/victim.txt").is_err());
+        let _ = fs::remove_dir_all(&root);
+    }
+}
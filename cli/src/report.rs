@@ -0,0 +1,228 @@
+//! `--report-out` で書き出す実行レポート（CI で仕様の品質の推移を追うための JSON）
+//!
+//! 形を変えるときは [`SCHEMA_VERSION`] を上げる。項目の追加だけなら上げない
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::Serialize;
+use usml_core::ast::UsmlDocument;
+use usml_core::diagnostic::{Diagnostic, Severity};
+use usml_core::stats::{FileStats, WorkspaceStats};
+
+use crate::batch::Batch;
+
+/// レポートの形の版
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub schema_version: u32,
+    pub usml_version: &'static str,
+    /// 実行したコマンド（`validate` / `check`）
+    pub command: &'static str,
+    /// `ok` / `error`
+    pub status: &'static str,
+    pub totals: Totals,
+    /// 規則ごとの診断の数（コード順。コードの無い診断は規則名）
+    pub rules: Vec<RuleCount>,
+    pub timings: Timings,
+    pub coverage: Coverage,
+    pub complexity: Complexity,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Totals {
+    pub files: usize,
+    /// エラー・警告のあったファイルの数
+    pub failed: usize,
+    pub errors: usize,
+    pub warnings: usize,
+    pub infos: usize,
+    pub hints: usize,
+    /// キャッシュの診断を使ったファイルの数（`--cache-dir` のときだけ）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cached: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RuleCount {
+    /// 規則のコード。パースエラーなど規則に属さない診断では規則名
+    pub code: String,
+    pub rule: String,
+    pub errors: usize,
+    pub warnings: usize,
+    pub infos: usize,
+    pub hints: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Timings {
+    pub elapsed_ms: u128,
+    /// import の解決にかかった時間の合計（`--resolve` のときだけ）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolve_ms: Option<u128>,
+    pub files: Vec<FileTiming>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileTiming {
+    pub file: String,
+    pub elapsed_ms: u128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolve_ms: Option<u128>,
+}
+
+/// 条件を満たすファイル・usecase の割合（%、小数 1 桁）。対象が無ければ null
+#[derive(Debug, Serialize)]
+pub struct Coverage {
+    /// エラーの無いファイル
+    pub clean_files: Option<f64>,
+    /// owner のある usecase
+    pub owner: Option<f64>,
+    /// summary のある usecase
+    pub summary: Option<f64>,
+    /// tags のある usecase
+    pub tags: Option<f64>,
+}
+
+/// 読み込めた usecase の規模・複雑さ（`usml stats --json` と同じ指標）
+#[derive(Debug, Serialize)]
+pub struct Complexity {
+    pub usecases: usize,
+    pub fields: usize,
+    /// usecase ごとの結合の段数の最大値のうち最大のもの
+    pub max_join_depth: usize,
+    pub join_depths: BTreeMap<usize, usize>,
+    pub transform_usage: BTreeMap<String, usize>,
+    pub table_fan_in: BTreeMap<String, usize>,
+    pub files: Vec<FileStats>,
+}
+
+/// 検証しながら読み込めたドキュメントの指標を集める
+#[derive(Default)]
+pub struct Collector {
+    stats: WorkspaceStats,
+    owner: usize,
+    summary: usize,
+    tags: usize,
+}
+
+impl Collector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, file: &str, doc: &UsmlDocument, diagnostics: &[Diagnostic]) {
+        self.stats.add(file, doc, diagnostics);
+        let usecase = &doc.usecase;
+        self.owner += usize::from(usecase.owner.is_some());
+        self.summary += usize::from(usecase.summary.is_some());
+        self.tags += usize::from(!usecase.tags.is_empty());
+    }
+
+    pub fn finish(self, command: &'static str, batch: &Batch) -> Report {
+        let totals = batch.totals();
+        let mut rules: BTreeMap<String, RuleCount> = BTreeMap::new();
+        for diag in batch.results.iter().flat_map(|result| &result.diagnostics) {
+            let code = diag.code.clone().unwrap_or_else(|| diag.rule.clone());
+            let count = rules.entry(code.clone()).or_insert_with(|| RuleCount {
+                code,
+                rule: diag.rule.clone(),
+                errors: 0,
+                warnings: 0,
+                infos: 0,
+                hints: 0,
+            });
+            match diag.severity {
+                Severity::Error => count.errors += 1,
+                Severity::Warning => count.warnings += 1,
+                Severity::Info => count.infos += 1,
+                Severity::Hint => count.hints += 1,
+            }
+        }
+
+        let resolving = batch.results.iter().any(|r| r.resolve.is_some());
+        let timings = Timings {
+            elapsed_ms: totals.elapsed.as_millis(),
+            resolve_ms: resolving.then(|| {
+                batch
+                    .results
+                    .iter()
+                    .filter_map(|r| r.resolve)
+                    .map(|time| time.as_millis())
+                    .sum()
+            }),
+            files: batch
+                .results
+                .iter()
+                .map(|r| FileTiming {
+                    file: r.path.clone(),
+                    elapsed_ms: r.elapsed.as_millis(),
+                    resolve_ms: r.resolve.map(|time| time.as_millis()),
+                })
+                .collect(),
+        };
+
+        let usecases = self.stats.files.len();
+        let clean = batch.results.iter().filter(|r| !r.has_error()).count();
+        let coverage = Coverage {
+            clean_files: percent(clean, totals.files),
+            owner: percent(self.owner, usecases),
+            summary: percent(self.summary, usecases),
+            tags: percent(self.tags, usecases),
+        };
+
+        let stats = self.stats;
+        let complexity = Complexity {
+            usecases,
+            fields: stats.fields,
+            max_join_depth: stats
+                .files
+                .iter()
+                .map(|f| f.max_join_depth)
+                .max()
+                .unwrap_or(0),
+            join_depths: stats.join_depths,
+            transform_usage: stats.transform_usage,
+            table_fan_in: stats.table_fan_in,
+            files: stats.files,
+        };
+
+        Report {
+            schema_version: SCHEMA_VERSION,
+            usml_version: env!("CARGO_PKG_VERSION"),
+            command,
+            status: if batch.has_error() { "error" } else { "ok" },
+            totals: Totals {
+                files: totals.files,
+                failed: totals.failed,
+                errors: totals.errors,
+                warnings: totals.warnings,
+                infos: totals.infos,
+                hints: totals.hints,
+                cached: totals.cached,
+            },
+            rules: rules.into_values().collect(),
+            timings,
+            coverage,
+            complexity,
+        }
+    }
+}
+
+impl Report {
+    pub fn write(&self, path: &str) -> io::Result<()> {
+        if let Some(parent) = Path::new(path).parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, json + "\n")
+    }
+}
+
+fn percent(count: usize, total: usize) -> Option<f64> {
+    (total > 0).then(|| (count as f64 * 1000.0 / total as f64).round() / 10.0)
+}
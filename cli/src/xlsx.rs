@@ -0,0 +1,105 @@
+//! `usml export xlsx` で書き出すワークブック
+//!
+//! usecase ごとのマッピング表のシートと、テーブル・カラムの利用状況をまとめたシートを作る
+
+use rust_xlsxwriter::{Format, Workbook, Worksheet, XlsxError};
+use usml_core::ast::UsmlDocument;
+use usml_core::export;
+
+/// 利用状況のシートの名前（先頭に置く）
+const SUMMARY_SHEET: &str = "テーブル利用状況";
+
+/// Excel のシート名の長さの上限
+const MAX_SHEET_NAME: usize = 31;
+
+pub fn write(path: &str, docs: &[&UsmlDocument]) -> Result<(), XlsxError> {
+    let mut workbook = Workbook::new();
+    let header = Format::new().set_bold().set_background_color("#E0E7FF");
+    let wrap = Format::new().set_text_wrap();
+
+    let summary = workbook.add_worksheet().set_name(SUMMARY_SHEET)?;
+    write_table(
+        summary,
+        export::USAGE_HEADER,
+        &export::usage_rows(docs.iter().copied()),
+        &header,
+        &wrap,
+    )?;
+
+    let mut names = vec![SUMMARY_SHEET.to_string()];
+    for doc in docs {
+        let name = sheet_name(&doc.usecase.name, &names);
+        let sheet = workbook.add_worksheet().set_name(&name)?;
+        write_table(
+            sheet,
+            export::MAPPING_HEADER,
+            &export::mapping_rows(doc),
+            &header,
+            &wrap,
+        )?;
+        names.push(name);
+    }
+    workbook.save(path)
+}
+
+/// 見出し行を固定し、オートフィルタと列幅を設定して表を書く
+fn write_table(
+    sheet: &mut Worksheet,
+    header: &[&str],
+    rows: &[Vec<String>],
+    header_format: &Format,
+    cell_format: &Format,
+) -> Result<(), XlsxError> {
+    for (col, title) in header.iter().enumerate() {
+        sheet.write_string_with_format(0, col as u16, *title, header_format)?;
+    }
+    let mut widths: Vec<usize> = header.iter().map(|h| display_width(h)).collect();
+    for (row, cells) in rows.iter().enumerate() {
+        for (col, cell) in cells.iter().enumerate() {
+            sheet.write_string_with_format(row as u32 + 1, col as u16, cell, cell_format)?;
+            let width = cell.lines().map(display_width).max().unwrap_or(0);
+            widths[col] = widths[col].max(width);
+        }
+    }
+    for (col, width) in widths.into_iter().enumerate() {
+        sheet.set_column_width(col as u16, width.clamp(8, 60) as f64 + 2.0)?;
+    }
+    sheet.set_freeze_panes(1, 0)?;
+    sheet.autofilter(0, 0, rows.len() as u32, header.len() as u16 - 1)?;
+    Ok(())
+}
+
+/// 全角文字を 2 として数えた表示幅
+fn display_width(text: &str) -> usize {
+    text.chars().map(|c| if c.is_ascii() { 1 } else { 2 }).sum()
+}
+
+/// Excel で使えない文字を置き換え、31 文字に切り詰め、既存のシート名と重ならない名前にする
+fn sheet_name(usecase: &str, used: &[String]) -> String {
+    let base: String = usecase
+        .chars()
+        .map(|c| match c {
+            '[' | ']' | ':' | '*' | '?' | '/' | '\\' => '_',
+            c => c,
+        })
+        .collect();
+    let base = base.trim_matches('\'');
+    let base = if base.is_empty() { "usecase" } else { base };
+    let mut n = 1;
+    loop {
+        let suffix = if n == 1 {
+            String::new()
+        } else {
+            format!(" ({})", n)
+        };
+        let name: String = base
+            .chars()
+            .take(MAX_SHEET_NAME - suffix.chars().count())
+            .chain(suffix.chars())
+            .collect();
+        if !used.iter().any(|u| u.eq_ignore_ascii_case(&name)) {
+            return name;
+        }
+        n += 1;
+    }
+}
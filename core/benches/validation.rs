@@ -0,0 +1,152 @@
+//! 大きな合成ドキュメント（1,000 フィールド・100 テーブル）での parse / validate / visualize の計測
+//!
+//! `cargo bench -p usml_core` で実行する
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::hint::black_box;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use usml_core::model::Model;
+use usml_core::resolver::{Resolver, ResolverError};
+use usml_core::{api, lineage, parser, validator};
+
+const TABLES: usize = 100;
+const COLUMNS: usize = 10;
+/// スカラーフィールドの数（残りは配列フィールドとそのサブフィールド）
+const SCALAR_FIELDS: usize = 900;
+const ARRAY_FIELDS: usize = 10;
+const SUB_FIELDS: usize = 9;
+
+/// import 先をメモリから返すリゾルバー
+struct MapResolver(HashMap<String, String>);
+
+impl Resolver for MapResolver {
+    fn load(&self, path: &str) -> Result<String, ResolverError> {
+        self.0
+            .get(path)
+            .cloned()
+            .ok_or_else(|| ResolverError::NotFound(path.to_string()))
+    }
+}
+
+fn column(i: usize) -> usize {
+    i / TABLES % COLUMNS
+}
+
+/// 1,000 フィールド・100 テーブルの USML を生成する
+fn usml() -> String {
+    let mut s = String::from("version: \"0.1\"\nimport:\n");
+    s.push_str("  openapi: ./api.yaml#paths[\"/items\"].get.responses[\"200\"]\n  dbml:\n");
+    for t in 0..TABLES {
+        writeln!(s, "    - ./schema.dbml#tables[\"t{t}\"]").unwrap();
+    }
+    s.push_str("usecase:\n  name: bench\n  response_mapping:\n");
+    for i in 0..SCALAR_FIELDS {
+        let table = i % TABLES;
+        if i % 10 == 0 {
+            let joined = (i + 1) % TABLES;
+            writeln!(s, "    - field: f{i}\n      source: a{i}.c1").unwrap();
+            writeln!(
+                s,
+                "      join:\n        table: t{joined}\n        alias: a{i}\n        on: a{i}.c0 = t{table}.c0"
+            )
+            .unwrap();
+        } else {
+            writeln!(
+                s,
+                "    - field: f{i}\n      source: t{table}.c{}",
+                column(i)
+            )
+            .unwrap();
+        }
+    }
+    for a in 0..ARRAY_FIELDS {
+        let table = a * 7 % TABLES;
+        writeln!(
+            s,
+            "    - field: list{a}\n      type: array\n      source_table: t{table}\n      fields:"
+        )
+        .unwrap();
+        for f in 0..SUB_FIELDS {
+            writeln!(
+                s,
+                "        - field: item{f}\n          source: t{table}.c{f}"
+            )
+            .unwrap();
+        }
+    }
+    s.push_str("  filters:\n");
+    for p in 0..20 {
+        writeln!(
+            s,
+            "    - param: p{p}\n      maps_to: t{p}.c0\n      condition: t{p}.c0 = :p{p}"
+        )
+        .unwrap();
+    }
+    s.push_str("  transforms:\n");
+    for i in (0..SCALAR_FIELDS).step_by(9) {
+        let table = i % TABLES;
+        writeln!(
+            s,
+            "    - target: f{i}\n      type: COALESCE\n      sources: [t{table}.c2, t{table}.c3]"
+        )
+        .unwrap();
+    }
+    s
+}
+
+fn dbml() -> String {
+    let mut s = String::new();
+    for t in 0..TABLES {
+        writeln!(s, "Table t{t} {{").unwrap();
+        for c in 0..COLUMNS {
+            writeln!(s, "  c{c} varchar").unwrap();
+        }
+        s.push_str("}\n\n");
+    }
+    s
+}
+
+fn openapi() -> String {
+    let mut s = String::from(
+        "openapi: \"3.0.0\"\ninfo:\n  title: bench\n  version: \"1.0\"\npaths:\n  /items:\n    get:\n      responses:\n        \"200\":\n          description: OK\n          content:\n            application/json:\n              schema:\n                type: object\n                properties:\n",
+    );
+    for i in 0..SCALAR_FIELDS {
+        writeln!(
+            s,
+            "                  f{i}:\n                    type: string"
+        )
+        .unwrap();
+    }
+    s
+}
+
+fn benches(c: &mut Criterion) {
+    let source = usml();
+    let doc = parser::parse(&source).expect("合成ドキュメントのパースに失敗しました");
+    let resolver = MapResolver(HashMap::from([
+        ("bench/./schema.dbml".to_string(), dbml()),
+        ("bench/./api.yaml".to_string(), openapi()),
+    ]));
+
+    c.bench_function("parse", |b| b.iter(|| parser::parse(black_box(&source))));
+    c.bench_function("model", |b| b.iter(|| Model::build(black_box(&doc))));
+    c.bench_function("validate", |b| {
+        b.iter(|| validator::validate(black_box(&doc)))
+    });
+    c.bench_function("validate_with_resolver", |b| {
+        b.iter(|| validator::validate_with_resolver(black_box(&doc), "bench", &resolver))
+    });
+    c.bench_function("lineage", |b| b.iter(|| lineage::lineage(black_box(&doc))));
+    c.bench_function("analyze", |b| {
+        b.iter(|| api::analyze(parser::parse(black_box(&source)).unwrap()))
+    });
+    #[cfg(feature = "visualizer")]
+    c.bench_function("visualize", |b| {
+        b.iter(|| usml_core::visualizer::generate_html(black_box(&doc)))
+    });
+}
+
+criterion_group!(validation, benches);
+criterion_main!(validation);
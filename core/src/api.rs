@@ -0,0 +1,215 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+
+use crate::ast::{Import, UsmlDocument};
+use crate::diagnostic::{self, Diagnostic};
+use crate::lineage::{self, LineageEdge};
+use crate::model::Model;
+use crate::resolver::Resolver;
+use crate::validator::{self, ResolveContext};
+
+/// ドキュメントの解析結果
+///
+/// CLI・LSP・可視化・外部ツールが共通で使う機械可読な出力。
+/// serde でそのまま JSON などに直列化できる
+#[derive(Debug, Serialize)]
+pub struct Analysis {
+    /// パース済みのドキュメント
+    pub document: UsmlDocument,
+    /// エイリアス・結合・transform を解決した意味モデル
+    pub model: Model,
+    /// import から解決した OpenAPI / DBML の情報（解決しない場合は空）
+    pub schemas: ResolveContext,
+    pub diagnostics: Vec<Diagnostic>,
+    pub lineage: Vec<LineageEdge>,
+    /// テーブルごとの利用状況
+    pub tables: Vec<TableUsage>,
+}
+
+/// テーブル 1 つ分の利用状況
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TableUsage {
+    /// エイリアスを解決した実テーブル名
+    pub name: String,
+    /// import.dbml で宣言されているか
+    pub imported: bool,
+    /// source / transforms / 結合条件で参照されるカラム
+    pub columns: Vec<String>,
+    /// このテーブルに依存するレスポンスフィールドのフルパス
+    pub fields: Vec<String>,
+}
+
+/// import を解決せずにドキュメントを解析する
+pub fn analyze(doc: UsmlDocument) -> Analysis {
+    let model = Model::build(&doc);
+    let diagnostics = diagnostic::from_errors(validator::validate_model(&doc, &model));
+    build_analysis(doc, model, ResolveContext::default(), diagnostics)
+}
+
+/// Resolver で import を解決してからドキュメントを解析する
+pub fn analyze_with_resolver<R: Resolver + ?Sized>(
+    doc: UsmlDocument,
+    base_dir: &str,
+    resolver: &R,
+) -> Analysis {
+    let (ctx, resolve_errors) = validator::resolve_imports(&doc, base_dir, resolver);
+    let model = Model::build(&doc);
+    let diagnostics = validator::validate_resolved(
+        &doc,
+        &model,
+        &ctx,
+        resolve_errors,
+        validator::DEFAULT_AGGREGATES,
+    );
+    build_analysis(doc, model, ctx, diagnostics)
+}
+
+fn build_analysis(
+    document: UsmlDocument,
+    model: Model,
+    schemas: ResolveContext,
+    diagnostics: Vec<Diagnostic>,
+) -> Analysis {
+    let lineage = lineage::from_model(&model);
+    let tables = table_usage(&model, &lineage);
+    Analysis {
+        document,
+        model,
+        schemas,
+        diagnostics,
+        lineage,
+        tables,
+    }
+}
+
+fn table_usage(model: &Model, edges: &[LineageEdge]) -> Vec<TableUsage> {
+    let mut fields_by_table: HashMap<&str, Vec<String>> = HashMap::new();
+    let mut seen: HashSet<(&str, &str)> = HashSet::new();
+    for edge in edges {
+        if seen.insert((&edge.table, &edge.field)) {
+            fields_by_table
+                .entry(&edge.table)
+                .or_default()
+                .push(edge.field.clone());
+        }
+    }
+
+    model
+        .tables
+        .iter()
+        .map(|table| TableUsage {
+            name: table.name.clone(),
+            imported: table.imported,
+            columns: table.columns.clone(),
+            fields: fields_by_table
+                .remove(table.name.as_str())
+                .unwrap_or_default(),
+        })
+        .collect()
+}
+
+/// import.dbml で宣言されたテーブル名を宣言順・重複なしで返す
+///
+/// `import.tables` で対応付けた DB 上のテーブルは usecase のテーブル名にする
+pub fn imported_tables(doc: &UsmlDocument) -> Vec<String> {
+    import_tables(&doc.import)
+}
+
+/// `imported_tables` と同じ。環境の上書きを重ねた import などドキュメント以外の import に使う
+pub(crate) fn import_tables(import: &Import) -> Vec<String> {
+    let mut tables: Vec<String> = Vec::new();
+    for entry in import.dbml.iter().flatten() {
+        if let Some(table) = extract_table_name(entry).map(|name| {
+            import
+                .tables
+                .iter()
+                .find(|(_, db_name)| **db_name == name)
+                .map_or(name, |(table, _)| table.clone())
+        }) && !tables.contains(&table)
+        {
+            tables.push(table);
+        }
+    }
+    tables
+}
+
+fn extract_table_name(value: &str) -> Option<String> {
+    let marker = "#tables[\"";
+    let start = value.find(marker)? + marker.len();
+    let remainder = &value[start..];
+    let end = remainder.find("\"]")?;
+    Some(remainder[..end].to_string())
+}
+
+/// 結合条件などの式から テーブル名.カラム名 パターンを抽出する
+pub fn table_refs(expr: &str) -> Vec<(String, String)> {
+    let mut refs = Vec::new();
+    for token in expr.split_whitespace() {
+        let clean = token.trim_matches(|c: char| !c.is_alphanumeric() && c != '.' && c != '_');
+        if let Some((table, col)) = clean.split_once('.')
+            && !table.is_empty()
+            && !col.is_empty()
+            && col.chars().all(|c| c.is_alphanumeric() || c == '_')
+        {
+            refs.push((table.to_string(), col.to_string()));
+        }
+    }
+    refs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+    use crate::resolver::ResolverError;
+
+    const DOC: &str = r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["posts"]
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: title
+      source: posts.title
+    - field: author_name
+      source: author.name
+      join:
+        table: users
+        alias: author
+        on: posts.user_id = author.id
+"#;
+
+    #[test]
+    fn test_analyze_collects_table_usage() {
+        let analysis = analyze(parser::parse(DOC).unwrap());
+        assert!(analysis.diagnostics.is_empty());
+        assert_eq!(analysis.lineage.len(), 2);
+
+        let users = analysis.tables.iter().find(|t| t.name == "users").unwrap();
+        assert!(users.imported);
+        assert_eq!(users.columns, vec!["id", "name"]);
+        assert_eq!(users.fields, vec!["author_name"]);
+
+        let posts = analysis.tables.iter().find(|t| t.name == "posts").unwrap();
+        assert_eq!(posts.columns, vec!["title", "user_id"]);
+        assert!(!analysis.tables.iter().any(|t| t.name == "author"));
+    }
+
+    #[test]
+    fn test_analyze_with_resolver_reports_resolve_errors() {
+        struct EmptyResolver;
+        impl Resolver for EmptyResolver {
+            fn load(&self, path: &str) -> Result<String, ResolverError> {
+                Err(ResolverError::NotFound(path.to_string()))
+            }
+        }
+
+        let analysis = analyze_with_resolver(parser::parse(DOC).unwrap(), ".", &EmptyResolver);
+        assert!(analysis.schemas.dbml_tables.is_empty());
+        assert!(analysis.diagnostics.iter().any(|d| d.rule == "import.dbml"));
+    }
+}
@@ -1,25 +1,54 @@
-use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
 
 /// USML ドキュメントのルート
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct UsmlDocument {
     pub version: String,
     pub import: Import,
     pub usecase: Usecase,
+    /// 環境（dev・stg・prod など）ごとの上書き。`--env` で選んだ環境を import に適用する
+    #[serde(default)]
+    pub environments: BTreeMap<String, Environment>,
 }
 
 /// 外部仕様ファイルへの参照
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct Import {
     pub openapi: Option<String>,
     pub dbml: Option<Vec<String>>,
+    /// usecase で使うテーブル名と、DB（DBML）上のテーブル名が異なる場合の対応（例: `users: stg_users`）
+    #[serde(default)]
+    pub tables: BTreeMap<String, String>,
+}
+
+/// 環境 1 つ分の上書き
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Environment {
+    /// 書いた項目だけを置き換える import（`tables` はテーブルごとに上書きする）
+    #[serde(default)]
+    pub import: Option<Import>,
+    /// import 以外の項目。レスポンスの構造を変えないよう上書きできない（U059）
+    #[serde(flatten)]
+    pub overrides: BTreeMap<String, serde_yaml::Value>,
 }
 
 /// ユースケース定義
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct Usecase {
     pub name: String,
     pub summary: Option<String>,
+    /// ドメイン・チームなどで usecase を分類するタグ
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// データアクセスに責任を持つチーム
+    #[serde(default)]
+    pub owner: Option<String>,
+    /// 変更をレビューする担当者
+    #[serde(default)]
+    pub reviewers: Vec<String>,
     #[serde(default)]
     pub output: Option<String>,
     pub response_mapping: Vec<ResponseMapping>,
@@ -27,10 +56,65 @@ pub struct Usecase {
     pub filters: Vec<Filter>,
     #[serde(default)]
     pub transforms: Vec<Transform>,
+    /// 重複行を除く（SELECT DISTINCT）
+    #[serde(default)]
+    pub distinct: bool,
+    /// 返す行数の上限（常に適用する LIMIT）
+    #[serde(default)]
+    pub limit: Option<u32>,
+    /// リクエストで行数を指定しない場合の LIMIT
+    #[serde(default)]
+    pub default_limit: Option<u32>,
+    /// 必要な権限と行レベルのアクセス制御
+    #[serde(default)]
+    pub authorization: Option<Authorization>,
+    /// レスポンスのキャッシュ
+    #[serde(default)]
+    pub cache: Option<Cache>,
+    /// 配列フィールドの要素の取り方
+    #[serde(default)]
+    pub hydration: Option<Hydration>,
+}
+
+/// 配列フィールドの要素をコード生成でどう取るか
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Hydration {
+    /// `batch`（親の行のあとにキーの IN で要素をまとめて取る）か `json`（親のクエリで JSON の配列に集約する）
+    pub strategy: String,
+    /// `json` で使う SQL の方言（`postgresql` / `mysql`）。省略時は postgresql
+    #[serde(default)]
+    pub dialect: Option<String>,
+}
+
+/// usecase のアクセス制御
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Authorization {
+    /// 必要な OAuth スコープ
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    /// 必要なロール
+    #[serde(default)]
+    pub roles: Vec<String>,
+    /// 常に WHERE に加える行レベルの条件（例: `posts.owner_id = :current_user_id`）
+    #[serde(default)]
+    pub row_filters: Vec<String>,
+}
+
+/// usecase のレスポンスキャッシュ
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Cache {
+    /// 有効期間（秒）
+    pub ttl: u32,
+    /// キャッシュキーに含めるパラメータ（filters[].param）
+    #[serde(default)]
+    pub key: Vec<String>,
+    /// 更新されたらキャッシュを破棄するテーブル
+    #[serde(default)]
+    pub invalidated_by: Vec<String>,
 }
 
 /// レスポンスフィールドとDBカラムの対応
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct ResponseMapping {
     pub field: String,
     #[serde(default)]
@@ -52,10 +136,86 @@ pub struct ResponseMapping {
     /// 配列のサブフィールド
     #[serde(default)]
     pub fields: Option<Vec<ResponseMapping>>,
+    /// ページネーションの対象になる配列（一覧レスポンスの items）
+    #[serde(default)]
+    pub collection: bool,
+    /// 一覧レスポンスの付帯フィールド（`total` / `next_cursor`）
+    #[serde(default)]
+    pub envelope: Option<String>,
+    /// レスポンスで NULL を返しうるか。省略時は OpenAPI の定義に従う
+    #[serde(default)]
+    pub nullable: Option<bool>,
+    /// source の代わりに返す固定値（`const:` とも書ける）。`:name` はリクエストパラメータの値
+    #[serde(default, alias = "const")]
+    pub value: Option<Literal>,
+    /// 値をそのまま返すリクエストパラメータ（`source: ":name"` と同じ）
+    #[serde(default)]
+    pub from_param: Option<String>,
+    /// 値を別の usecase のレスポンスから組み立てる場合の usecase 名
+    #[serde(default)]
+    pub source_usecase: Option<String>,
+    /// UTC で保存した日時のカラムを返すときのタイムゾーン。IANA 名（`Asia/Tokyo`）か `:name`（リクエストパラメータ）
+    #[serde(default)]
+    pub timezone: Option<String>,
+}
+
+impl ResponseMapping {
+    /// `from_param` か `source: ":name"` で指定したリクエストパラメータ名
+    pub fn param(&self) -> Option<&str> {
+        self.from_param.as_deref().or_else(|| {
+            self.source
+                .as_deref()
+                .and_then(|s| s.strip_prefix(':'))
+                .filter(|name| !name.is_empty())
+        })
+    }
+}
+
+/// `value:` に書く固定値
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Literal {
+    Bool(bool),
+    Integer(i64),
+    Number(f64),
+    String(String),
+}
+
+impl Literal {
+    /// `:name` 形式のリクエストパラメータ参照ならパラメータ名
+    pub fn param(&self) -> Option<&str> {
+        match self {
+            Literal::String(s) => s.strip_prefix(':').filter(|name| !name.is_empty()),
+            _ => None,
+        }
+    }
+
+    /// JSON Schema の型。パラメータ参照は None
+    pub fn json_type(&self) -> Option<&'static str> {
+        match self {
+            Literal::Bool(_) => Some("boolean"),
+            Literal::Integer(_) => Some("integer"),
+            Literal::Number(_) => Some("number"),
+            Literal::String(_) if self.param().is_some() => None,
+            Literal::String(_) => Some("string"),
+        }
+    }
+}
+
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Literal::Bool(b) => write!(f, "{}", b),
+            Literal::Integer(n) => write!(f, "{}", n),
+            Literal::Number(n) => write!(f, "{}", n),
+            Literal::String(s) if self.param().is_some() => f.write_str(s),
+            Literal::String(s) => write!(f, "{:?}", s),
+        }
+    }
 }
 
 /// テーブル結合定義
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct Join {
     pub table: String,
     pub on: String,
@@ -66,25 +226,33 @@ pub struct Join {
 }
 
 /// 多段結合の各エントリ
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct JoinChainEntry {
     pub table: String,
     pub on: String,
 }
 
 /// 集約定義
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct Aggregate {
     pub r#type: String,
     #[serde(default)]
     pub group_by: Option<String>,
+    /// 生成する SQL での取り方（`group_by` / `subquery`）。省略時は生成器が選ぶ
+    #[serde(default)]
+    pub strategy: Option<String>,
 }
 
 /// リクエストパラメータのDBクエリへの対応
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct Filter {
     pub param: String,
+    /// `use` でライブラリの定義を使う場合は省略できる
+    #[serde(default)]
     pub maps_to: String,
+    /// 使うライブラリのフィルタ名。書かれていない項目をライブラリの定義で補う
+    #[serde(default)]
+    pub r#use: Option<String>,
     /// WHERE 条件式
     #[serde(default)]
     pub condition: Option<String>,
@@ -99,6 +267,12 @@ pub struct Filter {
     pub max_page_size: Option<u32>,
     #[serde(default)]
     pub cursor_field: Option<String>,
+    /// cursor_field の値が同じ行の順序を決めるカラム（主キーなど）。cursor_field と組にしたキーで辿る
+    #[serde(default)]
+    pub cursor_tiebreaker: Option<String>,
+    /// `envelope: total` の件数の数え方（window / query / none）
+    #[serde(default)]
+    pub total_count: Option<String>,
     /// ソートのデフォルトカラム
     #[serde(default)]
     pub default_column: Option<String>,
@@ -108,13 +282,24 @@ pub struct Filter {
     pub allowed_columns: Option<Vec<String>>,
     #[serde(default)]
     pub allowed_directions: Option<Vec<String>>,
+    /// パラメータを必須にする。必須のフィルタの条件は常に加える
+    #[serde(default)]
+    pub required: Option<bool>,
+    /// パラメータが指定されない場合の値。既定値のあるフィルタの条件は常に加える
+    #[serde(default)]
+    pub default: Option<Literal>,
 }
 
 /// 変換・加工定義
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct Transform {
     pub target: String,
+    /// `use` でライブラリの定義を使う場合は省略できる
+    #[serde(default)]
     pub r#type: String,
+    /// 使うライブラリの transform 名。書かれていない項目をライブラリの定義で補う
+    #[serde(default)]
+    pub r#use: Option<String>,
     /// 単一ソース
     #[serde(default)]
     pub source: Option<String>,
@@ -145,17 +330,56 @@ pub struct Transform {
     /// CONDITIONAL_SOURCE 時の条件非マッチ時のソース
     #[serde(default)]
     pub else_source: Option<String>,
+    /// CONVERT 時に source に掛ける係数
+    #[serde(default)]
+    pub factor: Option<f64>,
+    /// CONVERT 時に係数を引く換算表（lookup と factor はどちらか一方）
+    #[serde(default)]
+    pub lookup: Option<ConvertLookup>,
+    /// CONVERT 時の丸め方（half_up / half_even / half_down / up / down / ceiling / floor。省略時は half_up）
+    #[serde(default)]
+    pub rounding: Option<String>,
+    /// CONVERT 時の出力の小数点以下の桁数
+    #[serde(default)]
+    pub scale: Option<u32>,
+    /// LOCALIZE 時に翻訳テーブルの行を選ぶ言語の設定
+    #[serde(default)]
+    pub locale: Option<LocaleLookup>,
+}
+
+/// CONVERT の換算表（為替レートなど）
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ConvertLookup {
+    pub table: String,
+    /// 換算表の行を選ぶ結合条件（例: `orders.currency = exchange_rates.currency`）
+    pub on: String,
+    /// 係数のカラム（`テーブル.カラム`）
+    pub rate: String,
+}
+
+/// LOCALIZE の翻訳テーブルの行の選び方
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LocaleLookup {
+    /// 翻訳テーブルの行を親の行に対応付ける条件（例: `products.id = product_translations.product_id`）
+    pub on: String,
+    /// 言語のカラム（`テーブル.カラム`）
+    pub column: String,
+    /// 言語を受け取るリクエストパラメータ
+    pub param: String,
+    /// パラメータの言語の訳が無いときに順に試す言語（例: `[en]`）
+    #[serde(default)]
+    pub fallback: Vec<String>,
 }
 
 /// CASE 分岐の各エントリ
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct CaseWhen {
     pub value: String,
     pub then: String,
 }
 
 /// 条件付き変換の条件
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct TransformCondition {
     /// リクエストパラメータを参照
     #[serde(default)]
@@ -169,3 +393,89 @@ pub struct TransformCondition {
     pub operator: String,
     pub value: String,
 }
+
+/// 共有ライブラリファイル（`library.usml.yaml`）のルート
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LibraryDocument {
+    pub version: String,
+    pub library: Library,
+}
+
+/// usecase から `use` で参照する名前付きの transform・filter
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Library {
+    #[serde(default)]
+    pub transforms: BTreeMap<String, TransformTemplate>,
+    #[serde(default)]
+    pub filters: BTreeMap<String, FilterTemplate>,
+}
+
+/// ライブラリの transform 定義（target を除く `Transform` の項目）
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TransformTemplate {
+    pub r#type: String,
+    #[serde(default)]
+    pub source: Option<String>,
+    #[serde(default)]
+    pub sources: Option<Vec<String>>,
+    #[serde(default)]
+    pub fallback: Option<String>,
+    #[serde(default)]
+    pub separator: Option<String>,
+    #[serde(default)]
+    pub when: Option<Vec<CaseWhen>>,
+    #[serde(default)]
+    pub else_value: Option<String>,
+    #[serde(default)]
+    pub mask_pattern: Option<String>,
+    #[serde(default)]
+    pub condition: Option<Vec<TransformCondition>>,
+    #[serde(default)]
+    pub then_source: Option<String>,
+    #[serde(default)]
+    pub else_source: Option<String>,
+    #[serde(default)]
+    pub factor: Option<f64>,
+    #[serde(default)]
+    pub lookup: Option<ConvertLookup>,
+    #[serde(default)]
+    pub rounding: Option<String>,
+    #[serde(default)]
+    pub scale: Option<u32>,
+    #[serde(default)]
+    pub locale: Option<LocaleLookup>,
+}
+
+/// ライブラリの filter 定義（param を除く `Filter` の項目）
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct FilterTemplate {
+    pub maps_to: String,
+    #[serde(default)]
+    pub condition: Option<String>,
+    #[serde(default)]
+    pub strategy: Option<String>,
+    #[serde(default)]
+    pub page_size: Option<u32>,
+    #[serde(default)]
+    pub limit_param: Option<String>,
+    #[serde(default)]
+    pub max_page_size: Option<u32>,
+    #[serde(default)]
+    pub cursor_field: Option<String>,
+    #[serde(default)]
+    pub cursor_tiebreaker: Option<String>,
+    #[serde(default)]
+    pub total_count: Option<String>,
+    #[serde(default)]
+    pub default_column: Option<String>,
+    #[serde(default)]
+    pub default_direction: Option<String>,
+    #[serde(default)]
+    pub allowed_columns: Option<Vec<String>>,
+    #[serde(default)]
+    pub allowed_directions: Option<Vec<String>>,
+    #[serde(default)]
+    pub required: Option<bool>,
+    #[serde(default)]
+    pub default: Option<Literal>,
+}
@@ -0,0 +1,191 @@
+//! ユーザーが用意したテンプレートに解析結果を渡してコードを生成する
+//!
+//! テンプレートは Jinja2 / Tera 互換の構文（minijinja で描画する）。参照できる変数は [`Analysis`] の
+//! フィールド（`document`・`model`・`schemas`・`diagnostics`・`lineage`・`tables`）と、
+//! `usecase`（`document.usecase` の別名）・`file`（入力ファイルのパス）。
+//! 識別子の変換に `snake_case`・`camel_case`・`pascal_case` フィルタを使える
+
+use std::error::Error;
+
+use minijinja::{Environment, UndefinedBehavior, Value, context};
+use thiserror::Error;
+
+use crate::api::Analysis;
+
+pub mod graphql;
+pub mod kotlin;
+pub mod mock;
+pub mod plan;
+pub mod sql;
+pub mod testcase;
+
+/// テンプレートの読み込み・描画のエラー
+#[derive(Debug, Error)]
+#[error("テンプレートエラー '{name}': {message}")]
+pub struct CodegenError {
+    pub name: String,
+    /// テンプレートの該当箇所と原因を含むメッセージ
+    pub message: String,
+}
+
+impl CodegenError {
+    fn new(name: &str, err: minijinja::Error) -> Self {
+        let mut message = format!("{:#}", err);
+        let mut source = err.source();
+        while let Some(err) = source {
+            message.push_str(": ");
+            message.push_str(&err.to_string());
+            source = err.source();
+        }
+        Self {
+            name: name.to_string(),
+            message,
+        }
+    }
+}
+
+/// 1 つのテンプレートから生成するジェネレータ
+pub struct Generator {
+    name: String,
+    source: String,
+    env: Environment<'static>,
+}
+
+impl Generator {
+    /// `name` はエラーメッセージに使うテンプレート名（ファイルパスなど）。構文エラーはここで返す
+    pub fn new(name: &str, template: &str) -> Result<Self, CodegenError> {
+        let mut env = Environment::new();
+        // 変数名の誤りを空文字列で埋めずにエラーにする
+        env.set_undefined_behavior(UndefinedBehavior::Strict);
+        env.set_keep_trailing_newline(true);
+        env.add_filter("snake_case", |s: &str| snake_case(s));
+        env.add_filter("camel_case", |s: &str| camel_case(s));
+        env.add_filter("pascal_case", |s: &str| pascal_case(s));
+        env.template_from_named_str(name, template)
+            .map_err(|e| CodegenError::new(name, e))?;
+        Ok(Self {
+            name: name.to_string(),
+            source: template.to_string(),
+            env,
+        })
+    }
+
+    /// `file` の解析結果をテンプレートに渡して描画する
+    pub fn render(&self, file: &str, analysis: &Analysis) -> Result<String, CodegenError> {
+        let context = context! {
+            usecase => Value::from_serialize(&analysis.document.usecase),
+            file => file,
+            ..Value::from_serialize(analysis)
+        };
+        self.env
+            .template_from_named_str(&self.name, &self.source)
+            .and_then(|template| template.render(context))
+            .map_err(|e| CodegenError::new(&self.name, e))
+    }
+}
+
+/// `userName`・`user-name`・`user.name` などを単語に分ける
+fn words(s: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut prev_lower = false;
+    for c in s.chars() {
+        if !c.is_alphanumeric() {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            prev_lower = false;
+            continue;
+        }
+        if c.is_uppercase() && prev_lower {
+            words.push(std::mem::take(&mut current));
+        }
+        prev_lower = c.is_lowercase() || c.is_ascii_digit();
+        current.extend(c.to_lowercase());
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+pub(crate) fn snake_case(s: &str) -> String {
+    words(s).join("_")
+}
+
+pub(crate) fn camel_case(s: &str) -> String {
+    let words = words(s);
+    let mut out = words.first().cloned().unwrap_or_default();
+    for word in &words[1.min(words.len())..] {
+        out.push_str(&capitalize(word));
+    }
+    out
+}
+
+pub(crate) fn pascal_case(s: &str) -> String {
+    words(s).iter().map(|w| capitalize(w)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{api, parser};
+
+    #[test]
+    fn test_render_template_with_analysis() {
+        let doc = parser::parse(
+            r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: UserList
+  response_mapping:
+    - field: userName
+      source: users.name
+    - field: id
+      source: users.id
+"#,
+        )
+        .unwrap();
+        let generator = Generator::new(
+            "handler.rs.tera",
+            "// {{ file }}\npub struct {{ usecase.name | pascal_case }}Row {\n\
+             {%- for field in model.fields %}\n    pub {{ field.name | snake_case }}: String,\n{%- endfor %}\n}\n\
+             // tables: {{ tables | map(attribute=\"name\") | join(\", \") }}",
+        )
+        .unwrap();
+
+        let output = generator
+            .render("users.usml.yaml", &api::analyze(doc))
+            .unwrap();
+        assert_eq!(
+            output,
+            "// users.usml.yaml\npub struct UserListRow {\n    pub user_name: String,\n    pub id: String,\n}\n// tables: users"
+        );
+    }
+
+    #[test]
+    fn test_undefined_variable_is_reported() {
+        let doc = parser::parse(
+            "version: \"0.1\"\nimport: {}\nusecase:\n  name: a\n  response_mapping:\n    - field: x\n      value: 1\n",
+        )
+        .unwrap();
+        let generator = Generator::new("t.tera", "{{ missing.name }}").unwrap();
+        let err = generator
+            .render("a.usml.yaml", &api::analyze(doc))
+            .unwrap_err();
+        assert_eq!(err.name, "t.tera");
+        assert!(err.message.contains("missing"));
+        assert_eq!(camel_case("user_name-id"), "userNameId");
+    }
+}
@@ -0,0 +1,1367 @@
+//! GraphQL の SDL と、async-graphql・sqlx（PostgreSQL）のリゾルバの雛形を生成する
+//!
+//! ルートが 1 件を返す usecase は `Option<型>`、ページネーションのあるルートはリスト、
+//! collection の配列を持つ usecase はその配列と envelope を組み立てたオブジェクトを返す。
+//! `authorization.row_filters` のパラメータは GraphQL の引数ではなく、コンテキストのスコープから取る。
+//! 配列は `hydration: batch` なら親の行のキーでまとめて取り、json なら親の行の JSON の列から読む
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::api::Analysis;
+use crate::ast::Literal;
+use crate::export;
+use crate::model::FieldKind;
+
+use super::plan::{self, Hydration, PlanError, Query, TotalCount, ValueType};
+use super::{camel_case, pascal_case, snake_case};
+
+/// SDL（型と `extend type Query`）を生成する
+pub fn sdl(file: &str, analysis: &Analysis) -> Result<String, PlanError> {
+    let graphql = Graphql::new(file, analysis)?;
+    let mut types = String::new();
+    let mut scalars = Vec::new();
+    for level in plan::levels(&analysis.model) {
+        if level.is_none()
+            && let Some(summary) = &analysis.document.usecase.summary
+        {
+            let _ = writeln!(types, "\"\"\"{}\"\"\"", summary);
+        }
+        let _ = writeln!(types, "type {} {{", graphql.type_name(level));
+        for (index, field) in plan::children(&analysis.model, level) {
+            let field_type = if field.kind == FieldKind::Array {
+                format!("[{}!]!", graphql.type_name(Some(index)))
+            } else {
+                let field_type = plan::field_type(analysis, field);
+                let scalar = graphql_type(field_type.value);
+                push_scalar(&mut scalars, scalar);
+                if field_type.nullable {
+                    scalar.to_string()
+                } else {
+                    format!("{}!", scalar)
+                }
+            };
+            let _ = writeln!(types, "  {}: {}", camel_case(&field.name), field_type);
+        }
+        types.push_str("}\n\n");
+    }
+
+    let args: Vec<String> = graphql
+        .args()
+        .iter()
+        .map(|arg| {
+            let scalar = graphql_type(arg.value);
+            push_scalar(&mut scalars, scalar);
+            match &arg.default {
+                Some(default) => format!("{}: {}! = {}", camel_case(&arg.name), scalar, default),
+                None if arg.required => format!("{}: {}!", camel_case(&arg.name), scalar),
+                None => format!("{}: {}", camel_case(&arg.name), scalar),
+            }
+        })
+        .collect();
+    let args = if args.is_empty() {
+        String::new()
+    } else {
+        format!("({})", args.join(", "))
+    };
+    let returns = match graphql.shape() {
+        Shape::List => format!("[{}!]!", graphql.type_name(None)),
+        Shape::Single => graphql.type_name(None),
+        Shape::Collection => format!("{}!", graphql.type_name(None)),
+    };
+
+    let mut out = format!(
+        "# usml generate --backend graphql-sdl で {} から生成（{}）\n\n",
+        file, analysis.document.usecase.name
+    );
+    for scalar in scalars {
+        let _ = writeln!(out, "scalar {}", scalar);
+    }
+    if !out.ends_with("\n\n") {
+        out.push('\n');
+    }
+    out.push_str(&types);
+    let _ = writeln!(
+        out,
+        "extend type Query {{\n  {}{}: {}\n}}",
+        camel_case(&graphql.identifier),
+        args,
+        returns
+    );
+    Ok(out)
+}
+
+/// async-graphql の型・リゾルバと、sqlx でクエリを組み立てる関数を生成する
+pub fn resolver(file: &str, analysis: &Analysis) -> Result<String, PlanError> {
+    let graphql = Graphql::new(file, analysis)?;
+    let mut out = format!(
+        "// usml generate --backend graphql で {} から生成（{}）\n\n\
+         use async_graphql::{{Context, Object, SimpleObject}};\n\
+         use sqlx::{{PgPool, Postgres, QueryBuilder}};\n\n",
+        file, analysis.document.usecase.name
+    );
+    for level in plan::levels(&analysis.model) {
+        graphql.object(&mut out, level);
+    }
+    let scope = graphql.scope_params();
+    if !scope.is_empty() {
+        let _ = writeln!(
+            out,
+            "/// 行レベルの条件に使う値。リクエストごとに `Request::data` で渡す\n\
+             #[derive(Debug, Clone)]\npub struct {}Scope {{",
+            graphql.base
+        );
+        for param in &scope {
+            let _ = writeln!(
+                out,
+                "    pub {}: {},",
+                snake_case(&param.name),
+                rust_type(param.value)
+            );
+        }
+        out.push_str("}\n\n");
+    }
+    graphql.query_object(&mut out);
+    graphql.push_conditions(&mut out);
+    for query in &graphql.queries {
+        graphql.fetch_function(&mut out, query);
+    }
+    if graphql.total_level().is_some()
+        && plan::total_count(&analysis.document.usecase) == TotalCount::Query
+    {
+        graphql.count_function(&mut out);
+    }
+    out.truncate(out.trim_end().len());
+    out.push('\n');
+    Ok(out)
+}
+
+/// リゾルバが返す形
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Shape {
+    /// ルートの行のリスト（ルートにページネーションがある）
+    List,
+    /// ルートの 1 行
+    Single,
+    /// collection の配列と envelope を組み立てたオブジェクト
+    Collection,
+}
+
+/// 配列を埋める親の行
+#[derive(Clone, Copy)]
+enum Rows<'s> {
+    /// 1 行（変数名）
+    One(&'s str),
+    /// 行のリスト（`iter_mut()` できる式）
+    Many(&'s str),
+}
+
+/// GraphQL の引数
+struct Arg {
+    name: String,
+    value: ValueType,
+    /// 既定値（GraphQL・Rust 共通の表記）。None なら省略可能な引数
+    default: Option<String>,
+    /// 既定値が無くても省略できない引数（`required: true` のフィルタ）
+    required: bool,
+}
+
+struct Graphql<'a> {
+    analysis: &'a Analysis,
+    /// ファイル名から作る識別子（`users_list`）
+    identifier: String,
+    /// 型名の接頭辞（`UsersList`）
+    base: String,
+    conditions: Vec<plan::Condition>,
+    params: Vec<plan::Param>,
+    /// 列の式の `:name` で受け取るパラメータ（timezone・LOCALIZE の言語。条件のパラメータを除く）
+    select_params: Vec<String>,
+    queries: Vec<Query<'a>>,
+}
+
+impl<'a> Graphql<'a> {
+    fn new(file: &str, analysis: &'a Analysis) -> Result<Self, PlanError> {
+        let identifier = export::identifier(file);
+        let conditions = plan::conditions(&analysis.document);
+        let params = plan::condition_params(analysis, &conditions);
+        let select_params = plan::select_params(analysis)
+            .into_iter()
+            .filter(|name| !params.iter().any(|p| &p.name == name))
+            .collect();
+        Ok(Self {
+            analysis,
+            base: pascal_case(&identifier),
+            identifier,
+            conditions,
+            params,
+            select_params,
+            queries: plan::queries(analysis)?,
+        })
+    }
+
+    fn type_name(&self, level: Option<usize>) -> String {
+        match level {
+            None => format!("{}Response", self.base),
+            Some(index) => format!(
+                "{}{}Item",
+                self.base,
+                pascal_case(&self.analysis.model.fields[index].path)
+            ),
+        }
+    }
+
+    fn shape(&self) -> Shape {
+        let model = &self.analysis.model;
+        if plan::paged_level(model).is_some() {
+            Shape::Collection
+        } else if plan::filter(&self.analysis.document.usecase, "PAGINATION").is_some() {
+            Shape::List
+        } else {
+            Shape::Single
+        }
+    }
+
+    /// 行レベルの条件のパラメータ（コンテキストのスコープから取る）
+    fn scope_params(&self) -> Vec<&plan::Param> {
+        self.params.iter().filter(|p| p.scope).collect()
+    }
+
+    /// GraphQL の引数。WHERE フィルタのパラメータと、ページネーション・ソート、timezone のパラメータ
+    fn args(&self) -> Vec<Arg> {
+        let usecase = &self.analysis.document.usecase;
+        let mut args: Vec<Arg> = self
+            .params
+            .iter()
+            .filter(|p| !p.scope)
+            .map(|p| Arg {
+                name: p.name.clone(),
+                value: p.value,
+                default: p.default.as_ref().map(graphql_literal),
+                required: p.required,
+            })
+            .collect();
+        if let Some(sort) = plan::filter(usecase, "ORDER_BY")
+            && sort.allowed_columns.is_some()
+        {
+            args.push(Arg {
+                name: sort.param.clone(),
+                value: ValueType::String,
+                default: None,
+                required: false,
+            });
+        }
+        if let Some(page) = plan::filter(usecase, "PAGINATION") {
+            if let Some(limit) = &page.limit_param {
+                args.push(Arg {
+                    name: limit.clone(),
+                    value: ValueType::Int,
+                    default: Some(plan::page_size(usecase, page).to_string()),
+                    required: false,
+                });
+            }
+            if page.strategy.as_deref() == Some("cursor") {
+                args.push(Arg {
+                    name: page.param.clone(),
+                    value: plan::parameter_type(self.analysis, &page.param),
+                    default: None,
+                    required: false,
+                });
+                if let Some(tiebreaker) = plan::tiebreaker_param(page) {
+                    args.push(Arg {
+                        value: plan::parameter_type(self.analysis, &tiebreaker),
+                        name: tiebreaker,
+                        default: None,
+                        required: false,
+                    });
+                }
+            } else {
+                args.push(Arg {
+                    name: page.param.clone(),
+                    value: ValueType::Int,
+                    default: Some("1".to_string()),
+                    required: false,
+                });
+            }
+        }
+        for name in &self.select_params {
+            args.push(Arg {
+                name: name.clone(),
+                value: ValueType::String,
+                default: None,
+                required: false,
+            });
+        }
+        args
+    }
+
+    /// `envelope: total` を持つ階層（collection の配列の親）
+    fn total_level(&self) -> Option<Option<usize>> {
+        let model = &self.analysis.model;
+        let collection = plan::paged_level(model)?;
+        let parent = model.fields[collection].parent;
+        plan::children(model, parent)
+            .any(|(_, f)| f.envelope.as_deref() == Some("total"))
+            .then_some(parent)
+    }
+
+    fn object(&self, out: &mut String, level: Option<usize>) {
+        let analysis = self.analysis;
+        let query = self.queries.iter().find(|q| q.level == level);
+        // JSON の列から読む配列の要素
+        let decoded = level.is_some_and(|index| {
+            plan::hydration(&analysis.document.usecase) != Hydration::Batch
+                && plan::parent_key(analysis, index).is_some()
+        });
+        if level.is_none()
+            && let Some(summary) = &analysis.document.usecase.summary
+        {
+            let _ = writeln!(out, "/// {}", summary);
+        }
+        if decoded {
+            out.push_str(
+                "#[derive(Debug, Clone, Default, SimpleObject, sqlx::FromRow, serde::Deserialize)]\n\
+                 #[serde(default)]\n",
+            );
+        } else {
+            out.push_str("#[derive(Debug, Clone, Default, SimpleObject, sqlx::FromRow)]\n");
+        }
+        let _ = writeln!(out, "pub struct {} {{", self.type_name(level));
+        if let Some(key) = query.and_then(|q| q.parent_key.as_ref()) {
+            let _ = writeln!(
+                out,
+                "    #[graphql(skip)]\n    pub parent_key: {},",
+                rust_type(key.value)
+            );
+        }
+        for (index, field) in plan::children(&analysis.model, level) {
+            // DB から取らないフィールドは行を取ったあとに埋める
+            let from_db = plan::from_db(analysis, field);
+            if query.is_some_and(|q| {
+                field.kind == FieldKind::Array
+                    && q.columns.iter().any(|(f, _)| f.path == field.path)
+            }) {
+                out.push_str("    #[sqlx(json)]\n");
+            } else if !from_db {
+                out.push_str("    #[sqlx(skip)]\n");
+            }
+            if decoded && snake_case(&field.name) != field.name {
+                let _ = writeln!(out, "    #[serde(rename = \"{}\")]", field.name);
+            }
+            let field_type = if field.kind == FieldKind::Array {
+                format!("Vec<{}>", self.type_name(Some(index)))
+            } else {
+                let field_type = plan::field_type(analysis, field);
+                let rust_type = rust_type(field_type.value);
+                if field_type.nullable {
+                    format!("Option<{}>", rust_type)
+                } else {
+                    rust_type.to_string()
+                }
+            };
+            let _ = writeln!(out, "    pub {}: {},", snake_case(&field.name), field_type);
+        }
+        for (field, key) in query.iter().flat_map(|q| &q.keys) {
+            let _ = writeln!(
+                out,
+                "    #[graphql(skip)]\n    pub {}_key: {},",
+                snake_case(&field.name),
+                rust_type(key.value)
+            );
+        }
+        if query.is_some_and(|q| q.window_total) {
+            out.push_str("    #[graphql(skip)]\n    pub total_count: i64,\n");
+        }
+        out.push_str("}\n\n");
+    }
+
+    fn query_object(&self, out: &mut String) {
+        let analysis = self.analysis;
+        let usecase = &analysis.document.usecase;
+        let scope = self.scope_params();
+        let _ = writeln!(
+            out,
+            "#[derive(Default)]\npub struct {}Query;\n\n#[Object]\nimpl {}Query {{",
+            self.base, self.base
+        );
+        let _ = writeln!(out, "    /// {}", usecase.name);
+        let _ = writeln!(
+            out,
+            "    async fn {}(\n        &self,\n        ctx: &Context<'_>,",
+            self.identifier
+        );
+        for arg in self.args() {
+            match &arg.default {
+                Some(default) => {
+                    let _ = writeln!(
+                        out,
+                        "        #[graphql(default = {})] {}: {},",
+                        default,
+                        snake_case(&arg.name),
+                        rust_type(arg.value)
+                    );
+                }
+                None if arg.required => {
+                    let _ = writeln!(
+                        out,
+                        "        {}: {},",
+                        snake_case(&arg.name),
+                        rust_type(arg.value)
+                    );
+                }
+                None => {
+                    let _ = writeln!(
+                        out,
+                        "        {}: Option<{}>,",
+                        snake_case(&arg.name),
+                        rust_type(arg.value)
+                    );
+                }
+            }
+        }
+        let shape = self.shape();
+        let returns = match shape {
+            Shape::List => format!("Vec<{}>", self.type_name(None)),
+            Shape::Single => format!("Option<{}>", self.type_name(None)),
+            Shape::Collection => self.type_name(None),
+        };
+        let _ = writeln!(out, "    ) -> async_graphql::Result<{}> {{", returns);
+        out.push_str("        let pool = ctx.data::<PgPool>()?;\n");
+        if !scope.is_empty() {
+            let _ = writeln!(
+                out,
+                "        let scope = ctx.data::<{}Scope>()?;",
+                self.base
+            );
+        }
+
+        let has_root_query = self.queries.iter().any(|q| q.level.is_none());
+        match shape {
+            Shape::List => {
+                let mut arrays = String::new();
+                self.hydrate(&mut arrays, None, Rows::Many("rows"), 2);
+                let mut body = String::new();
+                self.fill(&mut body, None, "row", 3);
+                if arrays.is_empty() && body.is_empty() {
+                    let _ = writeln!(out, "        let rows = {}.await?;", self.fetch_call(None));
+                } else {
+                    let _ = writeln!(
+                        out,
+                        "        let mut rows = {}.await?;",
+                        self.fetch_call(None)
+                    );
+                    out.push_str(&arrays);
+                    if !body.is_empty() {
+                        out.push_str("        for row in &mut rows {\n");
+                        out.push_str(&body);
+                        out.push_str("        }\n");
+                    }
+                }
+                out.push_str("        Ok(rows)\n");
+            }
+            Shape::Single if has_root_query => {
+                let mut body = String::new();
+                self.hydrate(&mut body, None, Rows::One("row"), 2);
+                self.fill(&mut body, None, "row", 2);
+                let _ = writeln!(
+                    out,
+                    "        let Some({}row) = {}.await?.into_iter().next() else {{\n            return Ok(None);\n        }};",
+                    if body.is_empty() { "" } else { "mut " },
+                    self.fetch_call(None)
+                );
+                out.push_str(&body);
+                out.push_str("        Ok(Some(row))\n");
+            }
+            _ => {
+                let _ = writeln!(
+                    out,
+                    "        let mut row = {}::default();",
+                    self.type_name(None)
+                );
+                self.hydrate(out, None, Rows::One("row"), 2);
+                self.fill(out, None, "row", 2);
+                if shape == Shape::Single {
+                    out.push_str("        Ok(Some(row))\n");
+                } else {
+                    out.push_str("        Ok(row)\n");
+                }
+            }
+        }
+        out.push_str("    }\n}\n\n");
+    }
+
+    /// `rows` の配列のフィールドを取って埋める文
+    ///
+    /// 親の行に対応付ける配列は全ての行のキーでまとめて取り、`parent_key` が行のキーと等しい要素をその行に入れる
+    fn hydrate(&self, out: &mut String, level: Option<usize>, rows: Rows, indent: usize) {
+        let model = &self.analysis.model;
+        let pad = "    ".repeat(indent);
+        for (index, field) in plan::children(model, level) {
+            let Some(query) = self.queries.iter().find(|q| q.level == Some(index)) else {
+                continue;
+            };
+            let name = snake_case(&field.name);
+            let mut nested = String::new();
+            if query.parent_key.is_none() {
+                let (target, close) = match rows {
+                    Rows::One(row) => (row.to_string(), None),
+                    Rows::Many(rows) => {
+                        let _ = writeln!(out, "{}for row in {}.iter_mut() {{", pad, rows);
+                        ("row".to_string(), Some(format!("{}}}\n", pad)))
+                    }
+                };
+                let inner = if close.is_some() { indent + 1 } else { indent };
+                let _ = writeln!(
+                    out,
+                    "{}{}.{} = {}.await?;",
+                    "    ".repeat(inner),
+                    target,
+                    name,
+                    self.fetch_call(Some(index))
+                );
+                self.hydrate(
+                    out,
+                    Some(index),
+                    Rows::Many(&format!("{}.{}", target, name)),
+                    inner,
+                );
+                out.extend(close);
+                continue;
+            }
+            let items = snake_case(&field.path);
+            self.hydrate(&mut nested, Some(index), Rows::Many(&items), indent);
+            match rows {
+                Rows::One(row) => {
+                    let _ = writeln!(out, "{}let keys = vec![{}.{}_key.clone()];", pad, row, name);
+                }
+                Rows::Many(rows) => {
+                    let _ = writeln!(
+                        out,
+                        "{}let keys: Vec<_> = {}.iter().map(|row| row.{}_key.clone()).collect();",
+                        pad, rows, name
+                    );
+                }
+            }
+            let _ = writeln!(
+                out,
+                "{}let {}{} = {}.await?;",
+                pad,
+                if nested.is_empty() { "" } else { "mut " },
+                items,
+                self.fetch_call(Some(index))
+            );
+            out.push_str(&nested);
+            match rows {
+                // キーが 1 つなら全ての要素がその行のもの
+                Rows::One(row) => {
+                    let _ = writeln!(out, "{}{}.{} = {};", pad, row, name, items);
+                }
+                Rows::Many(rows) => {
+                    let _ = writeln!(
+                        out,
+                        "{pad}for row in {rows}.iter_mut() {{\n\
+                         {pad}    row.{name} = {items}\n\
+                         {pad}        .iter()\n\
+                         {pad}        .filter(|item| item.parent_key == row.{name}_key)\n\
+                         {pad}        .cloned()\n\
+                         {pad}        .collect();\n\
+                         {pad}}}"
+                    );
+                }
+            }
+        }
+    }
+
+    /// `target` の DB から取らないフィールドを埋める文（配列の要素の中も）
+    fn fill(&self, out: &mut String, level: Option<usize>, target: &str, indent: usize) {
+        let analysis = self.analysis;
+        let model = &analysis.model;
+        let pad = "    ".repeat(indent);
+        let usecase = &analysis.document.usecase;
+        let args = self.args();
+        for (index, field) in plan::children(model, level) {
+            let name = snake_case(&field.name);
+            if field.kind == FieldKind::Array {
+                let mut nested = String::new();
+                self.fill(&mut nested, Some(index), "item", indent + 1);
+                if !nested.is_empty() {
+                    let _ = writeln!(out, "{}for item in &mut {}.{} {{", pad, target, name);
+                    out.push_str(&nested);
+                    let _ = writeln!(out, "{}}}", pad);
+                }
+                continue;
+            }
+            if let Some(value) = &field.value {
+                let _ = writeln!(
+                    out,
+                    "{}{}.{} = {};",
+                    pad,
+                    target,
+                    name,
+                    rust_literal(value, plan::field_type(analysis, field).nullable)
+                );
+            } else if let Some(param) = &field.param
+                && let Some(arg) = args.iter().find(|a| &a.name == param)
+            {
+                let value = snake_case(&arg.name);
+                let nullable = plan::field_type(analysis, field).nullable;
+                let value = match (arg.default.is_some() || arg.required, nullable) {
+                    (true, true) => format!("Some({})", passed(&value, arg.value)),
+                    (false, false) => format!("{}.unwrap_or_default()", passed(&value, arg.value)),
+                    _ => passed(&value, arg.value),
+                };
+                let _ = writeln!(out, "{}{}.{} = {};", pad, target, name, value);
+            } else if field.envelope.as_deref() == Some("total")
+                && plan::total_count(usecase) == TotalCount::Window
+                && let Some(collection) = plan::paged_level(model)
+            {
+                // ページの行の `COUNT(*) OVER()` はどの行も同じ件数になる
+                let _ = writeln!(
+                    out,
+                    "{}{}.{} = {}.{}.first().map_or(0, |item| item.total_count);",
+                    pad,
+                    target,
+                    name,
+                    target,
+                    snake_case(&model.fields[collection].name)
+                );
+            } else if field.envelope.as_deref() == Some("total") {
+                let _ = writeln!(
+                    out,
+                    "{}{}.{} = count(pool{}).await?;",
+                    pad,
+                    target,
+                    name,
+                    self.condition_args()
+                );
+            } else if field.envelope.as_deref() == Some("next_cursor")
+                && let Some(page) = plan::filter(usecase, "PAGINATION")
+                && let Some(collection) = plan::paged_level(model)
+                && let Some(query) = self.queries.iter().find(|q| q.level == Some(collection))
+            {
+                // カーソルのカラムを選んでいるフィールドの値を次のカーソルにする
+                let column = plan::cursor_column(page, &query.from);
+                if let Some((cursor_field, _)) = query.columns.iter().find(|(f, _)| {
+                    f.aggregate.is_none()
+                        && f.source.as_ref().is_some_and(|s| {
+                            format!("{}.{}", s.table, s.column) == column
+                                || format!("{}.{}", s.qualifier, s.column) == column
+                        })
+                }) {
+                    let value = format!("item.{}", snake_case(&cursor_field.name));
+                    let value = if plan::field_type(analysis, field).value == ValueType::String {
+                        format!("{}.to_string()", value)
+                    } else {
+                        passed(&value, plan::field_type(analysis, cursor_field).value)
+                    };
+                    let _ = writeln!(
+                        out,
+                        "{}{}.{} = {}.{}.last().map(|item| {});",
+                        pad,
+                        target,
+                        name,
+                        target,
+                        snake_case(&model.fields[collection].name),
+                        value
+                    );
+                }
+            }
+        }
+    }
+
+    /// 条件の引数（`, scope.tenant_id.clone(), status.clone()`）
+    fn condition_args(&self) -> String {
+        self.params
+            .iter()
+            .map(|p| {
+                if p.scope {
+                    format!(
+                        ", {}",
+                        passed(&format!("scope.{}", snake_case(&p.name)), p.value)
+                    )
+                } else {
+                    format!(", {}", passed(&snake_case(&p.name), p.value))
+                }
+            })
+            .collect()
+    }
+
+    fn fetch_name(&self, level: Option<usize>) -> String {
+        match level {
+            None => "fetch".to_string(),
+            Some(index) => format!(
+                "fetch_{}",
+                snake_case(&self.analysis.model.fields[index].path)
+            ),
+        }
+    }
+
+    fn fetch_call(&self, level: Option<usize>) -> String {
+        let keys = if level.is_some_and(|index| self.batched(index)) {
+            ", &keys"
+        } else {
+            ""
+        };
+        let mut call = format!(
+            "{}(pool{}{}",
+            self.fetch_name(level),
+            keys,
+            self.condition_args()
+        );
+        for name in self.column_params(level) {
+            let _ = write!(call, ", {}", passed(&snake_case(&name), ValueType::String));
+        }
+        if self.is_paged(level) {
+            for arg in self.paging_args() {
+                if arg.default.is_some() {
+                    let _ = write!(call, ", {}", snake_case(&arg.name));
+                } else {
+                    let _ = write!(call, ", {}", passed(&snake_case(&arg.name), arg.value));
+                }
+            }
+        }
+        call.push(')');
+        call
+    }
+
+    /// 階層のクエリの列が使う timezone・LOCALIZE のパラメータ
+    fn column_params(&self, level: Option<usize>) -> Vec<String> {
+        let Some(query) = self.queries.iter().find(|q| q.level == level) else {
+            return Vec::new();
+        };
+        let expressions: Vec<&str> = query.columns.iter().map(|(_, e)| e.as_str()).collect();
+        let (_, binds) = plan::bind_params(&expressions.join(", "));
+        self.select_params
+            .iter()
+            .filter(|name| binds.contains(name))
+            .cloned()
+            .collect()
+    }
+
+    /// 親の行のキーでまとめて取る配列か
+    fn batched(&self, index: usize) -> bool {
+        plan::hydration(&self.analysis.document.usecase) == Hydration::Batch
+            && plan::parent_key(self.analysis, index).is_some()
+    }
+
+    /// ページネーション・ソートを付ける階層か。collection が無ければルート
+    fn is_paged(&self, level: Option<usize>) -> bool {
+        match self.shape() {
+            Shape::Collection => plan::paged_level(&self.analysis.model) == level,
+            Shape::List => level.is_none(),
+            Shape::Single => false,
+        }
+    }
+
+    /// ページネーション・ソートの引数
+    fn paging_args(&self) -> Vec<Arg> {
+        let names: Vec<&str> = self
+            .params
+            .iter()
+            .filter(|p| !p.scope)
+            .map(|p| p.name.as_str())
+            .chain(self.select_params.iter().map(String::as_str))
+            .collect();
+        self.args()
+            .into_iter()
+            .filter(|a| !names.contains(&a.name.as_str()))
+            .collect()
+    }
+
+    /// 条件の引数の宣言（`, tenant_id: String, status: Option<String>`）
+    fn condition_signature(&self) -> String {
+        self.params
+            .iter()
+            .map(|p| {
+                if p.required || p.default.is_some() {
+                    format!(", {}: {}", snake_case(&p.name), rust_type(p.value))
+                } else {
+                    format!(", {}: Option<{}>", snake_case(&p.name), rust_type(p.value))
+                }
+            })
+            .collect()
+    }
+
+    fn push_conditions(&self, out: &mut String) {
+        let _ = writeln!(
+            out,
+            "fn push_conditions(query: &mut QueryBuilder<'_, Postgres>{}) {{",
+            self.condition_signature()
+        );
+        if self.conditions.is_empty() {
+            out.push_str("    let _ = query;\n");
+        }
+        let mut remaining: BTreeMap<&String, usize> = BTreeMap::new();
+        for bind in self.conditions.iter().flat_map(|c| &c.binds) {
+            *remaining.entry(bind).or_default() += 1;
+        }
+        for condition in &self.conditions {
+            let (pad, close) = match &condition.optional_param {
+                Some(param) => {
+                    let name = snake_case(param);
+                    let _ = writeln!(out, "    if let Some({}) = {} {{", name, name);
+                    ("        ", "    }\n")
+                }
+                None => ("    ", ""),
+            };
+            let mut parts: Vec<String> = condition
+                .sql
+                .split('?')
+                .map(|part| part.replace('"', "\\\""))
+                .collect();
+            if let Some(last) = parts.last_mut() {
+                last.push(')');
+            }
+            let _ = write!(out, "{}query.push(\" AND ({}\")", pad, parts[0]);
+            for (bind, part) in condition.binds.iter().zip(&parts[1..]) {
+                // 後の条件でも使う値は複製して渡す
+                let count = remaining.entry(bind).or_default();
+                *count -= 1;
+                let name = snake_case(bind);
+                let value = self
+                    .params
+                    .iter()
+                    .find(|p| &p.name == bind)
+                    .map_or(ValueType::String, |p| p.value);
+                if *count > 0 {
+                    let _ = write!(out, ".push_bind({})", passed(&name, value));
+                } else {
+                    let _ = write!(out, ".push_bind({})", name);
+                }
+                if !part.is_empty() {
+                    let _ = write!(out, ".push(\"{}\")", part);
+                }
+            }
+            out.push_str(";\n");
+            out.push_str(close);
+        }
+        out.push_str("}\n\n");
+    }
+
+    /// SELECT ... FROM ... JOIN ... WHERE TRUE
+    fn select_sql(&self, query: &Query, select: &str) -> String {
+        format!(
+            "{} {} WHERE TRUE",
+            select,
+            plan::from_sql(&self.analysis.model, query)
+        )
+        .replace('"', "\\\"")
+    }
+
+    fn fetch_function(&self, out: &mut String, query: &Query) {
+        let usecase = &self.analysis.document.usecase;
+        let paged = self.is_paged(query.level);
+        let _ = write!(
+            out,
+            "async fn {}(\n    pool: &PgPool{}{}",
+            self.fetch_name(query.level),
+            match &query.parent_key {
+                Some(key) => format!(",\n    keys: &[{}]", rust_type(key.value)),
+                None => String::new(),
+            },
+            self.condition_signature().replace(", ", ",\n    ")
+        );
+        for name in self.column_params(query.level) {
+            let _ = write!(out, ",\n    {}: Option<String>", snake_case(&name));
+        }
+        if paged {
+            for arg in self.paging_args() {
+                let rust_type = rust_type(arg.value);
+                if arg.default.is_some() {
+                    let _ = write!(out, ",\n    {}: {}", snake_case(&arg.name), rust_type);
+                } else {
+                    let _ = write!(
+                        out,
+                        ",\n    {}: Option<{}>",
+                        snake_case(&arg.name),
+                        rust_type
+                    );
+                }
+            }
+        }
+        let _ = writeln!(
+            out,
+            ",\n) -> sqlx::Result<Vec<{}>> {{",
+            self.type_name(query.level)
+        );
+        let mut columns: Vec<String> = query
+            .parent_key
+            .iter()
+            .map(|key| format!("{} AS parent_key", key.column))
+            .collect();
+        columns.extend(
+            query.columns.iter().map(|(field, expression)| {
+                format!("{} AS {}", expression, snake_case(&field.name))
+            }),
+        );
+        columns.extend(
+            query
+                .keys
+                .iter()
+                .map(|(field, key)| format!("{} AS {}_key", key.column, snake_case(&field.name))),
+        );
+        if query.window_total {
+            columns.push("COUNT(*) OVER() AS total_count".to_string());
+        }
+        let select = format!(
+            "SELECT {}{}",
+            if usecase.distinct { "DISTINCT " } else { "" },
+            columns.join(", ")
+        );
+        // timezone の :name は列の式の中で束縛する
+        let (sql, binds) = plan::bind_params(&self.select_sql(query, &select));
+        let mut parts = sql.split('?');
+        let _ = writeln!(
+            out,
+            "    let mut query = QueryBuilder::<Postgres>::new(\n        \"{}\",\n    );",
+            parts.next().unwrap_or_default()
+        );
+        for (position, (bind, part)) in binds.iter().zip(parts).enumerate() {
+            // 後の列や条件でも使う値は複製して渡す
+            let name = snake_case(bind);
+            let condition = self.params.iter().find(|p| &p.name == bind);
+            let value = if condition.is_some() || binds[position + 1..].contains(bind) {
+                passed(&name, condition.map_or(ValueType::String, |p| p.value))
+            } else {
+                name
+            };
+            let _ = writeln!(out, "    query.push_bind({}).push(\"{}\");", value, part);
+        }
+        let _ = writeln!(
+            out,
+            "    push_conditions(&mut query{});",
+            self.condition_call()
+        );
+        if let Some(key) = &query.parent_key {
+            let _ = writeln!(
+                out,
+                "    query\n        .push(\" AND {} = ANY(\")\n        .push_bind(keys.to_vec())\n        .push(\")\");",
+                key.column
+            );
+        }
+        if paged {
+            self.push_cursor(out, query);
+        }
+        if !query.group_by.is_empty() {
+            let _ = writeln!(
+                out,
+                "    query.push(\" GROUP BY {}\");",
+                query.group_by.join(", ")
+            );
+        }
+        if paged {
+            self.push_paging(out, query);
+        } else if query.level.is_none()
+            && let Some(limit) = usecase.limit.or(usecase.default_limit)
+        {
+            let _ = writeln!(out, "    query.push(\" LIMIT {}\");", limit);
+        }
+        out.push_str("    query.build_query_as().fetch_all(pool).await\n}\n\n");
+    }
+
+    /// `, tenant_id, status`
+    fn condition_call(&self) -> String {
+        self.params
+            .iter()
+            .map(|p| format!(", {}", snake_case(&p.name)))
+            .collect()
+    }
+
+    fn push_cursor(&self, out: &mut String, query: &Query) {
+        let usecase = &self.analysis.document.usecase;
+        if let Some(page) = plan::filter(usecase, "PAGINATION")
+            && page.strategy.as_deref() == Some("cursor")
+        {
+            let name = snake_case(&page.param);
+            let keyset = plan::keyset(page, &query.from);
+            let parts: Vec<&str> = keyset.condition.split('?').collect();
+            match plan::tiebreaker_param(page) {
+                Some(tiebreaker) => {
+                    let tiebreaker = snake_case(&tiebreaker);
+                    let _ = writeln!(
+                        out,
+                        "    if let (Some({}), Some({})) = ({}, {}) {{\n        query\n            .push(\" AND {}\")\n            .push_bind({})\n            .push(\"{}\")\n            .push_bind({})\n            .push(\"{}\");\n    }}",
+                        name,
+                        tiebreaker,
+                        name,
+                        tiebreaker,
+                        parts[0],
+                        name,
+                        parts[1],
+                        tiebreaker,
+                        parts[2]
+                    );
+                }
+                None => {
+                    let _ = writeln!(
+                        out,
+                        "    if let Some({}) = {} {{\n        query.push(\" AND {}\").push_bind({});\n    }}",
+                        name, name, parts[0], name
+                    );
+                }
+            }
+        }
+    }
+
+    fn push_paging(&self, out: &mut String, query: &Query) {
+        let usecase = &self.analysis.document.usecase;
+        if let Some(sort) = plan::filter(usecase, "ORDER_BY") {
+            let direction = match sort.default_direction.as_deref() {
+                Some(d) if d.eq_ignore_ascii_case("ASC") => "ASC",
+                _ => "DESC",
+            };
+            match &sort.allowed_columns {
+                Some(allowed) => {
+                    let default = sort
+                        .default_column
+                        .clone()
+                        .or_else(|| allowed.first().cloned())
+                        .unwrap_or_default();
+                    let allowed: Vec<String> =
+                        allowed.iter().map(|c| format!("\"{}\"", c)).collect();
+                    let _ = writeln!(
+                        out,
+                        "    let sort_column = match {}.as_deref() {{\n        Some(column @ ({})) => column,\n        _ => \"{}\",\n    }};",
+                        snake_case(&sort.param),
+                        allowed.join(" | "),
+                        default
+                    );
+                    let _ = writeln!(
+                        out,
+                        "    query.push(\" ORDER BY \").push(sort_column).push(\" {}\");",
+                        direction
+                    );
+                }
+                None => {
+                    if let Some(column) = &sort.default_column {
+                        let _ = writeln!(
+                            out,
+                            "    query.push(\" ORDER BY {} {}\");",
+                            column, direction
+                        );
+                    }
+                }
+            }
+        }
+        let Some(page) = plan::filter(usecase, "PAGINATION") else {
+            return;
+        };
+        let page_size = match &page.limit_param {
+            Some(limit) => match page.max_page_size {
+                Some(max) => format!("{}.min({})", snake_case(limit), max),
+                None => snake_case(limit),
+            },
+            None => format!("{}_i32", plan::page_size(usecase, page)),
+        };
+        let _ = writeln!(out, "    let page_size = i64::from({});", page_size);
+        if page.strategy.as_deref() == Some("cursor") {
+            let _ = writeln!(
+                out,
+                "    query.push(\" ORDER BY {} LIMIT \").push_bind(page_size);",
+                plan::keyset(page, &query.from).order_by()
+            );
+        } else {
+            let _ = writeln!(
+                out,
+                "    query.push(\" LIMIT \").push_bind(page_size);\n    query\n        .push(\" OFFSET \")\n        .push_bind((i64::from({}) - 1) * page_size);",
+                snake_case(&page.param)
+            );
+        }
+    }
+
+    /// `envelope: total` に入れる件数を数える関数
+    fn count_function(&self, out: &mut String) {
+        let model = &self.analysis.model;
+        let Some(collection) = plan::paged_level(model) else {
+            return;
+        };
+        let Some(query) = self.queries.iter().find(|q| q.level == Some(collection)) else {
+            return;
+        };
+        let _ = writeln!(
+            out,
+            "async fn count(\n    pool: &PgPool{},\n) -> sqlx::Result<i64> {{",
+            self.condition_signature().replace(", ", ",\n    ")
+        );
+        let _ = writeln!(
+            out,
+            "    let mut query = QueryBuilder::<Postgres>::new(\n        \"{}\",\n    );",
+            self.select_sql(query, "SELECT COUNT(*)")
+        );
+        let _ = writeln!(
+            out,
+            "    push_conditions(&mut query{});",
+            self.condition_call()
+        );
+        out.push_str("    query.build_query_scalar().fetch_one(pool).await\n}\n\n");
+    }
+}
+
+/// 値を渡す式。Copy でない型は複製する
+fn passed(expression: &str, value: ValueType) -> String {
+    match value {
+        ValueType::String | ValueType::Any => format!("{}.clone()", expression),
+        _ => expression.to_string(),
+    }
+}
+
+fn push_scalar(scalars: &mut Vec<&'static str>, scalar: &'static str) {
+    let builtin = ["Int", "Float", "String", "Boolean", "ID"];
+    if !builtin.contains(&scalar) && !scalars.contains(&scalar) {
+        scalars.push(scalar);
+    }
+}
+
+/// async-graphql が SDL に出力する型名
+fn graphql_type(value: ValueType) -> &'static str {
+    match value {
+        ValueType::Int | ValueType::Long => "Int",
+        ValueType::Float | ValueType::Double => "Float",
+        ValueType::Decimal => "Decimal",
+        ValueType::Boolean => "Boolean",
+        ValueType::String => "String",
+        ValueType::Uuid => "UUID",
+        ValueType::Date => "NaiveDate",
+        ValueType::Time => "NaiveTime",
+        ValueType::DateTime => "NaiveDateTime",
+        ValueType::OffsetDateTime => "DateTime",
+        ValueType::Any => "JSON",
+    }
+}
+
+fn rust_type(value: ValueType) -> &'static str {
+    match value {
+        ValueType::Int => "i32",
+        ValueType::Long => "i64",
+        ValueType::Float => "f32",
+        ValueType::Double => "f64",
+        ValueType::Decimal => "rust_decimal::Decimal",
+        ValueType::Boolean => "bool",
+        ValueType::String => "String",
+        ValueType::Uuid => "uuid::Uuid",
+        ValueType::Date => "chrono::NaiveDate",
+        ValueType::Time => "chrono::NaiveTime",
+        ValueType::DateTime => "chrono::NaiveDateTime",
+        ValueType::OffsetDateTime => "chrono::DateTime<chrono::Utc>",
+        ValueType::Any => "async_graphql::Json<serde_json::Value>",
+    }
+}
+
+/// 引数の既定値（GraphQL・Rust 共通の表記）
+fn graphql_literal(value: &Literal) -> String {
+    match value {
+        Literal::Bool(b) => b.to_string(),
+        Literal::Integer(i) => i.to_string(),
+        Literal::Number(n) => format!("{:?}", n),
+        Literal::String(s) => format!("{:?}", s),
+    }
+}
+
+fn rust_literal(value: &Literal, nullable: bool) -> String {
+    let literal = match value {
+        Literal::Bool(b) => b.to_string(),
+        Literal::Integer(i) => i.to_string(),
+        Literal::Number(n) => format!("{:?}", n),
+        Literal::String(s) => format!("{:?}.to_string()", s),
+    };
+    if nullable {
+        format!("Some({})", literal)
+    } else {
+        literal
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{api, parser};
+
+    const DOC: &str = r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["posts"]
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: 投稿フィード
+  response_mapping:
+    - field: items
+      type: array
+      collection: true
+      source_table: posts
+      fields:
+        - field: id
+          source: posts.id
+        - field: authorName
+          source: users.name
+          join:
+            table: users
+            on: posts.user_id = users.id
+    - field: total
+      envelope: total
+  filters:
+    - param: status
+      maps_to: WHERE
+      condition: posts.status = :status
+    - param: page
+      maps_to: PAGINATION
+      strategy: offset
+      page_size: 20
+  authorization:
+    row_filters:
+      - posts.tenant_id = :tenant_id
+"#;
+
+    #[test]
+    fn test_sdl() {
+        let analysis = api::analyze(parser::parse(DOC).unwrap());
+        let sdl = sdl("posts-feed.usml.yaml", &analysis).unwrap();
+        assert!(sdl.contains(
+            "type PostsFeedResponse {\n  items: [PostsFeedItemsItem!]!\n  total: Int!\n}"
+        ));
+        assert!(sdl.contains("type PostsFeedItemsItem {\n  id: String!\n  authorName: String!\n}"));
+        assert!(sdl.contains(
+            "extend type Query {\n  postsFeed(status: String, page: Int! = 1): PostsFeedResponse!\n}"
+        ));
+    }
+
+    #[test]
+    fn test_resolver() {
+        let analysis = api::analyze(parser::parse(DOC).unwrap());
+        let code = resolver("posts-feed.usml.yaml", &analysis).unwrap();
+        assert!(code.contains("pub struct PostsFeedScope {\n    pub tenant_id: String,\n}"));
+        assert!(code.contains("        row.items = fetch_items(pool, scope.tenant_id.clone(), status.clone(), page).await?;"));
+        assert!(code.contains(
+            "        row.total = count(pool, scope.tenant_id.clone(), status.clone()).await?;"
+        ));
+        assert!(code.contains(
+            "\"SELECT posts.id AS id, users.name AS author_name FROM posts LEFT JOIN users ON posts.user_id = users.id WHERE TRUE\""
+        ));
+        assert!(code.contains(
+            "    if let Some(status) = status {\n        query.push(\" AND (posts.status = \").push_bind(status).push(\")\");\n    }"
+        ));
+        assert!(code.contains(
+            "    query.push(\" AND (posts.tenant_id = \").push_bind(tenant_id).push(\")\");"
+        ));
+    }
+    #[test]
+    fn test_window_total_count() {
+        let doc = DOC.replace(
+            "      page_size: 20\n",
+            "      page_size: 20\n      total_count: window\n",
+        );
+        let analysis = api::analyze(parser::parse(&doc).unwrap());
+        let code = resolver("posts-feed.usml.yaml", &analysis).unwrap();
+        assert!(code.contains("    #[graphql(skip)]\n    pub total_count: i64,\n}"));
+        assert!(
+            code.contains(
+                "        row.total = row.items.first().map_or(0, |item| item.total_count);"
+            )
+        );
+        assert!(
+            code.contains("users.name AS author_name, COUNT(*) OVER() AS total_count FROM posts")
+        );
+        assert!(!code.contains("async fn count("));
+    }
+
+    #[test]
+    fn test_timezone_parameter_is_bound_in_select() {
+        let doc = DOC.replace(
+            "          source: posts.id\n",
+            "          source: posts.id\n        - field: createdAt\n          source: posts.created_at\n          timezone: \":tz\"\n",
+        );
+        let analysis = api::analyze(parser::parse(&doc).unwrap());
+        assert!(
+            sdl("posts-feed.usml.yaml", &analysis)
+                .unwrap()
+                .contains("postsFeed(status: String, page: Int! = 1, tz: String)")
+        );
+        let code = resolver("posts-feed.usml.yaml", &analysis).unwrap();
+        assert!(code.contains(
+            "        row.items = fetch_items(pool, scope.tenant_id.clone(), status.clone(), tz.clone(), page).await?;"
+        ));
+        assert!(
+            code.contains("    status: Option<String>,\n    tz: Option<String>,\n    page: i32,\n")
+        );
+        assert!(code.contains(
+            "\"SELECT posts.id AS id, (posts.created_at AT TIME ZONE 'UTC' AT TIME ZONE COALESCE(\",\n    );\n\
+             \x20   query.push_bind(tz).push(\", 'UTC')) AS created_at, users.name AS author_name FROM posts"
+        ));
+    }
+
+    #[test]
+    fn test_required_and_default_arguments() {
+        let doc = DOC.replace(
+            "      condition: posts.status = :status\n",
+            "      condition: posts.status = :status\n      default: published\n    - param: author_id\n      maps_to: WHERE\n      condition: posts.user_id = :author_id\n      required: true\n",
+        );
+        let analysis = api::analyze(parser::parse(&doc).unwrap());
+        assert!(sdl("posts-feed.usml.yaml", &analysis).unwrap().contains(
+            "postsFeed(authorId: String!, status: String! = \"published\", page: Int! = 1)"
+        ));
+        let code = resolver("posts-feed.usml.yaml", &analysis).unwrap();
+        assert!(code.contains(
+            "        author_id: String,\n        #[graphql(default = \"published\")] status: String,\n"
+        ));
+        assert!(code.contains(
+            "fn push_conditions(query: &mut QueryBuilder<'_, Postgres>, author_id: String, tenant_id: String, status: String) {"
+        ));
+        assert!(code.contains(
+            "    query.push(\" AND (posts.status = \").push_bind(status).push(\")\");\n"
+        ));
+    }
+
+    #[test]
+    fn test_batched_arrays_are_fetched_once() {
+        let analysis = api::analyze(
+            parser::parse(
+                r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["users"]
+    - ./schema.dbml#tables["posts"]
+usecase:
+  name: ユーザー一覧
+  response_mapping:
+    - field: id
+      source: users.id
+    - field: posts
+      type: array
+      source_table: posts
+      join:
+        table: posts
+        type: LEFT
+        on: users.id = posts.user_id
+      fields:
+        - field: title
+          source: posts.title
+  filters:
+    - param: page
+      maps_to: PAGINATION
+      strategy: offset
+      page_size: 20
+"#,
+            )
+            .unwrap(),
+        );
+        let code = resolver("users-list.usml.yaml", &analysis).unwrap();
+        assert!(code.contains("    #[graphql(skip)]\n    pub posts_key: String,\n}"));
+        assert!(code.contains(
+            "        let keys: Vec<_> = rows.iter().map(|row| row.posts_key.clone()).collect();\n\
+             \x20       let posts = fetch_posts(pool, &keys).await?;\n\
+             \x20       for row in rows.iter_mut() {\n\
+             \x20           row.posts = posts\n\
+             \x20               .iter()\n\
+             \x20               .filter(|item| item.parent_key == row.posts_key)\n\
+             \x20               .cloned()\n\
+             \x20               .collect();\n\
+             \x20       }\n"
+        ));
+        assert!(code.contains(
+            "\"SELECT users.id AS parent_key, posts.title AS title FROM users INNER JOIN posts ON users.id = posts.user_id WHERE TRUE\""
+        ));
+        assert!(code.contains(
+            "    query\n        .push(\" AND users.id = ANY(\")\n        .push_bind(keys.to_vec())\n        .push(\")\");"
+        ));
+    }
+}
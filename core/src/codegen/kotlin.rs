@@ -0,0 +1,620 @@
+//! Kotlin のレスポンスの data class と、jOOQ で結合・フィルタを組み立てるクエリを生成する
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use crate::api::Analysis;
+use crate::ast::Literal;
+use crate::export;
+use crate::model::{FieldKind, JoinKind};
+
+use super::plan::{self, PlanError, Query, ValueType};
+use super::{camel_case, pascal_case};
+
+/// `file` の解析結果から Kotlin のソースファイルを生成する。クラス名はファイル名から付ける
+pub fn generate(
+    file: &str,
+    analysis: &Analysis,
+    package: Option<&str>,
+) -> Result<String, PlanError> {
+    let queries = plan::queries(analysis)?;
+    let mut kotlin = Kotlin {
+        analysis,
+        name: pascal_case(&export::identifier(file)),
+        imports: BTreeSet::new(),
+        body: String::new(),
+    };
+    for level in plan::levels(&analysis.model) {
+        kotlin.data_class(level);
+    }
+    kotlin.query_object(&queries);
+
+    let mut out = format!(
+        "// usml generate --backend kotlin で {} から生成（{}）\n",
+        file, analysis.document.usecase.name
+    );
+    if let Some(package) = package {
+        let _ = writeln!(out, "package {}\n", package);
+    }
+    for import in &kotlin.imports {
+        let _ = writeln!(out, "import {}", import);
+    }
+    out.push('\n');
+    out.push_str(&kotlin.body);
+    Ok(out)
+}
+
+/// クエリ関数が受け取るパラメータ
+struct Param {
+    name: String,
+    kotlin_type: String,
+    /// 省略時の値（`= null` など）
+    default: Option<String>,
+}
+
+struct Kotlin<'a> {
+    analysis: &'a Analysis,
+    name: String,
+    imports: BTreeSet<&'static str>,
+    body: String,
+}
+
+impl Kotlin<'_> {
+    fn class_name(&self, level: Option<usize>) -> String {
+        match level {
+            None => format!("{}Response", self.name),
+            Some(index) => format!(
+                "{}{}Item",
+                self.name,
+                pascal_case(&self.analysis.model.fields[index].path)
+            ),
+        }
+    }
+
+    fn data_class(&mut self, level: Option<usize>) {
+        let analysis = self.analysis;
+        let mut properties = Vec::new();
+        for (index, field) in plan::children(&analysis.model, level) {
+            let mut line = format!("    val {}: ", camel_case(&field.name));
+            if field.kind == FieldKind::Array {
+                line.push_str(&format!("List<{}>", self.class_name(Some(index))));
+            } else {
+                let field_type = plan::field_type(analysis, field);
+                line.push_str(self.kotlin_type(field_type.value));
+                if field_type.nullable {
+                    line.push('?');
+                }
+                if let Some(value) = &field.value {
+                    line.push_str(&format!(" = {}", kotlin_literal(value)));
+                }
+            }
+            line.push(',');
+            properties.push(line);
+        }
+        if level.is_none()
+            && let Some(summary) = &analysis.document.usecase.summary
+        {
+            let _ = writeln!(self.body, "/** {} */", summary);
+        }
+        let _ = writeln!(self.body, "data class {}(", self.class_name(level));
+        for line in properties {
+            let _ = writeln!(self.body, "{}", line);
+        }
+        self.body.push_str(")\n\n");
+    }
+
+    fn kotlin_type(&mut self, value: ValueType) -> &'static str {
+        let path = match value {
+            ValueType::Int => "Int",
+            ValueType::Long => "Long",
+            ValueType::Float => "Float",
+            ValueType::Double => "Double",
+            ValueType::Decimal => "java.math.BigDecimal",
+            ValueType::Boolean => "Boolean",
+            ValueType::String => "String",
+            ValueType::Uuid => "java.util.UUID",
+            ValueType::Date => "java.time.LocalDate",
+            ValueType::Time => "java.time.LocalTime",
+            ValueType::DateTime => "java.time.LocalDateTime",
+            ValueType::OffsetDateTime => "java.time.OffsetDateTime",
+            ValueType::Any => "Any",
+        };
+        if path.contains('.') {
+            self.imports.insert(path);
+        }
+        path.rsplit('.').next().unwrap_or(path)
+    }
+
+    fn query_object(&mut self, queries: &[Query]) {
+        for import in [
+            "org.jooq.Condition",
+            "org.jooq.DSLContext",
+            "org.jooq.Record",
+            "org.jooq.Result",
+            "org.jooq.impl.DSL",
+        ] {
+            self.imports.insert(import);
+        }
+        let analysis = self.analysis;
+        let model = &analysis.model;
+        let conditions = plan::conditions(&analysis.document);
+        let condition_params: Vec<Param> = plan::condition_params(analysis, &conditions)
+            .into_iter()
+            .map(|p| {
+                let kotlin_type = self.kotlin_type(p.value);
+                let optional = !p.required && p.default.is_none();
+                Param {
+                    name: camel_case(&p.name),
+                    kotlin_type: if optional {
+                        format!("{}?", kotlin_type)
+                    } else {
+                        kotlin_type.to_string()
+                    },
+                    default: match &p.default {
+                        Some(value) => Some(kotlin_literal(value)),
+                        None => optional.then(|| "null".to_string()),
+                    },
+                }
+            })
+            .collect();
+
+        let mut functions = String::new();
+        for query in queries {
+            let function_name = match query.level {
+                None => "fetch".to_string(),
+                Some(index) => format!("fetch{}", pascal_case(&model.fields[index].path)),
+            };
+            self.query_function(&mut functions, &function_name, query, &condition_params);
+        }
+
+        let _ = writeln!(self.body, "object {}Query {{", self.name);
+        let signature: Vec<String> = condition_params
+            .iter()
+            .map(|p| format!("{}: {}", p.name, p.kotlin_type))
+            .collect();
+        let _ = writeln!(
+            self.body,
+            "    private fun conditions({}): List<Condition> {{",
+            signature.join(", ")
+        );
+        let _ = writeln!(
+            self.body,
+            "        val conditions = mutableListOf<Condition>()"
+        );
+        for condition in &conditions {
+            let args: String = condition
+                .binds
+                .iter()
+                .map(|b| format!(", {}", camel_case(b)))
+                .collect();
+            let line = format!(
+                "conditions += DSL.condition(\"{}\"{})",
+                condition.sql.replace('"', "\\\""),
+                args
+            );
+            match &condition.optional_param {
+                Some(param) => {
+                    let _ = writeln!(
+                        self.body,
+                        "        if ({} != null) {}",
+                        camel_case(param),
+                        line
+                    );
+                }
+                None => {
+                    let _ = writeln!(self.body, "        {}", line);
+                }
+            }
+        }
+        let _ = writeln!(self.body, "        return conditions\n    }}");
+        self.body.push_str(&functions);
+        self.body.push_str("}\n");
+    }
+
+    fn query_function(
+        &mut self,
+        out: &mut String,
+        name: &str,
+        query: &Query,
+        condition_params: &[Param],
+    ) {
+        let usecase = &self.analysis.document.usecase;
+        let mut params: Vec<Param> = condition_params
+            .iter()
+            .map(|p| Param {
+                name: p.name.clone(),
+                kotlin_type: p.kotlin_type.clone(),
+                default: p.default.clone(),
+            })
+            .collect();
+        // 列の式の timezone・LOCALIZE のパラメータ（省略可）
+        let expressions: Vec<&str> = query.columns.iter().map(|(_, e)| e.as_str()).collect();
+        for bind in plan::bind_params(&expressions.join(", ")).1 {
+            let name = camel_case(&bind);
+            if !params.iter().any(|p| p.name == name) {
+                params.push(Param {
+                    name,
+                    kotlin_type: "String?".to_string(),
+                    default: Some("null".to_string()),
+                });
+            }
+        }
+        if let Some(key) = &query.parent_key {
+            let kotlin_type = self.kotlin_type(key.value);
+            params.insert(
+                0,
+                Param {
+                    name: "parentKeys".to_string(),
+                    kotlin_type: format!("Collection<{}>", kotlin_type),
+                    default: None,
+                },
+            );
+        }
+        let mut tail = Vec::new();
+        let mut cursor = None;
+        if query.paged {
+            self.paging(&query.from, &mut params, &mut tail, &mut cursor);
+        } else if query.level.is_none()
+            && let Some(limit) = usecase.limit.or(usecase.default_limit)
+        {
+            tail.push(format!(".limit({})", limit));
+        }
+
+        let _ = writeln!(out, "\n    fun {}(\n        dsl: DSLContext,", name);
+        for param in &params {
+            let _ = write!(out, "        {}: {}", param.name, param.kotlin_type);
+            if let Some(default) = &param.default {
+                let _ = write!(out, " = {}", default);
+            }
+            out.push_str(",\n");
+        }
+        let _ = writeln!(out, "    ): Result<Record> {{");
+        if query.paged {
+            for line in self.paging_prelude(&params) {
+                let _ = writeln!(out, "        {}", line);
+            }
+        }
+        let select = if usecase.distinct {
+            "selectDistinct"
+        } else {
+            "select"
+        };
+        let _ = writeln!(out, "        return dsl.{}(", select);
+        if let Some(key) = &query.parent_key {
+            let _ = writeln!(
+                out,
+                "            DSL.field(\"{}\").`as`(\"parent_key\"),",
+                key.column
+            );
+        }
+        for (field, expression) in &query.columns {
+            let (sql, binds) = plan::bind_params(expression);
+            let args: String = binds
+                .iter()
+                .map(|b| format!(", {}", camel_case(b)))
+                .collect();
+            let _ = writeln!(
+                out,
+                "            DSL.field(\"{}\"{}).`as`(\"{}\"),",
+                sql, args, field.name
+            );
+        }
+        for (field, key) in &query.keys {
+            let _ = writeln!(
+                out,
+                "            DSL.field(\"{}\").`as`(\"{}_key\"),",
+                key.column, field.name
+            );
+        }
+        let _ = writeln!(out, "        )");
+        let model = &self.analysis.model;
+        let _ = writeln!(
+            out,
+            "            .from(DSL.table(\"{}\"))",
+            plan::table_sql(model, &query.from, None)
+        );
+        for join in &query.joins {
+            let method = match join.kind {
+                JoinKind::Inner => "join",
+                JoinKind::Left => "leftJoin",
+                JoinKind::Right => "rightJoin",
+            };
+            let db_name = model.db_table(&join.table);
+            let table = match join
+                .alias
+                .as_deref()
+                .or((db_name != join.table).then_some(join.table.as_str()))
+            {
+                Some(alias) => format!("DSL.table(\"{}\").`as`(\"{}\")", db_name, alias),
+                None => format!("DSL.table(\"{}\")", db_name),
+            };
+            let _ = writeln!(
+                out,
+                "            .{}({}).on(\"{}\")",
+                method, table, join.on
+            );
+        }
+        let condition_args: Vec<&str> = condition_params.iter().map(|p| p.name.as_str()).collect();
+        let mut condition = format!("conditions({})", condition_args.join(", "));
+        if let Some(cursor) = cursor {
+            condition.push_str(&format!(" + listOfNotNull({})", cursor));
+        }
+        if let Some(key) = &query.parent_key {
+            let _ = write!(
+                condition,
+                " + DSL.field(\"{}\").`in`(parentKeys)",
+                key.column
+            );
+        }
+        let _ = writeln!(out, "            .where({})", condition);
+        if !query.group_by.is_empty() {
+            let group_by: Vec<String> = query
+                .group_by
+                .iter()
+                .map(|c| format!("DSL.field(\"{}\")", c))
+                .collect();
+            let _ = writeln!(out, "            .groupBy({})", group_by.join(", "));
+        }
+        for line in tail {
+            let _ = writeln!(out, "            {}", line);
+        }
+        let _ = writeln!(out, "            .fetch()\n    }}");
+    }
+
+    /// ページネーション・ソートのパラメータと、クエリの末尾に付ける呼び出し。
+    /// カーソルの条件は `cursor` に入れる
+    fn paging(
+        &mut self,
+        from: &str,
+        params: &mut Vec<Param>,
+        tail: &mut Vec<String>,
+        cursor: &mut Option<String>,
+    ) {
+        let analysis = self.analysis;
+        let usecase = &analysis.document.usecase;
+        if let Some(sort) = plan::filter(usecase, "ORDER_BY") {
+            let direction = match sort.default_direction.as_deref() {
+                Some(d) if d.eq_ignore_ascii_case("ASC") => "asc",
+                _ => "desc",
+            };
+            if sort.allowed_columns.is_some() {
+                params.push(Param {
+                    name: camel_case(&sort.param),
+                    kotlin_type: "String?".to_string(),
+                    default: Some("null".to_string()),
+                });
+                tail.push(format!(".orderBy(DSL.field(sortColumn).{}())", direction));
+            } else if let Some(column) = &sort.default_column {
+                tail.push(format!(
+                    ".orderBy(DSL.field(\"{}\").{}())",
+                    column, direction
+                ));
+            }
+        }
+        let Some(page) = plan::filter(usecase, "PAGINATION") else {
+            if let Some(limit) = usecase.limit.or(usecase.default_limit) {
+                tail.push(format!(".limit({})", limit));
+            }
+            return;
+        };
+        let page_param = camel_case(&page.param);
+        if let Some(limit_param) = &page.limit_param {
+            params.push(Param {
+                name: camel_case(limit_param),
+                kotlin_type: "Int".to_string(),
+                default: Some(plan::page_size(usecase, page).to_string()),
+            });
+        }
+        if page.strategy.as_deref() == Some("cursor") {
+            let cursor_type = self.kotlin_type(plan::parameter_type(analysis, &page.param));
+            params.push(Param {
+                name: page_param.clone(),
+                kotlin_type: format!("{}?", cursor_type),
+                default: Some("null".to_string()),
+            });
+            let keyset = plan::keyset(page, from);
+            *cursor = Some(match plan::tiebreaker_param(page) {
+                Some(tiebreaker) => {
+                    let tiebreaker_param = camel_case(&tiebreaker);
+                    let tiebreaker_type =
+                        self.kotlin_type(plan::parameter_type(analysis, &tiebreaker));
+                    params.push(Param {
+                        name: tiebreaker_param.clone(),
+                        kotlin_type: format!("{}?", tiebreaker_type),
+                        default: Some("null".to_string()),
+                    });
+                    format!(
+                        "if ({} != null && {} != null) DSL.condition(\"{}\", {}, {}) else null",
+                        page_param,
+                        tiebreaker_param,
+                        keyset.condition,
+                        page_param,
+                        tiebreaker_param
+                    )
+                }
+                None => format!(
+                    "{}?.let {{ DSL.condition(\"{}\", it) }}",
+                    page_param, keyset.condition
+                ),
+            });
+            let order_by: Vec<String> = keyset
+                .columns
+                .iter()
+                .map(|c| format!("DSL.field(\"{}\").desc()", c))
+                .collect();
+            tail.push(format!(".orderBy({})", order_by.join(", ")));
+            tail.push(".limit(pageSize)".to_string());
+        } else {
+            params.push(Param {
+                name: page_param.clone(),
+                kotlin_type: "Int".to_string(),
+                default: Some("1".to_string()),
+            });
+            tail.push(".limit(pageSize)".to_string());
+            tail.push(format!(".offset(({} - 1) * pageSize)", page_param));
+        }
+    }
+
+    /// ページサイズ・ソートカラムを決める文
+    fn paging_prelude(&self, params: &[Param]) -> Vec<String> {
+        let usecase = &self.analysis.document.usecase;
+        let mut lines = Vec::new();
+        if let Some(sort) = plan::filter(usecase, "ORDER_BY")
+            && let Some(allowed) = &sort.allowed_columns
+        {
+            let default = sort
+                .default_column
+                .clone()
+                .or_else(|| allowed.first().cloned())
+                .unwrap_or_default();
+            let allowed: Vec<String> = allowed.iter().map(|c| format!("\"{}\"", c)).collect();
+            lines.push(format!(
+                "val sortColumn = {}?.takeIf {{ it in listOf({}) }} ?: \"{}\"",
+                camel_case(&sort.param),
+                allowed.join(", "),
+                default
+            ));
+        }
+        if let Some(page) = plan::filter(usecase, "PAGINATION") {
+            let size = match &page.limit_param {
+                Some(limit) if params.iter().any(|p| p.name == camel_case(limit)) => {
+                    match page.max_page_size {
+                        Some(max) => format!("minOf({}, {})", camel_case(limit), max),
+                        None => camel_case(limit),
+                    }
+                }
+                _ => plan::page_size(usecase, page).to_string(),
+            };
+            lines.push(format!("val pageSize = {}", size));
+        }
+        lines
+    }
+}
+
+fn kotlin_literal(value: &Literal) -> String {
+    match value {
+        Literal::Bool(b) => b.to_string(),
+        Literal::Integer(i) => format!("{}L", i),
+        Literal::Number(n) => format!("{:?}", n),
+        Literal::String(s) => format!("{:?}", s),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resolver::{DbmlColumn, DbmlTable};
+    use crate::{api, parser};
+
+    #[test]
+    fn test_generate_kotlin_data_class_and_query() {
+        let doc = parser::parse(
+            r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["users"]
+    - ./schema.dbml#tables["profiles"]
+usecase:
+  name: ユーザー一覧
+  response_mapping:
+    - field: id
+      source: users.id
+    - field: avatar_url
+      source: p.avatar_url
+      join:
+        table: profiles
+        alias: p
+        on: users.id = p.user_id
+  filters:
+    - param: status
+      maps_to: WHERE
+      condition: users.status = :status
+    - param: page
+      maps_to: PAGINATION
+      strategy: offset
+      page_size: 20
+  authorization:
+    row_filters:
+      - users.tenant_id = :tenant_id
+"#,
+        )
+        .unwrap();
+        let mut analysis = api::analyze(doc);
+        analysis.schemas.dbml_tables = vec![DbmlTable {
+            name: "users".to_string(),
+            columns: vec!["id".to_string()],
+            column_details: vec![DbmlColumn {
+                name: "id".to_string(),
+                data_type: "bigint".to_string(),
+                nullable: Some(false),
+                ..DbmlColumn::default()
+            }],
+            location: None,
+        }];
+
+        let kotlin = generate(
+            "usecases/users-list.usml.yaml",
+            &analysis,
+            Some("com.example"),
+        )
+        .unwrap();
+        assert!(kotlin.contains("package com.example\n"));
+        assert!(kotlin.contains(
+            "data class UsersListResponse(\n    val id: Long,\n    val avatarUrl: String,\n)"
+        ));
+        assert!(kotlin.contains(
+            "private fun conditions(tenantId: String, status: String?): List<Condition>"
+        ));
+        assert!(kotlin.contains(
+            "if (status != null) conditions += DSL.condition(\"users.status = ?\", status)"
+        ));
+        assert!(kotlin.contains("conditions += DSL.condition(\"users.tenant_id = ?\", tenantId)"));
+        assert!(kotlin.contains(
+            ".leftJoin(DSL.table(\"profiles\").`as`(\"p\")).on(\"users.id = p.user_id\")"
+        ));
+        assert!(kotlin.contains("            .offset((page - 1) * pageSize)\n"));
+    }
+
+    #[test]
+    fn test_required_and_default_filters_are_always_applied() {
+        let doc = parser::parse(
+            r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: ユーザー一覧
+  response_mapping:
+    - field: id
+      source: users.id
+  filters:
+    - param: status
+      maps_to: WHERE
+      condition: users.status = :status
+      default: active
+    - param: keyword
+      maps_to: WHERE
+      condition: users.name LIKE :keyword
+    - param: org_id
+      maps_to: WHERE
+      condition: users.org_id = :org_id
+      required: true
+"#,
+        )
+        .unwrap();
+        let kotlin = generate("users-list.usml.yaml", &api::analyze(doc), None).unwrap();
+        assert!(kotlin.contains(
+            "private fun conditions(orgId: String, status: String, keyword: String?): List<Condition>"
+        ));
+        assert!(
+            kotlin.contains("        conditions += DSL.condition(\"users.status = ?\", status)\n")
+        );
+        assert!(
+            kotlin.contains("        conditions += DSL.condition(\"users.org_id = ?\", orgId)\n")
+        );
+        assert!(kotlin.contains("        status: String = \"active\",\n"));
+        assert!(kotlin.contains("        keyword: String? = null,\n"));
+    }
+}
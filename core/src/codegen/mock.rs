@@ -0,0 +1,315 @@
+//! usecase のレスポンスのモックデータ
+//!
+//! フィールドの値は、固定値（`value`）、OpenAPI の example（プロパティの `example`・`examples` と
+//! レスポンスの example）、OpenAPI・DBML の enum、カラムの意味に合わせた値（[`crate::synth`]）、
+//! 型ごとの値の順に選ぶ。候補が複数あるときと型ごとの値は
+//! seed とフィールドのフルパス・配列の要素の番号から決めるので、同じ seed なら何度生成しても同じペイロードになる
+
+use serde_yaml::{Mapping, Value};
+
+use crate::api::Analysis;
+use crate::ast::Literal;
+use crate::config::SynthConfig;
+use crate::model::{FieldKind, FieldNode};
+use crate::synth::{self, date, mix, time};
+
+use super::plan::{self, ValueType};
+
+/// モックの生成オプション
+#[derive(Debug, Clone)]
+pub struct MockOptions {
+    /// 値を選ぶ乱数の種
+    pub seed: u64,
+    /// 配列の要素数
+    pub items: usize,
+    /// `[synth]` の設定
+    pub synth: SynthConfig,
+}
+
+impl Default for MockOptions {
+    fn default() -> Self {
+        MockOptions {
+            seed: 0,
+            items: 2,
+            synth: SynthConfig::default(),
+        }
+    }
+}
+
+/// レスポンスのモックのペイロード。フィールドは response_mapping の順に並ぶ
+pub fn mock(analysis: &Analysis, options: &MockOptions) -> Value {
+    object(analysis, None, options, 0)
+}
+
+fn object(analysis: &Analysis, parent: Option<usize>, options: &MockOptions, salt: u64) -> Value {
+    let mut map = Mapping::new();
+    for (index, field) in plan::children(&analysis.model, parent) {
+        map.insert(
+            Value::String(field.name.clone()),
+            field_value(analysis, index, field, options, salt),
+        );
+    }
+    Value::Mapping(map)
+}
+
+fn field_value(
+    analysis: &Analysis,
+    index: usize,
+    field: &FieldNode,
+    options: &MockOptions,
+    salt: u64,
+) -> Value {
+    if field.kind != FieldKind::Array {
+        return scalar(analysis, field, options, salt);
+    }
+    let nested = plan::children(&analysis.model, Some(index))
+        .next()
+        .is_some();
+    Value::Sequence(
+        (0..options.items as u64)
+            .map(|n| {
+                // 要素ごとに違う値にする
+                let salt = mix(salt ^ (n + 1));
+                if nested {
+                    object(analysis, Some(index), options, salt)
+                } else {
+                    scalar(analysis, field, options, salt)
+                }
+            })
+            .collect(),
+    )
+}
+
+fn scalar(analysis: &Analysis, field: &FieldNode, options: &MockOptions, salt: u64) -> Value {
+    if let Some(value) = &field.value
+        && value.param().is_none()
+    {
+        return literal(value);
+    }
+    let mut rng = Rng::new(options.seed, &field.path, salt);
+    let openapi = analysis
+        .schemas
+        .openapi
+        .as_ref()
+        .and_then(|o| o.property(&field.path));
+    if let Some(property) = openapi {
+        if !property.examples.is_empty() {
+            return literal(rng.pick(&property.examples));
+        }
+        if !property.enum_values.is_empty() {
+            return literal(rng.pick(&property.enum_values));
+        }
+    }
+    let column = field
+        .source
+        .as_ref()
+        .and_then(|s| plan::column(analysis, &s.table, &s.column));
+    if let Some(column) = column
+        && !column.enum_values.is_empty()
+    {
+        return Value::String(rng.pick(&column.enum_values).clone());
+    }
+    let value = plan::field_type(analysis, field).value;
+    let synthesized = match &field.source {
+        Some(source) => synth::value(
+            &options.synth,
+            Some(&source.table),
+            &source.column,
+            column.map(|c| c.data_type.as_str()),
+            column.is_some_and(|c| c.primary_key),
+            rng.next(),
+        ),
+        None => synth::value(&options.synth, None, &field.name, None, false, rng.next()),
+    };
+    match synthesized {
+        Some(literal) if plan::accepts(value, &literal) => self::literal(&literal),
+        _ => typed(value, &field.name, &mut rng),
+    }
+}
+
+/// 型ごとの値
+fn typed(value: ValueType, name: &str, rng: &mut Rng) -> Value {
+    match value {
+        ValueType::Int | ValueType::Long => Value::from(rng.below(1000) + 1),
+        ValueType::Float | ValueType::Double | ValueType::Decimal => {
+            Value::from(rng.below(100_000) as f64 / 100.0)
+        }
+        ValueType::Boolean => Value::Bool(rng.below(2) == 0),
+        ValueType::String => Value::String(format!("{}-{}", name, rng.below(1000) + 1)),
+        ValueType::Uuid => {
+            let (high, low) = (rng.next(), rng.next());
+            Value::String(format!(
+                "{:08x}-{:04x}-4{:03x}-{:04x}-{:012x}",
+                high >> 32,
+                (high >> 16) & 0xffff,
+                high & 0xfff,
+                0x8000 | (low >> 48) & 0x3fff,
+                low & 0xffff_ffff_ffff
+            ))
+        }
+        ValueType::Date => Value::String(date(rng.below(366))),
+        ValueType::Time => Value::String(time(rng.below(86_400))),
+        ValueType::DateTime => Value::String(format!(
+            "{}T{}",
+            date(rng.below(366)),
+            time(rng.below(86_400))
+        )),
+        ValueType::OffsetDateTime => Value::String(format!(
+            "{}T{}Z",
+            date(rng.below(366)),
+            time(rng.below(86_400))
+        )),
+        ValueType::Any => Value::Null,
+    }
+}
+
+fn literal(value: &Literal) -> Value {
+    match value {
+        Literal::Bool(b) => Value::Bool(*b),
+        Literal::Integer(n) => Value::from(*n),
+        Literal::Number(n) => Value::from(*n),
+        Literal::String(s) => Value::String(s.clone()),
+    }
+}
+
+/// 外部のクレートに頼らない決定的な乱数（SplitMix64）
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64, path: &str, salt: u64) -> Self {
+        // FNV-1a でフィールドのパスを混ぜる
+        let hash = path.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |h, b| {
+            (h ^ b as u64).wrapping_mul(0x100_0000_01b3)
+        });
+        Rng(mix(seed) ^ hash ^ salt)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        mix(self.0)
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+
+    fn pick<'a, T>(&mut self, values: &'a [T]) -> &'a T {
+        &values[self.below(values.len() as u64) as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resolver::{DbmlColumn, DbmlTable, OpenapiProperty, OpenapiResponse};
+    use crate::{api, parser};
+
+    fn analysis() -> Analysis {
+        let doc = parser::parse(
+            r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["posts"]
+usecase:
+  name: 投稿一覧
+  response_mapping:
+    - field: posts
+      type: array
+      source_table: posts
+      fields:
+        - field: title
+          source: posts.title
+        - field: status
+          source: posts.status
+        - field: kind
+          value: article
+        - field: createdAt
+          source: posts.created_at
+        - field: authorEmail
+          source: posts.author_email
+"#,
+        )
+        .unwrap();
+        let mut analysis = api::analyze(doc);
+        analysis.schemas.openapi = Some(OpenapiResponse {
+            properties: vec![OpenapiProperty {
+                name: "posts".to_string(),
+                schema_type: Some("array".to_string()),
+                properties: vec![OpenapiProperty {
+                    name: "title".to_string(),
+                    schema_type: Some("string".to_string()),
+                    examples: vec![Literal::String("はじめての投稿".to_string())],
+                    ..OpenapiProperty::default()
+                }],
+                ..OpenapiProperty::default()
+            }],
+            ..OpenapiResponse::default()
+        });
+        analysis.schemas.dbml_tables = vec![DbmlTable {
+            name: "posts".to_string(),
+            columns: vec!["title".to_string(), "status".to_string()],
+            column_details: vec![
+                DbmlColumn {
+                    name: "status".to_string(),
+                    data_type: "post_status".to_string(),
+                    enum_values: vec!["draft".to_string()],
+                    ..DbmlColumn::default()
+                },
+                DbmlColumn {
+                    name: "created_at".to_string(),
+                    data_type: "timestamptz".to_string(),
+                    ..DbmlColumn::default()
+                },
+            ],
+            location: None,
+        }];
+        analysis
+    }
+
+    #[test]
+    fn test_mock_prefers_examples_and_enums() {
+        let payload = mock(&analysis(), &MockOptions::default());
+        let posts = payload["posts"].as_sequence().unwrap();
+        assert_eq!(posts.len(), 2);
+        for post in posts {
+            assert_eq!(post["title"], Value::from("はじめての投稿"));
+            assert_eq!(post["status"], Value::from("draft"));
+            assert_eq!(post["kind"], Value::from("article"));
+            let created_at = post["createdAt"].as_str().unwrap();
+            assert!(created_at.starts_with("2024-") && created_at.ends_with('Z'));
+            let email = post["authorEmail"].as_str().unwrap();
+            assert!(email.ends_with("@example.com"));
+        }
+        let keys: Vec<&str> = posts[0]
+            .as_mapping()
+            .unwrap()
+            .keys()
+            .filter_map(Value::as_str)
+            .collect();
+        assert_eq!(
+            keys,
+            ["title", "status", "kind", "createdAt", "authorEmail"]
+        );
+    }
+
+    #[test]
+    fn test_mock_is_deterministic_per_seed() {
+        let analysis = analysis();
+        let seeded = |seed| {
+            mock(
+                &analysis,
+                &MockOptions {
+                    seed,
+                    items: 3,
+                    ..MockOptions::default()
+                },
+            )
+        };
+        assert_eq!(seeded(7), seeded(7));
+        assert_ne!(seeded(7), seeded(8));
+        let posts = seeded(7);
+        let posts = posts["posts"].as_sequence().unwrap();
+        assert_ne!(posts[0]["createdAt"], posts[1]["createdAt"]);
+    }
+}
@@ -0,0 +1,1070 @@
+//! 生成器に共通する、フィールドの型・階層ごとのクエリ・WHERE 条件の組み立て
+//!
+//! 型は OpenAPI のプロパティ、集約、DBML のカラムの順に決める（import を解決した場合）。
+//! 型が分からないフィールドは `ValueType::String` にする
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::api::Analysis;
+use crate::ast::{Filter, Literal, LocaleLookup, Usecase, UsmlDocument};
+use crate::model::{ColumnRef, FieldKind, FieldNode, JoinEdge, JoinKind, Model};
+use crate::resolver::DbmlColumn;
+
+/// クエリを組み立てられないドキュメント
+///
+/// バリデーションエラーの無いドキュメントでは起きない。生成器は不正なクエリを出力する代わりにこれを返す
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum PlanError {
+    /// 集約するカラムが無い（U063）
+    #[error(
+        "フィールド '{field}' の aggregate {aggregate} に集約するカラムの source がありません（U063）"
+    )]
+    AggregateWithoutSource { field: String, aggregate: String },
+    /// カラムを取るフィールドに source が無い
+    #[error("フィールド '{field}' に source がありません")]
+    MissingSource { field: String },
+    /// 相関サブクエリの FROM にする結合が無い
+    #[error("フィールド '{field}' を相関サブクエリで取るための結合がありません")]
+    MissingJoin { field: String },
+}
+
+/// 言語に依存しない値の型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValueType {
+    Int,
+    Long,
+    Float,
+    Double,
+    Decimal,
+    Boolean,
+    String,
+    Uuid,
+    Date,
+    Time,
+    /// タイムゾーン無しの日時
+    DateTime,
+    /// タイムゾーン付きの日時
+    OffsetDateTime,
+    /// 別の usecase の結果など、型を決められない値
+    Any,
+}
+
+/// フィールドの型と、null を取りうるか
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldType {
+    pub value: ValueType,
+    pub nullable: bool,
+}
+
+pub fn field_type(analysis: &Analysis, field: &FieldNode) -> FieldType {
+    let openapi = analysis
+        .schemas
+        .openapi
+        .as_ref()
+        .and_then(|o| o.property(&field.path));
+    let column = field
+        .source
+        .as_ref()
+        .and_then(|s| column(analysis, &s.table, &s.column));
+    let nullable = field
+        .nullable
+        .or(openapi.map(|p| p.nullable))
+        .or(column.and_then(|c| c.nullable))
+        // 訳の無い LOCALIZE は source が無ければ null になる
+        .unwrap_or_else(|| field.source.is_none() && localize(analysis, field).is_some());
+    let typed = |value| FieldType { value, nullable };
+
+    if let Some(value) =
+        openapi.and_then(|p| schema_type(p.schema_type.as_deref()?, p.format.as_deref()))
+    {
+        return typed(value);
+    }
+    if field.aggregate.as_deref() == Some("COUNT") || field.envelope.as_deref() == Some("total") {
+        return typed(ValueType::Long);
+    }
+    if field.source_usecase.is_some() {
+        return FieldType {
+            value: ValueType::Any,
+            nullable: true,
+        };
+    }
+    if field.envelope.as_deref() == Some("next_cursor") {
+        return FieldType {
+            value: ValueType::String,
+            nullable: true,
+        };
+    }
+    // CONVERT は小数点以下の桁数をそろえた数値を返す
+    if field
+        .transforms
+        .first()
+        .is_some_and(|t| t.kind == "CONVERT")
+    {
+        return typed(ValueType::Decimal);
+    }
+    let column = column.or_else(|| {
+        // COALESCE などは先頭の参照カラムの型を使う
+        let source = field.transforms.first()?.sources.first()?;
+        self::column(analysis, &source.table, &source.column)
+    });
+    if let Some(column) = column
+        && (field.transforms.is_empty()
+            || matches!(field.transforms[0].kind.as_str(), "COALESCE" | "LOCALIZE"))
+    {
+        // timezone で変換した日時はタイムゾーンを持たない
+        return typed(match column_type(column) {
+            ValueType::OffsetDateTime if field.timezone.is_some() => ValueType::DateTime,
+            value => value,
+        });
+    }
+    typed(match field.value.as_ref() {
+        Some(Literal::Bool(_)) => ValueType::Boolean,
+        Some(Literal::Integer(_)) => ValueType::Long,
+        Some(Literal::Number(_)) => ValueType::Double,
+        _ => ValueType::String,
+    })
+}
+
+/// リクエストパラメータの型。OpenAPI に無ければ `String`
+pub fn parameter_type(analysis: &Analysis, name: &str) -> ValueType {
+    analysis
+        .schemas
+        .openapi
+        .as_ref()
+        .and_then(|o| o.parameter(name))
+        .and_then(|p| schema_type(p.schema_type.as_deref()?, p.format.as_deref()))
+        .unwrap_or(ValueType::String)
+}
+
+/// DBML のカラム（import を解決した場合）
+pub fn column<'a>(analysis: &'a Analysis, table: &str, column: &str) -> Option<&'a DbmlColumn> {
+    analysis
+        .schemas
+        .dbml_tables
+        .iter()
+        .find(|t| t.name == table)?
+        .column(column)
+}
+
+/// OpenAPI の type・format の型（array・object は None）
+fn schema_type(schema_type: &str, format: Option<&str>) -> Option<ValueType> {
+    Some(match (schema_type, format) {
+        ("integer", Some("int32")) => ValueType::Int,
+        ("integer", _) => ValueType::Long,
+        ("number", Some("float")) => ValueType::Float,
+        ("number", _) => ValueType::Double,
+        ("boolean", _) => ValueType::Boolean,
+        ("string", Some("date-time")) => ValueType::OffsetDateTime,
+        ("string", Some("date")) => ValueType::Date,
+        ("string", Some("uuid")) => ValueType::Uuid,
+        ("string", _) => ValueType::String,
+        _ => return None,
+    })
+}
+
+/// 固定値がその型の値として使えるか
+pub fn accepts(value: ValueType, literal: &Literal) -> bool {
+    match literal {
+        Literal::Bool(_) => matches!(value, ValueType::Boolean | ValueType::Any),
+        Literal::Integer(_) => {
+            matches!(
+                value,
+                ValueType::Int
+                    | ValueType::Long
+                    | ValueType::Float
+                    | ValueType::Double
+                    | ValueType::Decimal
+                    | ValueType::Any
+            )
+        }
+        Literal::Number(_) => matches!(
+            value,
+            ValueType::Float | ValueType::Double | ValueType::Decimal | ValueType::Any
+        ),
+        Literal::String(_) => !matches!(
+            value,
+            ValueType::Int
+                | ValueType::Long
+                | ValueType::Float
+                | ValueType::Double
+                | ValueType::Decimal
+                | ValueType::Boolean
+        ),
+    }
+}
+
+/// DBML のカラム型の型
+pub fn column_type(column: &DbmlColumn) -> ValueType {
+    let base = column
+        .data_type
+        .split('(')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    match base.as_str() {
+        "int" | "integer" | "smallint" | "tinyint" | "int2" | "int4" | "serial" | "smallserial" => {
+            ValueType::Int
+        }
+        "bigint" | "int8" | "bigserial" => ValueType::Long,
+        "numeric" | "decimal" | "money" => ValueType::Decimal,
+        "real" | "float" | "float4" | "float8" | "double" | "double precision" => ValueType::Double,
+        "bool" | "boolean" => ValueType::Boolean,
+        "uuid" => ValueType::Uuid,
+        "date" => ValueType::Date,
+        "time" => ValueType::Time,
+        "timestamp" | "datetime" => ValueType::DateTime,
+        "timestamptz" => ValueType::OffsetDateTime,
+        _ => ValueType::String,
+    }
+}
+
+/// `parent` の直下のフィールド（`None` ならルート）
+pub fn children(model: &Model, parent: Option<usize>) -> impl Iterator<Item = (usize, &FieldNode)> {
+    model
+        .fields
+        .iter()
+        .enumerate()
+        .filter(move |(_, f)| f.parent == parent)
+}
+
+/// ルートと、配列のフィールド（`Model::fields` の順）
+pub fn levels(model: &Model) -> Vec<Option<usize>> {
+    let mut levels = vec![None];
+    levels.extend(
+        model
+            .fields
+            .iter()
+            .enumerate()
+            .filter(|(_, f)| f.kind == FieldKind::Array)
+            .map(|(i, _)| Some(i)),
+    );
+    levels
+}
+
+/// ページネーション・ソートを付ける階層。collection の配列、無ければルート
+pub fn paged_level(model: &Model) -> Option<usize> {
+    model.fields.iter().position(|f| f.collection)
+}
+
+/// `envelope: total` の件数の数え方（PAGINATION の `total_count`）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TotalCount {
+    /// 件数を数える別のクエリを発行する
+    Query,
+    /// ページのクエリに `COUNT(*) OVER()` の列を加え、先頭の行の値を使う
+    Window,
+    /// 数えない
+    None,
+}
+
+/// usecase の件数の数え方。省略時は Query
+pub fn total_count(usecase: &Usecase) -> TotalCount {
+    match filter(usecase, "PAGINATION").and_then(|f| f.total_count.as_deref()) {
+        Some(value) if value.eq_ignore_ascii_case("window") => TotalCount::Window,
+        Some(value) if value.eq_ignore_ascii_case("none") => TotalCount::None,
+        _ => TotalCount::Query,
+    }
+}
+
+/// collection の配列と同じ階層に `envelope: total` があるか
+pub fn has_total(model: &Model) -> bool {
+    paged_level(model).is_some_and(|collection| {
+        children(model, model.fields[collection].parent)
+            .any(|(_, f)| f.envelope.as_deref() == Some("total"))
+    })
+}
+
+/// 配列フィールドの要素の取り方（`usecase.hydration`）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hydration {
+    /// 親の行を取ったあと、親のキーの IN で配列の要素をまとめて取る
+    Batch,
+    /// 配列を親のクエリの列にして JSON の配列に集約する
+    Json(Dialect),
+}
+
+/// JSON の配列を組み立てる SQL の方言
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    /// `json_agg`・`json_build_object`
+    Postgresql,
+    /// `JSON_ARRAYAGG`・`JSON_OBJECT`
+    Mysql,
+}
+
+/// usecase の配列の取り方。省略時は Batch
+pub fn hydration(usecase: &Usecase) -> Hydration {
+    match &usecase.hydration {
+        Some(hydration) if hydration.strategy.eq_ignore_ascii_case("json") => {
+            Hydration::Json(match hydration.dialect.as_deref() {
+                Some(dialect) if dialect.eq_ignore_ascii_case("mysql") => Dialect::Mysql,
+                _ => Dialect::Postgresql,
+            })
+        }
+        _ => Hydration::Batch,
+    }
+}
+
+/// 配列の要素を親の行に対応付けるキー
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Key {
+    /// 親の側の列の式（配列の結合条件のうち、配列のテーブルでない側。例: `posts.id`）
+    pub column: String,
+    pub value: ValueType,
+}
+
+/// 親の階層の行に対応付けて取る配列なら、そのキー（`Model::parent_key`）
+pub fn parent_key(analysis: &Analysis, index: usize) -> Option<Key> {
+    let reference = analysis.model.parent_key(index)?;
+    Some(Key {
+        column: format!("{}.{}", reference.qualifier, reference.column),
+        value: column(analysis, &reference.table, &reference.column)
+            .map(column_type)
+            .unwrap_or(ValueType::String),
+    })
+}
+
+/// 1 つの階層の行を取るクエリ
+pub struct Query<'a> {
+    pub level: Option<usize>,
+    /// DB から取るフィールドと、SELECT に書く式（別名はフィールド名）。JSON で取る配列を含む
+    pub columns: Vec<(&'a FieldNode, String)>,
+    pub from: String,
+    /// 配列とその祖先・選ぶフィールドの結合（宣言順）
+    pub joins: Vec<JoinEdge>,
+    /// 集約を含む場合の、残りの列
+    pub group_by: Vec<String>,
+    /// ページネーション・ソートを付けるか
+    pub paged: bool,
+    /// バッチで取る子の配列と、対応付けるキー。`<配列のフィールド名>_key` の別名で SELECT する
+    pub keys: Vec<(&'a FieldNode, Key)>,
+    /// 親の行に対応付けて取る配列の階層なら、そのキー。`parent_key` の別名で SELECT し、キーの一覧の IN で絞り込む
+    pub parent_key: Option<Key>,
+    /// `envelope: total` の件数を `COUNT(*) OVER()` で取るか。`total_count` の別名で SELECT する
+    pub window_total: bool,
+}
+
+/// DB から取るフィールドのある階層ごとのクエリ
+///
+/// `hydration` が json なら、親の行に対応付ける配列は親のクエリの列になり、その階層のクエリは作らない
+pub fn queries(analysis: &Analysis) -> Result<Vec<Query<'_>>, PlanError> {
+    let model = &analysis.model;
+    check(model)?;
+    let hydration = hydration(&analysis.document.usecase);
+    let from = root_table(model);
+    let paged = paged_level(model);
+    let window_total =
+        has_total(model) && total_count(&analysis.document.usecase) == TotalCount::Window;
+    let dialect = match hydration {
+        Hydration::Json(dialect) => dialect,
+        Hydration::Batch => Dialect::Postgresql,
+    };
+    levels(model)
+        .into_iter()
+        .map(|level| {
+            let parent_key = level.and_then(|index| parent_key(analysis, index));
+            if parent_key.is_some() && hydration != Hydration::Batch {
+                return Ok(None);
+            }
+            let selected = selected(analysis, level);
+            if selected.is_empty() {
+                return Ok(None);
+            }
+            let mut columns: Vec<(&FieldNode, String)> = selected
+                .iter()
+                .map(|&(index, f)| Ok((f, select_expression(analysis, index, dialect)?)))
+                .collect::<Result<_, PlanError>>()?;
+            // サブクエリで取る集約の結合は、階層のクエリには加えない
+            let flat: Vec<&FieldNode> = selected
+                .iter()
+                .filter(|&&(index, _)| !model.aggregate_subquery(index))
+                .map(|&(_, f)| f)
+                .collect();
+            let mut keys = Vec::new();
+            for (index, field) in children(model, level) {
+                let Some(key) = self::parent_key(analysis, index) else {
+                    continue;
+                };
+                match hydration {
+                    Hydration::Batch => keys.push((field, key)),
+                    Hydration::Json(dialect) => {
+                        columns.push((field, json_array(analysis, index, dialect)?));
+                    }
+                }
+            }
+            let mut group_by: Vec<String> = Vec::new();
+            if flat.iter().any(|f| f.aggregate.is_some()) {
+                // 親の行のキー、無ければルートテーブルの主キーで行ごとにまとめる（Model::group_key）
+                let root_key = model
+                    .root_key()
+                    .filter(|_| parent_key.is_none())
+                    .map(|key| format!("{}.{}", key.qualifier, key.column));
+                // timezone・LOCALIZE の式はパラメータを含みうるため、変換前のカラムでまとめる
+                let columns = selected
+                    .iter()
+                    .filter(|(_, f)| f.aggregate.is_none() && f.source.is_some())
+                    .map(|&(index, _)| column_expression(model, index))
+                    .collect::<Result<Vec<_>, PlanError>>()?;
+                let grouped = parent_key
+                    .iter()
+                    .map(|key| key.column.clone())
+                    .chain(root_key)
+                    .chain(columns)
+                    .chain(keys.iter().map(|(_, key)| key.column.clone()));
+                for column in grouped {
+                    if !group_by.contains(&column) {
+                        group_by.push(column);
+                    }
+                }
+            }
+            Ok(Some(Query {
+                level,
+                joins: level_joins(model, level, &flat, parent_key.is_some()),
+                columns,
+                from: from.clone(),
+                group_by,
+                paged: level == paged,
+                keys,
+                parent_key,
+                window_total: window_total && level == paged,
+            }))
+        })
+        .filter_map(Result::transpose)
+        .collect()
+}
+
+/// source の無い集約（U063）はカラムを取れないため、どの階層のクエリも組み立てない
+///
+/// 生成器ごとに列を落としたり型だけを出したりしないよう、生成の前にまとめて調べる
+pub fn check(model: &Model) -> Result<(), PlanError> {
+    match model
+        .fields
+        .iter()
+        .filter(|f| f.kind == FieldKind::Scalar && f.source.is_none())
+        .find_map(|f| Some((f, f.aggregate.as_ref()?)))
+    {
+        Some((field, aggregate)) => Err(PlanError::AggregateWithoutSource {
+            field: field.path.clone(),
+            aggregate: aggregate.clone(),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// 階層の DB から取るスカラーのフィールド
+fn selected(analysis: &Analysis, level: Option<usize>) -> Vec<(usize, &FieldNode)> {
+    children(&analysis.model, level)
+        .filter(|(_, f)| from_db(analysis, f))
+        .collect()
+}
+
+/// DB から取るスカラーのフィールドか（source があるか、LOCALIZE で翻訳テーブルから取る）
+pub fn from_db(analysis: &Analysis, field: &FieldNode) -> bool {
+    field.kind == FieldKind::Scalar
+        && (field.source.is_some() || localize(analysis, field).is_some())
+}
+
+/// LOCALIZE の翻訳のカラムと、翻訳テーブルの行の選び方
+fn localize<'a>(
+    analysis: &'a Analysis,
+    field: &'a FieldNode,
+) -> Option<(&'a ColumnRef, &'a LocaleLookup)> {
+    let source = field
+        .transforms
+        .iter()
+        .find(|t| t.kind == "LOCALIZE")?
+        .sources
+        .first()?;
+    let lookup = analysis
+        .document
+        .usecase
+        .transforms
+        .iter()
+        .find(|t| t.target == field.path && t.r#type.eq_ignore_ascii_case("LOCALIZE"))?
+        .locale
+        .as_ref()?;
+    Some((source, lookup))
+}
+
+/// フィールドを SELECT する式
+fn select_expression(
+    analysis: &Analysis,
+    index: usize,
+    dialect: Dialect,
+) -> Result<String, PlanError> {
+    match localize(analysis, &analysis.model.fields[index]) {
+        Some((source, lookup)) => localized_expression(analysis, index, source, lookup),
+        None => zoned_expression(analysis, index, dialect),
+    }
+}
+
+/// スカラーのフィールドを取る式。集約は GROUP BY か相関サブクエリ
+fn column_expression(model: &Model, index: usize) -> Result<String, PlanError> {
+    let field = &model.fields[index];
+    let Some(source) = &field.source else {
+        return Err(match &field.aggregate {
+            Some(aggregate) => PlanError::AggregateWithoutSource {
+                field: field.path.clone(),
+                aggregate: aggregate.clone(),
+            },
+            None => PlanError::MissingSource {
+                field: field.path.clone(),
+            },
+        });
+    };
+    let column = format!("{}.{}", source.qualifier, source.column);
+    Ok(match &field.aggregate {
+        Some(aggregate) if model.aggregate_subquery(index) => correlated_subquery(
+            model,
+            index,
+            model.correlated_joins(index),
+            &format!("{}({})", aggregate, column),
+        )?,
+        Some(aggregate) => format!("{}({})", aggregate, column),
+        None => column,
+    })
+}
+
+/// `timezone` があれば、UTC で保存した日時をそのタイムゾーンの日時に変換した式
+///
+/// タイムゾーン付きのカラム（timestamptz）はそのまま、それ以外は UTC の日時とみなして変換する。
+/// `:name` のパラメータが null なら UTC のまま返す
+fn zoned_expression(
+    analysis: &Analysis,
+    index: usize,
+    dialect: Dialect,
+) -> Result<String, PlanError> {
+    let field = &analysis.model.fields[index];
+    let expression = column_expression(&analysis.model, index)?;
+    let Some(timezone) = &field.timezone else {
+        return Ok(expression);
+    };
+    let zone = match timezone.strip_prefix(':') {
+        Some(param) => format!("COALESCE(:{}, 'UTC')", param),
+        None => format!("'{}'", timezone),
+    };
+    Ok(match dialect {
+        Dialect::Postgresql => {
+            let offset = field
+                .source
+                .as_ref()
+                .and_then(|s| column(analysis, &s.table, &s.column))
+                .is_some_and(|c| column_type(c) == ValueType::OffsetDateTime);
+            if offset {
+                format!("({} AT TIME ZONE {})", expression, zone)
+            } else {
+                format!("({} AT TIME ZONE 'UTC' AT TIME ZONE {})", expression, zone)
+            }
+        }
+        Dialect::Mysql => format!("CONVERT_TZ({}, '+00:00', {})", expression, zone),
+    })
+}
+
+/// LOCALIZE の訳を、パラメータの言語・fallback の言語の順に翻訳テーブルから探す式
+///
+/// 言語ごとの相関サブクエリを COALESCE でつなぎ、どの言語の訳も無ければフィールドの source の値を返す。
+/// 翻訳テーブルは親の行と言語の組で 1 行に決まるものとする
+fn localized_expression(
+    analysis: &Analysis,
+    index: usize,
+    source: &ColumnRef,
+    lookup: &LocaleLookup,
+) -> Result<String, PlanError> {
+    let table = table_sql(
+        &analysis.model,
+        &source.table,
+        (source.qualifier != source.table).then_some(source.qualifier.as_str()),
+    );
+    let translation = |locale: String| {
+        format!(
+            "(SELECT {}.{} FROM {} WHERE {} AND {} = {})",
+            source.qualifier, source.column, table, lookup.on, lookup.column, locale
+        )
+    };
+    let mut candidates = vec![translation(format!(":{}", lookup.param))];
+    candidates.extend(
+        lookup
+            .fallback
+            .iter()
+            .map(|locale| translation(format!("'{}'", locale))),
+    );
+    if analysis.model.fields[index].source.is_some() {
+        candidates.push(column_expression(&analysis.model, index)?);
+    }
+    Ok(if candidates.len() == 1 {
+        candidates.remove(0)
+    } else {
+        format!("COALESCE({})", candidates.join(", "))
+    })
+}
+
+/// LOCALIZE の言語を受け取るパラメータ（出現順。重複を除く）
+pub fn locale_params(analysis: &Analysis) -> Vec<String> {
+    let mut params: Vec<String> = Vec::new();
+    for field in &analysis.model.fields {
+        if let Some((_, lookup)) = localize(analysis, field)
+            && !params.contains(&lookup.param)
+        {
+            params.push(lookup.param.clone());
+        }
+    }
+    params
+}
+
+/// 列の式の `:name` で受け取るパラメータ（timezone・LOCALIZE の言語。重複を除く）
+pub fn select_params(analysis: &Analysis) -> Vec<String> {
+    let mut params = timezone_params(&analysis.model);
+    for param in locale_params(analysis) {
+        if !params.contains(&param) {
+            params.push(param);
+        }
+    }
+    params
+}
+
+/// `timezone` の `:name` で受け取るパラメータ（出現順。重複を除く）
+pub fn timezone_params(model: &Model) -> Vec<String> {
+    let mut params: Vec<String> = Vec::new();
+    for field in &model.fields {
+        if let Some(param) = field.timezone.as_deref().and_then(|t| t.strip_prefix(':'))
+            && !params.iter().any(|p| p == param)
+        {
+            params.push(param.to_string());
+        }
+    }
+    params
+}
+
+/// 配列の要素を JSON の配列に集約する相関サブクエリ。要素が無ければ空の配列
+///
+/// 要素のフィールドの結合もサブクエリに加える。要素の中の配列も同じように入れ子にする
+fn json_array(analysis: &Analysis, index: usize, dialect: Dialect) -> Result<String, PlanError> {
+    let model = &analysis.model;
+    let mut joins = model.correlated_joins(index);
+    let mut entries = Vec::new();
+    for (child, field) in children(model, Some(index)) {
+        let expression = if field.kind == FieldKind::Array {
+            if parent_key(analysis, child).is_none() {
+                continue;
+            }
+            json_array(analysis, child, dialect)?
+        } else if from_db(analysis, field) {
+            if !model.aggregate_subquery(child) {
+                joins.extend(&field.joins);
+            }
+            select_expression(analysis, child, dialect)?
+        } else {
+            continue;
+        };
+        entries.push(format!("'{}', {}", field.name, expression));
+    }
+    let (array, object, empty) = match dialect {
+        Dialect::Postgresql => ("json_agg", "json_build_object", "'[]'::json"),
+        Dialect::Mysql => ("JSON_ARRAYAGG", "JSON_OBJECT", "JSON_ARRAY()"),
+    };
+    correlated_subquery(
+        model,
+        index,
+        joins,
+        &format!(
+            "COALESCE({}({}({})), {})",
+            array,
+            object,
+            entries.join(", "),
+            empty
+        ),
+    )
+}
+
+/// `FROM ... JOIN ... ON ...`
+pub fn from_sql(model: &Model, query: &Query) -> String {
+    let mut sql = format!("FROM {}", table_sql(model, &query.from, None));
+    for join in &query.joins {
+        let _ = write!(
+            sql,
+            " {} {} ON {}",
+            join_keyword(join),
+            join_table(model, join),
+            join.on
+        );
+    }
+    sql
+}
+
+/// `joins` の最初の結合を FROM にし、その結合条件で外側の行と対応付ける相関サブクエリ
+///
+/// `index` は相関サブクエリで取るフィールド（結合が無ければエラーにする）
+fn correlated_subquery(
+    model: &Model,
+    index: usize,
+    joins: BTreeSet<usize>,
+    select: &str,
+) -> Result<String, PlanError> {
+    let joins: Vec<&JoinEdge> = joins.into_iter().map(|i| &model.joins[i]).collect();
+    let Some((first, rest)) = joins.split_first() else {
+        return Err(PlanError::MissingJoin {
+            field: model.fields[index].path.clone(),
+        });
+    };
+    let mut sql = format!("(SELECT {} FROM {}", select, join_table(model, first));
+    for join in rest {
+        let _ = write!(
+            sql,
+            " {} {} ON {}",
+            join_keyword(join),
+            join_table(model, join),
+            join.on
+        );
+    }
+    let _ = write!(sql, " WHERE {})", first.on);
+    Ok(sql)
+}
+
+fn join_keyword(join: &JoinEdge) -> &'static str {
+    match join.kind {
+        JoinKind::Inner => "INNER JOIN",
+        JoinKind::Left => "LEFT JOIN",
+        JoinKind::Right => "RIGHT JOIN",
+    }
+}
+
+fn join_table(model: &Model, join: &JoinEdge) -> String {
+    table_sql(model, &join.table, join.alias.as_deref())
+}
+
+/// SQL に書くテーブル。`import.tables` で DB 上の名前が異なれば、usecase のテーブル名を別名にする
+pub fn table_sql(model: &Model, table: &str, alias: Option<&str>) -> String {
+    let db_name = model.db_table(table);
+    match alias.or((db_name != table).then_some(table)) {
+        Some(alias) => format!("{} {}", db_name, alias),
+        None => db_name.to_string(),
+    }
+}
+
+/// FROM に置くテーブル。結合で加わらず、フィールドから参照される最初のテーブル
+pub fn root_table(model: &Model) -> String {
+    model.root_table().unwrap_or_default().to_string()
+}
+
+/// `batched` なら、配列とその祖先の結合を INNER JOIN にする（要素の無い親の行を取らない）
+fn level_joins(
+    model: &Model,
+    level: Option<usize>,
+    selected: &[&FieldNode],
+    batched: bool,
+) -> Vec<JoinEdge> {
+    let mut indices: BTreeSet<usize> = BTreeSet::new();
+    let mut arrays: BTreeSet<usize> = BTreeSet::new();
+    let mut parent = level;
+    while let Some(index) = parent {
+        arrays.extend(&model.fields[index].joins);
+        parent = model.fields[index].parent;
+    }
+    indices.extend(&arrays);
+    for field in selected {
+        indices.extend(&field.joins);
+    }
+    indices
+        .into_iter()
+        .map(|i| {
+            let mut join = model.joins[i].clone();
+            if batched && arrays.contains(&i) {
+                join.kind = JoinKind::Inner;
+            }
+            join
+        })
+        .collect()
+}
+
+/// WHERE に加える条件
+pub struct Condition {
+    /// `:name` を `?` に置き換えた条件式
+    pub sql: String,
+    /// `?` に出現順に対応するパラメータ名
+    pub binds: Vec<String>,
+    /// このパラメータが指定された場合だけ加える（省略できる `maps_to: WHERE`）。None なら常に加える
+    pub optional_param: Option<String>,
+}
+
+impl Condition {
+    /// `?` を `:name` に戻した条件式
+    pub fn named_sql(&self) -> String {
+        let mut binds = self.binds.iter();
+        let mut sql = String::new();
+        for (i, part) in self.sql.split('?').enumerate() {
+            if i > 0
+                && let Some(bind) = binds.next()
+            {
+                let _ = write!(sql, ":{}", bind);
+            }
+            sql.push_str(part);
+        }
+        sql
+    }
+}
+
+/// 条件のパラメータ
+pub struct Param {
+    pub name: String,
+    pub value: ValueType,
+    /// 行レベルの条件と `required: true` のフィルタのパラメータは必須、それ以外の WHERE フィルタのパラメータは省略可能
+    pub required: bool,
+    /// 行レベルの条件のパラメータ（リクエストではなく認可の文脈から渡す）
+    pub scope: bool,
+    /// 省略された場合の値（フィルタの `default`）
+    pub default: Option<Literal>,
+}
+
+/// `maps_to: WHERE` の条件と `authorization.row_filters`
+pub fn conditions(doc: &UsmlDocument) -> Vec<Condition> {
+    let usecase = &doc.usecase;
+    let mut conditions: Vec<Condition> = usecase
+        .filters
+        .iter()
+        .filter(|f| f.maps_to == "WHERE")
+        .filter_map(|f| {
+            let (sql, binds) = bind_params(f.condition.as_deref()?);
+            // 必須・既定値のあるパラメータは常に値があるため、条件も常に加える
+            let always = f.required == Some(true) || f.default.is_some();
+            Some(Condition {
+                sql,
+                binds,
+                optional_param: (!always).then(|| f.param.clone()),
+            })
+        })
+        .collect();
+    for row_filter in usecase.authorization.iter().flat_map(|a| &a.row_filters) {
+        let (sql, binds) = bind_params(row_filter);
+        conditions.push(Condition {
+            sql,
+            binds,
+            optional_param: None,
+        });
+    }
+    conditions
+}
+
+/// 条件が使うパラメータ（必須のもの、既定値のあるもの、省略可能なものの順。重複を除く）
+pub fn condition_params(analysis: &Analysis, conditions: &[Condition]) -> Vec<Param> {
+    let filters = &analysis.document.usecase.filters;
+    let mut params: Vec<Param> = Vec::new();
+    for condition in conditions {
+        let names = match &condition.optional_param {
+            Some(param) => vec![param],
+            None => condition.binds.iter().collect(),
+        };
+        for name in names {
+            if params.iter().any(|p| &p.name == name) {
+                continue;
+            }
+            let filter = filters
+                .iter()
+                .find(|f| &f.param == name && f.maps_to == "WHERE");
+            let default = filter.and_then(|f| f.default.clone());
+            params.push(Param {
+                name: name.clone(),
+                value: parameter_type(analysis, name),
+                required: condition.optional_param.is_none() && default.is_none(),
+                scope: filter.is_none(),
+                default,
+            });
+        }
+    }
+    params.sort_by_key(|p| (!p.required, p.default.is_none()));
+    params
+}
+
+/// `maps_to` が一致する最初のフィルタ
+pub fn filter<'a>(usecase: &'a Usecase, maps_to: &str) -> Option<&'a Filter> {
+    usecase.filters.iter().find(|f| f.maps_to == maps_to)
+}
+
+/// ページサイズの既定値（page_size、default_limit、20 の順）
+pub fn page_size(usecase: &Usecase, page: &Filter) -> u32 {
+    page.page_size.or(usecase.default_limit).unwrap_or(20)
+}
+
+/// カーソルのカラム。テーブル名が無ければ `from` のカラムとする
+pub fn cursor_column(page: &Filter, from: &str) -> String {
+    let column = page.cursor_field.as_deref().unwrap_or("id");
+    if column.contains('.') {
+        column.to_string()
+    } else {
+        format!("{}.{}", from, column)
+    }
+}
+
+/// cursor_tiebreaker のカラム。テーブル名が無ければ `from` のカラムとする
+pub fn tiebreaker_column(page: &Filter, from: &str) -> Option<String> {
+    let column = page.cursor_tiebreaker.as_deref()?;
+    Some(if column.contains('.') {
+        column.to_string()
+    } else {
+        format!("{}.{}", from, column)
+    })
+}
+
+/// cursor_tiebreaker の値を受け取るパラメータ（`<param>_<カラム>`）
+pub fn tiebreaker_param(page: &Filter) -> Option<String> {
+    let column = page.cursor_tiebreaker.as_deref()?;
+    let column = column.rsplit('.').next().unwrap_or(column);
+    Some(format!("{}_{}", page.param, column))
+}
+
+/// カーソルで辿る条件と並び順。カーソルは降順（新しい順）に辿る
+pub struct Keyset {
+    /// カーソルのカラムと、あればタイブレーカーのカラム（降順に並べる）
+    pub columns: Vec<String>,
+    /// `?` はカーソル、タイブレーカーの値の順
+    pub condition: String,
+}
+
+impl Keyset {
+    /// `ORDER BY` に続ける並び順
+    pub fn order_by(&self) -> String {
+        let columns: Vec<String> = self.columns.iter().map(|c| format!("{} DESC", c)).collect();
+        columns.join(", ")
+    }
+}
+
+/// cursor_field（と cursor_tiebreaker の組）のキーセット
+pub fn keyset(page: &Filter, from: &str) -> Keyset {
+    let mut columns = vec![cursor_column(page, from)];
+    columns.extend(tiebreaker_column(page, from));
+    let condition = if columns.len() > 1 {
+        format!("({}) < (?, ?)", columns.join(", "))
+    } else {
+        format!("{} < ?", columns[0])
+    };
+    Keyset { columns, condition }
+}
+
+/// `:name` を `?` に置き換え、出現順のパラメータ名を返す。`::` のキャストは置き換えない
+pub fn bind_params(condition: &str) -> (String, Vec<String>) {
+    let mut sql = String::new();
+    let mut binds = Vec::new();
+    let mut chars = condition.chars().peekable();
+    let mut prev = None;
+    while let Some(c) = chars.next() {
+        let starts_name = chars
+            .peek()
+            .is_some_and(|n| n.is_ascii_alphabetic() || *n == '_');
+        if c == ':' && prev != Some(':') && starts_name {
+            let mut name = String::new();
+            while let Some(&n) = chars.peek() {
+                if !(n.is_ascii_alphanumeric() || n == '_') {
+                    break;
+                }
+                name.push(n);
+                chars.next();
+            }
+            binds.push(name);
+            sql.push('?');
+            prev = None;
+            continue;
+        }
+        sql.push(c);
+        prev = Some(c);
+    }
+    (sql, binds)
+}
+
+/// `?` を出現順に `binds` の `$n`（PostgreSQL のプレースホルダー）に置き換える
+///
+/// 番号は `params` での位置で、同じパラメータは同じ番号になる。`params` に無いパラメータは末尾に加える
+pub fn numbered(sql: &str, binds: &[String], params: &mut Vec<String>) -> String {
+    let mut binds = binds.iter();
+    let mut text = String::new();
+    for (i, part) in sql.split('?').enumerate() {
+        if i > 0
+            && let Some(bind) = binds.next()
+        {
+            let position = match params.iter().position(|p| p == bind) {
+                Some(position) => position,
+                None => {
+                    params.push(bind.clone());
+                    params.len() - 1
+                }
+            };
+            let _ = write!(text, "${}", position + 1);
+        }
+        text.push_str(part);
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::{graphql, kotlin, sql, testcase};
+    use crate::{api, parser};
+
+    #[test]
+    fn test_aggregate_without_source_fails_every_backend() {
+        let doc = parser::parse(
+            r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["users"]
+    - ./schema.dbml#tables["posts"]
+usecase:
+  name: ユーザー集計
+  response_mapping:
+    - field: id
+      source: users.id
+    - field: cnt
+      join:
+        table: posts
+        type: LEFT
+        on: users.id = posts.user_id
+      aggregate:
+        type: COUNT
+        group_by: users.id
+"#,
+        )
+        .unwrap();
+        let analysis = api::analyze(doc);
+        assert!(
+            analysis
+                .diagnostics
+                .iter()
+                .any(|d| d.code.as_deref() == Some("U063"))
+        );
+
+        // SQL で列を落とし、Kotlin・GraphQL で型だけを出すことのないよう、どの生成器も同じエラーにする
+        let expected = PlanError::AggregateWithoutSource {
+            field: "cnt".to_string(),
+            aggregate: "COUNT".to_string(),
+        };
+        let file = "user-stats.usml.yaml";
+        assert_eq!(sql::generate(file, &analysis), Err(expected.clone()));
+        assert_eq!(
+            kotlin::generate(file, &analysis, None),
+            Err(expected.clone())
+        );
+        assert_eq!(graphql::resolver(file, &analysis), Err(expected.clone()));
+        assert_eq!(graphql::sdl(file, &analysis), Err(expected.clone()));
+        assert_eq!(
+            testcase::test_case(file, &analysis, &Default::default()).err(),
+            Some(expected)
+        );
+    }
+
+    #[test]
+    fn test_bind_params_skips_casts() {
+        assert_eq!(
+            bind_params("posts.owner_id = :current_user_id AND posts.kind::text = :kind"),
+            (
+                "posts.owner_id = ? AND posts.kind::text = ?".to_string(),
+                vec!["current_user_id".to_string(), "kind".to_string()]
+            )
+        );
+    }
+}
@@ -0,0 +1,817 @@
+//! 階層ごとの SQL を、省略できるパラメータの条件を断片に分けて生成する
+//!
+//! 言語ごとの生成器が無いバックエンドでも、次の約束に従って文字列を組み立てれば条件付きの WHERE を扱える。
+//!
+//! - `--if :name` から `--end` までの行は、パラメータ `name` が指定された（null でない）場合だけ残す
+//! - `--sort :name 列, ...` の次の `ORDER BY` 行の列は、パラメータ `name` が一覧の列のいずれかなら置き換える
+//! - `:name` は名前付きのバインド変数。全パラメータを指定した場合はそのまま実行できる
+//! - `hydration: batch` で親の行に対応付ける配列のクエリは `:parent_keys` に親の行の `<配列>_key` の一覧を渡す。
+//!   要素の `parent_key` が親の行の `<配列>_key` と等しいものがその行の配列になる
+//!
+//! [`prepared`] は同じ文を `$1..$n` のプレースホルダーで書き、文ごとに束縛するパラメータの順を返す。
+//! 省略できる条件は `($n IS NULL OR 条件)`、`:parent_keys` は配列を受け取る `= ANY($n)` にする
+
+use std::fmt::Write;
+
+use serde::Serialize;
+
+use crate::api::Analysis;
+use crate::ast::Literal;
+
+use super::plan::{self, PlanError, Query, TotalCount, ValueType};
+
+/// `usml generate --backend sql-prepared` の出力（プリペアドステートメントと束縛するパラメータ）
+#[derive(Debug, Clone, Serialize)]
+pub struct Prepared {
+    pub file: String,
+    pub usecase: String,
+    /// 条件・timezone・LOCALIZE のパラメータ（`-- params:` と同じ順）
+    pub params: Vec<PreparedParam>,
+    pub statements: Vec<Statement>,
+}
+
+/// 文が受け取るパラメータ
+#[derive(Debug, Clone, Serialize)]
+pub struct PreparedParam {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub value: ValueType,
+    pub required: bool,
+    /// 行レベルの条件のパラメータ（リクエストではなく認可の文脈から渡す）
+    pub scope: bool,
+    pub default: Option<Literal>,
+}
+
+/// 1 つの階層を取る文
+#[derive(Debug, Clone, Serialize)]
+pub struct Statement {
+    /// `fetch` / `fetch_<フィールド>` / `count`
+    pub name: String,
+    pub sql: String,
+    /// `$1` から順に束縛するパラメータ名
+    pub params: Vec<String>,
+}
+
+/// 省略できる条件の書き方
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Optional {
+    /// `--if :name` 〜 `--end` の断片
+    Fragment,
+    /// `(:name IS NULL OR 条件)`。文が 1 つに決まるため、プレースホルダーの位置が変わらない
+    NullCheck,
+}
+
+/// `file` の解析結果から SQL ファイルを生成する
+pub fn generate(file: &str, analysis: &Analysis) -> Result<String, PlanError> {
+    let model = &analysis.model;
+    let usecase = &analysis.document.usecase;
+    let conditions = plan::conditions(&analysis.document);
+    let params = plan::condition_params(analysis, &conditions);
+
+    let mut out = format!(
+        "-- usml generate --backend sql で {} から生成（{}）\n\
+         -- --if :name 〜 --end の行はパラメータ name が null でない場合だけ残す\n",
+        file, usecase.name
+    );
+    let mut described: Vec<String> = params
+        .iter()
+        .map(|p| match (&p.default, p.required) {
+            (Some(default), _) => format!("{}（既定値 {}）", p.name, sql_literal(default)),
+            (None, true) => format!("{}（必須）", p.name),
+            (None, false) => format!("{}（省略可）", p.name),
+        })
+        .collect();
+    for param in plan::timezone_params(model) {
+        if !params.iter().any(|p| p.name == param) {
+            described.push(format!("{}（タイムゾーン。省略時は UTC）", param));
+        }
+    }
+    for param in plan::locale_params(analysis) {
+        if !params.iter().any(|p| p.name == param) {
+            described.push(format!("{}（言語。省略時は fallback の言語）", param));
+        }
+    }
+    if !described.is_empty() {
+        let _ = writeln!(out, "-- params: {}", described.join(", "));
+    }
+
+    for (name, sql) in statements(analysis, Optional::Fragment)? {
+        let _ = write!(out, "\n-- name: {}\n{};\n", name, sql);
+    }
+    Ok(out)
+}
+
+/// `file` の解析結果から、`$1..$n` のプレースホルダーを使う文と束縛するパラメータを組み立てる
+///
+/// 省略できる条件は `($n IS NULL OR 条件)` にするため、パラメータを省略しても文と番号は変わらない。
+/// `--sort` の行は [`generate`] と同じく呼び出し側で扱う
+pub fn prepared(file: &str, analysis: &Analysis) -> Result<Prepared, PlanError> {
+    let conditions = plan::conditions(&analysis.document);
+    let mut params: Vec<PreparedParam> = plan::condition_params(analysis, &conditions)
+        .into_iter()
+        .map(|p| PreparedParam {
+            name: p.name,
+            value: p.value,
+            required: p.required,
+            scope: p.scope,
+            default: p.default,
+        })
+        .collect();
+    for param in plan::select_params(analysis) {
+        if !params.iter().any(|p| p.name == param) {
+            params.push(PreparedParam {
+                name: param,
+                value: ValueType::String,
+                required: false,
+                scope: false,
+                default: None,
+            });
+        }
+    }
+
+    let statements = statements(analysis, Optional::NullCheck)?
+        .into_iter()
+        .map(|(name, named)| {
+            let mut params = Vec::new();
+            let sql: Vec<String> = named
+                .lines()
+                .map(|line| {
+                    if line.starts_with("--") {
+                        return line.to_string();
+                    }
+                    let (sql, binds) = plan::bind_params(line);
+                    plan::numbered(&sql, &binds, &mut params)
+                })
+                .collect();
+            Statement {
+                name,
+                sql: sql.join("\n"),
+                params,
+            }
+        })
+        .collect();
+
+    Ok(Prepared {
+        file: file.to_string(),
+        usecase: analysis.document.usecase.name.clone(),
+        params,
+        statements,
+    })
+}
+
+/// 階層ごとの文の名前と、`:name` のバインド変数で書いた SQL（末尾の `;` を除く）
+fn statements(analysis: &Analysis, optional: Optional) -> Result<Vec<(String, String)>, PlanError> {
+    let model = &analysis.model;
+    let queries = plan::queries(analysis)?;
+    let usecase = &analysis.document.usecase;
+
+    let mut where_sql = String::from("WHERE TRUE\n");
+    for condition in plan::conditions(&analysis.document) {
+        let condition_sql = condition.named_sql();
+        match (&condition.optional_param, optional) {
+            (Some(param), Optional::Fragment) => {
+                let _ = write!(
+                    where_sql,
+                    "--if :{}\n  AND ({})\n--end\n",
+                    param, condition_sql
+                );
+            }
+            (Some(param), Optional::NullCheck) => {
+                let _ = writeln!(
+                    where_sql,
+                    "  AND (:{} IS NULL OR ({}))",
+                    param, condition_sql
+                );
+            }
+            (None, _) => {
+                let _ = writeln!(where_sql, "  AND ({})", condition_sql);
+            }
+        }
+    }
+
+    let mut statements = Vec::new();
+    for query in &queries {
+        let name = match query.level {
+            None => "fetch".to_string(),
+            Some(index) => format!("fetch_{}", super::snake_case(&model.fields[index].path)),
+        };
+        let mut columns: Vec<String> = query
+            .parent_key
+            .iter()
+            .map(|key| format!("{} AS parent_key", key.column))
+            .collect();
+        columns.extend(
+            query
+                .columns
+                .iter()
+                .map(|(field, expression)| format!("{} AS {}", expression, field.name)),
+        );
+        columns.extend(
+            query
+                .keys
+                .iter()
+                .map(|(field, key)| format!("{} AS {}_key", key.column, field.name)),
+        );
+        if query.window_total {
+            columns.push("COUNT(*) OVER() AS total_count".to_string());
+        }
+        let mut sql = format!(
+            "SELECT {}{}\n{}\n{}",
+            if usecase.distinct { "DISTINCT " } else { "" },
+            columns.join(", "),
+            plan::from_sql(model, query),
+            where_sql
+        );
+        if let Some(key) = &query.parent_key {
+            match optional {
+                Optional::Fragment => {
+                    let _ = writeln!(sql, "  AND {} IN (:parent_keys)", key.column);
+                }
+                // プレースホルダー 1 つで一覧を渡すため配列で受け取る
+                Optional::NullCheck => {
+                    let _ = writeln!(sql, "  AND {} = ANY(:parent_keys)", key.column);
+                }
+            }
+        }
+        if query.paged {
+            push_cursor(&mut sql, analysis, query, optional);
+        }
+        if !query.group_by.is_empty() {
+            let _ = writeln!(sql, "GROUP BY {}", query.group_by.join(", "));
+        }
+        if query.paged {
+            push_paging(&mut sql, analysis, query);
+        } else if query.level.is_none()
+            && let Some(limit) = usecase.limit.or(usecase.default_limit)
+        {
+            let _ = writeln!(sql, "LIMIT {}", limit);
+        }
+        sql.truncate(sql.trim_end().len());
+        statements.push((name, sql));
+    }
+
+    if let Some(query) = total_query(analysis, &queries) {
+        let mut sql = format!(
+            "SELECT COUNT(*)\n{}\n{}",
+            plan::from_sql(model, query),
+            where_sql
+        );
+        sql.truncate(sql.trim_end().len());
+        statements.push(("count".to_string(), sql));
+    }
+    Ok(statements)
+}
+
+/// `envelope: total` の件数を数えるクエリ（collection の配列の階層）。`total_count: query` のときだけ
+fn total_query<'q>(analysis: &Analysis, queries: &'q [Query<'q>]) -> Option<&'q Query<'q>> {
+    let model = &analysis.model;
+    let collection = plan::paged_level(model)?;
+    if !plan::has_total(model) || plan::total_count(&analysis.document.usecase) != TotalCount::Query
+    {
+        return None;
+    }
+    queries.iter().find(|q| q.level == Some(collection))
+}
+
+fn push_cursor(out: &mut String, analysis: &Analysis, query: &Query, optional: Optional) {
+    let usecase = &analysis.document.usecase;
+    if let Some(page) = plan::filter(usecase, "PAGINATION")
+        && page.strategy.as_deref() == Some("cursor")
+    {
+        let mut condition = plan::keyset(page, &query.from).condition;
+        let mut params = vec![page.param.clone()];
+        params.extend(plan::tiebreaker_param(page));
+        for param in &params {
+            condition = condition.replacen('?', &format!(":{}", param), 1);
+        }
+        // タイブレーカーがある場合も、カーソルの値が指定されたときだけ加える
+        match optional {
+            Optional::Fragment => {
+                let _ = write!(out, "--if :{}\n  AND {}\n--end\n", page.param, condition);
+            }
+            Optional::NullCheck => {
+                let _ = writeln!(out, "  AND (:{} IS NULL OR {})", page.param, condition);
+            }
+        }
+    }
+}
+
+fn push_paging(out: &mut String, analysis: &Analysis, query: &Query) {
+    let usecase = &analysis.document.usecase;
+    let page = plan::filter(usecase, "PAGINATION");
+    if let Some(page) = page
+        && page.strategy.as_deref() == Some("cursor")
+    {
+        let _ = writeln!(
+            out,
+            "ORDER BY {}",
+            plan::keyset(page, &query.from).order_by()
+        );
+    } else if let Some(sort) = plan::filter(usecase, "ORDER_BY") {
+        let direction = match sort.default_direction.as_deref() {
+            Some(d) if d.eq_ignore_ascii_case("ASC") => "ASC",
+            _ => "DESC",
+        };
+        let default = sort
+            .default_column
+            .clone()
+            .or_else(|| sort.allowed_columns.as_ref()?.first().cloned());
+        if let Some(allowed) = &sort.allowed_columns {
+            let _ = writeln!(out, "--sort :{} {}", sort.param, allowed.join(", "));
+        }
+        if let Some(column) = default {
+            let _ = writeln!(out, "ORDER BY {} {}", column, direction);
+        }
+    }
+    let Some(page) = page else {
+        return;
+    };
+    let page_size = match &page.limit_param {
+        Some(limit) => match page.max_page_size {
+            Some(max) => format!("LEAST(:{}, {})", limit, max),
+            None => format!(":{}", limit),
+        },
+        None => plan::page_size(usecase, page).to_string(),
+    };
+    if page.strategy.as_deref() == Some("cursor") {
+        let _ = writeln!(out, "LIMIT {}", page_size);
+    } else {
+        let _ = writeln!(
+            out,
+            "LIMIT {} OFFSET (:{} - 1) * {}",
+            page_size, page.param, page_size
+        );
+    }
+}
+
+fn sql_literal(value: &Literal) -> String {
+    match value {
+        Literal::String(s) => format!("'{}'", s.replace('\'', "''")),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resolver::{DbmlColumn, DbmlTable};
+    use crate::{api, parser};
+
+    #[test]
+    fn test_optional_conditions_are_fragments() {
+        let doc = parser::parse(
+            r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["posts"]
+usecase:
+  name: 投稿一覧
+  response_mapping:
+    - field: id
+      source: posts.id
+  filters:
+    - param: status
+      maps_to: WHERE
+      condition: posts.status = :status
+    - param: kind
+      maps_to: WHERE
+      condition: posts.kind = :kind
+      default: article
+    - param: page
+      maps_to: PAGINATION
+      strategy: offset
+      page_size: 20
+    - param: sort
+      maps_to: ORDER_BY
+      allowed_columns: [posts.created_at, posts.id]
+  authorization:
+    row_filters:
+      - posts.tenant_id = :tenant_id
+"#,
+        )
+        .unwrap();
+        let sql = generate("posts-list.usml.yaml", &api::analyze(doc)).unwrap();
+        assert!(sql.contains(
+            "-- params: tenant_id（必須）, kind（既定値 'article'）, status（省略可）\n"
+        ));
+        assert!(sql.contains(
+            "-- name: fetch\nSELECT posts.id AS id\nFROM posts\nWHERE TRUE\n\
+             --if :status\n  AND (posts.status = :status)\n--end\n\
+             \x20 AND (posts.kind = :kind)\n\
+             \x20 AND (posts.tenant_id = :tenant_id)\n\
+             --sort :sort posts.created_at, posts.id\n\
+             ORDER BY posts.created_at DESC\n\
+             LIMIT 20 OFFSET (:page - 1) * 20;\n"
+        ));
+    }
+
+    #[test]
+    fn test_prepared_numbers_params_per_statement() {
+        let doc = parser::parse(
+            r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["posts"]
+usecase:
+  name: 投稿フィード
+  response_mapping:
+    - field: id
+      source: posts.id
+  filters:
+    - param: status
+      maps_to: WHERE
+      condition: posts.status = :status
+    - param: cursor
+      maps_to: PAGINATION
+      strategy: cursor
+      page_size: 20
+      cursor_field: created_at
+      cursor_tiebreaker: id
+  authorization:
+    row_filters:
+      - posts.tenant_id = :tenant_id
+"#,
+        )
+        .unwrap();
+        let prepared = prepared("posts-feed.usml.yaml", &api::analyze(doc)).unwrap();
+        let names: Vec<&str> = prepared.params.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["tenant_id", "status"]);
+        assert!(prepared.params[0].required && prepared.params[0].scope);
+
+        let fetch = &prepared.statements[0];
+        assert_eq!(fetch.name, "fetch");
+        assert_eq!(
+            fetch.sql,
+            "SELECT posts.id AS id\nFROM posts\nWHERE TRUE\n\
+             \x20 AND ($1 IS NULL OR (posts.status = $1))\n\
+             \x20 AND (posts.tenant_id = $2)\n\
+             \x20 AND ($3 IS NULL OR (posts.created_at, posts.id) < ($3, $4))\n\
+             ORDER BY posts.created_at DESC, posts.id DESC\n\
+             LIMIT 20"
+        );
+        assert_eq!(fetch.params, ["status", "tenant_id", "cursor", "cursor_id"]);
+    }
+
+    #[test]
+    fn test_cursor_with_tiebreaker_is_keyset() {
+        let doc = parser::parse(
+            r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["posts"]
+usecase:
+  name: 投稿フィード
+  response_mapping:
+    - field: id
+      source: posts.id
+  filters:
+    - param: cursor
+      maps_to: PAGINATION
+      strategy: cursor
+      page_size: 20
+      cursor_field: created_at
+      cursor_tiebreaker: id
+"#,
+        )
+        .unwrap();
+        let sql = generate("posts-feed.usml.yaml", &api::analyze(doc)).unwrap();
+        assert!(sql.contains(
+            "--if :cursor\n  AND (posts.created_at, posts.id) < (:cursor, :cursor_id)\n--end\n\
+             ORDER BY posts.created_at DESC, posts.id DESC\n\
+             LIMIT 20;\n"
+        ));
+    }
+
+    #[test]
+    fn test_window_total_count() {
+        let usml = r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["posts"]
+usecase:
+  name: 投稿一覧
+  response_mapping:
+    - field: items
+      type: array
+      collection: true
+      source_table: posts
+      fields:
+        - field: id
+          source: posts.id
+    - field: total
+      envelope: total
+  filters:
+    - param: page
+      maps_to: PAGINATION
+      strategy: offset
+      page_size: 20
+"#;
+        let sql = generate(
+            "posts-list.usml.yaml",
+            &api::analyze(parser::parse(usml).unwrap()),
+        )
+        .unwrap();
+        assert!(sql.contains("-- name: count\nSELECT COUNT(*)\nFROM posts\n"));
+
+        let usml = usml.replace(
+            "page_size: 20\n",
+            "page_size: 20\n      total_count: window\n",
+        );
+        let sql = generate(
+            "posts-list.usml.yaml",
+            &api::analyze(parser::parse(&usml).unwrap()),
+        )
+        .unwrap();
+        assert!(
+            sql.contains("SELECT posts.id AS id, COUNT(*) OVER() AS total_count\nFROM posts\n")
+        );
+        assert!(!sql.contains("-- name: count"));
+    }
+
+    #[test]
+    fn test_timezone_converts_utc_columns() {
+        let doc = parser::parse(
+            r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["posts"]
+usecase:
+  name: 投稿一覧
+  response_mapping:
+    - field: createdAt
+      source: posts.created_at
+      timezone: ":tz"
+    - field: updatedAt
+      source: posts.updated_at
+      timezone: Asia/Tokyo
+"#,
+        )
+        .unwrap();
+        let mut analysis = api::analyze(doc);
+        analysis.schemas.dbml_tables = vec![DbmlTable {
+            name: "posts".to_string(),
+            columns: vec!["created_at".to_string(), "updated_at".to_string()],
+            column_details: vec![
+                DbmlColumn {
+                    name: "created_at".to_string(),
+                    data_type: "timestamptz".to_string(),
+                    ..DbmlColumn::default()
+                },
+                DbmlColumn {
+                    name: "updated_at".to_string(),
+                    data_type: "timestamp".to_string(),
+                    ..DbmlColumn::default()
+                },
+            ],
+            location: None,
+        }];
+        let sql = generate("posts-list.usml.yaml", &analysis).unwrap();
+        assert!(sql.contains("-- params: tz（タイムゾーン。省略時は UTC）\n"));
+        assert!(sql.contains(
+            "SELECT (posts.created_at AT TIME ZONE COALESCE(:tz, 'UTC')) AS createdAt, \
+             (posts.updated_at AT TIME ZONE 'UTC' AT TIME ZONE 'Asia/Tokyo') AS updatedAt\n"
+        ));
+    }
+
+    #[test]
+    fn test_import_tables_use_db_names() {
+        let doc = parser::parse(
+            r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["stg_posts"]
+    - ./schema.dbml#tables["users"]
+  tables:
+    posts: stg_posts
+usecase:
+  name: 投稿一覧
+  response_mapping:
+    - field: id
+      source: posts.id
+    - field: author_name
+      source: users.name
+      join:
+        table: users
+        "on": posts.user_id = users.id
+"#,
+        )
+        .unwrap();
+        let sql = generate("posts-list.usml.yaml", &api::analyze(doc)).unwrap();
+        assert!(sql.contains(
+            "SELECT posts.id AS id, users.name AS author_name\n\
+             FROM stg_posts posts LEFT JOIN users ON posts.user_id = users.id\n"
+        ));
+    }
+
+    #[test]
+    fn test_localize_falls_back_through_locales() {
+        let doc = parser::parse(
+            r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["products"]
+    - ./schema.dbml#tables["product_translations"]
+usecase:
+  name: 商品一覧
+  response_mapping:
+    - field: name
+      source: products.name
+    - field: summary
+  transforms:
+    - target: name
+      type: LOCALIZE
+      source: product_translations.name
+      locale:
+        on: products.id = product_translations.product_id
+        column: product_translations.locale
+        param: lang
+        fallback: [en]
+    - target: summary
+      type: LOCALIZE
+      source: product_translations.summary
+      locale:
+        on: products.id = product_translations.product_id
+        column: product_translations.locale
+        param: lang
+"#,
+        )
+        .unwrap();
+        let sql = generate("products.usml.yaml", &api::analyze(doc)).unwrap();
+        assert!(sql.contains("-- params: lang（言語。省略時は fallback の言語）\n"));
+        assert!(sql.contains(
+            "SELECT COALESCE(\
+             (SELECT product_translations.name FROM product_translations \
+             WHERE products.id = product_translations.product_id AND product_translations.locale = :lang), \
+             (SELECT product_translations.name FROM product_translations \
+             WHERE products.id = product_translations.product_id AND product_translations.locale = 'en'), \
+             products.name) AS name, \
+             (SELECT product_translations.summary FROM product_translations \
+             WHERE products.id = product_translations.product_id AND product_translations.locale = :lang) AS summary\n\
+             FROM products\n"
+        ));
+    }
+
+    #[test]
+    fn test_multiplied_aggregates_are_subqueries() {
+        let doc = parser::parse(
+            r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["users"]
+    - ./schema.dbml#tables["posts"]
+    - ./schema.dbml#tables["likes"]
+    - ./schema.dbml#tables["comments"]
+usecase:
+  name: ユーザー活動集計
+  response_mapping:
+    - field: id
+      source: users.id
+    - field: post_count
+      source: posts.id
+      join:
+        table: posts
+        type: LEFT
+        on: users.id = posts.user_id
+      aggregate:
+        type: COUNT
+    - field: like_count
+      source: likes.id
+      join:
+        table: likes
+        type: LEFT
+        on: posts.id = likes.post_id
+      aggregate:
+        type: COUNT
+    - field: latest_comment_at
+      source: comments.created_at
+      join:
+        table: comments
+        type: LEFT
+        on: users.id = comments.user_id
+      aggregate:
+        type: MAX
+"#,
+        )
+        .unwrap();
+        let sql = generate("user-stats.usml.yaml", &api::analyze(doc)).unwrap();
+        assert!(sql.contains(
+            "-- name: fetch\n\
+             SELECT users.id AS id, \
+             (SELECT COUNT(posts.id) FROM posts WHERE users.id = posts.user_id) AS post_count, \
+             (SELECT COUNT(likes.id) FROM posts LEFT JOIN likes ON posts.id = likes.post_id \
+             WHERE users.id = posts.user_id) AS like_count, \
+             MAX(comments.created_at) AS latest_comment_at\n\
+             FROM users LEFT JOIN comments ON users.id = comments.user_id\n\
+             WHERE TRUE\n\
+             GROUP BY users.id;\n"
+        ));
+    }
+
+    #[test]
+    fn test_implicit_group_by_uses_root_key() {
+        let doc = parser::parse(
+            r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["users"]
+    - ./schema.dbml#tables["likes"]
+usecase:
+  name: いいね数
+  response_mapping:
+    - field: name
+      source: users.name
+    - field: like_count
+      source: likes.id
+      join:
+        table: likes
+        on: users.id = likes.user_id
+      aggregate:
+        type: COUNT
+"#,
+        )
+        .unwrap();
+        let sql = generate("likes.usml.yaml", &api::analyze(doc)).unwrap();
+        assert!(sql.contains("GROUP BY users.id, users.name;\n"), "{}", sql);
+    }
+
+    const ARRAYS: &str = r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["posts"]
+    - ./schema.dbml#tables["comments"]
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: 投稿詳細
+  response_mapping:
+    - field: id
+      source: posts.id
+    - field: comments
+      type: array
+      source_table: comments
+      join:
+        table: comments
+        type: LEFT
+        on: posts.id = comments.post_id
+      fields:
+        - field: body
+          source: comments.body
+        - field: author_name
+          source: users.name
+          join:
+            table: users
+            on: comments.user_id = users.id
+"#;
+
+    #[test]
+    fn test_batch_arrays_use_parent_keys() {
+        let analysis = api::analyze(parser::parse(ARRAYS).unwrap());
+        let sql = generate("posts-detail.usml.yaml", &analysis).unwrap();
+        assert!(sql.contains(
+            "-- name: fetch\nSELECT posts.id AS id, posts.id AS comments_key\nFROM posts\n"
+        ));
+        assert!(sql.contains(
+            "-- name: fetch_comments\n\
+             SELECT posts.id AS parent_key, comments.body AS body, users.name AS author_name\n\
+             FROM posts INNER JOIN comments ON posts.id = comments.post_id \
+             LEFT JOIN users ON comments.user_id = users.id\n\
+             WHERE TRUE\n\
+             \x20 AND posts.id IN (:parent_keys);\n"
+        ));
+
+        let prepared = prepared("posts-detail.usml.yaml", &analysis).unwrap();
+        let comments = &prepared.statements[1];
+        assert!(comments.sql.ends_with("  AND posts.id = ANY($1)"));
+        assert_eq!(comments.params, ["parent_keys"]);
+    }
+
+    #[test]
+    fn test_json_arrays_are_columns() {
+        let yaml = format!(
+            "{}  hydration:\n    strategy: json\n    dialect: mysql\n",
+            ARRAYS
+        );
+        let sql = generate(
+            "posts-detail.usml.yaml",
+            &api::analyze(parser::parse(&yaml).unwrap()),
+        )
+        .unwrap();
+        assert!(!sql.contains("fetch_comments"));
+        assert!(sql.contains(
+            "SELECT posts.id AS id, \
+             (SELECT COALESCE(JSON_ARRAYAGG(JSON_OBJECT('body', comments.body, 'author_name', users.name)), JSON_ARRAY()) \
+             FROM comments LEFT JOIN users ON comments.user_id = users.id \
+             WHERE posts.id = comments.post_id) AS comments\n\
+             FROM posts\n"
+        ));
+    }
+}
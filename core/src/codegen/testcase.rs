@@ -0,0 +1,646 @@
+//! usecase ごとのテストケース（SQL・フィクスチャ・パラメータの値・期待する列）
+//!
+//! SQL は PostgreSQL のプレースホルダー（`$1..$n`）を使い、ページネーションは最初のページにする。
+//! フィクスチャは参照するテーブルに修飾子（エイリアスまたはテーブル名）ごとに 1 行入れ、
+//! 結合条件の等式でつながるカラムには同じ値を入れる。`カラム = :param` の条件のパラメータには
+//! そのカラムの値を束縛するので、条件がすべて等式ならフィクスチャの行が結果に返る。
+//! 値はカラムの意味（メールアドレス・価格など、[`crate::synth`]）に合わせる
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use serde::Serialize;
+
+use crate::api::{self, Analysis};
+use crate::ast::Literal;
+use crate::config::SynthConfig;
+use crate::export;
+use crate::model::Model;
+use crate::synth;
+
+use super::plan::{self, PlanError, ValueType};
+use super::snake_case;
+
+/// 1 つの usecase のテストケース
+#[derive(Debug, Clone, Serialize)]
+pub struct TestCase {
+    pub file: String,
+    pub usecase: String,
+    /// フィクスチャの INSERT 文（結合で参照される側のテーブルが先）
+    pub fixtures: Vec<String>,
+    /// 条件のパラメータに束縛する値（None は NULL）
+    pub bindings: BTreeMap<String, Option<Literal>>,
+    /// フィクスチャに対して行が返るか。等式で満たせない条件があれば false
+    pub expect_rows: bool,
+    pub queries: Vec<TestQuery>,
+    #[serde(skip)]
+    binding_types: BTreeMap<String, ValueType>,
+    #[serde(skip)]
+    identifier: String,
+}
+
+/// 1 つの階層を取るクエリと、結果に期待する列
+#[derive(Debug, Clone, Serialize)]
+pub struct TestQuery {
+    /// 取得する配列のフィールドのパス。ルートなら None
+    pub level: Option<String>,
+    pub sql: String,
+    /// `$1` から順に束縛するパラメータ名
+    pub params: Vec<String>,
+    /// 結果の列（response_mapping のフィールド名）
+    pub columns: Vec<String>,
+}
+
+/// フィクスチャに入れる値
+struct Sample {
+    value: ValueType,
+    /// None は NULL
+    text: Option<String>,
+}
+
+/// 1 行分のフィクスチャ
+struct Row {
+    qualifier: String,
+    table: String,
+    columns: Vec<String>,
+}
+
+/// `synth` は `[synth]` の設定。フィクスチャの値をカラムの意味に合わせる
+pub fn test_case(
+    file: &str,
+    analysis: &Analysis,
+    synth: &SynthConfig,
+) -> Result<TestCase, PlanError> {
+    let model = &analysis.model;
+    let usecase = &analysis.document.usecase;
+    let conditions = plan::conditions(&analysis.document);
+    let params = plan::condition_params(analysis, &conditions);
+
+    let mut rows = rows(analysis, &conditions);
+    // 結合条件の等式でつながるカラムを同じ組にする
+    let mut classes: BTreeMap<String, usize> = BTreeMap::new();
+    for row in &rows {
+        for column in &row.columns {
+            let next = classes.len();
+            classes
+                .entry(format!("{}.{}", row.qualifier, column))
+                .or_insert(next);
+        }
+    }
+    let mut parents: Vec<(String, String)> = Vec::new();
+    for join in &model.joins {
+        for (left, right) in equalities(&join.on) {
+            let (Some(left_class), Some(right_class)) =
+                (classes.get(&left).copied(), classes.get(&right).copied())
+            else {
+                continue;
+            };
+            for class in classes.values_mut() {
+                if *class == right_class {
+                    *class = left_class;
+                }
+            }
+            // 主キー側の行を先に入れる
+            if is_key(analysis, &rows, &left) {
+                parents.push((qualifier(&right), qualifier(&left)));
+            } else if is_key(analysis, &rows, &right) {
+                parents.push((qualifier(&left), qualifier(&right)));
+            }
+        }
+    }
+    order_rows(&mut rows, &parents);
+    let mut numbers: BTreeMap<usize, usize> = BTreeMap::new();
+    for row in &rows {
+        for column in &row.columns {
+            let class = classes[&format!("{}.{}", row.qualifier, column)];
+            let next = numbers.len() + 1;
+            numbers.entry(class).or_insert(next);
+        }
+    }
+    let sample = |qualifier: &str, column: &str| {
+        let row = rows.iter().find(|r| r.qualifier == qualifier)?;
+        let number = numbers[&classes[&format!("{}.{}", qualifier, column)]];
+        Some(sample(analysis, synth, &row.table, column, number))
+    };
+
+    let fixtures = rows
+        .iter()
+        .map(|row| {
+            let values: Vec<String> = row
+                .columns
+                .iter()
+                .map(|column| sql_literal(&sample(&row.qualifier, column).unwrap()))
+                .collect();
+            format!(
+                "INSERT INTO {} ({}) VALUES ({})",
+                analysis.model.db_table(&row.table),
+                row.columns.join(", "),
+                values.join(", ")
+            )
+        })
+        .collect();
+
+    let mut expect_rows = true;
+    let mut bindings = BTreeMap::new();
+    let mut binding_types = BTreeMap::new();
+    let raw_conditions = usecase
+        .filters
+        .iter()
+        .filter(|f| f.maps_to == "WHERE")
+        .filter_map(|f| f.condition.as_deref())
+        .chain(
+            usecase
+                .authorization
+                .iter()
+                .flat_map(|a| &a.row_filters)
+                .map(String::as_str),
+        );
+    let bound: Vec<(String, String)> = raw_conditions.flat_map(equalities).collect();
+    for param in &params {
+        let placeholder = format!(":{}", param.name);
+        let column = bound.iter().find_map(|(left, right)| {
+            if *right == placeholder {
+                Some(left)
+            } else if *left == placeholder {
+                Some(right)
+            } else {
+                None
+            }
+        });
+        let value = match column.and_then(|c| sample(&qualifier(c), c.rsplit('.').next()?)) {
+            Some(value) => value,
+            None => {
+                expect_rows = false;
+                sample_value(param.value, 1, &param.name, None)
+            }
+        };
+        bindings.insert(param.name.clone(), literal(&value));
+        binding_types.insert(param.name.clone(), value.value);
+    }
+    if conditions
+        .iter()
+        .any(|c| c.binds.iter().any(|b| !bindings.contains_key(b)))
+    {
+        expect_rows = false;
+    }
+
+    let queries = plan::queries(analysis)?
+        .iter()
+        .map(|query| {
+            let mut params: Vec<String> = Vec::new();
+            let columns: Vec<String> = query
+                .columns
+                .iter()
+                .map(|(field, expression)| format!("{} AS \"{}\"", expression, field.name))
+                .collect();
+            let mut sql = format!(
+                "SELECT {}{} {}",
+                if usecase.distinct { "DISTINCT " } else { "" },
+                columns.join(", "),
+                plan::from_sql(&analysis.model, query)
+            );
+            let mut wheres = Vec::new();
+            for condition in &conditions {
+                let text = plan::numbered(&condition.sql, &condition.binds, &mut params);
+                wheres.push(format!("({})", text));
+            }
+            if !wheres.is_empty() {
+                let _ = write!(sql, " WHERE {}", wheres.join(" AND "));
+            }
+            if !query.group_by.is_empty() {
+                let _ = write!(sql, " GROUP BY {}", query.group_by.join(", "));
+            }
+            if query.paged {
+                push_first_page(&mut sql, analysis, &query.from);
+            } else if query.level.is_none()
+                && let Some(limit) = usecase.limit.or(usecase.default_limit)
+            {
+                let _ = write!(sql, " LIMIT {}", limit);
+            }
+            TestQuery {
+                level: query.level.map(|i| model.fields[i].path.clone()),
+                sql,
+                params,
+                columns: query.columns.iter().map(|(f, _)| f.name.clone()).collect(),
+            }
+        })
+        .collect();
+
+    Ok(TestCase {
+        file: file.to_string(),
+        usecase: usecase.name.clone(),
+        fixtures,
+        bindings,
+        expect_rows,
+        queries,
+        binding_types,
+        identifier: export::identifier(file),
+    })
+}
+
+impl TestCase {
+    /// `#[sqlx::test]`（PostgreSQL）で実行するテストモジュール
+    pub fn to_rust(&self) -> String {
+        let mut out = format!(
+            "// usml generate tests で {} から生成（{}）\n\
+             //\n\
+             // #[sqlx::test] はテストごとに DATABASE_URL のサーバーにデータベースを作り、migrations を適用してから実行する\n\n\
+             use sqlx::{{Column, Executor, PgPool}};\n\n\
+             const FIXTURES: &[&str] = &[\n",
+            self.file, self.usecase
+        );
+        for fixture in &self.fixtures {
+            let _ = writeln!(out, "    {:?},", fixture);
+        }
+        out.push_str(
+            "];\n\n\
+             async fn insert_fixtures(pool: &PgPool) -> sqlx::Result<()> {\n    \
+             for &fixture in FIXTURES {\n        \
+             sqlx::query(fixture).execute(pool).await?;\n    \
+             }\n    \
+             Ok(())\n\
+             }\n",
+        );
+        for query in &self.queries {
+            let name = match &query.level {
+                None => "fetch".to_string(),
+                Some(path) => format!("fetch_{}", snake_case(path)),
+            };
+            let _ = writeln!(
+                out,
+                "\n#[sqlx::test]\nasync fn {}_{}(pool: PgPool) -> sqlx::Result<()> {{",
+                self.identifier, name
+            );
+            out.push_str("    insert_fixtures(&pool).await?;\n");
+            let _ = writeln!(out, "    let sql = {:?};", query.sql);
+            out.push_str(
+                "    let columns: Vec<String> = pool\n        \
+                 .describe(sql)\n        \
+                 .await?\n        \
+                 .columns()\n        \
+                 .iter()\n        \
+                 .map(|column| column.name().to_string())\n        \
+                 .collect();\n",
+            );
+            let columns: Vec<String> = query.columns.iter().map(|c| format!("{:?}", c)).collect();
+            let _ = writeln!(out, "    assert_eq!(columns, [{}]);", columns.join(", "));
+            let fetch = if self.expect_rows {
+                "    let rows = sqlx::query(sql)"
+            } else {
+                "    sqlx::query(sql)"
+            };
+            out.push_str(fetch);
+            for param in &query.params {
+                let value = &self.bindings[param];
+                let _ = write!(
+                    out,
+                    "\n        .bind({})",
+                    rust_value(self.binding_types[param], value.as_ref())
+                );
+            }
+            out.push_str("\n        .fetch_all(&pool)\n        .await?;\n");
+            if self.expect_rows {
+                out.push_str("    assert!(!rows.is_empty(), \"フィクスチャの行が返りません\");\n");
+            }
+            out.push_str("    Ok(())\n}\n");
+        }
+        out
+    }
+}
+
+/// フィクスチャに入れる行。参照されるカラムと、DBML で値が必須のカラム
+fn rows(analysis: &Analysis, conditions: &[plan::Condition]) -> Vec<Row> {
+    let model = &analysis.model;
+    let mut rows: Vec<Row> = Vec::new();
+    let mut add = |qualifier: &str, table: &str, column: &str| {
+        let index = match rows.iter().position(|r| r.qualifier == qualifier) {
+            Some(index) => index,
+            None => {
+                rows.push(Row {
+                    qualifier: qualifier.to_string(),
+                    table: table.to_string(),
+                    columns: Vec::new(),
+                });
+                rows.len() - 1
+            }
+        };
+        if !rows[index].columns.iter().any(|c| c == column) {
+            rows[index].columns.push(column.to_string());
+        }
+    };
+    for field in &model.fields {
+        let transforms = field.transforms.iter().flat_map(|t| &t.sources);
+        for source in field.source.iter().chain(transforms) {
+            add(&source.qualifier, &source.table, &source.column);
+        }
+    }
+    for reference in model.joins.iter().flat_map(|j| &j.references) {
+        add(&reference.qualifier, &reference.table, &reference.column);
+    }
+    for condition in conditions {
+        for (qualifier, column) in api::table_refs(&condition.sql) {
+            if let Some(table) = table_of(model, &qualifier) {
+                add(&qualifier, &table, &column);
+            }
+        }
+    }
+
+    for row in &mut rows {
+        let Some(table) = analysis
+            .schemas
+            .dbml_tables
+            .iter()
+            .find(|t| t.name == row.table)
+        else {
+            continue;
+        };
+        for column in &table.column_details {
+            let required = column.nullable == Some(false) || column.primary_key;
+            if required
+                && !column.increment
+                && column.default.is_none()
+                && !row.columns.contains(&column.name)
+            {
+                row.columns.push(column.name.clone());
+            }
+        }
+    }
+    rows
+}
+
+/// 修飾子の実テーブル
+fn table_of(model: &Model, qualifier: &str) -> Option<String> {
+    model
+        .tables
+        .iter()
+        .find(|t| t.name == qualifier || t.aliases.iter().any(|a| a == qualifier))
+        .map(|t| t.name.clone())
+}
+
+/// `a = b` の形の項（AND で区切る）。両辺は前後の空白を除いたまま返す
+fn equalities(expr: &str) -> Vec<(String, String)> {
+    expr.split(" AND ")
+        .flat_map(|part| part.split(" and "))
+        .filter_map(|part| {
+            let (left, right) = part.split_once('=')?;
+            if left.ends_with(['<', '>', '!']) || right.starts_with('=') {
+                return None;
+            }
+            let side = |s: &str| s.trim().trim_matches(['(', ')']).trim().to_string();
+            Some((side(left), side(right)))
+        })
+        .collect()
+}
+
+fn qualifier(column: &str) -> String {
+    column.split('.').next().unwrap_or_default().to_string()
+}
+
+/// `修飾子.カラム` が主キー（DBML の pk、無ければ `id`）か
+fn is_key(analysis: &Analysis, rows: &[Row], column: &str) -> bool {
+    let Some((qualifier, name)) = column.split_once('.') else {
+        return false;
+    };
+    let Some(row) = rows.iter().find(|r| r.qualifier == qualifier) else {
+        return false;
+    };
+    match plan::column(analysis, &row.table, name) {
+        Some(column) => column.primary_key,
+        None => name == "id",
+    }
+}
+
+/// 参照される側（`parents` の右）の行が先になるように並べる
+fn order_rows(rows: &mut Vec<Row>, parents: &[(String, String)]) {
+    let mut ordered: Vec<Row> = Vec::new();
+    while !rows.is_empty() {
+        let ready = rows
+            .iter()
+            .position(|row| {
+                !parents.iter().any(|(child, parent)| {
+                    child == &row.qualifier
+                        && parent != child
+                        && rows.iter().any(|r| &r.qualifier == parent)
+                })
+            })
+            // 循環していれば宣言順に入れる
+            .unwrap_or(0);
+        ordered.push(rows.remove(ready));
+    }
+    *rows = ordered;
+}
+
+/// 最初のページの ORDER BY・LIMIT
+fn push_first_page(sql: &mut String, analysis: &Analysis, from: &str) {
+    let usecase = &analysis.document.usecase;
+    let page = plan::filter(usecase, "PAGINATION");
+    if let Some(page) = page
+        && page.strategy.as_deref() == Some("cursor")
+    {
+        let _ = write!(sql, " ORDER BY {} DESC", plan::cursor_column(page, from));
+    } else if let Some(sort) = plan::filter(usecase, "ORDER_BY")
+        && let Some(column) =
+            sort.default_column
+                .as_ref()
+                .or(sort.allowed_columns.iter().flatten().next())
+    {
+        let direction = match sort.default_direction.as_deref() {
+            Some(d) if d.eq_ignore_ascii_case("ASC") => "ASC",
+            _ => "DESC",
+        };
+        let _ = write!(sql, " ORDER BY {} {}", column, direction);
+    }
+    if let Some(page) = page {
+        let _ = write!(sql, " LIMIT {}", plan::page_size(usecase, page));
+    }
+}
+
+fn sample(
+    analysis: &Analysis,
+    synth: &SynthConfig,
+    table: &str,
+    column: &str,
+    number: usize,
+) -> Sample {
+    let dbml = plan::column(analysis, table, column);
+    let value = match dbml {
+        Some(dbml) => plan::column_type(dbml),
+        None if column == "id" || column.ends_with("_id") => ValueType::Long,
+        None => ValueType::String,
+    };
+    if dbml.is_none_or(|c| c.enum_values.is_empty())
+        && let Some(literal) = synth::value(
+            synth,
+            Some(table),
+            column,
+            dbml.map(|c| c.data_type.as_str()),
+            dbml.is_some_and(|c| c.primary_key),
+            number as u64,
+        )
+        && plan::accepts(value, &literal)
+    {
+        let text = match literal {
+            Literal::String(s) => s,
+            literal => literal.to_string(),
+        };
+        return Sample {
+            value,
+            text: Some(text),
+        };
+    }
+    sample_value(
+        value,
+        number,
+        column,
+        dbml.and_then(|c| c.enum_values.first()),
+    )
+}
+
+/// 型ごとのサンプル値。`number` は等しい値を持つカラムの組の番号
+fn sample_value(
+    value: ValueType,
+    number: usize,
+    column: &str,
+    enum_value: Option<&String>,
+) -> Sample {
+    let text = match value {
+        ValueType::Int
+        | ValueType::Long
+        | ValueType::Float
+        | ValueType::Double
+        | ValueType::Decimal => number.to_string(),
+        ValueType::Boolean => "true".to_string(),
+        ValueType::String => match enum_value {
+            Some(enum_value) => enum_value.clone(),
+            None => format!("{}-{}", column, number),
+        },
+        ValueType::Uuid => format!("00000000-0000-0000-0000-{:012}", number),
+        ValueType::Date => "2024-01-01".to_string(),
+        ValueType::Time => "12:00:00".to_string(),
+        ValueType::DateTime => "2024-01-01T12:00:00".to_string(),
+        ValueType::OffsetDateTime => "2024-01-01T12:00:00Z".to_string(),
+        ValueType::Any => return Sample { value, text: None },
+    };
+    Sample {
+        value,
+        text: Some(text),
+    }
+}
+
+fn is_numeric(value: ValueType) -> bool {
+    matches!(
+        value,
+        ValueType::Int
+            | ValueType::Long
+            | ValueType::Float
+            | ValueType::Double
+            | ValueType::Decimal
+    )
+}
+
+fn sql_literal(sample: &Sample) -> String {
+    match &sample.text {
+        None => "NULL".to_string(),
+        Some(text) if is_numeric(sample.value) || sample.value == ValueType::Boolean => {
+            text.clone()
+        }
+        Some(text) => format!("'{}'", text.replace('\'', "''")),
+    }
+}
+
+fn literal(sample: &Sample) -> Option<Literal> {
+    let text = sample.text.as_ref()?;
+    Some(match text.parse::<i64>() {
+        Ok(number) if is_numeric(sample.value) => Literal::Integer(number),
+        _ if sample.value == ValueType::Boolean => Literal::Bool(text == "true"),
+        _ => Literal::String(text.clone()),
+    })
+}
+
+/// `.bind()` に渡す Rust の式
+fn rust_value(value: ValueType, literal: Option<&Literal>) -> String {
+    let text = match literal {
+        Some(Literal::String(s)) => s.clone(),
+        Some(Literal::Bool(b)) => b.to_string(),
+        Some(Literal::Integer(i)) => i.to_string(),
+        Some(Literal::Number(n)) => n.to_string(),
+        None => return "None::<String>".to_string(),
+    };
+    match value {
+        ValueType::Int => format!("{}_i32", text),
+        ValueType::Long => format!("{}_i64", text),
+        ValueType::Float => format!("{}.0_f32", text),
+        ValueType::Double => format!("{}.0_f64", text),
+        ValueType::Decimal => format!("rust_decimal::Decimal::from({})", text),
+        ValueType::Boolean => text,
+        ValueType::String => format!("{:?}", text),
+        ValueType::Uuid => format!("{:?}.parse::<uuid::Uuid>().unwrap()", text),
+        ValueType::Date => format!("{:?}.parse::<chrono::NaiveDate>().unwrap()", text),
+        ValueType::Time => format!("{:?}.parse::<chrono::NaiveTime>().unwrap()", text),
+        ValueType::DateTime => format!("{:?}.parse::<chrono::NaiveDateTime>().unwrap()", text),
+        ValueType::OffsetDateTime => format!(
+            "{:?}.parse::<chrono::DateTime<chrono::Utc>>().unwrap()",
+            text
+        ),
+        ValueType::Any => "None::<String>".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{api, parser};
+
+    #[test]
+    fn test_case_links_join_columns_and_bindings() {
+        let doc = parser::parse(
+            r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["posts"]
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: 投稿一覧
+  response_mapping:
+    - field: title
+      source: posts.title
+    - field: authorName
+      source: users.name
+      join:
+        table: users
+        on: posts.user_id = users.id
+  filters:
+    - param: user_id
+      maps_to: WHERE
+      condition: users.id = :user_id
+"#,
+        )
+        .unwrap();
+        let case = test_case(
+            "posts-list.usml.yaml",
+            &api::analyze(doc),
+            &SynthConfig::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            case.fixtures,
+            vec![
+                "INSERT INTO users (name, id) VALUES ('鈴木 翔太', 2)",
+                "INSERT INTO posts (title, user_id) VALUES ('チームの振り返り', 2)",
+            ]
+        );
+        assert_eq!(case.bindings["user_id"], Some(Literal::Integer(2)));
+        assert!(case.expect_rows);
+        assert_eq!(
+            case.queries[0].sql,
+            "SELECT posts.title AS \"title\", users.name AS \"authorName\" \
+             FROM posts LEFT JOIN users ON posts.user_id = users.id WHERE (users.id = $1)"
+        );
+        assert_eq!(case.queries[0].columns, vec!["title", "authorName"]);
+        assert!(case.to_rust().contains(
+            "    assert_eq!(columns, [\"title\", \"authorName\"]);\n    let rows = sqlx::query(sql)\n        .bind(2_i64)\n"
+        ));
+    }
+}
@@ -0,0 +1,173 @@
+use crate::config::ComplexityConfig;
+use crate::rules::{self, CustomRule, Diagnostics, Rule, RuleContext};
+use crate::stats;
+
+/// `[lint.complexity]` の上限を超える usecase を報告する
+///
+/// 重大度を設定で変えられるよう、`rules::COMPLEXITY` を元にした規則を持つ
+#[derive(Debug)]
+pub struct ComplexityRule {
+    rule: Rule,
+    max_fields: Option<usize>,
+    max_join_depth: Option<usize>,
+    max_tables: Option<usize>,
+    max_nesting_depth: Option<usize>,
+}
+
+impl ComplexityRule {
+    pub fn new(config: &ComplexityConfig) -> Self {
+        let base = &rules::COMPLEXITY;
+        ComplexityRule {
+            rule: Rule {
+                code: base.code.clone(),
+                name: base.name.clone(),
+                category: base.category,
+                severity: config.severity,
+                summary: base.summary.clone(),
+                explanation: base.explanation.clone(),
+            },
+            max_fields: config.max_fields,
+            max_join_depth: config.max_join_depth,
+            max_tables: config.max_tables,
+            max_nesting_depth: config.max_nesting_depth,
+        }
+    }
+}
+
+impl CustomRule for ComplexityRule {
+    fn rule(&self) -> &Rule {
+        &self.rule
+    }
+
+    fn check(&self, cx: &RuleContext<'_>, diagnostics: &mut Diagnostics<'_>) {
+        let model = cx.model;
+        let name = &cx.document.usecase.name;
+        if let Some(max) = self.max_fields
+            && model.fields.len() > max
+        {
+            diagnostics.report(format!(
+                "usecase '{}' のフィールドが {} 個あります（上限 {}）",
+                name,
+                model.fields.len(),
+                max
+            ));
+        }
+        if let Some(max) = self.max_join_depth
+            && let Some((field, depth)) = model
+                .fields
+                .iter()
+                .map(|f| (f, stats::join_depth(model, f)))
+                .max_by_key(|&(_, depth)| depth)
+            && depth > max
+        {
+            diagnostics.report_node(
+                format!(
+                    "フィールド {} に届くまでの結合が {} 段あります（上限 {}）",
+                    field.path, depth, max
+                ),
+                &field.pointer,
+            );
+        }
+        if let Some(max) = self.max_tables {
+            let tables = stats::referenced_tables(model);
+            if tables.len() > max {
+                diagnostics.report(format!(
+                    "usecase '{}' が {} 個のテーブルを参照しています（上限 {}）: {}",
+                    name,
+                    tables.len(),
+                    max,
+                    tables.join(", ")
+                ));
+            }
+        }
+        if let Some(max) = self.max_nesting_depth
+            && let Some(field) = model.fields.iter().max_by_key(|f| f.depth)
+            && field.depth > max
+        {
+            diagnostics.report_node(
+                format!(
+                    "フィールド {} が {} 段入れ子になっています（上限 {}）",
+                    field.path, field.depth, max
+                ),
+                &field.pointer,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::diagnostic::Severity;
+    use crate::parser;
+    use crate::validator::Validator;
+
+    const DOC: &str = r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["posts"]
+    - ./schema.dbml#tables["users"]
+    - ./schema.dbml#tables["profiles"]
+usecase:
+  name: 投稿詳細
+  response_mapping:
+    - field: title
+      source: posts.title
+    - field: author_name
+      source: users.name
+      join:
+        table: users
+        on: posts.user_id = users.id
+    - field: author_bio
+      source: profiles.bio
+      join_chain:
+        - table: users
+          on: posts.user_id = users.id
+        - table: profiles
+          on: users.id = profiles.user_id
+    - field: meta
+      type: object
+      fields:
+        - field: author
+          type: object
+          fields:
+            - field: id
+              source: users.id
+"#;
+
+    #[test]
+    fn test_thresholds_report_with_configured_severity() {
+        let validate = |toml: &str| {
+            let config = Config::parse(toml, "usml.toml").unwrap();
+            Validator::from_config(&config)
+                .unwrap()
+                .validate(&parser::parse(DOC).unwrap())
+        };
+
+        let found =
+            validate("[lint.complexity]\nmax_fields = 2\nmax_join_depth = 1\nmax_tables = 2\n");
+        let messages: Vec<_> = found.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages.len(), 3);
+        assert!(messages[0].contains("フィールドが 6 個あります（上限 2）"));
+        assert!(
+            messages[1].contains("フィールド author_bio に届くまでの結合が 2 段あります（上限 1）")
+        );
+        assert!(messages[2].contains("3 個のテーブルを参照しています（上限 2）"));
+        assert!(found.iter().all(|d| d.severity == Severity::Warning));
+
+        let found = validate("[lint.complexity]\nmax_tables = 2\nseverity = \"error\"\n");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].severity, Severity::Error);
+        assert!(validate("[lint.complexity]\nmax_fields = 6\n").is_empty());
+
+        let found = validate("[lint.complexity]\nmax_nesting_depth = 1\n");
+        assert_eq!(found.len(), 1);
+        assert!(
+            found[0]
+                .message
+                .contains("フィールド meta.author.id が 2 段入れ子になっています（上限 1）")
+        );
+        assert!(validate("[lint.complexity]\nmax_nesting_depth = 2\n").is_empty());
+    }
+}
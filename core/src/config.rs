@@ -0,0 +1,408 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::ast::Literal;
+use crate::diagnostic::Severity;
+use crate::naming::Case;
+use crate::normalize::KeywordCase;
+use crate::resolver::Limits;
+use crate::synth::Kind;
+
+/// プロジェクト設定ファイルの名前
+pub const CONFIG_FILE: &str = "usml.toml";
+
+/// `usml.toml` の内容
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    pub lint: LintConfig,
+    #[serde(default)]
+    pub governance: GovernanceConfig,
+    #[serde(default)]
+    pub visualize: VisualizeConfig,
+    /// `[limits]`。import の解決の上限
+    #[serde(default)]
+    pub limits: Limits,
+    #[serde(default)]
+    pub fmt: FmtConfig,
+    #[serde(default)]
+    pub synth: SynthConfig,
+    /// `[size]`。レスポンスサイズの見積もり
+    #[serde(default)]
+    pub size: SizeConfig,
+}
+
+/// `[fmt]` セクション。`usml fmt` で行う正規化（指定したものだけ行う）
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FmtConfig {
+    /// import.dbml を参照の文字列順に並べる
+    #[serde(default)]
+    pub sort_imports: bool,
+    /// response_mapping（入れ子の fields も）を OpenAPI のプロパティが書かれた順に並べる
+    #[serde(default)]
+    pub order_fields: bool,
+    /// join.type の表記（`upper` なら `LEFT`、`lower` なら `left`。`LEFT JOIN` も `LEFT` にする）
+    pub join_type: Option<KeywordCase>,
+    /// 既定値と同じ値（`join.type: LEFT`・`collection: false`・`distinct: false`・空の `filters` など）を書かない
+    #[serde(default)]
+    pub collapse_defaults: bool,
+}
+
+impl FmtConfig {
+    /// 正規化を 1 つも指定していないか
+    pub fn is_empty(&self) -> bool {
+        !self.sort_imports
+            && !self.order_fields
+            && self.join_type.is_none()
+            && !self.collapse_defaults
+    }
+}
+
+/// `[synth]` セクション。モック・フィクスチャに入れる値の作り方
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SynthConfig {
+    /// `テーブル.カラム`（`*` は任意の文字列）ごとの値の種類（`"email"`）か候補の値の一覧
+    #[serde(default)]
+    pub columns: BTreeMap<String, ColumnSynth>,
+}
+
+impl SynthConfig {
+    /// カラムの指定。`テーブル.カラム` そのもののキーをワイルドカードより優先する
+    pub fn column(&self, table: &str, column: &str) -> Option<&ColumnSynth> {
+        let name = format!("{}.{}", table, column);
+        self.columns.get(&name).or_else(|| {
+            self.columns
+                .iter()
+                .find(|(pattern, _)| wildcard_match(pattern, &name))
+                .map(|(_, synth)| synth)
+        })
+    }
+}
+
+/// `[synth.columns]` の 1 カラムの指定
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum ColumnSynth {
+    /// 値の種類（[`Kind`] の snake_case の名前）
+    Kind(Kind),
+    /// 候補の値。番号に応じて順に選ぶ
+    Values(Vec<Literal>),
+}
+
+/// `[size]` セクション。`usml size` でレスポンスのサイズを見積もるときの前提と予算
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SizeConfig {
+    /// 最悪の場合のサイズ（バイト）の上限。超える usecase を報告する
+    pub budget: Option<u64>,
+    /// ページネーションの無い配列の典型的な要素数
+    pub items: u32,
+    /// ページネーションの無い配列と、上限の無い limit_param で取るページの最大の要素数
+    pub max_items: u32,
+    /// 長さの分からない文字列の最大のバイト数
+    pub max_string: u64,
+}
+
+impl Default for SizeConfig {
+    fn default() -> Self {
+        Self {
+            budget: None,
+            items: 10,
+            max_items: 100,
+            max_string: 1024,
+        }
+    }
+}
+
+/// `[visualize]` セクション。`usml visualize` の出力先
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VisualizeConfig {
+    /// 出力先ディレクトリ。省略時は `output`
+    pub output_dir: Option<String>,
+    /// 出力先ディレクトリからの相対パスのテンプレート（変数は [`crate::output::render`]）
+    pub output_template: Option<String>,
+}
+
+/// `[governance]` セクション
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GovernanceConfig {
+    /// 個人情報を含むカラム（`テーブル.カラム`、`*` は任意の文字列）
+    #[serde(default)]
+    pub pii: Vec<String>,
+}
+
+impl GovernanceConfig {
+    /// `pii` のいずれかに一致するか
+    pub fn is_pii(&self, table: &str, column: &str) -> bool {
+        let name = format!("{}.{}", table, column);
+        self.pii
+            .iter()
+            .any(|pattern| wildcard_match(pattern, &name))
+    }
+}
+
+/// `*` だけを特別扱いするワイルドカードの一致
+fn wildcard_match(pattern: &str, value: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = value.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+/// `[lint]` セクション
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LintConfig {
+    #[serde(default)]
+    pub naming: NamingConfig,
+    /// `[[lint.rules]]` で定義する宣言的な規則
+    #[serde(default)]
+    pub rules: Vec<RuleDefinition>,
+    /// `aggregate.type` に使える集約関数。省略時は COUNT・SUM・AVG・MIN・MAX
+    pub aggregates: Option<Vec<String>>,
+    /// `[lint.ownership]`。指定した場合だけ usecase の owner・reviewers を検査する
+    pub ownership: Option<OwnershipConfig>,
+    /// `[lint.complexity]`。指定した上限を超える usecase を報告する
+    pub complexity: Option<ComplexityConfig>,
+    /// 実行する規則（コード `U004` か名前 `join.alias`）。指定するとこれらの規則の診断だけを報告する
+    #[serde(default)]
+    pub enable: Vec<String>,
+    /// 報告しない規則（コードか名前）
+    #[serde(default)]
+    pub disable: Vec<String>,
+    /// `[lint.severity]`。規則（コードか名前）ごとの重大度の上書き
+    #[serde(default)]
+    pub severity: BTreeMap<String, Severity>,
+    /// 警告をエラーとして報告する
+    #[serde(default)]
+    pub warnings_as_errors: bool,
+}
+
+/// `[lint.naming]` セクション。指定した対象だけ表記を検査する
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NamingConfig {
+    /// レスポンスフィールド名の表記
+    pub fields: Option<Case>,
+    /// filters[].param の表記
+    pub params: Option<Case>,
+}
+
+/// `[lint.ownership]` セクション
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OwnershipConfig {
+    /// owner に指定できるチーム。空なら owner があればよい
+    #[serde(default)]
+    pub teams: Vec<String>,
+    /// reviewers に必要な人数
+    #[serde(default)]
+    pub min_reviewers: usize,
+}
+
+/// `[lint.complexity]` セクション。指定した上限だけを検査する
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ComplexityConfig {
+    /// レスポンスフィールドの数（入れ子含む）の上限
+    pub max_fields: Option<usize>,
+    /// フィールドに届くまでに通る結合の段数の上限
+    pub max_join_depth: Option<usize>,
+    /// 参照するテーブルの数の上限
+    pub max_tables: Option<usize>,
+    /// レスポンスフィールドの入れ子の段数（トップレベルのフィールドは 0 段）の上限
+    pub max_nesting_depth: Option<usize>,
+    #[serde(default = "default_complexity_severity")]
+    pub severity: Severity,
+}
+
+fn default_complexity_severity() -> Severity {
+    Severity::Warning
+}
+
+/// 宣言的な規則 1 つ分の定義
+///
+/// `each` で選んだ要素それぞれについて `pattern`・`require`・`forbid` をすべて満たすかを調べる
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuleDefinition {
+    /// 規則コード（組み込み規則の `U` と衝突しない接頭辞を使う）
+    pub code: String,
+    /// 診断の `rule` に出す名前。省略時はコード
+    pub name: Option<String>,
+    pub summary: String,
+    #[serde(default)]
+    pub explanation: String,
+    #[serde(default = "default_severity")]
+    pub severity: Severity,
+    pub each: Target,
+    /// 要素の名前が一致すべき正規表現
+    pub pattern: Option<String>,
+    /// 要素に設定されているべき属性
+    #[serde(default)]
+    pub require: Vec<String>,
+    /// 要素の属性が取ってはいけない値（大文字・小文字は区別しない）
+    #[serde(default)]
+    pub forbid: Vec<Forbid>,
+    /// 違反時のメッセージ。`{name}` は要素の名前に置き換える
+    pub message: Option<String>,
+}
+
+fn default_severity() -> Severity {
+    Severity::Error
+}
+
+/// 宣言的な規則が対象にする要素
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Target {
+    /// レスポンスフィールド（入れ子含む）
+    Field,
+    /// `type: array` のフィールド
+    ArrayField,
+    Join,
+    Filter,
+    Transform,
+    Table,
+}
+
+/// `forbid = [{ attribute = "type", values = ["RIGHT"] }]`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Forbid {
+    pub attribute: String,
+    pub values: Vec<String>,
+}
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("設定ファイル読み込みエラー '{file}': {source}")]
+    Io {
+        file: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("設定ファイルの形式が不正です '{file}': {source}")]
+    Parse {
+        file: String,
+        #[source]
+        source: Box<toml::de::Error>,
+    },
+    #[error("規則 {code} の定義が不正です: {message}")]
+    InvalidRule { code: String, message: String },
+    #[error("[lint] の {key} に指定した規則 '{rule}' がありません")]
+    UnknownRule { key: &'static str, rule: String },
+}
+
+impl Config {
+    /// TOML 文字列から読み込む。`file` はエラーメッセージに使う
+    pub fn parse(content: &str, file: &str) -> Result<Self, ConfigError> {
+        toml::from_str(content).map_err(|e| ConfigError::Parse {
+            file: file.to_string(),
+            source: Box::new(e),
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let file = path.to_string_lossy().to_string();
+        let content = fs::read_to_string(path).map_err(|e| ConfigError::Io {
+            file: file.clone(),
+            source: e,
+        })?;
+        Self::parse(&content, &file)
+    }
+
+    /// `dir` から親ディレクトリへ遡って最初に見つかった `usml.toml` の場所
+    pub fn find(dir: &Path) -> Option<PathBuf> {
+        dir.ancestors()
+            .map(|d| d.join(CONFIG_FILE))
+            .find(|path| path.is_file())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_lint_rules() {
+        let config = Config::parse(
+            r#"
+[[lint.rules]]
+code = "ACME001"
+summary = "RIGHT JOIN を使わない"
+each = "join"
+forbid = [{ attribute = "type", values = ["right"] }]
+
+[[lint.rules]]
+code = "ACME002"
+summary = "フィールド名は snake_case"
+severity = "warning"
+each = "field"
+pattern = "^[a-z][a-z0-9_]*$"
+"#,
+            "usml.toml",
+        )
+        .unwrap();
+
+        let rules = &config.lint.rules;
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].each, Target::Join);
+        assert_eq!(rules[0].severity, Severity::Error);
+        assert_eq!(rules[1].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown_keys() {
+        let err = Config::parse("[lint]\nrule = []\n", "usml.toml").unwrap_err();
+        assert!(matches!(err, ConfigError::Parse { .. }));
+    }
+
+    #[test]
+    fn test_parse_fmt() {
+        let config = Config::parse(
+            "[fmt]\nsort_imports = true\njoin_type = \"upper\"\n",
+            "usml.toml",
+        )
+        .unwrap();
+        assert!(config.fmt.sort_imports);
+        assert!(!config.fmt.order_fields);
+        assert_eq!(config.fmt.join_type, Some(KeywordCase::Upper));
+        assert!(Config::default().fmt.is_empty());
+    }
+
+    #[test]
+    fn test_governance_pii_patterns() {
+        let config = Config::parse(
+            "[governance]\npii = [\"users.email\", \"*.phone*\"]\n",
+            "usml.toml",
+        )
+        .unwrap();
+        let governance = &config.governance;
+        assert!(governance.is_pii("users", "email"));
+        assert!(governance.is_pii("profiles", "phone_number"));
+        assert!(!governance.is_pii("users", "name"));
+        assert!(!governance.is_pii("admins", "email"));
+    }
+}
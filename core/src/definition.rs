@@ -0,0 +1,195 @@
+//! 定義へのジャンプ
+//!
+//! カーソル位置の参照の定義がある場所を返す。import の参照は OpenAPI のオペレーション・DBML のテーブル、
+//! カラム参照（`source`・`join.on` など）は DBML のカラム、transform の `target` は同じ USML ファイルのフィールドに飛ぶ。
+//! import 先の位置はリゾルバーが解決時に記録したもの（`DbmlTable::location` など）を使う
+
+use serde::Serialize;
+
+use crate::diagnostic::Location;
+use crate::edit;
+use crate::hover::{COLUMN_KEYS, find_scalar, token_at};
+use crate::model::Model;
+use crate::parser;
+use crate::resolver::dbml::parse_dbml_ref;
+use crate::syntax::{Position, Range, Span, SyntaxError, SyntaxTree};
+use crate::validator::ResolveContext;
+
+/// 定義の位置
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Definition {
+    /// 定義の場所。import 先のファイルか、USML ファイル自身（`file`）
+    pub location: Location,
+    /// ジャンプ元の参照の範囲
+    pub span: Span,
+    pub range: Range,
+}
+
+/// `file` の USML のカーソル位置（1 始まりの行・列）にある参照の定義
+///
+/// 参照が無い位置や、定義の位置が分からない（`ctx` に無い・解決できなかった）参照では `Ok(None)`
+pub fn definition(
+    source: &str,
+    file: &str,
+    ctx: &ResolveContext,
+    position: Position,
+) -> Result<Option<Definition>, SyntaxError> {
+    let tree = SyntaxTree::parse(source)?;
+    let offset = tree.offset(source, position.line, position.column);
+    let Some(found) = find_scalar(&tree.root, offset, "", false, &[]) else {
+        return Ok(None);
+    };
+    let value = found.node.as_str().unwrap_or_default();
+    let table_location = |table: &str| {
+        ctx.dbml_tables
+            .iter()
+            .find(|t| t.name == table)
+            .and_then(|t| t.location.clone())
+    };
+
+    let (location, span) = match found.key {
+        "openapi" => (
+            ctx.openapi.as_ref().and_then(|o| o.location.clone()),
+            found.node.span,
+        ),
+        "dbml" => (
+            parse_dbml_ref(value).and_then(|(_, table)| table_location(table)),
+            found.node.span,
+        ),
+        "table" | "source_table" => (table_location(value), found.node.span),
+        "target" => (field_location(&tree, source, file, value), found.node.span),
+        "field" if !found.response_field => {
+            (field_location(&tree, source, file, value), found.node.span)
+        }
+        key if COLUMN_KEYS.contains(&key) => {
+            let span = token_at(source, found.node.span, offset);
+            let Some((qualifier, column)) = source[span.start..span.end].split_once('.') else {
+                return Ok(None);
+            };
+            // 編集途中で AST にできない場合はエイリアスを解決せずに探す
+            let model = parser::parse(source).ok().map(|doc| Model::build(&doc));
+            let table = model
+                .as_ref()
+                .map_or(qualifier, |m| m.resolve_table(qualifier));
+            let location = ctx
+                .dbml_tables
+                .iter()
+                .find(|t| t.name == table)
+                .and_then(|t| {
+                    t.column(column)
+                        .and_then(|c| c.location.clone())
+                        .or_else(|| t.location.clone())
+                });
+            (location, span)
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(location.map(|location| Definition {
+        location,
+        span,
+        range: tree.range(source, span),
+    }))
+}
+
+/// レスポンスフィールド（`comments.body`）の `field` の位置
+fn field_location(tree: &SyntaxTree, source: &str, file: &str, path: &str) -> Option<Location> {
+    let field = edit::find_mapping(tree, path)?.get("field")?;
+    Some(tree.location(source, file, field.span.start))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resolver::{DbmlColumn, DbmlTable, OpenapiResponse};
+
+    const DOC: &str = r#"version: "0.1"
+import:
+  openapi: ./api.yaml#paths["/posts/{id}"].get.responses["200"]
+  dbml:
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: 投稿詳細
+  response_mapping:
+    - field: author
+      source: u.name
+      join:
+        table: users
+        alias: u
+        on: u.id = posts.user_id
+    - field: comments
+      type: array
+      fields:
+        - field: body
+          source: comments.body
+  transforms:
+    - target: comments.body
+      type: MASK
+"#;
+
+    fn ctx() -> ResolveContext {
+        ResolveContext {
+            openapi: Some(OpenapiResponse {
+                location: Some(Location::at("specs/api.yaml", 12, 5)),
+                ..OpenapiResponse::default()
+            }),
+            dbml_tables: vec![DbmlTable {
+                name: "users".to_string(),
+                columns: vec!["id".to_string(), "name".to_string()],
+                column_details: vec![
+                    DbmlColumn {
+                        name: "id".to_string(),
+                        location: Some(Location::at("specs/schema.dbml", 2, 3)),
+                        ..DbmlColumn::default()
+                    },
+                    DbmlColumn {
+                        name: "name".to_string(),
+                        location: Some(Location::at("specs/schema.dbml", 3, 3)),
+                        ..DbmlColumn::default()
+                    },
+                ],
+                location: Some(Location::at("specs/schema.dbml", 1, 7)),
+            }],
+        }
+    }
+
+    fn at(line: usize, column: usize) -> Option<Definition> {
+        definition(
+            DOC,
+            "specs/posts.usml.yaml",
+            &ctx(),
+            Position { line, column },
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_definition_of_imports() {
+        let openapi = at(3, 20).unwrap();
+        assert_eq!(openapi.location, Location::at("specs/api.yaml", 12, 5));
+        let dbml = at(5, 10).unwrap();
+        assert_eq!(dbml.location, Location::at("specs/schema.dbml", 1, 7));
+        assert_eq!(at(12, 17).unwrap().location, dbml.location);
+    }
+
+    #[test]
+    fn test_definition_of_column_resolves_alias() {
+        let name = at(10, 17).unwrap();
+        assert_eq!(name.location, Location::at("specs/schema.dbml", 3, 3));
+        assert_eq!(&DOC[name.span.start..name.span.end], "u.name");
+        let id = at(14, 13).unwrap();
+        assert_eq!(id.location, Location::at("specs/schema.dbml", 2, 3));
+        // DBML に無いテーブル
+        assert_eq!(at(19, 20), None);
+    }
+
+    #[test]
+    fn test_definition_of_transform_target() {
+        let target = at(21, 16).unwrap();
+        assert_eq!(
+            target.location,
+            Location::at("specs/posts.usml.yaml", 18, 18)
+        );
+        assert_eq!(&DOC[target.span.start..target.span.end], "comments.body");
+    }
+}
@@ -0,0 +1,228 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rules::{self, Rule};
+use crate::validator::ValidationError;
+
+/// 診断の重大度
+///
+/// 情報（info）とヒント（hint）は報告に出すだけで、終了コードや「問題のあるファイル」の数には影響しない
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    /// 暗黙に適用した既定の動作などのお知らせ
+    Info,
+    /// 書き方の提案。直前の同じ規則の診断に添える
+    Hint,
+}
+
+impl Severity {
+    /// JSON 出力などで使う小文字の名前
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+            Severity::Hint => "hint",
+        }
+    }
+
+    /// エラー・警告か（情報・ヒントでないか）
+    pub fn is_problem(&self) -> bool {
+        matches!(self, Severity::Error | Severity::Warning)
+    }
+}
+
+/// 診断が指し示すファイル内の位置（行・列は 1 始まり）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Location {
+    pub file: String,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+impl Location {
+    /// 行・列が不明なファイル全体への位置
+    pub fn file(file: impl Into<String>) -> Self {
+        Location {
+            file: file.into(),
+            line: None,
+            column: None,
+        }
+    }
+
+    pub fn at(file: impl Into<String>, line: usize, column: usize) -> Self {
+        Location {
+            file: file.into(),
+            line: Some(line),
+            column: Some(column),
+        }
+    }
+
+    /// `content` のバイト位置 `offset` を指す位置（列は文字単位）
+    pub fn at_offset(file: impl Into<String>, content: &str, offset: usize) -> Self {
+        let before = &content[..offset.min(content.len())];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        Location::at(
+            file,
+            before.matches('\n').count() + 1,
+            before[line_start..].chars().count() + 1,
+        )
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.file)?;
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+            if let Some(column) = self.column {
+                write!(f, ":{}", column)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// ルール違反・外部ファイルの解決エラーを共通の形で表す診断
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// 規則の安定したコード（U001 など）。パースエラーなど規則に属さない診断では None
+    pub code: Option<String>,
+    pub rule: String,
+    pub message: String,
+    pub location: Option<Location>,
+    /// 診断に添えたヒント（直し方の提案など）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hints: Vec<String>,
+    /// 診断の対象の USML ドキュメント上のノード（JSON Pointer。例: `/usecase/response_mapping/3/join/on`）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pointer: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn error(rule: impl Into<String>, message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            code: None,
+            rule: rule.into(),
+            message: message.into(),
+            location: None,
+            hints: Vec::new(),
+            pointer: None,
+        }
+    }
+
+    pub fn warning(rule: impl Into<String>, message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            code: None,
+            rule: rule.into(),
+            message: message.into(),
+            location: None,
+            hints: Vec::new(),
+            pointer: None,
+        }
+    }
+
+    /// 規則の既定の重大度で診断を作る
+    pub fn from_rule(rule: &Rule, message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity: rule.severity,
+            code: Some(rule.code.to_string()),
+            rule: rule.name.to_string(),
+            message: message.into(),
+            location: None,
+            hints: Vec::new(),
+            pointer: None,
+        }
+    }
+
+    pub fn with_location(mut self, location: Option<Location>) -> Self {
+        self.location = location;
+        self
+    }
+
+    pub fn with_hint(mut self, hint: impl Into<String>) -> Self {
+        self.hints.push(hint.into());
+        self
+    }
+
+    pub fn with_pointer(mut self, pointer: impl Into<String>) -> Self {
+        self.pointer = Some(pointer.into());
+        self
+    }
+
+    /// 組み込み規則の診断ならその規則のドキュメントの URL
+    pub fn doc_url(&self) -> Option<String> {
+        rules::find(self.code.as_deref()?)?.doc_url()
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self.severity {
+            Severity::Error => "バリデーション",
+            Severity::Warning => "警告",
+            Severity::Info => "情報",
+            Severity::Hint => "ヒント",
+        };
+        match &self.code {
+            Some(code) if *code != self.rule => {
+                write!(f, "{}[{} {}]: {}", label, code, self.rule, self.message)
+            }
+            _ => write!(f, "{}[{}]: {}", label, self.rule, self.message),
+        }
+    }
+}
+
+impl From<ValidationError> for Diagnostic {
+    fn from(err: ValidationError) -> Self {
+        let (severity, rule, message, pointer) = match err {
+            ValidationError::Rule(rule, message, pointer) => {
+                (Severity::Error, rule, message, pointer)
+            }
+            ValidationError::Warning(rule, message, pointer) => {
+                (Severity::Warning, rule, message, pointer)
+            }
+            ValidationError::Info(rule, message, pointer) => {
+                (Severity::Info, rule, message, pointer)
+            }
+            ValidationError::Hint(rule, message, pointer) => {
+                (Severity::Hint, rule, message, pointer)
+            }
+        };
+        Diagnostic {
+            severity,
+            pointer,
+            ..Diagnostic::from_rule(rule, message)
+        }
+    }
+}
+
+impl From<&ValidationError> for Diagnostic {
+    fn from(err: &ValidationError) -> Self {
+        err.clone().into()
+    }
+}
+
+/// 検証結果を診断にする。ヒントは直前の同じ規則の診断に添え、添える先が無ければそのままヒントの診断にする
+pub fn from_errors(errors: impl IntoIterator<Item = ValidationError>) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    for err in errors {
+        if let ValidationError::Hint(rule, message, _) = &err
+            && let Some(last) = diagnostics.last_mut()
+            && last.severity != Severity::Hint
+            && last.code.as_deref() == Some(rule.code.as_ref())
+        {
+            last.hints.push(message.clone());
+            continue;
+        }
+        diagnostics.push(err.into());
+    }
+    diagnostics
+}
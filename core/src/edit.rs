@@ -0,0 +1,692 @@
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::syntax::{ScalarStyle, Span, SyntaxError, SyntaxKind, SyntaxNode, SyntaxTree};
+
+/// 編集操作のエラー
+#[derive(Debug, Error)]
+pub enum EditError {
+    #[error(transparent)]
+    Syntax(#[from] SyntaxError),
+
+    #[error("編集対象が見つかりません: {0}")]
+    NotFound(String),
+
+    #[error("この書式の YAML は編集できません: {0}")]
+    Unsupported(String),
+}
+
+/// ソース上の置換 1 件
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TextEdit {
+    pub span: Span,
+    pub text: String,
+}
+
+/// 追加する要素の値
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Scalar(String),
+    Sequence(Vec<String>),
+    Mapping(Vec<(&'static str, Value)>),
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::Scalar(value.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::Scalar(value)
+    }
+}
+
+/// コメントやレイアウトを保ったまま USML ドキュメントを書き換える
+///
+/// 操作ごとに構文ツリーを読み直し、対象ノードの範囲だけを差し替える。
+/// `--fix`・`usml rename`・スキャフォールドなど既存ファイルを編集するコマンドはこれを使う
+#[derive(Debug, Clone)]
+pub struct Editor {
+    source: String,
+}
+
+impl Editor {
+    pub fn new(source: impl Into<String>) -> Result<Self, EditError> {
+        let source = source.into();
+        SyntaxTree::parse(&source)?;
+        Ok(Editor { source })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    pub fn into_string(self) -> String {
+        self.source
+    }
+
+    /// `import.dbml` に参照を追加する。既にある場合は何もしない
+    pub fn add_dbml_import(&mut self, reference: &str) -> Result<(), EditError> {
+        let tree = SyntaxTree::parse(&self.source)?;
+        let import = tree
+            .root
+            .get("import")
+            .filter(|n| matches!(n.kind, SyntaxKind::Mapping(_)))
+            .ok_or_else(|| EditError::NotFound("import".to_string()))?;
+
+        let edit = match import.entry("dbml") {
+            Some(entry) if entry.value.is_empty_scalar() => {
+                let indent = self.key_indent(&tree, entry.key_span.start);
+                self.insert_after_line(
+                    entry.key_span.end,
+                    format!("{}  - {}\n", indent, scalar_text(reference)),
+                )
+            }
+            Some(entry) => {
+                let items = match &entry.value.kind {
+                    SyntaxKind::Sequence(items) => items,
+                    _ => return Err(EditError::Unsupported("import.dbml".to_string())),
+                };
+                if items.iter().any(|i| i.as_str() == Some(reference)) {
+                    return Ok(());
+                }
+                let last = items
+                    .last()
+                    .ok_or_else(|| EditError::Unsupported("import.dbml".to_string()))?;
+                if self.is_flow(&entry.value) {
+                    TextEdit {
+                        span: Span {
+                            start: last.span.end,
+                            end: last.span.end,
+                        },
+                        text: format!(", {}", scalar_text(reference)),
+                    }
+                } else {
+                    let indent = self.item_indent(&tree, last.span.start);
+                    self.insert_after_line(
+                        last.span.end,
+                        format!("{}- {}\n", indent, scalar_text(reference)),
+                    )
+                }
+            }
+            None => {
+                let last = import
+                    .entries()
+                    .last()
+                    .ok_or_else(|| EditError::Unsupported("import".to_string()))?;
+                let indent = self.key_indent(&tree, last.key_span.start);
+                self.insert_after_line(
+                    last.value.span.end,
+                    format!(
+                        "{}dbml:\n{}  - {}\n",
+                        indent,
+                        indent,
+                        scalar_text(reference)
+                    ),
+                )
+            }
+        };
+        self.apply(vec![edit]);
+        Ok(())
+    }
+
+    /// フィールドの join に alias を設定し、source と join.on の修飾子も alias に書き換える
+    pub fn set_join_alias(&mut self, field_path: &str, alias: &str) -> Result<(), EditError> {
+        let tree = SyntaxTree::parse(&self.source)?;
+        let mapping = find_mapping(&tree, field_path)
+            .ok_or_else(|| EditError::NotFound(field_path.to_string()))?;
+        let join = mapping
+            .get("join")
+            .filter(|n| matches!(n.kind, SyntaxKind::Mapping(_)))
+            .ok_or_else(|| EditError::NotFound(format!("{}.join", field_path)))?;
+        let table = join
+            .get("table")
+            .and_then(SyntaxNode::as_str)
+            .ok_or_else(|| EditError::NotFound(format!("{}.join.table", field_path)))?;
+
+        let mut edits = Vec::new();
+        let old_qualifier = match join.get("alias") {
+            Some(existing) => {
+                edits.push(self.replace_scalar(existing, alias));
+                existing.as_str().unwrap_or(table)
+            }
+            None => {
+                let last = join
+                    .entries()
+                    .last()
+                    .ok_or_else(|| EditError::Unsupported("join".to_string()))?;
+                let indent = self.key_indent(&tree, last.key_span.start);
+                edits.push(self.insert_after_line(
+                    last.value.span.end,
+                    format!("{}alias: {}\n", indent, scalar_text(alias)),
+                ));
+                table
+            }
+        };
+
+        for node in [mapping.get("source"), join.get("on")]
+            .into_iter()
+            .flatten()
+        {
+            if let Some(value) = node.as_str() {
+                let rewritten = replace_qualifier(value, old_qualifier, alias);
+                if rewritten != value {
+                    edits.push(self.replace_scalar(node, &rewritten));
+                }
+            }
+        }
+        self.apply(edits);
+        Ok(())
+    }
+
+    /// フィールドの aggregate に group_by を書く。既にあれば置き換える
+    pub fn set_group_by(&mut self, field_path: &str, group_by: &str) -> Result<(), EditError> {
+        let tree = SyntaxTree::parse(&self.source)?;
+        let aggregate = find_mapping(&tree, field_path)
+            .and_then(|m| m.get("aggregate"))
+            .filter(|n| matches!(n.kind, SyntaxKind::Mapping(_)))
+            .ok_or_else(|| EditError::NotFound(format!("{}.aggregate", field_path)))?;
+        let edit = match aggregate.get("group_by") {
+            Some(existing) => self.replace_scalar(existing, group_by),
+            None => {
+                let last = aggregate
+                    .entries()
+                    .last()
+                    .ok_or_else(|| EditError::Unsupported("aggregate".to_string()))?;
+                let indent = self.key_indent(&tree, last.key_span.start);
+                self.insert_after_line(
+                    last.value.span.end,
+                    format!("{}group_by: {}\n", indent, scalar_text(group_by)),
+                )
+            }
+        };
+        self.apply(vec![edit]);
+        Ok(())
+    }
+
+    /// レスポンスフィールドの名前を変え、そのフィールドを参照する transforms も追従させる
+    pub fn rename_field(&mut self, field_path: &str, new_name: &str) -> Result<(), EditError> {
+        let tree = SyntaxTree::parse(&self.source)?;
+        let field = find_mapping(&tree, field_path)
+            .and_then(|m| m.get("field"))
+            .ok_or_else(|| EditError::NotFound(field_path.to_string()))?;
+
+        let new_path = match field_path.rsplit_once('.') {
+            Some((parent, _)) => format!("{}.{}", parent, new_name),
+            None => new_name.to_string(),
+        };
+        let rename_path = |path: &str| -> Option<String> {
+            if path == field_path {
+                Some(new_path.clone())
+            } else {
+                path.strip_prefix(field_path)
+                    .filter(|rest| rest.starts_with('.'))
+                    .map(|rest| format!("{}{}", new_path, rest))
+            }
+        };
+
+        let mut edits = vec![self.replace_scalar(field, new_name)];
+        if let Some(transforms) = tree.lookup(&["usecase", "transforms"]) {
+            for transform in transforms.items() {
+                let conditions = transform.get("condition").map(SyntaxNode::items);
+                let references = std::iter::once(transform.get("target"))
+                    .chain(conditions.into_iter().flatten().map(|c| c.get("field")))
+                    .flatten();
+                for node in references {
+                    if let Some(renamed) = node.as_str().and_then(rename_path) {
+                        edits.push(self.replace_scalar(node, &renamed));
+                    }
+                }
+            }
+        }
+        self.apply(edits);
+        Ok(())
+    }
+
+    /// response_mapping の末尾にフィールドを追加する。`parent`（"comments"）を渡すとその配列フィールドの fields に追加する
+    pub fn add_field(
+        &mut self,
+        parent: Option<&str>,
+        entries: &[(&str, Value)],
+    ) -> Result<(), EditError> {
+        let tree = SyntaxTree::parse(&self.source)?;
+        let edit = match parent {
+            Some(parent) => {
+                let mapping = find_mapping(&tree, parent)
+                    .ok_or_else(|| EditError::NotFound(parent.to_string()))?;
+                self.append_item(&tree, mapping, "fields", &[], entries)?
+            }
+            None => {
+                let usecase = tree
+                    .root
+                    .get("usecase")
+                    .ok_or_else(|| EditError::NotFound("usecase".to_string()))?;
+                self.append_item(&tree, usecase, "response_mapping", &[], entries)?
+            }
+        };
+        self.apply(vec![edit]);
+        Ok(())
+    }
+
+    /// usecase.filters の末尾にフィルタを追加する。filters が無ければ response_mapping の後ろに作る
+    pub fn add_filter(&mut self, entries: &[(&str, Value)]) -> Result<(), EditError> {
+        let tree = SyntaxTree::parse(&self.source)?;
+        let usecase = tree
+            .root
+            .get("usecase")
+            .ok_or_else(|| EditError::NotFound("usecase".to_string()))?;
+        let edit = self.append_item(&tree, usecase, "filters", &["response_mapping"], entries)?;
+        self.apply(vec![edit]);
+        Ok(())
+    }
+
+    /// usecase.transforms の末尾に transform を追加する。transforms が無ければ filters（無ければ response_mapping）の後ろに作る
+    pub fn add_transform(&mut self, entries: &[(&str, Value)]) -> Result<(), EditError> {
+        let tree = SyntaxTree::parse(&self.source)?;
+        let usecase = tree
+            .root
+            .get("usecase")
+            .ok_or_else(|| EditError::NotFound("usecase".to_string()))?;
+        let edit = self.append_item(
+            &tree,
+            usecase,
+            "transforms",
+            &["response_mapping", "filters"],
+            entries,
+        )?;
+        self.apply(vec![edit]);
+        Ok(())
+    }
+
+    /// `owner` の `key` のシーケンスの末尾に要素を足す編集。キーが無ければ `after` のうち最後にあるキーの後ろに作る
+    fn append_item(
+        &self,
+        tree: &SyntaxTree,
+        owner: &SyntaxNode,
+        key: &str,
+        after: &[&str],
+        entries: &[(&str, Value)],
+    ) -> Result<TextEdit, EditError> {
+        let edit = match owner.entry(key) {
+            Some(entry) if entry.value.is_empty_scalar() => {
+                let indent = self.key_indent(tree, entry.key_span.start);
+                self.insert_after_line(
+                    entry.key_span.end,
+                    block_item(&format!("{}  ", indent), entries),
+                )
+            }
+            Some(entry) => {
+                let last = match &entry.value.kind {
+                    SyntaxKind::Sequence(items) if !self.is_flow(&entry.value) => items.last(),
+                    _ => None,
+                }
+                .ok_or_else(|| EditError::Unsupported(key.to_string()))?;
+                let indent = self.item_indent(tree, last.span.start);
+                self.insert_after_line(last.span.end, block_item(&indent, entries))
+            }
+            None => {
+                let anchor = after
+                    .iter()
+                    .rev()
+                    .find_map(|k| owner.entry(k))
+                    .or_else(|| owner.entries().last())
+                    .ok_or_else(|| EditError::Unsupported(key.to_string()))?;
+                let indent = self.key_indent(tree, anchor.key_span.start);
+                self.insert_after_line(
+                    anchor.value.span.end,
+                    format!(
+                        "{}{}:\n{}",
+                        indent,
+                        key,
+                        block_item(&format!("{}  ", indent), entries)
+                    ),
+                )
+            }
+        };
+        Ok(edit)
+    }
+
+    /// 編集を後ろから順に適用する（前方の位置がずれないように）
+    fn apply(&mut self, mut edits: Vec<TextEdit>) {
+        edits.sort_by_key(|e| std::cmp::Reverse(e.span.start));
+        for edit in edits {
+            self.source
+                .replace_range(edit.span.start..edit.span.end, &edit.text);
+        }
+    }
+
+    /// スカラーの値を元の引用符スタイルのまま差し替える
+    fn replace_scalar(&self, node: &SyntaxNode, value: &str) -> TextEdit {
+        let text = match &node.kind {
+            SyntaxKind::Scalar {
+                style: ScalarStyle::SingleQuoted,
+                ..
+            } => format!("'{}'", value.replace('\'', "''")),
+            SyntaxKind::Scalar {
+                style: ScalarStyle::DoubleQuoted,
+                ..
+            } => double_quoted(value),
+            _ => scalar_text(value),
+        };
+        TextEdit {
+            span: node.span,
+            text,
+        }
+    }
+
+    /// `offset` を含む行の次の行頭に `text` を挿入する
+    fn insert_after_line(&self, offset: usize, text: String) -> TextEdit {
+        let (at, text) = match self.source[offset..].find('\n') {
+            Some(i) => (offset + i + 1, text),
+            None => (self.source.len(), format!("\n{}", text)),
+        };
+        TextEdit {
+            span: Span { start: at, end: at },
+            text,
+        }
+    }
+
+    /// `offset` にあるマッピングキーと同じ字下げ（`- ` の後ろのキーなら `- ` 分も空白にする）
+    fn key_indent(&self, tree: &SyntaxTree, offset: usize) -> String {
+        let prefix = &self.source[tree.line_start(offset)..offset];
+        " ".repeat(prefix.chars().count())
+    }
+
+    /// `offset` にあるシーケンス要素の `- ` までの字下げ
+    fn item_indent(&self, tree: &SyntaxTree, offset: usize) -> String {
+        let prefix = &self.source[tree.line_start(offset)..offset];
+        prefix
+            .rfind('-')
+            .map_or(prefix, |i| &prefix[..i])
+            .to_string()
+    }
+
+    /// フロースタイル（`[a, b]`）のシーケンスか
+    fn is_flow(&self, node: &SyntaxNode) -> bool {
+        self.source[..node.span.start].trim_end().ends_with('[')
+    }
+}
+
+/// response_mapping をフィールドパス（"comments.author_name"）で辿る
+pub(crate) fn find_mapping<'a>(tree: &'a SyntaxTree, field_path: &str) -> Option<&'a SyntaxNode> {
+    let mut mappings = tree.lookup(&["usecase", "response_mapping"])?;
+    let mut segments = field_path.split('.').peekable();
+    while let Some(segment) = segments.next() {
+        let mapping = mappings
+            .items()
+            .iter()
+            .find(|m| m.get("field").and_then(SyntaxNode::as_str) == Some(segment))?;
+        if segments.peek().is_none() {
+            return Some(mapping);
+        }
+        mappings = mapping.get("fields")?;
+    }
+    None
+}
+
+/// `qualifier.` で始まる識別子だけを `replacement.` に置き換える
+pub(crate) fn replace_qualifier(value: &str, qualifier: &str, replacement: &str) -> String {
+    let pattern = format!("{}.", qualifier);
+    let mut result = String::new();
+    let mut rest = value;
+    while let Some(i) = rest.find(&pattern) {
+        let boundary = rest[..i]
+            .chars()
+            .last()
+            .is_none_or(|c| !(c.is_alphanumeric() || c == '_'));
+        result.push_str(&rest[..i]);
+        result.push_str(if boundary { replacement } else { qualifier });
+        result.push('.');
+        rest = &rest[i + pattern.len()..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// `indent` の位置に `- ` を置いたブロック形式のシーケンス要素
+fn block_item(indent: &str, entries: &[(&str, Value)]) -> String {
+    let mut text = String::new();
+    let nested = format!("{}  ", indent);
+    for (i, (key, value)) in entries.iter().enumerate() {
+        let prefix = if i == 0 {
+            format!("{}- ", indent)
+        } else {
+            nested.clone()
+        };
+        block_entry(&mut text, &prefix, &nested, key, value);
+    }
+    text
+}
+
+/// `prefix` に続けてエントリを書く。`indent` はキーの字下げ（入れ子の値はここから 2 つ下げる）
+fn block_entry(text: &mut String, prefix: &str, indent: &str, key: &str, value: &Value) {
+    match value {
+        Value::Scalar(value) => {
+            text.push_str(&format!("{}{}: {}\n", prefix, key, scalar_text(value)));
+        }
+        Value::Sequence(items) => {
+            text.push_str(&format!("{}{}:\n", prefix, key));
+            for item in items {
+                text.push_str(&format!("{}  - {}\n", indent, scalar_text(item)));
+            }
+        }
+        Value::Mapping(entries) => {
+            text.push_str(&format!("{}{}:\n", prefix, key));
+            let nested = format!("{}  ", indent);
+            for (key, value) in entries {
+                block_entry(text, &nested, &nested, key, value);
+            }
+        }
+    }
+}
+
+/// プレーンスカラーで書けるならそのまま、書けなければダブルクォートで囲む
+fn scalar_text(value: &str) -> String {
+    let needs_quote = value.is_empty()
+        || value != value.trim()
+        || value.starts_with(|c: char| "-?:,[]{}#&*!|>'\"%@`".contains(c))
+        || value.contains(": ")
+        || value.contains(" #")
+        || value.ends_with(':')
+        || value.contains('\n');
+    if needs_quote {
+        double_quoted(value)
+    } else {
+        value.to_string()
+    }
+}
+
+fn double_quoted(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("\"{}\"", escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+
+    const DOC: &str = r#"# 投稿一覧
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["posts"]   # 投稿
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: 投稿一覧
+  response_mapping:
+    # 著者
+    - field: author_name
+      source: users.name
+      join:
+        table: users
+        on: posts.user_id = users.id
+    - field: comments
+      type: array
+      fields:
+        - field: body
+          source: comments.body
+  transforms:
+    - target: comments.body
+      type: MASK
+      source: comments.body
+"#;
+
+    #[test]
+    fn test_add_dbml_import_preserves_comments() {
+        let mut editor = Editor::new(DOC).unwrap();
+        editor
+            .add_dbml_import("./schema.dbml#tables[\"comments\"]")
+            .unwrap();
+        let output = editor.as_str();
+        assert!(output.contains(
+            "    - ./schema.dbml#tables[\"users\"]\n    - ./schema.dbml#tables[\"comments\"]\n"
+        ));
+        assert!(output.contains("# 投稿一覧\n"));
+        assert!(output.contains("# 投稿\n"));
+
+        let doc = parser::parse(output).unwrap();
+        assert_eq!(doc.import.dbml.unwrap().len(), 3);
+
+        // 同じ参照の二重追加はしない
+        let before = editor.as_str().to_string();
+        editor
+            .add_dbml_import("./schema.dbml#tables[\"comments\"]")
+            .unwrap();
+        assert_eq!(editor.as_str(), before);
+    }
+
+    #[test]
+    fn test_set_join_alias_rewrites_qualifiers() {
+        let mut editor = Editor::new(DOC).unwrap();
+        editor.set_join_alias("author_name", "author").unwrap();
+        let output = editor.as_str();
+        assert!(output.contains("      source: author.name\n"));
+        assert!(output.contains("        on: posts.user_id = author.id\n        alias: author\n"));
+        assert!(output.contains("    # 著者\n"));
+        assert!(parser::parse(output).is_ok());
+    }
+
+    #[test]
+    fn test_rename_field_updates_transform_targets() {
+        let mut editor = Editor::new(DOC).unwrap();
+        editor.rename_field("comments.body", "text").unwrap();
+        let doc = parser::parse(editor.as_str()).unwrap();
+        let comments = doc.usecase.response_mapping[1].fields.as_ref().unwrap();
+        assert_eq!(comments[0].field, "text");
+        assert_eq!(doc.usecase.transforms[0].target, "comments.text");
+
+        assert!(matches!(
+            editor.rename_field("missing", "x"),
+            Err(EditError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_add_field_appends_mapping() {
+        let mut editor = Editor::new(DOC).unwrap();
+        editor
+            .add_field(
+                None,
+                &[
+                    ("field", "comment_count".into()),
+                    ("source", "comments.id".into()),
+                    (
+                        "join",
+                        Value::Mapping(vec![
+                            ("table", "comments".into()),
+                            ("on", "posts.id = comments.post_id".into()),
+                        ]),
+                    ),
+                ],
+            )
+            .unwrap();
+        editor
+            .add_field(
+                Some("comments"),
+                &[("field", "id".into()), ("source", "comments.id".into())],
+            )
+            .unwrap();
+        let output = editor.as_str();
+        assert!(output.contains(concat!(
+            "          source: comments.body\n",
+            "        - field: id\n",
+            "          source: comments.id\n",
+            "    - field: comment_count\n",
+            "      source: comments.id\n",
+            "      join:\n",
+            "        table: comments\n",
+            "        on: posts.id = comments.post_id\n",
+            "  transforms:\n",
+        )));
+        assert!(output.contains("    # 著者\n"));
+
+        let doc = parser::parse(output).unwrap();
+        assert_eq!(doc.usecase.response_mapping.len(), 3);
+        assert_eq!(
+            doc.usecase.response_mapping[1]
+                .fields
+                .as_ref()
+                .unwrap()
+                .len(),
+            2
+        );
+
+        assert!(matches!(
+            editor.add_field(Some("missing"), &[("field", "x".into())]),
+            Err(EditError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_add_filter_and_transform() {
+        let mut editor = Editor::new(DOC).unwrap();
+        let filter = [
+            ("param", Value::from("user_id")),
+            ("maps_to", "WHERE".into()),
+            ("condition", "posts.user_id = :user_id".into()),
+        ];
+        editor.add_filter(&filter).unwrap();
+        editor
+            .add_transform(&[
+                ("target", "author_name".into()),
+                ("type", "COALESCE".into()),
+                (
+                    "sources",
+                    Value::Sequence(vec!["users.name".to_string(), "users.email".to_string()]),
+                ),
+                ("fallback", "***".into()),
+            ])
+            .unwrap();
+        let output = editor.as_str();
+        // filters は response_mapping の直後に作る
+        assert!(output.contains(concat!(
+            "          source: comments.body\n",
+            "  filters:\n",
+            "    - param: user_id\n",
+            "      maps_to: WHERE\n",
+            "      condition: posts.user_id = :user_id\n",
+            "  transforms:\n",
+        )));
+        assert!(output.ends_with(concat!(
+            "      source: comments.body\n",
+            "    - target: author_name\n",
+            "      type: COALESCE\n",
+            "      sources:\n",
+            "        - users.name\n",
+            "        - users.email\n",
+            "      fallback: \"***\"\n",
+        )));
+
+        editor.add_filter(&filter).unwrap();
+        let doc = parser::parse(editor.as_str()).unwrap();
+        assert_eq!(doc.usecase.filters.len(), 2);
+        assert_eq!(doc.usecase.transforms.len(), 2);
+        assert_eq!(doc.usecase.transforms[1].fallback.as_deref(), Some("***"));
+    }
+}
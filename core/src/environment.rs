@@ -0,0 +1,106 @@
+//! 環境（dev・stg・prod など）ごとの上書き（`environments:`）を usecase に適用する
+//!
+//! `environments.<名前>.import` に書いた項目で `import` を置き換える。`tables` はテーブルごとに上書きする。
+//! レスポンスの構造を変えないよう、上書きできるのは import だけにする（U059）
+
+use thiserror::Error;
+
+use crate::ast::{Import, UsmlDocument};
+
+/// 環境の適用のエラー
+#[derive(Debug, Error)]
+pub enum EnvironmentError {
+    #[error("環境 '{name}' が environments にありません（{available}）")]
+    Unknown { name: String, available: String },
+}
+
+impl UsmlDocument {
+    /// `environments` の `name` の上書きを import に適用する
+    pub fn apply_environment(&mut self, name: &str) -> Result<(), EnvironmentError> {
+        let Some(environment) = self.environments.get(name) else {
+            let names: Vec<&str> = self.environments.keys().map(String::as_str).collect();
+            return Err(EnvironmentError::Unknown {
+                name: name.to_string(),
+                available: if names.is_empty() {
+                    "環境が宣言されていません".to_string()
+                } else {
+                    names.join("・")
+                },
+            });
+        };
+        if let Some(import) = environment.import.clone() {
+            self.import.overlay(import);
+        }
+        Ok(())
+    }
+}
+
+impl Import {
+    /// 環境の import を重ねる。書かれた openapi・dbml は置き換え、tables はテーブルごとに上書きする
+    pub fn overlay(&mut self, import: Import) {
+        if import.openapi.is_some() {
+            self.openapi = import.openapi;
+        }
+        if import.dbml.is_some() {
+            self.dbml = import.dbml;
+        }
+        self.tables.extend(import.tables);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser;
+
+    const DOC: &str = r#"
+version: "0.1"
+import:
+  openapi: ./api.yaml#paths["/users"].get.responses["200"]
+  dbml:
+    - ./schema.dbml#tables["users"]
+  tables:
+    profiles: user_profiles
+usecase:
+  name: ユーザー一覧
+  response_mapping:
+    - field: id
+      source: users.id
+environments:
+  stg:
+    import:
+      dbml:
+        - ./schema.stg.dbml#tables["stg_users"]
+      tables:
+        users: stg_users
+  prod: {}
+"#;
+
+    #[test]
+    fn test_apply_environment_overrides_import() {
+        let mut doc = parser::parse(DOC).unwrap();
+        doc.apply_environment("stg").unwrap();
+        assert_eq!(
+            doc.import.openapi.as_deref(),
+            Some("./api.yaml#paths[\"/users\"].get.responses[\"200\"]")
+        );
+        assert_eq!(
+            doc.import.dbml.unwrap(),
+            vec!["./schema.stg.dbml#tables[\"stg_users\"]"]
+        );
+        assert_eq!(doc.import.tables.len(), 2);
+        assert_eq!(doc.import.tables["users"], "stg_users");
+
+        let mut doc = parser::parse(DOC).unwrap();
+        doc.apply_environment("prod").unwrap();
+        assert!(!doc.import.tables.contains_key("users"));
+
+        let err = parser::parse(DOC)
+            .unwrap()
+            .apply_environment("dev")
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "環境 'dev' が environments にありません（prod・stg）"
+        );
+    }
+}
@@ -0,0 +1,75 @@
+use std::io;
+
+use thiserror::Error;
+
+use crate::diagnostic::Location;
+use crate::edit::EditError;
+use crate::environment::EnvironmentError;
+use crate::parser::ParseError;
+use crate::resolver::ResolverError;
+use crate::validator::ValidationError;
+
+/// ライブラリの公開 API が返すエラーをまとめた型
+///
+/// 各モジュールのエラーは `source()` で辿れる。
+/// ライブラリ内では `process::exit` や panic をせず、終了処理は呼び出し側（CLI など）に任せる
+#[derive(Debug, Error)]
+pub enum UsmlError {
+    #[error("ファイル読み込みエラー '{path}': {source}")]
+    Io {
+        path: String,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("パースエラー: {0}")]
+    Parse(#[from] ParseError),
+
+    #[error("解決エラー: {0}")]
+    Resolve(#[from] ResolverError),
+
+    #[error("{0}")]
+    Validation(#[from] ValidationError),
+
+    #[error("編集エラー: {0}")]
+    Edit(#[from] EditError),
+
+    #[error("{0}")]
+    Environment(#[from] EnvironmentError),
+}
+
+impl UsmlError {
+    /// エラーが発生したファイル内の位置（分かる場合のみ）
+    pub fn location(&self) -> Option<&Location> {
+        match self {
+            UsmlError::Resolve(err) => err.location(),
+            _ => None,
+        }
+    }
+}
+
+/// `UsmlError` を返す Result
+pub type Result<T> = std::result::Result<T, UsmlError>;
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+
+    use super::*;
+    use crate::parser;
+
+    #[test]
+    fn test_parse_error_is_chained() {
+        let err = UsmlError::from(parser::parse("version: [").unwrap_err());
+        assert!(err.to_string().starts_with("パースエラー: "));
+        assert!(err.source().is_some_and(|s| s.is::<ParseError>()));
+    }
+
+    #[test]
+    fn test_parse_file_reports_missing_file() {
+        let err = parser::parse_file("does/not/exist.usml.yaml").unwrap_err();
+        assert!(
+            matches!(err, UsmlError::Io { ref path, .. } if path == "does/not/exist.usml.yaml")
+        );
+    }
+}
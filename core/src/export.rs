@@ -0,0 +1,1012 @@
+//! マッピングとテーブル・カラムの利用状況を他のツール向けに書き出す
+//!
+//! 表計算ソフト向けの表は書き出す形式（xlsx など）には依存せず、行ごとの文字列の一覧を返す。
+//! dbt 向けには exposures の YAML を、DataHub 向けには取り込み用の MetadataChangeProposal を返す。
+//! DBML は usecase が参照するテーブル・カラム・結合だけを含むスキーマで、データベース全体のスキーマとの比較に使う。
+//! アクセス行列は usecase × カラムの参照と個人情報の印で、セキュリティレビューに使う
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use serde::Serialize;
+
+use crate::api;
+use crate::ast::UsmlDocument;
+use crate::config::GovernanceConfig;
+use crate::lineage::{EdgeKind, LineageEdge};
+use crate::model::{FieldKind, Model};
+use crate::resolver;
+use crate::stats;
+
+/// マッピング表の見出し
+pub const MAPPING_HEADER: &[&str] = &[
+    "フィールド",
+    "種類",
+    "ソース",
+    "テーブル",
+    "結合",
+    "集約",
+    "変換",
+    "固定値・パラメータ",
+    "NULL 許容",
+];
+
+/// 利用状況の表の見出し
+pub const USAGE_HEADER: &[&str] = &["テーブル", "カラム", "usecase 数", "usecase"];
+
+/// レスポンスフィールド 1 つにつき 1 行のマッピング表（`MAPPING_HEADER` の順）
+pub fn mapping_rows(doc: &UsmlDocument) -> Vec<Vec<String>> {
+    let model = Model::build(doc);
+    model
+        .fields
+        .iter()
+        .map(|field| {
+            let joins: Vec<String> = model
+                .joins_of(field)
+                .map(|j| format!("{} {} ON {}", j.kind.as_sql(), j.table, j.on))
+                .collect();
+            let transforms: Vec<&str> = field.transforms.iter().map(|t| t.kind.as_str()).collect();
+            let constant = if let Some(value) = &field.value {
+                value.to_string()
+            } else if let Some(param) = &field.param {
+                format!(":{}", param)
+            } else if let Some(usecase) = &field.source_usecase {
+                format!("usecase: {}", usecase)
+            } else {
+                String::new()
+            };
+            vec![
+                field.path.clone(),
+                match field.kind {
+                    FieldKind::Scalar => "scalar".to_string(),
+                    FieldKind::Array => "array".to_string(),
+                },
+                field
+                    .source
+                    .as_ref()
+                    .map(|s| format!("{}.{}", s.table, s.column))
+                    .unwrap_or_default(),
+                field
+                    .source
+                    .as_ref()
+                    .map(|s| s.table.clone())
+                    .or_else(|| field.source_table.clone())
+                    .unwrap_or_default(),
+                joins.join("\n"),
+                field.aggregate.clone().unwrap_or_default(),
+                transforms.join(", "),
+                constant,
+                field.nullable.map(|n| n.to_string()).unwrap_or_default(),
+            ]
+        })
+        .collect()
+}
+
+/// テーブル・カラムごとに参照する usecase をまとめた表（`USAGE_HEADER` の順、テーブル・カラムの名前順）
+pub fn usage_rows<'a>(docs: impl IntoIterator<Item = &'a UsmlDocument>) -> Vec<Vec<String>> {
+    let mut usage: BTreeMap<(String, String), Vec<String>> = BTreeMap::new();
+    for doc in docs {
+        let model = Model::build(doc);
+        for table in &model.tables {
+            for column in &table.columns {
+                let usecases = usage
+                    .entry((table.name.clone(), column.clone()))
+                    .or_default();
+                if !usecases.contains(&doc.usecase.name) {
+                    usecases.push(doc.usecase.name.clone());
+                }
+            }
+        }
+    }
+    usage
+        .into_iter()
+        .map(|((table, column), usecases)| {
+            vec![
+                table,
+                column,
+                usecases.len().to_string(),
+                usecases.join(", "),
+            ]
+        })
+        .collect()
+}
+
+/// dbt の exposures ファイル（`version: 2`）
+#[derive(Debug, Serialize)]
+pub struct DbtExposures {
+    pub version: u32,
+    pub exposures: Vec<DbtExposure>,
+}
+
+/// usecase 1 つ分の exposure
+#[derive(Debug, Serialize)]
+pub struct DbtExposure {
+    /// ファイル名から作る識別子（英数字と `_` のみ）
+    pub name: String,
+    /// usecase.name
+    pub label: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// `ref('<テーブル>')`、または `source('<ソース>', '<テーブル>')`
+    pub depends_on: Vec<String>,
+    pub owner: DbtOwner,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    pub meta: DbtMeta,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DbtOwner {
+    pub name: String,
+}
+
+/// dbt docs に表示する USML 固有の情報
+#[derive(Debug, Serialize)]
+pub struct DbtMeta {
+    pub usml_file: String,
+    /// `GET /users` の形の API エンドポイント
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub reviewers: Vec<String>,
+    /// テーブルごとに参照するカラム
+    pub columns: BTreeMap<String, Vec<String>>,
+}
+
+/// owner の無い usecase の exposure に入れる owner 名（dbt では owner が必須）
+pub const DBT_UNKNOWN_OWNER: &str = "unassigned";
+
+/// (ファイルパス, ドキュメント) ごとに exposure を作る
+///
+/// `source` を指定すると依存先を `source('<source>', '<テーブル>')` で、省略すると `ref('<テーブル>')` で書く
+pub fn dbt_exposures<'a>(
+    docs: impl IntoIterator<Item = (&'a str, &'a UsmlDocument)>,
+    source: Option<&str>,
+) -> DbtExposures {
+    let exposures = docs
+        .into_iter()
+        .map(|(path, doc)| {
+            let model = Model::build(doc);
+            let tables = stats::referenced_tables(&model);
+            let columns = model
+                .tables
+                .iter()
+                .filter(|t| !t.columns.is_empty())
+                .map(|t| {
+                    let mut columns = t.columns.clone();
+                    columns.sort();
+                    (t.name.clone(), columns)
+                })
+                .collect();
+            let usecase = &doc.usecase;
+            DbtExposure {
+                name: identifier(path),
+                label: usecase.name.clone(),
+                kind: "application".to_string(),
+                description: usecase.summary.clone(),
+                depends_on: tables
+                    .iter()
+                    .map(|table| match source {
+                        Some(source) => format!("source('{}', '{}')", source, table),
+                        None => format!("ref('{}')", table),
+                    })
+                    .collect(),
+                owner: DbtOwner {
+                    name: usecase
+                        .owner
+                        .clone()
+                        .unwrap_or_else(|| DBT_UNKNOWN_OWNER.to_string()),
+                },
+                tags: usecase.tags.clone(),
+                meta: DbtMeta {
+                    usml_file: path.to_string(),
+                    endpoint: endpoint(doc),
+                    reviewers: usecase.reviewers.clone(),
+                    columns,
+                },
+            }
+        })
+        .collect();
+    DbtExposures {
+        version: 2,
+        exposures,
+    }
+}
+
+impl DbtExposures {
+    pub fn to_yaml(&self) -> String {
+        serde_yaml::to_string(self).expect("exposures は YAML に変換できる")
+    }
+}
+
+/// DataHub に登録するときの設定
+#[derive(Debug, Clone)]
+pub struct DatahubOptions {
+    /// テーブルのデータプラットフォーム（例: `postgres`）
+    pub platform: String,
+    /// テーブル名の前に付けるデータベース・スキーマ名（例: `app.public`）
+    pub database: Option<String>,
+    /// DataHub の環境（`PROD` など）
+    pub env: String,
+}
+
+impl Default for DatahubOptions {
+    fn default() -> Self {
+        Self {
+            platform: "postgres".to_string(),
+            database: None,
+            env: "PROD".to_string(),
+        }
+    }
+}
+
+/// usecase を登録するデータプラットフォーム
+pub const DATAHUB_USECASE_PLATFORM: &str = "usml";
+
+/// DataHub の MetadataChangeProposal 1 件（`datahub ingest` の file ソースで読める形）
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DatahubProposal {
+    pub entity_type: &'static str,
+    pub entity_urn: String,
+    pub change_type: &'static str,
+    pub aspect_name: &'static str,
+    pub aspect: DatahubAspectJson,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DatahubAspectJson {
+    pub json: DatahubAspect,
+}
+
+/// usecase ごとに登録する aspect
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum DatahubAspect {
+    Properties(DatasetProperties),
+    SubTypes(SubTypes),
+    Lineage(UpstreamLineage),
+    Ownership(Ownership),
+    Tags(GlobalTags),
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DatasetProperties {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub custom_properties: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubTypes {
+    pub type_names: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpstreamLineage {
+    pub upstreams: Vec<Upstream>,
+    pub fine_grained_lineages: Vec<FineGrainedLineage>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Upstream {
+    pub dataset: String,
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+}
+
+/// テーブルのカラム → usecase のフィールド の辺
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FineGrainedLineage {
+    pub upstream_type: &'static str,
+    pub upstreams: Vec<String>,
+    pub downstream_type: &'static str,
+    pub downstreams: Vec<String>,
+    /// 辺の種類（`SOURCE`・`AGGREGATE`・`TRANSFORM`）
+    pub transform_operation: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Ownership {
+    pub owners: Vec<Owner>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Owner {
+    pub owner: String,
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GlobalTags {
+    pub tags: Vec<TagAssociation>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TagAssociation {
+    pub tag: String,
+}
+
+/// (ファイルパス, ドキュメント, リネージ) ごとに usecase を dataset として登録する proposal を作る
+///
+/// リネージは source_usecase を合成したもの（`Workspace::lineage`）を渡してもよい
+pub fn datahub_proposals<'a>(
+    docs: impl IntoIterator<Item = (&'a str, &'a UsmlDocument, Vec<LineageEdge>)>,
+    options: &DatahubOptions,
+) -> Vec<DatahubProposal> {
+    let mut proposals = Vec::new();
+    for (path, doc, edges) in docs {
+        let usecase = &doc.usecase;
+        let urn = dataset_urn(DATAHUB_USECASE_PLATFORM, &identifier(path), &options.env);
+        let mut push = |aspect_name, aspect| {
+            proposals.push(DatahubProposal {
+                entity_type: "dataset",
+                entity_urn: urn.clone(),
+                change_type: "UPSERT",
+                aspect_name,
+                aspect: DatahubAspectJson { json: aspect },
+            })
+        };
+
+        let mut custom_properties = BTreeMap::from([("usml_file".to_string(), path.to_string())]);
+        if let Some(endpoint) = endpoint(doc) {
+            custom_properties.insert("endpoint".to_string(), endpoint);
+        }
+        if !usecase.reviewers.is_empty() {
+            custom_properties.insert("reviewers".to_string(), usecase.reviewers.join(", "));
+        }
+        push(
+            "datasetProperties",
+            DatahubAspect::Properties(DatasetProperties {
+                name: usecase.name.clone(),
+                description: usecase.summary.clone(),
+                custom_properties,
+            }),
+        );
+        push(
+            "subTypes",
+            DatahubAspect::SubTypes(SubTypes {
+                type_names: vec!["API Usecase".to_string()],
+            }),
+        );
+
+        let table_urn = |table: &str| {
+            let name = match &options.database {
+                Some(database) => format!("{}.{}", database, table),
+                None => table.to_string(),
+            };
+            dataset_urn(&options.platform, &name, &options.env)
+        };
+        let mut tables: Vec<&str> = edges.iter().map(|e| e.table.as_str()).collect();
+        tables.sort();
+        tables.dedup();
+        push(
+            "upstreamLineage",
+            DatahubAspect::Lineage(UpstreamLineage {
+                upstreams: tables
+                    .iter()
+                    .map(|table| Upstream {
+                        dataset: table_urn(table),
+                        kind: "TRANSFORMED",
+                    })
+                    .collect(),
+                fine_grained_lineages: edges
+                    .iter()
+                    .map(|edge| FineGrainedLineage {
+                        upstream_type: "FIELD_SET",
+                        upstreams: vec![field_urn(&table_urn(&edge.table), &edge.column)],
+                        downstream_type: "FIELD",
+                        downstreams: vec![field_urn(&urn, &edge.field)],
+                        transform_operation: match edge.kind {
+                            EdgeKind::Source => "SOURCE",
+                            EdgeKind::Aggregate => "AGGREGATE",
+                            EdgeKind::Transform => "TRANSFORM",
+                        },
+                    })
+                    .collect(),
+            }),
+        );
+
+        if let Some(owner) = &usecase.owner {
+            push(
+                "ownership",
+                DatahubAspect::Ownership(Ownership {
+                    owners: vec![Owner {
+                        owner: format!("urn:li:corpGroup:{}", owner),
+                        kind: "TECHNICAL_OWNER",
+                    }],
+                }),
+            );
+        }
+        if !usecase.tags.is_empty() {
+            push(
+                "globalTags",
+                DatahubAspect::Tags(GlobalTags {
+                    tags: usecase
+                        .tags
+                        .iter()
+                        .map(|tag| TagAssociation {
+                            tag: format!("urn:li:tag:{}", tag),
+                        })
+                        .collect(),
+                }),
+            );
+        }
+    }
+    proposals
+}
+
+/// usecase が参照するテーブル・カラムと、結合条件の等式から作る関連だけを含む DBML
+///
+/// `tables` は import を解決した DBML のテーブルで、カラムの型・制約と enum に使う。
+/// 見つからないカラムの型は `unknown` にする
+pub fn usage_dbml<'a>(
+    docs: impl IntoIterator<Item = &'a UsmlDocument>,
+    tables: &[resolver::DbmlTable],
+) -> String {
+    let mut columns: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    let mut usecases: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut refs: BTreeSet<(String, &str, String)> = BTreeSet::new();
+    let dbml_column = |table: &str, column: &str| {
+        tables
+            .iter()
+            .find(|t| t.name == table)
+            .and_then(|t| t.column(column))
+    };
+    let is_key = |table: &str, column: &str| match dbml_column(table, column) {
+        Some(dbml) => dbml.primary_key,
+        None => column == "id",
+    };
+
+    for doc in docs {
+        let model = Model::build(doc);
+        let referenced = stats::referenced_tables(&model);
+        for table in model.tables.iter().filter(|t| referenced.contains(&t.name)) {
+            columns
+                .entry(table.name.clone())
+                .or_default()
+                .extend(table.columns.iter().cloned());
+            let names = usecases.entry(table.name.clone()).or_default();
+            if !names.contains(&doc.usecase.name) {
+                names.push(doc.usecase.name.clone());
+            }
+        }
+        // WHERE・行レベルの条件とソート・カーソルのカラム
+        let usecase = &doc.usecase;
+        let expressions = usecase
+            .filters
+            .iter()
+            .flat_map(|f| {
+                f.condition
+                    .iter()
+                    .chain(f.allowed_columns.iter().flatten())
+                    .chain(&f.default_column)
+                    .chain(&f.cursor_field)
+                    .chain(&f.cursor_tiebreaker)
+            })
+            .chain(usecase.authorization.iter().flat_map(|a| &a.row_filters));
+        for (qualifier, column) in expressions.flat_map(|e| api::table_refs(e)) {
+            if let Some(table) = model
+                .tables
+                .iter()
+                .find(|t| t.name == qualifier || t.aliases.contains(&qualifier))
+                && referenced.contains(&table.name)
+            {
+                columns
+                    .entry(table.name.clone())
+                    .or_default()
+                    .insert(column);
+            }
+        }
+        for join in &model.joins {
+            let table_of = |side: &str| {
+                let refs = api::table_refs(side);
+                let [(qualifier, column)] = refs.as_slice() else {
+                    return None;
+                };
+                let reference = join.references.iter().find(|r| &r.qualifier == qualifier)?;
+                Some((reference.table.clone(), column.clone()))
+            };
+            for part in join.on.split(" AND ").flat_map(|p| p.split(" and ")) {
+                let Some((left, right)) = part.split_once('=') else {
+                    continue;
+                };
+                let (Some((left_table, left)), Some((right_table, right))) =
+                    (table_of(left), table_of(right))
+                else {
+                    continue;
+                };
+                // 主キー側を「1」とする。どちらも主キーでなければ多対多
+                let (from, kind, to) = if is_key(&right_table, &right) {
+                    ((left_table, left), ">", (right_table, right))
+                } else if is_key(&left_table, &left) {
+                    ((right_table, right), ">", (left_table, left))
+                } else {
+                    ((left_table, left), "<>", (right_table, right))
+                };
+                refs.insert((
+                    format!("{}.{}", from.0, from.1),
+                    kind,
+                    format!("{}.{}", to.0, to.1),
+                ));
+            }
+        }
+    }
+
+    let mut out = String::from(
+        "// usml export dbml で生成。usecase が参照するテーブル・カラム・結合だけを含む\n",
+    );
+    let mut enums: BTreeMap<&str, &[String]> = BTreeMap::new();
+    for (table, names) in &columns {
+        for name in names {
+            if let Some(dbml) = dbml_column(table, name)
+                && !dbml.enum_values.is_empty()
+            {
+                enums.insert(&dbml.data_type, &dbml.enum_values);
+            }
+        }
+    }
+    for (name, values) in enums {
+        let _ = writeln!(out, "\nEnum {} {{", name);
+        for value in values {
+            let _ = writeln!(out, "  {}", value);
+        }
+        out.push_str("}\n");
+    }
+    for (table, names) in &columns {
+        // DBML の宣言順、DBML に無いカラムは名前順で後ろに置く
+        let declared: &[String] = tables
+            .iter()
+            .find(|t| &t.name == table)
+            .map(|t| t.columns.as_slice())
+            .unwrap_or_default();
+        let ordered = declared
+            .iter()
+            .filter(|c| names.contains(*c))
+            .chain(names.iter().filter(|n| !declared.contains(n)));
+
+        let _ = writeln!(out, "\nTable {} {{", table);
+        for name in ordered {
+            let Some(dbml) = dbml_column(table, name) else {
+                let _ = writeln!(out, "  {} unknown", name);
+                continue;
+            };
+            let mut settings = Vec::new();
+            if dbml.primary_key {
+                settings.push("pk");
+            }
+            if dbml.increment {
+                settings.push("increment");
+            }
+            if dbml.unique {
+                settings.push("unique");
+            }
+            match dbml.nullable {
+                Some(false) => settings.push("not null"),
+                Some(true) => settings.push("null"),
+                None => {}
+            }
+            let data_type = if dbml.data_type.contains(' ') {
+                format!("\"{}\"", dbml.data_type)
+            } else {
+                dbml.data_type.clone()
+            };
+            if settings.is_empty() {
+                let _ = writeln!(out, "  {} {}", name, data_type);
+            } else {
+                let _ = writeln!(out, "  {} {} [{}]", name, data_type, settings.join(", "));
+            }
+        }
+        let _ = writeln!(
+            out,
+            "  Note: '参照する usecase: {}'",
+            usecases[table].join(", ").replace('\'', "\\'")
+        );
+        out.push_str("}\n");
+    }
+    if !refs.is_empty() {
+        out.push('\n');
+    }
+    for (from, kind, to) in refs {
+        let _ = writeln!(out, "Ref: {} {} {}", from, kind, to);
+    }
+    out
+}
+
+/// アクセス行列の列にするカラム
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AccessColumn {
+    pub table: String,
+    pub column: String,
+    /// `[governance] pii` に一致する
+    pub pii: bool,
+}
+
+/// カラムへのアクセスの種類（ミューテーションを定義できるようになったら write を加える）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Access {
+    Read,
+}
+
+/// usecase 1 つとカラム 1 つの交点
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AccessCell {
+    pub access: Access,
+    /// このカラムの値を返すレスポンスフィールド。結合・条件にだけ使う場合は空
+    pub fields: Vec<String>,
+}
+
+/// アクセス行列の usecase 1 つ分
+#[derive(Debug, Clone, Serialize)]
+pub struct AccessRow {
+    pub file: String,
+    pub usecase: String,
+    pub endpoint: Option<String>,
+    /// `テーブル.カラム` ごとのアクセス（参照しないカラムは含まない）
+    pub cells: BTreeMap<String, AccessCell>,
+}
+
+/// usecase × テーブル・カラムのアクセス行列
+#[derive(Debug, Clone, Serialize)]
+pub struct AccessMatrix {
+    /// いずれかの usecase が参照するカラム（テーブル・カラムの名前順）
+    pub columns: Vec<AccessColumn>,
+    pub usecases: Vec<AccessRow>,
+}
+
+/// (ファイルパス, ドキュメント) ごとに、参照するカラムと値を返すフィールドをまとめる
+///
+/// source・transform で値を返すカラムはそのフィールドを、結合条件・`WHERE` と行レベルの条件にだけ
+/// 使うカラムは空のフィールドを持つ
+pub fn access_matrix<'a>(
+    docs: impl IntoIterator<Item = (&'a str, &'a UsmlDocument)>,
+    governance: &GovernanceConfig,
+) -> AccessMatrix {
+    let mut columns: BTreeSet<(String, String)> = BTreeSet::new();
+    let mut usecases = Vec::new();
+    for (path, doc) in docs {
+        let model = Model::build(doc);
+        let mut cells: BTreeMap<String, AccessCell> = BTreeMap::new();
+        let mut read = |table: &str, column: &str, field: Option<&str>| {
+            columns.insert((table.to_string(), column.to_string()));
+            let cell = cells
+                .entry(format!("{}.{}", table, column))
+                .or_insert_with(|| AccessCell {
+                    access: Access::Read,
+                    fields: Vec::new(),
+                });
+            if let Some(field) = field
+                && !cell.fields.iter().any(|f| f == field)
+            {
+                cell.fields.push(field.to_string());
+            }
+        };
+        for field in &model.fields {
+            let transforms = field.transforms.iter().flat_map(|t| &t.sources);
+            for source in field.source.iter().chain(transforms) {
+                read(&source.table, &source.column, Some(&field.path));
+            }
+        }
+        for reference in model.joins.iter().flat_map(|j| &j.references) {
+            read(&reference.table, &reference.column, None);
+        }
+        let usecase = &doc.usecase;
+        let conditions = usecase
+            .filters
+            .iter()
+            .filter_map(|f| f.condition.as_ref())
+            .chain(usecase.authorization.iter().flat_map(|a| &a.row_filters));
+        for (qualifier, column) in conditions.flat_map(|c| api::table_refs(c)) {
+            if let Some(table) = model
+                .tables
+                .iter()
+                .find(|t| t.name == qualifier || t.aliases.contains(&qualifier))
+            {
+                read(&table.name, &column, None);
+            }
+        }
+        usecases.push(AccessRow {
+            file: path.to_string(),
+            usecase: usecase.name.clone(),
+            endpoint: endpoint(doc),
+            cells,
+        });
+    }
+    AccessMatrix {
+        columns: columns
+            .into_iter()
+            .map(|(table, column)| AccessColumn {
+                pii: governance.is_pii(&table, &column),
+                table,
+                column,
+            })
+            .collect(),
+        usecases,
+    }
+}
+
+/// `import.openapi` のエンドポイント（`GET /users` の形）
+fn endpoint(doc: &UsmlDocument) -> Option<String> {
+    let (_, path, method, _) = doc
+        .import
+        .openapi
+        .as_deref()
+        .and_then(resolver::openapi::parse_openapi_ref)?;
+    Some(format!("{} {}", method.to_ascii_uppercase(), path))
+}
+
+fn dataset_urn(platform: &str, name: &str, env: &str) -> String {
+    format!(
+        "urn:li:dataset:(urn:li:dataPlatform:{},{},{})",
+        platform, name, env
+    )
+}
+
+fn field_urn(dataset_urn: &str, field: &str) -> String {
+    format!("urn:li:schemaField:({},{})", dataset_urn, field)
+}
+
+/// `usecases/users-list.usml.yaml` → `users_list`
+pub(crate) fn identifier(path: &str) -> String {
+    let file = path.rsplit(['/', '\\']).next().unwrap_or(path);
+    let stem = file.strip_suffix(".usml.yaml").unwrap_or(file);
+    stem.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+
+    #[test]
+    fn test_mapping_and_usage_rows() {
+        let doc = parser::parse(
+            r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["posts"]
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: 投稿詳細
+  response_mapping:
+    - field: title
+      source: posts.title
+    - field: author
+      source: u.name
+      nullable: true
+      join:
+        table: users
+        alias: u
+        on: posts.user_id = u.id
+    - field: api_version
+      value: v2
+"#,
+        )
+        .unwrap();
+
+        let rows = mapping_rows(&doc);
+        assert_eq!(rows.len(), 3);
+        assert!(rows.iter().all(|r| r.len() == MAPPING_HEADER.len()));
+        assert_eq!(rows[1][2], "users.name");
+        assert_eq!(rows[1][4], "LEFT JOIN users ON posts.user_id = u.id");
+        assert_eq!(rows[1][8], "true");
+        assert_eq!(rows[2][7], "\"v2\"");
+
+        let usage = usage_rows([&doc, &doc]);
+        assert_eq!(usage[0], vec!["posts", "title", "1", "投稿詳細"]);
+        assert!(usage.iter().any(|r| r[0] == "users" && r[1] == "name"));
+    }
+
+    #[test]
+    fn test_dbt_exposures() {
+        let doc = parser::parse(
+            r#"
+version: "0.1"
+import:
+  openapi: ./api.yaml#paths["/posts/{id}"].get.responses["200"]
+  dbml:
+    - ./schema.dbml#tables["posts"]
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: 投稿詳細
+  tags: [posts]
+  owner: content-team
+  response_mapping:
+    - field: title
+      source: posts.title
+    - field: author
+      source: users.name
+"#,
+        )
+        .unwrap();
+
+        let exposures = dbt_exposures([("usecases/post-detail.usml.yaml", &doc)], Some("app"));
+        let exposure = &exposures.exposures[0];
+        assert_eq!(exposure.name, "post_detail");
+        assert_eq!(
+            exposure.depends_on,
+            vec!["source('app', 'posts')", "source('app', 'users')"]
+        );
+        assert_eq!(exposure.owner.name, "content-team");
+        assert_eq!(exposure.meta.endpoint.as_deref(), Some("GET /posts/{id}"));
+        assert_eq!(exposure.meta.columns["users"], vec!["name"]);
+
+        let yaml = exposures.to_yaml();
+        assert!(yaml.starts_with("version: 2\nexposures:\n"));
+        assert!(yaml.contains("type: application"));
+    }
+
+    #[test]
+    fn test_usage_dbml() {
+        let doc = parser::parse(
+            r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["posts"]
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: 投稿一覧
+  response_mapping:
+    - field: title
+      source: posts.title
+    - field: author
+      source: author.name
+      join:
+        table: users
+        alias: author
+        on: posts.user_id = author.id
+"#,
+        )
+        .unwrap();
+        let users = resolver::DbmlTable {
+            name: "users".to_string(),
+            columns: vec!["id".to_string(), "name".to_string(), "email".to_string()],
+            column_details: vec![
+                resolver::DbmlColumn {
+                    name: "id".to_string(),
+                    data_type: "integer".to_string(),
+                    primary_key: true,
+                    ..Default::default()
+                },
+                resolver::DbmlColumn {
+                    name: "name".to_string(),
+                    data_type: "varchar".to_string(),
+                    nullable: Some(false),
+                    ..Default::default()
+                },
+            ],
+            location: None,
+        };
+
+        let dbml = usage_dbml([&doc], &[users]);
+        assert!(dbml.contains(
+            "Table posts {\n  title unknown\n  user_id unknown\n  Note: '参照する usecase: 投稿一覧'\n}"
+        ));
+        assert!(dbml.contains("Table users {\n  id integer [pk]\n  name varchar [not null]\n"));
+        assert!(dbml.ends_with("\nRef: posts.user_id > users.id\n"));
+    }
+
+    #[test]
+    fn test_access_matrix() {
+        let doc = parser::parse(
+            r#"
+version: "0.1"
+import:
+  openapi: ./api.yaml#paths["/users"].get.responses["200"]
+  dbml:
+    - ./schema.dbml#tables["users"]
+    - ./schema.dbml#tables["profiles"]
+usecase:
+  name: ユーザー一覧
+  response_mapping:
+    - field: email
+      source: users.email
+    - field: avatar
+      source: profiles.avatar_url
+      join:
+        table: profiles
+        on: users.id = profiles.user_id
+  filters:
+    - param: status
+      maps_to: WHERE
+      condition: users.status = :status
+"#,
+        )
+        .unwrap();
+        let governance = GovernanceConfig {
+            pii: vec!["users.email".to_string()],
+        };
+
+        let matrix = access_matrix([("users-list.usml.yaml", &doc)], &governance);
+        let names: Vec<String> = matrix
+            .columns
+            .iter()
+            .map(|c| format!("{}.{}", c.table, c.column))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "profiles.avatar_url",
+                "profiles.user_id",
+                "users.email",
+                "users.id",
+                "users.status"
+            ]
+        );
+        assert!(matrix.columns[2].pii);
+        let row = &matrix.usecases[0];
+        assert_eq!(row.endpoint.as_deref(), Some("GET /users"));
+        assert_eq!(row.cells["users.email"].fields, vec!["email"]);
+        assert!(row.cells["users.status"].fields.is_empty());
+    }
+
+    #[test]
+    fn test_datahub_proposals() {
+        let doc = parser::parse(
+            r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: ユーザー一覧
+  owner: core
+  response_mapping:
+    - field: name
+      source: users.name
+"#,
+        )
+        .unwrap();
+        let options = DatahubOptions {
+            database: Some("app.public".to_string()),
+            ..DatahubOptions::default()
+        };
+
+        let proposals = datahub_proposals(
+            [("users-list.usml.yaml", &doc, crate::lineage::lineage(&doc))],
+            &options,
+        );
+        let aspects: Vec<_> = proposals.iter().map(|p| p.aspect_name).collect();
+        assert_eq!(
+            aspects,
+            vec![
+                "datasetProperties",
+                "subTypes",
+                "upstreamLineage",
+                "ownership"
+            ]
+        );
+        assert_eq!(
+            proposals[0].entity_urn,
+            "urn:li:dataset:(urn:li:dataPlatform:usml,users_list,PROD)"
+        );
+        let DatahubAspect::Lineage(lineage) = &proposals[2].aspect.json else {
+            panic!("upstreamLineage ではない");
+        };
+        assert_eq!(
+            lineage.fine_grained_lineages[0].upstreams,
+            vec![
+                "urn:li:schemaField:(urn:li:dataset:(urn:li:dataPlatform:postgres,app.public.users,PROD),name)"
+            ]
+        );
+    }
+}
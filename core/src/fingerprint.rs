@@ -0,0 +1,222 @@
+//! 内容の指紋
+//!
+//! `semantic` は YAML の書き方（インデント・引用符・コメント・キーの順序・フロー形式）や説明のための項目に
+//! 左右されない usecase の意味の指紋で、`usml fmt` の正規化でも変わらない。`usml sign` / `usml verify` が承認した時点からの変更の検出に使う。
+//! 改ざんの検出ではなく変更の検出のためのもので、暗号学的なハッシュではない
+
+use serde_yaml::Value;
+
+use crate::ast::UsmlDocument;
+use crate::normalize;
+
+/// 材料を順に混ぜた 64 ビットの FNV-1a ハッシュ（16 進）
+///
+/// 材料の境目がずれて別の組み合わせと同じにならないよう、各材料の前に長さを混ぜる
+pub fn hash<'a>(parts: impl IntoIterator<Item = &'a str>) -> String {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+    let mut hash = OFFSET;
+    for part in parts {
+        for byte in (part.len() as u64)
+            .to_le_bytes()
+            .iter()
+            .chain(part.as_bytes())
+        {
+            hash ^= u64::from(*byte);
+            hash = hash.wrapping_mul(PRIME);
+        }
+    }
+    format!("{:016x}", hash)
+}
+
+/// usecase の意味の指紋
+///
+/// パース済みのドキュメントを正規の形（宣言順のフィールド・名前順のマップ）で書き出してハッシュにする。
+/// データの取り方を変えない `summary`・`tags`・`owner`・`reviewers`・`output` は含めない。
+/// `usml fmt` が変える表記（import.dbml の順・フィールドの順・join.type の表記と既定の LEFT）はそろえてから書き出す
+pub fn semantic(doc: &UsmlDocument) -> String {
+    let mut value = serde_yaml::to_value(doc).expect("ドキュメントは YAML に変換できる");
+    if let Some(usecase) = value.get_mut("usecase").and_then(|u| u.as_mapping_mut()) {
+        for key in ["summary", "tags", "owner", "reviewers", "output"] {
+            usecase.remove(key);
+        }
+        if let Some(mappings) = usecase.get_mut("response_mapping") {
+            canonical_mappings(mappings);
+        }
+    }
+    if let Some(Value::Sequence(dbml)) = value.get_mut("import").and_then(|i| i.get_mut("dbml")) {
+        dbml.sort_by(|a, b| a.as_str().cmp(&b.as_str()));
+    }
+    let canonical = serde_yaml::to_string(&value).expect("ドキュメントは YAML に変換できる");
+    hash(["usml-semantic", &canonical])
+}
+
+/// response_mapping（入れ子含む）をフィールド名の順に並べ、join.type を正規の表記にする（LEFT は省略と同じ）
+fn canonical_mappings(mappings: &mut Value) {
+    let Value::Sequence(items) = mappings else {
+        return;
+    };
+    for item in items.iter_mut() {
+        if let Some(join_type) = item.get_mut("join").and_then(|j| j.get_mut("type"))
+            && let Some(keyword) = join_type.as_str().and_then(normalize::join_keyword)
+        {
+            *join_type = match keyword {
+                "LEFT" => Value::Null,
+                keyword => Value::String(keyword.to_string()),
+            };
+        }
+        if let Some(fields) = item.get_mut("fields") {
+            canonical_mappings(fields);
+        }
+    }
+    items.sort_by(|a, b| {
+        let field = |v: &Value| v.get("field").and_then(Value::as_str).map(str::to_string);
+        field(a).cmp(&field(b))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::FmtConfig;
+    use crate::normalize::KeywordCase;
+    use crate::parser;
+    use crate::resolver::OpenapiResponse;
+
+    const DOC: &str = r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: ユーザー一覧
+  summary: 一覧を返す
+  response_mapping:
+    - field: id
+      source: users.id
+    - field: name
+      source: users.name
+  filters:
+    - param: name
+      maps_to: WHERE
+      condition: users.name = :name
+"#;
+
+    fn fingerprint(yaml: &str) -> String {
+        semantic(&parser::parse(yaml).unwrap())
+    }
+
+    #[test]
+    fn test_semantic_ignores_formatting() {
+        let reformatted = r#"
+# 並びと書き方だけを変える
+usecase:
+  filters: [{maps_to: WHERE, param: "name", condition: "users.name = :name"}]
+  name: "ユーザー一覧"
+  response_mapping:
+    - {source: users.id, field: id}
+    - field: name   # 名前
+      source: 'users.name'
+  summary: 説明を書き換えても承認は残る
+  tags: [users]
+  owner: team-a
+version: '0.1'
+import:
+  dbml: ['./schema.dbml#tables["users"]']
+"#;
+        assert_eq!(fingerprint(DOC), fingerprint(reformatted));
+    }
+
+    #[test]
+    fn test_semantic_detects_changes() {
+        let original = fingerprint(DOC);
+        assert_ne!(
+            original,
+            fingerprint(&DOC.replace("users.name = :name", "users.name LIKE :name"))
+        );
+        assert_ne!(
+            original,
+            fingerprint(&DOC.replace("source: users.name", "source: users.email"))
+        );
+        assert_ne!(
+            original,
+            fingerprint(&DOC.replace("name: ユーザー一覧", "name: 会員一覧"))
+        );
+    }
+
+    #[test]
+    fn test_semantic_ignores_fmt_normalizations() {
+        let doc = r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["users"]
+    - ./schema.dbml#tables["profiles"]
+    - ./schema.dbml#tables["comments"]
+usecase:
+  name: ユーザー詳細
+  distinct: false
+  response_mapping:
+    - field: name
+      source: users.name
+    - field: bio
+      source: profiles.bio
+      join:
+        table: profiles
+        on: users.id = profiles.user_id
+        type: left join
+    - field: comments
+      type: array
+      source_table: comments
+      collection: false
+      fields:
+        - field: text
+          source: comments.text
+        - field: id
+          source: comments.id
+          join:
+            table: users
+            on: comments.user_id = users.id
+            type: "Inner"
+  filters: []
+"#;
+        let openapi = OpenapiResponse {
+            field_order: ["comments", "comments.id", "comments.text", "bio", "name"]
+                .map(String::from)
+                .to_vec(),
+            ..OpenapiResponse::default()
+        };
+        let configs = [
+            FmtConfig {
+                sort_imports: true,
+                ..FmtConfig::default()
+            },
+            FmtConfig {
+                order_fields: true,
+                ..FmtConfig::default()
+            },
+            FmtConfig {
+                join_type: Some(KeywordCase::Lower),
+                ..FmtConfig::default()
+            },
+            FmtConfig {
+                join_type: Some(KeywordCase::Upper),
+                ..FmtConfig::default()
+            },
+            FmtConfig {
+                collapse_defaults: true,
+                ..FmtConfig::default()
+            },
+        ];
+        let original = fingerprint(doc);
+        for config in configs {
+            let formatted = normalize::normalize(doc, &config, Some(&openapi)).unwrap();
+            assert_ne!(formatted, doc, "{:?} は書き方を変える", config);
+            assert_eq!(fingerprint(&formatted), original, "{:?}", config);
+        }
+        assert_ne!(
+            original,
+            fingerprint(&doc.replace("type: left join", "type: INNER"))
+        );
+    }
+}
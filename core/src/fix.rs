@@ -0,0 +1,360 @@
+//! 診断の直し方（クイックフィックス）
+//!
+//! よく出る診断に、コメントとレイアウトを保ったまま（`edit::Editor`）適用できる置換を組み立てる。
+//! エディタのコードアクションと `usml fix` が使う。置換は元のソースとの差分 1 件で、
+//! 適用後のドキュメントが読めない直し方は出さない
+//!
+//! | 規則 | 直し方 |
+//! |---|---|
+//! | U001 `import.dbml` | 既存の DBML ファイル（無ければ `./schema.dbml`）のテーブルを import.dbml に加える |
+//! | U004 `join.alias` | 後から出てくる結合に alias を付け、source・join.on の修飾子も書き換える |
+//! | U005 `aggregate.group_by` | 暗黙にまとめるキー（ルートテーブルの主キー、配列の要素では親の行のキー）を group_by に書く |
+//! | U007 `filters.condition` | 宣言の無い :パラメータ を `maps_to: WHERE` のフィルタとして宣言する |
+
+use serde::Serialize;
+
+use crate::ast::{ResponseMapping, UsmlDocument};
+use crate::diagnostic::Diagnostic;
+use crate::edit::{Editor, TextEdit};
+use crate::model::Model;
+use crate::parser;
+use crate::syntax::{Range, Span, SyntaxTree};
+use crate::validator;
+
+/// 診断 1 件の直し方
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CodeAction {
+    /// 表示する説明（例: `import.dbml にテーブル 'users' を加える`）
+    pub title: String,
+    /// 直す診断の `diagnostics` 上の添字
+    pub diagnostic: usize,
+    /// 直す診断の規則のコード
+    pub code: String,
+    pub edit: TextEdit,
+    /// `edit` の範囲の行・列
+    pub range: Range,
+}
+
+impl CodeAction {
+    /// `source` に置換を適用する
+    pub fn apply(&self, source: &str) -> String {
+        let mut fixed = source.to_string();
+        fixed.replace_range(self.edit.span.start..self.edit.span.end, &self.edit.text);
+        fixed
+    }
+}
+
+/// `source` の検証で出た `diagnostics` のうち、直し方のあるものの直し方
+///
+/// 同じ置換になる診断（同じテーブルの U001 など）は最初の 1 件にだけ付ける。パースできないソースでは空
+pub fn code_actions(source: &str, diagnostics: &[Diagnostic]) -> Vec<CodeAction> {
+    let (Ok(doc), Ok(tree)) = (parser::parse(source), SyntaxTree::parse(source)) else {
+        return Vec::new();
+    };
+    let mut actions: Vec<CodeAction> = Vec::new();
+    for (index, diagnostic) in diagnostics.iter().enumerate() {
+        let Some(code) = diagnostic.code.as_deref() else {
+            continue;
+        };
+        let Ok(mut editor) = Editor::new(source) else {
+            break;
+        };
+        let title = match code {
+            "U001" => add_dbml_import(&mut editor, &doc, &diagnostic.message),
+            "U004" => add_join_alias(&mut editor, &doc, &diagnostic.message),
+            "U005" => add_group_by(&mut editor, &doc, &diagnostic.message),
+            "U007" => declare_param(&mut editor, &doc, &diagnostic.message),
+            _ => None,
+        };
+        let Some(title) = title else {
+            continue;
+        };
+        if parser::parse(editor.as_str()).is_err() {
+            continue;
+        }
+        let edit = diff(source, editor.as_str());
+        if actions.iter().any(|action| action.edit == edit) {
+            continue;
+        }
+        actions.push(CodeAction {
+            title,
+            diagnostic: index,
+            code: code.to_string(),
+            range: tree.range(source, edit.span),
+            edit,
+        });
+    }
+    actions
+}
+
+/// メッセージの最初の引用符の中身（`テーブル 'users' が…` の `users`）
+fn quoted(message: &str) -> Option<&str> {
+    message.split('\'').nth(1)
+}
+
+/// U001: テーブルを import.dbml に加える
+fn add_dbml_import(editor: &mut Editor, doc: &UsmlDocument, message: &str) -> Option<String> {
+    let table = quoted(message)?;
+    let file = doc
+        .import
+        .dbml
+        .iter()
+        .flatten()
+        .find_map(|reference| reference.split_once('#').map(|(file, _)| file))
+        .unwrap_or("./schema.dbml");
+    editor
+        .add_dbml_import(&format!("{}#tables[\"{}\"]", file, table))
+        .ok()?;
+    Some(format!("import.dbml にテーブル '{}' を加える", table))
+}
+
+/// U004: バリデータと同じ順に辿り、alias の要る結合のフィールドに alias を付ける
+fn add_join_alias(editor: &mut Editor, doc: &UsmlDocument, message: &str) -> Option<String> {
+    let table = quoted(message)?;
+    let (path, field) = duplicated_join(&doc.usecase.response_mapping, "", table, &mut None)?;
+
+    // フィールド名の先頭の語（editor_name なら editor）。使われている名前なら table_2, table_3, …
+    let model = Model::build(doc);
+    let taken = |name: &str| {
+        model
+            .tables
+            .iter()
+            .any(|t| t.name == name || t.aliases.iter().any(|a| a == name))
+    };
+    let alias = field
+        .split('_')
+        .next()
+        .filter(|word| !word.is_empty() && !taken(word))
+        .map(str::to_string)
+        .or_else(|| {
+            (2..)
+                .map(|n| format!("{}_{}", table, n))
+                .find(|name| !taken(name))
+        })?;
+    editor.set_join_alias(&path, &alias).ok()?;
+    Some(format!(
+        "フィールド '{}' の join に alias '{}' を付ける",
+        path, alias
+    ))
+}
+
+/// `table` を最初と違う結合条件で、どちらにも alias を付けずに結合するフィールドのパスと名前
+fn duplicated_join(
+    mappings: &[ResponseMapping],
+    prefix: &str,
+    table: &str,
+    first: &mut Option<(String, bool)>,
+) -> Option<(String, String)> {
+    for mapping in mappings {
+        let path = field_path(prefix, &mapping.field);
+        if let Some(join) = mapping.join.as_ref().filter(|join| join.table == table) {
+            match first {
+                Some((on, aliased)) => {
+                    if *on != join.on && !*aliased && join.alias.is_none() {
+                        return Some((path, mapping.field.clone()));
+                    }
+                }
+                None => *first = Some((join.on.clone(), join.alias.is_some())),
+            }
+        }
+        if let Some(fields) = &mapping.fields
+            && let Some(found) = duplicated_join(fields, &path, table, first)
+        {
+            return Some(found);
+        }
+    }
+    None
+}
+
+/// U005: 暗黙にまとめるキー（ルートテーブルの主キー・親の行のキー）を group_by に書く
+fn add_group_by(editor: &mut Editor, doc: &UsmlDocument, message: &str) -> Option<String> {
+    let name = quoted(message)?;
+    let path = ungrouped_aggregate(&doc.usecase.response_mapping, "", name)?;
+    let column = validator::group_by_example(&Model::build(doc), &path)?;
+    editor.set_group_by(&path, &column).ok()?;
+    Some(format!(
+        "フィールド '{}' の aggregate に group_by: {} を書く",
+        path, column
+    ))
+}
+
+/// group_by の無い集約フィールド `name` のパス
+fn ungrouped_aggregate(mappings: &[ResponseMapping], prefix: &str, name: &str) -> Option<String> {
+    for mapping in mappings {
+        let path = field_path(prefix, &mapping.field);
+        if mapping.field == name
+            && mapping
+                .aggregate
+                .as_ref()
+                .is_some_and(|agg| agg.group_by.is_none())
+        {
+            return Some(path);
+        }
+        if let Some(fields) = &mapping.fields
+            && let Some(found) = ungrouped_aggregate(fields, &path, name)
+        {
+            return Some(found);
+        }
+    }
+    None
+}
+
+/// U007: 宣言の無い :パラメータ を WHERE のフィルタとして宣言する
+fn declare_param(editor: &mut Editor, doc: &UsmlDocument, message: &str) -> Option<String> {
+    let param = quoted(message)?.strip_prefix(':')?;
+    if doc.usecase.filters.iter().any(|f| f.param == param) {
+        return None;
+    }
+    editor
+        .add_filter(&[("param", param.into()), ("maps_to", "WHERE".into())])
+        .ok()?;
+    Some(format!("filters に param '{}' を宣言する", param))
+}
+
+fn field_path(prefix: &str, field: &str) -> String {
+    if prefix.is_empty() {
+        field.to_string()
+    } else {
+        format!("{}.{}", prefix, field)
+    }
+}
+
+/// `before` を `after` にする置換 1 件（前後の一致する部分を除く）
+fn diff(before: &str, after: &str) -> TextEdit {
+    let mut prefix = before
+        .bytes()
+        .zip(after.bytes())
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !before.is_char_boundary(prefix) || !after.is_char_boundary(prefix) {
+        prefix -= 1;
+    }
+    let mut suffix = before[prefix..]
+        .bytes()
+        .rev()
+        .zip(after[prefix..].bytes().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !before.is_char_boundary(before.len() - suffix)
+        || !after.is_char_boundary(after.len() - suffix)
+    {
+        suffix -= 1;
+    }
+    TextEdit {
+        span: Span {
+            start: prefix,
+            end: before.len() - suffix,
+        },
+        text: after[prefix..after.len() - suffix].to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validator::Validator;
+
+    const DOC: &str = r#"version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["posts"]   # 投稿
+usecase:
+  name: 投稿一覧
+  response_mapping:
+    - field: id
+      source: posts.id
+    - field: author_name
+      source: users.name
+      join:
+        table: users
+        on: posts.user_id = users.id
+    - field: editor_name
+      source: users.name
+      join:
+        table: users
+        on: posts.editor_id = users.id
+    - field: like_count
+      source: likes.id
+      join:
+        table: likes
+        on: posts.id = likes.post_id
+      aggregate:
+        type: COUNT   # いいね数
+  filters:
+    - param: status
+      maps_to: WHERE
+      condition: posts.status = :status AND posts.kind = :kind
+"#;
+
+    fn actions(source: &str) -> Vec<CodeAction> {
+        let diagnostics = Validator::new().validate(&parser::parse(source).unwrap());
+        code_actions(source, &diagnostics)
+    }
+
+    fn fixed(title: &str) -> String {
+        let actions = actions(DOC);
+        let action = actions
+            .iter()
+            .find(|a| a.title == title)
+            .unwrap_or_else(|| panic!("'{}' がありません: {:?}", title, actions));
+        action.apply(DOC)
+    }
+
+    #[test]
+    fn test_code_actions_for_common_diagnostics() {
+        let titles: Vec<(String, String)> = actions(DOC)
+            .into_iter()
+            .map(|a| (a.code, a.title))
+            .collect();
+        assert_eq!(
+            titles,
+            [
+                ("U001", "import.dbml にテーブル 'users' を加える"),
+                ("U001", "import.dbml にテーブル 'likes' を加える"),
+                (
+                    "U004",
+                    "フィールド 'editor_name' の join に alias 'editor' を付ける"
+                ),
+                (
+                    "U005",
+                    "フィールド 'like_count' の aggregate に group_by: posts.id を書く"
+                ),
+                ("U007", "filters に param 'kind' を宣言する"),
+            ]
+            .map(|(code, title)| (code.to_string(), title.to_string()))
+        );
+    }
+
+    #[test]
+    fn test_code_actions_keep_comments_and_fix_diagnostics() {
+        let source = fixed("import.dbml にテーブル 'users' を加える");
+        assert!(source.contains(
+            "    - ./schema.dbml#tables[\"posts\"]   # 投稿\n    - ./schema.dbml#tables[\"users\"]\n"
+        ));
+
+        let source = fixed("フィールド 'editor_name' の join に alias 'editor' を付ける");
+        assert!(source.contains("      source: editor.name\n"));
+        assert!(
+            source.contains("        on: posts.editor_id = editor.id\n        alias: editor\n")
+        );
+
+        let source = fixed("フィールド 'like_count' の aggregate に group_by: posts.id を書く");
+        assert!(source.contains("        type: COUNT   # いいね数\n        group_by: posts.id\n"));
+
+        let source = fixed("filters に param 'kind' を宣言する");
+        assert!(source.ends_with("    - param: kind\n      maps_to: WHERE\n"));
+        let diagnostics = Validator::new().validate(&parser::parse(&source).unwrap());
+        assert!(
+            !diagnostics
+                .iter()
+                .any(|d| d.code.as_deref() == Some("U007"))
+        );
+    }
+
+    #[test]
+    fn test_diff_is_minimal_edit() {
+        let edit = diff("a: 1\nb: 日本\n", "a: 1\nb: 日本語\n");
+        assert_eq!(edit.span, Span { start: 14, end: 14 });
+        assert_eq!(edit.text, "語");
+    }
+}
@@ -0,0 +1,120 @@
+//! 生成物に付ける印
+//!
+//! 生成したファイルの先頭にコメントで `@generated by usml` の印を入れ、
+//! 印の無いファイル（手で書いたファイル、印を消して編集したファイル）を上書きしないようにする
+
+/// 生成物の先頭に入れる印
+pub const MARKER: &str = "@generated by usml";
+
+/// 印を探す先頭の行数（DOCTYPE・shebang の次の行に入るため 1 行目とは限らない）
+const MARKER_LINES: usize = 5;
+
+/// 印を入れるコメントの書き方
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentStyle {
+    /// `// ...`
+    Slash,
+    /// `# ...`
+    Hash,
+    /// `-- ...`
+    Dash,
+    /// `<!-- ... -->`
+    Html,
+}
+
+impl CommentStyle {
+    /// 出力先の拡張子から決める。コメントを書けない形式（JSON・CSV など）は None
+    pub fn for_path(path: &str) -> Option<Self> {
+        let file = path.rsplit(['/', '\\']).next().unwrap_or(path);
+        let (_, extension) = file.rsplit_once('.')?;
+        match extension.to_ascii_lowercase().as_str() {
+            "rs" | "kt" | "kts" | "java" | "scala" | "go" | "swift" | "ts" | "tsx" | "js"
+            | "jsx" | "c" | "h" | "cc" | "cpp" | "hpp" | "cs" | "dart" | "proto" | "dbml" => {
+                Some(Self::Slash)
+            }
+            "py" | "rb" | "sh" | "yaml" | "yml" | "toml" | "graphql" | "gql" | "tf" | "ex"
+            | "exs" => Some(Self::Hash),
+            "sql" | "lua" | "hs" => Some(Self::Dash),
+            "html" | "htm" | "xml" | "vue" | "svelte" | "md" => Some(Self::Html),
+            _ => None,
+        }
+    }
+
+    fn comment(self, text: &str) -> String {
+        match self {
+            Self::Slash => format!("// {}", text),
+            Self::Hash => format!("# {}", text),
+            Self::Dash => format!("-- {}", text),
+            Self::Html => format!("<!-- {} -->", text),
+        }
+    }
+}
+
+/// 先頭に印のコメントを入れる。DOCTYPE・XML 宣言・shebang はそのまま 1 行目に残す。すでに印があれば変えない
+pub fn mark(content: &str, style: CommentStyle) -> String {
+    if is_generated(content) {
+        return content.to_string();
+    }
+    let line = style.comment(&format!(
+        "{}。手で編集せず、元の USML から生成し直す",
+        MARKER
+    ));
+    let first = content.lines().next().unwrap_or_default();
+    let keep_first = first.starts_with("#!")
+        || first.to_ascii_lowercase().starts_with("<!doctype")
+        || first.starts_with("<?xml");
+    if keep_first {
+        let (first, rest) = content.split_at(first.len());
+        let rest = rest.strip_prefix('\n').unwrap_or(rest);
+        format!("{}\n{}\n{}", first, line, rest)
+    } else {
+        format!("{}\n{}", line, content)
+    }
+}
+
+/// 先頭付近に印があるか
+pub fn is_generated(content: &str) -> bool {
+    content
+        .lines()
+        .take(MARKER_LINES)
+        .any(|line| line.contains(MARKER))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_comment_style_for_path() {
+        assert_eq!(
+            CommentStyle::for_path("src/Users.kt"),
+            Some(CommentStyle::Slash)
+        );
+        assert_eq!(
+            CommentStyle::for_path("schema.graphql"),
+            Some(CommentStyle::Hash)
+        );
+        assert_eq!(
+            CommentStyle::for_path("out/report.HTML"),
+            Some(CommentStyle::Html)
+        );
+        assert_eq!(CommentStyle::for_path("vectors.json"), None);
+        assert_eq!(CommentStyle::for_path("Makefile"), None);
+    }
+
+    #[test]
+    fn test_mark_keeps_doctype_and_is_idempotent() {
+        let html = mark("<!DOCTYPE html>\n<html>\n", CommentStyle::Html);
+        assert_eq!(
+            html,
+            "<!DOCTYPE html>\n<!-- @generated by usml。手で編集せず、元の USML から生成し直す -->\n<html>\n"
+        );
+        assert!(is_generated(&html));
+        assert_eq!(mark(&html, CommentStyle::Html), html);
+
+        let sql = mark("SELECT 1;\n", CommentStyle::Dash);
+        assert!(sql.starts_with("-- @generated by usml"));
+        assert!(sql.ends_with("\nSELECT 1;\n"));
+        assert!(!is_generated("SELECT 1;\n"));
+    }
+}
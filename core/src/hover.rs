@@ -0,0 +1,438 @@
+use serde::Serialize;
+
+use crate::model::Model;
+use crate::parser;
+use crate::resolver::{DbmlColumn, OpenapiParameter, OpenapiProperty};
+use crate::syntax::{Position, Range, Span, SyntaxError, SyntaxKind, SyntaxNode, SyntaxTree};
+use crate::validator::ResolveContext;
+
+/// `テーブル.カラム` を値に取るキー
+pub(crate) const COLUMN_KEYS: &[&str] = &[
+    "source",
+    "sources",
+    "maps_to",
+    "on",
+    "condition",
+    "then_source",
+    "else_source",
+    "rate",
+    "column",
+    "cursor_field",
+    "cursor_tiebreaker",
+    "default_column",
+    "allowed_columns",
+    "group_by",
+];
+
+/// カーソル位置にある要素
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum HoverTarget {
+    /// レスポンスフィールド（`field`・`target` など）
+    Field {
+        /// フルパス（例: "comments.body"）
+        path: String,
+        property: Option<OpenapiProperty>,
+    },
+    /// DB カラム参照（`source`・`maps_to`・`join.on` 内の参照など）
+    Source {
+        /// 記述どおりの修飾子（エイリアスのこともある）
+        qualifier: String,
+        /// エイリアス解決済みのテーブル名
+        table: String,
+        column: String,
+        definition: Option<DbmlColumn>,
+    },
+    /// リクエストパラメータ（`param` や条件式内の `:param`）
+    Param {
+        name: String,
+        definition: Option<OpenapiParameter>,
+    },
+}
+
+/// ホバー結果
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Hover {
+    pub target: HoverTarget,
+    /// 表示用の Markdown
+    pub contents: String,
+    /// ホバー対象の範囲
+    pub span: Span,
+    pub range: Range,
+}
+
+/// カーソル位置（1 始まりの行・列）の要素と、解決済みスキーマから得た情報を返す
+///
+/// 要素が無い位置では `Ok(None)`。`ctx` が空でも USML 上の情報だけは返す
+pub fn hover(
+    source: &str,
+    ctx: &ResolveContext,
+    position: Position,
+) -> Result<Option<Hover>, SyntaxError> {
+    let tree = SyntaxTree::parse(source)?;
+    let offset = tree.offset(source, position.line, position.column);
+    let Some(found) = find_scalar(&tree.root, offset, "", false, &[]) else {
+        return Ok(None);
+    };
+    // 編集途中で AST にできない場合はエイリアスを解決せずに続ける
+    let model = parser::parse(source).ok().map(|doc| Model::build(&doc));
+
+    let value = found.node.as_str().unwrap_or_default();
+    let (target, span) = match found.key {
+        "field" if found.response_field => (field_target(ctx, found.path), found.node.span),
+        "field" | "target" => (field_target(ctx, value.to_string()), found.node.span),
+        "param" | "limit_param" => (param_target(ctx, value), found.node.span),
+        key => {
+            let span = token_at(source, found.node.span, offset);
+            let token = &source[span.start..span.end];
+            if let Some(name) = token.strip_prefix(':') {
+                (param_target(ctx, name), span)
+            } else if COLUMN_KEYS.contains(&key)
+                && let Some((qualifier, column)) = token.split_once('.')
+            {
+                let table = model
+                    .as_ref()
+                    .map_or(qualifier, |m| m.resolve_table(qualifier));
+                let definition = ctx
+                    .dbml_tables
+                    .iter()
+                    .find(|t| t.name == table)
+                    .and_then(|t| t.column(column))
+                    .cloned();
+                let target = HoverTarget::Source {
+                    qualifier: qualifier.to_string(),
+                    table: table.to_string(),
+                    column: column.to_string(),
+                    definition,
+                };
+                (target, span)
+            } else {
+                return Ok(None);
+            }
+        }
+    };
+
+    Ok(Some(Hover {
+        contents: contents(&target),
+        target,
+        span,
+        range: tree.range(source, span),
+    }))
+}
+
+/// カーソル位置を含むスカラーとその文脈
+pub(crate) struct Found<'a> {
+    /// スカラーを値に持つキー（シーケンス要素なら親のキー）
+    pub(crate) key: &'a str,
+    pub(crate) node: &'a SyntaxNode,
+    /// 囲んでいるレスポンスフィールドのフルパス
+    pub(crate) path: String,
+    /// response_mapping 要素の `field` かどうか
+    pub(crate) response_field: bool,
+}
+
+/// `mappings` が true のとき `node` は response_mapping（または fields）の要素
+pub(crate) fn find_scalar<'a>(
+    node: &'a SyntaxNode,
+    offset: usize,
+    key: &'a str,
+    mappings: bool,
+    path: &[&'a str],
+) -> Option<Found<'a>> {
+    match &node.kind {
+        SyntaxKind::Scalar { .. } => node.span.contains(offset).then(|| Found {
+            key,
+            node,
+            path: path.join("."),
+            response_field: false,
+        }),
+        SyntaxKind::Sequence(items) => items
+            .iter()
+            .find(|item| item.span.contains(offset))
+            .and_then(|item| find_scalar(item, offset, key, mappings, path)),
+        SyntaxKind::Mapping(entries) => {
+            let mut path = path.to_vec();
+            if mappings && let Some(field) = node.get("field").and_then(SyntaxNode::as_str) {
+                path.push(field);
+            }
+            let entry = entries.iter().find(|e| e.value.span.contains(offset))?;
+            let child_mappings =
+                entry.key == "response_mapping" || (mappings && entry.key == "fields");
+            let mut found = find_scalar(&entry.value, offset, &entry.key, child_mappings, &path)?;
+            if mappings && entry.key == "field" {
+                found.response_field = true;
+            }
+            Some(found)
+        }
+    }
+}
+
+/// スカラー内でカーソル位置を含む識別子（`posts.id`・`:post_id` など）の範囲
+pub(crate) fn token_at(source: &str, scalar: Span, offset: usize) -> Span {
+    let is_token = |c: char| c.is_alphanumeric() || matches!(c, '_' | '.' | ':');
+    let text = &source[scalar.start..scalar.end];
+    let at = offset.clamp(scalar.start, scalar.end) - scalar.start;
+    let start = text[..at]
+        .char_indices()
+        .rev()
+        .take_while(|&(_, c)| is_token(c))
+        .last()
+        .map_or(at, |(i, _)| i);
+    let end = text[at..]
+        .char_indices()
+        .find(|&(_, c)| !is_token(c))
+        .map_or(text.len(), |(i, _)| at + i);
+    Span {
+        start: scalar.start + start,
+        end: scalar.start + end,
+    }
+}
+
+fn field_target(ctx: &ResolveContext, path: String) -> HoverTarget {
+    let property = ctx
+        .openapi
+        .as_ref()
+        .and_then(|o| o.property(&path))
+        .cloned();
+    HoverTarget::Field { path, property }
+}
+
+fn param_target(ctx: &ResolveContext, name: &str) -> HoverTarget {
+    let definition = ctx
+        .openapi
+        .as_ref()
+        .and_then(|o| o.parameter(name))
+        .cloned();
+    HoverTarget::Param {
+        name: name.to_string(),
+        definition,
+    }
+}
+
+fn contents(target: &HoverTarget) -> String {
+    match target {
+        HoverTarget::Field { path, property } => {
+            let mut text = format!("**field** `{}`", path);
+            if let Some(p) = property {
+                let mut flags = Vec::new();
+                if p.required {
+                    flags.push("必須".to_string());
+                }
+                if p.nullable {
+                    flags.push("nullable".to_string());
+                }
+                push_schema(&mut text, &p.schema_type, &p.format, flags);
+                push_description(&mut text, &p.description);
+            }
+            text
+        }
+        HoverTarget::Source {
+            qualifier,
+            table,
+            column,
+            definition,
+        } => {
+            let mut text = format!("**column** `{}.{}`", qualifier, column);
+            if qualifier != table {
+                text.push_str(&format!("（`{}` のエイリアス）", table));
+            }
+            if let Some(c) = definition {
+                text.push_str(&format!("\n\nDBML: `{}`", c.data_type));
+                let mut constraints = Vec::new();
+                if c.primary_key {
+                    constraints.push("pk".to_string());
+                }
+                if c.increment {
+                    constraints.push("increment".to_string());
+                }
+                if c.unique {
+                    constraints.push("unique".to_string());
+                }
+                match c.nullable {
+                    Some(false) => constraints.push("not null".to_string()),
+                    Some(true) => constraints.push("null".to_string()),
+                    None => {}
+                }
+                if let Some(default) = &c.default {
+                    constraints.push(format!("default: {}", default));
+                }
+                if !constraints.is_empty() {
+                    text.push_str(&format!(" [{}]", constraints.join(", ")));
+                }
+                push_description(&mut text, &c.note);
+            }
+            text
+        }
+        HoverTarget::Param { name, definition } => {
+            let mut text = format!("**param** `{}`", name);
+            if let Some(p) = definition {
+                let mut flags = Vec::new();
+                if let Some(location) = &p.location {
+                    flags.push(format!("in: {}", location));
+                }
+                if p.required {
+                    flags.push("必須".to_string());
+                }
+                push_schema(&mut text, &p.schema_type, &p.format, flags);
+                push_description(&mut text, &p.description);
+            }
+            text
+        }
+    }
+}
+
+fn push_schema(
+    text: &mut String,
+    schema_type: &Option<String>,
+    format: &Option<String>,
+    flags: Vec<String>,
+) {
+    text.push_str("\n\nOpenAPI: ");
+    match (schema_type, format) {
+        (Some(t), Some(f)) => text.push_str(&format!("`{}` ({})", t, f)),
+        (Some(t), None) => text.push_str(&format!("`{}`", t)),
+        _ => text.push_str("型指定なし"),
+    }
+    if !flags.is_empty() {
+        text.push_str(&format!(" [{}]", flags.join(", ")));
+    }
+}
+
+fn push_description(text: &mut String, description: &Option<String>) {
+    if let Some(d) = description {
+        text.push_str("\n\n");
+        text.push_str(d);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resolver::{DbmlTable, OpenapiResponse};
+
+    const DOC: &str = r#"version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: 投稿詳細
+  response_mapping:
+    - field: author
+      source: u.name
+      join:
+        table: users
+        alias: u
+        on: u.id = posts.user_id
+    - field: comments
+      type: array
+      source_table: comments
+      fields:
+        - field: body
+          source: comments.body
+  filters:
+    - param: post_id
+      maps_to: posts.id
+      condition: posts.id = :post_id
+"#;
+
+    fn ctx() -> ResolveContext {
+        ResolveContext {
+            openapi: Some(OpenapiResponse {
+                properties: vec![OpenapiProperty {
+                    name: "comments".to_string(),
+                    schema_type: Some("array".to_string()),
+                    properties: vec![OpenapiProperty {
+                        name: "body".to_string(),
+                        schema_type: Some("string".to_string()),
+                        description: Some("本文".to_string()),
+                        ..OpenapiProperty::default()
+                    }],
+                    ..OpenapiProperty::default()
+                }],
+                parameter_details: vec![OpenapiParameter {
+                    name: "post_id".to_string(),
+                    location: Some("path".to_string()),
+                    required: true,
+                    schema_type: Some("integer".to_string()),
+                    ..OpenapiParameter::default()
+                }],
+                ..OpenapiResponse::default()
+            }),
+            dbml_tables: vec![DbmlTable {
+                name: "users".to_string(),
+                columns: vec!["name".to_string()],
+                column_details: vec![DbmlColumn {
+                    name: "name".to_string(),
+                    data_type: "varchar".to_string(),
+                    nullable: Some(false),
+                    ..DbmlColumn::default()
+                }],
+                location: None,
+            }],
+        }
+    }
+
+    fn at(line: usize, column: usize) -> Hover {
+        hover(DOC, &ctx(), Position { line, column })
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_hover_source_resolves_alias_to_dbml_column() {
+        let h = at(9, 17);
+        match &h.target {
+            HoverTarget::Source {
+                qualifier,
+                table,
+                definition,
+                ..
+            } => {
+                assert_eq!(qualifier, "u");
+                assert_eq!(table, "users");
+                assert_eq!(definition.as_ref().unwrap().data_type, "varchar");
+            }
+            other => panic!("unexpected target: {:?}", other),
+        }
+        assert_eq!(&DOC[h.span.start..h.span.end], "u.name");
+        assert!(h.contents.contains("not null"));
+
+        // join.on 内ではカーソル下の参照だけを対象にする
+        let on = at(13, 24);
+        assert_eq!(&DOC[on.span.start..on.span.end], "posts.user_id");
+    }
+
+    #[test]
+    fn test_hover_nested_field_shows_openapi_property() {
+        let h = at(18, 18);
+        match &h.target {
+            HoverTarget::Field { path, property } => {
+                assert_eq!(path, "comments.body");
+                assert_eq!(
+                    property.as_ref().unwrap().schema_type.as_deref(),
+                    Some("string")
+                );
+            }
+            other => panic!("unexpected target: {:?}", other),
+        }
+        assert!(h.contents.contains("本文"));
+    }
+
+    #[test]
+    fn test_hover_param_in_condition() {
+        let h = at(23, 30);
+        assert_eq!(&DOC[h.span.start..h.span.end], ":post_id");
+        match &h.target {
+            HoverTarget::Param { name, definition } => {
+                assert_eq!(name, "post_id");
+                assert!(definition.as_ref().unwrap().required);
+            }
+            other => panic!("unexpected target: {:?}", other),
+        }
+        assert!(
+            hover(DOC, &ctx(), Position { line: 1, column: 1 })
+                .unwrap()
+                .is_none()
+        );
+    }
+}
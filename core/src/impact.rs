@@ -0,0 +1,682 @@
+//! カラムを削除・改名したときに壊れる箇所の洗い出し（`usml impact`）
+//!
+//! ワークスペースの各 usecase について、source_usecase の参照先を合成したリネージ・結合条件・
+//! filters・transforms・行レベルの条件からカラムの参照を探す。`--schema-diff` では 2 つの版の DBML を
+//! 比べて削除・改名・型の変更があったカラムを求め、それぞれの参照箇所に影響の大きさを付ける
+
+use serde::Serialize;
+
+use crate::api::table_refs;
+use crate::ast::{Filter, Transform, UsmlDocument};
+use crate::lineage::EdgeKind;
+use crate::model::Model;
+use crate::resolver::{DbmlColumn, DbmlTable};
+use crate::workspace::Workspace;
+
+/// カラムを参照している箇所の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImpactKind {
+    /// レスポンスフィールドの値（source・aggregate・transform の入力）
+    Field,
+    /// 結合条件
+    Join,
+    Filter,
+    Transform,
+    /// `authorization.row_filters`
+    Authorization,
+}
+
+impl ImpactKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImpactKind::Field => "field",
+            ImpactKind::Join => "join",
+            ImpactKind::Filter => "filter",
+            ImpactKind::Transform => "transform",
+            ImpactKind::Authorization => "authorization",
+        }
+    }
+}
+
+/// カラムを参照している箇所 1 つ
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Impact {
+    pub file: String,
+    pub usecase: String,
+    pub kind: ImpactKind,
+    /// 箇所（フィールドのフルパス・filter の param・transform の target など）
+    pub target: String,
+    /// 参照している記述（`source`・`condition: users.email = :email` など）
+    pub detail: String,
+    /// source_usecase で参照した usecase を経由する場合はその usecase 名
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub via: Option<String>,
+}
+
+/// `table.column` を参照している箇所をワークスペース全体から探す（ファイル順）
+///
+/// テーブル名はエイリアスを解決して比べる。テーブル名の無いカラム名（`cursor_field: created_at` など）は、
+/// 結合で加わらないテーブル（FROM に置くテーブル）のカラムとみなす
+pub fn column_impact(workspace: &Workspace, table: &str, column: &str) -> Vec<Impact> {
+    let mut impacts = Vec::new();
+    for entry in workspace.documents() {
+        let doc = &entry.document;
+        let model = Model::build(doc);
+        let mut push = |kind: ImpactKind, target: &str, detail: String, via: Option<String>| {
+            let impact = Impact {
+                file: entry.path.clone(),
+                usecase: doc.usecase.name.clone(),
+                kind,
+                target: target.to_string(),
+                detail,
+                via,
+            };
+            if !impacts.contains(&impact) {
+                impacts.push(impact);
+            }
+        };
+        let matches = |reference: &str| references(&model, reference, table, column);
+
+        for edge in workspace.lineage(doc) {
+            if edge.table == table && edge.column == column {
+                let detail = match edge.kind {
+                    EdgeKind::Source => "source",
+                    EdgeKind::Aggregate => "aggregate",
+                    EdgeKind::Transform => "transform",
+                };
+                push(
+                    ImpactKind::Field,
+                    &edge.field,
+                    detail.to_string(),
+                    edge.usecase,
+                );
+            }
+        }
+        for field in &model.fields {
+            if let Some(group_by) = group_by(doc, &field.path)
+                && matches(group_by)
+            {
+                push(
+                    ImpactKind::Field,
+                    &field.path,
+                    format!("aggregate.group_by: {}", group_by),
+                    None,
+                );
+            }
+        }
+        for join in &model.joins {
+            if join
+                .references
+                .iter()
+                .any(|r| r.table == table && r.column == column)
+            {
+                push(
+                    ImpactKind::Join,
+                    &join.field,
+                    format!("JOIN {} ON {}", join.table, join.on),
+                    None,
+                );
+            }
+        }
+        for filter in &doc.usecase.filters {
+            for (key, value) in filter_columns(filter) {
+                if matches(value) {
+                    push(
+                        ImpactKind::Filter,
+                        &filter.param,
+                        format!("{}: {}", key, value),
+                        None,
+                    );
+                }
+            }
+        }
+        for transform in &doc.usecase.transforms {
+            for (key, value) in transform_columns(transform) {
+                if matches(value) {
+                    push(
+                        ImpactKind::Transform,
+                        &transform.target,
+                        format!("{} {}: {}", transform.r#type, key, value),
+                        None,
+                    );
+                }
+            }
+        }
+        for condition in doc
+            .usecase
+            .authorization
+            .iter()
+            .flat_map(|a| &a.row_filters)
+        {
+            if matches(condition) {
+                push(
+                    ImpactKind::Authorization,
+                    "row_filters",
+                    condition.clone(),
+                    None,
+                );
+            }
+        }
+    }
+    impacts
+}
+
+/// `reference`（`テーブル.カラム`・条件式・テーブル名の無いカラム名）が `table.column` を含むか
+fn references(model: &Model, reference: &str, table: &str, column: &str) -> bool {
+    if reference.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        let root = model
+            .tables
+            .iter()
+            .find(|t| !model.joins.iter().any(|j| j.table == t.name));
+        return reference == column && root.is_some_and(|t| t.name == table);
+    }
+    table_refs(reference)
+        .iter()
+        .any(|(qualifier, c)| c == column && model.resolve_table(qualifier) == table)
+}
+
+/// フィールドの `aggregate.group_by`
+fn group_by<'a>(doc: &'a UsmlDocument, path: &str) -> Option<&'a str> {
+    let mut mappings = &doc.usecase.response_mapping;
+    let mut segments = path.split('.').peekable();
+    while let Some(segment) = segments.next() {
+        let mapping = mappings.iter().find(|m| m.field == segment)?;
+        if segments.peek().is_none() {
+            return mapping.aggregate.as_ref()?.group_by.as_deref();
+        }
+        mappings = mapping.fields.as_ref()?;
+    }
+    None
+}
+
+/// filter がカラムを書く項目とその値
+fn filter_columns(filter: &Filter) -> Vec<(&'static str, &str)> {
+    let mut columns: Vec<(&'static str, &str)> = Vec::new();
+    columns.extend(filter.condition.as_deref().map(|c| ("condition", c)));
+    columns.extend(filter.cursor_field.as_deref().map(|c| ("cursor_field", c)));
+    columns.extend(
+        filter
+            .cursor_tiebreaker
+            .as_deref()
+            .map(|c| ("cursor_tiebreaker", c)),
+    );
+    columns.extend(
+        filter
+            .default_column
+            .as_deref()
+            .map(|c| ("default_column", c)),
+    );
+    columns.extend(
+        filter
+            .allowed_columns
+            .iter()
+            .flatten()
+            .map(|c| ("allowed_columns", c.as_str())),
+    );
+    columns
+}
+
+/// transform がカラムを書く項目とその値
+fn transform_columns(transform: &Transform) -> Vec<(&'static str, &str)> {
+    let mut columns: Vec<(&'static str, &str)> = Vec::new();
+    columns.extend(transform.source.as_deref().map(|c| ("source", c)));
+    columns.extend(
+        transform
+            .sources
+            .iter()
+            .flatten()
+            .map(|c| ("sources", c.as_str())),
+    );
+    columns.extend(transform.then_source.as_deref().map(|c| ("then_source", c)));
+    columns.extend(transform.else_source.as_deref().map(|c| ("else_source", c)));
+    columns.extend(
+        transform
+            .lookup
+            .as_ref()
+            .map(|l| ("lookup.rate", l.rate.as_str())),
+    );
+    columns.extend(
+        transform
+            .locale
+            .as_ref()
+            .map(|l| ("locale.column", l.column.as_str())),
+    );
+    columns.extend(
+        transform
+            .condition
+            .iter()
+            .flatten()
+            .filter_map(|c| c.source.as_deref())
+            .map(|c| ("condition.source", c)),
+    );
+    // テーブル名の無い値（固定値など）はカラムとみなさない
+    columns.retain(|(_, value)| value.contains('.'));
+    columns
+}
+
+/// 影響の大きさ（`usml impact --schema-diff`）
+///
+/// カラムの使われ方で決める。結合条件と行レベルの条件は usecase の結果全体に、
+/// filter・transform は一部の条件・値に、フィールドはその値だけに効く
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImpactSeverity {
+    Low,
+    Medium,
+    High,
+}
+
+impl ImpactSeverity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImpactSeverity::Low => "low",
+            ImpactSeverity::Medium => "medium",
+            ImpactSeverity::High => "high",
+        }
+    }
+}
+
+impl ImpactKind {
+    pub fn severity(&self) -> ImpactSeverity {
+        match self {
+            ImpactKind::Join | ImpactKind::Authorization => ImpactSeverity::High,
+            ImpactKind::Filter | ImpactKind::Transform => ImpactSeverity::Medium,
+            ImpactKind::Field => ImpactSeverity::Low,
+        }
+    }
+}
+
+/// 2 つの版の DBML の間のカラムの変更
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum ColumnChange {
+    Removed,
+    /// 改名（新しい名前）
+    Renamed {
+        to: String,
+    },
+    /// 型の変更
+    Retyped {
+        from: String,
+        to: String,
+    },
+}
+
+/// カラム 1 つの変更
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SchemaChange {
+    pub table: String,
+    /// 古い版でのカラム名
+    pub column: String,
+    #[serde(flatten)]
+    pub change: ColumnChange,
+}
+
+/// 古い版の DBML から新しい版への、削除・改名・型の変更があったカラム（古い版のテーブル・カラム順）
+///
+/// 改名は同じテーブルで消えたカラムと増えたカラムの型・制約が 1 対 1 で一致するときだけとみなし、
+/// 候補が複数あるときは削除とする。テーブルごと消えた場合はすべてのカラムを削除とする
+pub fn schema_diff(old: &[DbmlTable], new: &[DbmlTable]) -> Vec<SchemaChange> {
+    let mut changes = Vec::new();
+    for old_table in old {
+        let new_table = new.iter().find(|t| t.name == old_table.name);
+        let added: Vec<&DbmlColumn> = new_table
+            .iter()
+            .flat_map(|t| &t.column_details)
+            .filter(|c| old_table.column(&c.name).is_none())
+            .collect();
+        let removed: Vec<&DbmlColumn> = old_table
+            .column_details
+            .iter()
+            .filter(|c| new_table.and_then(|t| t.column(&c.name)).is_none())
+            .collect();
+
+        for column in &old_table.column_details {
+            let change = match new_table.and_then(|t| t.column(&column.name)) {
+                Some(current) if same_type(&column.data_type, &current.data_type) => continue,
+                Some(current) => ColumnChange::Retyped {
+                    from: column.data_type.clone(),
+                    to: current.data_type.clone(),
+                },
+                None => match renamed_to(column, &removed, &added) {
+                    Some(to) => ColumnChange::Renamed {
+                        to: to.name.clone(),
+                    },
+                    None => ColumnChange::Removed,
+                },
+            };
+            changes.push(SchemaChange {
+                table: old_table.name.clone(),
+                column: column.name.clone(),
+                change,
+            });
+        }
+    }
+    changes
+}
+
+/// 型の書き方の違い（大文字小文字・空白）は変更とみなさない
+fn same_type(a: &str, b: &str) -> bool {
+    let normalize = |t: &str| {
+        t.chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<String>()
+            .to_ascii_lowercase()
+    };
+    normalize(a) == normalize(b)
+}
+
+/// 消えたカラムと型・制約が 1 対 1 で一致する増えたカラム
+fn renamed_to<'a>(
+    column: &DbmlColumn,
+    removed: &[&DbmlColumn],
+    added: &[&'a DbmlColumn],
+) -> Option<&'a DbmlColumn> {
+    let alike = |a: &DbmlColumn, b: &DbmlColumn| {
+        same_type(&a.data_type, &b.data_type)
+            && a.primary_key == b.primary_key
+            && a.unique == b.unique
+            && a.nullable == b.nullable
+    };
+    let mut candidates = added.iter().filter(|c| alike(column, c));
+    let candidate = candidates.next()?;
+    if candidates.next().is_some() || removed.iter().filter(|r| alike(r, candidate)).count() > 1 {
+        return None;
+    }
+    Some(candidate)
+}
+
+/// 影響の大きさを付けた参照箇所
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RatedImpact {
+    #[serde(flatten)]
+    pub impact: Impact,
+    pub severity: ImpactSeverity,
+}
+
+/// スキーマの変更 1 つとそれで壊れる箇所
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChangeImpact {
+    #[serde(flatten)]
+    pub change: SchemaChange,
+    /// 参照箇所のうち最も大きい影響。参照が無ければ None
+    pub severity: Option<ImpactSeverity>,
+    /// 影響を受ける usecase の数
+    pub usecases: usize,
+    pub impacts: Vec<RatedImpact>,
+}
+
+/// スキーマの変更ごとに、古い版のカラムを参照している箇所を探す
+pub fn schema_impact(workspace: &Workspace, changes: Vec<SchemaChange>) -> Vec<ChangeImpact> {
+    changes
+        .into_iter()
+        .map(|change| {
+            let impacts: Vec<RatedImpact> = column_impact(workspace, &change.table, &change.column)
+                .into_iter()
+                .map(|impact| RatedImpact {
+                    severity: impact.kind.severity(),
+                    impact,
+                })
+                .collect();
+            let mut files: Vec<&str> = impacts.iter().map(|i| i.impact.file.as_str()).collect();
+            files.dedup();
+            ChangeImpact {
+                severity: impacts.iter().map(|i| i.severity).max(),
+                usecases: files.len(),
+                change,
+                impacts,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+
+    fn workspace() -> Workspace {
+        let mut workspace = Workspace::new();
+        workspace.add(
+            "users-detail.usml.yaml",
+            parser::parse(
+                r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: GetUser
+  response_mapping:
+    - field: id
+      source: users.id
+    - field: email
+      source: u.email
+      join:
+        table: users
+        alias: u
+        on: u.id = users.id
+    - field: contact
+      source: users.phone
+  transforms:
+    - target: contact
+      type: COALESCE
+      sources: [users.phone, users.email]
+  filters:
+    - param: email
+      maps_to: WHERE
+      condition: users.email = :email
+    - param: sort
+      maps_to: ORDER_BY
+      allowed_columns: [users.email, users.id]
+  authorization:
+    row_filters:
+      - users.email = :current_user_email
+"#,
+            )
+            .unwrap(),
+        );
+        workspace.add(
+            "posts.usml.yaml",
+            parser::parse(
+                r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["posts"]
+usecase:
+  name: ListPosts
+  response_mapping:
+    - field: title
+      source: posts.title
+    - field: author
+      source_usecase: GetUser
+"#,
+            )
+            .unwrap(),
+        );
+        workspace
+    }
+
+    #[test]
+    fn test_column_impact() {
+        let workspace = workspace();
+        let impacts = column_impact(&workspace, "users", "email");
+        let found: Vec<(&str, ImpactKind, &str, &str)> = impacts
+            .iter()
+            .map(|i| {
+                (
+                    i.usecase.as_str(),
+                    i.kind,
+                    i.target.as_str(),
+                    i.detail.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("GetUser", ImpactKind::Field, "email", "source"),
+                ("GetUser", ImpactKind::Field, "contact", "transform"),
+                (
+                    "GetUser",
+                    ImpactKind::Filter,
+                    "email",
+                    "condition: users.email = :email"
+                ),
+                (
+                    "GetUser",
+                    ImpactKind::Filter,
+                    "sort",
+                    "allowed_columns: users.email"
+                ),
+                (
+                    "GetUser",
+                    ImpactKind::Transform,
+                    "contact",
+                    "COALESCE sources: users.email"
+                ),
+                (
+                    "GetUser",
+                    ImpactKind::Authorization,
+                    "row_filters",
+                    "users.email = :current_user_email"
+                ),
+                ("ListPosts", ImpactKind::Field, "author.email", "source"),
+                (
+                    "ListPosts",
+                    ImpactKind::Field,
+                    "author.contact",
+                    "transform"
+                ),
+            ]
+        );
+        // source_usecase で参照した usecase を経由する
+        assert_eq!(impacts[6].via.as_deref(), Some("GetUser"));
+        assert_eq!(impacts[0].via, None);
+    }
+
+    #[test]
+    fn test_column_impact_joins_and_bare_columns() {
+        let workspace = workspace();
+        let joins = column_impact(&workspace, "users", "id");
+        assert!(joins.iter().any(|i| i.kind == ImpactKind::Join
+            && i.target == "email"
+            && i.detail == "JOIN users ON u.id = users.id"));
+
+        let mut workspace = Workspace::new();
+        workspace.add(
+            "feed.usml.yaml",
+            parser::parse(
+                r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["posts"]
+usecase:
+  name: Feed
+  response_mapping:
+    - field: id
+      source: posts.id
+  filters:
+    - param: cursor
+      maps_to: PAGINATION
+      strategy: cursor
+      cursor_field: created_at
+"#,
+            )
+            .unwrap(),
+        );
+        let impacts = column_impact(&workspace, "posts", "created_at");
+        assert_eq!(impacts.len(), 1);
+        assert_eq!(impacts[0].detail, "cursor_field: created_at");
+        assert!(column_impact(&workspace, "users", "created_at").is_empty());
+    }
+
+    fn table(name: &str, columns: &[(&str, &str)]) -> DbmlTable {
+        DbmlTable {
+            name: name.to_string(),
+            columns: columns.iter().map(|(c, _)| c.to_string()).collect(),
+            column_details: columns
+                .iter()
+                .map(|(c, t)| DbmlColumn {
+                    name: c.to_string(),
+                    data_type: t.to_string(),
+                    ..DbmlColumn::default()
+                })
+                .collect(),
+            location: None,
+        }
+    }
+
+    #[test]
+    fn test_schema_diff() {
+        let old = vec![
+            table(
+                "users",
+                &[
+                    ("id", "int"),
+                    ("email", "varchar"),
+                    ("phone", "varchar(20)"),
+                    ("age", "int"),
+                ],
+            ),
+            table("posts", &[("id", "int"), ("title", "varchar")]),
+            table("tags", &[("id", "int")]),
+        ];
+        let new = vec![
+            table(
+                "users",
+                &[
+                    ("id", "INT"),
+                    ("mail_address", "varchar"),
+                    ("phone", "varchar(32)"),
+                ],
+            ),
+            // 型の一致する増えたカラムが 2 つあるので改名とはみなさない
+            table(
+                "posts",
+                &[("id", "int"), ("headline", "varchar"), ("body", "varchar")],
+            ),
+        ];
+        let changes = schema_diff(&old, &new);
+        let found: Vec<(&str, &str, &ColumnChange)> = changes
+            .iter()
+            .map(|c| (c.table.as_str(), c.column.as_str(), &c.change))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    "users",
+                    "email",
+                    &ColumnChange::Renamed {
+                        to: "mail_address".to_string()
+                    }
+                ),
+                (
+                    "users",
+                    "phone",
+                    &ColumnChange::Retyped {
+                        from: "varchar(20)".to_string(),
+                        to: "varchar(32)".to_string()
+                    }
+                ),
+                ("users", "age", &ColumnChange::Removed),
+                ("posts", "title", &ColumnChange::Removed),
+                ("tags", "id", &ColumnChange::Removed),
+            ]
+        );
+
+        let impacts = schema_impact(&workspace(), changes);
+        assert_eq!(impacts[0].severity, Some(ImpactSeverity::High));
+        assert_eq!(impacts[0].usecases, 2);
+        assert!(
+            impacts[0].impacts.iter().any(
+                |i| i.impact.kind == ImpactKind::Filter && i.severity == ImpactSeverity::Medium
+            )
+        );
+        assert_eq!(impacts[2].severity, None);
+    }
+}
@@ -1,5 +1,45 @@
+pub mod api;
 pub mod ast;
+#[cfg(feature = "codegen")]
+pub mod codegen;
+pub mod complexity;
+pub mod config;
+pub mod definition;
+pub mod diagnostic;
+pub mod edit;
+pub mod environment;
+pub mod error;
+pub mod export;
+pub mod fingerprint;
+pub mod fix;
+pub mod generated;
+pub mod hover;
+pub mod impact;
+pub mod library;
+pub mod lineage;
+pub mod lint;
+pub mod locate;
+pub mod mask;
+pub mod model;
+pub mod naming;
+pub mod normalize;
+pub mod outline;
+pub mod output;
+pub mod ownership;
 pub mod parser;
 pub mod resolver;
+pub mod rule_docs;
+pub mod rules;
+pub mod schema;
+pub mod semantic_tokens;
+pub mod session;
+#[cfg(feature = "codegen")]
+pub mod size;
+pub mod stats;
+pub mod syntax;
+pub mod synth;
+pub mod testing;
 pub mod validator;
+#[cfg(feature = "visualizer")]
 pub mod visualizer;
+pub mod workspace;
@@ -0,0 +1,207 @@
+//! 共有ライブラリ（`library.usml.yaml`）の transform・filter を usecase に展開する
+//!
+//! usecase の transform・filter に `use: <名前>` と書くと、usecase 側に書かれていない項目を
+//! ライブラリの定義で補う。usecase 側に書いた項目はライブラリの定義より優先する
+
+use crate::ast::{Filter, FilterTemplate, Library, Transform, TransformTemplate, UsmlDocument};
+use crate::rules;
+use crate::validator::ValidationError;
+
+/// ワークスペースで共有ライブラリとして読み込むファイル名
+pub const LIBRARY_FILE: &str = "library.usml.yaml";
+
+impl Library {
+    /// 別のライブラリの定義を取り込む（同じ名前は先に取り込んだ定義を使う）
+    pub fn merge(&mut self, other: Library) {
+        for (name, template) in other.transforms {
+            self.transforms.entry(name).or_insert(template);
+        }
+        for (name, template) in other.filters {
+            self.filters.entry(name).or_insert(template);
+        }
+    }
+
+    /// `use` の参照先がライブラリにあるか（U038）
+    pub fn check(&self, doc: &UsmlDocument) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        for (index, transform) in doc.usecase.transforms.iter().enumerate() {
+            if let Some(name) = &transform.r#use
+                && !self.transforms.contains_key(name)
+            {
+                errors.push(
+                    ValidationError::new(
+                        &rules::LIBRARY_REFERENCE,
+                        format!(
+                            "transform {} の use '{}' がライブラリの transforms にありません",
+                            transform.target, name
+                        ),
+                    )
+                    .at(format!("/usecase/transforms/{}/use", index)),
+                );
+            }
+        }
+        for (index, filter) in doc.usecase.filters.iter().enumerate() {
+            if let Some(name) = &filter.r#use
+                && !self.filters.contains_key(name)
+            {
+                errors.push(
+                    ValidationError::new(
+                        &rules::LIBRARY_REFERENCE,
+                        format!(
+                            "フィルタ {} の use '{}' がライブラリの filters にありません",
+                            filter.param, name
+                        ),
+                    )
+                    .at(format!("/usecase/filters/{}/use", index)),
+                );
+            }
+        }
+        errors
+    }
+
+    /// `use` の参照先の定義で、usecase に書かれていない項目を補う
+    ///
+    /// 参照先が無い `use` はそのまま残す（`check` で報告する）
+    pub fn expand(&self, doc: &mut UsmlDocument) {
+        for transform in &mut doc.usecase.transforms {
+            if let Some(template) = transform
+                .r#use
+                .as_ref()
+                .and_then(|n| self.transforms.get(n))
+            {
+                expand_transform(transform, template);
+            }
+        }
+        for filter in &mut doc.usecase.filters {
+            if let Some(template) = filter.r#use.as_ref().and_then(|n| self.filters.get(n)) {
+                expand_filter(filter, template);
+            }
+        }
+    }
+}
+
+fn expand_transform(transform: &mut Transform, template: &TransformTemplate) {
+    if transform.r#type.is_empty() {
+        transform.r#type.clone_from(&template.r#type);
+    }
+    fill(&mut transform.source, &template.source);
+    fill(&mut transform.sources, &template.sources);
+    fill(&mut transform.fallback, &template.fallback);
+    fill(&mut transform.separator, &template.separator);
+    fill(&mut transform.when, &template.when);
+    fill(&mut transform.else_value, &template.else_value);
+    fill(&mut transform.mask_pattern, &template.mask_pattern);
+    fill(&mut transform.condition, &template.condition);
+    fill(&mut transform.then_source, &template.then_source);
+    fill(&mut transform.else_source, &template.else_source);
+    fill(&mut transform.factor, &template.factor);
+    fill(&mut transform.lookup, &template.lookup);
+    fill(&mut transform.rounding, &template.rounding);
+    fill(&mut transform.scale, &template.scale);
+    fill(&mut transform.locale, &template.locale);
+}
+
+fn expand_filter(filter: &mut Filter, template: &FilterTemplate) {
+    if filter.maps_to.is_empty() {
+        filter.maps_to.clone_from(&template.maps_to);
+    }
+    fill(&mut filter.condition, &template.condition);
+    fill(&mut filter.strategy, &template.strategy);
+    fill(&mut filter.page_size, &template.page_size);
+    fill(&mut filter.limit_param, &template.limit_param);
+    fill(&mut filter.max_page_size, &template.max_page_size);
+    fill(&mut filter.cursor_field, &template.cursor_field);
+    fill(&mut filter.cursor_tiebreaker, &template.cursor_tiebreaker);
+    fill(&mut filter.total_count, &template.total_count);
+    fill(&mut filter.default_column, &template.default_column);
+    fill(&mut filter.default_direction, &template.default_direction);
+    fill(&mut filter.allowed_columns, &template.allowed_columns);
+    fill(&mut filter.allowed_directions, &template.allowed_directions);
+    fill(&mut filter.required, &template.required);
+    fill(&mut filter.default, &template.default);
+}
+
+fn fill<T: Clone>(slot: &mut Option<T>, value: &Option<T>) {
+    if slot.is_none() {
+        slot.clone_from(value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser;
+    use crate::validator;
+
+    const LIBRARY: &str = r#"
+version: "0.1"
+library:
+  transforms:
+    mask_email:
+      type: MASK
+      mask_pattern: "^1***@***"
+  filters:
+    cursor_page:
+      maps_to: PAGINATION
+      strategy: cursor
+      cursor_field: id
+      page_size: 20
+"#;
+
+    const DOC: &str = r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: email
+      source: users.email
+    - field: id
+      source: users.id
+  filters:
+    - param: cursor
+      use: cursor_page
+      page_size: 50
+    - param: sort
+      use: standard_sort
+  transforms:
+    - target: email
+      use: mask_email
+      source: users.email
+"#;
+
+    #[test]
+    fn test_expand_fills_unset_items_from_library() {
+        let library = parser::parse_library(LIBRARY).unwrap().library;
+        let mut doc = parser::parse(DOC).unwrap();
+        library.expand(&mut doc);
+
+        let transform = &doc.usecase.transforms[0];
+        assert_eq!(transform.r#type, "MASK");
+        assert_eq!(transform.mask_pattern.as_deref(), Some("^1***@***"));
+        assert_eq!(transform.source.as_deref(), Some("users.email"));
+        let filter = &doc.usecase.filters[0];
+        assert_eq!(filter.maps_to, "PAGINATION");
+        assert_eq!(filter.page_size, Some(50));
+        assert_eq!(filter.strategy.as_deref(), Some("cursor"));
+    }
+
+    #[test]
+    fn test_unresolved_use_is_reported() {
+        let library = parser::parse_library(LIBRARY).unwrap().library;
+        let mut doc = parser::parse(DOC).unwrap();
+        let errors = library.check(&doc);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].to_string().contains("'standard_sort'"));
+
+        // use の無いフィルタは maps_to を省略できない
+        library.expand(&mut doc);
+        doc.usecase.filters[1].r#use = None;
+        let codes: Vec<_> = validator::validate(&doc)
+            .iter()
+            .map(|e| e.rule().code.as_ref())
+            .collect();
+        assert_eq!(codes, vec!["U038"]);
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::HashSet;
+
+use serde::Serialize;
+
+use crate::ast::UsmlDocument;
+use crate::model::{ColumnRef, Model};
+
+/// レスポンスフィールドが DB カラムに依存する経路の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeKind {
+    /// `source` による直接参照
+    Source,
+    /// `aggregate` を通した参照
+    Aggregate,
+    /// `transforms` の sources / then_source などによる参照
+    Transform,
+}
+
+/// レスポンスフィールド → テーブル.カラム の依存関係
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct LineageEdge {
+    /// フィールドのフルパス（例: "comments.id"）
+    pub field: String,
+    /// エイリアスを解決した実テーブル名
+    pub table: String,
+    pub column: String,
+    pub kind: EdgeKind,
+    /// source_usecase で参照した別の usecase を経由する場合の usecase 名
+    pub usecase: Option<String>,
+}
+
+/// ドキュメント全体のフィールド単位のリネージを求める
+pub fn lineage(doc: &UsmlDocument) -> Vec<LineageEdge> {
+    from_model(&Model::build(doc))
+}
+
+/// 組み立て済みの意味モデルからリネージを求める
+pub fn from_model(model: &Model) -> Vec<LineageEdge> {
+    let mut edges = Edges::default();
+    for field in &model.fields {
+        if let Some(source) = &field.source {
+            let kind = if field.aggregate.is_some() {
+                EdgeKind::Aggregate
+            } else {
+                EdgeKind::Source
+            };
+            edges.push(&field.path, source, kind);
+        }
+        for transform in &field.transforms {
+            for source in &transform.sources {
+                edges.push(&field.path, source, EdgeKind::Transform);
+            }
+        }
+    }
+    edges.edges
+}
+
+/// 出現順を保ったまま重複を除いて辺を集める
+#[derive(Default)]
+struct Edges {
+    edges: Vec<LineageEdge>,
+    seen: HashSet<LineageEdge>,
+}
+
+impl Edges {
+    fn push(&mut self, field_path: &str, source: &ColumnRef, kind: EdgeKind) {
+        let edge = LineageEdge {
+            field: field_path.to_string(),
+            table: source.table.clone(),
+            column: source.column.clone(),
+            kind,
+            usecase: None,
+        };
+        if self.seen.insert(edge.clone()) {
+            self.edges.push(edge);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+
+    #[test]
+    fn test_lineage_resolves_alias_and_nesting() {
+        let yaml = r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["posts"]
+    - ./schema.dbml#tables["comments"]
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: title
+      source: posts.title
+    - field: comments
+      type: array
+      source_table: comments
+      join:
+        table: comments
+        on: posts.id = comments.post_id
+      fields:
+        - field: author_name
+          source: comment_author.name
+          join:
+            table: users
+            alias: comment_author
+            on: comments.user_id = comment_author.id
+"#;
+        let doc = parser::parse(yaml).unwrap();
+        let edges = lineage(&doc);
+        assert_eq!(edges.len(), 2);
+        assert_eq!(edges[1].field, "comments.author_name");
+        assert_eq!(edges[1].table, "users");
+        assert_eq!(edges[1].column, "name");
+        assert_eq!(edges[1].kind, EdgeKind::Source);
+    }
+
+    #[test]
+    fn test_lineage_includes_transform_and_aggregate_sources() {
+        let yaml = r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["users"]
+    - ./schema.dbml#tables["profiles"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: display_name
+      source: profiles.display_name
+    - field: profile_count
+      source: profiles.id
+      aggregate:
+        type: COUNT
+  transforms:
+    - target: display_name
+      type: COALESCE
+      sources:
+        - profiles.display_name
+        - users.name
+"#;
+        let doc = parser::parse(yaml).unwrap();
+        let edges = lineage(&doc);
+        assert!(edges.contains(&LineageEdge {
+            field: "display_name".to_string(),
+            table: "users".to_string(),
+            column: "name".to_string(),
+            kind: EdgeKind::Transform,
+            usecase: None,
+        }));
+        assert!(
+            edges
+                .iter()
+                .any(|e| e.field == "profile_count" && e.kind == EdgeKind::Aggregate)
+        );
+    }
+}
@@ -0,0 +1,419 @@
+use std::borrow::Cow;
+
+use regex::Regex;
+
+use crate::config::{ConfigError, Forbid, RuleDefinition, Target};
+use crate::model::FieldKind;
+use crate::rules::{Category, CustomRule, Diagnostics, Rule, RuleContext};
+
+/// `usml.toml` の `[[lint.rules]]` 1 件を意味モデルに対して評価する規則
+///
+/// 対象の要素を名前と属性（文字列）の組に平坦化し、正規表現・必須属性・禁止値で判定する
+#[derive(Debug)]
+pub struct DeclarativeRule {
+    rule: Rule,
+    each: Target,
+    pattern: Option<Regex>,
+    require: Vec<String>,
+    forbid: Vec<Forbid>,
+    message: Option<String>,
+}
+
+impl DeclarativeRule {
+    pub fn new(def: &RuleDefinition) -> Result<Self, ConfigError> {
+        let invalid = |message: String| ConfigError::InvalidRule {
+            code: def.code.clone(),
+            message,
+        };
+
+        let pattern = def
+            .pattern
+            .as_deref()
+            .map(Regex::new)
+            .transpose()
+            .map_err(|e| invalid(format!("pattern が正規表現として不正です: {}", e)))?;
+        let known = attributes(def.each);
+        let attrs = def
+            .require
+            .iter()
+            .chain(def.forbid.iter().map(|f| &f.attribute));
+        for attr in attrs {
+            if !known.contains(&attr.as_str()) {
+                return Err(invalid(format!(
+                    "属性 '{}' は {} にありません（使える属性: {}）",
+                    attr,
+                    target_label(def.each),
+                    known.join(", ")
+                )));
+            }
+        }
+        if pattern.is_none() && def.require.is_empty() && def.forbid.is_empty() {
+            return Err(invalid(
+                "pattern・require・forbid のいずれかを指定してください".to_string(),
+            ));
+        }
+
+        Ok(DeclarativeRule {
+            rule: Rule {
+                code: Cow::Owned(def.code.clone()),
+                name: Cow::Owned(def.name.clone().unwrap_or_else(|| def.code.clone())),
+                category: Category::Custom,
+                severity: def.severity,
+                summary: Cow::Owned(def.summary.clone()),
+                explanation: Cow::Owned(def.explanation.clone()),
+            },
+            each: def.each,
+            pattern,
+            require: def.require.clone(),
+            forbid: def.forbid.clone(),
+            message: def.message.clone(),
+        })
+    }
+
+    fn report(&self, diagnostics: &mut Diagnostics<'_>, element: &Element, default: String) {
+        let message = match &self.message {
+            Some(message) => message.replace("{name}", &element.name),
+            None => default,
+        };
+        diagnostics.report_node(message, &element.pointer);
+    }
+}
+
+impl CustomRule for DeclarativeRule {
+    fn rule(&self) -> &Rule {
+        &self.rule
+    }
+
+    fn check(&self, cx: &RuleContext<'_>, diagnostics: &mut Diagnostics<'_>) {
+        let label = target_label(self.each);
+        for element in elements(self.each, cx) {
+            if let Some(pattern) = &self.pattern
+                && !pattern.is_match(&element.name)
+            {
+                self.report(
+                    diagnostics,
+                    &element,
+                    format!(
+                        "{} '{}' がパターン {} に一致しません",
+                        label,
+                        element.name,
+                        pattern.as_str()
+                    ),
+                );
+            }
+            for attr in &self.require {
+                if element.get(attr).is_none() {
+                    self.report(
+                        diagnostics,
+                        &element,
+                        format!(
+                            "{} '{}' に {} が指定されていません",
+                            label, element.name, attr
+                        ),
+                    );
+                }
+            }
+            for forbid in &self.forbid {
+                if let Some(value) = element.get(&forbid.attribute)
+                    && forbid.values.iter().any(|v| v.eq_ignore_ascii_case(value))
+                {
+                    self.report(
+                        diagnostics,
+                        &element,
+                        format!(
+                            "{} '{}' の {} に {} は使えません",
+                            label, element.name, forbid.attribute, value
+                        ),
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// 規則の評価対象として平坦化した要素
+struct Element {
+    name: String,
+    /// 要素を宣言した USML ドキュメントのノードの JSON Pointer
+    pointer: String,
+    attributes: Vec<(&'static str, Option<String>)>,
+}
+
+impl Element {
+    fn get(&self, attr: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| *key == attr)
+            .and_then(|(_, value)| value.as_deref())
+    }
+}
+
+fn target_label(target: Target) -> &'static str {
+    match target {
+        Target::Field => "フィールド",
+        Target::ArrayField => "配列フィールド",
+        Target::Join => "結合",
+        Target::Filter => "フィルタ",
+        Target::Transform => "transform",
+        Target::Table => "テーブル",
+    }
+}
+
+/// 要素の種類ごとに require・forbid で使える属性
+fn attributes(target: Target) -> &'static [&'static str] {
+    match target {
+        Target::Field | Target::ArrayField => &[
+            "path",
+            "type",
+            "source",
+            "source_table",
+            "aggregate",
+            "join",
+            "collection",
+            "envelope",
+            "value",
+            "param",
+            "timezone",
+        ],
+        Target::Join => &["table", "alias", "type", "on", "field"],
+        Target::Filter => &[
+            "maps_to",
+            "condition",
+            "strategy",
+            "page_size",
+            "limit_param",
+            "max_page_size",
+            "cursor_field",
+            "cursor_tiebreaker",
+            "total_count",
+            "default_column",
+            "default_direction",
+            "required",
+            "default",
+            "use",
+        ],
+        Target::Transform => &[
+            "type",
+            "source",
+            "sources",
+            "fallback",
+            "separator",
+            "else_value",
+            "mask_pattern",
+            "rounding",
+            "scale",
+            "locale",
+            "use",
+        ],
+        Target::Table => &["alias", "imported"],
+    }
+}
+
+fn elements(target: Target, cx: &RuleContext<'_>) -> Vec<Element> {
+    let usecase = &cx.document.usecase;
+    match target {
+        Target::Field | Target::ArrayField => cx
+            .model
+            .fields
+            .iter()
+            .filter(|f| target == Target::Field || f.kind == FieldKind::Array)
+            .map(|f| Element {
+                name: f.name.clone(),
+                pointer: f.pointer.clone(),
+                attributes: vec![
+                    ("path", Some(f.path.clone())),
+                    (
+                        "type",
+                        Some(match f.kind {
+                            FieldKind::Scalar => "scalar".to_string(),
+                            FieldKind::Array => "array".to_string(),
+                        }),
+                    ),
+                    (
+                        "source",
+                        f.source
+                            .as_ref()
+                            .map(|s| format!("{}.{}", s.qualifier, s.column)),
+                    ),
+                    ("source_table", f.source_table.clone()),
+                    ("aggregate", f.aggregate.clone()),
+                    ("join", cx.model.joins_of(f).next().map(|j| j.table.clone())),
+                    ("collection", Some(f.collection.to_string())),
+                    ("envelope", f.envelope.clone()),
+                    ("value", f.value.as_ref().map(|v| v.to_string())),
+                    ("param", f.param.clone()),
+                    ("timezone", f.timezone.clone()),
+                ],
+            })
+            .collect(),
+        Target::Join => cx
+            .model
+            .joins
+            .iter()
+            .map(|j| Element {
+                name: j.alias.clone().unwrap_or_else(|| j.table.clone()),
+                pointer: cx.model.join_pointer(j),
+                attributes: vec![
+                    ("table", Some(j.table.clone())),
+                    ("alias", j.alias.clone()),
+                    (
+                        "type",
+                        Some(j.kind.as_sql().trim_end_matches(" JOIN").to_string()),
+                    ),
+                    ("on", Some(j.on.clone())),
+                    ("field", Some(j.field.clone())),
+                ],
+            })
+            .collect(),
+        Target::Filter => usecase
+            .filters
+            .iter()
+            .enumerate()
+            .map(|(i, f)| Element {
+                name: f.param.clone(),
+                pointer: format!("/usecase/filters/{}", i),
+                attributes: vec![
+                    ("maps_to", Some(f.maps_to.clone())),
+                    ("condition", f.condition.clone()),
+                    ("strategy", f.strategy.clone()),
+                    ("page_size", f.page_size.map(|n| n.to_string())),
+                    ("limit_param", f.limit_param.clone()),
+                    ("max_page_size", f.max_page_size.map(|n| n.to_string())),
+                    ("cursor_field", f.cursor_field.clone()),
+                    ("cursor_tiebreaker", f.cursor_tiebreaker.clone()),
+                    ("total_count", f.total_count.clone()),
+                    ("default_column", f.default_column.clone()),
+                    ("default_direction", f.default_direction.clone()),
+                    ("required", f.required.map(|b| b.to_string())),
+                    ("default", f.default.as_ref().map(|v| v.to_string())),
+                    ("use", f.r#use.clone()),
+                ],
+            })
+            .collect(),
+        Target::Transform => usecase
+            .transforms
+            .iter()
+            .enumerate()
+            .map(|(i, t)| Element {
+                name: t.target.clone(),
+                pointer: format!("/usecase/transforms/{}", i),
+                attributes: vec![
+                    ("type", Some(t.r#type.clone())),
+                    ("source", t.source.clone()),
+                    ("sources", t.sources.as_ref().map(|s| s.join(","))),
+                    ("fallback", t.fallback.clone()),
+                    ("separator", t.separator.clone()),
+                    ("else_value", t.else_value.clone()),
+                    ("mask_pattern", t.mask_pattern.clone()),
+                    ("rounding", t.rounding.clone()),
+                    ("scale", t.scale.map(|s| s.to_string())),
+                    ("locale", t.locale.as_ref().map(|l| l.param.clone())),
+                    ("use", t.r#use.clone()),
+                ],
+            })
+            .collect(),
+        Target::Table => cx
+            .model
+            .tables
+            .iter()
+            .map(|t| Element {
+                name: t.name.clone(),
+                pointer: "/import".to_string(),
+                attributes: vec![
+                    ("alias", t.aliases.first().cloned()),
+                    ("imported", Some(t.imported.to_string())),
+                ],
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::parser;
+    use crate::validator::Validator;
+
+    const DOC: &str = r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["posts"]
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: 投稿一覧
+  response_mapping:
+    - field: postList
+      type: array
+      fields:
+        - field: id
+          source: posts.id
+        - field: author_name
+          source: users.name
+          join:
+            table: users
+            type: RIGHT
+            on: posts.user_id = users.id
+"#;
+
+    fn diagnostics(toml: &str) -> Vec<crate::diagnostic::Diagnostic> {
+        let config = Config::parse(toml, "usml.toml").unwrap();
+        let validator = Validator::from_config(&config).unwrap();
+        validator.validate(&parser::parse(DOC).unwrap())
+    }
+
+    #[test]
+    fn test_declarative_rules_report_pattern_require_and_forbid() {
+        let found = diagnostics(
+            r#"
+[[lint.rules]]
+code = "ACME001"
+summary = "フィールド名は snake_case"
+each = "field"
+pattern = "^[a-z][a-z0-9_]*$"
+
+[[lint.rules]]
+code = "ACME002"
+summary = "配列フィールドには source_table を指定する"
+each = "array_field"
+require = ["source_table"]
+
+[[lint.rules]]
+code = "ACME003"
+summary = "RIGHT JOIN を使わない"
+severity = "warning"
+each = "join"
+forbid = [{ attribute = "type", values = ["right"] }]
+message = "結合 {name} を LEFT JOIN に書き換えてください"
+"#,
+        );
+        let codes: Vec<_> = found.iter().filter_map(|d| d.code.as_deref()).collect();
+        assert_eq!(codes, vec!["ACME001", "ACME002", "ACME003"]);
+        assert!(found[0].message.contains("postList"));
+        assert_eq!(
+            found[2].message,
+            "結合 users を LEFT JOIN に書き換えてください"
+        );
+        assert_eq!(found[2].severity, crate::diagnostic::Severity::Warning);
+    }
+
+    #[test]
+    fn test_invalid_definitions_are_rejected() {
+        let config = Config::parse(
+            r#"
+[[lint.rules]]
+code = "ACME001"
+summary = "存在しない属性"
+each = "join"
+require = ["source_table"]
+"#,
+            "usml.toml",
+        )
+        .unwrap();
+        assert!(matches!(
+            Validator::from_config(&config),
+            Err(ConfigError::InvalidRule { code, .. }) if code == "ACME001"
+        ));
+    }
+}
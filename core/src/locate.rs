@@ -0,0 +1,151 @@
+use crate::diagnostic::Diagnostic;
+use crate::syntax::SyntaxTree;
+
+/// 位置を持たない規則の診断に、USML のソース上の位置を付ける
+///
+/// 規則は報告するときに対象のノードを JSON Pointer（`pointer`）で記録する。そのノードがソースに無ければ
+/// （省略した項目・ライブラリから補った項目など）ソースにある最も深い祖先を指す。
+/// JSON Pointer の無い診断（独自規則の `Diagnostics::report` など）は規則名の先頭（`filters`・`transforms` など）の
+/// セクションを指し、`pointer` もそのセクションにする。既に位置のある診断と構文エラーのソースはそのままにする
+pub fn locate(source: &str, file: &str, diagnostics: &mut [Diagnostic]) {
+    let Ok(tree) = SyntaxTree::parse(source) else {
+        return;
+    };
+    for diagnostic in diagnostics.iter_mut().filter(|d| d.location.is_none()) {
+        let pointer = diagnostic
+            .pointer
+            .get_or_insert_with(|| section(&diagnostic.rule).to_string());
+        let node = tree.nearest(pointer);
+        diagnostic.location = Some(tree.location(source, file, node.span.start));
+    }
+}
+
+/// 規則名からその規則が検査するセクションの JSON Pointer
+fn section(rule: &str) -> &'static str {
+    match rule.split('.').next().unwrap_or_default() {
+        "import" => "/import",
+        "filters" => "/usecase/filters",
+        "transforms" => "/usecase/transforms",
+        "cache" => "/usecase/cache",
+        "authorization" => "/usecase/authorization",
+        "usecase" | "ownership" | "complexity" | "library" => "/usecase",
+        _ => "/usecase/response_mapping",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostic::{self, Location};
+    use crate::parser;
+    use crate::validator;
+
+    const DOC: &str = r#"version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: ユーザー一覧
+  response_mapping:
+    - field: id
+      source: users.id
+    - field: title
+      source: posts.x
+    - field: bio
+      source: profiles.bio
+      join:
+        table: profiles
+        on: users.id = profiles.user_id
+    - field: avatar
+      source: profiles.avatar
+      join:
+        table: profiles
+        on: users.id = profiles.owner_id
+  filters:
+    - param: status
+      maps_to: WHERE
+      condition: users.status = :status
+    - param: status
+      maps_to: WHERE
+"#;
+
+    /// 検証した診断の (コード, JSON Pointer, 行, 列)
+    fn located() -> Vec<(String, String, usize, usize)> {
+        let doc = parser::parse(DOC).unwrap();
+        let mut diagnostics = diagnostic::from_errors(validator::validate(&doc));
+        locate(DOC, "a.usml.yaml", &mut diagnostics);
+        diagnostics
+            .into_iter()
+            .map(|d| {
+                let location = d.location.unwrap();
+                (
+                    d.code.unwrap(),
+                    d.pointer.unwrap(),
+                    location.line.unwrap(),
+                    location.column.unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_locate_uses_the_pointer_recorded_by_the_rule() {
+        let located = located();
+        let find = |code: &str| -> Vec<(&str, usize, usize)> {
+            located
+                .iter()
+                .filter(|(c, ..)| c == code)
+                .map(|(_, pointer, line, column)| (pointer.as_str(), *line, *column))
+                .collect()
+        };
+        assert_eq!(
+            find("U001"),
+            [
+                ("/usecase/response_mapping/1/source", 11, 15),
+                ("/usecase/response_mapping/2/source", 13, 15),
+            ]
+        );
+        assert_eq!(
+            find("U002"),
+            [
+                ("/usecase/response_mapping/2/join/on", 16, 13),
+                ("/usecase/response_mapping/3/join/on", 21, 13),
+            ]
+        );
+        // 2 つ目の結合に alias が要る
+        assert_eq!(find("U004"), [("/usecase/response_mapping/3/join", 20, 9)]);
+        assert_eq!(find("U044"), [("/usecase/filters/1/param", 26, 14)]);
+    }
+
+    #[test]
+    fn test_locate_falls_back_to_ancestor_and_section() {
+        // ソースに無いノードは最も深い祖先を指し、JSON Pointer は規則が記録したまま
+        let mut diagnostics = [Diagnostic::error("join.alias", "alias が要ります")
+            .with_pointer("/usecase/response_mapping/3/join/alias")];
+        locate(DOC, "a.usml.yaml", &mut diagnostics);
+        assert_eq!(
+            diagnostics[0].location,
+            Some(Location::at("a.usml.yaml", 20, 9))
+        );
+        assert_eq!(
+            diagnostics[0].pointer.as_deref(),
+            Some("/usecase/response_mapping/3/join/alias")
+        );
+
+        // JSON Pointer の無い診断は規則のセクション
+        let mut diagnostics = [Diagnostic::error("filters.acme", "独自規則")];
+        locate(DOC, "a.usml.yaml", &mut diagnostics);
+        assert_eq!(
+            diagnostics[0].location,
+            Some(Location::at("a.usml.yaml", 23, 7))
+        );
+        assert_eq!(diagnostics[0].pointer.as_deref(), Some("/usecase/filters"));
+
+        // 既に位置のある診断は変えない
+        let mut diagnostics = [Diagnostic::error("io", "読めません")
+            .with_location(Some(Location::file("schema.dbml")))];
+        locate(DOC, "a.usml.yaml", &mut diagnostics);
+        assert_eq!(diagnostics[0].location, Some(Location::file("schema.dbml")));
+        assert_eq!(diagnostics[0].pointer, None);
+    }
+}
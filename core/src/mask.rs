@@ -0,0 +1,164 @@
+//! MASK transform の `mask_pattern` のミニ言語
+//!
+//! - `*` : 元の値の 1 文字を `*` に置き換える
+//! - `^N` : 先頭の N 文字をそのまま残す（パターンの先頭にだけ書ける）
+//! - `$N` : 末尾の N 文字をそのまま残す（パターンの末尾にだけ書ける）
+//! - `\` : 直後の 1 文字（`*` `^` `$` `\`）を文字どおりに出力する
+//! - その他の文字 : そのまま出力する
+//!
+//! 例: `***-****-$4` は `090-1234-5678` を `***-****-5678` にする
+
+use thiserror::Error;
+
+/// パース済みのマスクパターン
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MaskPattern {
+    /// 先頭から残す文字数（`^N`）
+    pub keep_first: usize,
+    pub body: Vec<MaskToken>,
+    /// 末尾から残す文字数（`$N`）
+    pub keep_last: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaskToken {
+    /// 元の値の 1 文字を隠す
+    Mask,
+    Literal(char),
+}
+
+/// パターンの誤り。位置は 0 始まりの文字数
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum MaskError {
+    #[error("パターンが空です")]
+    Empty,
+    #[error("{position} 文字目の '{marker}' の後に残す文字数（1 以上の整数）がありません")]
+    MissingCount { marker: char, position: usize },
+    #[error("{position} 文字目の '^N' はパターンの先頭にだけ書けます")]
+    KeepFirstNotAtStart { position: usize },
+    #[error("{position} 文字目の '$N' はパターンの末尾にだけ書けます")]
+    KeepLastNotAtEnd { position: usize },
+    #[error("パターンの末尾の '\\' の後に文字がありません")]
+    TrailingEscape,
+    #[error("隠す文字 '*' がありません")]
+    NothingMasked,
+}
+
+impl MaskPattern {
+    pub fn parse(pattern: &str) -> Result<Self, MaskError> {
+        if pattern.is_empty() {
+            return Err(MaskError::Empty);
+        }
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut keep_first = 0;
+        let mut keep_last = 0;
+        let mut body = Vec::new();
+        let mut i = 0;
+        while i < chars.len() {
+            match chars[i] {
+                '*' => body.push(MaskToken::Mask),
+                '\\' => {
+                    i += 1;
+                    let c = *chars.get(i).ok_or(MaskError::TrailingEscape)?;
+                    body.push(MaskToken::Literal(c));
+                }
+                marker @ ('^' | '$') => {
+                    let position = i;
+                    let digits: String = chars[i + 1..]
+                        .iter()
+                        .take_while(|c| c.is_ascii_digit())
+                        .collect();
+                    let count = digits
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|&n| n > 0)
+                        .ok_or(MaskError::MissingCount { marker, position })?;
+                    i += digits.len();
+                    if marker == '^' {
+                        if position != 0 {
+                            return Err(MaskError::KeepFirstNotAtStart { position });
+                        }
+                        keep_first = count;
+                    } else {
+                        if i + 1 != chars.len() {
+                            return Err(MaskError::KeepLastNotAtEnd { position });
+                        }
+                        keep_last = count;
+                    }
+                }
+                c => body.push(MaskToken::Literal(c)),
+            }
+            i += 1;
+        }
+        if !body.contains(&MaskToken::Mask) {
+            return Err(MaskError::NothingMasked);
+        }
+        Ok(MaskPattern {
+            keep_first,
+            body,
+            keep_last,
+        })
+    }
+
+    /// 値にパターンを適用する
+    ///
+    /// `*` は残す文字を除いた部分の先頭から 1 文字ずつ対応し、元の値が短ければそこで打ち切る
+    pub fn apply(&self, value: &str) -> String {
+        let chars: Vec<char> = value.chars().collect();
+        let keep_first = self.keep_first.min(chars.len());
+        let keep_last = self.keep_last.min(chars.len() - keep_first);
+        let mut hidden = chars.len() - keep_first - keep_last;
+
+        let mut masked: String = chars[..keep_first].iter().collect();
+        for token in &self.body {
+            match token {
+                MaskToken::Mask if hidden == 0 => break,
+                MaskToken::Mask => {
+                    masked.push('*');
+                    hidden -= 1;
+                }
+                MaskToken::Literal(c) => masked.push(*c),
+            }
+        }
+        masked.extend(&chars[chars.len() - keep_last..]);
+        masked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_apply() {
+        let phone = MaskPattern::parse("***-****-$4").unwrap();
+        assert_eq!(phone.keep_last, 4);
+        assert_eq!(phone.apply("09012345678"), "***-****-5678");
+
+        let email = MaskPattern::parse(r"^1***@***.***").unwrap();
+        assert_eq!(email.apply("alice@example.com"), "a***@***.***");
+        assert_eq!(MaskPattern::parse(r"\$*").unwrap().apply("ab"), "$*");
+    }
+
+    #[test]
+    fn test_malformed_patterns() {
+        assert_eq!(MaskPattern::parse(""), Err(MaskError::Empty));
+        assert_eq!(
+            MaskPattern::parse("***$"),
+            Err(MaskError::MissingCount {
+                marker: '$',
+                position: 3
+            })
+        );
+        assert_eq!(
+            MaskPattern::parse("*$2*"),
+            Err(MaskError::KeepLastNotAtEnd { position: 1 })
+        );
+        assert_eq!(
+            MaskPattern::parse("*^2"),
+            Err(MaskError::KeepFirstNotAtStart { position: 1 })
+        );
+        assert_eq!(MaskPattern::parse("**\\"), Err(MaskError::TrailingEscape));
+        assert_eq!(MaskPattern::parse("$4"), Err(MaskError::NothingMasked));
+    }
+}
@@ -0,0 +1,726 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use serde::Serialize;
+
+use crate::api::{imported_tables, table_refs};
+use crate::ast::{Literal, ResponseMapping, Transform, UsmlDocument};
+use crate::edit::replace_qualifier;
+
+/// AST から導出した意味モデル
+///
+/// エイリアスの解決・join_chain の展開・transform のフィールドへの紐付け・
+/// 型の正規化を一度だけ行い、バリデータや可視化はこのモデルを参照する
+#[derive(Debug, Clone, Serialize)]
+pub struct Model {
+    /// response_mapping を深さ優先で平坦化したフィールド（親は子より前に並ぶ）
+    pub fields: Vec<FieldNode>,
+    /// 宣言順（import → 初出順）に並べたテーブル
+    pub tables: Vec<TableNode>,
+    /// join と join_chain の各段を展開した結合
+    pub joins: Vec<JoinEdge>,
+    #[serde(skip)]
+    index: ModelIndex,
+}
+
+/// 名前から `Model` の添字を引くための索引
+#[derive(Debug, Clone, Default)]
+struct ModelIndex {
+    fields: HashMap<String, usize>,
+    tables: HashMap<String, usize>,
+    /// エイリアス → テーブルの添字
+    aliases: HashMap<String, usize>,
+}
+
+/// レスポンスフィールドの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldKind {
+    Scalar,
+    Array,
+}
+
+/// 結合の種類。未指定は LEFT JOIN
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JoinKind {
+    Inner,
+    Left,
+    Right,
+}
+
+impl JoinKind {
+    /// `INNER JOIN` / `inner` / `LEFT` などの表記を正規化する
+    pub fn parse(value: Option<&str>) -> Self {
+        let normalized = value.unwrap_or_default().trim().to_ascii_uppercase();
+        match normalized
+            .strip_suffix("JOIN")
+            .unwrap_or(&normalized)
+            .trim()
+        {
+            "INNER" => JoinKind::Inner,
+            "RIGHT" => JoinKind::Right,
+            _ => JoinKind::Left,
+        }
+    }
+
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            JoinKind::Inner => "INNER JOIN",
+            JoinKind::Left => "LEFT JOIN",
+            JoinKind::Right => "RIGHT JOIN",
+        }
+    }
+}
+
+/// `テーブル.カラム` 形式の参照
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ColumnRef {
+    /// 記述されたままの修飾子（エイリアスまたはテーブル名）
+    pub qualifier: String,
+    /// エイリアスを解決した実テーブル名
+    pub table: String,
+    pub column: String,
+}
+
+/// フィールドに適用される transform
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TransformNode {
+    /// 大文字に正規化した種類（COALESCE, CASE など）
+    pub kind: String,
+    pub sources: Vec<ColumnRef>,
+}
+
+/// レスポンスフィールド 1 つ分
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldNode {
+    /// フルパス（例: "comments.author_name"）
+    pub path: String,
+    /// ドキュメント上の response_mapping の要素の JSON Pointer（例: `/usecase/response_mapping/2/fields/0`）
+    #[serde(skip)]
+    pub pointer: String,
+    pub name: String,
+    pub depth: usize,
+    /// 親フィールドの `Model::fields` 上の添字
+    pub parent: Option<usize>,
+    pub kind: FieldKind,
+    pub source: Option<ColumnRef>,
+    /// 配列要素の生成テーブル（エイリアス解決済み）
+    pub source_table: Option<String>,
+    /// 大文字に正規化した集約関数（COUNT など）
+    pub aggregate: Option<String>,
+    /// 小文字に正規化した `aggregate.strategy`（group_by / subquery）
+    pub aggregate_strategy: Option<String>,
+    /// このフィールドに付いた結合の `Model::joins` 上の添字
+    pub joins: Vec<usize>,
+    pub transforms: Vec<TransformNode>,
+    /// ページネーションの対象になる配列か
+    pub collection: bool,
+    /// 小文字に正規化した付帯フィールドの種類（total など）
+    pub envelope: Option<String>,
+    /// `nullable` の宣言
+    pub nullable: Option<bool>,
+    /// source の代わりに返す固定値
+    pub value: Option<Literal>,
+    /// 値をそのまま返すリクエストパラメータ（`from_param` / `source: ":name"`）
+    pub param: Option<String>,
+    /// 値を組み立てる別の usecase の名前
+    pub source_usecase: Option<String>,
+    /// 日時を返すときのタイムゾーン（`timezone`）
+    pub timezone: Option<String>,
+}
+
+/// テーブル 1 つ分
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TableNode {
+    pub name: String,
+    /// このテーブルに付けられたエイリアス
+    pub aliases: Vec<String>,
+    /// import.dbml で宣言されているか
+    pub imported: bool,
+    /// `import.tables` で対応付けた DB 上のテーブル名
+    pub db_name: Option<String>,
+    /// source / transforms / 結合条件で参照されるカラム（出現順）
+    pub columns: Vec<String>,
+}
+
+/// 結合 1 段分
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct JoinEdge {
+    /// 結合を宣言したフィールドのフルパス
+    pub field: String,
+    pub table: String,
+    pub alias: Option<String>,
+    pub kind: JoinKind,
+    pub on: String,
+    /// 結合条件が参照するカラム
+    pub references: Vec<ColumnRef>,
+    /// join_chain 由来なら段の番号（0 始まり）
+    pub chain_index: Option<usize>,
+}
+
+impl Model {
+    /// ドキュメントから意味モデルを組み立てる
+    pub fn build(doc: &UsmlDocument) -> Self {
+        let mut transforms: HashMap<&str, Vec<&Transform>> = HashMap::new();
+        for transform in &doc.usecase.transforms {
+            transforms
+                .entry(transform.target.as_str())
+                .or_default()
+                .push(transform);
+        }
+        let mut builder = Builder {
+            aliases: HashMap::new(),
+            transforms,
+            recorded_columns: HashSet::new(),
+            model: Model {
+                fields: Vec::new(),
+                tables: Vec::new(),
+                joins: Vec::new(),
+                index: ModelIndex::default(),
+            },
+        };
+        for name in imported_tables(doc) {
+            let index = builder.table_index(&name);
+            builder.model.tables[index].imported = true;
+        }
+        builder.collect_aliases(&doc.usecase.response_mapping);
+        builder.collect_fields(&doc.usecase.response_mapping, None);
+        for table in &mut builder.model.tables {
+            table.db_name = doc.import.tables.get(&table.name).cloned();
+        }
+        builder.model
+    }
+
+    pub fn field(&self, path: &str) -> Option<&FieldNode> {
+        self.index.fields.get(path).map(|&i| &self.fields[i])
+    }
+
+    /// フルパスのフィールドの `fields` 上の添字
+    pub fn field_index(&self, path: &str) -> Option<usize> {
+        self.index.fields.get(path).copied()
+    }
+
+    pub fn table(&self, name: &str) -> Option<&TableNode> {
+        self.index.tables.get(name).map(|&i| &self.tables[i])
+    }
+
+    /// DB 上のテーブル名（`import.tables` に無ければ usecase のテーブル名）
+    pub fn db_table<'a>(&'a self, name: &'a str) -> &'a str {
+        self.table(name)
+            .and_then(|t| t.db_name.as_deref())
+            .unwrap_or(name)
+    }
+
+    /// エイリアスまたはテーブル名を実テーブル名に解決する
+    pub fn resolve_table<'a>(&'a self, qualifier: &'a str) -> &'a str {
+        self.index
+            .aliases
+            .get(qualifier)
+            .map(|&i| self.tables[i].name.as_str())
+            .unwrap_or(qualifier)
+    }
+
+    /// 結合を宣言した `join`・`join_chain` の段の JSON Pointer
+    pub fn join_pointer(&self, join: &JoinEdge) -> String {
+        let field = self.field(&join.field).map_or("", |f| f.pointer.as_str());
+        match join.chain_index {
+            Some(index) => format!("{}/join_chain/{}", field, index),
+            None => format!("{}/join", field),
+        }
+    }
+
+    /// フィールドに付いた結合を宣言順に返す
+    pub fn joins_of<'a>(&'a self, field: &'a FieldNode) -> impl Iterator<Item = &'a JoinEdge> {
+        field.joins.iter().map(|&index| &self.joins[index])
+    }
+
+    /// ルートテーブル。結合で加わらず、フィールドから参照される最初のテーブル（無ければ最初のテーブル）
+    pub fn root_table(&self) -> Option<&str> {
+        let joined: BTreeSet<&str> = self.joins.iter().map(|j| j.table.as_str()).collect();
+        let referenced = |table: &str| {
+            self.fields.iter().any(|f| {
+                f.source.as_ref().is_some_and(|s| s.table == table)
+                    || f.source_table.as_deref() == Some(table)
+            })
+        };
+        self.tables
+            .iter()
+            .map(|t| t.name.as_str())
+            .find(|t| !joined.contains(t) && referenced(t))
+            .or(self.tables.first().map(|t| t.name.as_str()))
+    }
+
+    /// ルートテーブルの主キー
+    ///
+    /// DBML を読まずに分かる範囲で、ドキュメントが参照しているルートテーブルの `id` カラムとする
+    pub fn root_key(&self) -> Option<ColumnRef> {
+        let table = self.table(self.root_table()?)?;
+        table.columns.iter().any(|c| c == "id").then(|| ColumnRef {
+            qualifier: table.name.clone(),
+            table: table.name.clone(),
+            column: "id".to_string(),
+        })
+    }
+
+    /// group_by の無い集約フィールドをまとめるキー
+    ///
+    /// 親の行に対応付けて取る配列の要素なら親の側の列、それ以外はルートテーブルの主キー。
+    /// 生成するクエリはこのキーと、同じ階層でカラムを返すフィールドの列で GROUP BY する
+    pub fn group_key(&self, index: usize) -> Option<ColumnRef> {
+        self.fields[index]
+            .parent
+            .and_then(|parent| self.parent_key(parent))
+            .cloned()
+            .or_else(|| self.root_key())
+    }
+
+    /// 親の階層の行に対応付けて取る配列なら、結合条件のうち親の側の列
+    ///
+    /// 親の階層が DB から列を取り、配列の結合条件が親の側の列を参照する場合だけ。
+    /// collection の配列と、結合の無い配列は親の行に対応付けない
+    pub fn parent_key(&self, index: usize) -> Option<&ColumnRef> {
+        let field = &self.fields[index];
+        let parent_has_columns = self
+            .fields
+            .iter()
+            .any(|f| f.parent == field.parent && f.kind == FieldKind::Scalar && f.source.is_some());
+        if field.kind != FieldKind::Array || field.collection || !parent_has_columns {
+            return None;
+        }
+        let join = self.joins_of(field).next()?;
+        let own = join.alias.as_deref().unwrap_or(&join.table);
+        join.references.iter().find(|r| r.qualifier != own)
+    }
+
+    /// 集約フィールドを GROUP BY ではなく相関サブクエリで取るか
+    ///
+    /// `aggregate.strategy` が無い場合は、同じ階層の他の集約の結合で行が増えて GROUP BY では数が合わないときにサブクエリにする。
+    /// 結合の無い集約はサブクエリにできない
+    pub fn aggregate_subquery(&self, index: usize) -> bool {
+        let field = &self.fields[index];
+        if field.aggregate.is_none() || field.joins.is_empty() {
+            return false;
+        }
+        match field.aggregate_strategy.as_deref() {
+            Some("subquery") => true,
+            Some("group_by") => false,
+            _ => self.aggregate_multiplied(index, self.sibling_aggregates(index)),
+        }
+    }
+
+    /// GROUP BY で取る集約フィールドが、同じ階層で GROUP BY に残る他の集約の結合で行が増えて正しく集約できないか
+    pub fn group_by_multiplied(&self, index: usize) -> bool {
+        if self.fields[index].aggregate.is_none() || self.aggregate_subquery(index) {
+            return false;
+        }
+        let flat = self
+            .sibling_aggregates(index)
+            .filter(|&i| !self.aggregate_subquery(i));
+        self.aggregate_multiplied(index, flat)
+    }
+
+    /// フィールドの結合と、その結合条件が参照する同じ階層の結合（`Model::joins` の添字）。相関サブクエリの FROM になる
+    pub fn correlated_joins(&self, index: usize) -> BTreeSet<usize> {
+        let parent = self.fields[index].parent;
+        let level: Vec<usize> = self
+            .fields
+            .iter()
+            .filter(|f| f.parent == parent)
+            .flat_map(|f| f.joins.iter().copied())
+            .collect();
+        let mut joins: BTreeSet<usize> = self.fields[index].joins.iter().copied().collect();
+        loop {
+            let referenced: Vec<usize> = level
+                .iter()
+                .copied()
+                .filter(|i| !joins.contains(i))
+                .filter(|&i| {
+                    let join = &self.joins[i];
+                    let name = join.alias.as_deref().unwrap_or(&join.table);
+                    joins
+                        .iter()
+                        .flat_map(|&j| &self.joins[j].references)
+                        .any(|r| r.qualifier == name)
+                })
+                .collect();
+            if referenced.is_empty() {
+                return joins;
+            }
+            joins.extend(referenced);
+        }
+    }
+
+    /// 同じ階層で DB から取る他の集約フィールド
+    fn sibling_aggregates(&self, index: usize) -> impl Iterator<Item = usize> + '_ {
+        let parent = self.fields[index].parent;
+        self.fields
+            .iter()
+            .enumerate()
+            .filter(move |(i, f)| {
+                *i != index
+                    && f.parent == parent
+                    && f.kind == FieldKind::Scalar
+                    && f.source.is_some()
+                    && f.aggregate.is_some()
+            })
+            .map(|(i, _)| i)
+    }
+
+    /// `others` の集約の結合が、`index` の集約の行を増やすか。MIN・MAX は行が重複しても値が変わらない
+    fn aggregate_multiplied(&self, index: usize, mut others: impl Iterator<Item = usize>) -> bool {
+        if matches!(self.fields[index].aggregate.as_deref(), Some("MIN" | "MAX")) {
+            return false;
+        }
+        let own = self.correlated_joins(index);
+        others.any(|i| !self.correlated_joins(i).is_subset(&own))
+    }
+}
+
+struct Builder<'a> {
+    aliases: HashMap<String, String>,
+    /// target ごとにまとめた transforms
+    transforms: HashMap<&'a str, Vec<&'a Transform>>,
+    /// (テーブルの添字, カラム名) の記録済み集合
+    recorded_columns: HashSet<(usize, String)>,
+    model: Model,
+}
+
+impl Builder<'_> {
+    fn collect_aliases(&mut self, mappings: &[ResponseMapping]) {
+        for mapping in mappings {
+            if let Some(join) = &mapping.join
+                && let Some(alias) = &join.alias
+            {
+                self.aliases.insert(alias.clone(), join.table.clone());
+                let index = self.table_index(&join.table);
+                let table = &mut self.model.tables[index];
+                if !table.aliases.contains(alias) {
+                    table.aliases.push(alias.clone());
+                }
+                self.model
+                    .index
+                    .aliases
+                    .entry(alias.clone())
+                    .or_insert(index);
+            }
+            if let Some(fields) = &mapping.fields {
+                self.collect_aliases(fields);
+            }
+        }
+    }
+
+    fn collect_fields(&mut self, mappings: &[ResponseMapping], parent: Option<usize>) {
+        for (position, mapping) in mappings.iter().enumerate() {
+            let pointer = match parent {
+                Some(index) => format!("{}/fields/{}", self.model.fields[index].pointer, position),
+                None => format!("/usecase/response_mapping/{}", position),
+            };
+            let (path, depth) = match parent {
+                Some(index) => {
+                    let parent = &self.model.fields[index];
+                    (
+                        format!("{}.{}", parent.path, mapping.field),
+                        parent.depth + 1,
+                    )
+                }
+                None => (mapping.field.clone(), 0),
+            };
+
+            let mut joins = Vec::new();
+            if let Some(join) = &mapping.join {
+                joins.push(self.push_join(
+                    &path,
+                    &join.table,
+                    join.alias.clone(),
+                    JoinKind::parse(join.r#type.as_deref()),
+                    &join.on,
+                    None,
+                ));
+            }
+            for (i, entry) in mapping.join_chain.iter().flatten().enumerate() {
+                joins.push(self.push_join(
+                    &path,
+                    &entry.table,
+                    None,
+                    JoinKind::Left,
+                    &entry.on,
+                    Some(i),
+                ));
+            }
+
+            let source = mapping
+                .source
+                .as_deref()
+                .filter(|s| !s.starts_with(':'))
+                .and_then(|s| self.column_ref(s));
+            let source_table = mapping.source_table.as_deref().map(|t| self.resolve(t));
+            let targeted = self
+                .transforms
+                .get(path.as_str())
+                .cloned()
+                .unwrap_or_default();
+            let transforms = targeted
+                .into_iter()
+                .map(|t| TransformNode {
+                    kind: t.r#type.to_ascii_uppercase(),
+                    sources: transform_sources(t)
+                        .into_iter()
+                        .filter_map(|s| self.column_ref(s))
+                        .collect(),
+                })
+                .collect();
+
+            let index = self.model.fields.len();
+            self.model.index.fields.insert(path.clone(), index);
+            self.model.fields.push(FieldNode {
+                path,
+                pointer,
+                name: mapping.field.clone(),
+                depth,
+                parent,
+                kind: if mapping.r#type.as_deref() == Some("array") {
+                    FieldKind::Array
+                } else {
+                    FieldKind::Scalar
+                },
+                source,
+                source_table,
+                aggregate: mapping
+                    .aggregate
+                    .as_ref()
+                    .map(|a| a.r#type.to_ascii_uppercase()),
+                aggregate_strategy: mapping
+                    .aggregate
+                    .as_ref()
+                    .and_then(|a| a.strategy.as_ref())
+                    .map(|s| s.to_ascii_lowercase()),
+                joins,
+                transforms,
+                collection: mapping.collection,
+                envelope: mapping.envelope.as_ref().map(|e| e.to_ascii_lowercase()),
+                nullable: mapping.nullable,
+                value: mapping.value.clone(),
+                param: mapping.param().map(str::to_string),
+                source_usecase: mapping.source_usecase.clone(),
+                timezone: mapping.timezone.clone(),
+            });
+
+            if let Some(fields) = &mapping.fields {
+                self.collect_fields(fields, Some(index));
+            }
+        }
+    }
+
+    fn push_join(
+        &mut self,
+        field: &str,
+        table: &str,
+        alias: Option<String>,
+        kind: JoinKind,
+        on: &str,
+        chain_index: Option<usize>,
+    ) -> usize {
+        self.table_index(table);
+        // alias を付けた結合の on にエイリアス名が無ければ、テーブル名はこの結合のテーブルを指す
+        let on = match &alias {
+            Some(alias) if !table_refs(on).iter().any(|(q, _)| q == alias) => {
+                replace_qualifier(on, table, alias)
+            }
+            _ => on.to_string(),
+        };
+        let references = table_refs(&on)
+            .into_iter()
+            .map(|(qualifier, column)| self.record_column(qualifier, column))
+            .collect();
+        self.model.joins.push(JoinEdge {
+            field: field.to_string(),
+            table: table.to_string(),
+            alias,
+            kind,
+            on,
+            references,
+            chain_index,
+        });
+        self.model.joins.len() - 1
+    }
+
+    fn column_ref(&mut self, value: &str) -> Option<ColumnRef> {
+        let (qualifier, column) = value.split_once('.')?;
+        Some(self.record_column(qualifier.to_string(), column.to_string()))
+    }
+
+    /// 参照を解決し、参照先テーブルのカラム一覧にも記録する
+    fn record_column(&mut self, qualifier: String, column: String) -> ColumnRef {
+        let table = self.resolve(&qualifier);
+        let index = self.table_index(&table);
+        if self.recorded_columns.insert((index, column.clone())) {
+            self.model.tables[index].columns.push(column.clone());
+        }
+        ColumnRef {
+            qualifier,
+            table,
+            column,
+        }
+    }
+
+    fn resolve(&self, qualifier: &str) -> String {
+        self.aliases
+            .get(qualifier)
+            .cloned()
+            .unwrap_or_else(|| qualifier.to_string())
+    }
+
+    fn table_index(&mut self, name: &str) -> usize {
+        if let Some(&index) = self.model.index.tables.get(name) {
+            return index;
+        }
+        let index = self.model.tables.len();
+        self.model.tables.push(TableNode {
+            name: name.to_string(),
+            aliases: Vec::new(),
+            imported: false,
+            db_name: None,
+            columns: Vec::new(),
+        });
+        self.model.index.tables.insert(name.to_string(), index);
+        index
+    }
+}
+
+/// transform が参照する `テーブル.カラム` 形式のソースを列挙する
+pub(crate) fn transform_sources(transform: &Transform) -> Vec<&str> {
+    let mut sources: Vec<&str> = Vec::new();
+    sources.extend(transform.source.as_deref());
+    sources.extend(transform.sources.iter().flatten().map(String::as_str));
+    sources.extend(transform.then_source.as_deref());
+    sources.extend(transform.else_source.as_deref());
+    sources.extend(transform.lookup.as_ref().map(|l| l.rate.as_str()));
+    sources.extend(transform.locale.as_ref().map(|l| l.column.as_str()));
+    sources
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+
+    #[test]
+    fn test_model_resolves_aliases_and_expands_chains() {
+        let yaml = r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["posts"]
+    - ./schema.dbml#tables["users"]
+    - ./schema.dbml#tables["post_tags"]
+    - ./schema.dbml#tables["tags"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: author_name
+      source: author.name
+      join:
+        table: users
+        alias: author
+        type: inner
+        on: posts.user_id = author.id
+    - field: tags
+      type: array
+      source_table: tags
+      join_chain:
+        - table: post_tags
+          on: posts.id = post_tags.post_id
+        - table: tags
+          on: post_tags.tag_id = tags.id
+      fields:
+        - field: name
+          source: tags.name
+"#;
+        let model = Model::build(&parser::parse(yaml).unwrap());
+
+        let author = model.field("author_name").unwrap();
+        assert_eq!(author.source.as_ref().unwrap().table, "users");
+        let join = model.joins_of(author).next().unwrap();
+        assert_eq!(join.kind, JoinKind::Inner);
+        assert_eq!(join.references[1].table, "users");
+
+        let tags = model.field("tags").unwrap();
+        assert_eq!(tags.kind, FieldKind::Array);
+        assert_eq!(model.joins_of(tags).count(), 2);
+        assert_eq!(model.field("tags.name").unwrap().parent, Some(1));
+
+        assert_eq!(model.table("users").unwrap().aliases, vec!["author"]);
+        assert_eq!(model.resolve_table("author"), "users");
+        assert!(model.table("author").is_none());
+        assert_eq!(model.table("users").unwrap().columns, vec!["id", "name"]);
+    }
+
+    #[test]
+    fn test_model_attaches_transforms_by_full_path() {
+        let yaml = r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["comments"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: comments
+      type: array
+      fields:
+        - field: body
+          source: comments.body
+  transforms:
+    - target: comments.body
+      type: mask
+      source: comments.body
+"#;
+        let model = Model::build(&parser::parse(yaml).unwrap());
+        let body = model.field("comments.body").unwrap();
+        assert_eq!(body.transforms.len(), 1);
+        assert_eq!(body.transforms[0].kind, "MASK");
+        assert!(model.field("comments").unwrap().transforms.is_empty());
+    }
+
+    #[test]
+    fn test_model_qualifies_aliased_join_conditions() {
+        let yaml = r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["posts"]
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: author_name
+      source: users.name
+      join:
+        table: users
+        on: posts.user_id = users.id
+    - field: editor_name
+      source: editor.name
+      join:
+        table: users
+        alias: editor
+        on: posts.editor_id = users.id
+    - field: reviewer_name
+      source: reviewer.name
+      join:
+        table: users
+        alias: reviewer
+        on: posts.reviewer_id = reviewer.id
+"#;
+        let model = Model::build(&parser::parse(yaml).unwrap());
+        let on: Vec<&str> = model.joins.iter().map(|j| j.on.as_str()).collect();
+        assert_eq!(
+            on,
+            [
+                "posts.user_id = users.id",
+                "posts.editor_id = editor.id",
+                "posts.reviewer_id = reviewer.id"
+            ]
+        );
+        assert_eq!(model.joins[1].references[1].qualifier, "editor");
+    }
+}
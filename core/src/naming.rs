@@ -0,0 +1,183 @@
+use std::fmt;
+
+use serde::Deserialize;
+
+use crate::rules::{self, CustomRule, Diagnostics, Rule, RuleContext};
+
+/// 名前の表記規則
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Case {
+    #[serde(rename = "snake_case")]
+    Snake,
+    #[serde(rename = "camelCase")]
+    Camel,
+    #[serde(rename = "PascalCase")]
+    Pascal,
+    #[serde(rename = "kebab-case")]
+    Kebab,
+}
+
+impl Case {
+    /// 名前をこの表記に変換する
+    pub fn convert(&self, name: &str) -> String {
+        let words = words(name);
+        match self {
+            Case::Snake => words.join("_"),
+            Case::Kebab => words.join("-"),
+            Case::Camel => words
+                .iter()
+                .enumerate()
+                .map(|(i, w)| if i == 0 { w.clone() } else { capitalize(w) })
+                .collect(),
+            Case::Pascal => words.iter().map(|w| capitalize(w)).collect(),
+        }
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        self.convert(name) == name
+    }
+}
+
+impl fmt::Display for Case {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Case::Snake => "snake_case",
+            Case::Camel => "camelCase",
+            Case::Pascal => "PascalCase",
+            Case::Kebab => "kebab-case",
+        })
+    }
+}
+
+/// 区切り文字と大文字の境界で単語に分け、小文字にそろえる
+///
+/// 連続した大文字は 1 語とみなす（`userID` → `user`, `id`、`HTTPStatus` → `http`, `status`）
+fn words(name: &str) -> Vec<String> {
+    let chars: Vec<char> = name.chars().collect();
+    let mut words = Vec::new();
+    let mut current = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if c == '_' || c == '-' {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            continue;
+        }
+        if c.is_uppercase() && !current.is_empty() {
+            let prev = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+            if !prev.is_uppercase() || next_is_lower {
+                words.push(std::mem::take(&mut current));
+            }
+        }
+        current.extend(c.to_lowercase());
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// 表記の違い（大文字・小文字・区切り文字）を無視した比較用のキー
+pub fn normalized(name: &str) -> String {
+    words(name).concat()
+}
+
+/// 命名規則の対象
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NamingTarget {
+    /// レスポンスフィールド（入れ子含む）
+    Field,
+    /// filters[].param
+    Param,
+}
+
+/// `[lint.naming]` で指定した表記にフィールド名・パラメータ名がそろっているかを調べる
+#[derive(Debug)]
+pub struct NamingRule {
+    target: NamingTarget,
+    case: Case,
+}
+
+impl NamingRule {
+    pub fn new(target: NamingTarget, case: Case) -> Self {
+        NamingRule { target, case }
+    }
+}
+
+impl CustomRule for NamingRule {
+    fn rule(&self) -> &Rule {
+        match self.target {
+            NamingTarget::Field => &rules::FIELD_NAMING,
+            NamingTarget::Param => &rules::PARAM_NAMING,
+        }
+    }
+
+    fn check(&self, cx: &RuleContext<'_>, diagnostics: &mut Diagnostics<'_>) {
+        let (label, names): (&str, Vec<(&str, String)>) = match self.target {
+            NamingTarget::Field => (
+                "フィールド",
+                cx.model
+                    .fields
+                    .iter()
+                    .map(|f| (f.name.as_str(), format!("{}/field", f.pointer)))
+                    .collect(),
+            ),
+            NamingTarget::Param => (
+                "パラメータ",
+                cx.document
+                    .usecase
+                    .filters
+                    .iter()
+                    .enumerate()
+                    .map(|(i, f)| (f.param.as_str(), format!("/usecase/filters/{}/param", i)))
+                    .collect(),
+            ),
+        };
+        for (name, pointer) in names {
+            if !self.case.matches(name) {
+                diagnostics.report_node(
+                    format!(
+                        "{} '{}' が {} ではありません（候補: '{}'）",
+                        label,
+                        name,
+                        self.case,
+                        self.case.convert(name)
+                    ),
+                    pointer,
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_case_conversion_and_matching() {
+        assert_eq!(Case::Snake.convert("userID"), "user_id");
+        assert_eq!(Case::Camel.convert("avatar_url"), "avatarUrl");
+        assert_eq!(Case::Pascal.convert("http-status"), "HttpStatus");
+        assert_eq!(Case::Kebab.convert("HTTPStatus"), "http-status");
+        assert!(Case::Snake.matches("created_at2"));
+        assert!(!Case::Snake.matches("createdAt"));
+        assert!(Case::Camel.matches("createdAt"));
+        assert!(!Case::Camel.matches("created_at"));
+    }
+
+    #[test]
+    fn test_normalized_ignores_casing() {
+        assert_eq!(normalized("avatarUrl"), normalized("avatar_url"));
+        assert_ne!(normalized("avatar"), normalized("avatar_url"));
+    }
+}
@@ -0,0 +1,536 @@
+//! 書式の正規化（`usml fmt`）とフィールドの並べ替え（`usml sort`）
+//!
+//! `[fmt]` で指定したものだけを、コメントとレイアウトを保ったまま適用する。
+//! 要素を並べ替えるときは、要素の直前のコメント行も一緒に動かす
+
+use serde::Deserialize;
+
+use crate::config::FmtConfig;
+use crate::edit::{self, EditError, TextEdit};
+use crate::resolver::OpenapiResponse;
+use crate::syntax::{ScalarStyle, Span, SyntaxKind, SyntaxNode, SyntaxTree};
+
+/// キーワードの大文字・小文字
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeywordCase {
+    Upper,
+    Lower,
+}
+
+impl KeywordCase {
+    fn apply(&self, keyword: &str) -> String {
+        match self {
+            KeywordCase::Upper => keyword.to_ascii_uppercase(),
+            KeywordCase::Lower => keyword.to_ascii_lowercase(),
+        }
+    }
+}
+
+/// `config` で指定した正規化を `source` に適用する
+///
+/// `order_fields` は `openapi`（解決済みのレスポンス）が無ければ何もしない
+pub fn normalize(
+    source: &str,
+    config: &FmtConfig,
+    openapi: Option<&OpenapiResponse>,
+) -> Result<String, EditError> {
+    let mut source = source.to_string();
+    SyntaxTree::parse(&source)?;
+    if config.sort_imports {
+        sort_imports(&mut source)?;
+    }
+    if config.order_fields
+        && let Some(openapi) = openapi
+    {
+        order_fields(&mut source, FieldOrder::Openapi(&openapi.field_order))?;
+    }
+    if let Some(case) = config.join_type {
+        normalize_join_types(&mut source, case)?;
+    }
+    if config.collapse_defaults {
+        collapse_defaults(&mut source)?;
+    }
+    Ok(source)
+}
+
+/// import.dbml を参照の文字列順に並べる
+fn sort_imports(source: &mut String) -> Result<(), EditError> {
+    let tree = SyntaxTree::parse(source)?;
+    let Some(dbml) = tree.lookup(&["import", "dbml"]) else {
+        return Ok(());
+    };
+    let keys: Vec<&str> = dbml
+        .items()
+        .iter()
+        .map(|item| item.as_str().unwrap_or_default())
+        .collect();
+    if let Some(edit) = sort_items(source, &tree, dbml, &keys) {
+        apply(source, vec![edit]);
+    }
+    Ok(())
+}
+
+/// response_mapping の並べ方
+#[derive(Debug, Clone, Copy)]
+pub enum FieldOrder<'a> {
+    /// OpenAPI のプロパティが書かれた順（[`OpenapiResponse::field_order`]）。OpenAPI に無いフィールドは後ろに元の順で残す
+    Openapi(&'a [String]),
+    /// フィールド名の文字列順
+    Alphabetical,
+}
+
+/// response_mapping と配列フィールドの fields を `order` の順に並べる（`usml sort`）
+pub fn sort_fields(source: &str, order: FieldOrder<'_>) -> Result<String, EditError> {
+    let mut source = source.to_string();
+    order_fields(&mut source, order)?;
+    Ok(source)
+}
+
+fn order_fields(source: &mut String, order: FieldOrder<'_>) -> Result<(), EditError> {
+    // 並べ替えてもパスは変わらないため、外側から 1 階層ずつ読み直して並べ替える
+    let tree = SyntaxTree::parse(source)?;
+    let mut parents = vec![None];
+    if let Some(mappings) = tree.lookup(&["usecase", "response_mapping"]) {
+        nested_fields(mappings, "", &mut parents);
+    }
+    for parent in parents {
+        let tree = SyntaxTree::parse(source)?;
+        let mappings = match &parent {
+            Some(path) => edit::find_mapping(&tree, path).and_then(|m| m.get("fields")),
+            None => tree.lookup(&["usecase", "response_mapping"]),
+        };
+        let Some(mappings) = mappings else {
+            continue;
+        };
+        let keys: Vec<(usize, &str)> = mappings
+            .items()
+            .iter()
+            .map(|item| {
+                let Some(field) = item.get("field").and_then(SyntaxNode::as_str) else {
+                    return (usize::MAX, "");
+                };
+                match order {
+                    FieldOrder::Openapi(order) => {
+                        let path = match &parent {
+                            Some(parent) => format!("{}.{}", parent, field),
+                            None => field.to_string(),
+                        };
+                        let index = order.iter().position(|p| *p == path);
+                        (index.unwrap_or(usize::MAX), "")
+                    }
+                    FieldOrder::Alphabetical => (0, field),
+                }
+            })
+            .collect();
+        if let Some(edit) = sort_items(source, &tree, mappings, &keys) {
+            apply(source, vec![edit]);
+        }
+    }
+    Ok(())
+}
+
+/// `fields` を持つフィールドのパス
+fn nested_fields(mappings: &SyntaxNode, prefix: &str, paths: &mut Vec<Option<String>>) {
+    for item in mappings.items() {
+        let (Some(field), Some(fields)) = (
+            item.get("field").and_then(SyntaxNode::as_str),
+            item.get("fields"),
+        ) else {
+            continue;
+        };
+        let path = if prefix.is_empty() {
+            field.to_string()
+        } else {
+            format!("{}.{}", prefix, field)
+        };
+        paths.push(Some(path.clone()));
+        nested_fields(fields, &path, paths);
+    }
+}
+
+/// join.type を `INNER`・`LEFT`・`RIGHT` にそろえる（`left join` も `LEFT` にする）。それ以外の値は変えない
+fn normalize_join_types(source: &mut String, case: KeywordCase) -> Result<(), EditError> {
+    let tree = SyntaxTree::parse(source)?;
+    let mut edits = Vec::new();
+    for join in joins(&tree) {
+        let Some(node) = join.get("type") else {
+            continue;
+        };
+        let Some(keyword) = node.as_str().and_then(join_keyword) else {
+            continue;
+        };
+        let text = match &node.kind {
+            SyntaxKind::Scalar {
+                style: ScalarStyle::SingleQuoted,
+                ..
+            } => format!("'{}'", case.apply(keyword)),
+            SyntaxKind::Scalar {
+                style: ScalarStyle::DoubleQuoted,
+                ..
+            } => format!("\"{}\"", case.apply(keyword)),
+            _ => case.apply(keyword),
+        };
+        if source[node.span.start..node.span.end] != text {
+            edits.push(TextEdit {
+                span: node.span,
+                text,
+            });
+        }
+    }
+    apply(source, edits);
+    Ok(())
+}
+
+/// `left`・`LEFT JOIN` などの結合の種類。種類として読めなければ None
+pub(crate) fn join_keyword(value: &str) -> Option<&'static str> {
+    let normalized = value.trim().to_ascii_uppercase();
+    match normalized
+        .strip_suffix("JOIN")
+        .unwrap_or(&normalized)
+        .trim()
+    {
+        "INNER" => Some("INNER"),
+        "LEFT" => Some("LEFT"),
+        "RIGHT" => Some("RIGHT"),
+        _ => None,
+    }
+}
+
+/// 既定値と同じ値のエントリを消す
+fn collapse_defaults(source: &mut String) -> Result<(), EditError> {
+    let tree = SyntaxTree::parse(source)?;
+    let mut edits = Vec::new();
+    if let Some(usecase) = tree.root.get("usecase") {
+        for key in ["tags", "reviewers", "filters", "transforms"] {
+            if let Some(entry) = usecase.entry(key)
+                && matches!(&entry.value.kind, SyntaxKind::Sequence(items) if items.is_empty())
+            {
+                edits.extend(remove_entry(source, &tree, usecase, key));
+            }
+        }
+        if usecase.get("distinct").is_some_and(is_false) {
+            edits.extend(remove_entry(source, &tree, usecase, "distinct"));
+        }
+    }
+    for mapping in mappings(&tree) {
+        if mapping.get("collection").is_some_and(is_false) {
+            edits.extend(remove_entry(source, &tree, mapping, "collection"));
+        }
+    }
+    for join in joins(&tree) {
+        // 未指定の結合は LEFT JOIN
+        if join
+            .get("type")
+            .and_then(SyntaxNode::as_str)
+            .and_then(join_keyword)
+            == Some("LEFT")
+        {
+            edits.extend(remove_entry(source, &tree, join, "type"));
+        }
+    }
+    apply(source, edits);
+    Ok(())
+}
+
+fn is_false(node: &SyntaxNode) -> bool {
+    matches!(
+        &node.kind,
+        SyntaxKind::Scalar { value, style: ScalarStyle::Plain } if value == "false"
+    )
+}
+
+/// response_mapping の要素（入れ子含む）
+fn mappings(tree: &SyntaxTree) -> Vec<&SyntaxNode> {
+    fn walk<'a>(mappings: &'a SyntaxNode, found: &mut Vec<&'a SyntaxNode>) {
+        for item in mappings.items() {
+            found.push(item);
+            if let Some(fields) = item.get("fields") {
+                walk(fields, found);
+            }
+        }
+    }
+    let mut found = Vec::new();
+    if let Some(response_mapping) = tree.lookup(&["usecase", "response_mapping"]) {
+        walk(response_mapping, &mut found);
+    }
+    found
+}
+
+/// response_mapping の要素の join
+fn joins(tree: &SyntaxTree) -> Vec<&SyntaxNode> {
+    mappings(tree)
+        .into_iter()
+        .filter_map(|mapping| mapping.get("join"))
+        .filter(|join| matches!(join.kind, SyntaxKind::Mapping(_)))
+        .collect()
+}
+
+/// `mapping` の `key` の行を消す編集
+///
+/// ほかのエントリが無い、`- ` やフロースタイルと同じ行にある、値が行をまたぐ場合は消さない
+fn remove_entry(
+    source: &str,
+    tree: &SyntaxTree,
+    mapping: &SyntaxNode,
+    key: &str,
+) -> Option<TextEdit> {
+    let entry = mapping.entry(key)?;
+    if mapping.entries().len() < 2 {
+        return None;
+    }
+    let start = tree.line_start(entry.key_span.start);
+    if !source[start..entry.key_span.start].trim().is_empty() {
+        return None;
+    }
+    // 空のフローシーケンス（`[]`）の範囲は `]` の手前で終わる
+    let value_end = match &entry.value.kind {
+        SyntaxKind::Sequence(items) if items.is_empty() => source[entry.value.span.end..]
+            .find(']')
+            .map_or(entry.value.span.end, |i| entry.value.span.end + i + 1),
+        _ => entry.value.span.end,
+    };
+    let end = line_end(source, value_end);
+    let rest = source[value_end..end].trim();
+    if !(rest.is_empty() || rest.starts_with('#')) || source[start..end].trim_end().contains('\n') {
+        return None;
+    }
+    Some(TextEdit {
+        span: Span { start, end },
+        text: String::new(),
+    })
+}
+
+/// `offset` を含む行の次の行頭（最終行なら末尾）
+fn line_end(source: &str, offset: usize) -> usize {
+    source[offset..]
+        .find('\n')
+        .map_or(source.len(), |i| offset + i + 1)
+}
+
+/// ブロックスタイルのシーケンスの要素を `keys` の順に並べ替える編集（同じキーは元の順）。並んでいれば None
+///
+/// 要素はその行頭から次の要素まで（直前のコメント行は次の要素に含める）。
+/// `- ` の後ろ以外に要素がある（フロースタイルなど）場合は並べ替えない
+fn sort_items<K: Ord>(
+    source: &str,
+    tree: &SyntaxTree,
+    sequence: &SyntaxNode,
+    keys: &[K],
+) -> Option<TextEdit> {
+    let items = sequence.items();
+    let mut indices: Vec<usize> = (0..items.len()).collect();
+    indices.sort_by(|&a, &b| keys[a].cmp(&keys[b]));
+    if indices.iter().enumerate().all(|(i, &index)| i == index) {
+        return None;
+    }
+
+    let mut starts = Vec::new();
+    let mut previous_end = None;
+    for item in items {
+        let line = tree.line_start(item.span.start);
+        let prefix = source[line..item.span.start].trim();
+        if prefix != "-" {
+            return None;
+        }
+        // 前の要素の後ろにあるコメントだけの行は、この要素のものとして一緒に動かす
+        let mut start = line;
+        while start > 0 {
+            let above = tree.line_start(start - 1);
+            if previous_end.is_some_and(|end| above < end)
+                || !source[above..start].trim_start().starts_with('#')
+            {
+                break;
+            }
+            start = above;
+        }
+        starts.push(start);
+        previous_end = Some(line_end(source, item.span.end));
+    }
+    let end = previous_end?;
+    let blocks: Vec<String> = (0..items.len())
+        .map(|i| {
+            let block = &source[starts[i]..starts.get(i + 1).copied().unwrap_or(end)];
+            if block.ends_with('\n') {
+                block.to_string()
+            } else {
+                format!("{}\n", block)
+            }
+        })
+        .collect();
+    let mut text: String = indices.iter().map(|&i| blocks[i].as_str()).collect();
+    if !source[..end].ends_with('\n') {
+        text.pop();
+    }
+    Some(TextEdit {
+        span: Span {
+            start: starts[0],
+            end,
+        },
+        text,
+    })
+}
+
+/// 編集を後ろから順に適用する
+fn apply(source: &mut String, mut edits: Vec<TextEdit>) {
+    edits.sort_by_key(|e| std::cmp::Reverse(e.span.start));
+    for edit in edits {
+        source.replace_range(edit.span.start..edit.span.end, &edit.text);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+
+    const DOC: &str = r#"version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["users"]
+    # 投稿
+    - ./schema.dbml#tables["posts"]
+usecase:
+  name: 投稿詳細
+  distinct: false
+  response_mapping:
+    - field: author
+      source: users.name
+      join:
+        table: users
+        on: posts.user_id = users.id
+        type: left join   # 著者がいない投稿もある
+    # 本文
+    - field: body
+      source: posts.body
+    - field: comments
+      type: array
+      source_table: comments
+      collection: false
+      fields:
+        - field: text
+          source: comments.text
+        - field: id
+          source: comments.id
+          join:
+            table: users
+            on: comments.user_id = users.id
+            type: "Inner"
+  filters: []
+"#;
+
+    fn config() -> FmtConfig {
+        FmtConfig {
+            sort_imports: true,
+            order_fields: true,
+            join_type: Some(KeywordCase::Upper),
+            collapse_defaults: true,
+        }
+    }
+
+    fn openapi() -> OpenapiResponse {
+        OpenapiResponse {
+            field_order: ["body", "comments", "comments.id", "comments.text", "author"]
+                .map(String::from)
+                .to_vec(),
+            ..OpenapiResponse::default()
+        }
+    }
+
+    #[test]
+    fn test_normalize_applies_all_options() {
+        let output = normalize(DOC, &config(), Some(&openapi())).unwrap();
+        assert_eq!(
+            output,
+            r#"version: "0.1"
+import:
+  dbml:
+    # 投稿
+    - ./schema.dbml#tables["posts"]
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: 投稿詳細
+  response_mapping:
+    # 本文
+    - field: body
+      source: posts.body
+    - field: comments
+      type: array
+      source_table: comments
+      fields:
+        - field: id
+          source: comments.id
+          join:
+            table: users
+            on: comments.user_id = users.id
+            type: "INNER"
+        - field: text
+          source: comments.text
+    - field: author
+      source: users.name
+      join:
+        table: users
+        on: posts.user_id = users.id
+"#
+        );
+        assert!(parser::parse(&output).is_ok());
+        // 2 回目は何も変えない
+        assert_eq!(
+            normalize(&output, &config(), Some(&openapi())).unwrap(),
+            output
+        );
+    }
+
+    #[test]
+    fn test_normalize_only_configured_options() {
+        let config = FmtConfig {
+            join_type: Some(KeywordCase::Lower),
+            ..FmtConfig::default()
+        };
+        let output = normalize(DOC, &config, Some(&openapi())).unwrap();
+        assert!(output.contains("        type: left   # 著者がいない投稿もある\n"));
+        assert!(output.contains("            type: \"inner\"\n"));
+        assert_eq!(
+            output
+                .replace("type: left   #", "type: left join   #")
+                .replace("\"inner\"", "\"Inner\""),
+            DOC
+        );
+
+        // OpenAPI が無ければフィールドは並べ替えない
+        let config = FmtConfig {
+            order_fields: true,
+            ..FmtConfig::default()
+        };
+        assert_eq!(normalize(DOC, &config, None).unwrap(), DOC);
+    }
+
+    #[test]
+    fn test_sort_fields_alphabetical() {
+        let output = sort_fields(DOC, FieldOrder::Alphabetical).unwrap();
+        let doc = parser::parse(&output).unwrap();
+        let fields: Vec<&str> = doc
+            .usecase
+            .response_mapping
+            .iter()
+            .map(|m| m.field.as_str())
+            .collect();
+        assert_eq!(fields, ["author", "body", "comments"]);
+        let comments = doc.usecase.response_mapping[2].fields.as_ref().unwrap();
+        assert_eq!(comments[0].field, "id");
+        assert!(output.contains("    # 本文\n    - field: body\n"));
+        // 並べ替えだけで、表記は変えない
+        assert!(output.contains("type: left join   # 著者がいない投稿もある\n"));
+        assert!(output.contains("  filters: []\n"));
+    }
+
+    #[test]
+    fn test_sort_items_keeps_flow_sequences() {
+        let source = "import:\n  dbml: ['./b.dbml#tables[\"b\"]', './a.dbml#tables[\"a\"]']\n";
+        let config = FmtConfig {
+            sort_imports: true,
+            ..FmtConfig::default()
+        };
+        assert_eq!(normalize(source, &config, None).unwrap(), source);
+    }
+}
@@ -0,0 +1,223 @@
+use serde::Serialize;
+
+use crate::syntax::{Range, Span, SyntaxError, SyntaxNode, SyntaxTree};
+
+/// アウトライン要素の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SymbolKind {
+    Usecase,
+    Field,
+    /// `type: array` のフィールド
+    ArrayField,
+    Filter,
+    Transform,
+}
+
+/// エディタのアウトライン（documentSymbol）1 要素
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// source・maps_to・transform の種類など名前の横に出す補足
+    pub detail: Option<String>,
+    /// 要素全体の範囲
+    pub span: Span,
+    pub range: Range,
+    /// 名前部分（field・param・target の値）の範囲
+    pub selection_span: Span,
+    pub selection_range: Range,
+    pub children: Vec<Symbol>,
+}
+
+/// USML のソースからアウトラインを組み立てる
+///
+/// ユースケースを根に、レスポンスフィールド（入れ子含む）・filters・transforms を子として返す
+pub fn outline(source: &str) -> Result<Vec<Symbol>, SyntaxError> {
+    let tree = SyntaxTree::parse(source)?;
+    let builder = OutlineBuilder {
+        source,
+        tree: &tree,
+    };
+    let Some(usecase) = tree.root.entry("usecase") else {
+        return Ok(Vec::new());
+    };
+
+    let mut children = Vec::new();
+    if let Some(mappings) = usecase.value.get("response_mapping") {
+        children.extend(builder.fields(mappings));
+    }
+    for filter in usecase
+        .value
+        .get("filters")
+        .map_or(&[][..], SyntaxNode::items)
+    {
+        children.extend(builder.symbol(
+            filter,
+            "param",
+            SymbolKind::Filter,
+            detail(filter, "maps_to"),
+            Vec::new(),
+        ));
+    }
+    for transform in usecase
+        .value
+        .get("transforms")
+        .map_or(&[][..], SyntaxNode::items)
+    {
+        children.extend(builder.symbol(
+            transform,
+            "target",
+            SymbolKind::Transform,
+            detail(transform, "type"),
+            Vec::new(),
+        ));
+    }
+
+    let name_node = usecase.value.get("name");
+    let span = Span {
+        start: usecase.key_span.start,
+        end: usecase.value.span.end,
+    };
+    let selection_span = name_node.map_or(usecase.key_span, |n| n.span);
+    Ok(vec![Symbol {
+        name: name_node
+            .and_then(SyntaxNode::as_str)
+            .unwrap_or("usecase")
+            .to_string(),
+        kind: SymbolKind::Usecase,
+        detail: usecase
+            .value
+            .get("summary")
+            .and_then(SyntaxNode::as_str)
+            .map(str::to_string),
+        span,
+        range: tree.range(source, span),
+        selection_span,
+        selection_range: tree.range(source, selection_span),
+        children,
+    }])
+}
+
+struct OutlineBuilder<'a> {
+    source: &'a str,
+    tree: &'a SyntaxTree,
+}
+
+impl OutlineBuilder<'_> {
+    fn fields(&self, mappings: &SyntaxNode) -> Vec<Symbol> {
+        mappings
+            .items()
+            .iter()
+            .filter_map(|mapping| {
+                let is_array = mapping.get("type").and_then(SyntaxNode::as_str) == Some("array");
+                let (kind, detail) = if is_array {
+                    let table = mapping
+                        .get("source_table")
+                        .and_then(SyntaxNode::as_str)
+                        .map(|t| format!("array<{}>", t));
+                    (SymbolKind::ArrayField, table.or(Some("array".to_string())))
+                } else {
+                    (SymbolKind::Field, detail(mapping, "source"))
+                };
+                let children = mapping
+                    .get("fields")
+                    .map(|fields| self.fields(fields))
+                    .unwrap_or_default();
+                self.symbol(mapping, "field", kind, detail, children)
+            })
+            .collect()
+    }
+
+    /// `name_key` の値を名前とするシンボルを作る。名前が無い要素は省く
+    fn symbol(
+        &self,
+        node: &SyntaxNode,
+        name_key: &str,
+        kind: SymbolKind,
+        detail: Option<String>,
+        children: Vec<Symbol>,
+    ) -> Option<Symbol> {
+        let name_node = node.get(name_key)?;
+        Some(Symbol {
+            name: name_node.as_str()?.to_string(),
+            kind,
+            detail,
+            span: node.span,
+            range: self.tree.range(self.source, node.span),
+            selection_span: name_node.span,
+            selection_range: self.tree.range(self.source, name_node.span),
+            children,
+        })
+    }
+}
+
+fn detail(node: &SyntaxNode, key: &str) -> Option<String> {
+    node.get(key)
+        .and_then(SyntaxNode::as_str)
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOC: &str = r#"version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["posts"]
+usecase:
+  name: 投稿詳細
+  response_mapping:
+    - field: title
+      source: posts.title
+    - field: comments
+      type: array
+      source_table: comments
+      fields:
+        - field: body
+          source: comments.body
+  filters:
+    - param: post_id
+      maps_to: posts.id
+  transforms:
+    - target: title
+      type: COALESCE
+"#;
+
+    #[test]
+    fn test_outline_nests_fields_and_lists_filters_and_transforms() {
+        let symbols = outline(DOC).unwrap();
+        assert_eq!(symbols.len(), 1);
+        let usecase = &symbols[0];
+        assert_eq!(usecase.name, "投稿詳細");
+
+        let kinds: Vec<_> = usecase.children.iter().map(|s| s.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                SymbolKind::Field,
+                SymbolKind::ArrayField,
+                SymbolKind::Filter,
+                SymbolKind::Transform
+            ]
+        );
+        let comments = &usecase.children[1];
+        assert_eq!(comments.detail.as_deref(), Some("array<comments>"));
+        assert_eq!(comments.children[0].name, "body");
+        assert_eq!(usecase.children[2].detail.as_deref(), Some("posts.id"));
+    }
+
+    #[test]
+    fn test_outline_ranges_point_at_names() {
+        let symbols = outline(DOC).unwrap();
+        let title = &symbols[0].children[0];
+        assert_eq!(
+            &DOC[title.selection_span.start..title.selection_span.end],
+            "title"
+        );
+        assert_eq!(title.selection_range.start.line, 8);
+        assert_eq!(title.selection_range.start.column, 14);
+        assert!(title.span.contains(title.selection_span.start));
+    }
+}
@@ -0,0 +1,296 @@
+//! 生成物の出力先パスのテンプレート
+//!
+//! `{method}/{name}.html` のような文字列の変数を、ドキュメントのメタデータで置き換える。
+//! 値はパスに使えない文字を `-` にしてから埋め込む
+
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+use thiserror::Error;
+
+use crate::ast::UsmlDocument;
+use crate::resolver::openapi::parse_openapi_ref;
+
+/// テンプレートを指定しない場合の出力先（出力先ディレクトリからの相対パス）
+pub const DEFAULT_TEMPLATE: &str = "{name}.html";
+
+/// テンプレートで使える変数
+pub const VARIABLES: &[&str] = &[
+    "name", "file", "dir", "method", "path", "status", "tag", "owner",
+];
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum TemplateError {
+    #[error("出力先テンプレートの変数 '{{{0}}}' は使えません（使える変数: {vars}）", vars = VARIABLES.join(", "))]
+    UnknownVariable(String),
+    #[error("出力先テンプレートの変数 '{{{0}}}' の値がありません: {1}")]
+    MissingValue(String, &'static str),
+    #[error("出力先テンプレートの '{{' が閉じていません: {0}")]
+    Unclosed(String),
+}
+
+/// `usecase.output` が出力ディレクトリの中の HTML ファイルを指しているか。問題があればその説明を返す
+///
+/// 絶対パス・`..` を含むパスは出力ディレクトリの外に書き出してしまうため使えない
+pub fn check_output_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("空です".to_string());
+    }
+    let drive = name.as_bytes().get(1) == Some(&b':');
+    if name.starts_with(['/', '\\']) || drive {
+        return Err("絶対パスは使えません（出力ディレクトリからの相対パス）".to_string());
+    }
+    if name.split(['/', '\\']).any(|part| part == "..") {
+        return Err("'..' で出力ディレクトリの外を指すことはできません".to_string());
+    }
+    if !name.to_ascii_lowercase().ends_with(".html") {
+        return Err("拡張子は .html にしてください".to_string());
+    }
+    Ok(())
+}
+
+/// `template` の変数を `doc`（`file_path` から読んだもの）の値で置き換える
+///
+/// - `name`: usecase 名
+/// - `file`: ファイル名から `.usml.yaml` を除いたもの
+/// - `dir`: ファイルのあるディレクトリ（`./` と先頭の `/` は除く）
+/// - `method` / `path` / `status`: OpenAPI の import 参照のメソッド（小文字）・API パス・ステータスコード。
+///   `path` は `/users/{user_id}` → `users/user_id` のように階層を保つ
+/// - `tag` / `owner`: 先頭のタグと owner
+pub fn render(
+    template: &str,
+    doc: &UsmlDocument,
+    file_path: &str,
+) -> Result<String, TemplateError> {
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}') else {
+            return Err(TemplateError::Unclosed(template.to_string()));
+        };
+        let name = &rest[start + 1..start + end];
+        out.push_str(&value(name, doc, file_path)?);
+        rest = &rest[start + end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+fn value(name: &str, doc: &UsmlDocument, file_path: &str) -> Result<String, TemplateError> {
+    let missing = |reason| TemplateError::MissingValue(name.to_string(), reason);
+    let openapi = || {
+        doc.import
+            .openapi
+            .as_deref()
+            .and_then(parse_openapi_ref)
+            .ok_or_else(|| missing("OpenAPI の import がありません"))
+    };
+    match name {
+        "name" => Ok(sanitize(&doc.usecase.name)),
+        "file" => {
+            let file = file_path.rsplit(['/', '\\']).next().unwrap_or(file_path);
+            Ok(sanitize(file.strip_suffix(".usml.yaml").unwrap_or(file)))
+        }
+        "dir" => {
+            let parent = Path::new(file_path).parent().unwrap_or(Path::new(""));
+            let parts: Vec<String> = parent
+                .components()
+                .filter_map(|c| match c {
+                    Component::Normal(part) => Some(sanitize(&part.to_string_lossy())),
+                    Component::ParentDir => Some("..".to_string()),
+                    _ => None,
+                })
+                .collect();
+            Ok(if parts.is_empty() {
+                ".".to_string()
+            } else {
+                parts.join("/")
+            })
+        }
+        "method" => openapi().map(|(_, _, method, _)| sanitize(&method.to_ascii_lowercase())),
+        "path" => openapi().map(|(_, path, _, _)| {
+            path.split('/')
+                .map(|segment| segment.trim_start_matches('{').trim_end_matches('}'))
+                .filter(|segment| !segment.is_empty())
+                .map(sanitize)
+                .collect::<Vec<_>>()
+                .join("/")
+        }),
+        "status" => openapi().map(|(_, _, _, status)| sanitize(status)),
+        "tag" => doc
+            .usecase
+            .tags
+            .first()
+            .map(|tag| sanitize(tag))
+            .ok_or_else(|| missing("tags がありません")),
+        "owner" => doc
+            .usecase
+            .owner
+            .as_deref()
+            .map(sanitize)
+            .ok_or_else(|| missing("owner がありません")),
+        _ => Err(TemplateError::UnknownVariable(name.to_string())),
+    }
+}
+
+/// 英数字・`-`・`_` 以外を `-` に置き換える
+pub fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+/// 1 回の実行で書き出す出力先
+///
+/// `a/b` と `a-b` のように別の usecase が同じパスに解決されると、後のものが先のものを上書きする。
+/// 書き出す前に出力先を登録して、重なりを見つける
+#[derive(Debug, Default)]
+pub struct OutputPaths {
+    claimed: HashMap<PathBuf, String>,
+}
+
+impl OutputPaths {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `path` を `source` の出力先として登録する。既に別の入力が使っていればその入力を返す
+    ///
+    /// `./` や `dir/../` は取り除いてから比べる
+    pub fn claim(&mut self, path: &Path, source: &str) -> Result<(), String> {
+        let key = normalize(path);
+        match self.claimed.get(&key) {
+            Some(previous) => Err(previous.clone()),
+            None => {
+                self.claimed.insert(key, source.to_string());
+                Ok(())
+            }
+        }
+    }
+}
+
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if matches!(
+                    normalized.components().next_back(),
+                    Some(Component::Normal(_))
+                ) =>
+            {
+                normalized.pop();
+            }
+            _ => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+
+    const YAML: &str = r#"
+version: "0.1"
+import:
+  openapi: ./api.yaml#paths["/users/{user_id}/stats"].get.responses["200"]
+  dbml:
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: ユーザー 活動/集計
+  tags: [users]
+  response_mapping:
+    - field: id
+      source: users.id
+"#;
+
+    #[test]
+    fn test_render_template_variables() {
+        let doc = parser::parse(YAML).unwrap();
+        let path = render(
+            "{tag}/{method}/{path}/{dir}/{file}-{status}-{name}.html",
+            &doc,
+            "./services/user/user-stats.usml.yaml",
+        )
+        .unwrap();
+        assert_eq!(
+            path,
+            "users/get/users/user_id/stats/services/user/user-stats-200-ユーザー-活動-集計.html"
+        );
+        assert_eq!(
+            render(DEFAULT_TEMPLATE, &doc, "user-stats.usml.yaml").unwrap(),
+            "ユーザー-活動-集計.html"
+        );
+    }
+
+    #[test]
+    fn test_check_output_name() {
+        assert_eq!(check_output_name("users/list.html"), Ok(()));
+        assert_eq!(check_output_name("Report.HTML"), Ok(()));
+        for name in [
+            "",
+            "/tmp/list.html",
+            "\\share\\list.html",
+            "C:\\out\\list.html",
+            "../list.html",
+            "users/../../list.html",
+            "list.txt",
+            "list",
+        ] {
+            assert!(check_output_name(name).is_err(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_render_template_errors() {
+        let doc = parser::parse(YAML).unwrap();
+        assert_eq!(
+            render("{owner}/{name}.html", &doc, "a.usml.yaml"),
+            Err(TemplateError::MissingValue(
+                "owner".to_string(),
+                "owner がありません"
+            ))
+        );
+        assert_eq!(
+            render("{service}.html", &doc, "a.usml.yaml"),
+            Err(TemplateError::UnknownVariable("service".to_string()))
+        );
+        assert!(matches!(
+            render("{name.html", &doc, "a.usml.yaml"),
+            Err(TemplateError::Unclosed(_))
+        ));
+    }
+
+    #[test]
+    fn test_output_paths_detects_collisions() {
+        let doc = |name: &str| parser::parse(&YAML.replace("ユーザー 活動/集計", name)).unwrap();
+        let mut paths = OutputPaths::new();
+        for (file, name) in [("a.usml.yaml", "a/b"), ("c.usml.yaml", "c")] {
+            let relative = render(DEFAULT_TEMPLATE, &doc(name), file).unwrap();
+            assert_eq!(
+                paths.claim(&Path::new("output").join(relative), file),
+                Ok(())
+            );
+        }
+        let relative = render(DEFAULT_TEMPLATE, &doc("a-b"), "b.usml.yaml").unwrap();
+        assert_eq!(
+            paths.claim(&Path::new("./output").join(relative), "b.usml.yaml"),
+            Err("a.usml.yaml".to_string())
+        );
+        assert_eq!(
+            paths.claim(Path::new("output/sub/../c.html"), "d.usml.yaml"),
+            Err("c.usml.yaml".to_string())
+        );
+    }
+}
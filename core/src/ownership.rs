@@ -0,0 +1,110 @@
+use crate::config::OwnershipConfig;
+use crate::rules::{self, CustomRule, Diagnostics, Rule, RuleContext};
+
+/// `[lint.ownership]` の設定に usecase の owner・reviewers が沿っているかを調べる
+#[derive(Debug)]
+pub struct OwnershipRule {
+    teams: Vec<String>,
+    min_reviewers: usize,
+}
+
+impl OwnershipRule {
+    pub fn new(config: &OwnershipConfig) -> Self {
+        OwnershipRule {
+            teams: config.teams.clone(),
+            min_reviewers: config.min_reviewers,
+        }
+    }
+}
+
+impl CustomRule for OwnershipRule {
+    fn rule(&self) -> &Rule {
+        &rules::OWNERSHIP
+    }
+
+    fn check(&self, cx: &RuleContext<'_>, diagnostics: &mut Diagnostics<'_>) {
+        let usecase = &cx.document.usecase;
+        match &usecase.owner {
+            None => diagnostics.report_node(
+                format!("usecase '{}' に owner が指定されていません", usecase.name),
+                "/usecase",
+            ),
+            Some(owner) if !self.teams.is_empty() && !self.teams.contains(owner) => diagnostics
+                .report_node(
+                    format!(
+                        "owner '{}' は [lint.ownership] teams にありません（使えるチーム: {}）",
+                        owner,
+                        self.teams.join(", ")
+                    ),
+                    "/usecase/owner",
+                ),
+            Some(_) => {}
+        }
+        if usecase.reviewers.len() < self.min_reviewers {
+            diagnostics.report_node(
+                format!(
+                    "usecase '{}' の reviewers は {} 人です（{} 人以上必要）",
+                    usecase.name,
+                    usecase.reviewers.len(),
+                    self.min_reviewers
+                ),
+                "/usecase/reviewers",
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::Config;
+    use crate::parser;
+    use crate::validator::Validator;
+
+    fn codes_and_messages(owner: &str) -> Vec<(String, String)> {
+        let config = Config::parse(
+            "[lint.ownership]\nteams = [\"payments\", \"core\"]\nmin_reviewers = 1\n",
+            "usml.toml",
+        )
+        .unwrap();
+        let doc = parser::parse(&format!(
+            r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: テスト
+  {}
+  response_mapping:
+    - field: id
+      source: users.id
+"#,
+            owner
+        ))
+        .unwrap();
+        Validator::from_config(&config)
+            .unwrap()
+            .validate(&doc)
+            .into_iter()
+            .map(|d| (d.code.unwrap_or_default(), d.message))
+            .collect()
+    }
+
+    #[test]
+    fn test_owner_must_be_a_configured_team() {
+        let found = codes_and_messages("owner: marketing");
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].0, "U039");
+        assert!(
+            found[0]
+                .1
+                .contains("owner 'marketing' は [lint.ownership] teams にありません")
+        );
+        assert!(found[1].1.contains("reviewers は 0 人です（1 人以上必要）"));
+
+        let found = codes_and_messages("tags: []");
+        assert!(found[0].1.contains("owner が指定されていません"));
+
+        assert!(codes_and_messages("owner: core\n  reviewers: [alice]").is_empty());
+    }
+}
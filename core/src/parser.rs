@@ -1,6 +1,10 @@
+use std::fs;
+use std::path::Path;
+
 use thiserror::Error;
 
-use crate::ast::UsmlDocument;
+use crate::ast::{LibraryDocument, UsmlDocument};
+use crate::error::UsmlError;
 
 #[derive(Debug, Error)]
 pub enum ParseError {
@@ -9,10 +13,21 @@ pub enum ParseError {
 
     #[error("invalid version: expected '0.1', got '{0}'")]
     InvalidVersion(String),
+
+    #[error("nesting too deep: more than {limit} nested [ or {{ at line {line} column {column}")]
+    TooDeep {
+        line: usize,
+        column: usize,
+        limit: usize,
+    },
 }
 
+/// フロー形式（`[`・`{`）の入れ子の上限。serde_yaml はこれより深い入れ子をエラーにする
+pub const MAX_FLOW_DEPTH: usize = 128;
+
 /// USML ドキュメントを YAML 文字列からパースする
 pub fn parse(input: &str) -> Result<UsmlDocument, ParseError> {
+    check_flow_depth(input)?;
     let doc: UsmlDocument = serde_yaml::from_str(input)?;
 
     if doc.version != "0.1" {
@@ -22,6 +37,64 @@ pub fn parse(input: &str) -> Result<UsmlDocument, ParseError> {
     Ok(doc)
 }
 
+/// USML ファイルを読み込んでパースする
+pub fn parse_file(path: impl AsRef<Path>) -> Result<UsmlDocument, UsmlError> {
+    let path = path.as_ref();
+    let input = fs::read_to_string(path).map_err(|e| UsmlError::Io {
+        path: path.display().to_string(),
+        source: e,
+    })?;
+    Ok(parse(&input)?)
+}
+
+/// 共有ライブラリ（`library.usml.yaml`）を YAML 文字列からパースする
+pub fn parse_library(input: &str) -> Result<LibraryDocument, ParseError> {
+    check_flow_depth(input)?;
+    let doc: LibraryDocument = serde_yaml::from_str(input)?;
+
+    if doc.version != "0.1" {
+        return Err(ParseError::InvalidVersion(doc.version));
+    }
+
+    Ok(doc)
+}
+
+/// 共有ライブラリファイルを読み込んでパースする
+pub fn parse_library_file(path: impl AsRef<Path>) -> Result<LibraryDocument, UsmlError> {
+    let path = path.as_ref();
+    let input = fs::read_to_string(path).map_err(|e| UsmlError::Io {
+        path: path.display().to_string(),
+        source: e,
+    })?;
+    Ok(parse_library(&input)?)
+}
+
+/// フロー形式の入れ子が `MAX_FLOW_DEPTH` を超えていないか
+///
+/// serde_yaml（libyaml）の字句解析は深いフロー形式の入れ子に入力長の 2 乗の時間がかかり、
+/// `[[[…]]]` を並べただけの文書でも再帰の上限に届くまでに長く止まる。その前に括弧を数えて断る。
+/// 引用符やコメントの中の括弧も数えるため、実際の入れ子より深く見積もることはあっても浅くは見積もらない
+fn check_flow_depth(input: &str) -> Result<(), ParseError> {
+    let mut depth = 0usize;
+    for (index, line) in input.lines().enumerate() {
+        for (column, c) in line.chars().enumerate() {
+            match c {
+                '[' | '{' => depth += 1,
+                ']' | '}' => depth = depth.saturating_sub(1),
+                _ => continue,
+            }
+            if depth > MAX_FLOW_DEPTH {
+                return Err(ParseError::TooDeep {
+                    line: index + 1,
+                    column: column + 1,
+                    limit: MAX_FLOW_DEPTH,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -67,6 +140,30 @@ usecase:
         assert!(matches!(result.unwrap_err(), ParseError::InvalidVersion(_)));
     }
 
+    #[test]
+    fn test_deep_flow_nesting_is_rejected_before_yaml() {
+        let nested = |depth: usize| {
+            format!(
+                "version: \"0.1\"\nimport: {{}}\nusecase:\n  name: test\n  response_mapping: {}{}\n",
+                "[".repeat(depth),
+                "]".repeat(depth)
+            )
+        };
+        // serde_yaml に渡すと数秒かかる深さでもすぐに断る
+        assert!(matches!(
+            parse(&nested(50_000)),
+            Err(ParseError::TooDeep {
+                line: 5,
+                column: 149,
+                limit: MAX_FLOW_DEPTH
+            })
+        ));
+        assert!(matches!(
+            parse(&nested(MAX_FLOW_DEPTH - 2)),
+            Err(ParseError::YamlError(_))
+        ));
+    }
+
     #[test]
     fn test_document_with_join() {
         let yaml = r#"
@@ -168,3 +265,432 @@ usecase:
         assert_eq!(doc.usecase.transforms[0].r#type, "COALESCE");
     }
 }
+
+/// 任意の AST・任意の文字列に対する性質テスト
+#[cfg(test)]
+mod prop_tests {
+    use proptest::collection::vec;
+    use proptest::option;
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::ast::*;
+    use crate::syntax::Position;
+    use crate::validator::ResolveContext;
+    use crate::{api, definition, hover, lineage, outline, semantic_tokens, validator};
+
+    fn ident() -> impl Strategy<Value = String> {
+        "[a-z_][a-z0-9_]{0,8}"
+    }
+
+    /// `テーブル.カラム` 形式（まれに壊れた参照も混ぜる）
+    fn column_ref() -> impl Strategy<Value = String> {
+        prop_oneof![
+            4 => (ident(), ident()).prop_map(|(t, c)| format!("{}.{}", t, c)),
+            1 => "\\PC{0,12}",
+        ]
+    }
+
+    /// 引用符・コロン・改行などを含みうる自由文字列
+    fn text() -> impl Strategy<Value = String> {
+        "\\PC{0,16}|[\\s\\S]{0,8}"
+    }
+
+    fn join() -> impl Strategy<Value = Join> {
+        (
+            ident(),
+            text(),
+            option::of("INNER|LEFT|RIGHT|left"),
+            option::of(ident()),
+        )
+            .prop_map(|(table, on, r#type, alias)| Join {
+                table,
+                on,
+                r#type,
+                alias,
+            })
+    }
+
+    fn literal() -> impl Strategy<Value = Literal> {
+        prop_oneof![
+            any::<bool>().prop_map(Literal::Bool),
+            any::<i64>().prop_map(Literal::Integer),
+            (-1.0e6..1.0e6f64).prop_map(Literal::Number),
+            text().prop_map(Literal::String),
+        ]
+    }
+
+    fn mapping() -> impl Strategy<Value = ResponseMapping> {
+        let leaf = (
+            text(),
+            option::of(column_ref()),
+            option::of(join()),
+            option::of(vec(
+                (ident(), text()).prop_map(|(table, on)| JoinChainEntry { table, on }),
+                0..3,
+            )),
+            option::of(
+                (
+                    "COUNT|SUM|avg|\\PC{0,5}",
+                    option::of(column_ref()),
+                    option::of("group_by|subquery"),
+                )
+                    .prop_map(|(r#type, group_by, strategy)| Aggregate {
+                        r#type,
+                        group_by,
+                        strategy,
+                    }),
+            ),
+            option::of(literal()),
+            option::of(ident()),
+            option::of(text()),
+        )
+            .prop_map(
+                |(
+                    field,
+                    source,
+                    join,
+                    join_chain,
+                    aggregate,
+                    value,
+                    from_param,
+                    source_usecase,
+                )| ResponseMapping {
+                    field,
+                    source,
+                    r#type: None,
+                    source_table: None,
+                    join,
+                    join_chain,
+                    aggregate,
+                    fields: None,
+                    collection: false,
+                    envelope: None,
+                    nullable: None,
+                    value,
+                    from_param,
+                    source_usecase,
+                    timezone: None,
+                },
+            );
+        leaf.prop_recursive(2, 12, 4, |inner| {
+            (text(), option::of(ident()), vec(inner, 0..4)).prop_map(|(field, table, fields)| {
+                ResponseMapping {
+                    field,
+                    source: None,
+                    r#type: Some("array".to_string()),
+                    source_table: table,
+                    join: None,
+                    join_chain: None,
+                    aggregate: None,
+                    fields: Some(fields),
+                    collection: false,
+                    envelope: None,
+                    nullable: None,
+                    value: None,
+                    from_param: None,
+                    source_usecase: None,
+                    timezone: None,
+                }
+            })
+        })
+    }
+
+    fn filter() -> impl Strategy<Value = Filter> {
+        let filter = (
+            ident(),
+            prop_oneof![
+                column_ref(),
+                "WHERE|ORDER_BY|PAGINATION".prop_map(String::from)
+            ],
+            option::of(text()),
+            option::of("offset|cursor"),
+            option::of(any::<u32>()),
+            option::of(ident()),
+            option::of(any::<u32>()),
+            option::of(column_ref()),
+            option::of(column_ref()),
+            option::of("ASC|DESC"),
+            option::of(vec(column_ref(), 0..3)),
+            option::of(vec("ASC|DESC", 0..3)),
+        );
+        (
+            filter,
+            option::of(ident()),
+            option::of(any::<bool>()),
+            option::of(literal()),
+            option::of(column_ref()),
+            option::of("window|query|none"),
+        )
+            .prop_map(
+                |(
+                    (
+                        param,
+                        maps_to,
+                        condition,
+                        strategy,
+                        page_size,
+                        limit_param,
+                        max_page_size,
+                        cursor_field,
+                        default_column,
+                        default_direction,
+                        allowed_columns,
+                        allowed_directions,
+                    ),
+                    r#use,
+                    required,
+                    default,
+                    cursor_tiebreaker,
+                    total_count,
+                )| Filter {
+                    param,
+                    maps_to,
+                    r#use,
+                    condition,
+                    strategy,
+                    page_size,
+                    limit_param,
+                    max_page_size,
+                    cursor_field,
+                    cursor_tiebreaker,
+                    total_count,
+                    default_column,
+                    default_direction,
+                    allowed_columns,
+                    allowed_directions,
+                    required,
+                    default,
+                },
+            )
+    }
+
+    fn transform() -> impl Strategy<Value = Transform> {
+        let condition = (
+            option::of(ident()),
+            option::of(text()),
+            option::of(column_ref()),
+            "=|!=|IS NULL|\\PC{0,3}",
+            text(),
+        )
+            .prop_map(
+                |(param, field, source, operator, value)| TransformCondition {
+                    param,
+                    field,
+                    source,
+                    operator,
+                    value,
+                },
+            );
+        let transform = (
+            text(),
+            "COALESCE|CONCAT|CASE|MASK|CONDITIONAL_SOURCE|CONVERT|LOCALIZE|\\PC{0,6}",
+            option::of(column_ref()),
+            option::of(vec(column_ref(), 0..3)),
+            option::of(text()),
+            option::of(text()),
+            option::of(vec(
+                (text(), text()).prop_map(|(value, then)| CaseWhen { value, then }),
+                0..3,
+            )),
+            option::of(text()),
+            option::of(text()),
+            option::of(vec(condition, 0..2)),
+            option::of(column_ref()),
+            option::of(column_ref()),
+        );
+        (transform, option::of(ident())).prop_map(
+            |(
+                (
+                    target,
+                    r#type,
+                    source,
+                    sources,
+                    fallback,
+                    separator,
+                    when,
+                    else_value,
+                    mask_pattern,
+                    condition,
+                    then_source,
+                    else_source,
+                ),
+                r#use,
+            )| Transform {
+                target,
+                r#type,
+                r#use,
+                source,
+                sources,
+                fallback,
+                separator,
+                when,
+                else_value,
+                mask_pattern,
+                condition,
+                then_source,
+                else_source,
+                factor: None,
+                lookup: None,
+                rounding: None,
+                scale: None,
+                locale: None,
+            },
+        )
+    }
+
+    fn authorization() -> impl Strategy<Value = Authorization> {
+        (vec(text(), 0..3), vec(text(), 0..3), vec(text(), 0..3)).prop_map(
+            |(scopes, roles, row_filters)| Authorization {
+                scopes,
+                roles,
+                row_filters,
+            },
+        )
+    }
+
+    fn cache() -> impl Strategy<Value = Cache> {
+        (any::<u32>(), vec(ident(), 0..3), vec(ident(), 0..3)).prop_map(
+            |(ttl, key, invalidated_by)| Cache {
+                ttl,
+                key,
+                invalidated_by,
+            },
+        )
+    }
+
+    fn hydration() -> impl Strategy<Value = Hydration> {
+        ("batch|json|\\PC{0,5}", option::of("postgresql|mysql"))
+            .prop_map(|(strategy, dialect)| Hydration { strategy, dialect })
+    }
+
+    fn document() -> impl Strategy<Value = UsmlDocument> {
+        let import = (
+            option::of(text()),
+            option::of(vec(
+                prop_oneof![
+                    ident().prop_map(|t| format!("./schema.dbml#tables[\"{}\"]", t)),
+                    text(),
+                ],
+                0..4,
+            )),
+        )
+            .prop_map(|(openapi, dbml)| Import {
+                openapi,
+                dbml,
+                tables: Default::default(),
+            });
+        let usecase = (
+            text(),
+            option::of(text()),
+            (vec(ident(), 0..3), option::of(ident()), vec(ident(), 0..2)),
+            option::of(text()),
+            vec(mapping(), 0..6),
+            vec(filter(), 0..3),
+            vec(transform(), 0..3),
+            any::<bool>(),
+            option::of(any::<u32>()),
+            option::of(any::<u32>()),
+            (
+                option::of(authorization()),
+                option::of(cache()),
+                option::of(hydration()),
+            ),
+        )
+            .prop_map(
+                |(
+                    name,
+                    summary,
+                    (tags, owner, reviewers),
+                    output,
+                    response_mapping,
+                    filters,
+                    transforms,
+                    distinct,
+                    limit,
+                    default_limit,
+                    (authorization, cache, hydration),
+                )| Usecase {
+                    name,
+                    summary,
+                    tags,
+                    owner,
+                    reviewers,
+                    output,
+                    response_mapping,
+                    filters,
+                    transforms,
+                    distinct,
+                    limit,
+                    default_limit,
+                    authorization,
+                    cache,
+                    hydration,
+                },
+            );
+        (import, usecase).prop_map(|(import, usecase)| UsmlDocument {
+            version: "0.1".to_string(),
+            import,
+            usecase,
+            environments: Default::default(),
+        })
+    }
+
+    /// パースできた文書に対して解析系の API をすべて通す
+    fn exercise(doc: UsmlDocument) {
+        let _ = validator::validate(&doc);
+        let _ = lineage::lineage(&doc);
+        #[cfg(feature = "visualizer")]
+        let _ = crate::visualizer::generate_html(&doc);
+        let _ = api::analyze(doc);
+    }
+
+    /// ソーステキストを直接扱う API を通す
+    fn exercise_source(source: &str, line: usize, column: usize) {
+        let _ = outline::outline(source);
+        let _ = semantic_tokens::semantic_tokens(source);
+        let _ = hover::hover(
+            source,
+            &ResolveContext::default(),
+            Position { line, column },
+        );
+        let _ = definition::definition(
+            source,
+            "fuzz.usml.yaml",
+            &ResolveContext::default(),
+            Position { line, column },
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn prop_parse_never_panics(
+            input in "[\\s\\S]{0,256}",
+            line in 1usize..10,
+            column in 1usize..40,
+        ) {
+            exercise_source(&input, line, column);
+            if let Ok(doc) = parse(&input) {
+                exercise(doc);
+            }
+        }
+
+        #[test]
+        fn prop_serialized_document_round_trips(doc in document()) {
+            let yaml = serde_yaml::to_string(&doc).unwrap();
+            let reparsed = parse(&yaml).unwrap();
+            prop_assert_eq!(serde_yaml::to_string(&reparsed).unwrap(), yaml);
+        }
+
+        #[test]
+        fn prop_analysis_never_panics_on_arbitrary_documents(
+            doc in document(),
+            line in 1usize..80,
+            column in 1usize..40,
+        ) {
+            let yaml = serde_yaml::to_string(&doc).unwrap();
+            exercise_source(&yaml, line, column);
+            exercise(doc);
+        }
+    }
+}
@@ -1,35 +1,487 @@
+#[cfg(feature = "async")]
+pub mod async_resolver;
 pub mod dbml;
 pub mod openapi;
 
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use crate::ast::Literal;
+use crate::diagnostic::Location;
+
 #[derive(Debug, Error)]
 pub enum ResolverError {
-    #[error("ファイル読み込みエラー '{0}': {1}")]
-    IoError(String, std::io::Error),
+    #[error("ファイル読み込みエラー '{file}': {source}")]
+    Io {
+        file: String,
+        #[source]
+        source: std::io::Error,
+    },
 
-    #[error("DBML パースエラー '{0}': {1}")]
-    DbmlParseError(String, String),
+    #[error("DBML パースエラー '{location}': {cause}")]
+    DbmlParse {
+        location: Location,
+        #[source]
+        cause: DbmlParseCause,
+    },
 
-    #[error("OpenAPI パースエラー '{0}': {1}")]
-    OpenapiParseError(String, String),
+    #[error("OpenAPI パースエラー '{location}': {source}")]
+    OpenapiParse {
+        location: Location,
+        #[source]
+        source: serde_yaml::Error,
+    },
 
     #[error("参照先が見つかりません: '{0}'")]
     NotFound(String),
+
+    #[error("`{0}` feature が無効なため解決できません")]
+    FeatureDisabled(&'static str),
+
+    #[error("ファイル '{file}' が大きすぎます（{size} バイト。上限は {limit} バイト）")]
+    TooLarge { file: String, size: u64, limit: u64 },
+
+    #[error("'{file}' の読み込みが {seconds} 秒以内に終わりませんでした")]
+    Timeout { file: String, seconds: u64 },
+
+    #[error("スキーマの入れ子が {limit} 段を超えています '{location}'")]
+    TooDeep { location: Location, limit: usize },
+
+    #[error("'{file}' は読み込みを許可されたディレクトリ（{allowed}）の外にあります")]
+    NotAllowed { file: String, allowed: String },
+}
+
+impl ResolverError {
+    /// エラーが発生した外部ファイル内の位置
+    pub fn location(&self) -> Option<&Location> {
+        match self {
+            ResolverError::Io { .. }
+            | ResolverError::NotFound(_)
+            | ResolverError::FeatureDisabled(_)
+            | ResolverError::TooLarge { .. }
+            | ResolverError::Timeout { .. }
+            | ResolverError::NotAllowed { .. } => None,
+            ResolverError::DbmlParse { location, .. }
+            | ResolverError::OpenapiParse { location, .. }
+            | ResolverError::TooDeep { location, .. } => Some(location),
+        }
+    }
+
+    /// エラーが発生したファイル
+    pub fn file(&self) -> Option<&str> {
+        match self {
+            ResolverError::Io { file, .. }
+            | ResolverError::TooLarge { file, .. }
+            | ResolverError::Timeout { file, .. }
+            | ResolverError::NotAllowed { file, .. } => Some(file),
+            _ => self.location().map(|l| l.file.as_str()),
+        }
+    }
+
+    /// [`Limits`] の上限を超えたことによるエラーか
+    pub fn is_limit(&self) -> bool {
+        matches!(
+            self,
+            ResolverError::TooLarge { .. }
+                | ResolverError::Timeout { .. }
+                | ResolverError::TooDeep { .. }
+        )
+    }
+}
+
+/// 解決の上限（`usml.toml` の `[limits]`）
+///
+/// 巨大なファイルや深すぎる入れ子で止まったりスタックを使い切ったりしないよう、超えた時点で打ち切って U042 で報告する
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Limits {
+    /// import するファイルの最大サイズ（バイト）
+    pub max_file_size: u64,
+    /// リモートの import の読み込みを待つ最大秒数（`async_resolver::TimeoutResolver`）
+    pub fetch_timeout_secs: u64,
+    /// OpenAPI のレスポンススキーマの入れ子の最大段数
+    pub max_schema_depth: usize,
+    /// source_usecase の参照をたどる最大段数
+    pub max_reference_depth: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_file_size: 10 * 1024 * 1024,
+            fetch_timeout_secs: 30,
+            max_schema_depth: 32,
+            max_reference_depth: 16,
+        }
+    }
+}
+
+impl Limits {
+    pub fn fetch_timeout(&self) -> Duration {
+        Duration::from_secs(self.fetch_timeout_secs)
+    }
+
+    /// `resolver` で `path` を読み込む。`max_file_size` を超えるファイルはエラーにする
+    ///
+    /// 読み込む前にサイズが分かる Resolver（[`Resolver::size`]）では、読み込まずに打ち切る
+    pub fn load<R: Resolver + ?Sized>(
+        &self,
+        resolver: &R,
+        path: &str,
+    ) -> Result<String, ResolverError> {
+        let too_large = |size| ResolverError::TooLarge {
+            file: path.to_string(),
+            size,
+            limit: self.max_file_size,
+        };
+        if let Some(size) = resolver.size(path)
+            && size > self.max_file_size
+        {
+            return Err(too_large(size));
+        }
+        let content = resolver.load(path)?;
+        self.check_size(path, content)
+    }
+
+    /// 読み込んだ内容が `max_file_size` を超えていないか
+    pub fn check_size(&self, path: &str, content: String) -> Result<String, ResolverError> {
+        match content.len() as u64 {
+            size if size > self.max_file_size => Err(ResolverError::TooLarge {
+                file: path.to_string(),
+                size,
+                limit: self.max_file_size,
+            }),
+            _ => Ok(content),
+        }
+    }
+}
+
+/// DBML パース失敗の原因
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum DbmlParseCause {
+    /// 構文エラー（その位置で期待された構文要素）
+    #[error("構文エラー: {} のいずれかが必要です", .0.join(", "))]
+    Syntax(Vec<String>),
+
+    /// 構文は正しいが意味的に不正（未定義テーブルへの参照など）
+    #[error("{0}")]
+    Semantic(String),
 }
 
 /// DBML から抽出されたテーブル情報
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct DbmlTable {
     pub name: String,
     pub columns: Vec<String>,
+    /// カラムごとの型・制約（`columns` と同じ順序）
+    pub column_details: Vec<DbmlColumn>,
+    /// DBML ファイル上のテーブル名の位置（定義へのジャンプに使う）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<Location>,
+}
+
+impl DbmlTable {
+    pub fn column(&self, name: &str) -> Option<&DbmlColumn> {
+        self.column_details.iter().find(|c| c.name == name)
+    }
+}
+
+/// DBML カラムの型と制約
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DbmlColumn {
+    pub name: String,
+    /// DBML に書かれた型（`varchar(255)` など）
+    pub data_type: String,
+    pub primary_key: bool,
+    pub unique: bool,
+    /// `null` 指定なら true、`not null` 指定なら false、未指定なら None
+    pub nullable: Option<bool>,
+    pub increment: bool,
+    pub default: Option<String>,
+    pub note: Option<String>,
+    /// 型が同じファイルの enum の場合はその値（宣言順）。enum でなければ空
+    pub enum_values: Vec<String>,
+    /// DBML ファイル上のカラム名の位置
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<Location>,
+}
+
+impl DbmlColumn {
+    /// 型を JSON Schema の型（integer / number / boolean / string）に対応付ける。判別できない型は None
+    pub fn json_type(&self) -> Option<&'static str> {
+        let base = self
+            .data_type
+            .split('(')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+        match base.as_str() {
+            "int" | "integer" | "smallint" | "bigint" | "tinyint" | "int2" | "int4" | "int8"
+            | "serial" | "smallserial" | "bigserial" => Some("integer"),
+            "numeric" | "decimal" | "real" | "float" | "float4" | "float8" | "double"
+            | "double precision" | "money" => Some("number"),
+            "bool" | "boolean" => Some("boolean"),
+            "varchar" | "character varying" | "char" | "character" | "text" | "citext" | "uuid"
+            | "date" | "time" | "timestamp" | "timestamptz" | "datetime" => Some("string"),
+            _ => None,
+        }
+    }
 }
 
 /// OpenAPI から抽出されたレスポンス情報
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct OpenapiResponse {
     /// レスポンスのフィールド名一覧
     pub fields: Vec<String>,
     /// パラメータ名一覧
     pub parameters: Vec<String>,
+    /// レスポンスのプロパティ定義（名前順）
+    pub properties: Vec<OpenapiProperty>,
+    /// パラメータ定義（`parameters` と同じ順序）
+    pub parameter_details: Vec<OpenapiParameter>,
+    /// レスポンスのプロパティのフルパス（`comments.body`）を OpenAPI ファイルに書かれた順に並べたもの
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub field_order: Vec<String>,
+    /// OpenAPI ファイル上のオペレーション（`paths` のパスの下のメソッド）の位置
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<Location>,
+}
+
+impl OpenapiResponse {
+    /// `comments.body` のようなフィールドのフルパスからプロパティ定義を探す
+    pub fn property(&self, path: &str) -> Option<&OpenapiProperty> {
+        let mut segments = path.split('.');
+        let first = segments.next()?;
+        let mut property = self.properties.iter().find(|p| p.name == first)?;
+        for segment in segments {
+            property = property.properties.iter().find(|p| p.name == segment)?;
+        }
+        Some(property)
+    }
+
+    pub fn parameter(&self, name: &str) -> Option<&OpenapiParameter> {
+        self.parameter_details.iter().find(|p| p.name == name)
+    }
+}
+
+/// OpenAPI レスポンスのプロパティ定義
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct OpenapiProperty {
+    pub name: String,
+    pub schema_type: Option<String>,
+    pub format: Option<String>,
+    pub description: Option<String>,
+    pub required: bool,
+    pub nullable: bool,
+    /// object のプロパティ、または array 要素のプロパティ
+    pub properties: Vec<OpenapiProperty>,
+    /// `example`・`examples` とレスポンスの example に書かれた値（スカラーのみ。書かれた順）
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub examples: Vec<Literal>,
+    /// `enum` の値
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub enum_values: Vec<Literal>,
+}
+
+/// OpenAPI のリクエストパラメータ定義
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct OpenapiParameter {
+    pub name: String,
+    /// `in`（path・query・header・cookie）
+    pub location: Option<String>,
+    pub required: bool,
+    pub schema_type: Option<String>,
+    pub format: Option<String>,
+    pub description: Option<String>,
+    /// schema の default（配列・オブジェクトは除く）
+    pub default: Option<Literal>,
+}
+
+/// import 参照先のファイル内容を取得する
+///
+/// ファイルシステム以外（エディタのバッファ、リモート URL など）から
+/// スキーマを読む場合はこのトレイトを実装する
+pub trait Resolver {
+    fn load(&self, path: &str) -> Result<String, ResolverError>;
+
+    /// 読み込む前に分かる場合はファイルのサイズ（バイト）。[`Limits::load`] が巨大なファイルを読まずに済む
+    fn size(&self, _path: &str) -> Option<u64> {
+        None
+    }
+}
+
+/// ローカルファイルシステムから読み込む Resolver
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FsResolver;
+
+impl Resolver for FsResolver {
+    fn load(&self, path: &str) -> Result<String, ResolverError> {
+        // URL（`x://` のようなディレクトリ名も）をファイルとして読まない
+        if path.contains("://") {
+            return Err(ResolverError::Io {
+                file: path.to_string(),
+                source: std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "URL はファイルシステムから読み込めません",
+                ),
+            });
+        }
+        fs::read_to_string(path).map_err(|e| ResolverError::Io {
+            file: path.to_string(),
+            source: e,
+        })
+    }
+
+    fn size(&self, path: &str) -> Option<u64> {
+        if path.contains("://") {
+            return None;
+        }
+        fs::metadata(path).ok().map(|metadata| metadata.len())
+    }
+}
+
+/// 読み込めるディレクトリを制限する Resolver（`usml --allow-paths`）
+///
+/// 信頼できないブランチの仕様を共有のランナーで検証する場合に、`../../..` で作業ディレクトリの外の
+/// ファイルを読ませないために使う。シンボリックリンクは辿った先のパスで判定する。`http`・`https` の URL の import は対象外
+pub struct SandboxResolver<R> {
+    inner: R,
+    allowed: Vec<PathBuf>,
+}
+
+impl<R: Resolver> SandboxResolver<R> {
+    /// `allowed` のディレクトリ（以下）のファイルだけを `inner` で読み込む
+    pub fn new(inner: R, allowed: impl IntoIterator<Item = impl AsRef<Path>>) -> Self {
+        let allowed = allowed
+            .into_iter()
+            .map(|dir| absolute(dir.as_ref()))
+            .collect();
+        SandboxResolver { inner, allowed }
+    }
+
+    fn check(&self, path: &str) -> Result<(), ResolverError> {
+        if is_remote(path) {
+            return Ok(());
+        }
+        let target = absolute(Path::new(path));
+        if self.allowed.iter().any(|dir| target.starts_with(dir)) {
+            return Ok(());
+        }
+        Err(ResolverError::NotAllowed {
+            file: path.to_string(),
+            allowed: self
+                .allowed
+                .iter()
+                .map(|dir| dir.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+        })
+    }
+}
+
+impl<R: Resolver> Resolver for SandboxResolver<R> {
+    fn load(&self, path: &str) -> Result<String, ResolverError> {
+        self.check(path)?;
+        self.inner.load(path)
+    }
+
+    fn size(&self, path: &str) -> Option<u64> {
+        self.check(path).ok()?;
+        self.inner.size(path)
+    }
+}
+
+/// 絶対パスにする。存在するパスはシンボリックリンクを解決し、存在しなければ `..` を字句的に畳む
+fn absolute(path: &Path) -> PathBuf {
+    if let Ok(path) = fs::canonicalize(path) {
+        return path;
+    }
+    let joined = std::env::current_dir().unwrap_or_default().join(path);
+    let mut normalized = PathBuf::new();
+    for component in joined.components() {
+        match component {
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::CurDir => {}
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// リモートの Resolver で読み込む URL（`http://`・`https://`）か
+pub fn is_remote(path: &str) -> bool {
+    ["http://", "https://"].iter().any(|scheme| {
+        path.get(..scheme.len())
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(scheme))
+    })
+}
+
+/// import 参照のファイル部分を基準ディレクトリから解決する
+/// URL（`https://...` など）はそのまま返す
+pub fn join_import_path(base_dir: &str, file: &str) -> String {
+    if file.contains("://") {
+        file.to_string()
+    } else {
+        Path::new(base_dir).join(file).to_string_lossy().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sandbox_resolver_rejects_paths_outside_allowed_dirs() {
+        let root = std::env::temp_dir().join("usml_test_sandbox");
+        fs::create_dir_all(root.join("specs")).unwrap();
+        fs::write(root.join("specs/schema.dbml"), "Table users {}\n").unwrap();
+        fs::write(root.join("secret.dbml"), "Table secrets {}\n").unwrap();
+
+        let resolver = SandboxResolver::new(FsResolver, [root.join("specs")]);
+        let inside = root.join("specs/./schema.dbml");
+        assert!(resolver.load(inside.to_str().unwrap()).is_ok());
+
+        let outside = root.join("specs/../secret.dbml");
+        let err = resolver.load(outside.to_str().unwrap()).unwrap_err();
+        assert!(matches!(err, ResolverError::NotAllowed { .. }));
+        assert_eq!(resolver.size(outside.to_str().unwrap()), None);
+        // 存在しないファイルも字句的に判定する
+        let missing = root.join("specs/../../missing.dbml");
+        assert!(matches!(
+            resolver.load(missing.to_str().unwrap()),
+            Err(ResolverError::NotAllowed { .. })
+        ));
+    }
+
+    #[test]
+    fn test_sandbox_resolver_only_exempts_http_urls() {
+        struct Remote;
+
+        impl Resolver for Remote {
+            fn load(&self, _path: &str) -> Result<String, ResolverError> {
+                Ok(String::new())
+            }
+        }
+
+        let cwd = std::env::current_dir().unwrap();
+        let resolver = SandboxResolver::new(Remote, [&cwd]);
+        assert!(resolver.load("https://example.com/api.yaml").is_ok());
+        assert!(resolver.load("HTTP://example.com/api.yaml").is_ok());
+        // `x:` というディレクトリを通って作業ディレクトリの外を指すパス
+        assert!(matches!(
+            resolver.load("x://../../secret.dbml"),
+            Err(ResolverError::NotAllowed { .. })
+        ));
+        assert!(matches!(
+            FsResolver.load("x://schema.dbml"),
+            Err(ResolverError::Io { .. })
+        ));
+    }
 }
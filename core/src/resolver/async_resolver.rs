@@ -0,0 +1,185 @@
+use std::future::Future;
+use std::time::Duration;
+
+use futures::future::join_all;
+
+use super::{FsResolver, Resolver, ResolverError};
+use crate::ast::UsmlDocument;
+use crate::diagnostic::Diagnostic;
+use crate::validator::{self, ResolveContext};
+
+/// import 参照先のファイル内容を非同期に取得する
+///
+/// `usml serve` や LSP、リモート URL の import から使う。
+/// 複数の import は `resolve_imports_async` で並行に読み込まれる
+pub trait AsyncResolver: Sync {
+    fn load(&self, path: &str) -> impl Future<Output = Result<String, ResolverError>> + Send;
+}
+
+/// tokio でローカルファイルシステムから読み込む AsyncResolver
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TokioFsResolver;
+
+impl AsyncResolver for TokioFsResolver {
+    async fn load(&self, path: &str) -> Result<String, ResolverError> {
+        if path.contains("://") {
+            return FsResolver.load(path);
+        }
+        tokio::fs::read_to_string(path)
+            .await
+            .map_err(|e| ResolverError::Io {
+                file: path.to_string(),
+                source: e,
+            })
+    }
+}
+
+/// 読み込みに時間の上限を付ける AsyncResolver（`[limits] fetch_timeout_secs`）
+///
+/// リモートの import が応答しない場合も、上限の時間で `ResolverError::Timeout` にして先に進む
+pub struct TimeoutResolver<R> {
+    inner: R,
+    timeout: Duration,
+}
+
+impl<R: AsyncResolver> TimeoutResolver<R> {
+    pub fn new(inner: R, timeout: Duration) -> Self {
+        TimeoutResolver { inner, timeout }
+    }
+}
+
+impl<R: AsyncResolver> AsyncResolver for TimeoutResolver<R> {
+    async fn load(&self, path: &str) -> Result<String, ResolverError> {
+        tokio::time::timeout(self.timeout, self.inner.load(path))
+            .await
+            .unwrap_or_else(|_| {
+                Err(ResolverError::Timeout {
+                    file: path.to_string(),
+                    seconds: self.timeout.as_secs(),
+                })
+            })
+    }
+}
+
+/// AsyncResolver を同期の Resolver として使うためのアダプタ
+///
+/// 同期 API（CLI など）からは内部のランタイムで block_on して待つ
+pub struct BlockingResolver<R> {
+    inner: R,
+    runtime: tokio::runtime::Runtime,
+}
+
+impl<R: AsyncResolver> BlockingResolver<R> {
+    pub fn new(inner: R) -> std::io::Result<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        Ok(BlockingResolver { inner, runtime })
+    }
+}
+
+impl<R: AsyncResolver> Resolver for BlockingResolver<R> {
+    fn load(&self, path: &str) -> Result<String, ResolverError> {
+        self.runtime.block_on(self.inner.load(path))
+    }
+}
+
+/// import 宣言を非同期に解決する。全ファイルの読み込みを並行に待つ
+pub async fn resolve_imports_async<R: AsyncResolver + ?Sized>(
+    doc: &UsmlDocument,
+    base_dir: &str,
+    resolver: &R,
+) -> (ResolveContext, Vec<Diagnostic>) {
+    let plan = validator::plan_imports(doc, base_dir);
+
+    let openapi = async {
+        match &plan.openapi {
+            Some((file, ..)) => Some(resolver.load(file).await),
+            None => None,
+        }
+    };
+    let dbml = join_all(plan.dbml_files.iter().map(|file| resolver.load(file)));
+    let (openapi_content, dbml_contents) = futures::join!(openapi, dbml);
+
+    validator::build_context(&plan, openapi_content, dbml_contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+
+    fn write_fixture(dir_name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(dir_name);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("schema.dbml"),
+            "Table users {\n    id integer [pk]\n    name varchar\n}\n",
+        )
+        .unwrap();
+        dir
+    }
+
+    const DOC: &str = r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: phone
+      source: users.phone
+"#;
+
+    #[test]
+    fn test_validate_with_resolve_async() {
+        let dir = write_fixture("usml_test_async_resolver");
+        let doc = parser::parse(DOC).unwrap();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let diagnostics = runtime.block_on(validator::validate_with_resolve_async(
+            &doc,
+            dir.to_str().unwrap(),
+            &TokioFsResolver,
+        ));
+        assert!(
+            diagnostics
+                .iter()
+                .any(|d| d.rule == "response_mapping.source")
+        );
+    }
+
+    #[test]
+    fn test_timeout_resolver() {
+        struct SlowResolver;
+
+        impl AsyncResolver for SlowResolver {
+            async fn load(&self, _path: &str) -> Result<String, ResolverError> {
+                tokio::time::sleep(Duration::from_secs(5)).await;
+                Ok(String::new())
+            }
+        }
+
+        let resolver = BlockingResolver::new(TimeoutResolver::new(
+            SlowResolver,
+            Duration::from_millis(10),
+        ))
+        .unwrap();
+        let err = resolver.load("https://example.com/api.yaml").unwrap_err();
+        assert!(err.is_limit());
+        assert_eq!(err.file(), Some("https://example.com/api.yaml"));
+    }
+
+    #[test]
+    fn test_blocking_resolver_matches_sync_path() {
+        let dir = write_fixture("usml_test_blocking_resolver");
+        let doc = parser::parse(DOC).unwrap();
+        let resolver = BlockingResolver::new(TokioFsResolver).unwrap();
+        let blocking = validator::validate_with_resolver(&doc, dir.to_str().unwrap(), &resolver);
+        let sync = validator::validate_with_resolve(&doc, dir.to_str().unwrap());
+        assert_eq!(blocking, sync);
+    }
+}
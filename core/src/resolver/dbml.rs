@@ -1,20 +1,43 @@
-use std::fs;
+#[cfg(feature = "resolver-dbml")]
+use std::collections::HashMap;
 
-use super::{DbmlTable, ResolverError};
+#[cfg(feature = "resolver-dbml")]
+use pest::error::{ErrorVariant, LineColLocation};
+
+#[cfg(feature = "resolver-dbml")]
+use super::DbmlColumn;
+#[cfg(feature = "resolver-dbml")]
+use super::DbmlParseCause;
+use super::{DbmlTable, FsResolver, Resolver, ResolverError};
+#[cfg(feature = "resolver-dbml")]
+use crate::diagnostic::Location;
 
 /// DBML ファイルを読み込み、テーブル・カラム情報を抽出する
 pub fn resolve_dbml(file_path: &str) -> Result<Vec<DbmlTable>, ResolverError> {
-    let content = fs::read_to_string(file_path)
-        .map_err(|e| ResolverError::IoError(file_path.to_string(), e))?;
+    let content = FsResolver.load(file_path)?;
 
     parse_dbml_content(&content, file_path)
 }
 
 /// DBML 文字列をパースしてテーブル情報を抽出する
+#[cfg(feature = "resolver-dbml")]
 pub fn parse_dbml_content(content: &str, source: &str) -> Result<Vec<DbmlTable>, ResolverError> {
-    let ast = dbml_rs::parse_dbml(content)
-        .map_err(|e| ResolverError::DbmlParseError(source.to_string(), format!("{:?}", e)))?;
+    let ast = dbml_rs::parse_dbml_unchecked(content).map_err(|e| into_parse_error(e, source))?;
+    // 意味解析（重複・参照先の検査）は panic を避けた入力で行い、テーブル情報は元の AST から取る
+    let analyzable = analyzable_content(content, &ast, source)?;
+    dbml_rs::parse_dbml(analyzable.as_deref().unwrap_or(content))
+        .map_err(|e| into_parse_error(e, source))?;
 
+    let enums: HashMap<&str, Vec<String>> = ast
+        .enums()
+        .into_iter()
+        .map(|e| {
+            (
+                e.ident.name.to_string.as_str(),
+                e.values.iter().map(|v| v.value.to_string.clone()).collect(),
+            )
+        })
+        .collect();
     let mut tables = Vec::new();
 
     for table in ast.tables() {
@@ -23,15 +46,144 @@ pub fn parse_dbml_content(content: &str, source: &str) -> Result<Vec<DbmlTable>,
             .iter()
             .map(|c| c.name.to_string.clone())
             .collect();
+        let column_details = table
+            .cols
+            .iter()
+            .map(|col| DbmlColumn {
+                location: Some(Location::at_offset(
+                    source,
+                    content,
+                    col.name.span_range.start,
+                )),
+                ..column_detail(col, &enums)
+            })
+            .collect();
         tables.push(DbmlTable {
             name: table.ident.name.to_string.clone(),
             columns,
+            column_details,
+            location: Some(Location::at_offset(
+                source,
+                content,
+                table.ident.name.span_range.start,
+            )),
         });
     }
 
     Ok(tables)
 }
 
+/// dbml-rs の意味解析が panic しないよう、該当する列の型名を置き換えた DBML を返す
+///
+/// dbml-rs は長さ指定の無い bit・char・varbit・varchar に文字列の default があると、
+/// 存在しない長さ引数を添字で読んで panic する（wasm では trap になる）。
+/// 型名を同じバイト数の `text`（`bit` は `xml`）に置き換えるため、エラー位置はずれない。
+/// 置き換える列が無ければ None を返す
+#[cfg(feature = "resolver-dbml")]
+fn analyzable_content(
+    content: &str,
+    ast: &dbml_rs::ast::SchemaBlock,
+    source: &str,
+) -> Result<Option<String>, ResolverError> {
+    use dbml_rs::ast::{ColumnTypeName, Value};
+
+    let mut patched: Option<String> = None;
+    for col in ast.tables().into_iter().flat_map(|t| &t.cols) {
+        let ColumnTypeName::Raw(name) = &col.r#type.type_name else {
+            continue;
+        };
+        let sized = matches!(
+            name.parse(),
+            Ok(ColumnTypeName::Bit
+                | ColumnTypeName::Char
+                | ColumnTypeName::Varbit
+                | ColumnTypeName::VarChar)
+        );
+        let string_default = col
+            .settings
+            .as_ref()
+            .is_some_and(|s| matches!(s.default, Some(Value::String(_))));
+        if !sized || !col.r#type.args.is_empty() || !string_default {
+            continue;
+        }
+        // 引用符付きの型（`"varchar"`）は span が引用符から始まる
+        let span = col.r#type.span_range.start;
+        let start = span + usize::from(content[span..].starts_with('"'));
+        if !content[start..].starts_with(name.as_str()) {
+            return Err(ResolverError::DbmlParse {
+                location: Location::at_offset(source, content, span),
+                cause: DbmlParseCause::Semantic(format!(
+                    "文字列の default を持つ {} には長さを指定してください",
+                    name
+                )),
+            });
+        }
+        let replacement = if name.len() < 4 {
+            "xml".to_string()
+        } else {
+            format!("{:<width$}", "text", width = name.len())
+        };
+        patched
+            .get_or_insert_with(|| content.to_string())
+            .replace_range(start..start + name.len(), &replacement);
+    }
+    Ok(patched)
+}
+
+#[cfg(feature = "resolver-dbml")]
+fn column_detail(
+    col: &dbml_rs::ast::TableColumn,
+    enums: &HashMap<&str, Vec<String>>,
+) -> DbmlColumn {
+    use dbml_rs::ast::Nullable;
+
+    // スキーマ付きの型（`auth.user_status`）は名前部分で enum を探す
+    let type_name = col.r#type.raw.rsplit('.').next().unwrap_or_default();
+    let mut column = DbmlColumn {
+        name: col.name.to_string.clone(),
+        data_type: col.r#type.raw.clone(),
+        enum_values: enums
+            .get(type_name.trim_matches('"'))
+            .cloned()
+            .unwrap_or_default(),
+        ..DbmlColumn::default()
+    };
+    if let Some(settings) = &col.settings {
+        column.primary_key = settings.is_pk;
+        column.unique = settings.is_unique;
+        column.nullable = settings.nullable.as_ref().map(|n| *n == Nullable::Null);
+        column.increment = settings.is_incremental;
+        column.default = settings.default.as_ref().map(|v| v.to_string());
+        column.note = settings.note.clone();
+    }
+    column
+}
+
+/// `resolver-dbml` feature が無効な場合は解決できないことをエラーで返す
+#[cfg(not(feature = "resolver-dbml"))]
+pub fn parse_dbml_content(_content: &str, _source: &str) -> Result<Vec<DbmlTable>, ResolverError> {
+    Err(ResolverError::FeatureDisabled("resolver-dbml"))
+}
+
+/// dbml-rs (pest) のエラーを位置情報付きの ResolverError に変換する
+#[cfg(feature = "resolver-dbml")]
+fn into_parse_error<R: pest::RuleType>(err: pest::error::Error<R>, source: &str) -> ResolverError {
+    let (line, column) = match err.line_col {
+        LineColLocation::Pos(pos) => pos,
+        LineColLocation::Span(start, _) => start,
+    };
+    let cause = match err.variant {
+        ErrorVariant::ParsingError { positives, .. } => {
+            DbmlParseCause::Syntax(positives.iter().map(|r| format!("{:?}", r)).collect())
+        }
+        ErrorVariant::CustomError { message } => DbmlParseCause::Semantic(message),
+    };
+    ResolverError::DbmlParse {
+        location: Location::at(source, line, column),
+        cause,
+    }
+}
+
 /// DBML import 参照文字列から対象テーブル名を抽出する
 /// 例: `./schema.dbml#tables["users"]` → `("./schema.dbml", "users")`
 pub fn parse_dbml_ref(reference: &str) -> Option<(&str, &str)> {
@@ -66,6 +218,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "resolver-dbml")]
     fn test_parse_dbml_content_basic() {
         let dbml = r#"
 Project test_db {
@@ -103,6 +256,57 @@ Table profiles {
     }
 
     #[test]
+    #[cfg(feature = "resolver-dbml")]
+    fn test_parse_dbml_content_column_details() {
+        let dbml = r#"
+Table users {
+    id integer [pk, increment]
+    email varchar(255) [unique, not null, note: 'ログイン用']
+    created_at timestamp [default: `now()`]
+}
+"#;
+        let tables = parse_dbml_content(dbml, "test.dbml").unwrap();
+        let users = &tables[0];
+
+        let id = users.column("id").unwrap();
+        assert!(id.primary_key && id.increment);
+        assert_eq!(id.data_type, "integer");
+
+        let email = users.column("email").unwrap();
+        assert_eq!(email.data_type, "varchar(255)");
+        assert!(email.unique);
+        assert_eq!(email.nullable, Some(false));
+        assert_eq!(email.note.as_deref(), Some("ログイン用"));
+
+        let created_at = users.column("created_at").unwrap();
+        assert_eq!(created_at.default.as_deref(), Some("now()"));
+        assert_eq!(created_at.nullable, None);
+        assert!(created_at.enum_values.is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "resolver-dbml")]
+    fn test_parse_dbml_content_enum_values() {
+        let dbml = r#"
+Enum user_status {
+    active
+    suspended
+    "on hold"
+}
+
+Table users {
+    id integer [pk]
+    status user_status [not null]
+}
+"#;
+        let tables = parse_dbml_content(dbml, "test.dbml").unwrap();
+        let status = tables[0].column("status").unwrap();
+        assert_eq!(status.data_type, "user_status");
+        assert_eq!(status.enum_values, vec!["active", "suspended", "on hold"]);
+    }
+
+    #[test]
+    #[cfg(feature = "resolver-dbml")]
     fn test_parse_dbml_content_with_relations() {
         let dbml = r#"
 Project test_db {
@@ -141,9 +345,87 @@ Table likes {
 
         let posts = tables.iter().find(|t| t.name == "posts").unwrap();
         assert!(posts.columns.contains(&"status".to_string()));
+        // 定義へのジャンプに使うテーブル名・カラム名の位置
+        let location = posts.location.as_ref().unwrap();
+        assert_eq!(location.file, "test.dbml");
+        assert_eq!(
+            (location.line, location.column),
+            (dbml_line(dbml, "Table posts"), Some(7))
+        );
+        let status = posts.column("status").unwrap().location.as_ref().unwrap();
+        assert_eq!(
+            (status.line, status.column),
+            (dbml_line(dbml, "    status"), Some(5))
+        );
 
         let comments = tables.iter().find(|t| t.name == "comments").unwrap();
         assert!(comments.columns.contains(&"post_id".to_string()));
         assert!(comments.columns.contains(&"user_id".to_string()));
     }
+
+    /// `prefix` で始まる行の行番号（1 始まり）
+    #[cfg(feature = "resolver-dbml")]
+    fn dbml_line(content: &str, prefix: &str) -> Option<usize> {
+        content
+            .lines()
+            .position(|line| line.starts_with(prefix))
+            .map(|i| i + 1)
+    }
+
+    #[test]
+    #[cfg(feature = "resolver-dbml")]
+    fn test_parse_dbml_content_syntax_error_has_location() {
+        let dbml = "Table users {\n    id integer [pk]\n    name varchar [not null\n}\n";
+        let err = parse_dbml_content(dbml, "schema.dbml").unwrap_err();
+        match err {
+            ResolverError::DbmlParse { location, cause } => {
+                assert_eq!(location.file, "schema.dbml");
+                assert_eq!(location.line, Some(3));
+                assert!(matches!(cause, DbmlParseCause::Syntax(_)));
+            }
+            other => panic!("DbmlParse エラーを期待しました: {:?}", other),
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "resolver-dbml")]
+    fn test_parse_dbml_content_semantic_error() {
+        let dbml = "Table posts {\n    id integer [pk]\n    user_id integer [ref: > users.id]\n}\n";
+        let err = parse_dbml_content(dbml, "schema.dbml").unwrap_err();
+        assert!(matches!(
+            err,
+            ResolverError::DbmlParse {
+                cause: DbmlParseCause::Semantic(_),
+                ..
+            }
+        ));
+        assert!(err.location().and_then(|l| l.line).is_some());
+    }
+
+    #[test]
+    #[cfg(feature = "resolver-dbml")]
+    fn test_parse_dbml_content_string_default_without_length() {
+        let dbml = "Table users {\n    code bit [default: '1']\n    status varchar [default: 'active']\n    label \"char\" [default: 'a']\n}\n";
+        let tables = parse_dbml_content(dbml, "schema.dbml").unwrap();
+        let status = &tables[0].column_details[1];
+        assert_eq!(status.data_type, "varchar");
+        assert_eq!(status.default.as_deref(), Some("active"));
+        assert_eq!(tables[0].columns, ["code", "status", "label"]);
+        assert_eq!(tables[0].column_details[0].data_type, "bit");
+    }
+
+    #[test]
+    #[cfg(feature = "resolver-dbml")]
+    fn test_parse_dbml_content_string_default_keeps_semantic_errors() {
+        let dbml = "Table posts {\n    status varchar [default: 'draft']\n    user_id integer [ref: > users.id]\n}\n";
+        let err = parse_dbml_content(dbml, "schema.dbml").unwrap_err();
+        assert!(matches!(
+            err,
+            ResolverError::DbmlParse {
+                cause: DbmlParseCause::Semantic(_),
+                ..
+            }
+        ));
+        assert_eq!(err.location().and_then(|l| l.line), Some(3));
+    }
 }
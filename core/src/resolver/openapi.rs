@@ -1,6 +1,14 @@
-use std::fs;
-
-use super::{OpenapiResponse, ResolverError};
+use super::{FsResolver, Limits, OpenapiResponse, Resolver, ResolverError};
+#[cfg(feature = "resolver-openapi")]
+use super::{OpenapiParameter, OpenapiProperty};
+#[cfg(feature = "resolver-openapi")]
+use crate::ast::Literal;
+#[cfg(feature = "resolver-openapi")]
+use crate::diagnostic::Location;
+#[cfg(feature = "resolver-openapi")]
+use crate::syntax::{ScalarStyle, SyntaxKind, SyntaxNode, SyntaxTree};
+#[cfg(feature = "resolver-openapi")]
+use openapi3_parser::open_api::Schema;
 
 pub fn resolve_openapi(
     file_path: &str,
@@ -8,8 +16,7 @@ pub fn resolve_openapi(
     method: &str,
     status_code: &str,
 ) -> Result<OpenapiResponse, ResolverError> {
-    let content = fs::read_to_string(file_path)
-        .map_err(|e| ResolverError::IoError(file_path.to_string(), e))?;
+    let content = FsResolver.load(file_path)?;
 
     parse_openapi_content(&content, file_path, path, method, status_code)
 }
@@ -21,8 +28,27 @@ pub fn parse_openapi_content(
     method: &str,
     status_code: &str,
 ) -> Result<OpenapiResponse, ResolverError> {
-    let spec: openapi3_parser::open_api::OpenApiSpec = serde_yaml::from_str(content)
-        .map_err(|e| ResolverError::OpenapiParseError(source.to_string(), format!("{}", e)))?;
+    parse_openapi_content_with_limits(
+        content,
+        source,
+        path,
+        method,
+        status_code,
+        &Limits::default(),
+    )
+}
+
+/// `parse_openapi_content` と同じ。レスポンススキーマの入れ子が `limits.max_schema_depth` を超えるとエラーにする
+#[cfg(feature = "resolver-openapi")]
+pub fn parse_openapi_content_with_limits(
+    content: &str,
+    source: &str,
+    path: &str,
+    method: &str,
+    status_code: &str,
+    limits: &Limits,
+) -> Result<OpenapiResponse, ResolverError> {
+    let spec = parse_spec(content, source)?;
 
     let paths = spec.paths.as_ref().ok_or_else(|| {
         ResolverError::NotFound("OpenAPI に paths が定義されていません".to_string())
@@ -53,11 +79,26 @@ pub fn parse_openapi_content(
         ))
     })?;
 
-    let parameters: Vec<String> = operation
+    let parameter_details: Vec<OpenapiParameter> = operation
         .parameters
-        .as_ref()
-        .map(|params| params.iter().filter_map(|p| p.name.clone()).collect())
-        .unwrap_or_default();
+        .iter()
+        .flatten()
+        .filter_map(|p| {
+            let schema = p.schema.as_ref();
+            Some(OpenapiParameter {
+                name: p.name.clone()?,
+                location: p.in_.clone(),
+                required: p.required.unwrap_or(false),
+                schema_type: schema.and_then(|s| s.type_.clone()),
+                format: schema.and_then(|s| s.format.clone()),
+                description: p.description.clone(),
+                default: schema
+                    .and_then(|s| serde_yaml::to_value(s.default.as_ref()?).ok())
+                    .and_then(|v| serde_yaml::from_value(v).ok()),
+            })
+        })
+        .collect();
+    let parameters = parameter_details.iter().map(|p| p.name.clone()).collect();
 
     let responses = operation.responses.as_ref().ok_or_else(|| {
         ResolverError::NotFound(format!(
@@ -80,29 +121,266 @@ pub fn parse_openapi_content(
         ))
     })?;
 
-    let fields = extract_response_fields(response);
+    let schema = response_schema(response).filter(|s| s.type_.as_deref() == Some("object"));
+    // properties は HashMap なので、出力を安定させるため名前順に並べる
+    let mut fields: Vec<String> = schema
+        .and_then(|s| s.properties.as_ref())
+        .map(|props| props.keys().cloned().collect())
+        .unwrap_or_default();
+    fields.sort();
+    let mut properties = match schema {
+        Some(schema) => extract_properties(schema, limits.max_schema_depth).ok_or_else(|| {
+            ResolverError::TooDeep {
+                location: Location::file(source),
+                limit: limits.max_schema_depth,
+            }
+        })?,
+        None => Vec::new(),
+    };
+    attach_samples(content, path, method, status_code, &mut properties);
 
-    Ok(OpenapiResponse { fields, parameters })
+    Ok(OpenapiResponse {
+        fields,
+        parameters,
+        properties,
+        parameter_details,
+        field_order: field_order(content, path, method, status_code),
+        location: operation_location(content, source, path, method),
+    })
 }
 
-fn extract_response_fields(response: &openapi3_parser::open_api::Response) -> Vec<String> {
-    if let Some(content) = &response.content
-        && let Some(media_type) = content.get("application/json")
-        && let Some(schema) = &media_type.schema
+/// レスポンススキーマのプロパティのフルパスを書かれた順に返す（`properties` は名前順のため構文ツリーから取る）
+#[cfg(feature = "resolver-openapi")]
+fn field_order(content: &str, path: &str, method: &str, status_code: &str) -> Vec<String> {
+    let mut order = Vec::new();
+    if let Ok(tree) = SyntaxTree::parse(content)
+        && let Some(schema) = tree.lookup(&[
+            "paths",
+            path,
+            method,
+            "responses",
+            status_code,
+            "content",
+            "application/json",
+            "schema",
+        ])
     {
-        return extract_fields_from_schema(schema);
+        collect_field_order(schema, "", &mut order);
     }
-    Vec::new()
+    order
 }
 
-fn extract_fields_from_schema(schema: &openapi3_parser::open_api::Schema) -> Vec<String> {
-    if let Some(type_str) = &schema.type_
-        && type_str == "object"
-        && let Some(props) = &schema.properties
-    {
-        return props.keys().cloned().collect();
+#[cfg(feature = "resolver-openapi")]
+fn collect_field_order(schema: &SyntaxNode, prefix: &str, order: &mut Vec<String>) {
+    let schema = schema.get("items").unwrap_or(schema);
+    let Some(properties) = schema.get("properties") else {
+        return;
+    };
+    for entry in properties.entries() {
+        let path = if prefix.is_empty() {
+            entry.key.clone()
+        } else {
+            format!("{}.{}", prefix, entry.key)
+        };
+        order.push(path.clone());
+        collect_field_order(&entry.value, &path, order);
     }
-    Vec::new()
+}
+
+/// プロパティの example・enum を構文ツリーから取る（openapi3-parser の Schema は `enum`・`examples` を持たない）
+///
+/// レスポンスの `example`・`examples` に書かれたペイロードの値もそのプロパティの example にする
+#[cfg(feature = "resolver-openapi")]
+fn attach_samples(
+    content: &str,
+    path: &str,
+    method: &str,
+    status_code: &str,
+    properties: &mut [OpenapiProperty],
+) {
+    let Ok(tree) = SyntaxTree::parse(content) else {
+        return;
+    };
+    let Some(media) = tree.lookup(&[
+        "paths",
+        path,
+        method,
+        "responses",
+        status_code,
+        "content",
+        "application/json",
+    ]) else {
+        return;
+    };
+    let Some(schema) = media.get("schema") else {
+        return;
+    };
+    let mut payloads: Vec<&SyntaxNode> = media.get("example").into_iter().collect();
+    if let Some(examples) = media.get("examples") {
+        payloads.extend(
+            examples
+                .entries()
+                .iter()
+                .filter_map(|e| e.value.get("value")),
+        );
+    }
+    collect_samples(schema, &payloads, properties);
+}
+
+#[cfg(feature = "resolver-openapi")]
+fn collect_samples(
+    schema: &SyntaxNode,
+    payloads: &[&SyntaxNode],
+    properties: &mut [OpenapiProperty],
+) {
+    let schema = schema.get("items").unwrap_or(schema);
+    // 配列のペイロードは要素ごとに見る
+    let payloads: Vec<&SyntaxNode> = payloads
+        .iter()
+        .flat_map(|p| match &p.kind {
+            SyntaxKind::Sequence(items) => items.iter().collect(),
+            _ => vec![*p],
+        })
+        .collect();
+    let Some(nodes) = schema.get("properties") else {
+        return;
+    };
+    for property in properties {
+        let Some(node) = nodes.get(&property.name) else {
+            continue;
+        };
+        let values: Vec<&SyntaxNode> = payloads
+            .iter()
+            .filter_map(|p| p.get(&property.name))
+            .collect();
+        let examples = node
+            .get("example")
+            .into_iter()
+            .chain(node.get("examples").map_or(&[][..], SyntaxNode::items))
+            .chain(values.iter().copied())
+            .filter_map(literal);
+        for example in examples {
+            if !property.examples.contains(&example) {
+                property.examples.push(example);
+            }
+        }
+        property.enum_values = node
+            .get("enum")
+            .map_or(&[][..], SyntaxNode::items)
+            .iter()
+            .filter_map(literal)
+            .collect();
+        collect_samples(node, &values, &mut property.properties);
+    }
+}
+
+/// スカラーの値。引用符の無いものは YAML の型（真偽値・数値）で読む。null・配列・オブジェクトは None
+#[cfg(feature = "resolver-openapi")]
+fn literal(node: &SyntaxNode) -> Option<Literal> {
+    match &node.kind {
+        SyntaxKind::Scalar {
+            value,
+            style: ScalarStyle::Plain,
+        } => serde_yaml::from_str(value).ok(),
+        SyntaxKind::Scalar { value, .. } => Some(Literal::String(value.clone())),
+        _ => None,
+    }
+}
+
+/// `paths` のパスの下のメソッドのキーの位置。YAML（JSON）の構文ツリーにできなければ None
+#[cfg(feature = "resolver-openapi")]
+fn operation_location(content: &str, source: &str, path: &str, method: &str) -> Option<Location> {
+    let tree = SyntaxTree::parse(content).ok()?;
+    let entry = tree.lookup(&["paths", path])?.entry(method)?;
+    Some(tree.location(content, source, entry.key_span.start))
+}
+
+/// OpenAPI 文字列が OpenAPI 3 の文書として読めるかを確かめる
+#[cfg(feature = "resolver-openapi")]
+pub fn check_openapi_content(content: &str, source: &str) -> Result<(), ResolverError> {
+    parse_spec(content, source).map(|_| ())
+}
+
+#[cfg(feature = "resolver-openapi")]
+fn parse_spec(
+    content: &str,
+    source: &str,
+) -> Result<openapi3_parser::open_api::OpenApiSpec, ResolverError> {
+    serde_yaml::from_str(content).map_err(|e| {
+        let location = match e.location() {
+            Some(pos) => Location::at(source, pos.line(), pos.column()),
+            None => Location::file(source),
+        };
+        ResolverError::OpenapiParse {
+            location,
+            source: e,
+        }
+    })
+}
+
+#[cfg(feature = "resolver-openapi")]
+fn response_schema(response: &openapi3_parser::open_api::Response) -> Option<&Schema> {
+    response
+        .content
+        .as_ref()?
+        .get("application/json")?
+        .schema
+        .as_ref()
+}
+
+/// object のプロパティ（array の場合は要素のプロパティ）を名前順で取り出す
+///
+/// 入れ子が `depth` 段を超えたら打ち切って None を返す
+#[cfg(feature = "resolver-openapi")]
+fn extract_properties(schema: &Schema, depth: usize) -> Option<Vec<OpenapiProperty>> {
+    let schema = match &schema.items {
+        Some(items) if schema.type_.as_deref() == Some("array") => items,
+        _ => schema,
+    };
+    let Some(props) = &schema.properties else {
+        return Some(Vec::new());
+    };
+    if depth == 0 {
+        return None;
+    }
+    let required = schema.required.as_deref().unwrap_or_default();
+
+    let mut properties = props
+        .iter()
+        .map(|(name, prop)| {
+            Some(OpenapiProperty {
+                name: name.clone(),
+                schema_type: prop.type_.clone(),
+                format: prop.format.clone(),
+                description: prop.description.clone(),
+                required: required.contains(name),
+                nullable: prop.nullable.unwrap_or(false),
+                properties: extract_properties(prop, depth - 1)?,
+                ..OpenapiProperty::default()
+            })
+        })
+        .collect::<Option<Vec<_>>>()?;
+    properties.sort_by(|a, b| a.name.cmp(&b.name));
+    Some(properties)
+}
+
+/// `resolver-openapi` feature が無効な場合は解決できないことをエラーで返す
+#[cfg(not(feature = "resolver-openapi"))]
+pub fn parse_openapi_content_with_limits(
+    _content: &str,
+    _source: &str,
+    _path: &str,
+    _method: &str,
+    _status_code: &str,
+    _limits: &Limits,
+) -> Result<OpenapiResponse, ResolverError> {
+    Err(ResolverError::FeatureDisabled("resolver-openapi"))
+}
+
+/// `resolver-openapi` feature が無効な場合は確かめられないことをエラーで返す
+#[cfg(not(feature = "resolver-openapi"))]
+pub fn check_openapi_content(_content: &str, _source: &str) -> Result<(), ResolverError> {
+    Err(ResolverError::FeatureDisabled("resolver-openapi"))
 }
 
 pub fn parse_openapi_ref(reference: &str) -> Option<(&str, &str, &str, &str)> {
@@ -147,6 +425,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "resolver-openapi")]
     fn test_parse_openapi_content_basic() {
         let yaml = r#"
 openapi: "3.0.0"
@@ -182,6 +461,9 @@ paths:
                     type: string
 "#;
         let result = parse_openapi_content(yaml, "test.yaml", "/users", "get", "200").unwrap();
+        assert_eq!(result.location, Some(Location::at("test.yaml", 8, 5)));
+        // fields は名前順、field_order は書かれた順
+        assert_eq!(result.field_order, ["id", "name", "email"]);
         assert_eq!(result.parameters.len(), 2);
         assert!(result.parameters.contains(&"status".to_string()));
         assert!(result.parameters.contains(&"page".to_string()));
@@ -192,6 +474,127 @@ paths:
     }
 
     #[test]
+    #[cfg(feature = "resolver-openapi")]
+    fn test_parse_openapi_content_property_and_parameter_details() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  title: Test API
+  version: "1.0"
+paths:
+  /posts/{post_id}:
+    get:
+      parameters:
+        - name: post_id
+          in: path
+          required: true
+          description: 投稿 ID
+          schema:
+            type: integer
+            format: int64
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: object
+                required: [title]
+                properties:
+                  title:
+                    type: string
+                    description: タイトル
+                  comments:
+                    type: array
+                    items:
+                      type: object
+                      properties:
+                        body:
+                          type: string
+                          nullable: true
+"#;
+        let result =
+            parse_openapi_content(yaml, "test.yaml", "/posts/{post_id}", "get", "200").unwrap();
+
+        let title = result.property("title").unwrap();
+        assert_eq!(title.schema_type.as_deref(), Some("string"));
+        assert_eq!(title.description.as_deref(), Some("タイトル"));
+        assert!(title.required);
+
+        let body = result.property("comments.body").unwrap();
+        assert!(body.nullable);
+        assert!(!body.required);
+
+        let post_id = result.parameter("post_id").unwrap();
+        assert_eq!(post_id.location.as_deref(), Some("path"));
+        assert_eq!(post_id.format.as_deref(), Some("int64"));
+        assert!(post_id.required);
+    }
+
+    #[test]
+    #[cfg(feature = "resolver-openapi")]
+    fn test_parse_openapi_content_examples_and_enums() {
+        let yaml = r#"
+openapi: "3.0.0"
+info:
+  title: Test API
+  version: "1.0"
+paths:
+  /posts:
+    get:
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: object
+                properties:
+                  status:
+                    type: string
+                    enum: [draft, published]
+                  items:
+                    type: array
+                    items:
+                      type: object
+                      properties:
+                        title:
+                          type: string
+                          example: はじめての投稿
+                        views:
+                          type: integer
+                          examples: [120, 3]
+              example:
+                status: published
+                items:
+                  - title: 2 つ目の投稿
+                    views: 120
+"#;
+        let result = parse_openapi_content(yaml, "test.yaml", "/posts", "get", "200").unwrap();
+
+        let status = result.property("status").unwrap();
+        assert_eq!(
+            status.enum_values,
+            [
+                Literal::String("draft".to_string()),
+                Literal::String("published".to_string())
+            ]
+        );
+        assert_eq!(status.examples, [Literal::String("published".to_string())]);
+        let title = result.property("items.title").unwrap();
+        assert_eq!(
+            title.examples,
+            [
+                Literal::String("はじめての投稿".to_string()),
+                Literal::String("2 つ目の投稿".to_string())
+            ]
+        );
+        let views = result.property("items.views").unwrap();
+        assert_eq!(views.examples, [Literal::Integer(120), Literal::Integer(3)]);
+    }
+
+    #[test]
+    #[cfg(feature = "resolver-openapi")]
     fn test_parse_openapi_content_path_not_found() {
         let yaml = r#"
 openapi: "3.0.0"
@@ -209,4 +612,15 @@ paths:
         assert!(result.is_err());
         assert!(matches!(result.unwrap_err(), ResolverError::NotFound(_)));
     }
+
+    #[test]
+    #[cfg(feature = "resolver-openapi")]
+    fn test_parse_openapi_content_syntax_error_has_location() {
+        let yaml = "openapi: \"3.0.0\"\npaths:\n  /users:\n    get: [unclosed\n";
+        let err = parse_openapi_content(yaml, "api.yaml", "/users", "get", "200").unwrap_err();
+        let location = err.location().expect("位置情報があるはず");
+        assert_eq!(location.file, "api.yaml");
+        assert!(location.line.is_some());
+        assert!(matches!(err, ResolverError::OpenapiParse { .. }));
+    }
 }
@@ -0,0 +1,712 @@
+//! 規則レジストリから生成する規則のドキュメント（`docs/rules.md`・`usml rules --doc`）
+//!
+//! 規則ごとの説明は `rules` の定義、違反例はここに置く。
+//! `docs/rules.md` は `usml rules --markdown` の出力で、テストで生成結果と一致するかを確かめる
+
+use std::fmt::Write;
+
+use crate::rules::Rule;
+
+/// 規則のドキュメントの公開先。規則ごとのアンカーは `#u012` のようなコードの小文字
+pub const DOCS_URL: &str = "https://github.com/Nenene01/usml/blob/main/docs/rules.md";
+
+/// 全規則のドキュメント（`docs/rules.md`）
+pub fn markdown(rules: &[&Rule]) -> String {
+    let mut out = String::from(
+        "# USML バリデーション規則\n\n\
+<!-- @generated by usml。`usml rules --markdown > docs/rules.md` で生成し直す -->\n\n\
+`usml validate` などの診断に付く規則コードの一覧です。\
+規則ごとの説明は `usml rules --doc <CODE>` でも表示できます。\n\n\
+| コード | 名前 | 重大度 | 内容 |\n|---|---|---|---|\n",
+    );
+    for rule in rules {
+        let _ = writeln!(
+            out,
+            "| [{}](#{}) | `{}` | {} | {} |",
+            rule.code,
+            rule.doc_anchor(),
+            rule.name,
+            rule.severity.as_str(),
+            rule.summary
+        );
+    }
+    for rule in rules {
+        out.push('\n');
+        out.push_str(&section(rule));
+    }
+    out
+}
+
+/// 規則 1 つ分のドキュメント（見出し・分類・重大度・説明・違反例）
+pub fn section(rule: &Rule) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "<a id=\"{}\"></a>", rule.doc_anchor());
+    let _ = writeln!(out, "## {} {}\n", rule.code, rule.name);
+    let _ = writeln!(out, "- 分類: {}", rule.category.as_str());
+    let _ = writeln!(out, "- 重大度: {}\n", rule.severity.as_str());
+    let _ = writeln!(out, "{}\n", rule.summary);
+    if !rule.explanation.is_empty() {
+        let _ = writeln!(out, "{}\n", rule.explanation);
+    }
+    if let Some(example) = example(&rule.code) {
+        let _ = writeln!(out, "### 例\n\n```yaml\n{}```", example);
+    }
+    out
+}
+
+/// 組み込み規則の違反例。`# NG` の行が報告され、`# OK` の行が直し方
+pub fn example(code: &str) -> Option<&'static str> {
+    let example = match code {
+        "U001" => {
+            r#"import:
+  dbml:
+    - ./schema.dbml#tables["users"]
+usecase:
+  response_mapping:
+    - field: title
+      source: posts.title   # NG: posts が import.dbml に無い
+# OK: import.dbml に ./schema.dbml#tables["posts"] を加える
+"#
+        }
+        "U002" => {
+            r#"response_mapping:
+  - field: author_name
+    source: users.name
+    join:
+      table: users
+      on: accounts.id = users.account_id   # NG: accounts が import.dbml に無い
+"#
+        }
+        "U003" => {
+            r#"response_mapping:
+  - field: tags
+    type: array
+    source_table: tags
+    join:
+      table: post_tags
+      on: posts.id = post_tags.post_id
+    join_chain:
+      - table: tags
+        on: tag_links.tag_id = tags.id   # NG: tag_links が import.dbml に無い
+"#
+        }
+        "U004" => {
+            r#"response_mapping:
+  - field: author_name
+    source: users.name
+    join:
+      table: users
+      on: posts.author_id = users.id
+  - field: editor_name
+    source: users.name
+    join:
+      table: users                      # NG: 別の結合条件なのに alias が無い
+      on: posts.editor_id = users.id
+# OK: alias: editor を付け、source: editor.name で参照する
+"#
+        }
+        "U005" => {
+            r#"response_mapping:
+  - field: like_count
+    source: likes.id
+    join:
+      table: likes
+      on: posts.id = likes.post_id
+    aggregate:
+      type: COUNT   # NG: group_by が無い
+# OK: group_by: posts.id
+"#
+        }
+        "U006" => {
+            r#"response_mapping:
+  - field: tags
+    type: array
+    source_table: post_tags   # NG: join_chain の最後のテーブルは tags
+    join:
+      table: post_tags
+      on: posts.id = post_tags.post_id
+    join_chain:
+      - table: tags
+        on: post_tags.tag_id = tags.id
+"#
+        }
+        "U007" => {
+            r#"filters:
+  - param: status
+    maps_to: WHERE
+    condition: users.status = :state   # NG: :state を宣言した param が無い
+# OK: condition: users.status = :status
+"#
+        }
+        "U008" => {
+            r#"filters:
+  - param: sort
+    maps_to: ORDER_BY
+    default_column: users.updated_at   # NG: allowed_columns に無い
+    allowed_columns:
+      - users.created_at
+      - users.name
+"#
+        }
+        "U009" => {
+            r#"response_mapping:
+  - field: display_name
+    source: users.name
+transforms:
+  - target: nickname   # NG: response_mapping に nickname が無い
+    type: COALESCE
+    source: users.nickname
+    fallback: ""
+"#
+        }
+        "U010" => {
+            r#"transforms:
+  - target: email
+    type: MASK
+    source: users.email
+    mask_pattern: "^1***@***.***"
+    condition:
+      - param: viewer_role   # --resolve しないと OpenAPI のパラメータにあるか確認できない
+        operator: "!="
+        value: admin
+"#
+        }
+        "U011" => {
+            r#"# OpenAPI のレスポンスのプロパティは id・name だけ
+response_mapping:
+  - field: display_name   # NG: OpenAPI のレスポンスに無い
+    source: users.name
+"#
+        }
+        "U012" => {
+            r#"# schema.dbml: Table users { id int [pk]  name varchar }
+response_mapping:
+  - field: nickname
+    source: users.nick_name   # NG: DBML の users に nick_name が無い
+"#
+        }
+        "U013" => {
+            r#"# OpenAPI のオペレーションのパラメータは status だけ
+transforms:
+  - target: email
+    type: MASK
+    source: users.email
+    mask_pattern: "^1***@***.***"
+    condition:
+      - param: viewer_role   # NG: OpenAPI のパラメータに無い
+        operator: "!="
+        value: admin
+"#
+        }
+        "U014" => {
+            r#"import:
+  openapi: ./apis.yaml#paths["/users"].get.responses["200"]   # NG: ファイルが無い
+"#
+        }
+        "U015" => {
+            r#"import:
+  dbml:
+    - ./schemas.dbml#tables["users"]   # NG: ファイルが無い、または DBML の構文エラー
+"#
+        }
+        "U016" => {
+            r#"# usml.toml: [lint.naming] fields = "snake_case"
+response_mapping:
+  - field: displayName   # NG: snake_case では display_name
+    source: users.name
+"#
+        }
+        "U017" => {
+            r#"# usml.toml: [lint.naming] params = "camelCase"
+filters:
+  - param: user_status   # NG: camelCase では userStatus
+    maps_to: WHERE
+    condition: users.status = :user_status
+"#
+        }
+        "U018" => {
+            r#"# OpenAPI のレスポンスのプロパティは avatarUrl
+response_mapping:
+  - field: avatar_url   # NG: 表記が OpenAPI と異なる
+    source: profiles.avatar_url
+"#
+        }
+        "U019" => {
+            r#"transforms:
+  - target: display_name
+    type: COALESCE
+    sources:
+      - profiles.display_name
+      - user.name   # NG: user は import.dbml のテーブルでもレスポンスフィールドでもない
+"#
+        }
+        "U020" => {
+            r#"# schema.dbml: Table profiles { user_id int  display_name varchar }
+transforms:
+  - target: display_name
+    type: COALESCE
+    sources:
+      - profiles.nickname   # NG: DBML の profiles に nickname が無い
+      - users.name
+"#
+        }
+        "U021" => {
+            r#"# OpenAPI の body は string、posts.word_count は integer
+transforms:
+  - target: body
+    type: CONDITIONAL_SOURCE
+    condition:
+      - source: posts.status
+        operator: "=="
+        value: draft
+    then_source: posts.word_count   # NG: string のフィールドに integer のカラム
+    else_source: posts.body
+"#
+        }
+        "U022" => {
+            r#"transforms:
+  - target: phone
+    type: MASK
+    source: users.phone
+    mask_pattern: "$4-****"   # NG: $N はパターンの末尾にだけ書ける
+# OK: mask_pattern: "***-****-$4"
+"#
+        }
+        "U023" => {
+            r#"# schema.dbml: users.age int
+transforms:
+  - target: age
+    type: MASK
+    source: users.age   # NG: 文字列型のカラムではない
+    mask_pattern: "**"
+"#
+        }
+        "U024" => {
+            r#"# schema.dbml: enum user_status { active  suspended  deleted }
+transforms:
+  - target: status_label
+    type: CASE
+    source: users.status
+    when:   # NG: deleted を扱っておらず else_value も無い
+      - value: active
+        then: アクティブ
+      - value: suspended
+        then: 停止中
+"#
+        }
+        "U025" => {
+            r#"response_mapping:
+  - field: tag_names
+    source: tags.name
+    aggregate:
+      type: STRING_AGG   # NG: 既定では使えない（[lint] aggregates で許可する）
+      group_by: posts.id
+"#
+        }
+        "U026" => {
+            r#"# schema.dbml: orders.status varchar
+response_mapping:
+  - field: total
+    source: orders.status
+    aggregate:
+      type: SUM   # NG: 数値型のカラムではない
+      group_by: users.id
+"#
+        }
+        "U027" => {
+            r#"response_mapping:
+  - field: items
+    type: array
+    collection: true
+    source_table: posts
+    fields:
+      - field: id
+        source: posts.id
+filters:
+  - param: cursor
+    maps_to: PAGINATION
+    strategy: cursor
+    cursor_field: published_at   # NG: collection の要素に published_at が無い
+"#
+        }
+        "U028" => {
+            r#"response_mapping:
+  - field: items
+    type: array
+    collection: true
+    source_table: posts
+    fields:
+      - field: id
+        source: posts.id
+  - field: next_cursor
+    envelope: next_cursor   # NG: カーソル方式の PAGINATION フィルタが無い
+"#
+        }
+        "U029" => {
+            r#"usecase:
+  limit: 50
+  default_limit: 100   # NG: limit を超えている
+"#
+        }
+        "U030" => {
+            r#"# OpenAPI の name は required かつ nullable でない
+response_mapping:
+  - field: name
+    source: users.name
+    nullable: true   # NG: OpenAPI と矛盾する
+"#
+        }
+        "U031" => {
+            r#"# OpenAPI の avatar_url は required かつ nullable でない
+response_mapping:
+  - field: avatar_url
+    source: profiles.avatar_url   # NG: LEFT JOIN のため NULL になりうる
+    join:
+      table: profiles
+      on: users.id = profiles.user_id
+# OK: COALESCE の transform に fallback を指定する
+"#
+        }
+        "U032" => {
+            r#"response_mapping:
+  - field: api_version
+    value: v2
+    source: users.api_version   # NG: value と source は併記できない
+"#
+        }
+        "U033" => {
+            r#"# OpenAPI の beta は boolean
+response_mapping:
+  - field: beta
+    value: "yes"   # NG: boolean のプロパティに文字列
+"#
+        }
+        "U034" => {
+            r#"usecase:
+  authorization:
+    row_filters:
+      - accounts.owner_id = :current_user_id   # NG: accounts が import.dbml に無い
+"#
+        }
+        "U035" => {
+            r#"usecase:
+  cache:
+    ttl: 300
+    key: [page, locale]   # NG: locale を宣言した filters[].param が無い
+    invalidated_by: [posts]
+"#
+        }
+        "U036" => {
+            r#"response_mapping:
+  - field: badges
+    source_usecase: GetUserBadges
+    source: users.badges   # NG: source_usecase と source は併記できない
+"#
+        }
+        "U037" => {
+            r#"# usml validate --workspace usecases user-detail.usml.yaml
+response_mapping:
+  - field: badges
+    source_usecase: GetUserBadge   # NG: ワークスペースにこの usecase.name が無い
+"#
+        }
+        "U038" => {
+            r#"transforms:
+  - target: email
+    source: users.email   # NG: use が無いのに type が無い
+    mask_pattern: "^1***@***.***"
+# OK: type: MASK を書くか、use: mask_email で共有ライブラリの定義を使う
+"#
+        }
+        "U039" => {
+            r#"# usml.toml: [lint.ownership] teams = ["payments", "core"]  min_reviewers = 1
+usecase:
+  name: 請求一覧取得
+  owner: billing   # NG: teams に無いチーム、reviewers も無い
+"#
+        }
+        "U040" => {
+            r#"# usml.toml: [lint.complexity] max_join_depth = 2
+response_mapping:
+  - field: tag_names
+    type: array
+    source_table: tags
+    join:
+      table: post_tags
+      on: posts.id = post_tags.post_id
+    join_chain:   # NG: 結合の段数が 3
+      - table: tag_aliases
+        on: post_tags.tag_id = tag_aliases.tag_id
+      - table: tags
+        on: tag_aliases.tag_id = tags.id
+    fields:
+      - field: name
+        source: tags.name
+"#
+        }
+        "U041" => {
+            r#"import:
+  dbml:
+    - ./core.dbml#tables["users"]      # Table users { id int  email varchar }
+    - ./billing.dbml#tables["invoices"]  # NG: Table users { id bigint } も定義している
+"#
+        }
+        "U042" => {
+            r#"# usml.toml: [limits] max_file_size = 1048576
+import:
+  openapi: ./api.yaml#paths["/users"].get.responses["200"]  # NG: api.yaml が 1 MiB を超える
+"#
+        }
+        "U043" => {
+            r#"# usml validate --resolve --allow-paths specs specs/users-list.usml.yaml
+import:
+  openapi: ../../shared/api.yaml#paths["/users"].get.responses["200"]  # NG: specs の外
+"#
+        }
+        "U044" => {
+            r#"filters:
+  - param: status
+    maps_to: WHERE
+    condition: users.status = :status
+  - param: status   # NG: WHERE と PAGINATION の両方に使われている
+    maps_to: PAGINATION
+    strategy: offset
+"#
+        }
+        "U045" => {
+            r#"filters:
+  - param: sort
+    maps_to: ORDER_BY
+    allowed_columns: [created_at, name]
+  - param: order   # NG: ORDER_BY が 2 つ目
+    maps_to: ORDER_BY
+    allowed_columns: [id]
+"#
+        }
+        "U046" => {
+            r#"# schema.dbml: Table users { id int  status varchar  created_at timestamp }
+filters:
+  - param: status
+    maps_to: WHERE
+    condition: users.state = :status   # NG: users に state カラムが無い
+  - param: sort
+    maps_to: ORDER_BY
+    default_column: users.create_at   # NG: created_at の誤字
+"#
+        }
+        "U047" => {
+            r#"# schema.dbml: Table users { name varchar  age int }
+# OpenAPI: parameters: - name: min_name  schema: { type: integer }
+filters:
+  - param: min_name
+    maps_to: WHERE
+    condition: users.name >= :min_name   # NG: 文字列のカラムを >= で比較、型の違うパラメータ
+  - param: age
+    maps_to: WHERE
+    condition: users.age LIKE :age   # NG: 数値のカラムに LIKE
+"#
+        }
+        "U048" => {
+            r#"filters:
+  - param: status
+    maps_to: WHERE
+    condition: users.status = :status
+    required: true
+    default: active   # NG: 必須のパラメータに既定値
+"#
+        }
+        "U049" => {
+            r#"# OpenAPI: parameters: - name: status  in: query  required: false
+#                       schema: { type: string, default: active }
+filters:
+  - param: status
+    maps_to: WHERE
+    condition: users.status = :status
+    required: true   # NG: OpenAPI では省略できる
+    default: all     # NG: OpenAPI の default は active
+  - param: user_id   # OpenAPI: in: path  required: true
+    maps_to: WHERE
+    condition: users.id = :user_id
+                     # NG: required: true が無い（省略可能として生成される）
+"#
+        }
+        "U050" => {
+            r#"# schema.dbml: Table posts { id int [pk]  created_at timestamp }
+filters:
+  - param: cursor
+    maps_to: PAGINATION
+    strategy: cursor
+    cursor_field: created_at   # NG: 同じ時刻の投稿があると順序が決まらない
+    # cursor_tiebreaker: id   # OK: (created_at, id) の組で辿る
+"#
+        }
+        "U051" => {
+            r#"response_mapping:
+  - field: post_count
+    source: posts.id
+    join: { table: posts, type: LEFT, on: users.id = posts.user_id }
+    aggregate: { type: COUNT, group_by: users.id, strategy: group_by }   # NG: comments の行数だけ重複して数える
+  - field: comment_count
+    source: comments.id
+    join: { table: comments, type: LEFT, on: users.id = comments.user_id }
+    aggregate: { type: COUNT, group_by: users.id, strategy: group_by }
+# OK: strategy を省略するか subquery にすると、相関サブクエリで数える
+"#
+        }
+        "U052" => {
+            r#"usecase:
+  hydration:
+    strategy: nested     # NG: batch か json
+    dialect: sqlite      # NG: postgresql か mysql
+"#
+        }
+        "U053" => {
+            r#"response_mapping:
+  - field: tags
+    type: array
+    join: { table: post_tags, on: posts.id = post_tags.post_id }
+  - field: comments   # NG: tags と同じ posts.id で別のクエリになる
+    type: array
+    join: { table: comments, on: posts.id = comments.post_id }
+# OK: hydration: { strategy: json } で親のクエリにまとめる
+"#
+        }
+        "U054" => {
+            r#"usecase:
+  output: ../../public/index.html   # NG: 出力ディレクトリの外を指す
+# OK: output: users/list.html
+"#
+        }
+        "U055" => {
+            r#"filters:
+  - param: cursor
+    maps_to: PAGINATION
+    strategy: cursor
+    cursor_field: created_at
+    total_count: window   # NG: カーソルより後の行だけを数える。query にする
+"#
+        }
+        "U056" => {
+            r#"response_mapping:
+  - field: createdAt
+    source: posts.created_at
+    timezone: ":tz"        # OK: パラメータ tz のタイムゾーンで返す
+  - field: birthday
+    source: users.birthday
+    timezone: Asia/Tokyo   # NG: date のカラムには時刻が無い（--resolve 時）
+"#
+        }
+        "U057" => {
+            r#"transforms:
+  - target: price_usd
+    type: CONVERT
+    source: orders.amount
+    lookup:
+      table: exchange_rates
+      on: orders.currency = exchange_rates.currency
+      rate: exchange_rates.rate
+    rounding: half_even
+    # NG: scale が無い。scale: 2 のように出力の桁数を宣言する
+"#
+        }
+        "U058" => {
+            r#"transforms:
+  - target: name
+    type: LOCALIZE
+    source: product_translations.name
+    locale:
+      on: products.id = product_translations.product_id
+      column: product_translations.locale
+      param: locale
+      fallback: [en, en]   # NG: fallback の言語が重複している
+"#
+        }
+        "U059" => {
+            r#"environments:
+  stg:
+    import:
+      dbml:
+        - ./schema.stg.dbml#tables["stg_users"]
+      tables:
+        users: stg_users       # OK: DB 上のテーブル名だけを変える
+    usecase:                   # NG: import 以外は上書きできない
+      limit: 10
+"#
+        }
+        "U060" => {
+            r#"response_mapping:
+  - field: id
+    source: posts.id
+  - field: title
+    source: posts.title
+  - field: comment_count
+    aggregate:
+      type: COUNT
+      source: comments.id
+    # 情報: group_by が無いため posts.id, posts.title で GROUP BY する
+"#
+        }
+        "U061" => {
+            r#"response_mapping:
+  - field: name
+    source: users.          # NG: カラム名が無い
+  - field: posts
+    type: array
+    join:
+      table: posts
+      on: ""                # NG: 結合条件が空
+# OK: source: users.name / on: users.id = posts.user_id
+"#
+        }
+        "U062" => {
+            r#"filters:
+  - param: cursor
+    maps_to: PAGINATION
+    strategy: cursor        # NG: cursor_field が無い
+    page_size: 0            # NG: 0 件のページになる
+# OK: cursor_field: created_at / page_size: 20
+"#
+        }
+        "U063" => {
+            r#"response_mapping:
+  - field: post_count
+    aggregate:
+      type: COUNT           # NG: 数える行のカラムが無い
+      group_by: users.id
+# OK: source: posts.id
+"#
+        }
+        _ => return None,
+    };
+    Some(example)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::{self, RULES};
+
+    #[test]
+    fn test_every_builtin_rule_has_example_and_url() {
+        for rule in RULES {
+            assert!(example(&rule.code).is_some(), "{} に例が無い", rule.code);
+            assert_eq!(
+                rule.doc_url(),
+                Some(format!("{}#{}", DOCS_URL, rule.code.to_ascii_lowercase()))
+            );
+        }
+        let section = section(&rules::DBML_COLUMN);
+        assert!(section.starts_with("<a id=\"u012\"></a>\n## U012 response_mapping.source\n"));
+        assert!(section.contains("```yaml\n# schema.dbml:"));
+    }
+
+    #[test]
+    fn test_docs_rules_md_is_up_to_date() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../docs/rules.md");
+        let current = std::fs::read_to_string(path).unwrap_or_default();
+        assert!(
+            current == markdown(RULES),
+            "docs/rules.md が規則の定義と一致しません。`usml rules --markdown > docs/rules.md` で生成し直してください"
+        );
+    }
+}
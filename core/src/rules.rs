@@ -0,0 +1,1054 @@
+use std::borrow::Cow;
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::ast::UsmlDocument;
+use crate::diagnostic::{Diagnostic, Location, Severity};
+use crate::model::Model;
+use crate::rule_docs;
+use crate::validator::ResolveContext;
+
+/// 規則の分類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Category {
+    /// import 宣言
+    Import,
+    /// join・join_chain・alias
+    Join,
+    /// response_mapping の構造（集約・配列）
+    Mapping,
+    Filter,
+    Transform,
+    /// 解決した OpenAPI との照合
+    Openapi,
+    /// 解決した DBML との照合
+    Dbml,
+    /// フィールド名・パラメータ名の表記
+    Naming,
+    /// owner・reviewers・複雑さの上限などの運用上の規約
+    Governance,
+    /// 組み込み側で登録した独自規則
+    Custom,
+}
+
+impl Category {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Category::Import => "import",
+            Category::Join => "join",
+            Category::Mapping => "mapping",
+            Category::Filter => "filter",
+            Category::Transform => "transform",
+            Category::Openapi => "openapi",
+            Category::Dbml => "dbml",
+            Category::Naming => "naming",
+            Category::Governance => "governance",
+            Category::Custom => "custom",
+        }
+    }
+}
+
+/// バリデーション規則 1 つ分の定義
+///
+/// `code`（U001…）は設定・抑制・ベースラインなどから参照する安定した識別子で、
+/// 一度割り当てた番号は規則を削除しても再利用しない。
+/// `name` は従来の診断の `rule` に出していた名前。
+/// 設定ファイルから読み込む規則のために文字列は `Cow` で持つ
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct Rule {
+    pub code: Cow<'static, str>,
+    pub name: Cow<'static, str>,
+    pub category: Category,
+    /// 既定の重大度
+    pub severity: Severity,
+    /// 一覧に出す 1 行の説明
+    pub summary: Cow<'static, str>,
+    /// `usml rules --explain` で出す詳しい説明
+    pub explanation: Cow<'static, str>,
+}
+
+impl Rule {
+    /// ドキュメントのアンカー（コードの小文字）
+    pub fn doc_anchor(&self) -> String {
+        self.code.to_ascii_lowercase()
+    }
+
+    /// 組み込み規則のドキュメントの URL。独自規則は None
+    pub fn doc_url(&self) -> Option<String> {
+        find(&self.code)?;
+        Some(format!("{}#{}", rule_docs::DOCS_URL, self.doc_anchor()))
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.code, self.name)
+    }
+}
+
+pub const IMPORT_TABLE: Rule = Rule {
+    code: Cow::Borrowed("U001"),
+    name: Cow::Borrowed("import.dbml"),
+    category: Category::Import,
+    severity: Severity::Error,
+    summary: Cow::Borrowed("source・join で使うテーブルが import.dbml に含まれている"),
+    explanation: Cow::Borrowed(
+        "source・join・join_chain で使うテーブル（エイリアスは実テーブルに解決する）は、\
+import.dbml で宣言されている必要があります。宣言されていないテーブルはスキーマとの照合ができません。",
+    ),
+};
+
+pub const JOIN_ON_TABLE: Rule = Rule {
+    code: Cow::Borrowed("U002"),
+    name: Cow::Borrowed("join.on"),
+    category: Category::Join,
+    severity: Severity::Error,
+    summary: Cow::Borrowed("join.on で参照するテーブルが import.dbml に含まれている"),
+    explanation: Cow::Borrowed(
+        "join.on の結合条件に現れるテーブル（またはエイリアスの実テーブル）は import.dbml で\
+宣言されている必要があります。",
+    ),
+};
+
+pub const JOIN_CHAIN_ON_TABLE: Rule = Rule {
+    code: Cow::Borrowed("U003"),
+    name: Cow::Borrowed("join_chain.on"),
+    category: Category::Join,
+    severity: Severity::Error,
+    summary: Cow::Borrowed("join_chain[].on で参照するテーブルが import.dbml に含まれている"),
+    explanation: Cow::Borrowed(
+        "多段結合の各段の結合条件に現れるテーブルも、join.on と同じく import.dbml で\
+宣言されている必要があります。",
+    ),
+};
+
+pub const JOIN_ALIAS: Rule = Rule {
+    code: Cow::Borrowed("U004"),
+    name: Cow::Borrowed("join.alias"),
+    category: Category::Join,
+    severity: Severity::Error,
+    summary: Cow::Borrowed("同じテーブルを異なる結合条件で使う場合は alias を付ける"),
+    explanation: Cow::Borrowed(
+        "1 つのテーブルを異なる join.on で複数回結合すると、どの結合のカラムを指すのか\
+区別できません。少なくとも一方に alias を指定し、source では alias で参照してください。",
+    ),
+};
+
+pub const AGGREGATE_GROUP_BY: Rule = Rule {
+    code: Cow::Borrowed("U005"),
+    name: Cow::Borrowed("aggregate.group_by"),
+    category: Category::Mapping,
+    severity: Severity::Warning,
+    summary: Cow::Borrowed("aggregate には group_by を明示する"),
+    explanation: Cow::Borrowed(
+        "group_by を省略すると、ルートテーブルの主キー（配列の要素では親の行のキー）と、同じ階層でカラムを返す\
+フィールドの列で GROUP BY します（U060）。主キーは DBML を読まずに分かる範囲で、参照されているルートテーブルの id カラムです。\
+意図した粒度で集約されるよう、group_by を明示することを推奨します。",
+    ),
+};
+
+pub const SOURCE_TABLE: Rule = Rule {
+    code: Cow::Borrowed("U006"),
+    name: Cow::Borrowed("source_table"),
+    category: Category::Mapping,
+    severity: Severity::Error,
+    summary: Cow::Borrowed("配列フィールドの source_table が join の実際のソースと一致する"),
+    explanation: Cow::Borrowed(
+        "type: array のフィールドでは、source_table が join のテーブル\
+（join_chain がある場合はその最後のテーブル）と一致している必要があります。",
+    ),
+};
+
+pub const FILTER_CONDITION_PARAM: Rule = Rule {
+    code: Cow::Borrowed("U007"),
+    name: Cow::Borrowed("filters.condition"),
+    category: Category::Filter,
+    severity: Severity::Error,
+    summary: Cow::Borrowed("condition の :パラメータ が filters[].param で宣言されている"),
+    explanation: Cow::Borrowed(
+        "filters[].condition で `:status` のようにバインドするパラメータは、\
+いずれかの filters[].param で宣言されている必要があります。",
+    ),
+};
+
+pub const ORDER_BY_DEFAULT_COLUMN: Rule = Rule {
+    code: Cow::Borrowed("U008"),
+    name: Cow::Borrowed("filters.allowed_columns"),
+    category: Category::Filter,
+    severity: Severity::Error,
+    summary: Cow::Borrowed("ORDER_BY の default_column が allowed_columns に含まれる"),
+    explanation: Cow::Borrowed(
+        "maps_to: ORDER_BY で allowed_columns を指定した場合、default_column も\
+そのリストに含まれている必要があります。",
+    ),
+};
+
+pub const TRANSFORM_TARGET: Rule = Rule {
+    code: Cow::Borrowed("U009"),
+    name: Cow::Borrowed("transforms.target"),
+    category: Category::Transform,
+    severity: Severity::Error,
+    summary: Cow::Borrowed("transforms[].target が response_mapping の field に対応している"),
+    explanation: Cow::Borrowed(
+        "transform の結果はレスポンスフィールドの最終値になるため、target は\
+response_mapping のいずれかの field と一致している必要があります。",
+    ),
+};
+
+pub const TRANSFORM_PARAM_UNCHECKED: Rule = Rule {
+    code: Cow::Borrowed("U010"),
+    name: Cow::Borrowed("transforms.condition.param"),
+    category: Category::Transform,
+    severity: Severity::Warning,
+    summary: Cow::Borrowed("transform の condition.param は OpenAPI を解決しないと確認できない"),
+    explanation: Cow::Borrowed(
+        "OpenAPI を解決せずにバリデーションした場合、transform の condition で使う param が\
+API のパラメータに存在するかは確認されません。--resolve を付けると U013 で確認します。",
+    ),
+};
+
+pub const OPENAPI_FIELD: Rule = Rule {
+    code: Cow::Borrowed("U011"),
+    name: Cow::Borrowed("response_mapping.field"),
+    category: Category::Openapi,
+    severity: Severity::Error,
+    summary: Cow::Borrowed("response_mapping の field が OpenAPI レスポンスのプロパティに存在する"),
+    explanation: Cow::Borrowed(
+        "import.openapi で参照したレスポンススキーマに、response_mapping の各 field と\
+同名のプロパティが定義されている必要があります。",
+    ),
+};
+
+pub const DBML_COLUMN: Rule = Rule {
+    code: Cow::Borrowed("U012"),
+    name: Cow::Borrowed("response_mapping.source"),
+    category: Category::Dbml,
+    severity: Severity::Error,
+    summary: Cow::Borrowed("source のカラムが DBML のテーブルに存在する"),
+    explanation: Cow::Borrowed(
+        "source の `テーブル.カラム` のカラムが、import.dbml で解決したテーブル定義に\
+存在する必要があります。",
+    ),
+};
+
+pub const OPENAPI_TRANSFORM_PARAM: Rule = Rule {
+    code: Cow::Borrowed("U013"),
+    name: Cow::Borrowed("transforms.condition.param"),
+    category: Category::Openapi,
+    severity: Severity::Error,
+    summary: Cow::Borrowed("transform の condition.param が OpenAPI のパラメータに存在する"),
+    explanation: Cow::Borrowed(
+        "transform の condition で使う param は、import.openapi で参照した\
+オペレーションのパラメータとして定義されている必要があります。",
+    ),
+};
+
+pub const OPENAPI_RESOLVE: Rule = Rule {
+    code: Cow::Borrowed("U014"),
+    name: Cow::Borrowed("import.openapi"),
+    category: Category::Import,
+    severity: Severity::Warning,
+    summary: Cow::Borrowed("import.openapi のファイルを読み込み・解析できる"),
+    explanation: Cow::Borrowed(
+        "import.openapi で参照したファイルが読み込めない、または解析できない場合に報告します。\
+OpenAPI との照合（U011・U013）は行われません。",
+    ),
+};
+
+pub const DBML_RESOLVE: Rule = Rule {
+    code: Cow::Borrowed("U015"),
+    name: Cow::Borrowed("import.dbml"),
+    category: Category::Import,
+    severity: Severity::Warning,
+    summary: Cow::Borrowed("import.dbml のファイルを読み込み・解析できる"),
+    explanation: Cow::Borrowed(
+        "import.dbml で参照したファイルが読み込めない、または解析できない場合に報告します。\
+そのファイルのテーブルとの照合（U012）は行われません。",
+    ),
+};
+
+pub const FIELD_NAMING: Rule = Rule {
+    code: Cow::Borrowed("U016"),
+    name: Cow::Borrowed("naming.field"),
+    category: Category::Naming,
+    severity: Severity::Warning,
+    summary: Cow::Borrowed("レスポンスフィールド名が [lint.naming] fields の表記にそろっている"),
+    explanation: Cow::Borrowed(
+        "usml.toml の [lint.naming] で fields を指定した場合だけ有効になります。\
+入れ子のフィールドも含め、snake_case / camelCase / PascalCase / kebab-case のいずれかにそろえます。",
+    ),
+};
+
+pub const PARAM_NAMING: Rule = Rule {
+    code: Cow::Borrowed("U017"),
+    name: Cow::Borrowed("naming.param"),
+    category: Category::Naming,
+    severity: Severity::Warning,
+    summary: Cow::Borrowed("filters[].param が [lint.naming] params の表記にそろっている"),
+    explanation: Cow::Borrowed(
+        "usml.toml の [lint.naming] で params を指定した場合だけ有効になります。",
+    ),
+};
+
+pub const OPENAPI_FIELD_CASING: Rule = Rule {
+    code: Cow::Borrowed("U018"),
+    name: Cow::Borrowed("naming.openapi"),
+    category: Category::Naming,
+    severity: Severity::Error,
+    summary: Cow::Borrowed("field の表記が OpenAPI のプロパティと一致している"),
+    explanation: Cow::Borrowed(
+        "field が OpenAPI レスポンスに無く、大文字・小文字や区切り文字だけが異なる\
+プロパティ（avatarUrl と avatar_url など）がある場合に、U011 の代わりに報告します。",
+    ),
+};
+
+pub const TRANSFORM_SOURCE: Rule = Rule {
+    code: Cow::Borrowed("U019"),
+    name: Cow::Borrowed("transforms.source"),
+    category: Category::Transform,
+    severity: Severity::Error,
+    summary: Cow::Borrowed("transform の source・sources が実在する参照を指している"),
+    explanation: Cow::Borrowed(
+        "transform の source・sources（CONDITIONAL_SOURCE では then_source・else_source）の各要素は、import.dbml に含まれるテーブル（エイリアス可）の\
+`テーブル.カラム` か、response_mapping のフィールド（入れ子はフルパス）を指す必要があります。\
+COALESCE の候補のうち 1 つだけが壊れている場合も検出します。",
+    ),
+};
+pub const TRANSFORM_SOURCE_COLUMN: Rule = Rule {
+    code: Cow::Borrowed("U020"),
+    name: Cow::Borrowed("transforms.source.column"),
+    category: Category::Dbml,
+    severity: Severity::Error,
+    summary: Cow::Borrowed("transform の source のカラムが DBML のテーブルに存在する"),
+    explanation: Cow::Borrowed(
+        "transform の source・sources の `テーブル.カラム` のカラムが、import.dbml で解決した\
+テーブル定義に存在する必要があります（U012 の transform 版）。CONDITIONAL_SOURCE の\
+then_source・else_source も対象です。",
+    ),
+};
+
+pub const CONDITIONAL_SOURCE_TYPE: Rule = Rule {
+    code: Cow::Borrowed("U021"),
+    name: Cow::Borrowed("transforms.conditional_source.type"),
+    category: Category::Transform,
+    severity: Severity::Error,
+    summary: Cow::Borrowed("CONDITIONAL_SOURCE の分岐の型がフィールドの型と両立する"),
+    explanation: Cow::Borrowed(
+        "CONDITIONAL_SOURCE の then_source・else_source のカラム型（DBML）が、target の\
+OpenAPI プロパティの型と両立する必要があります。OpenAPI の型が分からない場合は 2 つの分岐の型を\
+比べます。integer は number と両立し、型を判別できないカラムは検査しません。",
+    ),
+};
+
+pub const MASK_PATTERN: Rule = Rule {
+    code: Cow::Borrowed("U022"),
+    name: Cow::Borrowed("transforms.mask_pattern"),
+    category: Category::Transform,
+    severity: Severity::Error,
+    summary: Cow::Borrowed("MASK の mask_pattern が正しい形式で書かれている"),
+    explanation: Cow::Borrowed(
+        "MASK には mask_pattern が必要です。`*` で 1 文字を隠し、先頭の `^N`・末尾の `$N` で\
+先頭・末尾の N 文字を残します（例: `***-****-$4`）。`\\` の直後の文字は文字どおりに出力します。\
+隠す文字が無いパターンや、`^N`・`$N` の位置・文字数が不正なパターンはエラーです。",
+    ),
+};
+pub const MASK_SOURCE_TYPE: Rule = Rule {
+    code: Cow::Borrowed("U023"),
+    name: Cow::Borrowed("transforms.mask.type"),
+    category: Category::Dbml,
+    severity: Severity::Error,
+    summary: Cow::Borrowed("MASK を文字列型のカラムに適用している"),
+    explanation: Cow::Borrowed(
+        "MASK の source は、DBML の型が文字列（varchar・text など）のカラムである必要があります。\
+型を判別できないカラムは検査しません。",
+    ),
+};
+
+pub const CASE_EXHAUSTIVE: Rule = Rule {
+    code: Cow::Borrowed("U024"),
+    name: Cow::Borrowed("transforms.case.exhaustive"),
+    category: Category::Dbml,
+    severity: Severity::Warning,
+    summary: Cow::Borrowed("enum カラムに対する CASE が全ての値を網羅している"),
+    explanation: Cow::Borrowed(
+        "CASE の source が DBML の enum 型のカラムで else_value が無い場合、when の value で\
+enum の全ての値を扱う必要があります。漏れた値（後から追加した値など）は NULL になるためです。",
+    ),
+};
+
+pub const AGGREGATE_TYPE: Rule = Rule {
+    code: Cow::Borrowed("U025"),
+    name: Cow::Borrowed("aggregate.type"),
+    category: Category::Mapping,
+    severity: Severity::Error,
+    summary: Cow::Borrowed("aggregate.type が許可された集約関数である"),
+    explanation: Cow::Borrowed(
+        "aggregate.type には COUNT・SUM・AVG・MIN・MAX（大文字・小文字は区別しない）だけを\
+使えます。使える集約関数は usml.toml の `[lint] aggregates` で変更できます。",
+    ),
+};
+pub const AGGREGATE_NUMERIC: Rule = Rule {
+    code: Cow::Borrowed("U026"),
+    name: Cow::Borrowed("aggregate.numeric"),
+    category: Category::Dbml,
+    severity: Severity::Error,
+    summary: Cow::Borrowed("SUM・AVG を数値型のカラムに適用している"),
+    explanation: Cow::Borrowed(
+        "aggregate.type が SUM・AVG のフィールドの source は、DBML の型が数値（integer・\
+decimal など）のカラムである必要があります。型を判別できないカラムは検査しません。",
+    ),
+};
+
+pub const COLLECTION: Rule = Rule {
+    code: Cow::Borrowed("U027"),
+    name: Cow::Borrowed("collection"),
+    category: Category::Mapping,
+    severity: Severity::Error,
+    summary: Cow::Borrowed("collection はルートの配列 1 つに付け、カーソルはその要素を指す"),
+    explanation: Cow::Borrowed(
+        "collection は一覧レスポンスの items にあたる配列の印で、ルートの `type: array` の\
+フィールド 1 つにだけ指定できます。PAGINATION の cursor_field は collection の要素の\
+フィールド名である必要があります。",
+    ),
+};
+pub const COLLECTION_ENVELOPE: Rule = Rule {
+    code: Cow::Borrowed("U028"),
+    name: Cow::Borrowed("collection.envelope"),
+    category: Category::Mapping,
+    severity: Severity::Error,
+    summary: Cow::Borrowed("一覧レスポンスの付帯フィールドが collection・PAGINATION と整合する"),
+    explanation: Cow::Borrowed(
+        "envelope には total（collection の総件数、COUNT(*)）か next_cursor（次ページの\
+カーソル）を指定します。値は自動で決まるため source・join・aggregate は書けず、ルートに置き、\
+collection の配列が必要です。next_cursor にはカーソル方式の PAGINATION フィルタが必要です。",
+    ),
+};
+
+pub const USECASE_LIMIT: Rule = Rule {
+    code: Cow::Borrowed("U029"),
+    name: Cow::Borrowed("usecase.limit"),
+    category: Category::Filter,
+    severity: Severity::Warning,
+    summary: Cow::Borrowed("usecase.limit・default_limit がページネーションと矛盾しない"),
+    explanation: Cow::Borrowed(
+        "usecase.limit は常に掛かる行数の上限、default_limit はリクエストで行数を指定しない\
+場合の LIMIT です。PAGINATION フィルタがある場合は page_size が default_limit の役割を持つため\
+default_limit は使わず、limit は page_size・max_page_size 以上にします。default_limit は limit 以下、\
+どちらも 1 以上である必要があります。",
+    ),
+};
+
+pub const NULLABLE_OPENAPI: Rule = Rule {
+    code: Cow::Borrowed("U030"),
+    name: Cow::Borrowed("nullable.openapi"),
+    category: Category::Openapi,
+    severity: Severity::Error,
+    summary: Cow::Borrowed("フィールドの nullable 宣言が OpenAPI と一致する"),
+    explanation: Cow::Borrowed(
+        "response_mapping の `nullable` は OpenAPI のプロパティと一致する必要があります。\
+`nullable: true` は OpenAPI で required かつ nullable でないプロパティには書けず、\
+`nullable: false` は OpenAPI で nullable のプロパティには書けません。",
+    ),
+};
+
+pub const NULLABLE_SOURCE: Rule = Rule {
+    code: Cow::Borrowed("U031"),
+    name: Cow::Borrowed("nullable.source"),
+    category: Category::Join,
+    severity: Severity::Warning,
+    summary: Cow::Borrowed("NULL を返さないフィールドの値が NULL になりえない"),
+    explanation: Cow::Borrowed(
+        "`nullable: false` のフィールド（宣言が無ければ OpenAPI で required かつ nullable でない\
+プロパティ）の source が LEFT JOIN で結合したテーブルや DBML で not null でないカラムの場合、\
+値が NULL になりえます。COALESCE の transform に fallback を指定してください。\
+COUNT の集約と配列フィールドは対象外です。",
+    ),
+};
+
+pub const FIELD_VALUE: Rule = Rule {
+    code: Cow::Borrowed("U032"),
+    name: Cow::Borrowed("value"),
+    category: Category::Mapping,
+    severity: Severity::Error,
+    summary: Cow::Borrowed("固定値・リクエストパラメータのフィールドに source などを併記しない"),
+    explanation: Cow::Borrowed(
+        "`value`（`const`）を指定したフィールドはデータベースを参照せず、その値をそのまま返します。\
+`value: \":name\"`・`from_param: name`・`source: \":name\"` はリクエストパラメータ name の値を返します。\
+これらは source のカラム・join・join_chain・aggregate・envelope・type: array と併記できず、\
+value と from_param も同時には指定できません。",
+    ),
+};
+
+pub const FIELD_VALUE_TYPE: Rule = Rule {
+    code: Cow::Borrowed("U033"),
+    name: Cow::Borrowed("value.type"),
+    category: Category::Openapi,
+    severity: Severity::Error,
+    summary: Cow::Borrowed("固定値・リクエストパラメータの型が OpenAPI のプロパティの型と一致する"),
+    explanation: Cow::Borrowed(
+        "`value` の固定値は OpenAPI のプロパティの型（integer は number にも入る）に合う必要があります。\
+リクエストパラメータを返すフィールド（`:name`・from_param）は OpenAPI のパラメータに name が存在し、\
+その型がプロパティの型に合う必要があります。",
+    ),
+};
+
+pub const AUTHORIZATION_ROW_FILTER: Rule = Rule {
+    code: Cow::Borrowed("U034"),
+    name: Cow::Borrowed("authorization.row_filter"),
+    category: Category::Filter,
+    severity: Severity::Error,
+    summary: Cow::Borrowed("行レベルの条件が import したテーブルの存在するカラムを参照する"),
+    explanation: Cow::Borrowed(
+        "authorization.row_filters の条件は常に WHERE に加わるアクセス制御です。\
+`テーブル.カラム`（エイリアス可）を 1 つ以上参照し、そのテーブルは import.dbml に含まれている必要があります。\
+DBML が解決できればカラムの存在も確認します。",
+    ),
+};
+
+pub const CACHE: Rule = Rule {
+    code: Cow::Borrowed("U035"),
+    name: Cow::Borrowed("cache"),
+    category: Category::Filter,
+    severity: Severity::Error,
+    summary: Cow::Borrowed("キャッシュのキーと破棄条件が宣言済みのパラメータ・テーブルを指す"),
+    explanation: Cow::Borrowed(
+        "usecase.cache の key はキャッシュキーに含めるパラメータで、filters[].param で宣言されている\
+必要があります。invalidated_by は更新されたらキャッシュを破棄するテーブルで、import.dbml に含まれている\
+必要があります。ttl は 1 秒以上にします。",
+    ),
+};
+
+pub const SOURCE_USECASE: Rule = Rule {
+    code: Cow::Borrowed("U036"),
+    name: Cow::Borrowed("source_usecase"),
+    category: Category::Mapping,
+    severity: Severity::Error,
+    summary: Cow::Borrowed("別の usecase を参照するフィールドに source などを併記しない"),
+    explanation: Cow::Borrowed(
+        "`source_usecase` を指定したフィールドの値は参照先の usecase のレスポンスで決まるため、\
+source・value・from_param・aggregate・envelope・fields とは併記できません。",
+    ),
+};
+
+pub const USECASE_REFERENCE: Rule = Rule {
+    code: Cow::Borrowed("U037"),
+    name: Cow::Borrowed("usecase.reference"),
+    category: Category::Import,
+    severity: Severity::Error,
+    summary: Cow::Borrowed("source_usecase の参照先がワークスペースにあり、参照が循環しない"),
+    explanation: Cow::Borrowed(
+        "ワークスペース（同じディレクトリ以下の .usml.yaml）を読み込んだ場合に、source_usecase が\
+usecase.name で参照する usecase が存在し、参照をたどって元の usecase に戻らないことを確認します。\
+usecase.name はワークスペース内で一意である必要があります。",
+    ),
+};
+
+pub const LIBRARY_REFERENCE: Rule = Rule {
+    code: Cow::Borrowed("U038"),
+    name: Cow::Borrowed("library.reference"),
+    category: Category::Import,
+    severity: Severity::Error,
+    summary: Cow::Borrowed("transform・filter の use が共有ライブラリの定義を指す"),
+    explanation: Cow::Borrowed(
+        "transform の type・filter の maps_to は、`use` で共有ライブラリ（library.usml.yaml）の定義を\
+使う場合だけ省略できます。ワークスペースを読み込んだ場合は、use の名前がライブラリの transforms・filters に\
+あることも確認します。",
+    ),
+};
+
+pub const OWNERSHIP: Rule = Rule {
+    code: Cow::Borrowed("U039"),
+    name: Cow::Borrowed("ownership"),
+    category: Category::Governance,
+    severity: Severity::Error,
+    summary: Cow::Borrowed(
+        "usecase に [lint.ownership] teams のチームの owner と必要な数の reviewers がある",
+    ),
+    explanation: Cow::Borrowed(
+        "usml.toml に [lint.ownership] がある場合だけ有効になります。\
+owner は teams に列挙したチームのいずれか（teams が空なら任意）でなければならず、\
+reviewers は min_reviewers 人以上必要です。",
+    ),
+};
+
+pub const COMPLEXITY: Rule = Rule {
+    code: Cow::Borrowed("U040"),
+    name: Cow::Borrowed("complexity"),
+    category: Category::Governance,
+    severity: Severity::Warning,
+    summary: Cow::Borrowed(
+        "usecase のフィールド数・結合の段数・テーブル数・入れ子の段数が [lint.complexity] の上限以内",
+    ),
+    explanation: Cow::Borrowed(
+        "usml.toml に [lint.complexity] がある場合だけ有効になり、指定した上限（max_fields・max_join_depth・\
+max_tables・max_nesting_depth）だけを検査します。重大度は severity で変更できます（既定は warning）。\
+上限を超える usecase はエンドポイントの分割を検討してください。",
+    ),
+};
+
+pub const DBML_CONFLICT: Rule = Rule {
+    code: Cow::Borrowed("U041"),
+    name: Cow::Borrowed("import.dbml.conflict"),
+    category: Category::Import,
+    severity: Severity::Error,
+    summary: Cow::Borrowed("import.dbml の複数のファイルで同じ名前のテーブルを異なる定義にしない"),
+    explanation: Cow::Borrowed(
+        "import.dbml の複数のファイルに同じ名前のテーブルがある場合、先に import したファイルの定義で\
+照合します。カラムの名前・型が異なる場合は、どのファイルの定義が使われたか分かるように報告します。\
+同じ定義であれば報告しません。スキーマファイルを 1 つにまとめるか、どちらかの定義を直してください。",
+    ),
+};
+
+/// 登録済みの全規則（コード順）
+pub const RULES: &[&Rule] = &[
+    &IMPORT_TABLE,
+    &JOIN_ON_TABLE,
+    &JOIN_CHAIN_ON_TABLE,
+    &JOIN_ALIAS,
+    &AGGREGATE_GROUP_BY,
+    &SOURCE_TABLE,
+    &FILTER_CONDITION_PARAM,
+    &ORDER_BY_DEFAULT_COLUMN,
+    &TRANSFORM_TARGET,
+    &TRANSFORM_PARAM_UNCHECKED,
+    &OPENAPI_FIELD,
+    &DBML_COLUMN,
+    &OPENAPI_TRANSFORM_PARAM,
+    &OPENAPI_RESOLVE,
+    &DBML_RESOLVE,
+    &FIELD_NAMING,
+    &PARAM_NAMING,
+    &OPENAPI_FIELD_CASING,
+    &TRANSFORM_SOURCE,
+    &TRANSFORM_SOURCE_COLUMN,
+    &CONDITIONAL_SOURCE_TYPE,
+    &MASK_PATTERN,
+    &MASK_SOURCE_TYPE,
+    &CASE_EXHAUSTIVE,
+    &AGGREGATE_TYPE,
+    &AGGREGATE_NUMERIC,
+    &COLLECTION,
+    &COLLECTION_ENVELOPE,
+    &USECASE_LIMIT,
+    &NULLABLE_OPENAPI,
+    &NULLABLE_SOURCE,
+    &FIELD_VALUE,
+    &FIELD_VALUE_TYPE,
+    &AUTHORIZATION_ROW_FILTER,
+    &CACHE,
+    &SOURCE_USECASE,
+    &USECASE_REFERENCE,
+    &LIBRARY_REFERENCE,
+    &OWNERSHIP,
+    &COMPLEXITY,
+    &DBML_CONFLICT,
+    &IMPORT_LIMIT,
+    &IMPORT_PATH,
+    &FILTER_DUPLICATE_PARAM,
+    &FILTER_ORDER_BY,
+    &FILTER_COLUMN,
+    &FILTER_CONDITION_TYPE,
+    &FILTER_DEFAULT,
+    &FILTER_REQUIRED_OPENAPI,
+    &CURSOR_ORDER,
+    &AGGREGATE_STRATEGY,
+    &HYDRATION,
+    &SIBLING_ARRAYS,
+    &OUTPUT_NAME,
+    &TOTAL_COUNT,
+    &FIELD_TIMEZONE,
+    &TRANSFORM_CONVERT,
+    &TRANSFORM_LOCALIZE,
+    &ENVIRONMENT_OVERLAY,
+    &IMPLICIT_GROUP_BY,
+    &REFERENCE_SYNTAX,
+    &PAGINATION,
+    &AGGREGATE_SOURCE,
+];
+
+pub const IMPORT_LIMIT: Rule = Rule {
+    code: Cow::Borrowed("U042"),
+    name: Cow::Borrowed("import.limit"),
+    category: Category::Import,
+    severity: Severity::Error,
+    summary: Cow::Borrowed(
+        "import の解決が上限（ファイルサイズ・待ち時間・入れ子の段数）を超えない",
+    ),
+    explanation: Cow::Borrowed(
+        "巨大なファイルや深すぎる入れ子で検証が止まったりスタックを使い切ったりしないよう、\
+import の解決には上限があります。import するファイルのサイズ（既定 10 MiB）、リモートの読み込みの待ち時間\
+（既定 30 秒）、OpenAPI のレスポンススキーマの入れ子（既定 32 段）、source_usecase の参照の段数（既定 16 段）を\
+超えると、その時点で打ち切って報告します。正当なスキーマであれば usml.toml の [limits] で上限を上げてください。",
+    ),
+};
+
+pub const IMPORT_PATH: Rule = Rule {
+    code: Cow::Borrowed("U043"),
+    name: Cow::Borrowed("import.path"),
+    category: Category::Import,
+    severity: Severity::Error,
+    summary: Cow::Borrowed("import のパスが読み込みを許可されたディレクトリの外を指さない"),
+    explanation: Cow::Borrowed(
+        "--allow-paths を指定した場合（環境変数 CI がある場合はカレントディレクトリが既定）、import は\
+そのディレクトリ以下のファイルしか読み込みません。信頼できないブランチの仕様を共有のランナーで検証するときに、\
+../../.. でリポジトリの外のファイルを読ませないためです。シンボリックリンクは辿った先で判定します。\
+スキーマをリポジトリ内に置くか、読み込んでよいディレクトリを --allow-paths に加えてください。",
+    ),
+};
+
+pub const FILTER_DUPLICATE_PARAM: Rule = Rule {
+    code: Cow::Borrowed("U044"),
+    name: Cow::Borrowed("filters.param.duplicate"),
+    category: Category::Filter,
+    severity: Severity::Error,
+    summary: Cow::Borrowed("filters[].param が重複しない"),
+    explanation: Cow::Borrowed(
+        "1 つのリクエストパラメータは 1 つのフィルタにだけ対応させます。同じ param を複数のフィルタで\
+宣言すると、どのフィルタの定義が使われるか実装によって変わります。特に WHERE と PAGINATION の両方に\
+使うと、絞り込みの値がページサイズとしても解釈されます。PAGINATION の limit_param も param と同じく数えます。",
+    ),
+};
+
+pub const FILTER_ORDER_BY: Rule = Rule {
+    code: Cow::Borrowed("U045"),
+    name: Cow::Borrowed("filters.order_by"),
+    category: Category::Filter,
+    severity: Severity::Warning,
+    summary: Cow::Borrowed("maps_to: ORDER_BY のフィルタは 1 つだけにする"),
+    explanation: Cow::Borrowed(
+        "ORDER_BY のフィルタが複数あると、どの順でソートキーを並べるかが仕様から読み取れません。\
+複数のカラムでソートする場合は 1 つのフィルタの allowed_columns にまとめてください。",
+    ),
+};
+
+pub const FILTER_COLUMN: Rule = Rule {
+    code: Cow::Borrowed("U046"),
+    name: Cow::Borrowed("filters.column"),
+    category: Category::Filter,
+    severity: Severity::Error,
+    summary: Cow::Borrowed("filters で参照するカラムが DBML のテーブルに存在する"),
+    explanation: Cow::Borrowed(
+        "filters[].condition・cursor_field・cursor_tiebreaker・default_column・allowed_columns に書いた `テーブル.カラム` の\
+カラムが、import.dbml で解決したテーブル定義に存在する必要があります（--resolve のときだけ検査します）。\
+テーブル名の無いカラム名は検査しません。",
+    ),
+};
+
+pub const FILTER_CONDITION_TYPE: Rule = Rule {
+    code: Cow::Borrowed("U047"),
+    name: Cow::Borrowed("filters.condition.type"),
+    category: Category::Filter,
+    severity: Severity::Warning,
+    summary: Cow::Borrowed("filters[].condition の比較が DBML のカラムの型に合っている"),
+    explanation: Cow::Borrowed(
+        "--resolve のとき、condition の比較をカラムの型と照合します。文字列のカラムを `>`・`<` で比べると\
+辞書順の比較になり、数値のカラムに LIKE を使うと暗黙の型変換で遅く不正確になります。\
+:パラメータ と比べる場合は OpenAPI のパラメータの型がカラムの型と合っている必要があります。\
+意図した比較であれば `::` か CAST で型を明示してください。",
+    ),
+};
+
+pub const FILTER_DEFAULT: Rule = Rule {
+    code: Cow::Borrowed("U048"),
+    name: Cow::Borrowed("filters.default"),
+    category: Category::Filter,
+    severity: Severity::Error,
+    summary: Cow::Borrowed("filters[].default を required: true のフィルタに付けない"),
+    explanation: Cow::Borrowed(
+        "default はパラメータが指定されなかったときに使う値で、必須のパラメータには使われません。\
+required: true と default を両方書くと、どちらが意図した仕様か読み取れません。default は\
+maps_to: WHERE のフィルタにだけ書き、`:パラメータ` の参照は書けません。",
+    ),
+};
+
+pub const FILTER_REQUIRED_OPENAPI: Rule = Rule {
+    code: Cow::Borrowed("U049"),
+    name: Cow::Borrowed("filters.required.openapi"),
+    category: Category::Openapi,
+    severity: Severity::Error,
+    summary: Cow::Borrowed("filters[].required・default が OpenAPI のパラメータと一致する"),
+    explanation: Cow::Borrowed(
+        "--resolve のとき、filters[] に書いた required を OpenAPI のパラメータの required と、default を\
+パラメータの schema の default と照合します。生成するコードはフィルタの宣言に従って条件を組み立てるため、\
+OpenAPI と食い違うと、省略できるはずのパラメータが必須になったり、既定値の無い条件が常に加わったりします。\
+required を省略したフィルタは省略可能として生成されるため、OpenAPI で必須のパラメータには required: true を書きます。\
+default の型もパラメータの型と両立する必要があります。",
+    ),
+};
+
+pub const CURSOR_ORDER: Rule = Rule {
+    code: Cow::Borrowed("U050"),
+    name: Cow::Borrowed("filters.cursor.order"),
+    category: Category::Filter,
+    severity: Severity::Warning,
+    summary: Cow::Borrowed("カーソル方式のページネーションの順序が一意に決まる"),
+    explanation: Cow::Borrowed(
+        "--resolve のとき、strategy: cursor の cursor_field が DBML で一意（pk・unique）かを確認します。\
+一意でないカラム（created_at など）だけで辿ると、同じ値の行がページの境目で抜けたり重複したりします。\
+cursor_tiebreaker に主キーなどの一意なカラムを指定すると、(cursor_field, cursor_tiebreaker) の組で\
+キーセットページネーションの SQL を生成します。cursor_tiebreaker も一意である必要があります。",
+    ),
+};
+
+pub const AGGREGATE_STRATEGY: Rule = Rule {
+    code: Cow::Borrowed("U051"),
+    name: Cow::Borrowed("aggregate.strategy"),
+    category: Category::Mapping,
+    severity: Severity::Warning,
+    summary: Cow::Borrowed("GROUP BY で取る集約が他の集約の結合で重複して数えられない"),
+    explanation: Cow::Borrowed(
+        "同じ階層に別々の 1 対多の結合を持つ集約が複数あると、1 つのクエリで LEFT JOIN して GROUP BY したとき\
+行が掛け合わされ、COUNT・SUM・AVG の値が正しくなくなります。aggregate.strategy を省略すると生成器は\
+そのような集約を相関サブクエリで取りますが、strategy: group_by を指定した場合や結合の無い集約は GROUP BY のまま残るため\
+警告します。strategy は group_by か subquery を指定します。",
+    ),
+};
+
+pub const HYDRATION: Rule = Rule {
+    code: Cow::Borrowed("U052"),
+    name: Cow::Borrowed("usecase.hydration"),
+    category: Category::Mapping,
+    severity: Severity::Error,
+    summary: Cow::Borrowed("hydration の strategy・dialect が生成器の扱える値"),
+    explanation: Cow::Borrowed(
+        "usecase.hydration.strategy は batch（親の行のキーの IN で配列の要素をまとめて取る）か\
+json（親のクエリで JSON の配列に集約する）を指定します。dialect は json のときの SQL の方言で、\
+postgresql か mysql を指定します。",
+    ),
+};
+
+pub const SIBLING_ARRAYS: Rule = Rule {
+    code: Cow::Borrowed("U053"),
+    name: Cow::Borrowed("usecase.hydration.sibling_arrays"),
+    category: Category::Mapping,
+    severity: Severity::Warning,
+    summary: Cow::Borrowed("同じ親のキーで取る兄弟の配列は取り方を明示する"),
+    explanation: Cow::Borrowed(
+        "同じ階層の複数の配列が同じ親の列（posts.id など）で親の行に対応付けられる場合、\
+hydration を省略するとコード生成は配列ごとに別のクエリを発行します。一覧のエンドポイントでは\
+配列の数だけ往復が増えます。hydration.strategy: json で親のクエリ 1 回にまとめるか、\
+配列ごとのクエリで構わなければ hydration.strategy: batch を明示してください。",
+    ),
+};
+
+pub const OUTPUT_NAME: Rule = Rule {
+    code: Cow::Borrowed("U054"),
+    name: Cow::Borrowed("usecase.output"),
+    category: Category::Governance,
+    severity: Severity::Error,
+    summary: Cow::Borrowed("output は出力ディレクトリの中の .html ファイルを指す"),
+    explanation: Cow::Borrowed(
+        "usecase.output は usml visualize が書き出す HTML の、出力ディレクトリからの相対パスです。\
+絶対パスや .. を含むパスは出力ディレクトリの外のファイルを上書きしてしまうため使えません。\
+拡張子は .html にしてください。",
+    ),
+};
+
+pub const TOTAL_COUNT: Rule = Rule {
+    code: Cow::Borrowed("U055"),
+    name: Cow::Borrowed("filters.total_count"),
+    category: Category::Filter,
+    severity: Severity::Error,
+    summary: Cow::Borrowed("PAGINATION の total_count が件数の取り方と矛盾しない"),
+    explanation: Cow::Borrowed(
+        "total_count は envelope: total の件数の数え方で、PAGINATION フィルタにだけ指定できます。\
+query（既定）は件数を数える別のクエリを発行し、window はページのクエリに COUNT(*) OVER() の列を加え、\
+none は件数を数えません。window はカーソル方式ではカーソルより後の行だけを、DISTINCT の usecase では\
+重複を除く前の行を数えるため使えません。none の usecase には envelope: total のフィールドを置けません。",
+    ),
+};
+
+pub const FIELD_TIMEZONE: Rule = Rule {
+    code: Cow::Borrowed("U056"),
+    name: Cow::Borrowed("field.timezone"),
+    category: Category::Mapping,
+    severity: Severity::Error,
+    summary: Cow::Borrowed("timezone は日時のカラムを返すフィールドに、IANA 名か :name で指定する"),
+    explanation: Cow::Borrowed(
+        "timezone は UTC で保存した日時のカラムを、指定したタイムゾーンの日時に変換して返す宣言です。\
+値は IANA のタイムゾーン名（Asia/Tokyo・UTC）か、リクエストパラメータを表す :name です。\
+source のカラムを返すスカラーのフィールドにだけ指定でき、集約は MIN・MAX に限ります。\
+--resolve のときは、source のカラムが DBML で timestamp・timestamptz・datetime であること\
+（date・time には変換する時刻・日付がありません）と、:name のパラメータが OpenAPI にあることも確認します。",
+    ),
+};
+
+pub const TRANSFORM_CONVERT: Rule = Rule {
+    code: Cow::Borrowed("U057"),
+    name: Cow::Borrowed("transform.convert"),
+    category: Category::Transform,
+    severity: Severity::Error,
+    summary: Cow::Borrowed(
+        "CONVERT の係数・丸め・桁数が宣言され、数値のカラムを数値のフィールドに換算する",
+    ),
+    explanation: Cow::Borrowed(
+        "CONVERT は source の値に係数を掛け、rounding の丸め方で小数点以下 scale 桁にそろえる変換です（通貨・単位の換算）。\
+係数は固定の factor か、換算表の lookup（table・on・rate）のどちらか一方で指定し、source と scale は必須です。\
+rounding は half_up（既定）・half_even・half_down・up・down・ceiling・floor のいずれかです。\
+--resolve のときは source と lookup.rate のカラムが DBML で数値型であることと、target の OpenAPI のフィールドが\
+換算結果を表せる型（number、scale が 0 なら integer、format: decimal の string）であることも確認します。",
+    ),
+};
+
+pub const TRANSFORM_LOCALIZE: Rule = Rule {
+    code: Cow::Borrowed("U058"),
+    name: Cow::Borrowed("transform.localize"),
+    category: Category::Transform,
+    severity: Severity::Error,
+    summary: Cow::Borrowed(
+        "LOCALIZE の翻訳テーブル・言語のパラメータ・fallback の言語が正しく宣言されている",
+    ),
+    explanation: Cow::Borrowed(
+        "LOCALIZE は翻訳テーブルから、リクエストパラメータの言語の訳を返す変換です。訳が無ければ fallback の言語を順に試し、\
+それでも無ければ target のフィールドの source の値を返します。\
+source は訳のカラム、locale.column は同じテーブルの言語のカラムで、locale.on は翻訳テーブルの行を親の行に対応付ける条件です。\
+locale.param はパラメータ名、locale.fallback は重複の無い言語タグ（en・ja-JP など）の一覧にします。\
+--resolve のときは locale.param が OpenAPI の string のパラメータであることと、locale.column が DBML で文字列型であることも確認します。",
+    ),
+};
+
+pub const ENVIRONMENT_OVERLAY: Rule = Rule {
+    code: Cow::Borrowed("U059"),
+    name: Cow::Borrowed("import.environment"),
+    category: Category::Import,
+    severity: Severity::Error,
+    summary: Cow::Borrowed("environments の上書きがレスポンスの構造を変えない"),
+    explanation: Cow::Borrowed(
+        "environments は環境（dev・stg・prod など）ごとに import のファイルや DB 上のテーブル名（import.tables）を変える宣言で、\
+--env で選んだ環境を validate・generate の前に適用します。環境によってレスポンスの構造が変わらないよう、\
+上書きできるのは import だけです。import.openapi は同じレスポンス（パス・メソッド・ステータス）を、\
+import.dbml は import.tables の対応を通して同じテーブルを参照する必要があります。",
+    ),
+};
+
+pub const IMPLICIT_GROUP_BY: Rule = Rule {
+    code: Cow::Borrowed("U060"),
+    name: Cow::Borrowed("aggregate.implicit_group_by"),
+    category: Category::Mapping,
+    severity: Severity::Info,
+    summary: Cow::Borrowed("group_by の無い集約をどの列でまとめるかを知らせる"),
+    explanation: Cow::Borrowed(
+        "group_by を書かない集約は、生成するクエリでルートテーブルの主キー（配列の要素では親の行のキー）と、\
+同じ階層でカラムを返すフィールドの列で GROUP BY します（U005 と同じ規則）。\
+この規則は情報で、適用する列を知らせるだけです。終了コードには影響しません。\
+意図した粒度と違う場合は aggregate.group_by を明示してください（U005）。サブクエリで取る集約は対象外です。",
+    ),
+};
+
+pub const REFERENCE_SYNTAX: Rule = Rule {
+    code: Cow::Borrowed("U061"),
+    name: Cow::Borrowed("reference.syntax"),
+    category: Category::Mapping,
+    severity: Severity::Error,
+    summary: Cow::Borrowed("source は `テーブル.カラム`、join.on は空でない結合条件で書く"),
+    explanation: Cow::Borrowed(
+        "source の `テーブル.カラム` はテーブル名・カラム名のどちらも省略できません。`users.` のような参照は\
+`users. AS name` という不正な SQL になります。join.on・join_chain[].on が空だと ON の後に条件の無い\
+結合になるため、結合するテーブルと親の側のカラムを比べる条件を書きます。",
+    ),
+};
+
+pub const PAGINATION: Rule = Rule {
+    code: Cow::Borrowed("U062"),
+    name: Cow::Borrowed("filters.pagination"),
+    category: Category::Filter,
+    severity: Severity::Error,
+    summary: Cow::Borrowed(
+        "PAGINATION の page_size は 1 以上で、カーソル方式には cursor_field がある",
+    ),
+    explanation: Cow::Borrowed(
+        "page_size・max_page_size が 0 だと LIMIT 0 になり、常に空のページを返します。strategy: cursor は\
+cursor_field のカラムの値で次のページを辿るため、cursor_field が無いとキーセットの条件と ORDER BY を\
+組み立てられません。",
+    ),
+};
+
+pub const AGGREGATE_SOURCE: Rule = Rule {
+    code: Cow::Borrowed("U063"),
+    name: Cow::Borrowed("aggregate.source"),
+    category: Category::Mapping,
+    severity: Severity::Error,
+    summary: Cow::Borrowed("aggregate のフィールドに集約するカラムの source がある"),
+    explanation: Cow::Borrowed(
+        "aggregate は source のカラムを集約します。source の無い aggregate は SQL の列にできないため、\
+COUNT も数える行のテーブルのカラム（主キーなど）を source に書きます。`COUNT(*)` は LEFT JOIN で\
+相手の無い行も 1 件と数えるため使いません。",
+    ),
+};
+
+/// コード（大文字・小文字は区別しない）から規則を探す
+pub fn find(code: &str) -> Option<&'static Rule> {
+    RULES
+        .iter()
+        .copied()
+        .find(|rule| rule.code.eq_ignore_ascii_case(code))
+}
+
+/// 独自規則に渡す検証対象
+pub struct RuleContext<'a> {
+    pub document: &'a UsmlDocument,
+    pub model: &'a Model,
+    /// import を解決した場合の OpenAPI / DBML の情報
+    pub schemas: Option<&'a ResolveContext>,
+}
+
+/// 独自規則が違反を報告する先
+///
+/// 報告した診断には規則のコード・名前・既定の重大度が付く
+pub struct Diagnostics<'a> {
+    rule: &'a Rule,
+    items: &'a mut Vec<Diagnostic>,
+}
+
+impl<'a> Diagnostics<'a> {
+    pub(crate) fn new(rule: &'a Rule, items: &'a mut Vec<Diagnostic>) -> Self {
+        Diagnostics { rule, items }
+    }
+
+    pub fn report(&mut self, message: impl Into<String>) {
+        self.items.push(Diagnostic::from_rule(self.rule, message));
+    }
+
+    pub fn report_at(&mut self, message: impl Into<String>, location: Location) {
+        self.items
+            .push(Diagnostic::from_rule(self.rule, message).with_location(Some(location)));
+    }
+
+    /// USML ドキュメントのノード（JSON Pointer。例: `/usecase/filters/0/condition`）に対する診断を報告する
+    pub fn report_node(&mut self, message: impl Into<String>, pointer: impl Into<String>) {
+        self.items
+            .push(Diagnostic::from_rule(self.rule, message).with_pointer(pointer));
+    }
+}
+
+/// 組織固有の規約（命名・ページネーション必須など）を組み込み側で追加するための規則
+///
+/// `validator::Validator::with_rule` で登録する。コードは組み込み規則の `U` と
+/// 衝突しない接頭辞（例: `ACME001`）を使う
+pub trait CustomRule {
+    /// 規則の定義。`category` は通常 `Category::Custom`
+    fn rule(&self) -> &Rule;
+
+    fn check(&self, cx: &RuleContext<'_>, diagnostics: &mut Diagnostics<'_>);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_rule_codes_are_unique_and_sorted() {
+        let codes: HashSet<&str> = RULES.iter().map(|r| r.code.as_ref()).collect();
+        assert_eq!(codes.len(), RULES.len());
+        assert!(RULES.windows(2).all(|w| w[0].code < w[1].code));
+    }
+
+    #[test]
+    fn test_find_is_case_insensitive() {
+        assert_eq!(find("u004"), Some(&JOIN_ALIAS));
+        assert_eq!(find("U999"), None);
+    }
+}
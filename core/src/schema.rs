@@ -0,0 +1,331 @@
+//! import 先の OpenAPI・DBML ファイル単体の検査（`usml validate-schema`）
+//!
+//! USML から参照される前に、スキーマ自体が読めるか・構造に問題が無いかを確かめる。
+//! 読み込みとパースはリゾルバーと同じものを使うため、ここで通ったファイルは `--resolve` でも読める
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use serde_yaml::Value;
+
+use crate::diagnostic::{Diagnostic, Location};
+use crate::resolver::{self, Resolver, ResolverError};
+
+/// スキーマの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaKind {
+    Openapi,
+    Dbml,
+}
+
+impl SchemaKind {
+    /// 拡張子から決める。`.dbml` は DBML、`.yaml`・`.yml`・`.json` は OpenAPI
+    pub fn for_path(path: &str) -> Option<Self> {
+        let extension = Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "dbml" => Some(Self::Dbml),
+            "yaml" | "yml" | "json" => Some(Self::Openapi),
+            _ => None,
+        }
+    }
+}
+
+/// `path` のファイルを読み込み、種類に応じて検査する
+pub fn check_file(path: &str, kind: SchemaKind, resolver: &impl Resolver) -> Vec<Diagnostic> {
+    let content = match resolver.load(path) {
+        Ok(content) => content,
+        Err(e) => return vec![resolver_diagnostic(kind, &e)],
+    };
+    match kind {
+        SchemaKind::Openapi => check_openapi(&content, path, resolver),
+        SchemaKind::Dbml => check_dbml(&content, path),
+    }
+}
+
+/// DBML を検査する。テーブル名・カラム名の重複や未定義のテーブルへの参照は dbml-rs が検出する
+pub fn check_dbml(content: &str, source: &str) -> Vec<Diagnostic> {
+    match resolver::dbml::parse_dbml_content(content, source) {
+        Ok(tables) if tables.is_empty() => vec![
+            Diagnostic::warning("dbml.tables", "テーブルが 1 つも定義されていません")
+                .with_location(Some(Location::file(source))),
+        ],
+        Ok(_) => Vec::new(),
+        Err(e) => vec![resolver_diagnostic(SchemaKind::Dbml, &e)],
+    }
+}
+
+/// OpenAPI を検査する
+///
+/// - YAML・JSON として読めて、OpenAPI 3 の文書として解釈できる
+/// - `paths` がある
+/// - `$ref` の参照先がある（`other.yaml#/...` の外部ファイルは `source` のディレクトリから読む）
+/// - `operationId` が重複しない
+pub fn check_openapi(content: &str, source: &str, resolver: &impl Resolver) -> Vec<Diagnostic> {
+    let root: Value = match serde_yaml::from_str(content) {
+        Ok(root) => root,
+        Err(e) => {
+            let location = match e.location() {
+                Some(pos) => Location::at(source, pos.line(), pos.column()),
+                None => Location::file(source),
+            };
+            return vec![
+                Diagnostic::error("openapi.parse", format!("YAML として読めません: {}", e))
+                    .with_location(Some(location)),
+            ];
+        }
+    };
+
+    let mut diagnostics = Vec::new();
+    match resolver::openapi::check_openapi_content(content, source) {
+        Ok(()) | Err(ResolverError::FeatureDisabled(_)) => {}
+        Err(e) => diagnostics.push(resolver_diagnostic(SchemaKind::Openapi, &e)),
+    }
+    if root.get("openapi").is_none() {
+        diagnostics.push(
+            Diagnostic::warning(
+                "openapi.version",
+                "openapi（バージョン）がありません。OpenAPI 3 の文書ではない可能性があります",
+            )
+            .with_location(Some(Location::file(source))),
+        );
+    }
+    if root.get("paths").is_none() {
+        diagnostics.push(
+            Diagnostic::warning("openapi.paths", "paths がありません")
+                .with_location(Some(Location::file(source))),
+        );
+    }
+
+    // 同じ参照は最初に現れた行で 1 回だけ報告する
+    let mut refs = Vec::new();
+    collect_refs(&root, &mut refs);
+    refs.sort();
+    refs.dedup();
+    let base_dir = Path::new(source)
+        .parent()
+        .map(|dir| dir.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mut external: HashMap<String, Result<Value, String>> = HashMap::new();
+    for reference in refs {
+        let (file, pointer) = reference.split_once('#').unwrap_or((&reference, ""));
+        let message = if file.is_empty() {
+            (!resolves(&root, pointer))
+                .then(|| format!("$ref '{}' の参照先がありません", reference))
+        } else {
+            let path = resolver::join_import_path(&base_dir, file);
+            let document = external.entry(path.clone()).or_insert_with(|| {
+                let content = resolver.load(&path).map_err(|e| e.to_string())?;
+                serde_yaml::from_str(&content)
+                    .map_err(|e| format!("YAML パースエラー '{}': {}", path, e))
+            });
+            match document {
+                Ok(document) if resolves(document, pointer) => None,
+                Ok(_) => Some(format!(
+                    "$ref '{}' の参照先が '{}' にありません",
+                    reference, path
+                )),
+                Err(e) => Some(format!("$ref '{}' を解決できません: {}", reference, e)),
+            }
+        };
+        if let Some(message) = message {
+            let location = match line_of(content, &reference) {
+                Some(line) => Location::at(source, line, 1),
+                None => Location::file(source),
+            };
+            diagnostics
+                .push(Diagnostic::error("openapi.ref", message).with_location(Some(location)));
+        }
+    }
+
+    let mut operation_ids: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    if let Some(Value::Mapping(paths)) = root.get("paths") {
+        for (path, item) in paths {
+            let (Some(path), Value::Mapping(item)) = (path.as_str(), item) else {
+                continue;
+            };
+            for (method, operation) in item {
+                if let Some(id) = operation.get("operationId").and_then(Value::as_str) {
+                    let method = method.as_str().unwrap_or_default().to_ascii_uppercase();
+                    operation_ids
+                        .entry(id)
+                        .or_default()
+                        .push(format!("{} {}", method, path));
+                }
+            }
+        }
+    }
+    for (id, operations) in operation_ids {
+        if operations.len() > 1 {
+            let location = match line_of(content, id) {
+                Some(line) => Location::at(source, line, 1),
+                None => Location::file(source),
+            };
+            diagnostics.push(
+                Diagnostic::error(
+                    "openapi.operation_id",
+                    format!(
+                        "operationId '{}' が重複しています: {}",
+                        id,
+                        operations.join(", ")
+                    ),
+                )
+                .with_location(Some(location)),
+            );
+        }
+    }
+    diagnostics
+}
+
+fn resolver_diagnostic(kind: SchemaKind, err: &ResolverError) -> Diagnostic {
+    let rule = match (kind, err) {
+        (_, ResolverError::Io { .. }) => "io",
+        (_, ResolverError::NotAllowed { .. }) => "import.path",
+        (SchemaKind::Openapi, _) => "openapi.parse",
+        (SchemaKind::Dbml, _) => "dbml.parse",
+    };
+    let location = err
+        .location()
+        .cloned()
+        .or_else(|| err.file().map(Location::file));
+    Diagnostic::error(rule, err.to_string()).with_location(location)
+}
+
+fn collect_refs(value: &Value, refs: &mut Vec<String>) {
+    match value {
+        Value::Mapping(mapping) => {
+            for (key, value) in mapping {
+                match (key.as_str(), value) {
+                    (Some("$ref"), Value::String(reference)) => refs.push(reference.clone()),
+                    _ => collect_refs(value, refs),
+                }
+            }
+        }
+        Value::Sequence(items) => items.iter().for_each(|item| collect_refs(item, refs)),
+        Value::Tagged(tagged) => collect_refs(&tagged.value, refs),
+        _ => {}
+    }
+}
+
+/// JSON Pointer（`/components/schemas/User`）が `root` の中を指すか
+fn resolves(root: &Value, pointer: &str) -> bool {
+    let mut current = root;
+    for token in pointer.split('/').skip(1) {
+        let token = token.replace("~1", "/").replace("~0", "~");
+        let next = match current {
+            // `200:` のように引用符の無いキーは数値として読まれる
+            Value::Mapping(mapping) => mapping.iter().find_map(|(key, value)| {
+                let matches = match key {
+                    Value::String(key) => *key == token,
+                    Value::Number(key) => key.to_string() == token,
+                    _ => false,
+                };
+                matches.then_some(value)
+            }),
+            Value::Sequence(items) => token.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => None,
+        };
+        match next {
+            Some(next) => current = next,
+            None => return false,
+        }
+    }
+    true
+}
+
+/// `needle` を含む最初の行（1 始まり）
+fn line_of(content: &str, needle: &str) -> Option<usize> {
+    content
+        .lines()
+        .position(|line| line.contains(needle))
+        .map(|index| index + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct MapResolver(HashMap<&'static str, &'static str>);
+
+    impl Resolver for MapResolver {
+        fn load(&self, path: &str) -> Result<String, ResolverError> {
+            self.0
+                .get(path)
+                .map(|content| content.to_string())
+                .ok_or_else(|| ResolverError::NotFound(path.to_string()))
+        }
+    }
+
+    #[test]
+    fn test_check_openapi_refs_and_operation_ids() {
+        let content = r##"openapi: 3.0.0
+info: { title: t, version: "1" }
+paths:
+  /users:
+    get:
+      operationId: listUsers
+      responses:
+        200:
+          $ref: "#/components/responses/Users"
+    post:
+      operationId: listUsers
+      responses:
+        "201":
+          $ref: "./common.yaml#/components/schemas/Missing"
+  /users/{id}:
+    get:
+      responses:
+        "200":
+          $ref: "#/paths/~1users/get/responses/200"
+components:
+  responses:
+    Users: { description: ok }
+"##;
+        let resolver = MapResolver(HashMap::from([(
+            "specs/./common.yaml",
+            "components:\n  schemas:\n    User: {}\n",
+        )]));
+        let diagnostics = check_openapi(content, "specs/api.yaml", &resolver);
+        let messages: Vec<(&str, &str, Option<usize>)> = diagnostics
+            .iter()
+            .map(|d| {
+                (
+                    d.rule.as_str(),
+                    d.message.as_str(),
+                    d.location.as_ref().and_then(|l| l.line),
+                )
+            })
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                (
+                    "openapi.ref",
+                    "$ref './common.yaml#/components/schemas/Missing' の参照先が 'specs/./common.yaml' にありません",
+                    Some(14)
+                ),
+                (
+                    "openapi.operation_id",
+                    "operationId 'listUsers' が重複しています: GET /users, POST /users",
+                    Some(6)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "resolver-dbml")]
+    fn test_check_dbml_reports_duplicate_table() {
+        let diagnostics = check_dbml(
+            "Table users {\n  id int\n}\nTable users {\n  name text\n}\n",
+            "schema.dbml",
+        );
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].rule, "dbml.parse");
+        assert_eq!(
+            diagnostics[0].location,
+            Some(Location::at("schema.dbml", 4, 7))
+        );
+        assert!(diagnostics[0].message.contains("Duplicate table name"));
+        assert!(check_dbml("Table users {\n  id int\n}\n", "schema.dbml").is_empty());
+    }
+}
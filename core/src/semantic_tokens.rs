@@ -0,0 +1,256 @@
+//! セマンティックトークン
+//!
+//! YAML のスカラーの中に書かれた USML の小さな言語（`テーブル.カラム`・`:パラメータ`・import の参照など）を
+//! 種類ごとに分類する。エディタは YAML の構文ハイライトの上にこの分類で色を付ける
+
+use serde::Serialize;
+
+use crate::hover::COLUMN_KEYS;
+use crate::syntax::{Range, Span, SyntaxError, SyntaxKind, SyntaxNode, SyntaxTree};
+
+/// トークンの種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenKind {
+    /// テーブル名・エイリアス（`join.table`・`テーブル.カラム` の修飾子など）
+    Table,
+    /// `テーブル.カラム` のカラム名
+    Column,
+    /// リクエストパラメータ（`param` の値や条件式内の `:status`）
+    Parameter,
+    /// `import.openapi` の参照の API パス（`/posts/{id}`）
+    OpenapiPath,
+    /// transform の種類（`MASK` など）
+    TransformType,
+}
+
+impl TokenKind {
+    /// すべての種類（LSP の legend の順）
+    pub const ALL: [TokenKind; 5] = [
+        TokenKind::Table,
+        TokenKind::Column,
+        TokenKind::Parameter,
+        TokenKind::OpenapiPath,
+        TokenKind::TransformType,
+    ];
+}
+
+/// 分類したトークン 1 つ。範囲は 1 行に収まる
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SemanticToken {
+    pub kind: TokenKind,
+    pub span: Span,
+    pub range: Range,
+}
+
+/// 値がパラメータ名のキー
+const PARAM_KEYS: &[&str] = &["param", "limit_param", "from_param"];
+
+/// 値がテーブル名（エイリアス）のキー
+const TABLE_KEYS: &[&str] = &["table", "source_table", "alias"];
+
+/// `:name` でリクエストパラメータを参照できる、カラム参照以外のキー
+const VALUE_KEYS: &[&str] = &["value", "const", "timezone"];
+
+/// USML のソースのトークンを出現順に返す
+pub fn semantic_tokens(source: &str) -> Result<Vec<SemanticToken>, SyntaxError> {
+    let tree = SyntaxTree::parse(source)?;
+    let mut spans = Vec::new();
+    collect(source, &tree.root, &[], &mut spans);
+    spans.sort_by_key(|(span, _): &(Span, TokenKind)| span.start);
+    Ok(spans
+        .into_iter()
+        .map(|(span, kind)| SemanticToken {
+            kind,
+            span,
+            range: tree.range(source, span),
+        })
+        .collect())
+}
+
+/// `path` は `node` までのキー（シーケンスの要素ではキーを積まない）
+fn collect(source: &str, node: &SyntaxNode, path: &[&str], spans: &mut Vec<(Span, TokenKind)>) {
+    match &node.kind {
+        SyntaxKind::Mapping(entries) => {
+            for entry in entries {
+                let mut path = path.to_vec();
+                path.push(&entry.key);
+                // import.tables はキー（usecase 上の名前）も値（DB 上の名前）もテーブル名
+                if path == ["import", "tables", entry.key.as_str()] {
+                    spans.push((entry.key_span, TokenKind::Table));
+                }
+                collect(source, &entry.value, &path, spans);
+            }
+        }
+        SyntaxKind::Sequence(items) => {
+            for item in items {
+                collect(source, item, path, spans);
+            }
+        }
+        SyntaxKind::Scalar { .. } => scalar(source, node.span, path, spans),
+    }
+}
+
+fn scalar(source: &str, span: Span, path: &[&str], spans: &mut Vec<(Span, TokenKind)>) {
+    let text = &source[span.start..span.end];
+    let at = |start: usize, end: usize| Span {
+        start: span.start + start,
+        end: span.start + end,
+    };
+    match path {
+        ["import", "openapi"] => {
+            if let Some(start) = text.find("paths[\"").map(|i| i + "paths[\"".len())
+                && let Some(len) = text[start..].find('"')
+            {
+                spans.push((at(start, start + len), TokenKind::OpenapiPath));
+            }
+        }
+        ["import", "dbml"] => {
+            if let Some(start) = text.find("tables[\"").map(|i| i + "tables[\"".len())
+                && let Some(len) = text[start..].find('"')
+            {
+                spans.push((at(start, start + len), TokenKind::Table));
+            }
+        }
+        ["import", "tables", _] => spans.push((span, TokenKind::Table)),
+        [.., "transforms", "type"] => spans.push((span, TokenKind::TransformType)),
+        [.., key] if TABLE_KEYS.contains(key) => spans.push((span, TokenKind::Table)),
+        [.., key] if PARAM_KEYS.contains(key) => spans.push((span, TokenKind::Parameter)),
+        [.., key] if COLUMN_KEYS.contains(key) || VALUE_KEYS.contains(key) => {
+            for (start, end) in words(text) {
+                let word = &text[start..end];
+                if word.len() > 1 && word.starts_with(':') {
+                    spans.push((at(start, end), TokenKind::Parameter));
+                } else if COLUMN_KEYS.contains(key)
+                    && let Some((qualifier, column)) = word.split_once('.')
+                    && qualifier.starts_with(|c: char| c.is_alphabetic() || c == '_')
+                    && !column.is_empty()
+                    && !column.contains('.')
+                {
+                    let dot = start + qualifier.len();
+                    spans.push((at(start, dot), TokenKind::Table));
+                    spans.push((at(dot + 1, end), TokenKind::Column));
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// 識別子（`posts.id`・`:status`）の範囲。引用符で囲んだ文字列リテラルの中は飛ばす
+fn words(text: &str) -> Vec<(usize, usize)> {
+    let is_word = |c: char| c.is_alphanumeric() || matches!(c, '_' | '.' | ':');
+    let mut words = Vec::new();
+    let mut start = None;
+    let mut quote = None;
+    for (i, c) in text.char_indices() {
+        if let Some(q) = quote {
+            if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        if is_word(c) {
+            start.get_or_insert(i);
+            continue;
+        }
+        if let Some(s) = start.take() {
+            words.push((s, i));
+        }
+        // スカラー自体の引用符（`"posts.id"`）は先頭と末尾にしか無い
+        if c == '\'' && i > 0 {
+            quote = Some(c);
+        }
+    }
+    if let Some(s) = start {
+        words.push((s, text.len()));
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOC: &str = r#"version: "0.1"
+import:
+  openapi: ./api.yaml#paths["/posts/{id}"].get.responses["200"]
+  dbml:
+    - ./schema.dbml#tables["posts"]
+  tables:
+    users: stg_users
+usecase:
+  name: 投稿詳細
+  response_mapping:
+    - field: author
+      source: u.name
+      join:
+        table: users
+        alias: u
+        on: u.id = posts.user_id
+  filters:
+    - param: status
+      maps_to: WHERE
+      condition: posts.status = :status AND posts.kind = 'a.b'
+  transforms:
+    - target: author
+      type: MASK
+"#;
+
+    fn tokens() -> Vec<(TokenKind, &'static str)> {
+        semantic_tokens(DOC)
+            .unwrap()
+            .into_iter()
+            .map(|t| (t.kind, &DOC[t.span.start..t.span.end]))
+            .collect()
+    }
+
+    #[test]
+    fn test_semantic_tokens_classify_embedded_languages() {
+        use TokenKind::*;
+        assert_eq!(
+            tokens(),
+            [
+                (OpenapiPath, "/posts/{id}"),
+                (Table, "posts"),
+                (Table, "users"),
+                (Table, "stg_users"),
+                (Table, "u"),
+                (Column, "name"),
+                (Table, "users"),
+                (Table, "u"),
+                (Table, "u"),
+                (Column, "id"),
+                (Table, "posts"),
+                (Column, "user_id"),
+                (Parameter, "status"),
+                (Table, "posts"),
+                (Column, "status"),
+                (Parameter, ":status"),
+                (Table, "posts"),
+                (Column, "kind"),
+                (TransformType, "MASK"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_semantic_tokens_have_line_ranges() {
+        let tokens = semantic_tokens(DOC).unwrap();
+        let path = tokens[0];
+        assert_eq!((path.range.start.line, path.range.start.column), (3, 30));
+        assert_eq!(path.range.end.column, 41);
+        assert!(
+            tokens
+                .iter()
+                .all(|t| t.range.start.line == t.range.end.line)
+        );
+    }
+
+    #[test]
+    fn test_words_skip_string_literals() {
+        let text = "posts.kind = 'a.b' OR :q";
+        let words: Vec<&str> = words(text).into_iter().map(|(s, e)| &text[s..e]).collect();
+        assert_eq!(words, ["posts.kind", "OR", ":q"]);
+    }
+}
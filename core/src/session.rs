@@ -0,0 +1,473 @@
+use std::cell::OnceCell;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::ast::UsmlDocument;
+use crate::diagnostic::{self, Diagnostic};
+use crate::error::UsmlError;
+use crate::model::Model;
+use crate::parser;
+use crate::resolver::{DbmlTable, FsResolver, OpenapiResponse, Resolver};
+use crate::validator::{self, ImportPlan, ResolveContext};
+
+/// OpenAPI 参照のキャッシュキー（ファイルパス, APIパス, メソッド, ステータスコード）
+type OpenapiKey = (String, String, String, String);
+
+/// import 1 件分の解決結果と、解決時に出た警告
+#[derive(Debug)]
+struct Resolved<T> {
+    value: Option<T>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// ドキュメントごとのキャッシュ。None の段階は次回のバリデーションで作り直す
+#[derive(Debug, Default)]
+struct DocumentEntry {
+    source: Option<String>,
+    document: Option<UsmlDocument>,
+    /// import 先に依存しない規則の結果
+    document_rules: Option<Vec<Diagnostic>>,
+    /// OpenAPI と照合する規則の結果
+    openapi_rules: Option<Vec<Diagnostic>>,
+    /// DBML と照合する規則の結果（OpenAPI のプロパティ型も参照する）
+    dbml_rules: Option<Vec<Diagnostic>>,
+}
+
+/// 1 回のバリデーションの段階ごとの所要時間
+///
+/// キャッシュを使って実行しなかった段階は None
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Timings {
+    pub parse: Option<Duration>,
+    /// import 先の読み込みとパース
+    pub resolve: Option<Duration>,
+    pub document_rules: Option<Duration>,
+    pub openapi_rules: Option<Duration>,
+    pub dbml_rules: Option<Duration>,
+    pub total: Duration,
+}
+
+/// `Session::validate` の結果
+#[derive(Debug, Clone, Serialize)]
+pub struct Run {
+    pub diagnostics: Vec<Diagnostic>,
+    pub timings: Timings,
+}
+
+/// パース結果と解決済み import をファイル単位でキャッシュし、変更の影響を受ける規則だけを再実行する
+///
+/// watch モードや LSP のように同じファイルを繰り返し検証する用途向け。
+/// 結果は `validator::validate_with_resolver` と同じ診断になる
+#[derive(Debug)]
+pub struct Session<R = FsResolver> {
+    resolver: R,
+    documents: HashMap<String, DocumentEntry>,
+    openapi: HashMap<OpenapiKey, Resolved<OpenapiResponse>>,
+    dbml: HashMap<String, Resolved<Vec<DbmlTable>>>,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self::with_resolver(FsResolver)
+    }
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R: Resolver> Session<R> {
+    pub fn with_resolver(resolver: R) -> Self {
+        Session {
+            resolver,
+            documents: HashMap::new(),
+            openapi: HashMap::new(),
+            dbml: HashMap::new(),
+        }
+    }
+
+    /// エディタのバッファなど、リゾルバーを通さずにドキュメントの内容を設定する
+    ///
+    /// 内容が前回と同じなら何もしない
+    pub fn update(&mut self, path: &str, source: String) {
+        let entry = self.documents.entry(path.to_string()).or_default();
+        if entry.source.as_deref() != Some(source.as_str()) {
+            *entry = DocumentEntry {
+                source: Some(source),
+                ..DocumentEntry::default()
+            };
+        }
+    }
+
+    /// ファイルの変更を通知する
+    ///
+    /// USML ドキュメントなら次回リゾルバーから読み直す。import 先のファイルなら
+    /// その解決結果を捨て、参照しているドキュメントの照合規則だけを再実行対象にする
+    pub fn invalidate(&mut self, path: &str) {
+        if let Some(entry) = self.documents.get_mut(path) {
+            *entry = DocumentEntry::default();
+        }
+
+        let openapi_count = self.openapi.len();
+        self.openapi.retain(|key, _| !same_file(&key.0, path));
+        let openapi_changed = self.openapi.len() != openapi_count;
+        let dbml_count = self.dbml.len();
+        self.dbml.retain(|file, _| !same_file(file, path));
+        let dbml_changed = self.dbml.len() != dbml_count;
+        if !openapi_changed && !dbml_changed {
+            return;
+        }
+
+        for (doc_path, entry) in &mut self.documents {
+            let Some(doc) = &entry.document else {
+                continue;
+            };
+            let plan = validator::plan_imports(doc, &base_dir(doc_path));
+            if openapi_changed
+                && plan
+                    .openapi
+                    .is_some_and(|(file, ..)| same_file(&file, path))
+            {
+                // DBML の規則もプロパティの型を参照するので作り直す
+                entry.openapi_rules = None;
+                entry.dbml_rules = None;
+            }
+            if dbml_changed && plan.dbml_files.iter().any(|file| same_file(file, path)) {
+                entry.dbml_rules = None;
+            }
+        }
+    }
+
+    /// ドキュメントをセッションから外す（import 先のキャッシュは残す）
+    pub fn remove(&mut self, path: &str) {
+        self.documents.remove(path);
+    }
+
+    /// キャッシュ済みのパース結果
+    pub fn document(&self, path: &str) -> Option<&UsmlDocument> {
+        self.documents.get(path)?.document.as_ref()
+    }
+
+    /// キャッシュ済みの解決結果からドキュメントの ResolveContext を組み立てる
+    pub fn context(&self, path: &str) -> Option<ResolveContext> {
+        let doc = self.document(path)?;
+        let plan = validator::plan_imports(doc, &base_dir(path));
+        Some(assemble(&plan, &self.openapi, &self.dbml).0)
+    }
+
+    /// ドキュメントを検証する。前回から変わっていない段階はキャッシュを使う
+    pub fn validate(&mut self, path: &str) -> Result<Run, UsmlError> {
+        let started = Instant::now();
+        let mut timings = Timings::default();
+
+        let entry = self.documents.entry(path.to_string()).or_default();
+        let source = match &mut entry.source {
+            Some(source) => source,
+            slot => slot.insert(self.resolver.load(path)?),
+        };
+        let doc = match &mut entry.document {
+            Some(doc) => &*doc,
+            slot => {
+                let t = Instant::now();
+                let doc = slot.insert(parser::parse(source)?);
+                timings.parse = Some(t.elapsed());
+                &*doc
+            }
+        };
+
+        let plan = validator::plan_imports(doc, &base_dir(path));
+        let t = Instant::now();
+        let mut resolved = false;
+        if let Some((file, api_path, method, status)) = &plan.openapi {
+            let key = (
+                file.clone(),
+                api_path.to_string(),
+                method.to_string(),
+                status.to_string(),
+            );
+            if !self.openapi.contains_key(&key) {
+                let single = ImportPlan {
+                    openapi: plan.openapi.clone(),
+                    dbml_files: Vec::new(),
+                    tables: BTreeMap::new(),
+                };
+                let content = self.resolver.load(file);
+                let (ctx, diagnostics) =
+                    validator::build_context(&single, Some(content), Vec::new());
+                self.openapi.insert(
+                    key,
+                    Resolved {
+                        value: ctx.openapi,
+                        diagnostics,
+                    },
+                );
+                resolved = true;
+            }
+        }
+        for file in &plan.dbml_files {
+            if !self.dbml.contains_key(file) {
+                let single = ImportPlan {
+                    openapi: None,
+                    dbml_files: vec![file.clone()],
+                    tables: BTreeMap::new(),
+                };
+                let content = self.resolver.load(file);
+                let (ctx, diagnostics) = validator::build_context(&single, None, vec![content]);
+                self.dbml.insert(
+                    file.clone(),
+                    Resolved {
+                        value: Some(ctx.dbml_tables),
+                        diagnostics,
+                    },
+                );
+                resolved = true;
+            }
+        }
+        if resolved {
+            timings.resolve = Some(t.elapsed());
+        }
+        let (ctx, resolve_errors) = assemble(&plan, &self.openapi, &self.dbml);
+
+        // 3 つの規則の組で同じ意味モデルを使う（キャッシュが全部ある場合は組み立てない）
+        let model = OnceCell::new();
+        if entry.document_rules.is_none() {
+            let t = Instant::now();
+            let model = model.get_or_init(|| Model::build(doc));
+            entry.document_rules = Some(diagnostics(validator::validate_model(doc, model)));
+            timings.document_rules = Some(t.elapsed());
+        }
+        if entry.openapi_rules.is_none() {
+            let t = Instant::now();
+            let errors = ctx
+                .openapi
+                .as_ref()
+                .map(|openapi| {
+                    let model = model.get_or_init(|| Model::build(doc));
+                    validator::validate_against_openapi(doc, model, openapi)
+                })
+                .unwrap_or_default();
+            entry.openapi_rules = Some(diagnostics(errors));
+            timings.openapi_rules = Some(t.elapsed());
+        }
+        if entry.dbml_rules.is_none() {
+            let t = Instant::now();
+            let model = model.get_or_init(|| Model::build(doc));
+            let errors = validator::validate_against_dbml(
+                doc,
+                model,
+                &ctx.dbml_tables,
+                ctx.openapi.as_ref(),
+            );
+            entry.dbml_rules = Some(diagnostics(errors));
+            timings.dbml_rules = Some(t.elapsed());
+        }
+
+        let diagnostics = [
+            &entry.document_rules,
+            &entry.openapi_rules,
+            &entry.dbml_rules,
+        ]
+        .into_iter()
+        .flatten()
+        .flatten()
+        .cloned()
+        .chain(resolve_errors)
+        .collect();
+        timings.total = started.elapsed();
+        Ok(Run {
+            diagnostics,
+            timings,
+        })
+    }
+}
+
+/// キャッシュからドキュメント 1 件分の ResolveContext と解決時の警告を組み立てる
+fn assemble(
+    plan: &ImportPlan<'_>,
+    openapi: &HashMap<OpenapiKey, Resolved<OpenapiResponse>>,
+    dbml: &HashMap<String, Resolved<Vec<DbmlTable>>>,
+) -> (ResolveContext, Vec<Diagnostic>) {
+    let mut ctx = ResolveContext::default();
+    let mut errors = Vec::new();
+
+    if let Some((file, path, method, status)) = &plan.openapi {
+        let key = (
+            file.clone(),
+            path.to_string(),
+            method.to_string(),
+            status.to_string(),
+        );
+        if let Some(resolved) = openapi.get(&key) {
+            ctx.openapi = resolved.value.clone();
+            errors.extend(resolved.diagnostics.iter().cloned());
+        }
+    }
+    let mut tables = Vec::new();
+    for (file, resolved) in plan
+        .dbml_files
+        .iter()
+        .filter_map(|file| Some((file, dbml.get(file)?)))
+    {
+        if let Some(value) = &resolved.value {
+            tables.push((file.as_str(), value.as_slice()));
+        }
+        errors.extend(resolved.diagnostics.iter().cloned());
+    }
+    let (tables, conflicts) = validator::merge_dbml_tables(tables);
+    ctx.dbml_tables = validator::rename_dbml_tables(tables, &plan.tables);
+    errors.extend(conflicts);
+
+    (ctx, errors)
+}
+
+fn diagnostics(errors: Vec<validator::ValidationError>) -> Vec<Diagnostic> {
+    diagnostic::from_errors(errors)
+}
+
+/// `specs/./schema.dbml` と `specs/schema.dbml` のような表記揺れを同一視して比較する
+fn same_file(a: &str, b: &str) -> bool {
+    Path::new(a).components().eq(Path::new(b).components())
+}
+
+fn base_dir(path: &str) -> String {
+    Path::new(path)
+        .parent()
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+    use crate::resolver::ResolverError;
+
+    const DOC: &str = r#"version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: ユーザー一覧
+  response_mapping:
+    - field: name
+      source: users.name
+"#;
+
+    /// 読み込んだパスを記録するメモリ上のリゾルバー
+    #[derive(Default)]
+    struct MemoryResolver {
+        files: RefCell<HashMap<String, String>>,
+        loads: RefCell<Vec<String>>,
+    }
+
+    impl MemoryResolver {
+        fn with(files: &[(&str, &str)]) -> Self {
+            let resolver = MemoryResolver::default();
+            for (path, content) in files {
+                resolver.set(path, content);
+            }
+            resolver
+        }
+
+        fn set(&self, path: &str, content: &str) {
+            self.files
+                .borrow_mut()
+                .insert(path.to_string(), content.to_string());
+        }
+    }
+
+    impl Resolver for &MemoryResolver {
+        fn load(&self, path: &str) -> Result<String, ResolverError> {
+            self.loads.borrow_mut().push(path.to_string());
+            self.files
+                .borrow()
+                .get(path)
+                .cloned()
+                .ok_or_else(|| ResolverError::NotFound(path.to_string()))
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "resolver-dbml")]
+    fn test_unchanged_document_reuses_every_stage() {
+        let resolver = MemoryResolver::with(&[
+            ("specs/users.usml.yaml", DOC),
+            (
+                "specs/./schema.dbml",
+                "Table users {\n  id integer\n  name varchar\n}\n",
+            ),
+        ]);
+        let mut session = Session::with_resolver(&resolver);
+
+        let first = session.validate("specs/users.usml.yaml").unwrap();
+        assert!(first.diagnostics.is_empty());
+        assert!(first.timings.parse.is_some() && first.timings.resolve.is_some());
+
+        let second = session.validate("specs/users.usml.yaml").unwrap();
+        assert!(second.timings.parse.is_none());
+        assert!(second.timings.resolve.is_none());
+        assert!(second.timings.document_rules.is_none());
+        assert_eq!(resolver.loads.borrow().len(), 2);
+    }
+
+    #[test]
+    #[cfg(feature = "resolver-dbml")]
+    fn test_invalidating_import_reruns_only_dbml_rules() {
+        let resolver = MemoryResolver::with(&[
+            ("specs/users.usml.yaml", DOC),
+            (
+                "specs/./schema.dbml",
+                "Table users {\n  id integer\n  name varchar\n}\n",
+            ),
+        ]);
+        let mut session = Session::with_resolver(&resolver);
+        session.validate("specs/users.usml.yaml").unwrap();
+
+        resolver.set("specs/./schema.dbml", "Table users {\n  id integer\n}\n");
+        session.invalidate("specs/schema.dbml");
+        let run = session.validate("specs/users.usml.yaml").unwrap();
+
+        assert!(run.timings.parse.is_none());
+        assert!(run.timings.document_rules.is_none());
+        assert!(run.timings.openapi_rules.is_none());
+        assert!(run.timings.dbml_rules.is_some());
+        assert!(
+            run.diagnostics
+                .iter()
+                .any(|d| d.rule == "response_mapping.source")
+        );
+    }
+
+    #[test]
+    fn test_updated_buffer_is_reparsed_without_reloading_imports() {
+        let resolver = MemoryResolver::with(&[(
+            "specs/./schema.dbml",
+            "Table users {\n  id integer\n  name varchar\n}\n",
+        )]);
+        let mut session = Session::with_resolver(&resolver);
+        session.update("specs/users.usml.yaml", DOC.to_string());
+        session.validate("specs/users.usml.yaml").unwrap();
+
+        session.update(
+            "specs/users.usml.yaml",
+            DOC.replace("users.name", "users.email"),
+        );
+        let run = session.validate("specs/users.usml.yaml").unwrap();
+
+        assert!(run.timings.parse.is_some());
+        assert!(run.timings.resolve.is_none());
+        assert_eq!(*resolver.loads.borrow(), vec!["specs/./schema.dbml"]);
+        let expected = validator::validate_with_resolver(
+            session.document("specs/users.usml.yaml").unwrap(),
+            "specs",
+            &&resolver,
+        );
+        assert_eq!(run.diagnostics, expected);
+    }
+}
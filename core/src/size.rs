@@ -0,0 +1,390 @@
+//! usecase のレスポンスの JSON のサイズの見積もり
+//!
+//! フィールドの型（[`plan::field_type`]）と DBML のカラム型の長さ（`varchar(n)` など）から値のバイト数を、
+//! PAGINATION のページサイズと `[size]` の要素数から配列の長さを決め、典型的な場合と最悪の場合の 2 つを見積もる。
+//! 文字列は典型的な場合を ASCII、最悪の場合を 1 文字 3 バイト（UTF-8 の日本語）として数える
+
+use serde::Serialize;
+
+use crate::api::Analysis;
+use crate::ast::Literal;
+use crate::codegen::plan::{self, ValueType};
+use crate::config::SizeConfig;
+use crate::model::{FieldKind, FieldNode};
+
+/// 長さの分からない文字列の典型的なバイト数
+const TYPICAL_STRING: u64 = 24;
+/// `text` 型のカラムの典型的なバイト数
+const TYPICAL_TEXT: u64 = 256;
+
+/// 典型的な場合と最悪の場合のバイト数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Bytes {
+    pub typical: u64,
+    pub worst: u64,
+}
+
+impl Bytes {
+    fn new(typical: u64, worst: u64) -> Self {
+        Bytes { typical, worst }
+    }
+
+    fn exact(bytes: u64) -> Self {
+        Bytes::new(bytes, bytes)
+    }
+
+    fn add(self, other: Bytes) -> Self {
+        Bytes::new(
+            self.typical.saturating_add(other.typical),
+            self.worst.saturating_add(other.worst),
+        )
+    }
+
+    /// `count` 個並べたバイト数（区切りの `,` を含む）
+    fn times(self, count: Bytes) -> Self {
+        Bytes::new(
+            count.typical.saturating_mul(self.typical.saturating_add(1)),
+            count.worst.saturating_mul(self.worst.saturating_add(1)),
+        )
+    }
+}
+
+/// 1 フィールドがレスポンス全体で占めるバイト数（配列の要素の分を合わせた値）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldSize {
+    pub path: String,
+    #[serde(flatten)]
+    pub bytes: Bytes,
+}
+
+/// 1 usecase のレスポンスの見積もり
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ResponseSize {
+    pub file: String,
+    pub usecase: String,
+    #[serde(flatten)]
+    pub bytes: Bytes,
+    /// ページの要素数（PAGINATION がある場合）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<Bytes>,
+    /// スカラーのフィールド（最悪の場合のバイト数の大きい順）
+    pub fields: Vec<FieldSize>,
+    /// 最悪の場合が `[size] budget` を超えるか
+    pub over_budget: bool,
+}
+
+/// レスポンスのサイズを見積もる
+pub fn estimate(file: &str, analysis: &Analysis, config: &SizeConfig) -> ResponseSize {
+    let usecase = &analysis.document.usecase;
+    let page = plan::filter(usecase, "PAGINATION").map(|page| {
+        let size = plan::page_size(usecase, page);
+        // limit_param で上限無くページサイズを変えられる場合は max_items まで取るとみなす
+        let max = match page.max_page_size {
+            Some(max) => max,
+            None if page.limit_param.is_some() => size.max(config.max_items),
+            None => size,
+        };
+        Bytes::new(size.into(), max.into())
+    });
+    let mut estimator = Estimator {
+        analysis,
+        config,
+        page,
+        paged: plan::paged_level(&analysis.model),
+        fields: Vec::new(),
+    };
+    let bytes = match (estimator.paged, page) {
+        // collection の配列が無ければルートの行を並べた配列を返す
+        (None, Some(page)) => Bytes::exact(2).add(estimator.object(None, page).times(page)),
+        _ => estimator.object(None, Bytes::exact(1)),
+    };
+    let mut fields = estimator.fields;
+    fields.sort_by_key(|f| std::cmp::Reverse(f.bytes.worst));
+    ResponseSize {
+        file: file.to_string(),
+        usecase: usecase.name.clone(),
+        bytes,
+        page,
+        fields,
+        over_budget: config.budget.is_some_and(|budget| bytes.worst > budget),
+    }
+}
+
+struct Estimator<'a> {
+    analysis: &'a Analysis,
+    config: &'a SizeConfig,
+    page: Option<Bytes>,
+    /// ページの要素数で並ぶ配列（collection）
+    paged: Option<usize>,
+    fields: Vec<FieldSize>,
+}
+
+impl Estimator<'_> {
+    /// `parent` の直下のフィールドを持つオブジェクト 1 つのバイト数。`count` はレスポンス中のオブジェクトの数
+    fn object(&mut self, parent: Option<usize>, count: Bytes) -> Bytes {
+        let analysis = self.analysis;
+        let mut bytes = Bytes::exact(2);
+        for (index, field) in plan::children(&analysis.model, parent) {
+            // `"name":` と区切りの `,`
+            let key = Bytes::exact((field.name.len() as u64).saturating_add(4));
+            let value = if field.kind == FieldKind::Array {
+                self.array(index, field, count)
+            } else {
+                let value = scalar(analysis, field, self.config);
+                self.record(field, key.add(value), count);
+                value
+            };
+            bytes = bytes.add(key).add(value);
+        }
+        bytes
+    }
+
+    fn array(&mut self, index: usize, field: &FieldNode, count: Bytes) -> Bytes {
+        let items = match self.page {
+            Some(page) if self.paged == Some(index) => page,
+            _ => Bytes::new(self.config.items.into(), self.config.max_items.into()),
+        };
+        let total = Bytes::new(
+            count.typical.saturating_mul(items.typical),
+            count.worst.saturating_mul(items.worst),
+        );
+        let nested = plan::children(&self.analysis.model, Some(index))
+            .next()
+            .is_some();
+        let item = if nested {
+            self.object(Some(index), total)
+        } else {
+            let value = scalar(self.analysis, field, self.config);
+            self.record(field, value, total);
+            value
+        };
+        Bytes::exact(2).add(item.times(items))
+    }
+
+    fn record(&mut self, field: &FieldNode, bytes: Bytes, count: Bytes) {
+        self.fields.push(FieldSize {
+            path: field.path.clone(),
+            bytes: Bytes::new(
+                count.typical.saturating_mul(bytes.typical),
+                count.worst.saturating_mul(bytes.worst),
+            ),
+        });
+    }
+}
+
+/// スカラーの値 1 つのバイト数
+fn scalar(analysis: &Analysis, field: &FieldNode, config: &SizeConfig) -> Bytes {
+    if let Some(value) = &field.value
+        && value.param().is_none()
+    {
+        return Bytes::exact(literal(value));
+    }
+    let openapi = analysis
+        .schemas
+        .openapi
+        .as_ref()
+        .and_then(|o| o.property(&field.path));
+    if let Some(property) = openapi
+        && !property.enum_values.is_empty()
+    {
+        return choices(property.enum_values.iter().map(literal));
+    }
+    let column = field
+        .source
+        .as_ref()
+        .and_then(|s| plan::column(analysis, &s.table, &s.column));
+    if let Some(column) = column
+        && !column.enum_values.is_empty()
+    {
+        return choices(
+            column
+                .enum_values
+                .iter()
+                .map(|v| (v.len() as u64).saturating_add(2)),
+        );
+    }
+    match plan::field_type(analysis, field).value {
+        ValueType::Int => Bytes::new(4, 11),
+        ValueType::Long => Bytes::new(6, 20),
+        ValueType::Float | ValueType::Double => Bytes::new(8, 24),
+        ValueType::Decimal => {
+            // numeric(p, s) は符号と小数点を加えた p + 2 桁まで
+            let worst = column
+                .and_then(|c| length(&c.data_type))
+                .map_or(24, |precision| precision.saturating_add(2));
+            Bytes::new(worst.min(8), worst)
+        }
+        ValueType::Boolean => Bytes::new(5, 5),
+        ValueType::Uuid => Bytes::exact(38),
+        ValueType::Date => Bytes::exact(12),
+        ValueType::Time => Bytes::new(10, 17),
+        // 小数秒は 6 桁まで
+        ValueType::DateTime => Bytes::new(21, 28),
+        ValueType::OffsetDateTime => Bytes::new(22, 34),
+        // 別の usecase の結果は長さの分からない文字列とみなす
+        ValueType::String | ValueType::Any => {
+            let data_type = column.map(|c| c.data_type.to_ascii_lowercase());
+            if let Some(length) = data_type.as_deref().and_then(length) {
+                Bytes::new(
+                    length.min(TYPICAL_STRING) + 2,
+                    length.saturating_mul(3).saturating_add(2),
+                )
+            } else if data_type.as_deref() == Some("text") {
+                Bytes::new(TYPICAL_TEXT + 2, config.max_string.saturating_add(2))
+            } else {
+                Bytes::new(TYPICAL_STRING + 2, config.max_string.saturating_add(2))
+            }
+        }
+    }
+}
+
+/// 候補の値の平均と最大のバイト数
+fn choices(lengths: impl Iterator<Item = u64>) -> Bytes {
+    let lengths: Vec<u64> = lengths.collect();
+    let sum = lengths.iter().fold(0u64, |sum, &n| sum.saturating_add(n));
+    Bytes::new(
+        sum / (lengths.len() as u64).max(1),
+        lengths.iter().copied().max().unwrap_or(0),
+    )
+}
+
+/// JSON にした値のバイト数
+fn literal(value: &Literal) -> u64 {
+    match value {
+        Literal::Bool(b) => b.to_string().len() as u64,
+        Literal::Integer(n) => n.to_string().len() as u64,
+        Literal::Number(n) => n.to_string().len() as u64,
+        Literal::String(s) => (s.len() as u64).saturating_add(2),
+    }
+}
+
+/// `varchar(255)`・`numeric(10, 2)` の先頭の数（文字列の長さ・数値の桁数）
+fn length(data_type: &str) -> Option<u64> {
+    let (_, args) = data_type.split_once('(')?;
+    args.split([',', ')']).next()?.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resolver::{DbmlColumn, DbmlTable};
+    use crate::{api, parser};
+
+    fn analysis(pagination: &str) -> Analysis {
+        let doc = parser::parse(&format!(
+            r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["posts"]
+usecase:
+  name: 投稿一覧
+  response_mapping:
+    - field: posts
+      type: array
+      source_table: posts
+      collection: true
+      fields:
+        - field: id
+          source: posts.id
+        - field: title
+          source: posts.title
+        - field: tags
+          type: array
+          source: posts.tag
+  filters:
+{}
+"#,
+            pagination
+        ))
+        .unwrap();
+        let mut analysis = api::analyze(doc);
+        let column = |name: &str, data_type: &str| DbmlColumn {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            ..DbmlColumn::default()
+        };
+        analysis.schemas.dbml_tables = vec![DbmlTable {
+            name: "posts".to_string(),
+            columns: vec!["id".to_string(), "title".to_string(), "tag".to_string()],
+            column_details: vec![
+                column("id", "uuid"),
+                column("title", "varchar(100)"),
+                column("tag", "varchar(10)"),
+            ],
+            location: None,
+        }];
+        analysis
+    }
+
+    #[test]
+    fn test_estimate_multiplies_by_page_size() {
+        let analysis = analysis(
+            "    - param: page\n      maps_to: PAGINATION\n      page_size: 20\n      max_page_size: 50",
+        );
+        let size = estimate("posts.usml.yaml", &analysis, &SizeConfig::default());
+        assert_eq!(size.page, Some(Bytes::new(20, 50)));
+        // 1 件: {"id":<uuid>,"title":<100 文字>,"tags":[<10 文字> × 10〜100]}
+        let item = Bytes::new(
+            2 + (6 + 38) + (9 + 26) + (8 + 2 + 10 * 13),
+            2 + (6 + 38) + (9 + 302) + (8 + 2 + 100 * 33),
+        );
+        let posts = Bytes::exact(2).add(item.times(Bytes::new(20, 50)));
+        assert_eq!(size.bytes, Bytes::exact(2 + 9).add(posts));
+        assert_eq!(size.fields[0].path, "posts.tags");
+        assert_eq!(
+            size.fields[0].bytes,
+            Bytes::new(20 * 10 * 12, 50 * 100 * 32)
+        );
+        assert!(!size.over_budget);
+
+        let config = SizeConfig {
+            budget: Some(100_000),
+            ..SizeConfig::default()
+        };
+        let over = estimate("posts.usml.yaml", &analysis, &config);
+        assert!(over.over_budget);
+    }
+
+    #[test]
+    fn test_estimate_unbounded_limit_uses_max_items() {
+        let analysis =
+            analysis("    - param: page\n      maps_to: PAGINATION\n      limit_param: limit");
+        let config = SizeConfig {
+            budget: Some(256 * 1024),
+            ..SizeConfig::default()
+        };
+        let size = estimate("posts.usml.yaml", &analysis, &config);
+        assert_eq!(size.page, Some(Bytes::new(20, 100)));
+        assert!(size.bytes.typical < size.bytes.worst);
+        assert!(size.over_budget);
+    }
+
+    #[test]
+    fn test_estimate_saturates_on_deep_nesting() {
+        let mut yaml = String::from(
+            "version: \"0.1\"\nimport:\n  dbml:\n    - ./schema.dbml#tables[\"posts\"]\nusecase:\n  name: 入れ子\n  response_mapping:\n",
+        );
+        for depth in 0..30 {
+            let indent = "  ".repeat(depth * 2 + 2);
+            yaml.push_str(&format!(
+                "{indent}- field: level{depth}\n{indent}  type: array\n{indent}  fields:\n"
+            ));
+        }
+        let indent = "  ".repeat(30 * 2 + 2);
+        yaml.push_str(&format!(
+            "{indent}- field: title\n{indent}  source: posts.title\n"
+        ));
+        let analysis = api::analyze(parser::parse(&yaml).unwrap());
+
+        let config = SizeConfig {
+            budget: Some(1024 * 1024),
+            ..SizeConfig::default()
+        };
+        let size = estimate("deep.usml.yaml", &analysis, &config);
+        // 要素数を掛け合わせると u64 を超える
+        assert_eq!(size.bytes, Bytes::exact(u64::MAX));
+        assert_eq!(size.fields[0].bytes, Bytes::exact(u64::MAX));
+        assert!(size.over_budget);
+    }
+}
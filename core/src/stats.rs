@@ -0,0 +1,177 @@
+//! ワークスペースの仕様の規模・複雑さを表す指標
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::ast::UsmlDocument;
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::model::{FieldNode, Model};
+
+/// 1 ファイル分の指標
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileStats {
+    pub file: String,
+    pub usecase: String,
+    /// レスポンスフィールドの数（入れ子含む）
+    pub fields: usize,
+    /// 結合の数（join_chain は段ごとに数える）
+    pub joins: usize,
+    /// フィールドに届くまでに通る結合の段数の最大値
+    pub max_join_depth: usize,
+    pub transforms: usize,
+    /// 参照するテーブル（名前順）
+    pub tables: Vec<String>,
+    pub errors: usize,
+    pub warnings: usize,
+}
+
+/// ファイルごとの指標と、ワークスペース全体の集計
+#[derive(Debug, Default, Serialize)]
+pub struct WorkspaceStats {
+    pub files: Vec<FileStats>,
+    pub fields: usize,
+    /// 結合の段数ごとのフィールド数
+    pub join_depths: BTreeMap<usize, usize>,
+    /// transform の種類ごとの使用回数
+    pub transform_usage: BTreeMap<String, usize>,
+    /// テーブルごとの参照する usecase の数
+    pub table_fan_in: BTreeMap<String, usize>,
+    pub errors: usize,
+    pub warnings: usize,
+}
+
+impl WorkspaceStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 1 ファイル分の指標を計算して集計に加える。`diagnostics` はそのファイルの検証結果
+    pub fn add(&mut self, file: &str, doc: &UsmlDocument, diagnostics: &[Diagnostic]) {
+        let model = Model::build(doc);
+        let depths: Vec<usize> = model.fields.iter().map(|f| join_depth(&model, f)).collect();
+        for &depth in &depths {
+            *self.join_depths.entry(depth).or_default() += 1;
+        }
+        for transform in &doc.usecase.transforms {
+            *self
+                .transform_usage
+                .entry(transform.r#type.to_ascii_uppercase())
+                .or_default() += 1;
+        }
+        let tables = referenced_tables(&model);
+        for table in &tables {
+            *self.table_fan_in.entry(table.clone()).or_default() += 1;
+        }
+
+        let count = |severity| {
+            diagnostics
+                .iter()
+                .filter(|d| d.severity == severity)
+                .count()
+        };
+        let stats = FileStats {
+            file: file.to_string(),
+            usecase: doc.usecase.name.clone(),
+            fields: model.fields.len(),
+            joins: model.joins.len(),
+            max_join_depth: depths.iter().copied().max().unwrap_or(0),
+            transforms: doc.usecase.transforms.len(),
+            tables,
+            errors: count(Severity::Error),
+            warnings: count(Severity::Warning),
+        };
+        self.fields += stats.fields;
+        self.errors += stats.errors;
+        self.warnings += stats.warnings;
+        self.files.push(stats);
+    }
+}
+
+/// フィールド自身と祖先のフィールドに付いた結合の段数の合計
+pub fn join_depth(model: &Model, field: &FieldNode) -> usize {
+    let mut depth = field.joins.len();
+    let mut parent = field.parent;
+    while let Some(index) = parent {
+        depth += model.fields[index].joins.len();
+        parent = model.fields[index].parent;
+    }
+    depth
+}
+
+/// source・transform・結合・配列要素の生成で実際に参照されるテーブル（名前順）
+pub fn referenced_tables(model: &Model) -> Vec<String> {
+    let mut tables: Vec<String> = model
+        .tables
+        .iter()
+        .filter(|t| {
+            !t.columns.is_empty()
+                || model.joins.iter().any(|j| j.table == t.name)
+                || model
+                    .fields
+                    .iter()
+                    .any(|f| f.source_table.as_deref() == Some(t.name.as_str()))
+        })
+        .map(|t| t.name.clone())
+        .collect();
+    tables.sort();
+    tables
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+    use crate::validator::Validator;
+
+    #[test]
+    fn test_stats_aggregate_files() {
+        let doc = parser::parse(
+            r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["posts"]
+    - ./schema.dbml#tables["users"]
+    - ./schema.dbml#tables["comments"]
+usecase:
+  name: 投稿一覧
+  response_mapping:
+    - field: posts
+      type: array
+      source_table: posts
+      fields:
+        - field: title
+          source: posts.title
+        - field: author_name
+          source: users.name
+          join:
+            table: users
+            on: posts.user_id = users.id
+  transforms:
+    - target: author_name
+      type: coalesce
+      sources: [users.name]
+      fallback: "退会済み"
+"#,
+        )
+        .unwrap();
+        let diagnostics = Validator::new().validate(&doc);
+
+        let mut stats = WorkspaceStats::new();
+        stats.add("a.usml.yaml", &doc, &diagnostics);
+        stats.add("b.usml.yaml", &doc, &diagnostics);
+
+        let file = &stats.files[0];
+        assert_eq!(file.fields, 3);
+        assert_eq!(file.joins, 1);
+        assert_eq!(file.max_join_depth, 1);
+        assert_eq!(file.tables, vec!["posts", "users"]);
+        assert_eq!(stats.fields, 6);
+        assert_eq!(stats.join_depths[&0], 4);
+        assert_eq!(stats.join_depths[&1], 2);
+        assert_eq!(stats.transform_usage["COALESCE"], 2);
+        assert_eq!(stats.table_fan_in["users"], 2);
+        assert!(!stats.table_fan_in.contains_key("comments"));
+    }
+}
@@ -0,0 +1,518 @@
+use serde::Serialize;
+use yaml_rust2::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust2::scanner::{Marker, TScalarStyle};
+
+use crate::diagnostic::Location;
+
+/// ソース上のバイト範囲（`start..end`）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn contains(&self, offset: usize) -> bool {
+        self.start <= offset && offset <= self.end
+    }
+}
+
+/// 1 始まりの行・列（列は文字単位）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// 行・列で表した範囲
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+
+/// スカラーの書式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScalarStyle {
+    Plain,
+    SingleQuoted,
+    DoubleQuoted,
+    /// `|` / `>` のブロックスカラー
+    Block,
+}
+
+/// 位置情報付きの YAML ノード
+#[derive(Debug, Clone)]
+pub struct SyntaxNode {
+    pub span: Span,
+    pub kind: SyntaxKind,
+}
+
+#[derive(Debug, Clone)]
+pub enum SyntaxKind {
+    Scalar { value: String, style: ScalarStyle },
+    Mapping(Vec<SyntaxEntry>),
+    Sequence(Vec<SyntaxNode>),
+}
+
+/// マッピングの 1 エントリ
+#[derive(Debug, Clone)]
+pub struct SyntaxEntry {
+    pub key: String,
+    pub key_span: Span,
+    pub value: SyntaxNode,
+}
+
+impl SyntaxNode {
+    /// マッピングのエントリを返す
+    pub fn entry(&self, key: &str) -> Option<&SyntaxEntry> {
+        match &self.kind {
+            SyntaxKind::Mapping(entries) => entries.iter().find(|e| e.key == key),
+            _ => None,
+        }
+    }
+
+    /// マッピングの値を返す
+    pub fn get(&self, key: &str) -> Option<&SyntaxNode> {
+        self.entry(key).map(|e| &e.value)
+    }
+
+    pub fn entries(&self) -> &[SyntaxEntry] {
+        match &self.kind {
+            SyntaxKind::Mapping(entries) => entries,
+            _ => &[],
+        }
+    }
+
+    /// シーケンスの要素を返す
+    pub fn items(&self) -> &[SyntaxNode] {
+        match &self.kind {
+            SyntaxKind::Sequence(items) => items,
+            _ => &[],
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match &self.kind {
+            SyntaxKind::Scalar { value, .. } => Some(value),
+            _ => None,
+        }
+    }
+
+    /// 値が空（`key:` のみ）のスカラーか
+    pub fn is_empty_scalar(&self) -> bool {
+        matches!(&self.kind, SyntaxKind::Scalar { value, .. } if value.is_empty())
+            && self.span.start == self.span.end
+    }
+}
+
+/// YAML テキストと各ノードのバイト範囲の対応表
+///
+/// serde_yaml の AST は位置を持たないため、エディタ向けの機能（アウトライン・ホバー・
+/// 書式を保った編集）はこのツリーで位置を引く
+#[derive(Debug, Clone)]
+pub struct SyntaxTree {
+    pub root: SyntaxNode,
+    line_starts: Vec<usize>,
+}
+
+/// YAML の構文エラー
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("YAML 構文エラー ({line}:{column}): {message}")]
+pub struct SyntaxError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl SyntaxTree {
+    pub fn parse(source: &str) -> Result<Self, SyntaxError> {
+        let line_starts = line_starts(source);
+        let mut builder = TreeBuilder {
+            source,
+            line_starts: &line_starts,
+            stack: Vec::new(),
+            root: None,
+        };
+        Parser::new_from_str(source)
+            .load(&mut builder, false)
+            .map_err(|e| SyntaxError {
+                line: e.marker().line(),
+                column: e.marker().col() + 1,
+                message: e.info().to_string(),
+            })?;
+        let root = builder.root.unwrap_or(SyntaxNode {
+            span: Span { start: 0, end: 0 },
+            kind: SyntaxKind::Mapping(Vec::new()),
+        });
+        Ok(SyntaxTree { root, line_starts })
+    }
+
+    /// `["usecase", "response_mapping", "0", "field"]` のようなパスでノードを引く
+    pub fn lookup(&self, path: &[&str]) -> Option<&SyntaxNode> {
+        let mut node = &self.root;
+        for segment in path {
+            node = match &node.kind {
+                SyntaxKind::Mapping(_) => node.get(segment)?,
+                SyntaxKind::Sequence(items) => items.get(segment.parse::<usize>().ok()?)?,
+                SyntaxKind::Scalar { .. } => return None,
+            };
+        }
+        Some(node)
+    }
+
+    /// JSON Pointer（`/usecase/filters/0/condition`）のノード。ソースに無ければ、ソースにある最も深い祖先
+    pub fn nearest(&self, pointer: &str) -> &SyntaxNode {
+        let mut node = &self.root;
+        for segment in pointer.split('/').skip(1) {
+            let segment = segment.replace("~1", "/").replace("~0", "~");
+            let next = match &node.kind {
+                SyntaxKind::Mapping(_) => node.get(&segment),
+                SyntaxKind::Sequence(items) => {
+                    segment.parse::<usize>().ok().and_then(|i| items.get(i))
+                }
+                SyntaxKind::Scalar { .. } => None,
+            };
+            match next {
+                Some(next) => node = next,
+                None => break,
+            }
+        }
+        node
+    }
+
+    /// バイト位置を含む最も深いノードの JSON Pointer（例: `/usecase/response_mapping/3/join/on`）
+    ///
+    /// キーの位置ならそのエントリの値を指す。キーの `~`・`/` は `~0`・`~1` にする
+    pub fn pointer(&self, offset: usize) -> String {
+        let mut pointer = String::new();
+        let mut node = &self.root;
+        loop {
+            let (segment, next) = match &node.kind {
+                SyntaxKind::Mapping(entries) => {
+                    let Some(entry) = entries
+                        .iter()
+                        .find(|e| e.key_span.contains(offset) || e.value.span.contains(offset))
+                    else {
+                        break;
+                    };
+                    if entry.key_span.contains(offset) {
+                        pointer.push('/');
+                        pointer.push_str(&escape_pointer(&entry.key));
+                        break;
+                    }
+                    (escape_pointer(&entry.key), &entry.value)
+                }
+                SyntaxKind::Sequence(items) => {
+                    let Some((index, item)) = items
+                        .iter()
+                        .enumerate()
+                        .find(|(_, item)| item.span.contains(offset))
+                    else {
+                        break;
+                    };
+                    (index.to_string(), item)
+                }
+                SyntaxKind::Scalar { .. } => break,
+            };
+            pointer.push('/');
+            pointer.push_str(&segment);
+            node = next;
+        }
+        pointer
+    }
+
+    /// バイト位置を 1 始まりの (行, 列) に変換する。列は文字単位
+    pub fn line_col(&self, source: &str, offset: usize) -> (usize, usize) {
+        let line = self.line_starts.partition_point(|&s| s <= offset).max(1);
+        let start = self.line_starts[line - 1];
+        let column = source[start..offset.min(source.len())].chars().count() + 1;
+        (line, column)
+    }
+
+    /// 1 始まりの (行, 列) をバイト位置に変換する
+    pub fn offset(&self, source: &str, line: usize, column: usize) -> usize {
+        let Some(&start) = self.line_starts.get(line.saturating_sub(1)) else {
+            return source.len();
+        };
+        char_offset(source, start, column.saturating_sub(1))
+    }
+
+    /// バイト範囲を行・列の範囲に変換する
+    pub fn range(&self, source: &str, span: Span) -> Range {
+        let position = |offset| {
+            let (line, column) = self.line_col(source, offset);
+            Position { line, column }
+        };
+        Range {
+            start: position(span.start),
+            end: position(span.end),
+        }
+    }
+
+    /// バイト位置から診断用の Location を作る
+    pub fn location(&self, source: &str, file: &str, offset: usize) -> Location {
+        let (line, column) = self.line_col(source, offset);
+        Location::at(file, line, column)
+    }
+
+    /// 指定位置を含む行の先頭バイト位置
+    pub fn line_start(&self, offset: usize) -> usize {
+        let line = self.line_starts.partition_point(|&s| s <= offset).max(1);
+        self.line_starts[line - 1]
+    }
+}
+
+fn line_starts(source: &str) -> Vec<usize> {
+    let mut starts = vec![0];
+    starts.extend(source.match_indices('\n').map(|(i, _)| i + 1));
+    starts
+}
+
+/// 行頭 `line_start` から `chars` 文字進んだバイト位置
+fn char_offset(source: &str, line_start: usize, chars: usize) -> usize {
+    source[line_start..]
+        .char_indices()
+        .nth(chars)
+        .map(|(i, _)| line_start + i)
+        .unwrap_or(source.len())
+}
+
+enum Frame {
+    Mapping {
+        start: Option<usize>,
+        entries: Vec<SyntaxEntry>,
+        pending_key: Option<(String, Span)>,
+    },
+    Sequence {
+        start: Option<usize>,
+        items: Vec<SyntaxNode>,
+    },
+}
+
+struct TreeBuilder<'a> {
+    source: &'a str,
+    line_starts: &'a [usize],
+    stack: Vec<Frame>,
+    root: Option<SyntaxNode>,
+}
+
+impl TreeBuilder<'_> {
+    fn offset(&self, mark: Marker) -> usize {
+        match self.line_starts.get(mark.line().saturating_sub(1)) {
+            Some(&start) => char_offset(self.source, start, mark.col()),
+            None => self.source.len(),
+        }
+    }
+
+    fn attach(&mut self, node: SyntaxNode) {
+        match self.stack.last_mut() {
+            Some(Frame::Mapping {
+                start,
+                entries,
+                pending_key,
+            }) => match pending_key.take() {
+                Some((key, key_span)) => entries.push(SyntaxEntry {
+                    key,
+                    key_span,
+                    value: node,
+                }),
+                None => {
+                    start.get_or_insert(node.span.start);
+                    let key = node.as_str().unwrap_or_default().to_string();
+                    *pending_key = Some((key, node.span));
+                }
+            },
+            Some(Frame::Sequence { start, items }) => {
+                start.get_or_insert(node.span.start);
+                items.push(node);
+            }
+            None => self.root = Some(node),
+        }
+    }
+
+    fn scalar_end(&self, start: usize, value: &str, style: TScalarStyle) -> usize {
+        let rest = &self.source[start..];
+        match style {
+            TScalarStyle::Plain => {
+                if value.is_empty() || rest.starts_with(value) {
+                    start + value.len()
+                } else {
+                    start + plain_line_len(rest)
+                }
+            }
+            TScalarStyle::SingleQuoted => start + quoted_len(rest, '\''),
+            TScalarStyle::DoubleQuoted => start + quoted_len(rest, '"'),
+            TScalarStyle::Literal | TScalarStyle::Folded => block_scalar_end(self.source, start),
+        }
+    }
+}
+
+impl MarkedEventReceiver for TreeBuilder<'_> {
+    fn on_event(&mut self, event: Event, mark: Marker) {
+        match event {
+            Event::MappingStart(..) => self.stack.push(Frame::Mapping {
+                start: None,
+                entries: Vec::new(),
+                pending_key: None,
+            }),
+            Event::SequenceStart(..) => self.stack.push(Frame::Sequence {
+                start: None,
+                items: Vec::new(),
+            }),
+            Event::MappingEnd | Event::SequenceEnd => {
+                let fallback = self.offset(mark);
+                let node = match self.stack.pop() {
+                    Some(Frame::Mapping { start, entries, .. }) => SyntaxNode {
+                        span: Span {
+                            start: start.unwrap_or(fallback),
+                            end: entries.last().map_or(fallback, |e| e.value.span.end),
+                        },
+                        kind: SyntaxKind::Mapping(entries),
+                    },
+                    Some(Frame::Sequence { start, items }) => SyntaxNode {
+                        span: Span {
+                            start: start.unwrap_or(fallback),
+                            end: items.last().map_or(fallback, |i| i.span.end),
+                        },
+                        kind: SyntaxKind::Sequence(items),
+                    },
+                    None => return,
+                };
+                self.attach(node);
+            }
+            Event::Scalar(value, style, ..) => {
+                let start = self.offset(mark);
+                let end = self.scalar_end(start, &value, style);
+                let style = match style {
+                    TScalarStyle::SingleQuoted => ScalarStyle::SingleQuoted,
+                    TScalarStyle::DoubleQuoted => ScalarStyle::DoubleQuoted,
+                    TScalarStyle::Literal | TScalarStyle::Folded => ScalarStyle::Block,
+                    _ => ScalarStyle::Plain,
+                };
+                self.attach(SyntaxNode {
+                    span: Span { start, end },
+                    kind: SyntaxKind::Scalar { value, style },
+                });
+            }
+            Event::Alias(_) => {
+                let start = self.offset(mark);
+                self.attach(SyntaxNode {
+                    span: Span { start, end: start },
+                    kind: SyntaxKind::Scalar {
+                        value: String::new(),
+                        style: ScalarStyle::Plain,
+                    },
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+/// 複数行のプレーンスカラーなど値と原文が一致しない場合は行末（コメント手前）までとする
+fn plain_line_len(rest: &str) -> usize {
+    let line = rest.split('\n').next().unwrap_or_default();
+    let line = line.find(" #").map_or(line, |i| &line[..i]);
+    line.trim_end().len()
+}
+
+/// 閉じ引用符の直後までの長さ
+fn quoted_len(rest: &str, quote: char) -> usize {
+    let mut chars = rest.char_indices().skip(1).peekable();
+    while let Some((i, ch)) = chars.next() {
+        if quote == '"' && ch == '\\' {
+            chars.next();
+        } else if ch == quote {
+            if quote == '\'' && chars.peek().is_some_and(|&(_, c)| c == '\'') {
+                chars.next();
+            } else {
+                return i + 1;
+            }
+        }
+    }
+    rest.len()
+}
+
+/// ブロックスカラーの最終行の末尾。インジケータ行より深く字下げされた行を本文とみなす
+fn block_scalar_end(source: &str, start: usize) -> usize {
+    let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+    let indent = indent_width(&source[line_start..]);
+    let mut end = source[start..]
+        .find('\n')
+        .map_or(source.len(), |i| start + i);
+    let mut cursor = end;
+    while cursor < source.len() {
+        let next = cursor + 1;
+        let line_end = source[next..].find('\n').map_or(source.len(), |i| next + i);
+        let line = &source[next..line_end];
+        if line.trim().is_empty() {
+            cursor = line_end;
+            continue;
+        }
+        if indent_width(line) <= indent {
+            break;
+        }
+        end = line_end;
+        cursor = line_end;
+    }
+    end
+}
+
+fn indent_width(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+/// JSON Pointer の参照トークンのエスケープ（RFC 6901）
+fn escape_pointer(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOC: &str = "# コメント\nversion: \"0.1\"\nusecase:\n  name: 日本語 # 名前\n  response_mapping:\n    - field: id\n      source: 'users.id'\n";
+
+    #[test]
+    fn test_spans_point_at_source_text() {
+        let tree = SyntaxTree::parse(DOC).unwrap();
+        let name = tree.lookup(&["usecase", "name"]).unwrap();
+        assert_eq!(&DOC[name.span.start..name.span.end], "日本語");
+
+        let source = tree
+            .lookup(&["usecase", "response_mapping", "0", "source"])
+            .unwrap();
+        assert_eq!(&DOC[source.span.start..source.span.end], "'users.id'");
+        assert_eq!(source.as_str(), Some("users.id"));
+        assert_eq!(tree.line_col(DOC, source.span.start), (7, 15));
+        assert_eq!(
+            tree.pointer(source.span.start),
+            "/usecase/response_mapping/0/source"
+        );
+        assert_eq!(
+            tree.nearest("/usecase/response_mapping/0/source").span,
+            source.span
+        );
+        // ソースに無いノードは最も深い祖先
+        assert_eq!(
+            tree.nearest("/usecase/response_mapping/0/join/on").span,
+            tree.lookup(&["usecase", "response_mapping", "0"])
+                .unwrap()
+                .span
+        );
+
+        let version = tree.root.entry("version").unwrap();
+        assert_eq!(
+            &DOC[version.key_span.start..version.key_span.end],
+            "version"
+        );
+        assert_eq!(tree.pointer(version.key_span.start), "/version");
+    }
+
+    #[test]
+    fn test_syntax_error_has_position() {
+        let err = SyntaxTree::parse("a: [1, 2\nb: 3\n").unwrap_err();
+        assert!(err.line >= 1);
+    }
+}
@@ -0,0 +1,361 @@
+//! カラムの意味に合わせたそれらしい値の合成
+//!
+//! カラム名と DBML の型から値の種類（メールアドレス・`*_at` の日時・価格など）を推測し、
+//! モック（`usml generate mock`）やフィクスチャ（`usml generate tests`）に入れる値を作る。
+//! 推測が合わないカラムは `usml.toml` の `[synth.columns]` で種類か候補の値を指定する。
+//! 値は `number` だけで決まるので、同じ番号からは同じ値（同じ人の氏名とメールアドレス）になる
+
+use serde::Deserialize;
+
+use crate::ast::Literal;
+use crate::config::{ColumnSynth, SynthConfig};
+
+/// 値の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    /// `hanako.sato@example.com`
+    Email,
+    Uuid,
+    /// タイムゾーン付きの日時（`created_at` など）
+    Timestamp,
+    Date,
+    /// 価格・金額（円）
+    Price,
+    /// 件数・数量
+    Quantity,
+    /// 氏名（`佐藤 花子`）
+    PersonName,
+    FirstName,
+    LastName,
+    Username,
+    Title,
+    /// 本文・説明
+    Text,
+    Url,
+    ImageUrl,
+    Phone,
+    City,
+    PostalCode,
+}
+
+/// `name` だけで人の氏名とみなすテーブル
+const PERSON_TABLES: &[&str] = &[
+    "users",
+    "members",
+    "customers",
+    "authors",
+    "employees",
+    "accounts",
+    "profiles",
+    "staff",
+];
+
+/// 姓（漢字・ローマ字）
+const FAMILY_NAMES: &[(&str, &str)] = &[
+    ("佐藤", "sato"),
+    ("鈴木", "suzuki"),
+    ("高橋", "takahashi"),
+    ("田中", "tanaka"),
+    ("伊藤", "ito"),
+    ("渡辺", "watanabe"),
+    ("山本", "yamamoto"),
+    ("中村", "nakamura"),
+    ("小林", "kobayashi"),
+    ("加藤", "kato"),
+];
+
+/// 名（漢字・ローマ字）
+const GIVEN_NAMES: &[(&str, &str)] = &[
+    ("花子", "hanako"),
+    ("太郎", "taro"),
+    ("美咲", "misaki"),
+    ("翔太", "shota"),
+    ("結衣", "yui"),
+    ("大輔", "daisuke"),
+    ("さくら", "sakura"),
+];
+
+const TITLES: &[&str] = &[
+    "はじめての投稿",
+    "週末に行きたいカフェ 5 選",
+    "リリースノート 2.4",
+    "チームの振り返り",
+    "新機能のお知らせ",
+    "よくある質問",
+];
+
+const TEXTS: &[&str] = &[
+    "今日は新しい機能を試してみました。思ったより使いやすかったです。",
+    "来週の定例で詳細を共有します。",
+    "ご不明な点があればお気軽にお問い合わせください。",
+    "写真をたくさん撮ったので、あとでまとめて載せます。",
+];
+
+const CITIES: &[&str] = &["東京", "大阪", "名古屋", "札幌", "福岡", "横浜", "京都"];
+
+const PRICES: &[i64] = &[480, 980, 1280, 1980, 2980, 4980, 9800, 12800];
+
+/// `table.column` の値。`[synth.columns]` の指定を優先し、無ければカラム名と DBML の型から推測する
+///
+/// `table` が無い（カラムを参照しないフィールド）ときは `column` にフィールド名を渡し、推測だけ行う。
+/// 種類が分からなければ None
+pub fn value(
+    config: &SynthConfig,
+    table: Option<&str>,
+    column: &str,
+    data_type: Option<&str>,
+    primary_key: bool,
+    number: u64,
+) -> Option<Literal> {
+    let kind = match table.and_then(|table| config.column(table, column)) {
+        Some(ColumnSynth::Values(values)) if !values.is_empty() => {
+            return Some(values[(number % values.len() as u64) as usize].clone());
+        }
+        Some(ColumnSynth::Kind(kind)) => *kind,
+        _ => detect(table, column, data_type, primary_key)?,
+    };
+    Some(generate(kind, number))
+}
+
+/// カラム名と DBML の型から値の種類を推測する。型が名前の意味と合わないもの（`email` が整数など）は None
+pub fn detect(
+    table: Option<&str>,
+    column: &str,
+    data_type: Option<&str>,
+    primary_key: bool,
+) -> Option<Kind> {
+    let name = column.to_ascii_lowercase();
+    let data_type = data_type.map(|t| {
+        t.split('(')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase()
+    });
+    let is = |types: &[&str]| data_type.as_deref().is_none_or(|t| types.contains(&t));
+    let textual = data_type.as_deref().is_none_or(|t| {
+        matches!(
+            t,
+            "text" | "varchar" | "char" | "character varying" | "string"
+        )
+    });
+
+    if data_type.as_deref() == Some("uuid")
+        || ((name == "uuid" || name.ends_with("_uuid") || primary_key && name == "id")
+            && data_type.is_none())
+    {
+        return Some(Kind::Uuid);
+    }
+    if name.ends_with("_at") && is(&["timestamptz"]) {
+        return Some(Kind::Timestamp);
+    }
+    if (name.ends_with("_on") || name.ends_with("_date") || name == "birthday") && is(&["date"]) {
+        return Some(Kind::Date);
+    }
+    if ["price", "amount", "cost", "fee"]
+        .iter()
+        .any(|word| name.contains(word))
+        && is(&[
+            "numeric", "decimal", "money", "int", "integer", "bigint", "int4", "int8",
+        ])
+    {
+        return Some(Kind::Price);
+    }
+    if (name.ends_with("_count") || name == "quantity" || name == "stock")
+        && is(&[
+            "int", "integer", "smallint", "bigint", "int2", "int4", "int8",
+        ])
+    {
+        return Some(Kind::Quantity);
+    }
+    if !textual {
+        return None;
+    }
+    let person = table.is_some_and(|t| PERSON_TABLES.contains(&t.to_ascii_lowercase().as_str()));
+    Some(match name.as_str() {
+        n if n.contains("email") => Kind::Email,
+        n if n.contains("phone") || n == "tel" => Kind::Phone,
+        n if n.ends_with("url") => {
+            if ["avatar", "image", "icon", "photo", "thumbnail"]
+                .iter()
+                .any(|word| n.contains(word))
+            {
+                Kind::ImageUrl
+            } else {
+                Kind::Url
+            }
+        }
+        "first_name" | "given_name" => Kind::FirstName,
+        "last_name" | "family_name" => Kind::LastName,
+        "username" | "login" | "handle" | "screen_name" => Kind::Username,
+        "full_name" | "display_name" | "author_name" | "nickname" => Kind::PersonName,
+        "name" if person => Kind::PersonName,
+        "title" | "subject" | "headline" => Kind::Title,
+        "body" | "content" | "description" | "bio" | "comment" | "summary" | "note" => Kind::Text,
+        "city" => Kind::City,
+        "postal_code" | "zip" | "zip_code" => Kind::PostalCode,
+        _ => return None,
+    })
+}
+
+/// 種類の値。`number` が同じなら同じ値になる
+pub fn generate(kind: Kind, number: u64) -> Literal {
+    let pick = |values: &[&str]| values[(number % values.len() as u64) as usize].to_string();
+    let (family, given) = person(number);
+    Literal::String(match kind {
+        Kind::Email => format!("{}.{}@example.com", given.1, family.1),
+        Kind::Uuid => {
+            let (high, low) = (mix(number), mix(number ^ 0x5bd1_e995));
+            format!(
+                "{:08x}-{:04x}-4{:03x}-{:04x}-{:012x}",
+                high >> 32,
+                (high >> 16) & 0xffff,
+                high & 0xfff,
+                0x8000 | (low >> 48) & 0x3fff,
+                low & 0xffff_ffff_ffff
+            )
+        }
+        Kind::Timestamp => {
+            // 営業時間内の分単位の時刻
+            let hash = mix(number);
+            format!(
+                "{}T{}Z",
+                date(hash % 366),
+                time(9 * 3600 + (hash >> 16) % (10 * 60) * 60)
+            )
+        }
+        Kind::Date => date(mix(number) % 366),
+        Kind::Price => {
+            return Literal::Integer(PRICES[(number % PRICES.len() as u64) as usize]);
+        }
+        Kind::Quantity => return Literal::Integer((mix(number) % 20 + 1) as i64),
+        Kind::PersonName => format!("{} {}", family.0, given.0),
+        Kind::FirstName => given.0.to_string(),
+        Kind::LastName => family.0.to_string(),
+        Kind::Username => format!("{}_{}", given.1, family.1),
+        Kind::Title => pick(TITLES),
+        Kind::Text => pick(TEXTS),
+        Kind::Url => format!("https://example.com/{}/{}", given.1, number % 1000),
+        Kind::ImageUrl => format!("https://example.com/images/{}.png", number % 1000),
+        Kind::Phone => format!("090-{:04}-{:04}", mix(number) % 10000, number % 10000),
+        Kind::City => pick(CITIES),
+        Kind::PostalCode => format!("{:03}-{:04}", 100 + mix(number) % 900, number % 10000),
+    })
+}
+
+/// 番号の人の姓と名。名は姓と違う周期で変える
+fn person(number: u64) -> ((&'static str, &'static str), (&'static str, &'static str)) {
+    let family = FAMILY_NAMES[(number % FAMILY_NAMES.len() as u64) as usize];
+    let given = GIVEN_NAMES[(number % GIVEN_NAMES.len() as u64 * 3) as usize % GIVEN_NAMES.len()];
+    (family, given)
+}
+
+/// 2024-01-01 から `days` 日後の日付
+pub(crate) fn date(days: u64) -> String {
+    const MONTHS: [u64; 12] = [31, 29, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
+    let mut day = days;
+    let mut month = 0;
+    while day >= MONTHS[month] {
+        day -= MONTHS[month];
+        month += 1;
+    }
+    format!("2024-{:02}-{:02}", month + 1, day + 1)
+}
+
+/// 0 時から `seconds` 秒後の時刻
+pub(crate) fn time(seconds: u64) -> String {
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// SplitMix64 の攪拌
+pub(crate) fn mix(value: u64) -> u64 {
+    let mut z = value;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn test_detect_by_name_and_type() {
+        assert_eq!(
+            detect(Some("users"), "email", Some("varchar(255)"), false),
+            Some(Kind::Email)
+        );
+        assert_eq!(detect(Some("users"), "email", Some("int"), false), None);
+        assert_eq!(
+            detect(Some("posts"), "created_at", None, false),
+            Some(Kind::Timestamp)
+        );
+        // タイムゾーンの無い日時は型から値を作る
+        assert_eq!(
+            detect(Some("posts"), "created_at", Some("timestamp"), false),
+            None
+        );
+        assert_eq!(
+            detect(Some("orders"), "id", Some("uuid"), true),
+            Some(Kind::Uuid)
+        );
+        assert_eq!(
+            detect(Some("items"), "unit_price", Some("numeric(10,2)"), false),
+            Some(Kind::Price)
+        );
+        assert_eq!(
+            detect(Some("users"), "name", None, false),
+            Some(Kind::PersonName)
+        );
+        assert_eq!(detect(Some("tags"), "name", None, false), None);
+        assert_eq!(
+            detect(None, "avatar_url", None, false),
+            Some(Kind::ImageUrl)
+        );
+    }
+
+    #[test]
+    fn test_generate_is_stable_and_consistent() {
+        assert_eq!(
+            generate(Kind::PersonName, 1),
+            Literal::String("鈴木 翔太".to_string())
+        );
+        assert_eq!(
+            generate(Kind::Email, 1),
+            Literal::String("shota.suzuki@example.com".to_string())
+        );
+        assert_eq!(generate(Kind::Price, 3), Literal::Integer(1980));
+        assert_eq!(generate(Kind::Uuid, 5), generate(Kind::Uuid, 5));
+        assert_ne!(generate(Kind::Uuid, 5), generate(Kind::Uuid, 6));
+        let Literal::String(timestamp) = generate(Kind::Timestamp, 2) else {
+            panic!("timestamp is not a string");
+        };
+        assert!(timestamp.starts_with("2024-") && timestamp.ends_with(":00Z"));
+    }
+
+    #[test]
+    fn test_value_prefers_config_overrides() {
+        let config = Config::parse(
+            "[synth.columns]\n\"users.contact\" = \"email\"\n\"*.status\" = [\"draft\", \"published\"]\n",
+            "usml.toml",
+        )
+        .unwrap();
+        let synth = &config.synth;
+        assert_eq!(
+            value(synth, Some("users"), "contact", None, false, 0),
+            Some(Literal::String("hanako.sato@example.com".to_string()))
+        );
+        assert_eq!(
+            value(synth, Some("posts"), "status", None, false, 1),
+            Some(Literal::String("published".to_string()))
+        );
+        assert_eq!(value(synth, Some("posts"), "kind", None, false, 1), None);
+    }
+}
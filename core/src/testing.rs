@@ -0,0 +1,232 @@
+//! 規則の単体テストの道具
+//!
+//! 組み込み規則も `CustomRule` の独自規則も、インラインの YAML と期待する診断のメッセージだけで書ける。
+//! `Validator::run_rule` が対象の規則の診断だけを返すため、他の規則の診断を除く必要は無い。
+//! YAML の `version` と `import` は省略でき、省略時は `version: "0.1"` と空の import を補う
+
+use crate::ast::UsmlDocument;
+use crate::diagnostic::Diagnostic;
+use crate::model::Model;
+use crate::parser;
+use crate::resolver::{DbmlTable, OpenapiResponse};
+use crate::rules::{CustomRule, RuleContext};
+use crate::validator::{ResolveContext, Validator};
+
+/// 1 つの規則のテスト
+pub struct RuleTest {
+    rule: String,
+    validator: Validator,
+    schemas: Option<ResolveContext>,
+}
+
+impl RuleTest {
+    /// `rule` はコード（`U004`）か名前（`join.alias`）
+    pub fn new(rule: &str) -> Self {
+        RuleTest {
+            rule: rule.to_string(),
+            validator: Validator::new(),
+            schemas: None,
+        }
+    }
+
+    /// 独自規則を登録する
+    pub fn with_rule(mut self, rule: impl CustomRule + 'static) -> Self {
+        self.validator.register(Box::new(rule));
+        self
+    }
+
+    /// 設定（`Validator::from_config` など）を組み込んだバリデータで実行する
+    pub fn with_validator(mut self, validator: Validator) -> Self {
+        self.validator = validator;
+        self
+    }
+
+    /// 解決済みの OpenAPI・DBML と照合する規則も実行する
+    pub fn with_schemas(mut self, schemas: ResolveContext) -> Self {
+        self.schemas = Some(schemas);
+        self
+    }
+
+    /// 解決済みの OpenAPI のレスポンスと照合する規則も実行する
+    pub fn with_openapi(mut self, openapi: OpenapiResponse) -> Self {
+        self.schemas
+            .get_or_insert_with(ResolveContext::default)
+            .openapi = Some(openapi);
+        self
+    }
+
+    /// 解決済みの DBML のテーブルと照合する規則も実行する
+    pub fn with_dbml(mut self, tables: Vec<DbmlTable>) -> Self {
+        self.schemas
+            .get_or_insert_with(ResolveContext::default)
+            .dbml_tables = tables;
+        self
+    }
+
+    /// `yaml` に規則を実行した診断。YAML がパースできない、規則が無い場合は panic する
+    pub fn run(&self, yaml: &str) -> Vec<Diagnostic> {
+        let doc = document(yaml);
+        let model = Model::build(&doc);
+        let cx = RuleContext {
+            document: &doc,
+            model: &model,
+            schemas: self.schemas.as_ref(),
+        };
+        self.validator
+            .run_rule(&self.rule, &cx)
+            .unwrap_or_else(|| panic!("規則 '{}' がありません", self.rule))
+    }
+
+    /// 診断が `expected` と同じ数だけ出て、順にそのメッセージを含むことを確かめる。空なら診断が出ないこと
+    pub fn expect(&self, yaml: &str, expected: &[&str]) {
+        let diagnostics = self.run(yaml);
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        let matched = messages.len() == expected.len()
+            && messages
+                .iter()
+                .zip(expected)
+                .all(|(message, expected)| message.contains(expected));
+        assert!(
+            matched,
+            "規則 '{}' の診断が一致しません\n  期待: {:?}\n  実際: {:?}",
+            self.rule, expected, messages
+        );
+    }
+}
+
+/// テスト用の YAML をパースする。`version` と `import` が無ければ補う
+pub fn document(yaml: &str) -> UsmlDocument {
+    let mut value: serde_yaml::Value =
+        serde_yaml::from_str(yaml).unwrap_or_else(|e| panic!("YAML のパースエラー: {}", e));
+    if let Some(mapping) = value.as_mapping_mut() {
+        if !mapping.contains_key("version") {
+            mapping.insert("version".into(), "0.1".into());
+        }
+        if !mapping.contains_key("import") {
+            mapping.insert("import".into(), serde_yaml::Mapping::new().into());
+        }
+    }
+    let yaml = serde_yaml::to_string(&value).expect("YAML に変換できる");
+    parser::parse(&yaml).unwrap_or_else(|e| panic!("パースエラー: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use super::*;
+    use crate::diagnostic::Severity;
+    use crate::resolver::DbmlColumn;
+    use crate::rules::{Category, Diagnostics, Rule};
+
+    const NO_DELETED: Rule = Rule {
+        code: Cow::Borrowed("ACME002"),
+        name: Cow::Borrowed("acme.deleted"),
+        category: Category::Custom,
+        severity: Severity::Warning,
+        summary: Cow::Borrowed("deleted_at を返さない"),
+        explanation: Cow::Borrowed(""),
+    };
+
+    struct NoDeleted;
+
+    impl CustomRule for NoDeleted {
+        fn rule(&self) -> &Rule {
+            &NO_DELETED
+        }
+
+        fn check(&self, cx: &RuleContext<'_>, diagnostics: &mut Diagnostics<'_>) {
+            for field in &cx.model.fields {
+                if field
+                    .source
+                    .as_ref()
+                    .is_some_and(|s| s.column == "deleted_at")
+                {
+                    diagnostics.report(format!("{} は deleted_at を返しています", field.path));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_builtin_rule_reports_only_its_diagnostics() {
+        // import が無いため U001 も出るが、U004 の診断だけを比べる
+        let rule = RuleTest::new("U004");
+        rule.expect(
+            r#"
+usecase:
+  name: テスト
+  response_mapping:
+    - field: author_name
+      source: users.name
+      join: { table: users, on: posts.user_id = users.id }
+    - field: editor_name
+      source: users.name
+      join: { table: users, on: posts.editor_id = users.id }
+"#,
+            &["users"],
+        );
+        RuleTest::new("join.alias").expect(
+            r#"
+usecase:
+  name: テスト
+  response_mapping:
+    - field: id
+      source: posts.id
+"#,
+            &[],
+        );
+    }
+
+    #[test]
+    fn test_custom_rule() {
+        let rule = RuleTest::new("acme002").with_rule(NoDeleted);
+        rule.expect(
+            r#"
+usecase:
+  name: テスト
+  response_mapping:
+    - field: id
+      source: users.id
+    - field: deleted
+      source: users.deleted_at
+"#,
+            &["deleted は deleted_at"],
+        );
+    }
+
+    #[test]
+    fn test_rule_with_schemas() {
+        let schemas = ResolveContext {
+            openapi: None,
+            dbml_tables: vec![DbmlTable {
+                name: "users".to_string(),
+                columns: vec!["id".to_string()],
+                column_details: vec![DbmlColumn {
+                    name: "id".to_string(),
+                    ..Default::default()
+                }],
+                location: None,
+            }],
+        };
+        let yaml = r#"
+import:
+  dbml:
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: email
+      source: users.email
+"#;
+        let rule = RuleTest::new("U012");
+        rule.expect(yaml, &[]);
+        rule.with_schemas(schemas).expect(yaml, &["email"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "規則 'U999' がありません")]
+    fn test_unknown_rule_panics() {
+        RuleTest::new("U999").run("usecase: { name: テスト, response_mapping: [] }");
+    }
+}
@@ -1,40 +1,450 @@
-use std::collections::HashMap;
-use std::path::Path;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
+use serde::Serialize;
 use thiserror::Error;
 
-use crate::ast::{ResponseMapping, UsmlDocument};
-use crate::resolver::{self, DbmlTable, OpenapiResponse};
+use crate::api::table_refs;
+use crate::ast::{Literal, ResponseMapping, Transform, UsmlDocument};
+use crate::complexity::ComplexityRule;
+use crate::config::{Config, ConfigError};
+use crate::diagnostic::{self, Diagnostic, Location, Severity};
+use crate::lint::DeclarativeRule;
+use crate::mask::MaskPattern;
+use crate::model::{self, FieldKind, FieldNode, JoinKind, Model};
+use crate::naming::{self, NamingRule, NamingTarget};
+use crate::output;
+use crate::ownership::OwnershipRule;
+use crate::resolver::{
+    self, DbmlColumn, DbmlTable, FsResolver, Limits, OpenapiResponse, Resolver, ResolverError,
+};
+use crate::rules::{self, CustomRule, Diagnostics, Rule, RuleContext};
 
 /// 解決済みの外部スキーマ情報
+#[derive(Debug, Default, Serialize)]
 pub struct ResolveContext {
     pub openapi: Option<OpenapiResponse>,
     pub dbml_tables: Vec<DbmlTable>,
 }
 
-#[derive(Debug, Error, PartialEq)]
+#[derive(Debug, Clone, Error, PartialEq)]
+///
+/// 3 つ目の値は違反の対象のノードの JSON Pointer（`ValidationError::at`）
 pub enum ValidationError {
     #[error("バリデーション[{0}]: {1}")]
-    Rule(String, String),
+    Rule(&'static Rule, String, Option<String>),
     #[error("警告[{0}]: {1}")]
-    Warning(String, String),
+    Warning(&'static Rule, String, Option<String>),
+    #[error("情報[{0}]: {1}")]
+    Info(&'static Rule, String, Option<String>),
+    /// 直前の同じ規則の診断に添えるヒント（`diagnostic::from_errors`）
+    #[error("ヒント[{0}]: {1}")]
+    Hint(&'static Rule, String, Option<String>),
+}
+
+impl ValidationError {
+    /// 規則の既定の重大度に応じたバリアントで違反を作る
+    pub fn new(rule: &'static Rule, message: impl Into<String>) -> Self {
+        match rule.severity {
+            Severity::Error => ValidationError::Rule(rule, message.into(), None),
+            Severity::Warning => ValidationError::Warning(rule, message.into(), None),
+            Severity::Info => ValidationError::Info(rule, message.into(), None),
+            Severity::Hint => ValidationError::Hint(rule, message.into(), None),
+        }
+    }
+
+    /// 違反の対象のノードを JSON Pointer（例: `/usecase/response_mapping/3/join/on`）で記録する
+    pub fn at(mut self, pointer: impl Into<String>) -> Self {
+        match &mut self {
+            ValidationError::Rule(_, _, target)
+            | ValidationError::Warning(_, _, target)
+            | ValidationError::Info(_, _, target)
+            | ValidationError::Hint(_, _, target) => *target = Some(pointer.into()),
+        }
+        self
+    }
+
+    pub fn rule(&self) -> &'static Rule {
+        match self {
+            ValidationError::Rule(rule, ..)
+            | ValidationError::Warning(rule, ..)
+            | ValidationError::Info(rule, ..)
+            | ValidationError::Hint(rule, ..) => rule,
+        }
+    }
+
+    pub fn pointer(&self) -> Option<&str> {
+        match self {
+            ValidationError::Rule(_, _, pointer)
+            | ValidationError::Warning(_, _, pointer)
+            | ValidationError::Info(_, _, pointer)
+            | ValidationError::Hint(_, _, pointer) => pointer.as_deref(),
+        }
+    }
 }
 
 /// バリデーション結果を収集する
 pub fn validate(doc: &UsmlDocument) -> Vec<ValidationError> {
+    validate_model(doc, &Model::build(doc))
+}
+
+/// 組み立て済みの意味モデルを使ってバリデーションする
+///
+/// 同じドキュメントでリネージや可視化も行う場合にモデルの再構築を避けられる
+pub fn validate_model(doc: &UsmlDocument, model: &Model) -> Vec<ValidationError> {
+    check_model(doc, model, DEFAULT_AGGREGATES)
+}
+
+/// `aggregate.type` に使える集約関数の既定値（`[lint] aggregates` で変更できる）
+pub const DEFAULT_AGGREGATES: &[&str] = &["COUNT", "SUM", "AVG", "MIN", "MAX"];
+
+fn check_model(doc: &UsmlDocument, model: &Model, aggregates: &[&str]) -> Vec<ValidationError> {
     let mut errors = Vec::new();
-    let imported_tables = parse_imported_tables(doc);
 
-    validate_imports(doc, &imported_tables, &mut errors);
-    validate_response_mapping(&doc.usecase.response_mapping, &imported_tables, &mut errors);
+    validate_imports(model, &mut errors);
+    validate_join_references(model, &mut errors);
+    validate_response_mapping(&doc.usecase.response_mapping, model, &mut errors);
     validate_filters(doc, &mut errors);
+    validate_filter_conflicts(doc, &mut errors);
+    validate_limits(doc, &mut errors);
     validate_transforms(doc, &mut errors);
+    validate_transform_sources(doc, model, &mut errors);
+    validate_aggregate_types(model, aggregates, &mut errors);
+    validate_aggregate_sources(model, &mut errors);
+    validate_aggregate_strategies(model, &mut errors);
+    validate_implicit_group_by(doc, model, &mut errors);
+    validate_reference_syntax(model, &mut errors);
+    validate_pagination(doc, &mut errors);
+    validate_hydration(doc, &mut errors);
+    validate_sibling_arrays(doc, model, &mut errors);
+    validate_output(doc, &mut errors);
+    validate_total_count(doc, model, &mut errors);
+    validate_collection(doc, model, &mut errors);
+    validate_values(model, &mut errors);
+    validate_timezones(model, &mut errors);
+    validate_row_filters(doc, model, &mut errors);
+    validate_cache(doc, model, &mut errors);
+    validate_source_usecases(model, &mut errors);
+    validate_library_uses(doc, &mut errors);
+    validate_environments(doc, &mut errors);
+    validate_nullable_joins(doc, model, None, &mut errors);
 
     errors
 }
 
-/// import 宣言を実際に解決する
-fn resolve_imports(doc: &UsmlDocument, base_dir: &str) -> (ResolveContext, Vec<ValidationError>) {
+/// 組み込み規則に独自規則を加えてバリデーションする
+///
+/// 独自規則は組み込み規則のあとに登録順で実行する
+#[derive(Default)]
+pub struct Validator {
+    custom_rules: Vec<Box<dyn CustomRule>>,
+    /// None なら DEFAULT_AGGREGATES
+    aggregates: Option<Vec<String>>,
+    limits: Limits,
+    policy: RulePolicy,
+}
+
+/// 報告する規則の選択と重大度の上書き（`[lint]` の `enable`・`disable`・`severity`・`warnings_as_errors`）
+///
+/// 規則はコードで持つ。規則に属さない診断（パースエラーなど）には適用しない
+#[derive(Debug, Clone, Default)]
+struct RulePolicy {
+    /// 空なら全規則
+    enable: Vec<String>,
+    disable: Vec<String>,
+    severity: Vec<(String, Severity)>,
+    warnings_as_errors: bool,
+}
+
+impl RulePolicy {
+    fn apply(&self, diagnostics: &mut Vec<Diagnostic>) {
+        diagnostics.retain(|d| {
+            d.code.as_ref().is_none_or(|code| {
+                (self.enable.is_empty() || self.enable.contains(code))
+                    && !self.disable.contains(code)
+            })
+        });
+        for diagnostic in diagnostics {
+            let Some(code) = &diagnostic.code else {
+                continue;
+            };
+            if let Some((_, severity)) = self.severity.iter().find(|(c, _)| c == code) {
+                // ヒントは直前の診断に添えるものなので重大度を変えない
+                if diagnostic.severity != Severity::Hint {
+                    diagnostic.severity = *severity;
+                }
+            } else if self.warnings_as_errors && diagnostic.severity == Severity::Warning {
+                diagnostic.severity = Severity::Error;
+            }
+        }
+    }
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `usml.toml` の `[lint.naming]`・`[lint.ownership]`・`[lint.complexity]` と `[[lint.rules]]` の規則を登録し、
+    /// `[limits]` を解決の上限にする。`[lint]` の `enable`・`disable`・`severity` に無い規則があればエラー
+    pub fn from_config(config: &Config) -> Result<Self, ConfigError> {
+        let mut validator = Self::new();
+        let naming = &config.lint.naming;
+        if let Some(case) = naming.fields {
+            validator.register(Box::new(NamingRule::new(NamingTarget::Field, case)));
+        }
+        if let Some(case) = naming.params {
+            validator.register(Box::new(NamingRule::new(NamingTarget::Param, case)));
+        }
+        if let Some(ownership) = &config.lint.ownership {
+            validator.register(Box::new(OwnershipRule::new(ownership)));
+        }
+        if let Some(complexity) = &config.lint.complexity {
+            validator.register(Box::new(ComplexityRule::new(complexity)));
+        }
+        for def in &config.lint.rules {
+            validator.register(Box::new(DeclarativeRule::new(def)?));
+        }
+        validator.aggregates = config.lint.aggregates.clone();
+        validator.limits = config.limits.clone();
+        let code = |key: &'static str, rule: &str| {
+            validator
+                .rules()
+                .find(|r| r.code.eq_ignore_ascii_case(rule) || r.name == rule)
+                .map(|r| r.code.to_string())
+                .ok_or_else(|| ConfigError::UnknownRule {
+                    key,
+                    rule: rule.to_string(),
+                })
+        };
+        let lint = &config.lint;
+        let policy = RulePolicy {
+            enable: lint
+                .enable
+                .iter()
+                .map(|rule| code("enable", rule))
+                .collect::<Result<_, _>>()?,
+            disable: lint
+                .disable
+                .iter()
+                .map(|rule| code("disable", rule))
+                .collect::<Result<_, _>>()?,
+            severity: lint
+                .severity
+                .iter()
+                .map(|(rule, severity)| Ok((code("severity", rule)?, *severity)))
+                .collect::<Result<_, _>>()?,
+            warnings_as_errors: lint.warnings_as_errors,
+        };
+        validator.policy = policy;
+        Ok(validator)
+    }
+
+    /// 警告をエラーとして報告する（`[lint] warnings_as_errors` と同じ）
+    pub fn with_warnings_as_errors(mut self) -> Self {
+        self.policy.warnings_as_errors = true;
+        self
+    }
+
+    /// 解決の上限を置き換える
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /// `aggregate.type` に使える集約関数を置き換える
+    pub fn with_aggregates(mut self, aggregates: Vec<String>) -> Self {
+        self.aggregates = Some(aggregates);
+        self
+    }
+
+    fn aggregates(&self) -> Vec<&str> {
+        match &self.aggregates {
+            Some(aggregates) => aggregates.iter().map(String::as_str).collect(),
+            None => DEFAULT_AGGREGATES.to_vec(),
+        }
+    }
+
+    pub fn with_rule(mut self, rule: impl CustomRule + 'static) -> Self {
+        self.register(Box::new(rule));
+        self
+    }
+
+    pub fn register(&mut self, rule: Box<dyn CustomRule>) {
+        self.custom_rules.push(rule);
+    }
+
+    /// 組み込み規則と登録済みの独自規則の定義
+    pub fn rules(&self) -> impl Iterator<Item = &Rule> {
+        rules::RULES
+            .iter()
+            .copied()
+            .chain(self.custom_rules.iter().map(|rule| rule.rule()))
+    }
+
+    /// import を解決せずに検証する
+    pub fn validate(&self, doc: &UsmlDocument) -> Vec<Diagnostic> {
+        let model = Model::build(doc);
+        let mut diagnostics = diagnostic::from_errors(check_model(doc, &model, &self.aggregates()));
+        self.run_custom_rules(doc, &model, None, &mut diagnostics);
+        self.policy.apply(&mut diagnostics);
+        diagnostics
+    }
+
+    /// 任意の Resolver で import を解決して検証する
+    pub fn validate_with_resolver<R: Resolver + ?Sized>(
+        &self,
+        doc: &UsmlDocument,
+        base_dir: &str,
+        resolver: &R,
+    ) -> Vec<Diagnostic> {
+        let (ctx, resolve_errors) =
+            resolve_imports_with_limits(doc, base_dir, resolver, &self.limits);
+        self.validate_with_context(doc, &ctx, resolve_errors)
+    }
+
+    /// 解決済みの import（`resolve_imports` の結果）で検証する。解決にかかる時間を分けて測る場合に使う
+    pub fn validate_with_context(
+        &self,
+        doc: &UsmlDocument,
+        ctx: &ResolveContext,
+        resolve_errors: Vec<Diagnostic>,
+    ) -> Vec<Diagnostic> {
+        let model = Model::build(doc);
+        let mut diagnostics =
+            validate_resolved(doc, &model, ctx, resolve_errors, &self.aggregates());
+        self.run_custom_rules(doc, &model, Some(ctx), &mut diagnostics);
+        self.policy.apply(&mut diagnostics);
+        diagnostics
+    }
+
+    /// 1 つの規則の診断を返す
+    ///
+    /// `rule` はコード（`U004`・`ACME001`。大文字・小文字は区別しない）か名前（`join.alias`）。
+    /// 組み込み規則は個別に呼び出せないため、検証全体（`cx.schemas` があれば照合規則も含む）を実行して
+    /// その規則の診断だけを残す。独自規則はその規則だけを実行する。
+    /// 組み込み規則にも登録済みの独自規則にも無ければ None
+    pub fn run_rule(&self, rule: &str, cx: &RuleContext<'_>) -> Option<Vec<Diagnostic>> {
+        let matches = |r: &Rule| r.code.eq_ignore_ascii_case(rule) || r.name == rule;
+        if let Some(builtin) = rules::RULES.iter().find(|r| matches(r)) {
+            let diagnostics = match cx.schemas {
+                Some(ctx) => {
+                    validate_resolved(cx.document, cx.model, ctx, Vec::new(), &self.aggregates())
+                }
+                None => {
+                    diagnostic::from_errors(check_model(cx.document, cx.model, &self.aggregates()))
+                }
+            };
+            return Some(
+                diagnostics
+                    .into_iter()
+                    .filter(|d| d.code.as_deref() == Some(builtin.code.as_ref()))
+                    .collect(),
+            );
+        }
+        let custom = self.custom_rules.iter().find(|c| matches(c.rule()))?;
+        let mut diagnostics = Vec::new();
+        custom.check(cx, &mut Diagnostics::new(custom.rule(), &mut diagnostics));
+        Some(diagnostics)
+    }
+
+    fn run_custom_rules(
+        &self,
+        doc: &UsmlDocument,
+        model: &Model,
+        schemas: Option<&ResolveContext>,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) {
+        let cx = RuleContext {
+            document: doc,
+            model,
+            schemas,
+        };
+        for rule in &self.custom_rules {
+            rule.check(&cx, &mut Diagnostics::new(rule.rule(), diagnostics));
+        }
+    }
+}
+
+/// 解決エラーを外部ファイルの位置付きの警告診断に変換する
+/// 上限（`[limits]`）を超えたエラーは U042、許可されていないパスは U043 にする
+fn resolve_diagnostic(rule: &'static Rule, label: &str, err: &ResolverError) -> Diagnostic {
+    let location = err
+        .location()
+        .cloned()
+        .or_else(|| err.file().map(Location::file));
+    let rule = match err {
+        ResolverError::NotAllowed { .. } => &rules::IMPORT_PATH,
+        _ if err.is_limit() => &rules::IMPORT_LIMIT,
+        _ => rule,
+    };
+    Diagnostic::from_rule(rule, format!("{}解決に失敗しました: {}", label, err))
+        .with_location(location)
+}
+
+/// import 宣言から読み込むべきファイルを洗い出した結果
+pub struct ImportPlan<'a> {
+    /// (ファイルパス, APIパス, メソッド, ステータスコード)
+    pub openapi: Option<(String, &'a str, &'a str, &'a str)>,
+    /// 重複を除いた DBML ファイルパス
+    pub dbml_files: Vec<String>,
+    /// `import.tables`（usecase のテーブル名 → DB 上のテーブル名）
+    pub tables: BTreeMap<String, String>,
+}
+
+/// import 宣言を読み込み対象のファイル一覧に変換する
+pub fn plan_imports<'a>(doc: &'a UsmlDocument, base_dir: &str) -> ImportPlan<'a> {
+    let openapi = doc
+        .import
+        .openapi
+        .as_deref()
+        .and_then(resolver::openapi::parse_openapi_ref)
+        .map(|(file, path, method, status)| {
+            (
+                resolver::join_import_path(base_dir, file),
+                path,
+                method,
+                status,
+            )
+        });
+
+    let mut dbml_files: Vec<String> = Vec::new();
+    for dbml_ref in doc.import.dbml.iter().flatten() {
+        if let Some((file, _table_name)) = resolver::dbml::parse_dbml_ref(dbml_ref) {
+            let full_path = resolver::join_import_path(base_dir, file);
+            if !dbml_files.contains(&full_path) {
+                dbml_files.push(full_path);
+            }
+        }
+    }
+
+    ImportPlan {
+        openapi,
+        dbml_files,
+        tables: doc.import.tables.clone(),
+    }
+}
+
+/// 読み込んだファイル内容をパースして ResolveContext を組み立てる
+/// `dbml_contents` は `plan.dbml_files` と同じ順序で渡す
+pub fn build_context(
+    plan: &ImportPlan<'_>,
+    openapi_content: Option<Result<String, ResolverError>>,
+    dbml_contents: Vec<Result<String, ResolverError>>,
+) -> (ResolveContext, Vec<Diagnostic>) {
+    build_context_with_limits(plan, openapi_content, dbml_contents, &Limits::default())
+}
+
+/// `build_context` と同じ。OpenAPI のスキーマの入れ子は `limits.max_schema_depth` 段まで読む
+pub fn build_context_with_limits(
+    plan: &ImportPlan<'_>,
+    openapi_content: Option<Result<String, ResolverError>>,
+    dbml_contents: Vec<Result<String, ResolverError>>,
+    limits: &Limits,
+) -> (ResolveContext, Vec<Diagnostic>) {
     let mut errors = Vec::new();
     let mut ctx = ResolveContext {
         openapi: None,
@@ -42,196 +452,408 @@ fn resolve_imports(doc: &UsmlDocument, base_dir: &str) -> (ResolveContext, Vec<V
     };
 
     // OpenAPI 解決
-    if let Some(openapi_ref) = &doc.import.openapi
-        && let Some((file, path, method, status)) =
-            resolver::openapi::parse_openapi_ref(openapi_ref)
-    {
-        let full_path = Path::new(base_dir).join(file).to_string_lossy().to_string();
-        match resolver::openapi::resolve_openapi(&full_path, path, method, status) {
+    if let (Some((file, path, method, status)), Some(content)) = (&plan.openapi, openapi_content) {
+        match content.and_then(|c| {
+            resolver::openapi::parse_openapi_content_with_limits(
+                &c, file, path, method, status, limits,
+            )
+        }) {
             Ok(resp) => ctx.openapi = Some(resp),
-            Err(e) => errors.push(ValidationError::Warning(
-                "import.openapi".to_string(),
-                format!("OpenAPI解決に失敗しました: {}", e),
-            )),
+            Err(e) => errors.push(resolve_diagnostic(&rules::OPENAPI_RESOLVE, "OpenAPI", &e)),
         }
     }
 
     // DBML 解決
-    if let Some(dbml_refs) = &doc.import.dbml {
-        for dbml_ref in dbml_refs {
-            if let Some((file, _table_name)) = resolver::dbml::parse_dbml_ref(dbml_ref) {
-                let full_path = Path::new(base_dir).join(file).to_string_lossy().to_string();
-                match resolver::dbml::resolve_dbml(&full_path) {
-                    Ok(tables) => {
-                        for table in tables {
-                            if !ctx.dbml_tables.iter().any(|t| t.name == table.name) {
-                                ctx.dbml_tables.push(table);
-                            }
-                        }
-                    }
-                    Err(e) => errors.push(ValidationError::Warning(
-                        "import.dbml".to_string(),
-                        format!("DBML解決に失敗しました: {}", e),
-                    )),
-                }
-            }
+    let mut resolved = Vec::new();
+    for (file, content) in plan.dbml_files.iter().zip(dbml_contents) {
+        match content.and_then(|c| resolver::dbml::parse_dbml_content(&c, file)) {
+            Ok(tables) => resolved.push((file.as_str(), tables)),
+            Err(e) => errors.push(resolve_diagnostic(&rules::DBML_RESOLVE, "DBML", &e)),
         }
     }
+    let (tables, conflicts) = merge_dbml_tables(
+        resolved
+            .iter()
+            .map(|(file, tables)| (*file, tables.as_slice())),
+    );
+    ctx.dbml_tables = rename_dbml_tables(tables, &plan.tables);
+    errors.extend(conflicts);
 
     (ctx, errors)
 }
 
+/// `import.tables` で対応付けた DB 上のテーブルを usecase のテーブル名にする
+///
+/// usecase のテーブル名と同じ名前の DB 上のテーブルは、対応付けたテーブルと取り違えないよう除く
+pub fn rename_dbml_tables(
+    mut tables: Vec<DbmlTable>,
+    names: &BTreeMap<String, String>,
+) -> Vec<DbmlTable> {
+    if names.is_empty() {
+        return tables;
+    }
+    tables.retain(|t| names.get(&t.name).is_none_or(|db_name| *db_name == t.name));
+    for table in &mut tables {
+        if let Some((name, _)) = names.iter().find(|(_, db_name)| **db_name == table.name) {
+            table.name.clone_from(name);
+        }
+    }
+    tables
+}
+
+/// import したファイル順に DBML のテーブルをまとめる
+///
+/// 同じ名前のテーブルは先に import したファイルの定義を使う。
+/// カラムの名前・型が異なる場合はどちらを使ったかを U041 で報告する
+pub fn merge_dbml_tables<'a>(
+    files: impl IntoIterator<Item = (&'a str, &'a [DbmlTable])>,
+) -> (Vec<DbmlTable>, Vec<Diagnostic>) {
+    let mut merged: Vec<(&str, DbmlTable)> = Vec::new();
+    let mut errors = Vec::new();
+    for (file, tables) in files {
+        for table in tables {
+            let Some((first_file, first)) = merged.iter().find(|(_, t)| t.name == table.name)
+            else {
+                merged.push((file, table.clone()));
+                continue;
+            };
+            let differences = table_differences(first_file, first, file, table);
+            if !differences.is_empty() {
+                errors.push(
+                    Diagnostic::from_rule(
+                        &rules::DBML_CONFLICT,
+                        format!(
+                            "テーブル '{}' が '{}' と '{}' で異なる定義になっています（{}）。'{}' の定義を使います",
+                            table.name,
+                            first_file,
+                            file,
+                            differences.join("、"),
+                            first_file
+                        ),
+                    )
+                    .with_location(Some(Location::file(file))),
+                );
+            }
+        }
+    }
+    (merged.into_iter().map(|(_, table)| table).collect(), errors)
+}
+
+/// 同名のテーブルのカラムの違い（片方にしか無いカラム・型の違い）
+fn table_differences(
+    first_file: &str,
+    first: &DbmlTable,
+    file: &str,
+    table: &DbmlTable,
+) -> Vec<String> {
+    let only = |file: &str, a: &DbmlTable, b: &DbmlTable| {
+        let names: Vec<&str> = a
+            .columns
+            .iter()
+            .filter(|c| !b.columns.contains(c))
+            .map(String::as_str)
+            .collect();
+        (!names.is_empty()).then(|| format!("'{}' のみ: {}", file, names.join(", ")))
+    };
+    let types: Vec<String> = first
+        .column_details
+        .iter()
+        .filter_map(|a| {
+            let b = table.column(&a.name)?;
+            (!a.data_type.eq_ignore_ascii_case(&b.data_type))
+                .then(|| format!("{}（{} / {}）", a.name, a.data_type, b.data_type))
+        })
+        .collect();
+    [
+        only(first_file, first, table),
+        only(file, table, first),
+        (!types.is_empty()).then(|| format!("型が異なる: {}", types.join(", "))),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// import 宣言を実際に解決する
+pub fn resolve_imports<R: Resolver + ?Sized>(
+    doc: &UsmlDocument,
+    base_dir: &str,
+    resolver: &R,
+) -> (ResolveContext, Vec<Diagnostic>) {
+    resolve_imports_with_limits(doc, base_dir, resolver, &Limits::default())
+}
+
+/// `resolve_imports` と同じ。`limits` を超えるファイル・スキーマは U042 で報告する
+pub fn resolve_imports_with_limits<R: Resolver + ?Sized>(
+    doc: &UsmlDocument,
+    base_dir: &str,
+    resolver: &R,
+    limits: &Limits,
+) -> (ResolveContext, Vec<Diagnostic>) {
+    let plan = plan_imports(doc, base_dir);
+    let openapi_content = plan
+        .openapi
+        .as_ref()
+        .map(|(file, ..)| limits.load(resolver, file));
+    let dbml_contents = plan
+        .dbml_files
+        .iter()
+        .map(|file| limits.load(resolver, file))
+        .collect();
+    build_context_with_limits(&plan, openapi_content, dbml_contents, limits)
+}
+
 /// リゾルバーを使用したバリデーション
 /// base_dir: import参照のファイルパスを解決するための基準ディレクトリ
-pub fn validate_with_resolve(doc: &UsmlDocument, base_dir: &str) -> Vec<ValidationError> {
-    let mut errors = Vec::new();
+///
+/// 外部ファイルの解決エラーはルール違反と同じ `Diagnostic` として、
+/// 壊れている行を指す位置情報付きで返す
+pub fn validate_with_resolve(doc: &UsmlDocument, base_dir: &str) -> Vec<Diagnostic> {
+    validate_with_resolver(doc, base_dir, &FsResolver)
+}
 
-    // まず基本バリデーション実行
-    errors.extend(validate(doc));
+/// 任意の Resolver で import を解決してバリデーションする
+pub fn validate_with_resolver<R: Resolver + ?Sized>(
+    doc: &UsmlDocument,
+    base_dir: &str,
+    resolver: &R,
+) -> Vec<Diagnostic> {
+    let (ctx, resolve_errors) = resolve_imports(doc, base_dir, resolver);
+    validate_resolved(
+        doc,
+        &Model::build(doc),
+        &ctx,
+        resolve_errors,
+        DEFAULT_AGGREGATES,
+    )
+}
+
+/// 非同期 Resolver で import を並行に解決してバリデーションする
+#[cfg(feature = "async")]
+pub async fn validate_with_resolve_async<R: resolver::async_resolver::AsyncResolver + ?Sized>(
+    doc: &UsmlDocument,
+    base_dir: &str,
+    resolver: &R,
+) -> Vec<Diagnostic> {
+    let (ctx, resolve_errors) =
+        resolver::async_resolver::resolve_imports_async(doc, base_dir, resolver).await;
+    validate_resolved(
+        doc,
+        &Model::build(doc),
+        &ctx,
+        resolve_errors,
+        DEFAULT_AGGREGATES,
+    )
+}
 
-    // 外部ファイル解決
-    let (ctx, resolve_errors) = resolve_imports(doc, base_dir);
-    errors.extend(resolve_errors);
+/// 解決済みコンテキストに対して基本規則と照合規則を実行する
+pub(crate) fn validate_resolved(
+    doc: &UsmlDocument,
+    model: &Model,
+    ctx: &ResolveContext,
+    resolve_errors: Vec<Diagnostic>,
+    aggregates: &[&str],
+) -> Vec<Diagnostic> {
+    let mut errors = Vec::new();
 
-    // Rule 1: OpenAPIレスポンスフィールドとの照合
+    // まず基本バリデーション実行
+    errors.extend(check_model(doc, model, aggregates));
     if let Some(ref openapi) = ctx.openapi {
-        validate_openapi_fields(&doc.usecase.response_mapping, openapi, &mut errors);
+        errors.extend(validate_against_openapi(doc, model, openapi));
     }
+    errors.extend(validate_against_dbml(
+        doc,
+        model,
+        &ctx.dbml_tables,
+        ctx.openapi.as_ref(),
+    ));
 
-    // Rule 3: DBMLカラム存在確認
-    if !ctx.dbml_tables.is_empty() {
-        validate_dbml_columns(&doc.usecase.response_mapping, &ctx.dbml_tables, &mut errors);
-    }
+    let mut diagnostics = diagnostic::from_errors(errors);
+    diagnostics.extend(resolve_errors);
+    diagnostics
+}
 
+/// OpenAPI と照合する規則（Rule 1・Rule 10・U030・U031・U033・U049・U056・U057・U058）
+pub(crate) fn validate_against_openapi(
+    doc: &UsmlDocument,
+    model: &Model,
+    openapi: &OpenapiResponse,
+) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    // Rule 1: OpenAPIレスポンスフィールドとの照合
+    validate_openapi_fields(&doc.usecase.response_mapping, openapi, &mut errors);
     // Rule 10アップグレード: OpenAPIパラメータの存在確認
-    if let Some(ref openapi) = ctx.openapi {
-        validate_transform_params(&doc.usecase.transforms, openapi, &mut errors);
-    }
-
+    validate_transform_params(&doc.usecase.transforms, openapi, &mut errors);
+    validate_convert_output_types(&doc.usecase.transforms, openapi, &mut errors);
+    validate_locale_params(&doc.usecase.transforms, openapi, &mut errors);
+    validate_nullable_declarations(model, openapi, &mut errors);
+    validate_nullable_joins(doc, model, Some(openapi), &mut errors);
+    validate_value_types(model, openapi, &mut errors);
+    validate_timezone_params(model, openapi, &mut errors);
+    validate_filter_requirements(&doc.usecase.filters, openapi, &mut errors);
     errors
 }
 
-/// import.dbml から テーブル名のリストを抽出する
-fn parse_imported_tables(doc: &UsmlDocument) -> Vec<String> {
-    match &doc.import.dbml {
-        Some(refs) => refs
+/// DBML と照合する規則（Rule 3・U020・U021・U023・U024・U026・U031・U034・U046・U047・U050・U056・U057・U058）
+///
+/// U021・U031・U047 は OpenAPI が解決できていればプロパティの定義とも照合する
+pub(crate) fn validate_against_dbml(
+    doc: &UsmlDocument,
+    model: &Model,
+    dbml_tables: &[DbmlTable],
+    openapi: Option<&OpenapiResponse>,
+) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    if !dbml_tables.is_empty() {
+        let columns: HashMap<&str, HashSet<&str>> = dbml_tables
             .iter()
-            .filter_map(|r| {
-                r.split("tables[\"")
-                    .nth(1)
-                    .and_then(|s| s.strip_suffix("\"]"))
-                    .map(|s| s.to_string())
+            .rev()
+            .map(|t| {
+                (
+                    t.name.as_str(),
+                    t.columns.iter().map(String::as_str).collect(),
+                )
             })
-            .collect(),
-        None => Vec::new(),
+            .collect();
+        validate_dbml_columns(
+            &doc.usecase.response_mapping,
+            "/usecase/response_mapping",
+            &columns,
+            &mut errors,
+        );
+        validate_transform_source_columns(&doc.usecase.transforms, &columns, &mut errors);
+        let tables: HashMap<&str, &DbmlTable> = dbml_tables
+            .iter()
+            .rev()
+            .map(|t| (t.name.as_str(), t))
+            .collect();
+        validate_conditional_source_types(&doc.usecase.transforms, &tables, openapi, &mut errors);
+        validate_mask_source_types(&doc.usecase.transforms, &tables, &mut errors);
+        validate_convert_source_types(&doc.usecase.transforms, &tables, &mut errors);
+        validate_locale_column_types(&doc.usecase.transforms, &tables, &mut errors);
+        validate_case_exhaustive(&doc.usecase.transforms, &tables, &mut errors);
+        validate_aggregate_arguments(
+            &doc.usecase.response_mapping,
+            "/usecase/response_mapping",
+            &tables,
+            &mut errors,
+        );
+        validate_nullable_columns(doc, model, &tables, openapi, &mut errors);
+        validate_row_filter_columns(doc, model, &columns, &mut errors);
+        validate_filter_columns(doc, model, &columns, &mut errors);
+        validate_condition_types(doc, model, &tables, openapi, &mut errors);
+        validate_cursor_order(doc, model, &tables, &mut errors);
+        validate_timezone_columns(model, &tables, &mut errors);
     }
+    errors
 }
 
-/// join.on の式から テーブル名.カラム名 パターンを抽出する
-fn extract_table_refs(on_expr: &str) -> Vec<(String, String)> {
-    let mut refs = Vec::new();
-    for token in on_expr.split_whitespace() {
-        let clean = token.trim_matches(|c: char| !c.is_alphanumeric() && c != '.' && c != '_');
-        if let Some((table, col)) = clean.split_once('.')
-            && !table.is_empty()
-            && !col.is_empty()
-            && col.chars().all(|c| c.is_alphanumeric() || c == '_')
-        {
-            refs.push((table.to_string(), col.to_string()));
+/// Rule 2: source・join・join_chain で使われるテーブル（エイリアス解決後）が import.dbml に含まれるか
+fn validate_imports(model: &Model, errors: &mut Vec<ValidationError>) {
+    let mut reported: HashSet<&str> = HashSet::new();
+    for field in &model.fields {
+        let tables = field
+            .source
+            .iter()
+            .map(|source| (source.table.as_str(), format!("{}/source", field.pointer)))
+            .chain(model.joins_of(field).map(|join| {
+                (
+                    join.table.as_str(),
+                    format!("{}/table", model.join_pointer(join)),
+                )
+            }));
+        for (table, pointer) in tables {
+            let imported = model.table(table).is_some_and(|t| t.imported);
+            if !imported && reported.insert(table) {
+                errors.push(
+                    ValidationError::new(
+                        &rules::IMPORT_TABLE,
+                        format!("テーブル '{}' が import.dbml に含まれていません", table),
+                    )
+                    .at(pointer),
+                );
+            }
         }
     }
-    refs
 }
 
-/// Rule 2: source で使われるテーブルが import.dbml に含まれるか
-fn validate_imports(
-    doc: &UsmlDocument,
-    imported_tables: &[String],
-    errors: &mut Vec<ValidationError>,
-) {
-    collect_used_tables(&doc.usecase.response_mapping)
-        .into_iter()
-        .for_each(|table| {
-            if !imported_tables.contains(&table) {
-                errors.push(ValidationError::Rule(
-                    "import.dbml".to_string(),
-                    format!("テーブル '{}' が import.dbml に含まれていません", table),
-                ));
+/// Rule 6: join.on / join_chain.on で参照されるテーブル（エイリアス解決後）が import.dbml に含まれるか
+fn validate_join_references(model: &Model, errors: &mut Vec<ValidationError>) {
+    for join in &model.joins {
+        let rule = if join.chain_index.is_some() {
+            &rules::JOIN_CHAIN_ON_TABLE
+        } else {
+            &rules::JOIN_ON_TABLE
+        };
+        for reference in &join.references {
+            if !model.table(&reference.table).is_some_and(|t| t.imported) {
+                errors.push(
+                    ValidationError::new(
+                        rule,
+                        format!(
+                            "{} で参照されるテーブル '{}' が import.dbml に含まれていません",
+                            rule.name, reference.qualifier
+                        ),
+                    )
+                    .at(format!("{}/on", model.join_pointer(join))),
+                );
             }
-        });
+        }
+    }
 }
 
 /// response_mapping の結合・エイリアス・集約・配列規則を検証
 fn validate_response_mapping(
     mappings: &[ResponseMapping],
-    imported_tables: &[String],
+    model: &Model,
     errors: &mut Vec<ValidationError>,
 ) {
     let mut join_map: HashMap<String, (String, Option<String>)> = HashMap::new();
 
-    validate_response_mapping_inner(mappings, imported_tables, &mut join_map, errors);
+    validate_response_mapping_inner(
+        mappings,
+        "",
+        "/usecase/response_mapping",
+        model,
+        &mut join_map,
+        errors,
+    );
 }
 
+/// `pointer` は `mappings` の JSON Pointer
 fn validate_response_mapping_inner(
     mappings: &[ResponseMapping],
-    imported_tables: &[String],
+    prefix: &str,
+    pointer: &str,
+    model: &Model,
     join_map: &mut HashMap<String, (String, Option<String>)>,
     errors: &mut Vec<ValidationError>,
 ) {
-    for mapping in mappings {
+    for (index, mapping) in mappings.iter().enumerate() {
+        let path = if prefix.is_empty() {
+            mapping.field.clone()
+        } else {
+            format!("{}.{}", prefix, mapping.field)
+        };
+        let pointer = format!("{}/{}", pointer, index);
         // Rule 7: 同テーブルが異なる結合条件で複数参照される場合に alias が必要
         if let Some(join) = &mapping.join {
             let key = join.table.clone();
             if let Some((existing_on, existing_alias)) = join_map.get(&key) {
                 if *existing_on != join.on && join.alias.is_none() && existing_alias.is_none() {
-                    errors.push(ValidationError::Rule(
-                        "join.alias".to_string(),
-                        format!(
-                            "テーブル '{}' が異なる結合条件で複数参照されていますが、alias が指定されていません",
-                            join.table
-                        ),
-                    ));
-                }
-            } else {
-                join_map.insert(key, (join.on.clone(), join.alias.clone()));
-            }
-
-            // Rule 6: join.on で参照されるテーブルが import.dbml に含まれるか
-            let refs = extract_table_refs(&join.on);
-            for (table, _col) in &refs {
-                // エイリアス名は検証対象外
-                if let Some(alias) = &join.alias
-                    && table == alias
-                {
-                    continue;
-                }
-                if !imported_tables.contains(table) {
-                    errors.push(ValidationError::Rule(
-                        "join.on".to_string(),
-                        format!(
-                            "join.on で参照されるテーブル '{}' が import.dbml に含まれていません",
-                            table
-                        ),
-                    ));
-                }
-            }
-        }
-
-        // Rule 6: join_chain で参照されるテーブルも検証
-        if let Some(chain) = &mapping.join_chain {
-            for entry in chain {
-                let refs = extract_table_refs(&entry.on);
-                for (table, _col) in &refs {
-                    if !imported_tables.contains(table) {
-                        errors.push(ValidationError::Rule(
-                            "join_chain.on".to_string(),
+                    errors.push(
+                        ValidationError::new(
+                            &rules::JOIN_ALIAS,
                             format!(
-                                "join_chain.on で参照されるテーブル '{}' が import.dbml に含まれていません",
-                                table
+                                "テーブル '{}' が異なる結合条件で複数参照されていますが、alias が指定されていません",
+                                join.table
                             ),
-                        ));
-                    }
+                        )
+                        .at(format!("{}/join", pointer)),
+                    );
                 }
+            } else {
+                join_map.insert(key, (join.on.clone(), join.alias.clone()));
             }
         }
 
@@ -239,13 +861,26 @@ fn validate_response_mapping_inner(
         if let Some(agg) = &mapping.aggregate
             && agg.group_by.is_none()
         {
-            errors.push(ValidationError::Warning(
-                "aggregate.group_by".to_string(),
-                format!(
-                    "フィールド '{}' に aggregate ({}) が使われていますが group_by が指定されていません。省略時はルートテーブルの主キーが自動適用されます",
-                    mapping.field, agg.r#type
-                ),
-            ));
+            errors.push(
+                ValidationError::new(
+                    &rules::AGGREGATE_GROUP_BY,
+                    format!(
+                        "フィールド '{}' に aggregate ({}) が使われていますが group_by が指定されていません。省略時はルートテーブルの主キー（配列の要素では親の行のキー）と同じ階層のカラムでまとめます（U060）",
+                        mapping.field, agg.r#type
+                    ),
+                )
+                .at(format!("{}/aggregate", pointer)),
+            );
+            if let Some(column) = group_by_example(model, &path) {
+                errors.push(ValidationError::Hint(
+                    &rules::AGGREGATE_GROUP_BY,
+                    format!(
+                        "集約の単位を group_by に書いてください（例: group_by: {}）",
+                        column
+                    ),
+                    Some(format!("{}/aggregate", pointer)),
+                ));
+            }
         }
 
         // Rule 11: source_table が配列フィールドの join で参照されるテーブルと一致するか
@@ -262,33 +897,44 @@ fn validate_response_mapping_inner(
                 &join.table
             };
             if source_table != actual_source {
-                errors.push(ValidationError::Rule(
-                    "source_table".to_string(),
-                    format!(
-                        "配列フィールド '{}' の source_table '{}' がjoin の実際のソーステーブル '{}' と一致しません",
-                        mapping.field, source_table, actual_source
-                    ),
-                ));
+                errors.push(
+                    ValidationError::new(
+                        &rules::SOURCE_TABLE,
+                        format!(
+                            "配列フィールド '{}' の source_table '{}' がjoin の実際のソーステーブル '{}' と一致しません",
+                            mapping.field, source_table, actual_source
+                        ),
+                    )
+                    .at(format!("{}/source_table", pointer)),
+                );
             }
         }
 
         // 配列フィールドの再帰検証
         if let Some(sub_fields) = &mapping.fields {
-            validate_response_mapping_inner(sub_fields, imported_tables, join_map, errors);
+            validate_response_mapping_inner(
+                sub_fields,
+                &path,
+                &format!("{}/fields", pointer),
+                model,
+                join_map,
+                errors,
+            );
         }
     }
 }
 
 /// Rule 9, 12: filters の検証
 fn validate_filters(doc: &UsmlDocument, errors: &mut Vec<ValidationError>) {
-    let declared_params: Vec<&str> = doc
+    let declared_params: HashSet<&str> = doc
         .usecase
         .filters
         .iter()
         .map(|f| f.param.as_str())
         .collect();
 
-    for filter in &doc.usecase.filters {
+    for (index, filter) in doc.usecase.filters.iter().enumerate() {
+        let pointer = |key: &str| format!("/usecase/filters/{}/{}", index, key);
         // Rule 9: condition で使用される :パラメータ がすべて filters[].param で宣言されているか
         if let Some(condition) = &filter.condition {
             for token in condition.split_whitespace() {
@@ -296,13 +942,16 @@ fn validate_filters(doc: &UsmlDocument, errors: &mut Vec<ValidationError>) {
                     let clean =
                         param_name.trim_end_matches(|c: char| !c.is_alphanumeric() && c != '_');
                     if !clean.is_empty() && !declared_params.contains(&clean) {
-                        errors.push(ValidationError::Rule(
-                            "filters.condition".to_string(),
-                            format!(
-                                "condition で使用されるパラメータ ':{}' が filters[].param で宣言されていません",
-                                clean
-                            ),
-                        ));
+                        errors.push(
+                            ValidationError::new(
+                                &rules::FILTER_CONDITION_PARAM,
+                                format!(
+                                    "condition で使用されるパラメータ ':{}' が filters[].param で宣言されていません",
+                                    clean
+                                ),
+                            )
+                            .at(pointer("condition")),
+                        );
                     }
                 }
             }
@@ -314,566 +963,4899 @@ fn validate_filters(doc: &UsmlDocument, errors: &mut Vec<ValidationError>) {
                 (&filter.allowed_columns, &filter.default_column)
             && !allowed.contains(default_col)
         {
-            errors.push(ValidationError::Rule(
-                "filters.allowed_columns".to_string(),
-                format!(
-                    "ORDER_BY の default_column '{}' が allowed_columns リスト外です",
-                    default_col
-                ),
-            ));
-        }
-    }
-}
-
-/// Rule 5, 10: transforms の検証
-fn validate_transforms(doc: &UsmlDocument, errors: &mut Vec<ValidationError>) {
-    let field_names: Vec<&str> = doc
-        .usecase
-        .response_mapping
-        .iter()
-        .map(|m| m.field.as_str())
-        .collect();
-
-    for transform in &doc.usecase.transforms {
-        // Rule 5: target が response_mapping のいずれかの field に対応しているか
-        if !field_names.contains(&transform.target.as_str()) {
-            errors.push(ValidationError::Rule(
-                "transforms.target".to_string(),
-                format!(
-                    "transform の target '{}' が response_mapping のいずれかの field に対応していません",
-                    transform.target
-                ),
-            ));
+            errors.push(
+                ValidationError::new(
+                    &rules::ORDER_BY_DEFAULT_COLUMN,
+                    format!(
+                        "ORDER_BY の default_column '{}' が allowed_columns リスト外です",
+                        default_col
+                    ),
+                )
+                .at(pointer("default_column")),
+            );
         }
 
-        // Rule 10: condition に param が使われている場合は警告（OpenAPI解析未実装のため）
-        if let Some(conditions) = &transform.condition {
-            for cond in conditions {
-                if cond.param.is_some() {
-                    errors.push(ValidationError::Warning(
-                        "transforms.condition.param".to_string(),
-                        format!(
-                            "transform '{}' の condition に param が使われていますが、OpenAPI解析が未実装のためパラメータの存在確認はスキップされます",
-                            transform.target
-                        ),
-                    ));
-                }
+        // U048: default は省略できる WHERE のフィルタに固定値で書く
+        if let Some(default) = &filter.default {
+            let reason = if filter.required == Some(true) {
+                Some("required: true のフィルタには使われません")
+            } else if !filter.maps_to.is_empty() && filter.maps_to != "WHERE" {
+                Some("maps_to: WHERE のフィルタにだけ書けます")
+            } else if default.param().is_some() {
+                Some(":パラメータ の参照は書けません")
+            } else {
+                None
+            };
+            if let Some(reason) = reason {
+                errors.push(
+                    ValidationError::new(
+                        &rules::FILTER_DEFAULT,
+                        format!("filters[].default（param '{}'）: {}", filter.param, reason),
+                    )
+                    .at(pointer("default")),
+                );
             }
         }
     }
 }
 
-/// Rule 1: response_mapping のフィールド名がOpenAPIレスポンスに存在するか
-fn validate_openapi_fields(
-    mappings: &[ResponseMapping],
-    openapi: &OpenapiResponse,
-    errors: &mut Vec<ValidationError>,
-) {
-    for mapping in mappings {
-        if !openapi.fields.contains(&mapping.field) {
-            errors.push(ValidationError::Rule(
-                "response_mapping.field".to_string(),
-                format!(
-                    "フィールド {} がOpenAPIレスポンスのプロパティに存在しません",
-                    mapping.field
-                ),
+/// U044・U045: filters の間で param が重複していないか、ORDER_BY が 1 つだけか
+fn validate_filter_conflicts(doc: &UsmlDocument, errors: &mut Vec<ValidationError>) {
+    let filters = &doc.usecase.filters;
+    // PAGINATION の limit_param もリクエストのパラメータとして数える
+    let mut params: Vec<(&str, &str, String)> = Vec::new();
+    for (index, filter) in filters.iter().enumerate() {
+        params.push((
+            &filter.param,
+            &filter.maps_to,
+            format!("/usecase/filters/{}/param", index),
+        ));
+        if filter.maps_to == "PAGINATION"
+            && let Some(limit_param) = &filter.limit_param
+            && *limit_param != filter.param
+        {
+            params.push((
+                limit_param,
+                "PAGINATION",
+                format!("/usecase/filters/{}/limit_param", index),
             ));
         }
     }
-}
+    let mut reported = HashSet::new();
+    for (param, ..) in &params {
+        if !reported.insert(*param) {
+            continue;
+        }
+        let declared: Vec<&(&str, &str, String)> =
+            params.iter().filter(|(p, ..)| p == param).collect();
+        let mut maps_to: Vec<&str> = declared.iter().map(|(_, m, _)| *m).collect();
+        let count = maps_to.len();
+        if count < 2 {
+            continue;
+        }
+        maps_to.dedup();
+        let message = if maps_to.len() > 1 {
+            format!(
+                "filters[].param '{}' が {} の両方に使われています",
+                param,
+                maps_to.join(" と ")
+            )
+        } else {
+            format!(
+                "filters[].param '{}' が {} 回宣言されています",
+                param, count
+            )
+        };
+        // 2 つ目の宣言を指す
+        errors.push(
+            ValidationError::new(&rules::FILTER_DUPLICATE_PARAM, message).at(declared[1].2.clone()),
+        );
+    }
 
-/// Rule 3: source で参照されるテーブル.カラムがDBMLに実際に存在するか
-fn validate_dbml_columns(
-    mappings: &[ResponseMapping],
-    dbml_tables: &[DbmlTable],
-    errors: &mut Vec<ValidationError>,
-) {
-    for mapping in mappings {
-        if let Some(source) = &mapping.source
-            && let Some((table_name, col_name)) = source.split_once('.')
-            && let Some(table) = dbml_tables.iter().find(|t| t.name == table_name)
-            && !table.columns.contains(&col_name.to_string())
-        {
-            errors.push(ValidationError::Rule(
-                "response_mapping.source".to_string(),
+    let order_by: Vec<(usize, &str)> = filters
+        .iter()
+        .enumerate()
+        .filter(|(_, f)| f.maps_to == "ORDER_BY")
+        .map(|(i, f)| (i, f.param.as_str()))
+        .collect();
+    if order_by.len() > 1 {
+        let params: Vec<&str> = order_by.iter().map(|(_, p)| *p).collect();
+        errors.push(
+            ValidationError::new(
+                &rules::FILTER_ORDER_BY,
                 format!(
-                    "カラム {} がテーブル {} に存在しません",
-                    col_name, table_name
+                    "maps_to: ORDER_BY のフィルタが {} 個あります（{}）。ソート順が一意に決まりません",
+                    order_by.len(),
+                    params.join(", ")
                 ),
-            ));
+            )
+            .at(format!("/usecase/filters/{}/maps_to", order_by[1].0)),
+        );
+    }
+}
+
+/// U029: usecase.limit・default_limit が互いに、またページネーションと矛盾しないか（警告）
+fn validate_limits(doc: &UsmlDocument, errors: &mut Vec<ValidationError>) {
+    let usecase = &doc.usecase;
+    let mut warn = |message: String, pointer: String| {
+        errors.push(ValidationError::new(&rules::USECASE_LIMIT, message).at(pointer))
+    };
+
+    for (key, value) in [
+        ("limit", usecase.limit),
+        ("default_limit", usecase.default_limit),
+    ] {
+        if value == Some(0) {
+            warn(
+                format!("usecase.{} が 0 のため常に空の結果になります", key),
+                format!("/usecase/{}", key),
+            );
         }
+    }
+    if let (Some(limit), Some(default_limit)) = (usecase.limit, usecase.default_limit)
+        && default_limit > limit
+    {
+        warn(
+            format!(
+                "usecase.default_limit ({}) が limit ({}) を超えています",
+                default_limit, limit
+            ),
+            "/usecase/default_limit".to_string(),
+        );
+    }
 
-        // サブフィールドの再帰検証
-        if let Some(sub_fields) = &mapping.fields {
-            validate_dbml_columns(sub_fields, dbml_tables, errors);
+    let Some((index, pagination)) = usecase
+        .filters
+        .iter()
+        .enumerate()
+        .find(|(_, f)| f.maps_to == "PAGINATION")
+    else {
+        return;
+    };
+    if usecase.default_limit.is_some() {
+        warn(
+            format!(
+                "PAGINATION フィルタ '{}' の page_size が既定の行数になるため、usecase.default_limit は使われません",
+                pagination.param
+            ),
+            "/usecase/default_limit".to_string(),
+        );
+    }
+    if let Some(limit) = usecase.limit {
+        let page_sizes = [
+            ("page_size", pagination.page_size),
+            ("max_page_size", pagination.max_page_size),
+        ];
+        for (key, size) in page_sizes {
+            if let Some(size) = size
+                && size > limit
+            {
+                warn(
+                    format!(
+                        "PAGINATION フィルタ '{}' の {} ({}) が usecase.limit ({}) を超えているため、ページが途中で切れます",
+                        pagination.param, key, size, limit
+                    ),
+                    format!("/usecase/filters/{}/{}", index, key),
+                );
+            }
         }
     }
 }
 
-/// Rule 10: transform の condition.param がOpenAPIパラメータに存在するか
-fn validate_transform_params(
-    transforms: &[crate::ast::Transform],
-    openapi: &OpenapiResponse,
-    errors: &mut Vec<ValidationError>,
-) {
-    for transform in transforms {
-        if let Some(conditions) = &transform.condition {
-            for cond in conditions {
-                if let Some(param) = &cond.param
-                    && !openapi.parameters.contains(param)
-                {
-                    errors.push(ValidationError::Rule(
-                        "transforms.condition.param".to_string(),
+/// Rule 5, 10, U022, U057: transforms の検証
+fn validate_transforms(doc: &UsmlDocument, errors: &mut Vec<ValidationError>) {
+    let field_names: HashSet<&str> = doc
+        .usecase
+        .response_mapping
+        .iter()
+        .map(|m| m.field.as_str())
+        .collect();
+
+    for (index, transform) in doc.usecase.transforms.iter().enumerate() {
+        let pointer = format!("/usecase/transforms/{}", index);
+        // Rule 5: target が response_mapping のいずれかの field に対応しているか
+        if !field_names.contains(&transform.target.as_str()) {
+            errors.push(
+                ValidationError::new(
+                    &rules::TRANSFORM_TARGET,
+                    format!(
+                        "transform の target '{}' が response_mapping のいずれかの field に対応していません",
+                        transform.target
+                    ),
+                )
+                .at(format!("{}/target", pointer)),
+            );
+        }
+
+        // U022: MASK の mask_pattern が指定され、ミニ言語として正しいか
+        if transform.r#type.eq_ignore_ascii_case("MASK") {
+            let error = match &transform.mask_pattern {
+                Some(pattern) => MaskPattern::parse(pattern).err().map(|e| e.to_string()),
+                None => Some("mask_pattern が指定されていません".to_string()),
+            };
+            if let Some(error) = error {
+                errors.push(
+                    ValidationError::new(
+                        &rules::MASK_PATTERN,
                         format!(
-                            "transform {} の condition.param {} がOpenAPIパラメータに存在しません",
-                            transform.target, param
+                            "transform '{}' の mask_pattern: {}",
+                            transform.target, error
                         ),
-                    ));
+                    )
+                    .at(format!("{}/mask_pattern", pointer)),
+                );
+            }
+        }
+
+        // U057: CONVERT の係数・丸め・桁数が宣言されているか
+        if transform.r#type.eq_ignore_ascii_case("CONVERT") {
+            for error in convert_errors(transform) {
+                errors.push(
+                    ValidationError::new(
+                        &rules::TRANSFORM_CONVERT,
+                        format!("transform '{}' の CONVERT: {}", transform.target, error),
+                    )
+                    .at(&pointer),
+                );
+            }
+        }
+
+        // U058: LOCALIZE の翻訳テーブル・言語のパラメータ・fallback の言語が宣言されているか
+        if transform.r#type.eq_ignore_ascii_case("LOCALIZE") {
+            for error in localize_errors(transform) {
+                errors.push(
+                    ValidationError::new(
+                        &rules::TRANSFORM_LOCALIZE,
+                        format!("transform '{}' の LOCALIZE: {}", transform.target, error),
+                    )
+                    .at(&pointer),
+                );
+            }
+        }
+
+        // Rule 10: condition に param が使われている場合は警告（OpenAPI解析未実装のため）
+        if let Some(conditions) = &transform.condition {
+            for (i, cond) in conditions.iter().enumerate() {
+                if cond.param.is_some() {
+                    errors.push(
+                        ValidationError::new(
+                            &rules::TRANSFORM_PARAM_UNCHECKED,
+                            format!(
+                                "transform '{}' の condition に param が使われていますが、OpenAPI解析が未実装のためパラメータの存在確認はスキップされます",
+                                transform.target
+                            ),
+                        )
+                        .at(format!("{}/condition/{}/param", pointer, i)),
+                    );
                 }
             }
         }
     }
 }
 
-/// response_mapping から使われるテーブル名を収集する
-fn collect_used_tables(mappings: &[ResponseMapping]) -> Vec<String> {
-    let mut tables = Vec::new();
+/// CONVERT の丸め方
+const ROUNDING_MODES: &[&str] = &[
+    "half_up",
+    "half_even",
+    "half_down",
+    "up",
+    "down",
+    "ceiling",
+    "floor",
+];
 
-    for mapping in mappings {
-        if let Some(source) = &mapping.source
-            && let Some(table) = source.split('.').next()
-            && !tables.contains(&table.to_string())
+fn convert_errors(transform: &Transform) -> Vec<String> {
+    let mut errors = Vec::new();
+    if transform.source.is_none() {
+        errors.push("source が指定されていません".to_string());
+    }
+    match (transform.factor, &transform.lookup) {
+        (None, None) => errors.push("factor か lookup のどちらかを指定します".to_string()),
+        (Some(_), Some(_)) => errors.push("factor と lookup は同時に指定できません".to_string()),
+        (Some(factor), None) if !factor.is_finite() || factor == 0.0 => {
+            errors.push(format!("factor {} は 0 以外の数にします", factor));
+        }
+        (_, Some(lookup))
+            if lookup
+                .rate
+                .split_once('.')
+                .is_none_or(|(table, _)| table != lookup.table) =>
+        {
+            errors.push(format!(
+                "lookup.rate '{}' は lookup.table '{}' のカラムにします",
+                lookup.rate, lookup.table
+            ));
+        }
+        _ => {}
+    }
+    if transform.scale.is_none() {
+        errors.push("scale（出力の小数点以下の桁数）が指定されていません".to_string());
+    }
+    if let Some(rounding) = &transform.rounding
+        && !ROUNDING_MODES.contains(&rounding.to_ascii_lowercase().as_str())
+    {
+        errors.push(format!(
+            "rounding '{}' は使えません（{}）",
+            rounding,
+            ROUNDING_MODES.join(" / ")
+        ));
+    }
+    errors
+}
+
+fn localize_errors(transform: &Transform) -> Vec<String> {
+    let mut errors = Vec::new();
+    let qualifier = match &transform.source {
+        Some(source) => source.split_once('.').map(|(qualifier, _)| qualifier),
+        None => {
+            errors.push("source（訳のカラム）が指定されていません".to_string());
+            None
+        }
+    };
+    let Some(locale) = &transform.locale else {
+        errors.push("locale（翻訳テーブルの行の選び方）が指定されていません".to_string());
+        return errors;
+    };
+    if let Some(qualifier) = qualifier {
+        if locale.column.split_once('.').map(|(q, _)| q) != Some(qualifier) {
+            errors.push(format!(
+                "locale.column '{}' は source と同じテーブル '{}' のカラムにします",
+                locale.column, qualifier
+            ));
+        }
+        if !table_refs(&locale.on).iter().any(|(q, _)| q == qualifier) {
+            errors.push(format!(
+                "locale.on '{}' が翻訳テーブル '{}' のカラムを参照していません",
+                locale.on, qualifier
+            ));
+        }
+    }
+    if locale.param.is_empty()
+        || !locale
+            .param
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        errors.push(format!(
+            "locale.param '{}' はパラメータ名（英数字と _）で指定します",
+            locale.param
+        ));
+    }
+    for (i, tag) in locale.fallback.iter().enumerate() {
+        if !is_locale_tag(tag) {
+            errors.push(format!(
+                "locale.fallback の '{}' は言語タグ（en・ja-JP・zh_Hant など）ではありません",
+                tag
+            ));
+        } else if locale.fallback[..i].contains(tag) {
+            errors.push(format!("locale.fallback の '{}' が重複しています", tag));
+        }
+    }
+    errors
+}
+
+/// `en`・`ja-JP`・`zh_Hant` 形式の言語タグか（言語は英字 2〜8 文字、以降は `-` か `_` 区切りの英数字 1〜8 文字）
+fn is_locale_tag(tag: &str) -> bool {
+    let mut segments = tag.split(['-', '_']);
+    segments.next().is_some_and(|language| {
+        (2..=8).contains(&language.len()) && language.chars().all(|c| c.is_ascii_alphabetic())
+    }) && segments.all(|segment| {
+        (1..=8).contains(&segment.len()) && segment.chars().all(|c| c.is_ascii_alphanumeric())
+    })
+}
+
+/// U019: transform の source・sources・then_source・else_source が import 済みテーブルのカラムかレスポンスフィールドを指しているか
+fn validate_transform_sources(
+    doc: &UsmlDocument,
+    model: &Model,
+    errors: &mut Vec<ValidationError>,
+) {
+    for (index, transform) in doc.usecase.transforms.iter().enumerate() {
+        for source in model::transform_sources(transform) {
+            if model.field(source).is_some() {
+                continue;
+            }
+            let message = match source.split_once('.') {
+                Some((qualifier, _)) => {
+                    let table = model.resolve_table(qualifier);
+                    if model.table(table).is_some_and(|t| t.imported) {
+                        continue;
+                    }
+                    format!(
+                        "transform '{}' の source '{}' のテーブル '{}' が import.dbml に含まれていません",
+                        transform.target, source, table
+                    )
+                }
+                None => format!(
+                    "transform '{}' の source '{}' が response_mapping のいずれの field にも対応していません",
+                    transform.target, source
+                ),
+            };
+            errors.push(
+                ValidationError::new(&rules::TRANSFORM_SOURCE, message)
+                    .at(transform_source_pointer(index, transform, source)),
+            );
+        }
+    }
+}
+
+/// `index` 番目の transform で `source` を書いた項目の JSON Pointer
+fn transform_source_pointer(index: usize, transform: &Transform, source: &str) -> String {
+    let is = |value: Option<&String>| value.is_some_and(|v| v == source);
+    let key = if is(transform.source.as_ref()) {
+        "source".to_string()
+    } else if let Some(i) = transform.sources.iter().flatten().position(|s| s == source) {
+        format!("sources/{}", i)
+    } else if is(transform.then_source.as_ref()) {
+        "then_source".to_string()
+    } else if is(transform.else_source.as_ref()) {
+        "else_source".to_string()
+    } else if is(transform.lookup.as_ref().map(|l| &l.rate)) {
+        "lookup/rate".to_string()
+    } else if is(transform.locale.as_ref().map(|l| &l.column)) {
+        "locale/column".to_string()
+    } else {
+        return format!("/usecase/transforms/{}", index);
+    };
+    format!("/usecase/transforms/{}/{}", index, key)
+}
+
+/// U025: aggregate.type が許可された集約関数か
+fn validate_aggregate_types(model: &Model, aggregates: &[&str], errors: &mut Vec<ValidationError>) {
+    for field in &model.fields {
+        if let Some(aggregate) = &field.aggregate
+            && !aggregates.iter().any(|a| a.eq_ignore_ascii_case(aggregate))
         {
-            tables.push(table.to_string());
+            errors.push(
+                ValidationError::new(
+                    &rules::AGGREGATE_TYPE,
+                    format!(
+                        "フィールド '{}' の aggregate.type '{}' は使えません（使える集約関数: {}）",
+                        field.path,
+                        aggregate,
+                        aggregates.join(", ")
+                    ),
+                )
+                .at(format!("{}/aggregate/type", field.pointer)),
+            );
+        }
+    }
+}
+
+/// U063: aggregate のフィールドに集約するカラムの source があるか
+///
+/// value・from_param・source_usecase と併記した aggregate は U032・U036 が報告する
+fn validate_aggregate_sources(model: &Model, errors: &mut Vec<ValidationError>) {
+    for field in &model.fields {
+        if let Some(aggregate) = &field.aggregate
+            && field.kind == FieldKind::Scalar
+            && field.source.is_none()
+            && field.value.is_none()
+            && field.param.is_none()
+            && field.source_usecase.is_none()
+        {
+            errors.push(
+                ValidationError::new(
+                    &rules::AGGREGATE_SOURCE,
+                    format!(
+                        "フィールド '{}' の aggregate {} に集約するカラムの source がありません",
+                        field.path, aggregate
+                    ),
+                )
+                .at(&field.pointer),
+            );
         }
+    }
+}
 
-        if let Some(join) = &mapping.join
-            && !tables.contains(&join.table)
+/// U051: GROUP BY で取る集約が、同じ階層の他の集約の結合で行が増えて正しく集約できなくならないか
+fn validate_aggregate_strategies(model: &Model, errors: &mut Vec<ValidationError>) {
+    for (index, field) in model.fields.iter().enumerate() {
+        if let Some(strategy) = &field.aggregate_strategy
+            && strategy != "group_by"
+            && strategy != "subquery"
         {
-            tables.push(join.table.clone());
+            errors.push(
+                ValidationError::new(
+                    &rules::AGGREGATE_STRATEGY,
+                    format!(
+                        "フィールド '{}' の aggregate.strategy '{}' は使えません（group_by か subquery）",
+                        field.path, strategy
+                    ),
+                )
+                .at(format!("{}/aggregate/strategy", field.pointer)),
+            );
+        } else if model.group_by_multiplied(index) {
+            errors.push(
+                ValidationError::new(
+                    &rules::AGGREGATE_STRATEGY,
+                    format!(
+                        "フィールド '{}' の {} は同じ階層の他の集約の結合で行が重複するため、GROUP BY では正しく集約できません。\
+                         join のある集約は aggregate.strategy を subquery にすると相関サブクエリで取ります",
+                        field.path,
+                        field.aggregate.as_deref().unwrap_or_default()
+                    ),
+                )
+                .at(format!("{}/aggregate", field.pointer)),
+            );
         }
+    }
+}
+
+/// フルパス `path` の集約フィールドをまとめるキー（`Model::group_key`）。group_by の書き方の例に使う
+pub(crate) fn group_by_example(model: &Model, path: &str) -> Option<String> {
+    let key = model.group_key(model.field_index(path)?)?;
+    Some(format!("{}.{}", key.qualifier, key.column))
+}
 
-        if let Some(chain) = &mapping.join_chain {
-            for entry in chain {
-                if !tables.contains(&entry.table) {
-                    tables.push(entry.table.clone());
+/// U060: group_by の無い集約を、生成するクエリが同じ階層のどの列でまとめるかを知らせる（情報）
+fn validate_implicit_group_by(
+    doc: &UsmlDocument,
+    model: &Model,
+    errors: &mut Vec<ValidationError>,
+) {
+    for (index, field) in model.fields.iter().enumerate() {
+        if field.aggregate.is_none() || model.aggregate_subquery(index) {
+            continue;
+        }
+        let declared = mapping_at(&doc.usecase.response_mapping, &field.path)
+            .and_then(|mapping| mapping.aggregate.as_ref())
+            .is_some_and(|agg| agg.group_by.is_some());
+        if declared {
+            continue;
+        }
+        let mut columns: Vec<String> = model
+            .group_key(index)
+            .map(|key| format!("{}.{}", key.qualifier, key.column))
+            .into_iter()
+            .collect();
+        for sibling in &model.fields {
+            if sibling.parent != field.parent
+                || sibling.kind != FieldKind::Scalar
+                || sibling.aggregate.is_some()
+            {
+                continue;
+            }
+            if let Some(source) = &sibling.source {
+                let column = format!("{}.{}", source.qualifier, source.column);
+                if !columns.contains(&column) {
+                    columns.push(column);
                 }
             }
         }
+        if !columns.is_empty() {
+            errors.push(
+                ValidationError::new(
+                    &rules::IMPLICIT_GROUP_BY,
+                    format!(
+                        "フィールド '{}' の集約に暗黙の group_by を適用します: {}",
+                        field.path,
+                        columns.join(", ")
+                    ),
+                )
+                .at(format!("{}/aggregate", field.pointer)),
+            );
+        }
+    }
+}
 
-        if let Some(sub_fields) = &mapping.fields {
-            for table in collect_used_tables(sub_fields) {
-                if !tables.contains(&table) {
-                    tables.push(table);
-                }
+/// U061: source がテーブル名・カラム名のそろった参照で、join.on・join_chain[].on が空でないか
+fn validate_reference_syntax(model: &Model, errors: &mut Vec<ValidationError>) {
+    for field in &model.fields {
+        if let Some(source) = &field.source
+            && (source.qualifier.trim().is_empty() || source.column.trim().is_empty())
+        {
+            errors.push(
+                ValidationError::new(
+                    &rules::REFERENCE_SYNTAX,
+                    format!(
+                        "フィールド '{}' の source '{}.{}' は テーブル.カラム の形式ではありません",
+                        field.path, source.qualifier, source.column
+                    ),
+                )
+                .at(format!("{}/source", field.pointer)),
+            );
+        }
+    }
+    for join in &model.joins {
+        if join.on.trim().is_empty() {
+            let key = if join.chain_index.is_some() {
+                "join_chain[].on"
+            } else {
+                "join.on"
+            };
+            errors.push(
+                ValidationError::new(
+                    &rules::REFERENCE_SYNTAX,
+                    format!(
+                        "フィールド '{}' の {}（テーブル '{}'）が空です",
+                        join.field, key, join.table
+                    ),
+                )
+                .at(format!("{}/on", model.join_pointer(join))),
+            );
+        }
+    }
+}
+
+/// U062: PAGINATION の page_size・max_page_size が 1 以上で、カーソル方式に cursor_field があるか
+fn validate_pagination(doc: &UsmlDocument, errors: &mut Vec<ValidationError>) {
+    for (index, filter) in doc
+        .usecase
+        .filters
+        .iter()
+        .enumerate()
+        .filter(|(_, f)| f.maps_to == "PAGINATION")
+    {
+        for (key, size) in [
+            ("page_size", filter.page_size),
+            ("max_page_size", filter.max_page_size),
+        ] {
+            if size == Some(0) {
+                errors.push(
+                    ValidationError::new(
+                        &rules::PAGINATION,
+                        format!(
+                            "PAGINATION フィルタ '{}' の {} が 0 です（1 以上）",
+                            filter.param, key
+                        ),
+                    )
+                    .at(format!("/usecase/filters/{}/{}", index, key)),
+                );
             }
         }
+        if filter.strategy.as_deref() == Some("cursor") && filter.cursor_field.is_none() {
+            errors.push(
+                ValidationError::new(
+                    &rules::PAGINATION,
+                    format!(
+                        "PAGINATION フィルタ '{}' は strategy: cursor ですが cursor_field がありません",
+                        filter.param
+                    ),
+                )
+                .at(format!("/usecase/filters/{}/strategy", index)),
+            );
+        }
     }
+}
 
-    tables
+/// `comments.author` のようなフルパスの response_mapping
+fn mapping_at<'a>(mappings: &'a [ResponseMapping], path: &str) -> Option<&'a ResponseMapping> {
+    let (name, rest) = match path.split_once('.') {
+        Some((name, rest)) => (name, Some(rest)),
+        None => (path, None),
+    };
+    let mapping = mappings.iter().find(|m| m.field == name)?;
+    match rest {
+        Some(rest) => mapping_at(mapping.fields.as_deref()?, rest),
+        None => Some(mapping),
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::parser;
-    use crate::resolver::{DbmlTable, OpenapiResponse};
+/// U052: 配列の要素の取り方が生成器の扱える値か
+fn validate_hydration(doc: &UsmlDocument, errors: &mut Vec<ValidationError>) {
+    let Some(hydration) = &doc.usecase.hydration else {
+        return;
+    };
+    let strategy = hydration.strategy.to_ascii_lowercase();
+    if strategy != "batch" && strategy != "json" {
+        errors.push(
+            ValidationError::new(
+                &rules::HYDRATION,
+                format!(
+                    "hydration.strategy '{}' は使えません（batch か json）",
+                    hydration.strategy
+                ),
+            )
+            .at("/usecase/hydration/strategy"),
+        );
+    }
+    if let Some(dialect) = &hydration.dialect {
+        if !["postgresql", "mysql"].contains(&dialect.to_ascii_lowercase().as_str()) {
+            errors.push(
+                ValidationError::new(
+                    &rules::HYDRATION,
+                    format!(
+                        "hydration.dialect '{}' は使えません（postgresql か mysql）",
+                        dialect
+                    ),
+                )
+                .at("/usecase/hydration/dialect"),
+            );
+        } else if strategy == "batch" {
+            errors.push(
+                ValidationError::new(
+                    &rules::HYDRATION,
+                    "hydration.dialect は strategy: json のときだけ指定できます".to_string(),
+                )
+                .at("/usecase/hydration/dialect"),
+            );
+        }
+    }
+}
 
-    #[test]
-    fn test_valid_document_no_errors() {
-        let yaml = r#"
-version: "0.1"
-import:
-  openapi: ./api.yaml#paths["/users"].get.responses["200"]
-  dbml:
-    - ./schema.dbml#tables["users"]
-    - ./schema.dbml#tables["profiles"]
-usecase:
-  name: ユーザー一覧取得
-  response_mapping:
-    - field: id
-      source: users.id
-    - field: avatar_url
-      source: profiles.avatar_url
-      join:
-        table: profiles
-        on: users.id = profiles.user_id
-  transforms:
-    - target: avatar_url
-      type: COALESCE
-      sources:
-        - profiles.avatar_url
-      fallback: "/default.png"
-"#;
-        let doc = parser::parse(yaml).unwrap();
-        let errors = validate(&doc);
-        let hard_errors: Vec<_> = errors
-            .iter()
-            .filter(|e| matches!(e, ValidationError::Rule(..)))
-            .collect();
-        assert!(
-            hard_errors.is_empty(),
-            "エラーがありました: {:?}",
-            hard_errors
+/// U053: hydration を省略した usecase で、同じ親のキーで取る兄弟の配列が配列ごとのクエリにならないか
+fn validate_sibling_arrays(doc: &UsmlDocument, model: &Model, errors: &mut Vec<ValidationError>) {
+    if doc.usecase.hydration.is_some() {
+        return;
+    }
+    // (親のフィールド, 親の側の列) ごとの配列
+    let mut siblings: BTreeMap<(Option<usize>, String), Vec<&FieldNode>> = BTreeMap::new();
+    for (index, field) in model.fields.iter().enumerate() {
+        if let Some(key) = model.parent_key(index) {
+            siblings
+                .entry((field.parent, format!("{}.{}", key.qualifier, key.column)))
+                .or_default()
+                .push(field);
+        }
+    }
+    for ((_, key), arrays) in siblings {
+        if arrays.len() < 2 {
+            continue;
+        }
+        let names: Vec<String> = arrays.iter().map(|a| format!("'{}'", a.path)).collect();
+        errors.push(
+            ValidationError::new(
+                &rules::SIBLING_ARRAYS,
+                format!(
+                    "配列 {} はどれも {} で親の行に対応付けるため、配列ごとにクエリを発行します。\
+                     hydration.strategy: json で親のクエリにまとめるか、batch を明示してください",
+                    names.join("・"),
+                    key
+                ),
+            )
+            .at(&arrays[1].pointer),
         );
     }
+}
 
-    #[test]
-    fn test_missing_import_table() {
-        let yaml = r#"
-version: "0.1"
-import:
-  openapi: ./api.yaml#paths["/users"].get.responses["200"]
-  dbml:
-    - ./schema.dbml#tables["users"]
-usecase:
-  name: テスト
+/// U054: output が出力ディレクトリの中の HTML ファイルを指しているか
+fn validate_output(doc: &UsmlDocument, errors: &mut Vec<ValidationError>) {
+    if let Some(name) = &doc.usecase.output
+        && let Err(reason) = output::check_output_name(name)
+    {
+        errors.push(
+            ValidationError::new(
+                &rules::OUTPUT_NAME,
+                format!("output '{}' は使えません: {}", name, reason),
+            )
+            .at("/usecase/output"),
+        );
+    }
+}
+
+/// U055: PAGINATION の total_count が件数の取り方と矛盾しないか
+fn validate_total_count(doc: &UsmlDocument, model: &Model, errors: &mut Vec<ValidationError>) {
+    let usecase = &doc.usecase;
+    for (index, filter) in usecase.filters.iter().enumerate() {
+        let Some(total_count) = &filter.total_count else {
+            continue;
+        };
+        let message = match total_count.to_ascii_lowercase().as_str() {
+            _ if filter.maps_to != "PAGINATION" => format!(
+                "total_count は PAGINATION フィルタにだけ指定できます（'{}'）",
+                filter.param
+            ),
+            "query" => continue,
+            "window" if filter.strategy.as_deref() == Some("cursor") => {
+                "total_count: window はカーソル方式では使えません（カーソルより後の行だけを数えます）"
+                    .to_string()
+            }
+            "window" if usecase.distinct => {
+                "total_count: window は DISTINCT の usecase では使えません（重複を除く前の行を数えます）"
+                    .to_string()
+            }
+            "window" => continue,
+            "none" => {
+                let Some(total) = model
+                    .fields
+                    .iter()
+                    .find(|f| f.envelope.as_deref() == Some("total"))
+                else {
+                    continue;
+                };
+                format!(
+                    "total_count: none の usecase に付帯フィールド '{}'（total）は置けません",
+                    total.path
+                )
+            }
+            _ => format!(
+                "total_count '{}' は使えません（window / query / none）",
+                total_count
+            ),
+        };
+        errors.push(
+            ValidationError::new(&rules::TOTAL_COUNT, message)
+                .at(format!("/usecase/filters/{}/total_count", index)),
+        );
+    }
+}
+
+/// U027, U028: 一覧レスポンスの collection と付帯フィールドが PAGINATION フィルタと整合しているか
+fn validate_collection(doc: &UsmlDocument, model: &Model, errors: &mut Vec<ValidationError>) {
+    let pagination = doc
+        .usecase
+        .filters
+        .iter()
+        .position(|f| f.maps_to == "PAGINATION");
+    let pagination_pointer = |key: &str| {
+        format!(
+            "/usecase/filters/{}/{}",
+            pagination.unwrap_or_default(),
+            key
+        )
+    };
+    let pagination = pagination.map(|index| &doc.usecase.filters[index]);
+    let collections: Vec<&FieldNode> = model.fields.iter().filter(|f| f.collection).collect();
+
+    // U027: collection はルートの配列 1 つだけに付け、カーソルはその要素のフィールドを指す
+    for field in &collections {
+        if field.kind != FieldKind::Array {
+            errors.push(
+                ValidationError::new(
+                    &rules::COLLECTION,
+                    format!(
+                        "collection を指定したフィールド '{}' が type: array ではありません",
+                        field.path
+                    ),
+                )
+                .at(format!("{}/collection", field.pointer)),
+            );
+        }
+        if field.depth > 0 {
+            errors.push(
+                ValidationError::new(
+                    &rules::COLLECTION,
+                    format!(
+                        "collection はルートのフィールドにだけ指定できます（'{}'）",
+                        field.path
+                    ),
+                )
+                .at(format!("{}/collection", field.pointer)),
+            );
+        }
+    }
+    if collections.len() > 1 {
+        let paths: Vec<&str> = collections.iter().map(|f| f.path.as_str()).collect();
+        errors.push(
+            ValidationError::new(
+                &rules::COLLECTION,
+                format!(
+                    "collection が複数のフィールド（{}）に指定されています",
+                    paths.join(", ")
+                ),
+            )
+            .at(format!("{}/collection", collections[1].pointer)),
+        );
+    }
+    if let (Some(collection), Some(cursor_field)) = (
+        collections.first(),
+        pagination.and_then(|f| f.cursor_field.as_deref()),
+    ) && model
+        .field(&format!("{}.{}", collection.path, cursor_field))
+        .is_none()
+    {
+        errors.push(
+            ValidationError::new(
+                &rules::COLLECTION,
+                format!(
+                    "PAGINATION の cursor_field '{}' が collection '{}' の要素のフィールドにありません",
+                    cursor_field, collection.path
+                ),
+            )
+            .at(pagination_pointer("cursor_field")),
+        );
+    }
+
+    // U028: 付帯フィールドは値が自動で決まり、collection と PAGINATION を前提にする
+    let cursor_pagination = pagination.is_some_and(|f| f.strategy.as_deref() == Some("cursor"));
+    for field in &model.fields {
+        let Some(envelope) = &field.envelope else {
+            continue;
+        };
+        let message = if envelope != "total" && envelope != "next_cursor" {
+            format!(
+                "フィールド '{}' の envelope '{}' は使えません（total / next_cursor）",
+                field.path, envelope
+            )
+        } else if field.depth > 0 {
+            format!("付帯フィールド '{}' はルートにだけ置けます", field.path)
+        } else if field.source.is_some()
+            || !field.joins.is_empty()
+            || field.aggregate.is_some()
+            || field.kind == FieldKind::Array
+        {
+            format!(
+                "付帯フィールド '{}' の値は自動で決まるため、source・join・aggregate・type: array は指定できません",
+                field.path
+            )
+        } else if collections.is_empty() {
+            format!(
+                "付帯フィールド '{}'（{}）に対応する collection の配列がありません",
+                field.path, envelope
+            )
+        } else if envelope == "next_cursor" && !cursor_pagination {
+            format!(
+                "付帯フィールド '{}'（next_cursor）にはカーソル方式（strategy: cursor）の PAGINATION フィルタが必要です",
+                field.path
+            )
+        } else {
+            continue;
+        };
+        errors.push(
+            ValidationError::new(&rules::COLLECTION_ENVELOPE, message)
+                .at(format!("{}/envelope", field.pointer)),
+        );
+    }
+}
+
+/// U032: 固定値・リクエストパラメータのフィールドがデータベースを参照していないか
+fn validate_values(model: &Model, errors: &mut Vec<ValidationError>) {
+    for field in &model.fields {
+        let key = match (&field.value, &field.param) {
+            (Some(_), Some(_)) => {
+                errors.push(
+                    ValidationError::new(
+                        &rules::FIELD_VALUE,
+                        format!(
+                            "フィールド '{}' に value と from_param（source: \":name\"）の両方が指定されています",
+                            field.path
+                        ),
+                    )
+                    .at(&field.pointer),
+                );
+                continue;
+            }
+            (Some(_), None) => "value",
+            (None, Some(_)) => "from_param",
+            (None, None) => continue,
+        };
+        if field.source.is_some()
+            || !field.joins.is_empty()
+            || field.aggregate.is_some()
+            || field.envelope.is_some()
+            || field.kind == FieldKind::Array
+        {
+            errors.push(
+                ValidationError::new(
+                    &rules::FIELD_VALUE,
+                    format!(
+                        "フィールド '{}' は {} を指定しているため、source・join・aggregate・envelope・type: array は指定できません",
+                        field.path, key
+                    ),
+                )
+                .at(&field.pointer),
+            );
+        }
+    }
+}
+
+/// U056: timezone が source のカラムを返すフィールドに、タイムゾーン名か `:name` で書かれているか
+fn validate_timezones(model: &Model, errors: &mut Vec<ValidationError>) {
+    for field in &model.fields {
+        let Some(timezone) = &field.timezone else {
+            continue;
+        };
+        if field.kind != FieldKind::Scalar || field.source.is_none() {
+            errors.push(
+                ValidationError::new(
+                    &rules::FIELD_TIMEZONE,
+                    format!(
+                        "フィールド '{}' の timezone は source のカラムを返すスカラーのフィールドにだけ指定できます",
+                        field.path
+                    ),
+                )
+                .at(format!("{}/timezone", field.pointer)),
+            );
+        } else if let Some(aggregate) = &field.aggregate
+            && aggregate != "MIN"
+            && aggregate != "MAX"
+        {
+            errors.push(
+                ValidationError::new(
+                    &rules::FIELD_TIMEZONE,
+                    format!(
+                        "フィールド '{}' の timezone は集約 {} と併用できません（MIN・MAX だけ）",
+                        field.path, aggregate
+                    ),
+                )
+                .at(format!("{}/timezone", field.pointer)),
+            );
+        }
+        let valid = match timezone.strip_prefix(':') {
+            Some(param) => {
+                param.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                    && param.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            }
+            None => is_timezone_name(timezone),
+        };
+        if !valid {
+            errors.push(
+                ValidationError::new(
+                    &rules::FIELD_TIMEZONE,
+                    format!(
+                        "フィールド '{}' の timezone '{}' は IANA のタイムゾーン名（Asia/Tokyo・UTC）か :name で指定します",
+                        field.path, timezone
+                    ),
+                )
+                .at(format!("{}/timezone", field.pointer)),
+            );
+        }
+    }
+}
+
+/// `UTC` か `Area/Location` 形式（`America/Argentina/Buenos_Aires`・`Etc/GMT+9` など）のタイムゾーン名か
+fn is_timezone_name(name: &str) -> bool {
+    name.eq_ignore_ascii_case("UTC")
+        || (name.contains('/')
+            && name.split('/').all(|segment| {
+                segment.starts_with(|c: char| c.is_ascii_alphabetic())
+                    && segment
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '+'))
+            }))
+}
+
+/// U034: authorization.row_filters の条件が import したテーブルのカラムを参照しているか
+fn validate_row_filters(doc: &UsmlDocument, model: &Model, errors: &mut Vec<ValidationError>) {
+    let Some(authorization) = &doc.usecase.authorization else {
+        return;
+    };
+    for (index, condition) in authorization.row_filters.iter().enumerate() {
+        let pointer = format!("/usecase/authorization/row_filters/{}", index);
+        let refs = table_refs(condition);
+        if refs.is_empty() {
+            errors.push(
+                ValidationError::new(
+                    &rules::AUTHORIZATION_ROW_FILTER,
+                    format!(
+                        "authorization.row_filters の条件 '{}' がカラム（テーブル.カラム）を参照していません",
+                        condition
+                    ),
+                )
+                .at(&pointer),
+            );
+        }
+        let mut reported: HashSet<&str> = HashSet::new();
+        for (qualifier, _) in &refs {
+            let table = model.resolve_table(qualifier);
+            if !model.table(table).is_some_and(|t| t.imported) && reported.insert(table) {
+                errors.push(
+                    ValidationError::new(
+                        &rules::AUTHORIZATION_ROW_FILTER,
+                        format!(
+                            "authorization.row_filters の条件 '{}' のテーブル '{}' が import.dbml に含まれていません",
+                            condition, table
+                        ),
+                    )
+                    .at(&pointer),
+                );
+            }
+        }
+    }
+}
+
+/// U035: usecase.cache の key・invalidated_by が宣言済みのパラメータ・テーブルを指すか
+fn validate_cache(doc: &UsmlDocument, model: &Model, errors: &mut Vec<ValidationError>) {
+    let Some(cache) = &doc.usecase.cache else {
+        return;
+    };
+    let mut report = |message: String, pointer: String| {
+        errors.push(ValidationError::new(&rules::CACHE, message).at(pointer))
+    };
+    if cache.ttl == 0 {
+        report(
+            "cache.ttl が 0 のためキャッシュされません".to_string(),
+            "/usecase/cache/ttl".to_string(),
+        );
+    }
+    for (index, param) in cache.key.iter().enumerate() {
+        if !doc.usecase.filters.iter().any(|f| &f.param == param) {
+            report(
+                format!(
+                    "cache.key のパラメータ '{}' が filters[].param で宣言されていません",
+                    param
+                ),
+                format!("/usecase/cache/key/{}", index),
+            );
+        }
+    }
+    for (index, table) in cache.invalidated_by.iter().enumerate() {
+        if !model.table(table).is_some_and(|t| t.imported) {
+            report(
+                format!(
+                    "cache.invalidated_by のテーブル '{}' が import.dbml に含まれていません",
+                    table
+                ),
+                format!("/usecase/cache/invalidated_by/{}", index),
+            );
+        }
+    }
+}
+
+/// U036: 別の usecase を参照するフィールドが自分でも値を決めていないか
+fn validate_source_usecases(model: &Model, errors: &mut Vec<ValidationError>) {
+    for (index, field) in model.fields.iter().enumerate() {
+        if field.source_usecase.is_none() {
+            continue;
+        }
+        let has_children = model.fields.iter().any(|f| f.parent == Some(index));
+        if field.source.is_some()
+            || field.value.is_some()
+            || field.param.is_some()
+            || field.aggregate.is_some()
+            || field.envelope.is_some()
+            || has_children
+        {
+            errors.push(
+                ValidationError::new(
+                    &rules::SOURCE_USECASE,
+                    format!(
+                        "フィールド '{}' は source_usecase を指定しているため、source・value・from_param・aggregate・envelope・fields は指定できません",
+                        field.path
+                    ),
+                )
+                .at(format!("{}/source_usecase", field.pointer)),
+            );
+        }
+    }
+}
+
+/// U038: type・maps_to を省略した transform・filter がライブラリの定義を使っているか
+fn validate_library_uses(doc: &UsmlDocument, errors: &mut Vec<ValidationError>) {
+    for (index, transform) in doc.usecase.transforms.iter().enumerate() {
+        if transform.r#type.is_empty() && transform.r#use.is_none() {
+            errors.push(
+                ValidationError::new(
+                    &rules::LIBRARY_REFERENCE,
+                    format!(
+                        "transform {} に type も use も指定されていません",
+                        transform.target
+                    ),
+                )
+                .at(format!("/usecase/transforms/{}", index)),
+            );
+        }
+    }
+    for (index, filter) in doc.usecase.filters.iter().enumerate() {
+        if filter.maps_to.is_empty() && filter.r#use.is_none() {
+            errors.push(
+                ValidationError::new(
+                    &rules::LIBRARY_REFERENCE,
+                    format!(
+                        "フィルタ {} に maps_to も use も指定されていません",
+                        filter.param
+                    ),
+                )
+                .at(format!("/usecase/filters/{}", index)),
+            );
+        }
+    }
+}
+
+/// U059: environments の上書きがレスポンスの構造を変えないか
+///
+/// 上書きできるのは import だけで、import.openapi は同じレスポンスを、import.dbml は同じテーブルを参照する
+fn validate_environments(doc: &UsmlDocument, errors: &mut Vec<ValidationError>) {
+    let response = |reference: Option<&str>| {
+        reference
+            .and_then(resolver::openapi::parse_openapi_ref)
+            .map(|(_, path, method, status)| {
+                format!("{} {} {}", method.to_ascii_uppercase(), path, status)
+            })
+    };
+    let tables = |import: &crate::ast::Import| {
+        let mut tables = crate::api::import_tables(import);
+        tables.sort();
+        tables
+    };
+    for (name, environment) in &doc.environments {
+        let pointer = |key: &str| format!("/environments/{}/{}", name, key);
+        for key in environment.overrides.keys() {
+            errors.push(
+                ValidationError::new(
+                    &rules::ENVIRONMENT_OVERLAY,
+                    format!(
+                        "environments.{} の {} は上書きできません（環境ごとに変えられるのは import だけです）",
+                        name, key
+                    ),
+                )
+                .at(pointer(key)),
+            );
+        }
+        let Some(overlay) = &environment.import else {
+            continue;
+        };
+        let mut import = doc.import.clone();
+        import.overlay(overlay.clone());
+        if overlay.openapi.is_some() {
+            let expected = response(doc.import.openapi.as_deref());
+            let actual = response(import.openapi.as_deref());
+            if actual != expected {
+                errors.push(
+                    ValidationError::new(
+                        &rules::ENVIRONMENT_OVERLAY,
+                        format!(
+                            "environments.{} の import.openapi は {} を参照しています（import は {}）",
+                            name,
+                            actual.as_deref().unwrap_or("レスポンス以外"),
+                            expected.as_deref().unwrap_or("openapi 無し")
+                        ),
+                    )
+                    .at(pointer("import/openapi")),
+                );
+            }
+        }
+        let (expected, actual) = (tables(&doc.import), tables(&import));
+        if actual != expected {
+            errors.push(
+                ValidationError::new(
+                    &rules::ENVIRONMENT_OVERLAY,
+                    format!(
+                        "environments.{} の import.dbml・import.tables で取り込むテーブル（{}）が import（{}）と異なります",
+                        name,
+                        actual.join(", "),
+                        expected.join(", ")
+                    ),
+                )
+                .at(pointer("import")),
+            );
+        }
+    }
+}
+
+/// NULL を返さないはずのフィールドについて、その根拠（宣言か OpenAPI か）
+///
+/// `openapi` が None の場合は `nullable: false` の宣言だけを見る
+fn not_null_reason(field: &FieldNode, openapi: Option<&OpenapiResponse>) -> Option<&'static str> {
+    if field.kind == FieldKind::Array || field.aggregate.as_deref() == Some("COUNT") {
+        return None;
+    }
+    match field.nullable {
+        Some(false) => Some("nullable: false"),
+        Some(true) => None,
+        None => openapi
+            .and_then(|openapi| openapi.property(&field.path))
+            .filter(|property| property.required && !property.nullable)
+            .map(|_| "OpenAPI で required"),
+    }
+}
+
+/// LEFT JOIN で結合されるテーブル（配列の要素を作る結合は NULL を生まないので除く）
+fn left_joined_tables(model: &Model) -> HashSet<&str> {
+    model
+        .joins
+        .iter()
+        .filter(|join| join.kind == JoinKind::Left)
+        .filter(|join| {
+            model
+                .field(&join.field)
+                .is_some_and(|f| f.kind == FieldKind::Scalar)
+        })
+        .map(|join| join.table.as_str())
+        .collect()
+}
+
+/// `target` に fallback 付きの COALESCE が掛かっているか
+fn has_fallback(doc: &UsmlDocument, target: &str) -> bool {
+    doc.usecase.transforms.iter().any(|t| {
+        t.target == target && t.r#type.eq_ignore_ascii_case("COALESCE") && t.fallback.is_some()
+    })
+}
+
+/// U030: nullable の宣言が OpenAPI のプロパティ定義と一致するか
+fn validate_nullable_declarations(
+    model: &Model,
+    openapi: &OpenapiResponse,
+    errors: &mut Vec<ValidationError>,
+) {
+    for field in &model.fields {
+        let (Some(nullable), Some(property)) = (field.nullable, openapi.property(&field.path))
+        else {
+            continue;
+        };
+        let conflict = if nullable {
+            property.required && !property.nullable
+        } else {
+            property.nullable
+        };
+        if conflict {
+            errors.push(
+                ValidationError::new(
+                    &rules::NULLABLE_OPENAPI,
+                    format!(
+                        "フィールド {} は nullable: {} ですが、OpenAPI では {}",
+                        field.path,
+                        nullable,
+                        if property.nullable {
+                            "nullable です"
+                        } else {
+                            "required かつ nullable ではありません"
+                        }
+                    ),
+                )
+                .at(format!("{}/nullable", field.pointer)),
+            );
+        }
+    }
+}
+
+/// U031: NULL を返さないフィールドの source が LEFT JOIN で結合したテーブルにないか（警告）
+///
+/// `openapi` が None なら `nullable: false` の宣言だけ、Some なら宣言の無いフィールドを OpenAPI で調べる
+fn validate_nullable_joins(
+    doc: &UsmlDocument,
+    model: &Model,
+    openapi: Option<&OpenapiResponse>,
+    errors: &mut Vec<ValidationError>,
+) {
+    let left_joined = left_joined_tables(model);
+    for field in &model.fields {
+        if openapi.is_some() && field.nullable.is_some() {
+            continue;
+        }
+        let Some(source) = &field.source else {
+            continue;
+        };
+        if let Some(reason) = not_null_reason(field, openapi)
+            && left_joined.contains(source.table.as_str())
+            && !has_fallback(doc, &field.path)
+        {
+            errors.push(
+                ValidationError::new(
+                    &rules::NULLABLE_SOURCE,
+                    format!(
+                        "フィールド '{}' は {} ですが、source '{}.{}' は LEFT JOIN で結合したテーブルのため NULL になりえます（COALESCE の fallback がありません）",
+                        field.path, reason, source.qualifier, source.column
+                    ),
+                )
+                .at(format!("{}/source", field.pointer)),
+            );
+        }
+    }
+}
+
+/// U033: 固定値・リクエストパラメータの型が OpenAPI のプロパティの型に合うか
+fn validate_value_types(
+    model: &Model,
+    openapi: &OpenapiResponse,
+    errors: &mut Vec<ValidationError>,
+) {
+    for field in &model.fields {
+        let param = field
+            .param
+            .as_deref()
+            .or_else(|| field.value.as_ref().and_then(|v| v.param()));
+        let expected = openapi
+            .property(&field.path)
+            .and_then(|p| p.schema_type.as_deref());
+        let message = match (param, &field.value) {
+            (Some(name), _) => match openapi.parameter(name) {
+                None => format!(
+                    "フィールド '{}' が参照するパラメータ '{}' が OpenAPI に存在しません",
+                    field.path, name
+                ),
+                Some(parameter) => match (expected, parameter.schema_type.as_deref()) {
+                    (Some(expected), Some(actual)) if !json_type_compatible(expected, actual) => {
+                        format!(
+                            "フィールド '{}' は OpenAPI で {} ですが、パラメータ '{}' は {} です",
+                            field.path, expected, name, actual
+                        )
+                    }
+                    _ => continue,
+                },
+            },
+            (None, None) => continue,
+            (None, Some(value)) => match (expected, value.json_type()) {
+                (Some(expected), Some(actual)) if !json_type_compatible(expected, actual) => {
+                    format!(
+                        "フィールド '{}' は OpenAPI で {} ですが、value {} は {} です",
+                        field.path, expected, value, actual
+                    )
+                }
+                _ => continue,
+            },
+        };
+        errors.push(ValidationError::new(&rules::FIELD_VALUE_TYPE, message).at(&field.pointer));
+    }
+}
+
+/// U049: filters[].required・default が OpenAPI のパラメータの required・schema の default と一致するか
+///
+/// required を省略したフィルタは任意として生成されるため、OpenAPI で必須のパラメータには
+/// required: true を書かせる。OpenAPI に無いパラメータは対象にしない
+fn validate_filter_requirements(
+    filters: &[crate::ast::Filter],
+    openapi: &OpenapiResponse,
+    errors: &mut Vec<ValidationError>,
+) {
+    for (index, filter) in filters.iter().enumerate() {
+        let Some(parameter) = openapi.parameter(&filter.param) else {
+            continue;
+        };
+        let mut messages = Vec::new();
+        match filter.required {
+            Some(required) if required != parameter.required => messages.push(format!(
+                "required: {} ですが、OpenAPI では required: {} です",
+                required, parameter.required
+            )),
+            None if parameter.required => messages.push(
+                "OpenAPI では required: true ですが、required がありません（省略すると任意のパラメータとして生成されます）"
+                    .to_string(),
+            ),
+            _ => {}
+        }
+        if let Some(default) = &filter.default {
+            match &parameter.default {
+                None => messages.push(format!(
+                    "default {} がありますが、OpenAPI の schema に default がありません",
+                    default
+                )),
+                Some(expected) if !literal_matches(default, expected) => messages.push(format!(
+                    "default {} ですが、OpenAPI の schema の default は {} です",
+                    default, expected
+                )),
+                Some(_) => {}
+            }
+            if let (Some(expected), Some(actual)) =
+                (parameter.schema_type.as_deref(), default.json_type())
+                && !json_type_compatible(expected, actual)
+            {
+                messages.push(format!(
+                    "default {} は {} ですが、パラメータは OpenAPI で {} です",
+                    default, actual, expected
+                ));
+            }
+        }
+        for message in messages {
+            errors.push(
+                ValidationError::new(
+                    &rules::FILTER_REQUIRED_OPENAPI,
+                    format!("filters[]（param '{}'）: {}", filter.param, message),
+                )
+                .at(format!("/usecase/filters/{}", index)),
+            );
+        }
+    }
+}
+
+/// 固定値が等しいか（数値は整数と小数を区別しない）
+fn literal_matches(a: &Literal, b: &Literal) -> bool {
+    match (a, b) {
+        (Literal::Integer(a), Literal::Number(b)) | (Literal::Number(b), Literal::Integer(a)) => {
+            *a as f64 == *b
+        }
+        _ => a == b,
+    }
+}
+
+/// Rule 1: response_mapping のフィールド名がOpenAPIレスポンスに存在するか
+fn validate_openapi_fields(
+    mappings: &[ResponseMapping],
+    openapi: &OpenapiResponse,
+    errors: &mut Vec<ValidationError>,
+) {
+    let fields: HashSet<&str> = openapi.fields.iter().map(String::as_str).collect();
+    for (index, mapping) in mappings.iter().enumerate() {
+        if fields.contains(mapping.field.as_str()) {
+            continue;
+        }
+        let pointer = format!("/usecase/response_mapping/{}/field", index);
+        let key = naming::normalized(&mapping.field);
+        if let Some(property) = openapi
+            .fields
+            .iter()
+            .find(|property| naming::normalized(property) == key)
+        {
+            errors.push(
+                ValidationError::new(
+                    &rules::OPENAPI_FIELD_CASING,
+                    format!(
+                        "フィールド {} は OpenAPI では {} と表記されています",
+                        mapping.field, property
+                    ),
+                )
+                .at(&pointer),
+            );
+        } else {
+            errors.push(
+                ValidationError::new(
+                    &rules::OPENAPI_FIELD,
+                    format!(
+                        "フィールド {} がOpenAPIレスポンスのプロパティに存在しません",
+                        mapping.field
+                    ),
+                )
+                .at(&pointer),
+            );
+        }
+    }
+}
+
+/// Rule 3: source で参照されるテーブル.カラムがDBMLに実際に存在するか
+fn validate_dbml_columns(
+    mappings: &[ResponseMapping],
+    pointer: &str,
+    dbml_columns: &HashMap<&str, HashSet<&str>>,
+    errors: &mut Vec<ValidationError>,
+) {
+    for (index, mapping) in mappings.iter().enumerate() {
+        let pointer = format!("{}/{}", pointer, index);
+        if let Some(source) = &mapping.source
+            && !source.starts_with(':')
+            && let Some((table_name, col_name)) = source.split_once('.')
+            && let Some(columns) = dbml_columns.get(table_name)
+            && !columns.contains(col_name)
+        {
+            errors.push(
+                ValidationError::new(
+                    &rules::DBML_COLUMN,
+                    format!(
+                        "カラム {} がテーブル {} に存在しません",
+                        col_name, table_name
+                    ),
+                )
+                .at(format!("{}/source", pointer)),
+            );
+        }
+
+        // サブフィールドの再帰検証
+        if let Some(sub_fields) = &mapping.fields {
+            validate_dbml_columns(
+                sub_fields,
+                &format!("{}/fields", pointer),
+                dbml_columns,
+                errors,
+            );
+        }
+    }
+}
+
+/// U020: transform の source・sources・then_source・else_source で参照されるテーブル.カラムがDBMLに実際に存在するか
+fn validate_transform_source_columns(
+    transforms: &[crate::ast::Transform],
+    dbml_columns: &HashMap<&str, HashSet<&str>>,
+    errors: &mut Vec<ValidationError>,
+) {
+    for (index, transform) in transforms.iter().enumerate() {
+        for source in model::transform_sources(transform) {
+            if let Some((table_name, col_name)) = source.split_once('.')
+                && let Some(columns) = dbml_columns.get(table_name)
+                && !columns.contains(col_name)
+            {
+                errors.push(
+                    ValidationError::new(
+                        &rules::TRANSFORM_SOURCE_COLUMN,
+                        format!(
+                            "transform {} の source のカラム {} がテーブル {} に存在しません",
+                            transform.target, col_name, table_name
+                        ),
+                    )
+                    .at(transform_source_pointer(index, transform, source)),
+                );
+            }
+        }
+    }
+}
+
+/// U021: CONDITIONAL_SOURCE の分岐のカラム型が OpenAPI のフィールド型（不明なら互いの型）と両立するか
+fn validate_conditional_source_types(
+    transforms: &[crate::ast::Transform],
+    tables: &HashMap<&str, &DbmlTable>,
+    openapi: Option<&OpenapiResponse>,
+    errors: &mut Vec<ValidationError>,
+) {
+    for (index, transform) in transforms.iter().enumerate() {
+        if !transform.r#type.eq_ignore_ascii_case("CONDITIONAL_SOURCE") {
+            continue;
+        }
+        let branches: Vec<(&str, &str)> = [&transform.then_source, &transform.else_source]
+            .into_iter()
+            .filter_map(|source| column_json_type(source.as_deref()?, tables))
+            .collect();
+        let expected = openapi
+            .and_then(|openapi| openapi.property(&transform.target))
+            .and_then(|property| property.schema_type.as_deref());
+        match expected {
+            Some(expected) => {
+                for (source, actual) in &branches {
+                    if !json_type_compatible(expected, actual) {
+                        errors.push(
+                            ValidationError::new(
+                                &rules::CONDITIONAL_SOURCE_TYPE,
+                                format!(
+                                    "transform {} の {} は {} 型ですが、OpenAPI のフィールド型は {} です",
+                                    transform.target, source, actual, expected
+                                ),
+                            )
+                            .at(transform_source_pointer(index, transform, source)),
+                        );
+                    }
+                }
+            }
+            None => {
+                if let [(then_source, then_type), (else_source, else_type)] = branches[..]
+                    && !json_type_compatible(then_type, else_type)
+                    && !json_type_compatible(else_type, then_type)
+                {
+                    errors.push(
+                        ValidationError::new(
+                            &rules::CONDITIONAL_SOURCE_TYPE,
+                            format!(
+                                "transform {} の then_source {}（{}）と else_source {}（{}）の型が一致しません",
+                                transform.target, then_source, then_type, else_source, else_type
+                            ),
+                        )
+                        .at(format!("/usecase/transforms/{}/else_source", index)),
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// U023: MASK の source が文字列型のカラムか
+fn validate_mask_source_types(
+    transforms: &[crate::ast::Transform],
+    tables: &HashMap<&str, &DbmlTable>,
+    errors: &mut Vec<ValidationError>,
+) {
+    for (index, transform) in transforms.iter().enumerate() {
+        if !transform.r#type.eq_ignore_ascii_case("MASK") {
+            continue;
+        }
+        if let Some((source, json_type)) = transform
+            .source
+            .as_deref()
+            .and_then(|source| column_json_type(source, tables))
+            && json_type != "string"
+        {
+            errors.push(
+                ValidationError::new(
+                    &rules::MASK_SOURCE_TYPE,
+                    format!(
+                        "transform {} の MASK の source {} は {} 型です（文字列型のカラムにだけ適用できます）",
+                        transform.target, source, json_type
+                    ),
+                )
+                .at(format!("/usecase/transforms/{}/source", index)),
+            );
+        }
+    }
+}
+
+/// U057: CONVERT の source と lookup.rate が数値型のカラムか
+fn validate_convert_source_types(
+    transforms: &[crate::ast::Transform],
+    tables: &HashMap<&str, &DbmlTable>,
+    errors: &mut Vec<ValidationError>,
+) {
+    for (index, transform) in transforms.iter().enumerate() {
+        if !transform.r#type.eq_ignore_ascii_case("CONVERT") {
+            continue;
+        }
+        let columns = [
+            ("source", transform.source.as_deref()),
+            (
+                "lookup.rate",
+                transform.lookup.as_ref().map(|l| l.rate.as_str()),
+            ),
+        ];
+        for (key, column) in columns {
+            if let Some((source, json_type)) = column.and_then(|c| column_json_type(c, tables))
+                && json_type != "integer"
+                && json_type != "number"
+            {
+                errors.push(
+                    ValidationError::new(
+                        &rules::TRANSFORM_CONVERT,
+                        format!(
+                            "transform {} の CONVERT の {} {} は {} 型です（数値型のカラムにだけ適用できます）",
+                            transform.target, key, source, json_type
+                        ),
+                    )
+                    .at(transform_source_pointer(index, transform, source)),
+                );
+            }
+        }
+    }
+}
+
+/// U058: LOCALIZE の locale.column が文字列型のカラムか
+fn validate_locale_column_types(
+    transforms: &[crate::ast::Transform],
+    tables: &HashMap<&str, &DbmlTable>,
+    errors: &mut Vec<ValidationError>,
+) {
+    for (index, transform) in transforms.iter().enumerate() {
+        if !transform.r#type.eq_ignore_ascii_case("LOCALIZE") {
+            continue;
+        }
+        if let Some((column, json_type)) = transform
+            .locale
+            .as_ref()
+            .and_then(|l| column_json_type(&l.column, tables))
+            && json_type != "string"
+        {
+            errors.push(
+                ValidationError::new(
+                    &rules::TRANSFORM_LOCALIZE,
+                    format!(
+                        "transform {} の LOCALIZE の locale.column {} は {} 型です（言語は文字列型のカラムにします）",
+                        transform.target, column, json_type
+                    ),
+                )
+                .at(format!("/usecase/transforms/{}/locale/column", index)),
+            );
+        }
+    }
+}
+
+/// U047: filters[].condition の比較がカラムの型に合っているか（警告）
+///
+/// - 文字列のカラムを `<`・`>`・`<=`・`>=` で比較している（日付・時刻の型は除く）
+/// - 数値のカラムに LIKE・ILIKE を使っている
+/// - `:パラメータ` の OpenAPI の型とカラムの型が合わない
+///
+/// `::` や CAST で型を明示した比較は対象にしない
+fn validate_condition_types(
+    doc: &UsmlDocument,
+    model: &Model,
+    tables: &HashMap<&str, &DbmlTable>,
+    openapi: Option<&OpenapiResponse>,
+    errors: &mut Vec<ValidationError>,
+) {
+    let column = |operand: &str| {
+        if operand.starts_with(':') || operand.contains("::") {
+            return None;
+        }
+        let (qualifier, name) = operand.split_once('.')?;
+        tables.get(model.resolve_table(qualifier))?.column(name)
+    };
+    for (index, filter) in doc.usecase.filters.iter().enumerate() {
+        let Some(condition) = &filter.condition else {
+            continue;
+        };
+        for (left, operator, right) in comparisons(condition) {
+            let (source, dbml_column, other) = match (column(left), column(right)) {
+                (Some(found), _) => (left, found, right),
+                (None, Some(found)) => (right, found, left),
+                (None, None) => continue,
+            };
+            let json_type = dbml_column.json_type();
+            let base = dbml_column
+                .data_type
+                .split('(')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_ascii_lowercase();
+            let temporal = matches!(
+                base.as_str(),
+                "date" | "time" | "timestamp" | "timestamptz" | "datetime"
+            );
+            let like = operator == "LIKE" || operator == "ILIKE";
+            let mut message = None;
+            if matches!(operator.as_str(), "<" | ">" | "<=" | ">=")
+                && json_type == Some("string")
+                && !temporal
+            {
+                message = Some(format!(
+                    "文字列のカラム {}（{}）を {} で比較しています。辞書順の比較になります",
+                    source, dbml_column.data_type, operator
+                ));
+            } else if like && matches!(json_type, Some("integer" | "number")) {
+                message = Some(format!(
+                    "数値のカラム {}（{}）に {} を使っています",
+                    source, dbml_column.data_type, operator
+                ));
+            } else if !like
+                && let Some(param) = other.strip_prefix(':')
+                && !param.contains("::")
+                && let Some(param_type) = openapi
+                    .and_then(|openapi| openapi.parameter(param))
+                    .and_then(|p| p.schema_type.as_deref())
+                && let Some(json_type) = json_type
+                && !json_type_compatible(json_type, param_type)
+                && !json_type_compatible(param_type, json_type)
+            {
+                message = Some(format!(
+                    "パラメータ :{}（OpenAPI の型 {}）をカラム {}（{}）と比較しています",
+                    param, param_type, source, dbml_column.data_type
+                ));
+            }
+            if let Some(message) = message {
+                errors.push(
+                    ValidationError::new(
+                        &rules::FILTER_CONDITION_TYPE,
+                        format!(
+                            "filters[].condition（param '{}'）: {}",
+                            filter.param, message
+                        ),
+                    )
+                    .at(format!("/usecase/filters/{}/condition", index)),
+                );
+            }
+        }
+    }
+}
+
+/// 条件式の比較（左辺・演算子・右辺）。演算子は `=`・`!=`・`<>`・`<`・`>`・`<=`・`>=`・LIKE・ILIKE
+///
+/// 括弧や関数呼び出しに接する辺は `(`・`)` になるため、呼び出し側で比較の対象から外れる
+fn comparisons(condition: &str) -> Vec<(&str, String, &str)> {
+    // 識別子・:パラメータ・リテラルと、記号の並びに分ける
+    let mut tokens: Vec<&str> = Vec::new();
+    let mut chars = condition.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let is_word = |c: char| c.is_alphanumeric() || matches!(c, '_' | '.' | ':' | '\'');
+        let is_operator = |c: char| matches!(c, '<' | '>' | '=' | '!');
+        let mut end = start + c.len_utf8();
+        if c.is_whitespace() {
+            continue;
+        } else if c == '\'' {
+            // 文字列リテラル
+            for (i, c) in chars.by_ref() {
+                end = i + c.len_utf8();
+                if c == '\'' {
+                    break;
+                }
+            }
+        } else if is_word(c) || is_operator(c) {
+            let same = if is_word(c) { is_word } else { is_operator };
+            while let Some(&(i, c)) = chars.peek() {
+                if !same(c) {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+        }
+        tokens.push(&condition[start..end]);
+    }
+
+    let mut comparisons = Vec::new();
+    for i in 1..tokens.len().saturating_sub(1) {
+        let operator = tokens[i].to_ascii_uppercase();
+        if matches!(
+            operator.as_str(),
+            "=" | "!=" | "<>" | "<" | ">" | "<=" | ">=" | "LIKE" | "ILIKE"
+        ) {
+            comparisons.push((tokens[i - 1], operator, tokens[i + 1]));
+        }
+    }
+    comparisons
+}
+
+/// U024: else_value の無い CASE が enum カラムの全ての値を when で扱っているか（警告）
+fn validate_case_exhaustive(
+    transforms: &[crate::ast::Transform],
+    tables: &HashMap<&str, &DbmlTable>,
+    errors: &mut Vec<ValidationError>,
+) {
+    for (index, transform) in transforms.iter().enumerate() {
+        if !transform.r#type.eq_ignore_ascii_case("CASE") || transform.else_value.is_some() {
+            continue;
+        }
+        let Some((table_name, col_name)) = transform
+            .source
+            .as_deref()
+            .and_then(|source| source.split_once('.'))
+        else {
+            continue;
+        };
+        let Some(column) = tables
+            .get(table_name)
+            .and_then(|table| table.column(col_name))
+        else {
+            continue;
+        };
+        let covered: HashSet<&str> = transform
+            .when
+            .iter()
+            .flatten()
+            .map(|w| w.value.as_str())
+            .collect();
+        let missing: Vec<&str> = column
+            .enum_values
+            .iter()
+            .map(String::as_str)
+            .filter(|value| !covered.contains(value))
+            .collect();
+        if !missing.is_empty() {
+            errors.push(
+                ValidationError::new(
+                    &rules::CASE_EXHAUSTIVE,
+                    format!(
+                        "transform {} の CASE は enum {} の値 {} を扱っておらず、else_value も無いため NULL になります",
+                        transform.target,
+                        column.data_type,
+                        missing.join(", ")
+                    ),
+                )
+                .at(format!("/usecase/transforms/{}", index)),
+            );
+        }
+    }
+}
+
+/// U026: SUM・AVG の source が数値型のカラムか
+fn validate_aggregate_arguments(
+    mappings: &[ResponseMapping],
+    pointer: &str,
+    tables: &HashMap<&str, &DbmlTable>,
+    errors: &mut Vec<ValidationError>,
+) {
+    for (index, mapping) in mappings.iter().enumerate() {
+        let pointer = format!("{}/{}", pointer, index);
+        if let Some(aggregate) = &mapping.aggregate
+            && ["SUM", "AVG"]
+                .iter()
+                .any(|a| a.eq_ignore_ascii_case(&aggregate.r#type))
+            && let Some((source, json_type)) = mapping
+                .source
+                .as_deref()
+                .and_then(|source| column_json_type(source, tables))
+            && json_type != "integer"
+            && json_type != "number"
+        {
+            errors.push(
+                ValidationError::new(
+                    &rules::AGGREGATE_NUMERIC,
+                    format!(
+                        "フィールド {} の {} の source {} は {} 型です（数値型のカラムにだけ適用できます）",
+                        mapping.field,
+                        aggregate.r#type.to_ascii_uppercase(),
+                        source,
+                        json_type
+                    ),
+                )
+                .at(format!("{}/source", pointer)),
+            );
+        }
+
+        if let Some(sub_fields) = &mapping.fields {
+            validate_aggregate_arguments(
+                sub_fields,
+                &format!("{}/fields", pointer),
+                tables,
+                errors,
+            );
+        }
+    }
+}
+
+/// U031: NULL を返さないフィールドの source が DBML で not null でないカラムか（警告）
+///
+/// LEFT JOIN 由来の場合は validate_nullable_joins が報告するので除く
+fn validate_nullable_columns(
+    doc: &UsmlDocument,
+    model: &Model,
+    tables: &HashMap<&str, &DbmlTable>,
+    openapi: Option<&OpenapiResponse>,
+    errors: &mut Vec<ValidationError>,
+) {
+    let left_joined = left_joined_tables(model);
+    for field in &model.fields {
+        let Some(source) = &field.source else {
+            continue;
+        };
+        let Some(column) = tables
+            .get(source.table.as_str())
+            .and_then(|table| table.column(&source.column))
+        else {
+            continue;
+        };
+        if let Some(reason) = not_null_reason(field, openapi)
+            && column.nullable != Some(false)
+            && !column.primary_key
+            && !left_joined.contains(source.table.as_str())
+            && !has_fallback(doc, &field.path)
+        {
+            errors.push(
+                ValidationError::new(
+                    &rules::NULLABLE_SOURCE,
+                    format!(
+                        "フィールド {} は {} ですが、カラム {}.{} は not null ではありません（COALESCE の fallback がありません）",
+                        field.path, reason, source.table, source.column
+                    ),
+                )
+                .at(format!("{}/source", field.pointer)),
+            );
+        }
+    }
+}
+
+/// U056: timezone を指定したフィールドの source が日時（日付と時刻）のカラムか
+fn validate_timezone_columns(
+    model: &Model,
+    tables: &HashMap<&str, &DbmlTable>,
+    errors: &mut Vec<ValidationError>,
+) {
+    for field in &model.fields {
+        let (Some(_), Some(source)) = (&field.timezone, &field.source) else {
+            continue;
+        };
+        let Some(column) = tables
+            .get(source.table.as_str())
+            .and_then(|table| table.column(&source.column))
+        else {
+            continue;
+        };
+        let base = column
+            .data_type
+            .split('(')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+        if !(base.starts_with("timestamp") || base == "datetime") {
+            errors.push(
+                ValidationError::new(
+                    &rules::FIELD_TIMEZONE,
+                    format!(
+                        "フィールド {} の timezone は日時のカラムにだけ指定できます（{}.{} は {}）",
+                        field.path, source.table, source.column, column.data_type
+                    ),
+                )
+                .at(format!("{}/timezone", field.pointer)),
+            );
+        }
+    }
+}
+
+/// U034: authorization.row_filters の条件が参照するカラムが DBML に存在するか
+fn validate_row_filter_columns(
+    doc: &UsmlDocument,
+    model: &Model,
+    dbml_columns: &HashMap<&str, HashSet<&str>>,
+    errors: &mut Vec<ValidationError>,
+) {
+    let Some(authorization) = &doc.usecase.authorization else {
+        return;
+    };
+    for (index, condition) in authorization.row_filters.iter().enumerate() {
+        for (qualifier, column) in table_refs(condition) {
+            let table = model.resolve_table(&qualifier);
+            if let Some(columns) = dbml_columns.get(table)
+                && !columns.contains(column.as_str())
+            {
+                errors.push(
+                    ValidationError::new(
+                        &rules::AUTHORIZATION_ROW_FILTER,
+                        format!(
+                            "authorization.row_filters の条件 '{}' のカラム {} がテーブル {} に存在しません",
+                            condition, column, table
+                        ),
+                    )
+                    .at(format!("/usecase/authorization/row_filters/{}", index)),
+                );
+            }
+        }
+    }
+}
+
+/// U046: filters の condition・cursor_field・cursor_tiebreaker・default_column・allowed_columns が参照するカラムが DBML に存在するか
+///
+/// テーブル名の無いカラム名（`cursor_field: created_at` など）は対象にしない
+fn validate_filter_columns(
+    doc: &UsmlDocument,
+    model: &Model,
+    dbml_columns: &HashMap<&str, HashSet<&str>>,
+    errors: &mut Vec<ValidationError>,
+) {
+    for (index, filter) in doc.usecase.filters.iter().enumerate() {
+        let mut refs: Vec<(&str, String, String, String)> = Vec::new();
+        if let Some(condition) = &filter.condition {
+            refs.extend(
+                table_refs(condition)
+                    .into_iter()
+                    .map(|(qualifier, column)| {
+                        ("condition", "condition".to_string(), qualifier, column)
+                    }),
+            );
+        }
+        let columns = [
+            ("cursor_field", filter.cursor_field.as_slice()),
+            ("cursor_tiebreaker", filter.cursor_tiebreaker.as_slice()),
+            ("default_column", filter.default_column.as_slice()),
+            (
+                "allowed_columns",
+                filter.allowed_columns.as_deref().unwrap_or_default(),
+            ),
+        ];
+        for (key, values) in columns {
+            for (i, value) in values.iter().enumerate() {
+                if let Some((qualifier, column)) = value.split_once('.') {
+                    // allowed_columns だけが配列
+                    let path = match key {
+                        "allowed_columns" => format!("{}/{}", key, i),
+                        _ => key.to_string(),
+                    };
+                    refs.push((key, path, qualifier.to_string(), column.to_string()));
+                }
+            }
+        }
+        for (key, path, qualifier, column) in refs {
+            let table = model.resolve_table(&qualifier);
+            if let Some(columns) = dbml_columns.get(table)
+                && !columns.contains(column.as_str())
+            {
+                errors.push(
+                    ValidationError::new(
+                        &rules::FILTER_COLUMN,
+                        format!(
+                            "filters[].{}（param '{}'）のカラム {} がテーブル {} に存在しません",
+                            key, filter.param, column, table
+                        ),
+                    )
+                    .at(format!("/usecase/filters/{}/{}", index, path)),
+                );
+            }
+        }
+    }
+}
+
+/// U050: カーソル方式のページネーションの順序が一意に決まるか（警告）
+///
+/// テーブル名の無いカラム名は、結合で加わらないテーブル（FROM に置くテーブル）のカラムとして探す
+fn validate_cursor_order(
+    doc: &UsmlDocument,
+    model: &Model,
+    tables: &HashMap<&str, &DbmlTable>,
+    errors: &mut Vec<ValidationError>,
+) {
+    let Some((index, page)) = doc
+        .usecase
+        .filters
+        .iter()
+        .enumerate()
+        .find(|(_, f)| f.maps_to == "PAGINATION" && f.strategy.as_deref() == Some("cursor"))
+    else {
+        return;
+    };
+    let root = model
+        .tables
+        .iter()
+        .find(|t| !model.joins.iter().any(|j| j.table == t.name))
+        .map(|t| t.name.as_str());
+    let column = |reference: &str| {
+        let (table, column) = match reference.split_once('.') {
+            Some((qualifier, column)) => (model.resolve_table(qualifier), column),
+            None => (root?, reference),
+        };
+        tables.get(table)?.column(column)
+    };
+    let unique = |column: &DbmlColumn| column.primary_key || column.unique;
+    let cursor_field = page.cursor_field.as_deref().unwrap_or("id");
+    let (key, message) = match (column(cursor_field), &page.cursor_tiebreaker) {
+        (Some(cursor), None) if !unique(cursor) => (
+            "cursor_field",
+            format!(
+                "cursor_field '{}' は一意でないため、同じ値の行の順序が決まりません。cursor_tiebreaker に一意なカラムを指定してください",
+                cursor_field
+            ),
+        ),
+        (_, Some(tiebreaker)) => match column(tiebreaker) {
+            Some(found) if !unique(found) => (
+                "cursor_tiebreaker",
+                format!(
+                    "cursor_tiebreaker '{}' が一意ではありません。主キーなど一意なカラムを指定してください",
+                    tiebreaker
+                ),
+            ),
+            _ => return,
+        },
+        _ => return,
+    };
+    errors.push(
+        ValidationError::new(
+            &rules::CURSOR_ORDER,
+            format!("PAGINATION（param '{}'）: {}", page.param, message),
+        )
+        .at(format!("/usecase/filters/{}/{}", index, key)),
+    );
+}
+
+/// `テーブル.カラム` の参照と、そのカラムの JSON Schema 型
+fn column_json_type<'a>(
+    source: &'a str,
+    tables: &HashMap<&str, &DbmlTable>,
+) -> Option<(&'a str, &'static str)> {
+    let (table, column) = source.split_once('.')?;
+    let json_type = tables.get(table)?.column(column)?.json_type()?;
+    Some((source, json_type))
+}
+
+/// `actual` 型の値を `expected` 型のフィールドに入れられるか（integer は number に入る）
+fn json_type_compatible(expected: &str, actual: &str) -> bool {
+    expected == actual || (expected == "number" && actual == "integer")
+}
+
+/// U056: timezone の `:name` が OpenAPI のパラメータにあるか
+fn validate_timezone_params(
+    model: &Model,
+    openapi: &OpenapiResponse,
+    errors: &mut Vec<ValidationError>,
+) {
+    for field in &model.fields {
+        if let Some(param) = field.timezone.as_deref().and_then(|t| t.strip_prefix(':'))
+            && !openapi.parameters.iter().any(|p| p == param)
+        {
+            errors.push(
+                ValidationError::new(
+                    &rules::FIELD_TIMEZONE,
+                    format!(
+                        "フィールド '{}' の timezone のパラメータ {} がOpenAPIパラメータに存在しません",
+                        field.path, param
+                    ),
+                )
+                .at(format!("{}/timezone", field.pointer)),
+            );
+        }
+    }
+}
+
+/// U057: CONVERT の target の OpenAPI のフィールドが、小数点以下 scale 桁の数値を表せるか
+fn validate_convert_output_types(
+    transforms: &[crate::ast::Transform],
+    openapi: &OpenapiResponse,
+    errors: &mut Vec<ValidationError>,
+) {
+    for (index, transform) in transforms.iter().enumerate() {
+        if !transform.r#type.eq_ignore_ascii_case("CONVERT") {
+            continue;
+        }
+        let (Some(scale), Some(property)) = (transform.scale, openapi.property(&transform.target))
+        else {
+            continue;
+        };
+        let Some(schema_type) = property.schema_type.as_deref() else {
+            continue;
+        };
+        let format = property.format.as_deref();
+        let fits = match schema_type {
+            "number" => true,
+            "integer" => scale == 0,
+            "string" => format == Some("decimal"),
+            _ => false,
+        };
+        if !fits {
+            errors.push(
+                ValidationError::new(
+                    &rules::TRANSFORM_CONVERT,
+                    format!(
+                        "transform {} の CONVERT は小数点以下 {} 桁の数値を返しますが、OpenAPI のフィールドは {} です",
+                        transform.target,
+                        scale,
+                        match format {
+                            Some(format) => format!("{}（{}）", schema_type, format),
+                            None => schema_type.to_string(),
+                        }
+                    ),
+                )
+                .at(format!("/usecase/transforms/{}/scale", index)),
+            );
+        }
+    }
+}
+
+/// U058: LOCALIZE の locale.param が OpenAPI の文字列のパラメータか
+fn validate_locale_params(
+    transforms: &[crate::ast::Transform],
+    openapi: &OpenapiResponse,
+    errors: &mut Vec<ValidationError>,
+) {
+    for (index, transform) in transforms.iter().enumerate() {
+        if !transform.r#type.eq_ignore_ascii_case("LOCALIZE") {
+            continue;
+        }
+        let Some(locale) = &transform.locale else {
+            continue;
+        };
+        if !openapi.parameters.contains(&locale.param) {
+            errors.push(
+                ValidationError::new(
+                    &rules::TRANSFORM_LOCALIZE,
+                    format!(
+                        "transform {} の LOCALIZE の locale.param {} がOpenAPIパラメータに存在しません",
+                        transform.target, locale.param
+                    ),
+                )
+                .at(format!("/usecase/transforms/{}/locale/param", index)),
+            );
+        } else if let Some(schema_type) = openapi
+            .parameter(&locale.param)
+            .and_then(|p| p.schema_type.as_deref())
+            .filter(|t| *t != "string")
+        {
+            errors.push(
+                ValidationError::new(
+                    &rules::TRANSFORM_LOCALIZE,
+                    format!(
+                        "transform {} の LOCALIZE の locale.param {} は {} 型です（言語は string のパラメータで受け取ります）",
+                        transform.target, locale.param, schema_type
+                    ),
+                )
+                .at(format!("/usecase/transforms/{}/locale/param", index)),
+            );
+        }
+    }
+}
+
+/// Rule 10: transform の condition.param がOpenAPIパラメータに存在するか
+fn validate_transform_params(
+    transforms: &[crate::ast::Transform],
+    openapi: &OpenapiResponse,
+    errors: &mut Vec<ValidationError>,
+) {
+    let parameters: HashSet<&str> = openapi.parameters.iter().map(String::as_str).collect();
+    for (index, transform) in transforms.iter().enumerate() {
+        if let Some(conditions) = &transform.condition {
+            for (i, cond) in conditions.iter().enumerate() {
+                if let Some(param) = &cond.param
+                    && !parameters.contains(param.as_str())
+                {
+                    errors.push(
+                        ValidationError::new(
+                            &rules::OPENAPI_TRANSFORM_PARAM,
+                            format!(
+                                "transform {} の condition.param {} がOpenAPIパラメータに存在しません",
+                                transform.target, param
+                            ),
+                        )
+                        .at(format!("/usecase/transforms/{}/condition/{}/param", index, i)),
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+    use crate::resolver::{
+        DbmlColumn, DbmlTable, OpenapiParameter, OpenapiProperty, OpenapiResponse,
+    };
+    use crate::testing::{self, RuleTest};
+
+    #[test]
+    fn test_valid_document_no_errors() {
+        let yaml = r#"
+version: "0.1"
+import:
+  openapi: ./api.yaml#paths["/users"].get.responses["200"]
+  dbml:
+    - ./schema.dbml#tables["users"]
+    - ./schema.dbml#tables["profiles"]
+usecase:
+  name: ユーザー一覧取得
+  response_mapping:
+    - field: id
+      source: users.id
+    - field: avatar_url
+      source: profiles.avatar_url
+      join:
+        table: profiles
+        on: users.id = profiles.user_id
+  transforms:
+    - target: avatar_url
+      type: COALESCE
+      sources:
+        - profiles.avatar_url
+      fallback: "/default.png"
+"#;
+        let doc = parser::parse(yaml).unwrap();
+        let errors = validate(&doc);
+        let hard_errors: Vec<_> = errors
+            .iter()
+            .filter(|e| matches!(e, ValidationError::Rule(..)))
+            .collect();
+        assert!(
+            hard_errors.is_empty(),
+            "エラーがありました: {:?}",
+            hard_errors
+        );
+    }
+
+    #[test]
+    fn test_missing_import_table() {
+        RuleTest::new("import.dbml").expect(
+            r#"
+import:
+  dbml:
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: avatar_url
+      source: profiles.avatar_url
+      join:
+        table: profiles
+        on: users.id = profiles.user_id
+"#,
+            &["profiles"],
+        );
+    }
+
+    #[test]
+    fn test_duplicate_join_without_alias() {
+        RuleTest::new("join.alias").expect(
+            r#"
+import:
+  dbml:
+    - ./schema.dbml#tables["posts"]
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: author_name
+      source: users.name
+      join:
+        table: users
+        on: posts.user_id = users.id
+    - field: editor_name
+      source: users.name
+      join:
+        table: users
+        on: posts.editor_id = users.id
+"#,
+            &["users"],
+        );
+    }
+
+    #[test]
+    fn test_alias_source_is_resolved_to_imported_table() {
+        let yaml = r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["posts"]
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: author_name
+      source: post_author.name
+      join:
+        table: users
+        alias: post_author
+        on: posts.user_id = post_author.id
+    - field: editor_name
+      source: editor.name
+      join:
+        table: users
+        alias: editor
+        on: post_author.editor_id = editor.id
+"#;
+        let doc = parser::parse(yaml).unwrap();
+        let errors = validate(&doc);
+        assert!(
+            !errors.iter().any(|e| matches!(
+                e,
+                ValidationError::Rule(rule, ..) if rule.name == "import.dbml" || rule.name == "join.on"
+            )),
+            "エイリアスが未 import として報告されました: {:?}",
+            errors
+        );
+    }
+
+    #[test]
+    fn test_transform_target_not_in_mapping() {
+        let yaml = r#"
+version: "0.1"
+import:
+  openapi: ./api.yaml#paths["/users"].get.responses["200"]
+  dbml:
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: id
+      source: users.id
+  transforms:
+    - target: nonexistent_field
+      type: COALESCE
+      sources:
+        - users.name
+"#;
+        let doc = parser::parse(yaml).unwrap();
+        let errors = validate(&doc);
+        assert!(errors.iter().any(|e| {
+            matches!(e, ValidationError::Rule(rule, ..) if rule.name == "transforms.target")
+        }));
+    }
+
+    // --- 新規テスト: Rule 6 ---
+    #[test]
+    fn test_rule6_join_on_references_non_imported_table() {
+        let yaml = r#"
+version: "0.1"
+import:
+  openapi: ./api.yaml#paths["/posts"].get.responses["200"]
+  dbml:
+    - ./schema.dbml#tables["posts"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: author_name
+      source: users.name
+      join:
+        table: users
+        on: posts.user_id = users.id
+"#;
+        let doc = parser::parse(yaml).unwrap();
+        let errors = validate(&doc);
+        // users テーブルが import にないため Rule 6 (join.on) と Rule 2 (import.dbml) が発火
+        assert!(errors
+            .iter()
+            .any(|e| matches!(e, ValidationError::Rule(rule, ..) if rule.name == "join.on" || rule.name == "import.dbml")));
+    }
+
+    // --- 新規テスト: Rule 8 ---
+    #[test]
+    fn test_rule8_aggregate_without_group_by_warns() {
+        let yaml = r#"
+version: "0.1"
+import:
+  openapi: ./api.yaml#paths["/posts"].get.responses["200"]
+  dbml:
+    - ./schema.dbml#tables["posts"]
+    - ./schema.dbml#tables["likes"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: like_count
+      source: likes.id
+      join:
+        table: likes
+        on: posts.id = likes.post_id
+      aggregate:
+        type: COUNT
+"#;
+        let doc = parser::parse(yaml).unwrap();
+        let errors = validate(&doc);
+        assert!(errors.iter().any(
+            |e| matches!(e, ValidationError::Warning(rule, ..) if rule.name == "aggregate.group_by")
+        ));
+    }
+
+    #[test]
+    fn test_implicit_group_by_info() {
+        let yaml = r#"
+usecase:
+  name: テスト
+  response_mapping:
+    - field: id
+      source: posts.id
+    - field: title
+      source: posts.title
+    - field: like_count
+      source: likes.id
+      join:
+        table: likes
+        on: posts.id = likes.post_id
+      aggregate:
+        type: COUNT
+"#;
+        RuleTest::new("U060").expect(
+            yaml,
+            &["フィールド 'like_count' の集約に暗黙の group_by を適用します: posts.id, posts.title"],
+        );
+        RuleTest::new("U060").expect(
+            &yaml.replace("type: COUNT", "type: COUNT\n        group_by: posts.id"),
+            &[],
+        );
+
+        // U005 の警告には group_by の例のヒントが付く
+        let doc = testing::document(yaml);
+        let diagnostics = Validator::new().validate(&doc);
+        let warning = diagnostics
+            .iter()
+            .find(|d| d.code.as_deref() == Some("U005"))
+            .expect("U005 の警告");
+        assert_eq!(
+            warning.hints,
+            vec!["集約の単位を group_by に書いてください（例: group_by: posts.id）"]
+        );
+        let info = diagnostics
+            .iter()
+            .find(|d| d.code.as_deref() == Some("U060"))
+            .unwrap();
+        assert_eq!(info.severity, Severity::Info);
+        assert!(!info.severity.is_problem());
+
+        // 最初のカラムではなくルートテーブルの主キーでまとめる
+        let yaml = r#"
+usecase:
+  name: テスト
+  response_mapping:
+    - field: name
+      source: users.name
+    - field: like_count
+      source: likes.id
+      join:
+        table: likes
+        on: users.id = likes.user_id
+      aggregate:
+        type: COUNT
+"#;
+        RuleTest::new("U060").expect(
+            yaml,
+            &["フィールド 'like_count' の集約に暗黙の group_by を適用します: users.id, users.name"],
+        );
+        let diagnostics = Validator::new().validate(&testing::document(yaml));
+        let warning = diagnostics
+            .iter()
+            .find(|d| d.code.as_deref() == Some("U005"))
+            .unwrap();
+        assert_eq!(
+            warning.hints,
+            vec!["集約の単位を group_by に書いてください（例: group_by: users.id）"]
+        );
+    }
+
+    #[test]
+    fn test_reference_syntax_and_pagination() {
+        let yaml = r#"
+usecase:
+  name: テスト
+  response_mapping:
+    - field: name
+      source: users.
+    - field: posts
+      type: array
+      join:
+        table: posts
+        on: ""
+      fields:
+        - field: title
+          source: posts.title
+  filters:
+    - param: cursor
+      maps_to: PAGINATION
+      strategy: cursor
+      page_size: 0
+"#;
+        RuleTest::new("U061").expect(
+            yaml,
+            &[
+                "フィールド 'name' の source 'users.' は テーブル.カラム の形式ではありません",
+                "フィールド 'posts' の join.on（テーブル 'posts'）が空です",
+            ],
+        );
+        RuleTest::new("U062").expect(
+            yaml,
+            &[
+                "PAGINATION フィルタ 'cursor' の page_size が 0 です（1 以上）",
+                "PAGINATION フィルタ 'cursor' は strategy: cursor ですが cursor_field がありません",
+            ],
+        );
+        let fixed = yaml
+            .replace("source: users.\n", "source: users.name\n")
+            .replace("on: \"\"", "on: users.id = posts.user_id")
+            .replace(
+                "page_size: 0",
+                "page_size: 20\n      cursor_field: created_at",
+            );
+        RuleTest::new("U061").expect(&fixed, &[]);
+        RuleTest::new("U062").expect(&fixed, &[]);
+    }
+
+    #[test]
+    fn test_aggregate_without_source() {
+        let yaml = r#"
+usecase:
+  name: テスト
+  response_mapping:
+    - field: id
+      source: users.id
+    - field: post_count
+      aggregate:
+        type: COUNT
+        group_by: users.id
+    - field: total
+      aggregate:
+        type: sum
+        group_by: users.id
+"#;
+        RuleTest::new("U063").expect(
+            yaml,
+            &[
+                "フィールド 'post_count' の aggregate COUNT に集約するカラムの source がありません",
+                "フィールド 'total' の aggregate SUM に集約するカラムの source がありません",
+            ],
+        );
+        let fixed = yaml.replace(
+            "      aggregate:\n",
+            "      source: posts.id\n      aggregate:\n",
+        );
+        RuleTest::new("U063").expect(&fixed, &[]);
+    }
+
+    // --- 新規テスト: Rule 9 ---
+    #[test]
+    fn test_rule9_undeclared_param_in_condition() {
+        let yaml = r#"
+version: "0.1"
+import:
+  openapi: ./api.yaml#paths["/users"].get.responses["200"]
+  dbml:
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: id
+      source: users.id
+  filters:
+    - param: status
+      maps_to: WHERE
+      condition: users.status = :status AND users.role = :role
+"#;
+        let doc = parser::parse(yaml).unwrap();
+        let errors = validate(&doc);
+        // :role は filters[].param に宣言されていないため Rule 9 が発火
+        assert!(errors.iter().any(
+            |e| matches!(e, ValidationError::Rule(rule, ..) if rule.name == "filters.condition")
+        ));
+    }
+
+    // --- 新規テスト: Rule 11 ---
+    #[test]
+    fn test_rule11_source_table_mismatch() {
+        let yaml = r#"
+version: "0.1"
+import:
+  openapi: ./api.yaml#paths["/posts"].get.responses["200"]
+  dbml:
+    - ./schema.dbml#tables["posts"]
+    - ./schema.dbml#tables["comments"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: comments
+      type: array
+      source_table: wrong_table
+      join:
+        table: comments
+        on: posts.id = comments.post_id
+      fields:
+        - field: id
+          source: comments.id
+"#;
+        let doc = parser::parse(yaml).unwrap();
+        let errors = validate(&doc);
+        assert!(
+            errors.iter().any(
+                |e| matches!(e, ValidationError::Rule(rule, ..) if rule.name == "source_table")
+            )
+        );
+    }
+
+    // --- 新規テスト: Rule 12 ---
+    #[test]
+    fn test_rule12_default_column_not_in_allowed() {
+        let yaml = r#"
+version: "0.1"
+import:
+  openapi: ./api.yaml#paths["/users"].get.responses["200"]
+  dbml:
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: id
+      source: users.id
+  filters:
+    - param: sort
+      maps_to: ORDER_BY
+      default_column: users.secret_field
+      allowed_columns:
+        - users.created_at
+        - users.name
+"#;
+        let doc = parser::parse(yaml).unwrap();
+        let errors = validate(&doc);
+        assert!(errors.iter().any(
+            |e| matches!(e, ValidationError::Rule(rule, ..) if rule.name == "filters.allowed_columns")
+        ));
+    }
+
+    #[test]
+    fn test_duplicate_and_conflicting_filters() {
+        let yaml = r#"
+version: "0.1"
+import:
+  openapi: ./api.yaml#paths["/users"].get.responses["200"]
+  dbml:
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: id
+      source: users.id
+  filters:
+    - param: status
+      maps_to: WHERE
+      condition: users.status = :status
+    - param: status
+      maps_to: WHERE
+      condition: users.status <> :status
+    - param: page
+      maps_to: PAGINATION
+      strategy: offset
+      limit_param: size
+    - param: size
+      maps_to: WHERE
+      condition: users.size = :size
+    - param: sort
+      maps_to: ORDER_BY
+    - param: order
+      maps_to: ORDER_BY
+"#;
+        RuleTest::new("U044").expect(
+            yaml,
+            &[
+                "filters[].param 'status' が 2 回宣言されています",
+                "filters[].param 'size' が PAGINATION と WHERE の両方に使われています",
+            ],
+        );
+        RuleTest::new("U045").expect(
+            yaml,
+            &["maps_to: ORDER_BY のフィルタが 2 個あります（sort, order）。ソート順が一意に決まりません"],
+        );
+    }
+
+    #[test]
+    fn test_filter_required_and_default() {
+        let yaml = r#"
+version: "0.1"
+import:
+  openapi: ./api.yaml#paths["/users"].get.responses["200"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: id
+      source: users.id
+  filters:
+    - param: status
+      maps_to: WHERE
+      condition: users.status = :status
+      required: true
+      default: active
+    - param: role
+      maps_to: WHERE
+      condition: users.role = :role
+      default: ":status"
+    - param: sort
+      maps_to: ORDER_BY
+      default: name
+    - param: min_age
+      maps_to: WHERE
+      condition: users.age >= :min_age
+      default: 18
+    - param: tenant
+      maps_to: WHERE
+      condition: users.tenant_id = :tenant
+      required: false
+"#;
+        RuleTest::new("U048").expect(
+            yaml,
+            &[
+                "（param 'status'）: required: true のフィルタには使われません",
+                "（param 'role'）: :パラメータ の参照は書けません",
+                "（param 'sort'）: maps_to: WHERE のフィルタにだけ書けます",
+            ],
+        );
+
+        let parameter = |name: &str, required: bool, default: Option<Literal>| OpenapiParameter {
+            name: name.to_string(),
+            required,
+            schema_type: Some("integer".to_string()),
+            default,
+            ..OpenapiParameter::default()
+        };
+        let openapi = |parameter_details| OpenapiResponse {
+            parameter_details,
+            ..OpenapiResponse::default()
+        };
+        RuleTest::new("U049")
+            .with_openapi(openapi(vec![
+                parameter("min_age", false, Some(Literal::Number(18.0))),
+                parameter("tenant", true, None),
+            ]))
+            .expect(
+                yaml,
+                &["filters[]（param 'tenant'）: required: false ですが、OpenAPI では required: true です"],
+            );
+        RuleTest::new("U049")
+            .with_openapi(openapi(vec![parameter("min_age", false, None)]))
+            .expect(
+                yaml,
+                &["default 18 がありますが、OpenAPI の schema に default がありません"],
+            );
+
+        // required を省略したフィルタは任意として生成されるため、必須のパラメータでは報告する
+        RuleTest::new("U049")
+            .with_openapi(openapi(vec![parameter("status", true, None)]))
+            .expect(
+                &yaml.replace("      required: true\n      default: active\n", ""),
+                &["filters[]（param 'status'）: OpenAPI では required: true ですが、required がありません"],
+            );
+    }
+
+    // --- 新規テスト: Rule 11 with join_chain ---
+    #[test]
+    fn test_rule11_source_table_with_join_chain() {
+        let yaml = r#"
+version: "0.1"
+import:
+  openapi: ./api.yaml#paths["/posts"].get.responses["200"]
+  dbml:
+    - ./schema.dbml#tables["posts"]
+    - ./schema.dbml#tables["post_tags"]
+    - ./schema.dbml#tables["tags"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: tags
+      type: array
+      source_table: tags
+      join:
+        table: post_tags
+        on: posts.id = post_tags.post_id
+      join_chain:
+        - table: tags
+          on: post_tags.tag_id = tags.id
+      fields:
+        - field: id
+          source: tags.id
+"#;
+        let doc = parser::parse(yaml).unwrap();
+        let errors = validate(&doc);
+        // source_table: tags と join_chain の最後のテーブル tags が一致するのでエラーなし
+        let hard_errors: Vec<_> = errors
+            .iter()
+            .filter(|e| matches!(e, ValidationError::Rule(..)))
+            .collect();
+        assert!(
+            hard_errors.is_empty(),
+            "エラーがありました: {:?}",
+            hard_errors
+        );
+    }
+
+    #[test]
+    fn test_validate_openapi_fields_mismatch() {
+        // OpenAPI に id, name, email があるが response_mapping に nonexistent を指定
+        let openapi = OpenapiResponse {
+            fields: vec!["id".to_string(), "name".to_string(), "email".to_string()],
+            parameters: vec!["status".to_string()],
+            ..OpenapiResponse::default()
+        };
+        let yaml = r#"
+version: "0.1"
+import:
+  openapi: ./api.yaml#paths["/users"].get.responses["200"]
+  dbml:
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: nonexistent
+      source: users.id
+"#;
+        let doc = parser::parse(yaml).unwrap();
+        let mappings = &doc.usecase.response_mapping;
+        let mut errors = Vec::new();
+        validate_openapi_fields(mappings, &openapi, &mut errors);
+        assert!(errors.iter().any(
+            |e| matches!(e, ValidationError::Rule(rule, ..) if rule.name == "response_mapping.field")
+        ));
+    }
+
+    #[test]
+    fn test_openapi_field_differing_only_in_casing() {
+        let openapi = OpenapiResponse {
+            fields: vec!["id".to_string(), "avatar_url".to_string()],
+            ..OpenapiResponse::default()
+        };
+        let yaml = r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: avatarUrl
+      source: users.avatar_url
+"#;
+        let doc = parser::parse(yaml).unwrap();
+        let errors = validate_against_openapi(&doc, &Model::build(&doc), &openapi);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].rule(), &rules::OPENAPI_FIELD_CASING);
+        assert!(errors[0].to_string().contains("avatar_url"));
+    }
+
+    #[test]
+    fn test_validate_dbml_columns_missing() {
+        let tables = vec![DbmlTable {
+            name: "users".to_string(),
+            columns: vec!["id".to_string(), "name".to_string(), "email".to_string()],
+            column_details: Vec::new(),
+            location: None,
+        }];
+        let yaml = r#"
+version: "0.1"
+import:
+  openapi: ./api.yaml#paths["/users"].get.responses["200"]
+  dbml:
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: phone
+      source: users.phone
+"#;
+        let doc = parser::parse(yaml).unwrap();
+        let errors = validate_against_dbml(&doc, &Model::build(&doc), &tables, None);
+        assert!(errors.iter().any(
+            |e| matches!(e, ValidationError::Rule(rule, ..) if rule.name == "response_mapping.source")
+        ));
+    }
+
+    #[test]
+    fn test_transform_sources_must_resolve() {
+        let yaml = r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["users"]
+    - ./schema.dbml#tables["profiles"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: display_name
+      source: users.name
+    - field: nickname
+      source: p.nickname
+      join:
+        table: profiles
+        alias: p
+        on: users.id = p.user_id
+  transforms:
+    - target: display_name
+      type: COALESCE
+      sources:
+        - p.display_name
+        - nickname
+        - accounts.name
+        - nick_name
+"#;
+        let doc = parser::parse(yaml).unwrap();
+        let errors: Vec<_> = validate(&doc)
+            .into_iter()
+            .filter(|e| e.rule().name == "transforms.source")
+            .collect();
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].to_string().contains("accounts"));
+        assert!(errors[1].to_string().contains("nick_name"));
+    }
+
+    #[test]
+    fn test_transform_source_columns_missing_in_dbml() {
+        let tables = vec![DbmlTable {
+            name: "users".to_string(),
+            columns: vec!["id".to_string(), "name".to_string()],
+            column_details: Vec::new(),
+            location: None,
+        }];
+        let yaml = r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: display_name
+      source: users.name
+  transforms:
+    - target: display_name
+      type: COALESCE
+      sources:
+        - users.nickname
+        - users.name
+"#;
+        let doc = parser::parse(yaml).unwrap();
+        let errors = validate_against_dbml(&doc, &Model::build(&doc), &tables, None);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].rule().code, "U020");
+    }
+
+    #[test]
+    fn test_conditional_source_branches_checked_against_dbml() {
+        let column = |name: &str, data_type: &str| DbmlColumn {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            ..DbmlColumn::default()
+        };
+        let tables = vec![DbmlTable {
+            name: "users".to_string(),
+            columns: vec!["id".to_string(), "name".to_string(), "score".to_string()],
+            column_details: vec![
+                column("id", "integer"),
+                column("name", "varchar(255)"),
+                column("score", "decimal(5,2)"),
+            ],
+            location: None,
+        }];
+        let yaml = r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: label
+      source: users.name
+    - field: rank
+      source: users.score
+  transforms:
+    - target: label
+      type: CONDITIONAL_SOURCE
+      condition:
+        - field: rank
+          operator: ">"
+          value: "0"
+      then_source: users.nickname
+      else_source: users.name
+    - target: rank
+      type: CONDITIONAL_SOURCE
+      condition:
+        - field: label
+          operator: "="
+          value: "admin"
+      then_source: users.id
+      else_source: users.name
+"#;
+        let doc = parser::parse(yaml).unwrap();
+        let codes = |errors: &[ValidationError]| -> Vec<&'static str> {
+            errors.iter().map(|e| e.rule().code.as_ref()).collect()
+        };
+
+        let errors = validate_against_dbml(&doc, &Model::build(&doc), &tables, None);
+        assert_eq!(codes(&errors), vec!["U020", "U021"]);
+        assert!(errors[1].to_string().contains("users.id（integer）"));
+
+        let openapi = OpenapiResponse {
+            properties: vec![OpenapiProperty {
+                name: "rank".to_string(),
+                schema_type: Some("number".to_string()),
+                ..OpenapiProperty::default()
+            }],
+            ..OpenapiResponse::default()
+        };
+        let errors = validate_against_dbml(&doc, &Model::build(&doc), &tables, Some(&openapi));
+        assert_eq!(codes(&errors), vec!["U020", "U021"]);
+        assert!(errors[1].to_string().contains("users.name は string 型"));
+    }
+
+    #[test]
+    fn test_condition_comparisons_checked_against_column_types() {
+        let column = |name: &str, data_type: &str| DbmlColumn {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            ..DbmlColumn::default()
+        };
+        let tables = vec![DbmlTable {
+            name: "users".to_string(),
+            columns: vec![
+                "name".to_string(),
+                "age".to_string(),
+                "created_at".to_string(),
+            ],
+            column_details: vec![
+                column("name", "varchar(255)"),
+                column("age", "int"),
+                column("created_at", "timestamp"),
+            ],
+            location: None,
+        }];
+        let yaml = r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: name
+      source: u.name
+  filters:
+    - param: min_name
+      maps_to: WHERE
+      condition: u.name >= :min_name AND u.name::int>0
+    - param: age
+      maps_to: WHERE
+      condition: "u.age LIKE :age OR u.created_at < now()"
+    - param: keyword
+      maps_to: WHERE
+      condition: "u.name ILIKE :keyword AND u.age = :min_age"
+"#
+        .replace("u.", "users.");
+        RuleTest::new("U047").with_dbml(tables.clone()).expect(
+            &yaml,
+            &[
+                "文字列のカラム users.name（varchar(255)）を >= で比較",
+                "数値のカラム users.age（int）に LIKE",
+            ],
+        );
+
+        let parameter = |name: &str, schema_type: &str| OpenapiParameter {
+            name: name.to_string(),
+            schema_type: Some(schema_type.to_string()),
+            ..OpenapiParameter::default()
+        };
+        let openapi = OpenapiResponse {
+            parameter_details: vec![
+                parameter("min_name", "string"),
+                parameter("keyword", "string"),
+                parameter("min_age", "string"),
+            ],
+            ..OpenapiResponse::default()
+        };
+        RuleTest::new("U047")
+            .with_dbml(tables)
+            .with_openapi(openapi)
+            .expect(
+                &yaml,
+                &[
+                    "文字列のカラム users.name（varchar(255)）を >= で比較",
+                    "数値のカラム users.age（int）に LIKE",
+                    "パラメータ :min_age（OpenAPI の型 string）をカラム users.age（int）と比較",
+                ],
+            );
+    }
+
+    #[test]
+    fn test_mask_pattern_and_source_type() {
+        let tables = vec![DbmlTable {
+            name: "users".to_string(),
+            columns: vec!["id".to_string(), "phone".to_string()],
+            column_details: vec![
+                DbmlColumn {
+                    name: "id".to_string(),
+                    data_type: "integer".to_string(),
+                    ..DbmlColumn::default()
+                },
+                DbmlColumn {
+                    name: "phone".to_string(),
+                    data_type: "varchar(20)".to_string(),
+                    ..DbmlColumn::default()
+                },
+            ],
+            location: None,
+        }];
+        let yaml = r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: id
+      source: users.id
+    - field: phone
+      source: users.phone
+  transforms:
+    - target: phone
+      type: MASK
+      source: users.phone
+      mask_pattern: "***-$4-****"
+    - target: id
+      type: MASK
+      source: users.id
+      mask_pattern: "**$2"
+"#;
+        RuleTest::new("U022").expect(yaml, &["'phone'"]);
+        RuleTest::new("U023").with_dbml(tables).expect(
+            yaml,
+            &["transform id の MASK の source users.id は integer 型です"],
+        );
+    }
+
+    #[test]
+    fn test_case_over_enum_column_without_else_warns() {
+        let tables = vec![DbmlTable {
+            name: "users".to_string(),
+            columns: vec!["status".to_string()],
+            column_details: vec![DbmlColumn {
+                name: "status".to_string(),
+                data_type: "user_status".to_string(),
+                enum_values: vec![
+                    "active".to_string(),
+                    "suspended".to_string(),
+                    "deleted".to_string(),
+                ],
+                ..DbmlColumn::default()
+            }],
+            location: None,
+        }];
+        let transforms = |else_value: &str| {
+            format!(
+                r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: status_label
+      source: users.status
+  transforms:
+    - target: status_label
+      type: CASE
+      source: users.status
+      when:
+        - value: active
+          then: アクティブ
+        - value: suspended
+          then: 停止中
+{}"#,
+                else_value
+            )
+        };
+
+        let doc = parser::parse(&transforms("")).unwrap();
+        let errors = validate_against_dbml(&doc, &Model::build(&doc), &tables, None);
+        assert_eq!(errors.len(), 1);
+        assert!(
+            matches!(&errors[0], ValidationError::Warning(rule, message, _)
+            if rule.code == "U024" && message.contains("deleted"))
+        );
+
+        let doc = parser::parse(&transforms("      else_value: 不明\n")).unwrap();
+        assert!(validate_against_dbml(&doc, &Model::build(&doc), &tables, None).is_empty());
+    }
+
+    #[test]
+    fn test_validate_transform_params_missing() {
+        let openapi = OpenapiResponse {
+            fields: vec!["id".to_string()],
+            parameters: vec!["status".to_string()],
+            ..OpenapiResponse::default()
+        };
+        let yaml = r#"
+version: "0.1"
+import:
+  openapi: ./api.yaml#paths["/users"].get.responses["200"]
+  dbml:
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: id
+      source: users.id
+  transforms:
+    - target: id
+      type: CONDITIONAL_SOURCE
+      condition:
+        - param: undeclared_param
+          operator: "="
+          value: "active"
+      then_source: users.id
+      else_source: users.name
+"#;
+        let doc = parser::parse(yaml).unwrap();
+        let mut errors = Vec::new();
+        validate_transform_params(&doc.usecase.transforms, &openapi, &mut errors);
+        assert!(errors.iter().any(
+            |e| matches!(e, ValidationError::Rule(rule, ..) if rule.name == "transforms.condition.param")
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "resolver-dbml")]
+    fn test_resolve_error_surfaces_location() {
+        let dir = std::env::temp_dir().join("usml_test_resolve_error_location");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("schema.dbml"),
+            "Table users {\n    id integer [pk]\n    name varchar [not null\n}\n",
+        )
+        .unwrap();
+        let yaml = r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: id
+      source: users.id
+"#;
+        let doc = parser::parse(yaml).unwrap();
+        let diagnostics = validate_with_resolve(&doc, dir.to_str().unwrap());
+        let diag = diagnostics
+            .iter()
+            .find(|d| d.rule == "import.dbml")
+            .expect("DBML 解決エラーの診断があるはず");
+        let location = diag.location.as_ref().expect("位置情報があるはず");
+        assert!(location.file.ends_with("schema.dbml"));
+        assert_eq!(location.line, Some(3));
+    }
+
+    #[test]
+    #[cfg(feature = "resolver-dbml")]
+    fn test_conflicting_dbml_tables_across_imports() {
+        let yaml = r#"
+version: "0.1"
+import:
+  dbml:
+    - ./core.dbml#tables["users"]
+    - ./billing.dbml#tables["invoices"]
+    - ./shared.dbml#tables["plans"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: id
+      source: users.id
+"#;
+        let plan_doc = parser::parse(yaml).unwrap();
+        let plan = plan_imports(&plan_doc, "specs");
+        let (ctx, errors) = build_context(
+            &plan,
+            None,
+            vec![
+                Ok("Table users {\n  id int\n  email text\n}\nTable plans {\n  id int\n}\n".to_string()),
+                Ok("Table invoices {\n  id int\n}\nTable users {\n  id bigint\n  nickname text\n}\n".to_string()),
+                Ok("Table plans {\n  id int\n}\n".to_string()),
+            ],
+        );
+        let users = ctx.dbml_tables.iter().find(|t| t.name == "users").unwrap();
+        assert_eq!(users.columns, vec!["id", "email"]);
+        assert_eq!(ctx.dbml_tables.len(), 3);
+        // 同じ定義の plans は報告しない
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code.as_deref(), Some("U041"));
+        assert_eq!(
+            errors[0].message,
+            "テーブル 'users' が 'specs/./core.dbml' と 'specs/./billing.dbml' で異なる定義になっています\
+（'specs/./core.dbml' のみ: email、'specs/./billing.dbml' のみ: nickname、型が異なる: id（int / bigint））。\
+'specs/./core.dbml' の定義を使います"
+        );
+        assert_eq!(
+            errors[0].location,
+            Some(Location::file("specs/./billing.dbml"))
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "resolver-openapi")]
+    fn test_import_limits() {
+        let yaml = r#"
+version: "0.1"
+import:
+  openapi: ./api.yaml#paths["/users"].get.responses["200"]
+  dbml:
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: id
+      source: users.id
+"#;
+        let api = r#"
+openapi: "3.0.0"
+info: { title: t, version: "1" }
+paths:
+  /users:
+    get:
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: object
+                properties:
+                  profile:
+                    type: object
+                    properties:
+                      address:
+                        type: object
+                        properties:
+                          city: { type: string }
+"#;
+        let doc = parser::parse(yaml).unwrap();
+        let plan = plan_imports(&doc, "specs");
+        let limits = Limits {
+            max_file_size: 64,
+            max_schema_depth: 2,
+            ..Limits::default()
+        };
+        let dbml =
+            "Table users {\n  id int\n  name varchar\n  email varchar\n  created_at timestamp\n}\n";
+        let (ctx, errors) = build_context_with_limits(
+            &plan,
+            Some(Ok(api.to_string())),
+            vec![limits.check_size("specs/./schema.dbml", dbml.to_string())],
+            &limits,
+        );
+        assert!(ctx.openapi.is_none());
+        let messages: Vec<(Option<&str>, &str)> = errors
+            .iter()
+            .map(|e| (e.code.as_deref(), e.message.as_str()))
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                (
+                    Some("U042"),
+                    "OpenAPI解決に失敗しました: スキーマの入れ子が 2 段を超えています 'specs/./api.yaml'"
+                ),
+                (
+                    Some("U042"),
+                    "DBML解決に失敗しました: ファイル 'specs/./schema.dbml' が大きすぎます（79 バイト。上限は 64 バイト）"
+                ),
+            ]
+        );
+
+        // 既定の上限では解決できる
+        let (ctx, errors) =
+            build_context(&plan, Some(Ok(api.to_string())), vec![Ok(dbml.to_string())]);
+        assert!(errors.is_empty());
+        assert!(
+            ctx.openapi
+                .unwrap()
+                .property("profile.address.city")
+                .is_some()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "resolver-dbml")]
+    fn test_environment_overlay() {
+        let yaml = r#"
+version: "0.1"
+import:
+  openapi: ./api.yaml#paths["/users"].get.responses["200"]
+  dbml:
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: id
+      source: users.id
+environments:
+  stg:
+    import:
+      openapi: ./api.stg.yaml#paths["/users"].get.responses["200"]
+      dbml:
+        - ./schema.stg.dbml#tables["stg_users"]
+      tables:
+        users: stg_users
+  qa:
+    import:
+      openapi: ./api.yaml#paths["/members"].get.responses["200"]
+      dbml:
+        - ./schema.dbml#tables["members"]
+    usecase:
+      limit: 10
+"#;
+        RuleTest::new("U059").expect(
+            yaml,
+            &[
+                "environments.qa の usecase は上書きできません（環境ごとに変えられるのは import だけです）",
+                "environments.qa の import.openapi は GET /members 200 を参照しています（import は GET /users 200）",
+                "environments.qa の import.dbml・import.tables で取り込むテーブル（members）が import（users）と異なります",
+            ],
+        );
+
+        // 適用した環境の DB 上のテーブルを usecase のテーブル名で照合する
+        let mut doc = parser::parse(yaml).unwrap();
+        doc.apply_environment("stg").unwrap();
+        assert!(validate(&doc).iter().all(|e| e.rule().code != "U001"));
+        let plan = plan_imports(&doc, "specs");
+        let dbml = "Table users {\n  id int\n}\nTable stg_users {\n  id int\n  name varchar\n}\n";
+        let (ctx, errors) = build_context(&plan, None, vec![Ok(dbml.to_string())]);
+        assert!(errors.is_empty());
+        let names: Vec<&str> = ctx.dbml_tables.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["users"]);
+        assert!(ctx.dbml_tables[0].column("name").is_some());
+    }
+
+    use std::borrow::Cow;
+
+    /// 配列を返すユースケースにページネーションを必須とする独自規則
+    struct RequirePagination;
+
+    const REQUIRE_PAGINATION: Rule = Rule {
+        code: Cow::Borrowed("ACME001"),
+        name: Cow::Borrowed("acme.pagination"),
+        category: rules::Category::Custom,
+        severity: crate::diagnostic::Severity::Error,
+        summary: Cow::Borrowed("配列を返すユースケースはページネーションを持つ"),
+        explanation: Cow::Borrowed(""),
+    };
+
+    impl CustomRule for RequirePagination {
+        fn rule(&self) -> &Rule {
+            &REQUIRE_PAGINATION
+        }
+
+        fn check(&self, cx: &RuleContext<'_>, diagnostics: &mut Diagnostics<'_>) {
+            let returns_array = cx
+                .model
+                .fields
+                .iter()
+                .any(|f| f.kind == crate::model::FieldKind::Array && f.parent.is_none());
+            let paginated = cx
+                .document
+                .usecase
+                .filters
+                .iter()
+                .any(|f| f.maps_to == "PAGINATION");
+            if returns_array && !paginated {
+                diagnostics.report(format!(
+                    "ユースケース '{}' はページネーションを指定していません",
+                    cx.document.usecase.name
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn test_validator_runs_registered_custom_rules() {
+        let yaml = r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["posts"]
+usecase:
+  name: 投稿一覧
+  response_mapping:
+    - field: posts
+      type: array
+      source_table: posts
+      fields:
+        - field: id
+          source: posts.id
+"#;
+        let doc = parser::parse(yaml).unwrap();
+        let validator = Validator::new().with_rule(RequirePagination);
+
+        let diagnostics = validator.validate(&doc);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code.as_deref(), Some("ACME001"));
+        assert_eq!(diagnostics[0].rule, "acme.pagination");
+        assert!(validator.rules().any(|r| r.code == "ACME001"));
+        assert!(Validator::new().validate(&doc).is_empty());
+    }
+
+    #[test]
+    fn test_naming_config_checks_fields_and_params() {
+        let config = Config::parse(
+            "[lint.naming]\nfields = \"snake_case\"\nparams = \"camelCase\"\n",
+            "usml.toml",
+        )
+        .unwrap();
+        let yaml = r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: displayName
+      source: users.display_name
+  filters:
+    - param: user_id
+      maps_to: WHERE
+      condition: users.id = :user_id
+"#;
+        let doc = parser::parse(yaml).unwrap();
+        let diagnostics = Validator::from_config(&config).unwrap().validate(&doc);
+        let codes: Vec<_> = diagnostics
+            .iter()
+            .filter_map(|d| d.code.as_deref())
+            .collect();
+        assert_eq!(codes, vec!["U016", "U017"]);
+        assert!(diagnostics[0].message.contains("display_name"));
+        assert!(diagnostics[1].message.contains("userId"));
+    }
+
+    #[test]
+    fn test_lint_policy_selects_rules_and_overrides_severity() {
+        let yaml = r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: テスト
   response_mapping:
-    - field: avatar_url
-      source: profiles.avatar_url
-      join:
-        table: profiles
-        on: users.id = profiles.user_id
+    - field: displayName
+      source: users.display_name
+  filters:
+    - param: user_id
+      maps_to: WHERE
+      condition: users.id = :user_id
 "#;
         let doc = parser::parse(yaml).unwrap();
-        let errors = validate(&doc);
+        let report = |toml: &str| -> Vec<(String, Severity)> {
+            let config = Config::parse(
+                &format!(
+                    "[lint.naming]\nfields = \"snake_case\"\nparams = \"camelCase\"\n{}",
+                    toml
+                ),
+                "usml.toml",
+            )
+            .unwrap();
+            Validator::from_config(&config)
+                .unwrap()
+                .validate(&doc)
+                .into_iter()
+                .map(|d| (d.code.unwrap(), d.severity))
+                .collect()
+        };
+        assert_eq!(
+            report("[lint]\ndisable = [\"naming.param\"]\n"),
+            [("U016".to_string(), Severity::Warning)]
+        );
+        assert_eq!(
+            report("[lint]\nenable = [\"U017\"]\n[lint.severity]\nU017 = \"error\"\n"),
+            [("U017".to_string(), Severity::Error)]
+        );
         assert!(
-            errors
+            report("[lint]\nwarnings_as_errors = true\n")
+                .iter()
+                .all(|(_, severity)| *severity == Severity::Error)
+        );
+
+        let config = Config::parse("[lint]\ndisable = [\"U999\"]\n", "usml.toml").unwrap();
+        assert!(matches!(
+            Validator::from_config(&config),
+            Err(ConfigError::UnknownRule { key: "disable", .. })
+        ));
+    }
+
+    #[test]
+    fn test_aggregate_type_whitelist_is_configurable() {
+        let yaml = r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["posts"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: post_count
+      source: posts.id
+      aggregate:
+        type: count
+        group_by: posts.user_id
+    - field: titles
+      source: posts.title
+      aggregate:
+        type: STRING_AGG
+        group_by: posts.user_id
+"#;
+        let doc = parser::parse(yaml).unwrap();
+        let codes = |validator: Validator| -> Vec<String> {
+            validator
+                .validate(&doc)
+                .into_iter()
+                .filter_map(|d| d.code)
+                .collect()
+        };
+        assert_eq!(codes(Validator::new()), vec!["U025"]);
+
+        let config = Config::parse(
+            "[lint]\naggregates = [\"COUNT\", \"STRING_AGG\"]\n",
+            "usml.toml",
+        )
+        .unwrap();
+        assert!(codes(Validator::from_config(&config).unwrap()).is_empty());
+    }
+
+    #[test]
+    fn test_group_by_aggregate_multiplied_by_sibling_join() {
+        let yaml = |strategy: &str| {
+            format!(
+                r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["users"]
+    - ./schema.dbml#tables["posts"]
+    - ./schema.dbml#tables["comments"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: id
+      source: users.id
+    - field: post_count
+      source: posts.id
+      join:
+        table: posts
+        type: LEFT
+        on: users.id = posts.user_id
+      aggregate:
+        type: COUNT
+        group_by: users.id
+        {}
+    - field: latest_comment_at
+      source: comments.created_at
+      join:
+        table: comments
+        type: LEFT
+        on: users.id = comments.user_id
+      aggregate:
+        type: MAX
+        group_by: users.id
+"#,
+                strategy
+            )
+        };
+        let rule = RuleTest::new("U051");
+        // 省略時はサブクエリにするので警告しない。MAX は重複しても値が変わらない
+        rule.expect(&yaml(""), &[]);
+        rule.expect(&yaml("strategy: subquery"), &[]);
+        rule.expect(&yaml("strategy: group_by"), &["'post_count' の COUNT"]);
+        rule.expect(&yaml("strategy: lateral"), &["'lateral' は使えません"]);
+    }
+
+    #[test]
+    fn test_hydration_values() {
+        let yaml = |hydration: &str| {
+            format!(
+                r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: id
+      source: users.id
+  hydration:
+{}
+"#,
+                hydration
+            )
+        };
+        let rule = RuleTest::new("U052");
+        rule.expect(&yaml("    strategy: batch"), &[]);
+        rule.expect(&yaml("    strategy: json\n    dialect: MySQL"), &[]);
+        rule.expect(
+            &yaml("    strategy: nested\n    dialect: sqlite"),
+            &["'nested'", "'sqlite'"],
+        );
+        rule.expect(
+            &yaml("    strategy: batch\n    dialect: mysql"),
+            &["strategy: json のときだけ"],
+        );
+    }
+
+    #[test]
+    fn test_sibling_arrays_on_same_parent_key() {
+        let yaml = |hydration: &str| {
+            format!(
+                r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["posts"]
+    - ./schema.dbml#tables["comments"]
+    - ./schema.dbml#tables["likes"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: id
+      source: posts.id
+    - field: comments
+      type: array
+      source_table: comments
+      join:
+        table: comments
+        on: posts.id = comments.post_id
+      fields:
+        - field: body
+          source: comments.body
+    - field: likes
+      type: array
+      source_table: likes
+      join:
+        table: likes
+        on: posts.id = likes.post_id
+      fields:
+        - field: user_id
+          source: likes.user_id
+{}
+"#,
+                hydration
+            )
+        };
+        let rule = RuleTest::new("U053");
+        rule.expect(&yaml(""), &["'comments'・'likes' はどれも posts.id"]);
+        rule.expect(&yaml("  hydration:\n    strategy: batch"), &[]);
+    }
+
+    #[test]
+    fn test_output_name() {
+        let yaml = |output: &str| {
+            format!(
+                r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: テスト
+  output: {}
+  response_mapping:
+    - field: id
+      source: users.id
+"#,
+                output
+            )
+        };
+        let rule = RuleTest::new("U054");
+        rule.expect(&yaml("users/list.html"), &[]);
+        rule.expect(
+            &yaml("../public/index.html"),
+            &["output '../public/index.html' は使えません"],
+        );
+        rule.expect(&yaml("list.txt"), &["output 'list.txt'"]);
+    }
+
+    #[test]
+    fn test_total_count() {
+        let yaml = |strategy: &str, total_count: &str, distinct: bool| {
+            format!(
+                r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["posts"]
+usecase:
+  name: テスト
+  distinct: {}
+  response_mapping:
+    - field: items
+      type: array
+      collection: true
+      source_table: posts
+      fields:
+        - field: id
+          source: posts.id
+    - field: total
+      envelope: total
+  filters:
+    - param: page
+      maps_to: PAGINATION
+      strategy: {}
+      cursor_field: id
+      total_count: {}
+"#,
+                distinct, strategy, total_count
+            )
+        };
+        let rule = RuleTest::new("U055");
+        rule.expect(&yaml("offset", "window", false), &[]);
+        rule.expect(&yaml("cursor", "QUERY", false), &[]);
+        rule.expect(
+            &yaml("cursor", "window", false),
+            &["カーソル方式では使えません"],
+        );
+        rule.expect(&yaml("offset", "window", true), &["DISTINCT"]);
+        rule.expect(
+            &yaml("offset", "none", false),
+            &["total_count: none の usecase に付帯フィールド 'total'（total）は置けません"],
+        );
+        rule.expect(
+            &yaml("offset", "estimate", false),
+            &["'estimate' は使えません"],
+        );
+    }
+
+    #[test]
+    fn test_timezone() {
+        let yaml = r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["posts"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: createdAt
+      source: posts.created_at
+      timezone: ":tz"
+    - field: publishedOn
+      source: posts.published_on
+      timezone: Asia/Tokyo
+    - field: latest
+      source: posts.created_at
+      aggregate:
+        type: MAX
+        group_by: posts.user_id
+      timezone: UTC
+    - field: count
+      source: posts.id
+      aggregate:
+        type: COUNT
+        group_by: posts.user_id
+      timezone: UTC
+    - field: label
+      value: fixed
+      timezone: JST
+"#;
+        let document_messages = [
+            "'count' の timezone は集約 COUNT と併用できません",
+            "'label' の timezone は source のカラムを返すスカラーのフィールドにだけ指定できます",
+            "timezone 'JST' は IANA のタイムゾーン名",
+        ];
+        RuleTest::new("U056").expect(yaml, &document_messages);
+
+        let tables = vec![DbmlTable {
+            name: "posts".to_string(),
+            columns: vec!["created_at".to_string(), "published_on".to_string()],
+            column_details: vec![
+                DbmlColumn {
+                    name: "created_at".to_string(),
+                    data_type: "timestamptz".to_string(),
+                    ..DbmlColumn::default()
+                },
+                DbmlColumn {
+                    name: "published_on".to_string(),
+                    data_type: "date".to_string(),
+                    ..DbmlColumn::default()
+                },
+            ],
+            location: None,
+        }];
+        let mut expected = document_messages.to_vec();
+        expected.push(
+            "publishedOn の timezone は日時のカラムにだけ指定できます（posts.published_on は date）",
+        );
+        RuleTest::new("U056")
+            .with_dbml(tables)
+            .expect(yaml, &expected);
+
+        let openapi = OpenapiResponse {
+            parameters: vec!["locale".to_string()],
+            ..OpenapiResponse::default()
+        };
+        let mut expected = document_messages.to_vec();
+        expected.push("timezone のパラメータ tz がOpenAPIパラメータに存在しません");
+        RuleTest::new("U056")
+            .with_openapi(openapi)
+            .expect(yaml, &expected);
+    }
+
+    #[test]
+    fn test_convert() {
+        let yaml = r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["orders"]
+    - ./schema.dbml#tables["exchange_rates"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: price_usd
+      source: orders.amount
+    - field: weight_kg
+      source: orders.weight
+    - field: note
+      source: orders.note
+  transforms:
+    - target: price_usd
+      type: CONVERT
+      source: orders.amount
+      lookup:
+        table: exchange_rates
+        on: orders.currency = exchange_rates.currency
+        rate: exchange_rates.rate
+      rounding: half_even
+      scale: 2
+    - target: weight_kg
+      type: CONVERT
+      source: orders.weight
+      factor: 0.001
+      lookup:
+        table: exchange_rates
+        on: orders.currency = exchange_rates.currency
+        rate: exchange_rates.rate
+      rounding: bankers
+    - target: note
+      type: convert
+      source: orders.note
+      factor: 1.5
+      scale: 0
+"#;
+        let document_messages = [
+            "transform 'weight_kg' の CONVERT: factor と lookup は同時に指定できません",
+            "scale（出力の小数点以下の桁数）が指定されていません",
+            "rounding 'bankers' は使えません",
+        ];
+        RuleTest::new("U057").expect(yaml, &document_messages);
+
+        let table = |name: &str, columns: &[(&str, &str)]| DbmlTable {
+            name: name.to_string(),
+            columns: columns.iter().map(|(c, _)| c.to_string()).collect(),
+            column_details: columns
+                .iter()
+                .map(|(c, t)| DbmlColumn {
+                    name: c.to_string(),
+                    data_type: t.to_string(),
+                    ..DbmlColumn::default()
+                })
+                .collect(),
+            location: None,
+        };
+        let tables = vec![
+            table(
+                "orders",
+                &[
+                    ("amount", "decimal(12,2)"),
+                    ("weight", "int"),
+                    ("note", "text"),
+                    ("currency", "varchar"),
+                ],
+            ),
+            table(
+                "exchange_rates",
+                &[("currency", "varchar"), ("rate", "numeric")],
+            ),
+        ];
+        let mut expected = document_messages.to_vec();
+        expected.push(
+            "transform note の CONVERT の source orders.note は string 型です（数値型のカラムにだけ適用できます）",
+        );
+        RuleTest::new("U057")
+            .with_dbml(tables)
+            .expect(yaml, &expected);
+
+        let property = |name: &str, schema_type: &str, format: Option<&str>| OpenapiProperty {
+            name: name.to_string(),
+            schema_type: Some(schema_type.to_string()),
+            format: format.map(str::to_string),
+            ..OpenapiProperty::default()
+        };
+        let openapi = OpenapiResponse {
+            properties: vec![
+                property("price_usd", "integer", Some("int64")),
+                property("note", "integer", None),
+            ],
+            ..OpenapiResponse::default()
+        };
+        let mut expected = document_messages.to_vec();
+        expected.push(
+            "transform price_usd の CONVERT は小数点以下 2 桁の数値を返しますが、OpenAPI のフィールドは integer（int64） です",
+        );
+        RuleTest::new("U057")
+            .with_openapi(openapi)
+            .expect(yaml, &expected);
+    }
+
+    #[test]
+    fn test_localize() {
+        let yaml = r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["products"]
+    - ./schema.dbml#tables["product_translations"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: name
+      source: products.name
+    - field: description
+    - field: summary
+  transforms:
+    - target: name
+      type: LOCALIZE
+      source: product_translations.name
+      locale:
+        on: products.id = product_translations.product_id
+        column: product_translations.locale
+        param: lang
+        fallback: [ja-JP, en]
+    - target: description
+      type: LOCALIZE
+      source: product_translations.description
+      locale:
+        on: products.id = product_translations.product_id
+        column: product_translations.product_id
+        param: lang
+        fallback: [en, english_, en]
+    - target: summary
+      type: localize
+      source: product_translations.summary
+"#;
+        let document_messages = [
+            "transform 'description' の LOCALIZE: locale.fallback の 'english_' は言語タグ",
+            "locale.fallback の 'en' が重複しています",
+            "transform 'summary' の LOCALIZE: locale（翻訳テーブルの行の選び方）が指定されていません",
+        ];
+        RuleTest::new("U058").expect(yaml, &document_messages);
+
+        let table = |name: &str, columns: &[(&str, &str)]| DbmlTable {
+            name: name.to_string(),
+            columns: columns.iter().map(|(c, _)| c.to_string()).collect(),
+            column_details: columns
                 .iter()
-                .any(|e| matches!(e, ValidationError::Rule(rule, _) if rule == "import.dbml"))
+                .map(|(c, t)| DbmlColumn {
+                    name: c.to_string(),
+                    data_type: t.to_string(),
+                    ..DbmlColumn::default()
+                })
+                .collect(),
+            location: None,
+        };
+        let tables = vec![
+            table("products", &[("id", "int"), ("name", "varchar")]),
+            table(
+                "product_translations",
+                &[
+                    ("product_id", "int"),
+                    ("locale", "varchar(8)"),
+                    ("name", "varchar"),
+                    ("description", "text"),
+                    ("summary", "text"),
+                ],
+            ),
+        ];
+        let mut expected = document_messages.to_vec();
+        expected.push(
+            "transform description の LOCALIZE の locale.column product_translations.product_id は integer 型です（言語は文字列型のカラムにします）",
         );
+        RuleTest::new("U058")
+            .with_dbml(tables)
+            .expect(yaml, &expected);
+
+        let openapi = OpenapiResponse {
+            parameters: vec!["lang".to_string()],
+            parameter_details: vec![OpenapiParameter {
+                name: "lang".to_string(),
+                location: Some("query".to_string()),
+                required: false,
+                schema_type: Some("integer".to_string()),
+                format: None,
+                description: None,
+                default: None,
+            }],
+            ..OpenapiResponse::default()
+        };
+        let mut expected = document_messages.to_vec();
+        expected.extend([
+            "transform name の LOCALIZE の locale.param lang は integer 型です（言語は string のパラメータで受け取ります）",
+            "transform description の LOCALIZE の locale.param lang は integer 型です",
+        ]);
+        RuleTest::new("U058")
+            .with_openapi(openapi)
+            .expect(yaml, &expected);
+
+        let mut expected = document_messages.to_vec();
+        expected.extend([
+            "locale.param lang がOpenAPIパラメータに存在しません",
+            "locale.param lang がOpenAPIパラメータに存在しません",
+        ]);
+        RuleTest::new("U058")
+            .with_openapi(OpenapiResponse::default())
+            .expect(yaml, &expected);
+    }
+
+    #[test]
+    fn test_sum_over_non_numeric_column() {
+        let tables = vec![DbmlTable {
+            name: "orders".to_string(),
+            columns: vec!["amount".to_string(), "note".to_string()],
+            column_details: vec![
+                DbmlColumn {
+                    name: "amount".to_string(),
+                    data_type: "decimal(10,2)".to_string(),
+                    ..DbmlColumn::default()
+                },
+                DbmlColumn {
+                    name: "note".to_string(),
+                    data_type: "text".to_string(),
+                    ..DbmlColumn::default()
+                },
+            ],
+            location: None,
+        }];
+        let yaml = r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["orders"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: total
+      source: orders.amount
+      aggregate:
+        type: SUM
+        group_by: orders.user_id
+    - field: average_note
+      source: orders.note
+      aggregate:
+        type: avg
+        group_by: orders.user_id
+"#;
+        let doc = parser::parse(yaml).unwrap();
+        let errors = validate_against_dbml(&doc, &Model::build(&doc), &tables, None);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].rule().code, "U026");
+        assert!(errors[0].to_string().contains("average_note の AVG"));
+    }
+
+    #[test]
+    fn test_collection_envelope_tied_to_pagination() {
+        let yaml = |strategy: &str| {
+            format!(
+                r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["posts"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: items
+      type: array
+      collection: true
+      source_table: posts
+      fields:
+        - field: id
+          source: posts.id
+    - field: total
+      envelope: total
+    - field: next_cursor
+      envelope: next_cursor
+    - field: page_count
+      envelope: pages
+  filters:
+    - param: cursor
+      maps_to: PAGINATION
+      strategy: {}
+      cursor_field: id
+"#,
+                strategy
+            )
+        };
+        let messages = |strategy: &str| -> Vec<String> {
+            validate(&parser::parse(&yaml(strategy)).unwrap())
+                .into_iter()
+                .filter(|e| e.rule().category == rules::Category::Mapping)
+                .map(|e| e.to_string())
+                .collect()
+        };
+
+        let errors = messages("cursor");
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("'pages'"));
+
+        let errors = messages("offset");
+        assert_eq!(errors.len(), 2);
+        assert!(errors[1].contains("next_cursor"));
+    }
+
+    #[test]
+    fn test_collection_must_be_root_array_holding_cursor_field() {
+        let yaml = r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["posts"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: items
+      collection: true
+      source: posts.id
+    - field: total
+      envelope: total
+      source: posts.id
+  filters:
+    - param: cursor
+      maps_to: PAGINATION
+      strategy: cursor
+      cursor_field: created_at
+"#;
+        let codes: Vec<_> = validate(&parser::parse(yaml).unwrap())
+            .iter()
+            .map(|e| e.rule().code.as_ref())
+            .collect();
+        assert_eq!(codes, vec!["U027", "U027", "U028"]);
     }
 
     #[test]
-    fn test_duplicate_join_without_alias() {
+    fn test_usecase_limit_conflicts_with_pagination() {
         let yaml = r#"
 version: "0.1"
 import:
-  openapi: ./api.yaml#paths["/posts"].get.responses["200"]
   dbml:
     - ./schema.dbml#tables["posts"]
-    - ./schema.dbml#tables["users"]
 usecase:
   name: テスト
+  distinct: true
+  limit: 50
+  default_limit: 20
   response_mapping:
-    - field: author_name
-      source: users.name
-      join:
-        table: users
-        on: posts.user_id = users.id
-    - field: editor_name
-      source: users.name
-      join:
-        table: users
-        on: posts.editor_id = users.id
+    - field: id
+      source: posts.id
+  filters:
+    - param: page
+      maps_to: PAGINATION
+      strategy: offset
+      page_size: 20
+      max_page_size: 100
 "#;
         let doc = parser::parse(yaml).unwrap();
-        let errors = validate(&doc);
-        assert!(
-            errors
-                .iter()
-                .any(|e| matches!(e, ValidationError::Rule(rule, _) if rule == "join.alias"))
-        );
+        assert!(doc.usecase.distinct);
+        let messages: Vec<String> = validate(&doc)
+            .iter()
+            .filter(|e| e.rule().name == "usecase.limit")
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].contains("default_limit"));
+        assert!(messages[1].contains("max_page_size (100)"));
     }
 
     #[test]
-    fn test_transform_target_not_in_mapping() {
-        let yaml = r#"
+    fn test_not_null_field_through_left_join_without_fallback() {
+        let openapi = OpenapiResponse {
+            fields: vec!["avatar_url".to_string(), "bio".to_string()],
+            properties: vec![
+                OpenapiProperty {
+                    name: "avatar_url".to_string(),
+                    required: true,
+                    ..OpenapiProperty::default()
+                },
+                OpenapiProperty {
+                    name: "bio".to_string(),
+                    nullable: true,
+                    ..OpenapiProperty::default()
+                },
+            ],
+            ..OpenapiResponse::default()
+        };
+        let yaml = |fallback: &str| {
+            format!(
+                r#"
 version: "0.1"
 import:
-  openapi: ./api.yaml#paths["/users"].get.responses["200"]
   dbml:
     - ./schema.dbml#tables["users"]
+    - ./schema.dbml#tables["profiles"]
 usecase:
   name: テスト
   response_mapping:
-    - field: id
-      source: users.id
+    - field: avatar_url
+      source: profiles.avatar_url
+      join:
+        table: profiles
+        on: users.id = profiles.user_id
+    - field: bio
+      source: profiles.bio
+      nullable: false
   transforms:
-    - target: nonexistent_field
+    - target: avatar_url
       type: COALESCE
-      sources:
-        - users.name
-"#;
-        let doc = parser::parse(yaml).unwrap();
-        let errors = validate(&doc);
-        assert!(errors.iter().any(|e| {
-            matches!(e, ValidationError::Rule(rule, _) if rule == "transforms.target")
-        }));
+      source: profiles.avatar_url
+{}"#,
+                fallback
+            )
+        };
+
+        RuleTest::new("U031").expect(&yaml(""), &["'bio' は nullable: false"]);
+        let doc = parser::parse(&yaml("")).unwrap();
+        let codes: Vec<_> = validate_against_openapi(&doc, &Model::build(&doc), &openapi)
+            .iter()
+            .map(|e| e.rule().code.as_ref())
+            .collect();
+        assert_eq!(codes, vec!["U030", "U031"]);
+
+        let doc = parser::parse(&yaml("      fallback: /default.png\n")).unwrap();
+        let codes: Vec<_> = validate_against_openapi(&doc, &Model::build(&doc), &openapi)
+            .iter()
+            .map(|e| e.rule().code.as_ref())
+            .collect();
+        assert_eq!(codes, vec!["U030"]);
     }
 
-    // --- 新規テスト: Rule 6 ---
     #[test]
-    fn test_rule6_join_on_references_non_imported_table() {
+    fn test_not_null_field_sourced_from_nullable_column() {
+        let column = |name: &str, nullable: Option<bool>| DbmlColumn {
+            name: name.to_string(),
+            data_type: "varchar".to_string(),
+            nullable,
+            ..DbmlColumn::default()
+        };
+        let tables = vec![DbmlTable {
+            name: "users".to_string(),
+            columns: vec!["name".to_string(), "nickname".to_string()],
+            column_details: vec![column("name", Some(false)), column("nickname", None)],
+            location: None,
+        }];
         let yaml = r#"
 version: "0.1"
 import:
-  openapi: ./api.yaml#paths["/posts"].get.responses["200"]
   dbml:
-    - ./schema.dbml#tables["posts"]
+    - ./schema.dbml#tables["users"]
 usecase:
   name: テスト
   response_mapping:
-    - field: author_name
+    - field: name
       source: users.name
-      join:
-        table: users
-        on: posts.user_id = users.id
+      nullable: false
+    - field: nickname
+      source: users.nickname
+      nullable: false
 "#;
         let doc = parser::parse(yaml).unwrap();
-        let errors = validate(&doc);
-        // users テーブルが import にないため Rule 6 (join.on) と Rule 2 (import.dbml) が発火
-        assert!(errors
-            .iter()
-            .any(|e| matches!(e, ValidationError::Rule(rule, _) if rule == "join.on" || rule == "import.dbml")));
+        let errors = validate_against_dbml(&doc, &Model::build(&doc), &tables, None);
+        assert_eq!(errors.len(), 1);
+        assert!(
+            matches!(&errors[0], ValidationError::Warning(rule, message, _)
+            if rule.code == "U031" && message.contains("users.nickname"))
+        );
     }
 
-    // --- 新規テスト: Rule 8 ---
     #[test]
-    fn test_rule8_aggregate_without_group_by_warns() {
+    fn test_field_values_are_literals_checked_against_openapi() {
         let yaml = r#"
 version: "0.1"
 import:
-  openapi: ./api.yaml#paths["/posts"].get.responses["200"]
   dbml:
-    - ./schema.dbml#tables["posts"]
-    - ./schema.dbml#tables["likes"]
+    - ./schema.dbml#tables["users"]
 usecase:
   name: テスト
   response_mapping:
-    - field: like_count
-      source: likes.id
-      join:
-        table: likes
-        on: posts.id = likes.post_id
-      aggregate:
-        type: COUNT
+    - field: api_version
+      value: v2
+    - field: page
+      const: ":page"
+    - field: locale
+      value: ":locale"
+    - field: beta
+      value: 1
+      source: users.beta
 "#;
         let doc = parser::parse(yaml).unwrap();
         let errors = validate(&doc);
-        assert!(errors.iter().any(
-            |e| matches!(e, ValidationError::Warning(rule, _) if rule == "aggregate.group_by")
-        ));
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].rule().code, "U032");
+        assert!(errors[0].to_string().contains("'beta'"));
+
+        let property = |name: &str, schema_type: &str| OpenapiProperty {
+            name: name.to_string(),
+            schema_type: Some(schema_type.to_string()),
+            ..OpenapiProperty::default()
+        };
+        let openapi = OpenapiResponse {
+            fields: ["api_version", "page", "locale", "beta"]
+                .map(String::from)
+                .to_vec(),
+            parameters: vec!["page".to_string()],
+            properties: vec![
+                property("api_version", "string"),
+                property("page", "string"),
+                property("locale", "string"),
+                property("beta", "boolean"),
+            ],
+            parameter_details: vec![OpenapiParameter {
+                name: "page".to_string(),
+                schema_type: Some("integer".to_string()),
+                ..OpenapiParameter::default()
+            }],
+            ..OpenapiResponse::default()
+        };
+        RuleTest::new("U033").with_openapi(openapi).expect(
+            yaml,
+            &[
+                "パラメータ 'page' は integer",
+                "'locale' が OpenAPI に存在しません",
+                "value 1 は integer",
+            ],
+        );
     }
 
-    // --- 新規テスト: Rule 9 ---
     #[test]
-    fn test_rule9_undeclared_param_in_condition() {
+    fn test_param_sourced_fields_echo_request_parameters() {
         let yaml = r#"
 version: "0.1"
 import:
-  openapi: ./api.yaml#paths["/users"].get.responses["200"]
   dbml:
     - ./schema.dbml#tables["users"]
 usecase:
   name: テスト
   response_mapping:
-    - field: id
-      source: users.id
-  filters:
-    - param: status
-      maps_to: WHERE
-      condition: users.status = :status AND users.role = :role
+    - field: user_id
+      source: ":user_id"
+    - field: query
+      from_param: q
+    - field: name
+      source: users.name
+      from_param: name
 "#;
         let doc = parser::parse(yaml).unwrap();
+        let model = Model::build(&doc);
+        assert_eq!(model.fields[0].param.as_deref(), Some("user_id"));
+        assert!(model.fields[0].source.is_none());
         let errors = validate(&doc);
-        // :role は filters[].param に宣言されていないため Rule 9 が発火
-        assert!(
-            errors.iter().any(
-                |e| matches!(e, ValidationError::Rule(rule, _) if rule == "filters.condition")
-            )
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].rule().code, "U032");
+        assert!(errors[0].to_string().contains("'name' は from_param"));
+
+        let users = DbmlTable {
+            name: "users".to_string(),
+            columns: vec!["id".to_string()],
+            column_details: Vec::new(),
+            location: None,
+        };
+        let errors = validate_against_dbml(&doc, &Model::build(&doc), &[users], None);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].to_string().contains("カラム name"));
+
+        let openapi = OpenapiResponse {
+            fields: ["user_id", "query", "name"].map(String::from).to_vec(),
+            parameters: vec!["user_id".to_string()],
+            properties: vec![OpenapiProperty {
+                name: "user_id".to_string(),
+                schema_type: Some("string".to_string()),
+                ..OpenapiProperty::default()
+            }],
+            parameter_details: vec![OpenapiParameter {
+                name: "user_id".to_string(),
+                schema_type: Some("integer".to_string()),
+                ..OpenapiParameter::default()
+            }],
+            ..OpenapiResponse::default()
+        };
+        RuleTest::new("U033").with_openapi(openapi).expect(
+            yaml,
+            &[
+                "パラメータ 'user_id' は integer",
+                "'q' が OpenAPI に存在しません",
+                "'name' が参照するパラメータ 'name' が OpenAPI に存在しません",
+            ],
         );
     }
 
-    // --- 新規テスト: Rule 11 ---
     #[test]
-    fn test_rule11_source_table_mismatch() {
+    fn test_authorization_row_filters_reference_imported_columns() {
         let yaml = r#"
 version: "0.1"
 import:
-  openapi: ./api.yaml#paths["/posts"].get.responses["200"]
   dbml:
     - ./schema.dbml#tables["posts"]
-    - ./schema.dbml#tables["comments"]
+    - ./schema.dbml#tables["users"]
 usecase:
   name: テスト
+  authorization:
+    scopes: [posts:read]
+    row_filters:
+      - author.org_id = :current_org_id
+      - posts.visibility = 'public'
+      - orgs.id = :current_org_id
+      - ":current_user_id IS NOT NULL"
   response_mapping:
-    - field: comments
-      type: array
-      source_table: wrong_table
+    - field: title
+      source: posts.title
+    - field: author_name
+      source: author.name
       join:
-        table: comments
-        on: posts.id = comments.post_id
-      fields:
-        - field: id
-          source: comments.id
+        table: users
+        alias: author
+        on: posts.user_id = author.id
 "#;
+        RuleTest::new("U034").expect(
+            yaml,
+            &[
+                "テーブル 'orgs' が import.dbml に含まれていません",
+                "参照していません",
+            ],
+        );
         let doc = parser::parse(yaml).unwrap();
-        let errors = validate(&doc);
+
+        let table = |name: &str, columns: &[&str]| DbmlTable {
+            name: name.to_string(),
+            columns: columns.iter().map(|c| c.to_string()).collect(),
+            column_details: Vec::new(),
+            location: None,
+        };
+        let tables = [
+            table("posts", &["id", "title", "user_id"]),
+            table("users", &["id", "name", "org_id"]),
+        ];
+        let errors = validate_against_dbml(&doc, &Model::build(&doc), &tables, None);
+        assert_eq!(errors.len(), 1);
         assert!(
-            errors
-                .iter()
-                .any(|e| matches!(e, ValidationError::Rule(rule, _) if rule == "source_table"))
+            errors[0]
+                .to_string()
+                .contains("カラム visibility がテーブル posts")
         );
     }
 
-    // --- 新規テスト: Rule 12 ---
     #[test]
-    fn test_rule12_default_column_not_in_allowed() {
+    fn test_filter_columns_checked_against_dbml() {
         let yaml = r#"
 version: "0.1"
 import:
-  openapi: ./api.yaml#paths["/users"].get.responses["200"]
   dbml:
+    - ./schema.dbml#tables["posts"]
     - ./schema.dbml#tables["users"]
 usecase:
   name: テスト
   response_mapping:
-    - field: id
-      source: users.id
+    - field: title
+      source: posts.title
+    - field: author_name
+      source: author.name
+      join:
+        table: users
+        alias: author
+        on: posts.user_id = author.id
   filters:
+    - param: status
+      maps_to: WHERE
+      condition: posts.state = :status AND author.name IS NOT NULL
+    - param: cursor
+      maps_to: PAGINATION
+      strategy: cursor
+      cursor_field: title
     - param: sort
       maps_to: ORDER_BY
-      default_column: users.secret_field
-      allowed_columns:
-        - users.created_at
-        - users.name
+      default_column: posts.create_at
+      allowed_columns: [posts.create_at, author.nmae, title]
 "#;
         let doc = parser::parse(yaml).unwrap();
-        let errors = validate(&doc);
-        assert!(errors.iter().any(
-            |e| matches!(e, ValidationError::Rule(rule, _) if rule == "filters.allowed_columns")
-        ));
-    }
+        let table = |name: &str, columns: &[&str]| DbmlTable {
+            name: name.to_string(),
+            columns: columns.iter().map(|c| c.to_string()).collect(),
+            column_details: Vec::new(),
+            location: None,
+        };
+        let tables = [
+            table("posts", &["id", "title", "state", "create_at", "user_id"]),
+            table("users", &["id", "name", "nmae"]),
+        ];
+        assert!(validate_against_dbml(&doc, &Model::build(&doc), &tables, None).is_empty());
 
-    // --- 新規テスト: Rule 11 with join_chain ---
-    #[test]
-    fn test_rule11_source_table_with_join_chain() {
-        let yaml = r#"
-version: "0.1"
-import:
-  openapi: ./api.yaml#paths["/posts"].get.responses["200"]
-  dbml:
-    - ./schema.dbml#tables["posts"]
-    - ./schema.dbml#tables["post_tags"]
-    - ./schema.dbml#tables["tags"]
-usecase:
-  name: テスト
-  response_mapping:
-    - field: tags
-      type: array
-      source_table: tags
-      join:
-        table: post_tags
-        on: posts.id = post_tags.post_id
-      join_chain:
-        - table: tags
-          on: post_tags.tag_id = tags.id
-      fields:
-        - field: id
-          source: tags.id
-"#;
-        let doc = parser::parse(yaml).unwrap();
-        let errors = validate(&doc);
-        // source_table: tags と join_chain の最後のテーブル tags が一致するのでエラーなし
-        let hard_errors: Vec<_> = errors
-            .iter()
-            .filter(|e| matches!(e, ValidationError::Rule(..)))
-            .collect();
-        assert!(
-            hard_errors.is_empty(),
-            "エラーがありました: {:?}",
-            hard_errors
+        let tables = [
+            table("posts", &["id", "title", "status", "created_at", "user_id"]),
+            table("users", &["id", "name"]),
+        ];
+        RuleTest::new("U046").with_dbml(tables.to_vec()).expect(
+            yaml,
+            &[
+                "filters[].condition（param 'status'）のカラム state がテーブル posts に存在しません",
+                "default_column（param 'sort'）のカラム create_at",
+                "allowed_columns（param 'sort'）のカラム create_at",
+                "カラム nmae がテーブル users",
+            ],
         );
     }
 
     #[test]
-    fn test_validate_openapi_fields_mismatch() {
-        // OpenAPI に id, name, email があるが response_mapping に nonexistent を指定
-        let openapi = OpenapiResponse {
-            fields: vec!["id".to_string(), "name".to_string(), "email".to_string()],
-            parameters: vec!["status".to_string()],
+    fn test_cursor_order_needs_unique_tiebreaker() {
+        let column = |name: &str, primary_key: bool| DbmlColumn {
+            name: name.to_string(),
+            data_type: "int".to_string(),
+            primary_key,
+            ..DbmlColumn::default()
         };
-        let yaml = r#"
+        let tables = [DbmlTable {
+            name: "posts".to_string(),
+            columns: vec!["id".to_string(), "created_at".to_string()],
+            column_details: vec![column("id", true), column("created_at", false)],
+            location: None,
+        }];
+        let yaml = |page: &str| {
+            format!(
+                r#"
 version: "0.1"
 import:
-  openapi: ./api.yaml#paths["/users"].get.responses["200"]
   dbml:
-    - ./schema.dbml#tables["users"]
+    - ./schema.dbml#tables["posts"]
 usecase:
   name: テスト
   response_mapping:
-    - field: nonexistent
-      source: users.id
-"#;
-        let doc = parser::parse(yaml).unwrap();
-        let mappings = &doc.usecase.response_mapping;
-        let mut errors = Vec::new();
-        validate_openapi_fields(mappings, &openapi, &mut errors);
-        assert!(errors.iter().any(
-            |e| matches!(e, ValidationError::Rule(rule, _) if rule == "response_mapping.field")
-        ));
+    - field: id
+      source: posts.id
+  filters:
+    - param: cursor
+      maps_to: PAGINATION
+      strategy: cursor
+{}"#,
+                page
+            )
+        };
+        let rule = RuleTest::new("U050").with_dbml(tables.to_vec());
+        rule.expect(&yaml("      cursor_field: id\n"), &[]);
+        rule.expect(
+            &yaml("      cursor_field: created_at\n      cursor_tiebreaker: posts.id\n"),
+            &[],
+        );
+        rule.expect(
+            &yaml("      cursor_field: created_at\n"),
+            &["PAGINATION（param 'cursor'）: cursor_field 'created_at' は一意でないため"],
+        );
+        rule.expect(
+            &yaml("      cursor_field: id\n      cursor_tiebreaker: created_at\n"),
+            &["cursor_tiebreaker 'created_at' が一意ではありません"],
+        );
     }
 
     #[test]
-    fn test_validate_dbml_columns_missing() {
-        let tables = vec![DbmlTable {
-            name: "users".to_string(),
-            columns: vec!["id".to_string(), "name".to_string(), "email".to_string()],
-        }];
+    fn test_cache_key_and_invalidation_targets() {
         let yaml = r#"
 version: "0.1"
 import:
-  openapi: ./api.yaml#paths["/users"].get.responses["200"]
   dbml:
-    - ./schema.dbml#tables["users"]
+    - ./schema.dbml#tables["posts"]
 usecase:
   name: テスト
+  cache:
+    ttl: 0
+    key: [page, locale]
+    invalidated_by: [posts, users]
   response_mapping:
-    - field: phone
-      source: users.phone
+    - field: title
+      source: posts.title
+  filters:
+    - param: page
+      maps_to: PAGINATION
 "#;
-        let doc = parser::parse(yaml).unwrap();
-        let mappings = &doc.usecase.response_mapping;
-        let mut errors = Vec::new();
-        validate_dbml_columns(mappings, &tables, &mut errors);
-        assert!(errors.iter().any(
-            |e| matches!(e, ValidationError::Rule(rule, _) if rule == "response_mapping.source")
-        ));
+        RuleTest::new("U035").expect(
+            yaml,
+            &[
+                "ttl が 0",
+                "'locale' が filters[].param",
+                "'users' が import.dbml",
+            ],
+        );
     }
 
     #[test]
-    fn test_validate_transform_params_missing() {
-        let openapi = OpenapiResponse {
-            fields: vec!["id".to_string()],
-            parameters: vec!["status".to_string()],
-        };
+    fn test_source_usecase_excludes_own_sources() {
         let yaml = r#"
 version: "0.1"
 import:
-  openapi: ./api.yaml#paths["/users"].get.responses["200"]
   dbml:
     - ./schema.dbml#tables["users"]
 usecase:
   name: テスト
   response_mapping:
-    - field: id
-      source: users.id
-  transforms:
-    - target: id
-      type: CONDITIONAL_SOURCE
-      condition:
-        - param: undeclared_param
-          operator: "="
-          value: "active"
-      then_source: users.id
-      else_source: users.name
+    - field: badges
+      source_usecase: GetUserBadges
+    - field: profile
+      source_usecase: GetProfile
+      source: users.profile
 "#;
         let doc = parser::parse(yaml).unwrap();
-        let mut errors = Vec::new();
-        validate_transform_params(&doc.usecase.transforms, &openapi, &mut errors);
-        assert!(errors.iter().any(
-            |e| matches!(e, ValidationError::Rule(rule, _) if rule == "transforms.condition.param")
-        ));
+        let errors = validate(&doc);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].rule().code, "U036");
+        assert!(errors[0].to_string().contains("'profile'"));
     }
 }
@@ -1,13 +1,15 @@
-use std::collections::{HashMap, HashSet};
-use std::fmt::Write;
+use std::fmt::{self, Write};
 
-use crate::ast::{ResponseMapping, Transform, UsmlDocument};
+use crate::ast::UsmlDocument;
+use crate::lineage::{self, EdgeKind, LineageEdge};
+use crate::model::{FieldKind, Model, TableNode};
 use crate::resolver;
 
 struct FieldEntry {
     field: String,
     field_path: String,     // フルパス（例: "comments.id"）
     source: Option<String>, // 元のsource（例: "posts.id"）
+    value: Option<String>,  // 固定値（例: "\"v2\""、":locale"）
     badges: Vec<String>,
     join_lines: Vec<String>,
     transforms: Vec<String>,
@@ -16,39 +18,309 @@ struct FieldEntry {
     join_type: String,
 }
 
-struct TableContext {
-    columns: HashMap<String, HashSet<String>>,
-    order: Vec<String>,
-    seen: HashSet<String>,
-    alias_map: HashMap<String, String>,
+pub fn generate_html(doc: &UsmlDocument) -> String {
+    let mut html = String::new();
+    // String への書き込みは失敗しないため fmt::Error は起こらない
+    let _ = write_html(&mut html, doc);
+    html
 }
 
-pub fn generate_html(doc: &UsmlDocument) -> String {
-    let transform_map = build_transform_map(&doc.usecase.transforms);
-    let table_order = extract_import_tables(doc);
-    let mut table_ctx = TableContext {
-        columns: table_order
-            .iter()
-            .cloned()
-            .map(|table| (table, HashSet::new()))
-            .collect(),
-        order: table_order.clone(),
-        seen: table_order.iter().cloned().collect(),
-        alias_map: HashMap::new(),
+/// 同じテーブルに触れる複数の usecase を 1 つにまとめた HTML（`usml visualize --merge`）
+///
+/// usecase ごとのタブにレスポンスフィールドと結合・変換を、右側の共有パネルに全 usecase のテーブルを並べる。
+/// テーブルのカードには参照する usecase をカラムごとに示し、選択中のタブが参照しないテーブルは薄く表示する
+pub fn generate_merged_html(docs: &[&UsmlDocument]) -> String {
+    let mut html = String::new();
+    let _ = write_merged_html(&mut html, docs);
+    html
+}
+
+/// 共有パネルの 1 テーブル分
+struct SharedTable {
+    name: String,
+    aliases: Vec<String>,
+    /// 参照する usecase の添字
+    usecases: Vec<usize>,
+    /// カラムと、それを参照する usecase の添字
+    columns: Vec<(String, Vec<usize>)>,
+}
+
+/// usecase ごとのテーブルを名前順にまとめる
+fn shared_tables(models: &[Model]) -> Vec<SharedTable> {
+    let mut tables: Vec<SharedTable> = Vec::new();
+    for (i, model) in models.iter().enumerate() {
+        for table in &model.tables {
+            let shared = match tables.iter_mut().find(|t| t.name == table.name) {
+                Some(shared) => shared,
+                None => {
+                    tables.push(SharedTable {
+                        name: table.name.clone(),
+                        aliases: Vec::new(),
+                        usecases: Vec::new(),
+                        columns: Vec::new(),
+                    });
+                    tables.last_mut().expect("直前に追加した")
+                }
+            };
+            shared.usecases.push(i);
+            for alias in &table.aliases {
+                if !shared.aliases.contains(alias) {
+                    shared.aliases.push(alias.clone());
+                }
+            }
+            for column in &table.columns {
+                match shared.columns.iter_mut().find(|(c, _)| c == column) {
+                    Some((_, usecases)) if usecases.last() != Some(&i) => usecases.push(i),
+                    Some(_) => {}
+                    None => shared.columns.push((column.clone(), vec![i])),
+                }
+            }
+        }
+    }
+    for table in &mut tables {
+        table.columns.sort();
+    }
+    tables.sort_by(|a, b| a.name.cmp(&b.name));
+    tables
+}
+
+fn write_merged_html(html: &mut String, docs: &[&UsmlDocument]) -> fmt::Result {
+    let models: Vec<Model> = docs.iter().map(|doc| Model::build(doc)).collect();
+    let tables = shared_tables(&models);
+
+    write_head(html, models.iter().map(max_depth).max().unwrap_or(0))?;
+
+    html.push_str("<div class=\"header\">\n");
+    let shared: Vec<&str> = tables
+        .iter()
+        .filter(|t| t.usecases.len() > 1)
+        .map(|t| t.name.as_str())
+        .collect();
+    let title = if shared.is_empty() {
+        "USML Data Flow".to_string()
+    } else {
+        shared.join(", ")
     };
-    let mut entries = Vec::new();
+    write!(html, "<h1>{}</h1>", escape_html(&title))?;
+    write!(
+        html,
+        "<p class=\"summary\">{} usecase・テーブル {}（複数の usecase が参照: {}）</p>",
+        docs.len(),
+        tables.len(),
+        shared.len()
+    )?;
+    let labels: Vec<(String, String)> = docs
+        .iter()
+        .enumerate()
+        .map(|(i, doc)| {
+            (
+                format!("uc{}", i),
+                format!("{}. {}", i + 1, doc.usecase.name),
+            )
+        })
+        .collect();
+    let tabs: Vec<(&str, &str, &str)> = labels
+        .iter()
+        .map(|(view, label)| (view.as_str(), "fa-file-alt", label.as_str()))
+        .collect();
+    write_tabs(html, &tabs)?;
+    html.push_str("</div>\n"); // header の終了
+
+    html.push_str("<div class=\"main-content\">\n<div class=\"merged nav-scope\">\n<div>\n");
+    for (i, (doc, model)) in docs.iter().zip(&models).enumerate() {
+        let entries = collect_entries(model);
+        writeln!(
+            html,
+            "<div id=\"uc{}-view\" class=\"view{}\" role=\"tabpanel\" aria-labelledby=\"tab-uc{}\">",
+            i,
+            if i == 0 { " active" } else { "" },
+            i
+        )?;
+        write!(
+            html,
+            "<div class=\"usecase-info\"><h2>{}</h2>",
+            escape_html(&doc.usecase.name)
+        )?;
+        write_usecase_info(html, doc)?;
+        html.push_str("</div>\n<div class=\"grid two\">\n");
+        write_response_column(html, &entries)?;
+        write_joins_column(html, &entries)?;
+        html.push_str("</div>\n</div>\n"); // grid, usecase のビューの終了
+    }
+    html.push_str("</div>\n");
 
-    collect_entries(
-        &doc.usecase.response_mapping,
-        0,
-        "",
-        &transform_map,
-        &mut entries,
-        &mut table_ctx,
-    );
+    html.push_str("<div class=\"column\">\n<h2>Tables</h2>\n");
+    if tables.is_empty() {
+        html.push_str("<div class=\"empty\">No tables imported.</div>");
+    } else {
+        html.push_str("<div class=\"nav-group\" role=\"list\" aria-label=\"Tables\">\n");
+        for table in &tables {
+            let usecases: Vec<String> = table.usecases.iter().map(|i| format!("uc{}", i)).collect();
+            write!(
+                html,
+                "<div class=\"card table-card{}\" role=\"listitem\" tabindex=\"0\" data-table=\"{}\" data-usecases=\"{}\"><div class=\"field-name\">{}",
+                if table.usecases.contains(&0) {
+                    ""
+                } else {
+                    " inactive"
+                },
+                escape_html(&table.name),
+                usecases.join(","),
+                escape_html(&table.name)
+            )?;
+            if !table.aliases.is_empty() {
+                write!(
+                    html,
+                    " <span style=\"color: #6b7280; font-weight: 400;\">(as {})</span>",
+                    escape_html(&table.aliases.join(", "))
+                )?;
+            }
+            html.push_str("</div><div>");
+            for &i in &table.usecases {
+                write!(
+                    html,
+                    "<span class=\"badge\">{}</span>",
+                    escape_html(&labels[i].1)
+                )?;
+            }
+            html.push_str("</div>");
+            if table.columns.is_empty() {
+                html.push_str("<div class=\"join-line\" style=\"color: #9ca3af;\">No columns referenced</div>");
+            } else {
+                html.push_str("<table class=\"usage\"><thead><tr><th scope=\"col\">Column</th>");
+                for &i in &table.usecases {
+                    write!(
+                        html,
+                        "<th scope=\"col\" title=\"{}\">{}</th>",
+                        escape_html(&docs[i].usecase.name),
+                        i + 1
+                    )?;
+                }
+                html.push_str("</tr></thead><tbody>");
+                for (column, users) in &table.columns {
+                    write!(
+                        html,
+                        "<tr><td><code class=\"inline\">{}</code></td>",
+                        escape_html(column)
+                    )?;
+                    for i in &table.usecases {
+                        html.push_str(if users.contains(i) {
+                            "<td>✓</td>"
+                        } else {
+                            "<td></td>"
+                        });
+                    }
+                    html.push_str("</tr>");
+                }
+                html.push_str("</tbody></table>");
+            }
+            html.push_str("</div>\n");
+        }
+        html.push_str("</div>\n");
+    }
+    html.push_str("</div>\n</div>\n</div>\n"); // column (Tables), merged, main-content の終了
 
-    let mut html = String::new();
-    html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str(SCRIPT);
+    html.push_str("</body>\n</html>\n");
+    Ok(())
+}
+
+fn write_html(html: &mut String, doc: &UsmlDocument) -> fmt::Result {
+    let model = Model::build(doc);
+    let entries = collect_entries(&model);
+
+    write_head(html, max_depth(&model))?;
+
+    // ヘッダー
+    html.push_str("<div class=\"header\">\n");
+    write!(html, "<h1>{}</h1>", escape_html(&doc.usecase.name))?;
+    write_usecase_info(html, doc)?;
+
+    write_tabs(
+        html,
+        &[
+            ("table", "fa-table", "テーブル"),
+            ("visual", "fa-project-diagram", "ビジュアル"),
+            ("lineage", "fa-code-branch", "リネージ"),
+        ],
+    )?;
+    html.push_str("</div>\n"); // header の終了
+
+    // メインコンテンツ
+    html.push_str("<div class=\"main-content\">\n");
+
+    // ビジュアルビュー
+    html.push_str("<div id=\"visual-view\" class=\"view\" role=\"tabpanel\" aria-labelledby=\"tab-visual\">\n");
+    html.push_str("<div class=\"grid\">\n");
+
+    write_response_column(html, &entries)?;
+    write_joins_column(html, &entries)?;
+    write_tables_column(html, &model)?;
+    html.push_str("</div>\n</div>\n"); // grid, visual-view の終了
+
+    // リネージビュー
+    html.push_str("<div id=\"lineage-view\" class=\"view\" role=\"tabpanel\" aria-labelledby=\"tab-lineage\">\n");
+    write_lineage_view(html, &model)?;
+    html.push_str("</div>\n");
+
+    // テーブルビュー
+    html.push_str("<div id=\"table-view\" class=\"view active\" role=\"tabpanel\" aria-labelledby=\"tab-table\">\n");
+    generate_table_view(html, &entries, &model, doc)?;
+    html.push_str("</div>\n");
+
+    html.push_str("</div>\n"); // main-content の終了
+
+    html.push_str(SCRIPT);
+    html.push_str("</body>\n</html>\n");
+    Ok(())
+}
+
+/// 入れ子の段ごとの色（枠・カードの背景・表の行の背景）。5 段目からは先頭から繰り返す
+const DEPTH_COLORS: [(&str, &str, &str); 4] = [
+    ("#3b82f6", "#dbeafe", "#eff6ff"),
+    ("#8b5cf6", "#e9d5ff", "#f3e8ff"),
+    ("#ec4899", "#fce7f3", "#fce7f3"),
+    ("#f59e0b", "#fef3c7", "#fef3c7"),
+];
+
+/// 最も深いフィールドの段（トップレベルは 0）
+fn max_depth(model: &Model) -> usize {
+    model.fields.iter().map(|f| f.depth).max().unwrap_or(0)
+}
+
+/// `max_depth` 段までのカードの字下げ（`.depth-N`）と色。段ごとに 24px ずつ下げる
+fn write_depth_styles(html: &mut String, max_depth: usize) -> fmt::Result {
+    for depth in 1..=max_depth.max(DEPTH_COLORS.len()) {
+        let (border, background, _) = DEPTH_COLORS[(depth - 1) % DEPTH_COLORS.len()];
+        writeln!(
+            html,
+            ".depth-{} {{ margin-left: {}px; padding-left: 12px; border-left: 3px solid {}; background: {} !important; }}",
+            depth,
+            depth * 24,
+            border,
+            background
+        )?;
+    }
+    Ok(())
+}
+
+/// `max_depth` 段までの表の行の字下げ（`.indent-N`）と色
+fn write_indent_styles(html: &mut String, max_depth: usize) -> fmt::Result {
+    for depth in 1..=max_depth.max(DEPTH_COLORS.len()) {
+        let (_, _, background) = DEPTH_COLORS[(depth - 1) % DEPTH_COLORS.len()];
+        writeln!(
+            html,
+            ".indent-{} {{ padding-left: {}px; background: {}; }}",
+            depth,
+            16 + depth * 16,
+            background
+        )?;
+    }
+    Ok(())
+}
+
+fn write_head(html: &mut String, max_depth: usize) -> fmt::Result {
+    html.push_str("<!DOCTYPE html>\n<html lang=\"ja\">\n<head>\n<meta charset=\"utf-8\">\n");
     html.push_str("<title>USML Data Flow Visualizer</title>\n");
     html.push_str("<link rel=\"stylesheet\" href=\"https://cdnjs.cloudflare.com/ajax/libs/font-awesome/6.4.0/css/all.min.css\">\n");
     html.push_str("<style>\n");
@@ -67,11 +339,39 @@ pub fn generate_html(doc: &UsmlDocument) -> String {
     html.push_str(".method-patch { background: #f3e8ff; color: #6b21a8; }\n");
     html.push_str(".api-path { font-family: 'Monaco', 'Menlo', monospace; font-size: 0.9rem; color: #374151; background: #f3f4f6; padding: 6px 12px; border-radius: 4px; }\n");
     html.push_str(".status-badge { display: inline-block; padding: 4px 10px; border-radius: 4px; font-size: 0.75rem; font-weight: 600; background: #d1fae5; color: #065f46; }\n");
+    html.push_str(
+        ".query-info { display: flex; gap: 8px; margin: -12px 0 24px 0; flex-wrap: wrap; }\n",
+    );
+    html.push_str(".query-badge { display: inline-block; padding: 4px 10px; border-radius: 4px; font-size: 0.75rem; font-weight: 600; background: #e0e7ff; color: #3730a3; font-family: 'Monaco', 'Menlo', monospace; }\n");
+    html.push_str(
+        ".tag-info { display: flex; align-items: center; gap: 8px; margin: -12px 0 24px 0; flex-wrap: wrap; }\n",
+    );
+    html.push_str(".tag-badge { display: inline-block; padding: 4px 10px; border-radius: 999px; font-size: 0.75rem; font-weight: 600; background: #f3e8ff; color: #6b21a8; }\n");
+    html.push_str(
+        ".owner-info { display: flex; align-items: center; gap: 8px; margin: -12px 0 24px 0; flex-wrap: wrap; }\n",
+    );
+    html.push_str(".owner-badge { display: inline-block; padding: 4px 10px; border-radius: 4px; font-size: 0.75rem; font-weight: 600; background: #e0f2fe; color: #075985; }\n");
+    html.push_str(".owner-badge.reviewer { background: #f1f5f9; color: #334155; }\n");
+    html.push_str(
+        ".output-info { display: flex; align-items: center; gap: 8px; margin: -12px 0 24px 0; flex-wrap: wrap; }\n",
+    );
+    html.push_str(".output-badge { display: inline-block; padding: 4px 10px; border-radius: 4px; font-size: 0.75rem; font-weight: 600; background: #f1f5f9; color: #334155; font-family: 'Monaco', 'Menlo', monospace; }\n");
+    html.push_str(
+        ".auth-info { display: flex; align-items: center; gap: 8px; margin: -12px 0 24px 0; flex-wrap: wrap; }\n",
+    );
+    html.push_str(".auth-badge { display: inline-block; padding: 4px 10px; border-radius: 4px; font-size: 0.75rem; font-weight: 600; background: #fee2e2; color: #991b1b; }\n");
+    html.push_str(".row-filter { font-family: 'Monaco', 'Menlo', monospace; background: #fef2f2; border: 1px dashed #f87171; }\n");
+    html.push_str(
+        ".cache-info { display: flex; align-items: center; gap: 8px; margin: -12px 0 24px 0; flex-wrap: wrap; }\n",
+    );
+    html.push_str(".library-ref { margin-left: 6px; font-size: 0.75rem; color: #6b21a8; }\n");
+    html.push_str(".cache-badge { display: inline-block; padding: 4px 10px; border-radius: 4px; font-size: 0.75rem; font-weight: 600; background: #ecfccb; color: #3f6212; }\n");
     html.push_str(".tabs { display: flex; gap: 4px; margin-top: 0; }\n");
     html.push_str(".tab { display: flex; align-items: center; gap: 8px; padding: 12px 24px; background: transparent; color: #6b7280; border: none; border-bottom: 3px solid transparent; cursor: pointer; font-size: 0.95rem; font-weight: 500; transition: all 0.2s; }\n");
     html.push_str(".tab:hover { color: #1f2937; background: #f9fafb; }\n");
     html.push_str(".tab.active { color: #3b82f6; border-bottom-color: #3b82f6; }\n");
     html.push_str(".tab i { font-size: 1.1rem; }\n");
+    html.push_str(".tab:focus-visible, .card:focus-visible { outline: 3px solid #2563eb; outline-offset: 2px; }\n");
     html.push_str(".main-content { padding: 32px 32px 80px 32px; background: #fff; min-height: calc(100vh - 180px); }\n");
     html.push_str(".view { display: none; }\n");
     html.push_str(".view.active { display: block; }\n");
@@ -85,17 +385,16 @@ pub fn generate_html(doc: &UsmlDocument) -> String {
     html.push_str(".response-card { background: #e8f4fd; }\n");
     html.push_str(".join-card { background: #fff8e1; }\n");
     html.push_str(".table-card { background: #f0faf0; }\n");
+    html.push_str(".const-card { background: #fffbeb; border-left: 3px dashed #d97706; }\n");
     html.push_str(
         ".badge { display: inline-block; background: #6c757d; color: #fff; border-radius: 999px; font-size: 0.72rem; padding: 2px 8px; margin-right: 4px; }\n",
     );
     html.push_str(".field-name { font-weight: 600; margin-bottom: 6px; }\n");
     html.push_str(".field-name.small { font-weight: 500; font-size: 0.9rem; color: #394150; }\n");
     html.push_str(".join-line, .transform-line { font-size: 0.9rem; margin-top: 4px; }\n");
+    html.push_str(".field-value { font-family: 'Monaco', 'Menlo', monospace; font-size: 0.85rem; color: #92400e; margin-bottom: 4px; }\n");
     html.push_str(".empty { color: #6b7280; font-size: 0.9rem; }\n");
-    html.push_str(".depth-1 { margin-left: 24px; padding-left: 12px; border-left: 3px solid #3b82f6; background: #dbeafe !important; }\n");
-    html.push_str(".depth-2 { margin-left: 48px; padding-left: 12px; border-left: 3px solid #8b5cf6; background: #e9d5ff !important; }\n");
-    html.push_str(".depth-3 { margin-left: 72px; padding-left: 12px; border-left: 3px solid #ec4899; background: #fce7f3 !important; }\n");
-    html.push_str(".depth-4 { margin-left: 96px; padding-left: 12px; border-left: 3px solid #f59e0b; background: #fef3c7 !important; }\n");
+    write_depth_styles(html, max_depth)?;
     html.push_str("#flow-container { position: relative; }\n");
     html.push_str("#flow-svg { position: absolute; top: 0; left: 0; width: 100%; height: 100%; pointer-events: none; z-index: 10; }\n");
     html.push_str(".arrow-simple { stroke: #9ca3af; }\n");
@@ -119,23 +418,47 @@ pub fn generate_html(doc: &UsmlDocument) -> String {
     html.push_str("tbody tr:hover { background: #f9fafb; }\n");
     html.push_str(".table-section { margin-bottom: 32px; }\n");
     html.push_str(".table-section h2 { font-size: 1.3rem; margin-bottom: 16px; }\n");
-    html.push_str(".indent-1 { padding-left: 32px; background: #eff6ff; }\n");
-    html.push_str(".indent-2 { padding-left: 48px; background: #f3e8ff; }\n");
-    html.push_str(".indent-3 { padding-left: 64px; background: #fce7f3; }\n");
-    html.push_str(".indent-4 { padding-left: 80px; background: #fef3c7; }\n");
+    write_indent_styles(html, max_depth)?;
     html.push_str("code.inline { background: #e5e7eb; padding: 2px 6px; border-radius: 4px; font-size: 0.9em; }\n");
+    html.push_str(
+        ".lineage-filters { display: flex; gap: 16px; margin-bottom: 16px; font-size: 0.9rem; }\n",
+    );
+    html.push_str(".lineage-filters label { display: flex; align-items: center; gap: 6px; cursor: pointer; }\n");
+    html.push_str(".lineage-svg { font-family: 'Monaco', 'Menlo', monospace; font-size: 12px; }\n");
+    html.push_str(".lineage-node rect { fill: #e8f4fd; stroke: #93c5fd; }\n");
+    html.push_str(".lineage-node.column-node rect { fill: #f0faf0; stroke: #86efac; }\n");
+    html.push_str(".lineage-node { outline: none; }\n");
+    html.push_str(".lineage-node:focus-visible rect { stroke: #2563eb; stroke-width: 3; }\n");
+    html.push_str(
+        ".lineage-node.highlighted rect { fill: #fef3c7; stroke: #fbbf24; stroke-width: 2; }\n",
+    );
+    html.push_str(".lineage-edge { fill: none; stroke-width: 1.5; opacity: 0.7; }\n");
+    html.push_str(".edge-source { stroke: #3b82f6; }\n");
+    html.push_str(".edge-transform { stroke: #10b981; stroke-dasharray: 5 3; }\n");
+    html.push_str(".edge-aggregate { stroke: #8b5cf6; }\n");
+    html.push_str(".legend-line.edge-source { background: #3b82f6; }\n");
+    html.push_str(".legend-line.edge-transform { background: #10b981; }\n");
+    html.push_str(".legend-line.edge-aggregate { background: #8b5cf6; }\n");
+    html.push_str(".lineage-svg.focused .lineage-edge { opacity: 0.1; }\n");
+    html.push_str(
+        ".lineage-svg.focused .lineage-edge.highlighted { opacity: 1; stroke-width: 3; }\n",
+    );
+    html.push_str(".lineage-svg.hide-source .edge-source, .lineage-svg.hide-transform .edge-transform, .lineage-svg.hide-aggregate .edge-aggregate { display: none; }\n");
+    html.push_str(".merged { display: grid; grid-template-columns: 2fr 1fr; gap: 24px; align-items: start; }\n");
+    html.push_str(".grid.two { grid-template-columns: repeat(2, 1fr); }\n");
+    html.push_str(".usecase-info h2 { font-size: 1.3rem; margin: 0 0 8px 0; }\n");
+    html.push_str(".table-card.inactive { opacity: 0.4; }\n");
+    html.push_str("table.usage { margin-top: 8px; box-shadow: none; font-size: 0.85rem; }\n");
+    html.push_str("table.usage th, table.usage td { padding: 4px 8px; text-align: center; }\n");
+    html.push_str("table.usage th:first-child, table.usage td:first-child { text-align: left; }\n");
     html.push_str("</style>\n</head>\n<body>\n");
+    Ok(())
+}
 
-    // ヘッダー
-    html.push_str("<div class=\"header\">\n");
-    write!(&mut html, "<h1>{}</h1>", escape_html(&doc.usecase.name)).unwrap();
+/// ヘッダーの usecase の説明（summary・API・タグ・責任者・クエリ・権限・キャッシュ）
+fn write_usecase_info(html: &mut String, doc: &UsmlDocument) -> fmt::Result {
     if let Some(summary) = &doc.usecase.summary {
-        write!(
-            &mut html,
-            "<p class=\"summary\">{}</p>",
-            escape_html(summary)
-        )
-        .unwrap();
+        write!(html, "<p class=\"summary\">{}</p>", escape_html(summary))?;
     }
 
     // OpenAPI情報を表示
@@ -156,82 +479,226 @@ pub fn generate_html(doc: &UsmlDocument) -> String {
             _ => "method-get",
         };
         write!(
-            &mut html,
+            html,
             "<span class=\"method-badge {}\">{}</span>",
             method_class,
             escape_html(&method_upper)
-        )
-        .unwrap();
+        )?;
 
         // APIパス
         write!(
-            &mut html,
+            html,
             "<span class=\"api-path\">{}</span>",
             escape_html(path)
-        )
-        .unwrap();
+        )?;
 
         // ステータスコード
         write!(
-            &mut html,
+            html,
             "<span class=\"status-badge\">Status: {}</span>",
             escape_html(status)
-        )
-        .unwrap();
+        )?;
 
         html.push_str("</div>\n");
     }
 
-    html.push_str("<div class=\"tabs\">\n");
-    html.push_str("<button class=\"tab active\" onclick=\"switchView('table', event)\"><i class=\"fas fa-table\"></i> テーブル</button>\n");
-    html.push_str("<button class=\"tab\" onclick=\"switchView('visual', event)\"><i class=\"fas fa-project-diagram\"></i> ビジュアル</button>\n");
-    html.push_str("</div></div>\n");
+    let usecase = &doc.usecase;
+    if !usecase.tags.is_empty() {
+        html.push_str("<div class=\"tag-info\"><i class=\"fas fa-tags\" aria-hidden=\"true\"></i>");
+        for tag in &usecase.tags {
+            write!(
+                html,
+                "<span class=\"tag-badge\">{}</span>",
+                escape_html(tag)
+            )?;
+        }
+        html.push_str("</div>\n");
+    }
 
-    // メインコンテンツ
-    html.push_str("<div class=\"main-content\">\n");
+    // データアクセスの責任者とレビュー担当
+    if usecase.owner.is_some() || !usecase.reviewers.is_empty() {
+        html.push_str(
+            "<div class=\"owner-info\"><i class=\"fas fa-user-shield\" aria-hidden=\"true\"></i>",
+        );
+        if let Some(owner) = &usecase.owner {
+            write!(
+                html,
+                "<span class=\"owner-badge\">owner: {}</span>",
+                escape_html(owner)
+            )?;
+        }
+        if !usecase.reviewers.is_empty() {
+            write!(
+                html,
+                "<span class=\"owner-badge reviewer\">review: {}</span>",
+                escape_html(&usecase.reviewers.join(", "))
+            )?;
+        }
+        html.push_str("</div>\n");
+    }
 
-    // ビジュアルビュー
-    html.push_str("<div id=\"visual-view\" class=\"view\">\n");
-    html.push_str("<div class=\"grid\">\n");
+    // USML ファイルで指定した出力ファイル名
+    if let Some(output) = &usecase.output {
+        writeln!(
+            html,
+            "<div class=\"output-info\"><i class=\"fas fa-file-export\" aria-hidden=\"true\"></i>\
+             <span class=\"output-badge\">output: {}</span></div>",
+            escape_html(output)
+        )?;
+    }
 
+    // クエリ全体に掛かる DISTINCT・LIMIT
+    let mut query_badges = Vec::new();
+    if usecase.distinct {
+        query_badges.push("DISTINCT".to_string());
+    }
+    if let Some(limit) = usecase.limit {
+        query_badges.push(format!("LIMIT {}", limit));
+    }
+    if let Some(default_limit) = usecase.default_limit {
+        query_badges.push(format!("既定 LIMIT {}", default_limit));
+    }
+    if !query_badges.is_empty() {
+        html.push_str("<div class=\"query-info\">");
+        for badge in &query_badges {
+            write!(
+                html,
+                "<span class=\"query-badge\">{}</span>",
+                escape_html(badge)
+            )?;
+        }
+        html.push_str("</div>\n");
+    }
+
+    // 必要な権限と常に掛かる行レベルの条件
+    if let Some(authorization) = &usecase.authorization {
+        let badges = authorization
+            .scopes
+            .iter()
+            .map(|scope| ("auth-badge", format!("scope: {}", scope)))
+            .chain(
+                authorization
+                    .roles
+                    .iter()
+                    .map(|role| ("auth-badge", format!("role: {}", role))),
+            )
+            .chain(
+                authorization
+                    .row_filters
+                    .iter()
+                    .map(|condition| ("auth-badge row-filter", condition.clone())),
+            );
+        html.push_str(
+            "<div class=\"auth-info\"><i class=\"fas fa-lock\" aria-hidden=\"true\"></i>",
+        );
+        for (class, label) in badges {
+            write!(
+                html,
+                "<span class=\"{}\">{}</span>",
+                class,
+                escape_html(&label)
+            )?;
+        }
+        html.push_str("</div>\n");
+    }
+
+    // レスポンスキャッシュ
+    if let Some(cache) = &usecase.cache {
+        let mut badges = vec![format!("TTL {} 秒", cache.ttl)];
+        if !cache.key.is_empty() {
+            badges.push(format!("key: {}", cache.key.join(", ")));
+        }
+        if !cache.invalidated_by.is_empty() {
+            badges.push(format!("破棄: {}", cache.invalidated_by.join(", ")));
+        }
+        html.push_str(
+            "<div class=\"cache-info\"><i class=\"fas fa-database\" aria-hidden=\"true\"></i>",
+        );
+        for badge in &badges {
+            write!(
+                html,
+                "<span class=\"cache-badge\">{}</span>",
+                escape_html(badge)
+            )?;
+        }
+        html.push_str("</div>\n");
+    }
+    Ok(())
+}
+
+/// `(ビュー, アイコン, ラベル)` のタブ。最初のタブを選択状態にする
+fn write_tabs(html: &mut String, tabs: &[(&str, &str, &str)]) -> fmt::Result {
+    html.push_str("<div class=\"tabs\" role=\"tablist\" aria-label=\"表示の切り替え\">\n");
+    for (i, (view, icon, label)) in tabs.iter().enumerate() {
+        // 矢印キーでタブを移動するため、選択中のタブだけを Tab キーで止まる位置にする
+        let selected = i == 0;
+        writeln!(
+            html,
+            "<button class=\"tab{}\" id=\"tab-{}\" role=\"tab\" aria-selected=\"{}\" aria-controls=\"{}-view\" tabindex=\"{}\" onclick=\"switchView('{}')\"><i class=\"fas {}\" aria-hidden=\"true\"></i> {}</button>",
+            if selected { " active" } else { "" },
+            view,
+            selected,
+            view,
+            if selected { 0 } else { -1 },
+            view,
+            icon,
+            escape_html(label)
+        )?;
+    }
+    html.push_str("</div>"); // tablist の終了
+    Ok(())
+}
+
+fn write_response_column(html: &mut String, entries: &[FieldEntry]) -> fmt::Result {
     html.push_str("<div class=\"column\">\n<h2>Response Fields</h2>\n");
     if entries.is_empty() {
         html.push_str("<div class=\"empty\">No response mappings.</div>");
     } else {
-        for entry in &entries {
+        html.push_str("<div class=\"nav-group\" role=\"list\" aria-label=\"Response Fields\">\n");
+        for entry in entries {
             let depth_class = depth_class(entry.depth);
+            let const_class = if entry.value.is_some() {
+                " const-card"
+            } else {
+                ""
+            };
             write!(
-                &mut html,
-                "<div class=\"card response-card{}\" data-field=\"{}\" data-tables=\"{}\" data-join-type=\"{}\">",
+                html,
+                "<div class=\"card response-card{}{}\" role=\"listitem\" tabindex=\"0\" data-field=\"{}\" data-tables=\"{}\" data-join-type=\"{}\">",
                 depth_class,
+                const_class,
                 escape_html(&entry.field_path),
                 escape_html(&entry.tables.join(",")),
                 escape_html(&entry.join_type)
-            )
-            .unwrap();
+            )?;
             write!(
-                &mut html,
+                html,
                 "<div class=\"field-name\">{}</div>",
                 escape_html(&entry.field)
-            )
-            .unwrap();
+            )?;
+            if let Some(value) = &entry.value {
+                write!(
+                    html,
+                    "<div class=\"field-value\">= {}</div>",
+                    escape_html(value)
+                )?;
+            }
             if !entry.badges.is_empty() {
                 html.push_str("<div>");
                 for badge in &entry.badges {
-                    write!(
-                        &mut html,
-                        "<span class=\"badge\">{}</span>",
-                        escape_html(badge)
-                    )
-                    .unwrap();
+                    write!(html, "<span class=\"badge\">{}</span>", escape_html(badge))?;
                 }
                 html.push_str("</div>");
             }
             html.push_str("</div>\n");
         }
+        html.push_str("</div>\n");
     }
     html.push_str("</div>\n");
+    Ok(())
+}
 
+fn write_joins_column(html: &mut String, entries: &[FieldEntry]) -> fmt::Result {
     html.push_str("<div class=\"column\">\n<h2>Joins &amp; Transforms</h2>\n");
     let has_joins_or_transforms = entries
         .iter()
@@ -239,7 +706,10 @@ pub fn generate_html(doc: &UsmlDocument) -> String {
     if !has_joins_or_transforms {
         html.push_str("<div class=\"empty\">No joins or transforms.</div>");
     } else {
-        for entry in &entries {
+        html.push_str(
+            "<div class=\"nav-group\" role=\"list\" aria-label=\"Joins &amp; Transforms\">\n",
+        );
+        for entry in entries {
             // JOINやtransformがない場合はスキップ
             if entry.join_lines.is_empty() && entry.transforms.is_empty() {
                 continue;
@@ -247,18 +717,16 @@ pub fn generate_html(doc: &UsmlDocument) -> String {
 
             let depth_class = depth_class(entry.depth);
             write!(
-                &mut html,
-                "<div class=\"card join-card{}\" data-field=\"{}\">",
+                html,
+                "<div class=\"card join-card{}\" role=\"listitem\" tabindex=\"0\" data-field=\"{}\">",
                 depth_class,
                 escape_html(&entry.field_path)
-            )
-            .unwrap();
+            )?;
             write!(
-                &mut html,
+                html,
                 "<div class=\"field-name small\">{}</div>",
                 escape_html(&entry.field)
-            )
-            .unwrap();
+            )?;
 
             // 種類バッジを追加
             let join_type_label = match entry.join_type.as_str() {
@@ -269,65 +737,62 @@ pub fn generate_html(doc: &UsmlDocument) -> String {
                 _ => "Simple",
             };
             write!(
-                &mut html,
+                html,
                 "<div style=\"margin-bottom: 6px;\"><span class=\"badge\">{}</span></div>",
                 join_type_label
-            )
-            .unwrap();
+            )?;
 
             for join_line in &entry.join_lines {
                 write!(
-                    &mut html,
+                    html,
                     "<div class=\"join-line\">{}</div>",
                     escape_html(join_line)
-                )
-                .unwrap();
+                )?;
             }
             if !entry.transforms.is_empty() {
                 html.push_str("<div class=\"transform-line\">Transforms:</div>");
                 html.push_str("<div>");
                 for transform in &entry.transforms {
                     write!(
-                        &mut html,
+                        html,
                         "<span class=\"badge\">{}</span>",
                         escape_html(transform)
-                    )
-                    .unwrap();
+                    )?;
                 }
                 html.push_str("</div>");
             }
             html.push_str("</div>\n");
         }
+        html.push_str("</div>\n");
     }
     html.push_str("</div>\n");
+    Ok(())
+}
 
+fn write_tables_column(html: &mut String, model: &Model) -> fmt::Result {
     html.push_str("<div class=\"column\">\n<h2>Tables</h2>\n");
-    if table_ctx.order.is_empty() {
+    if model.tables.is_empty() {
         html.push_str("<div class=\"empty\">No tables imported.</div>");
     } else {
-        for table in &table_ctx.order {
-            let columns = table_ctx.columns.get(table);
-            // エイリアスかどうかを判定
-            let display_name = if let Some(actual_table) = table_ctx.alias_map.get(table) {
-                format!(
+        html.push_str("<div class=\"nav-group\" role=\"list\" aria-label=\"Tables\">\n");
+        for table in &model.tables {
+            let display_name = match alias_label(table) {
+                Some(aliases) => format!(
                     "{} <span style=\"color: #6b7280; font-weight: 400;\">(as {})</span>",
-                    actual_table, table
-                )
-            } else {
-                table.clone()
+                    escape_html(&table.name),
+                    escape_html(&aliases)
+                ),
+                None => escape_html(&table.name),
             };
             write!(
-                &mut html,
-                "<div class=\"card table-card\" data-table=\"{}\"><div class=\"field-name\">{}</div>",
-                escape_html(table),
+                html,
+                "<div class=\"card table-card\" role=\"listitem\" tabindex=\"0\" data-table=\"{}\"><div class=\"field-name\">{}</div>",
+                escape_html(&table.name),
                 display_name
-            )
-            .unwrap();
+            )?;
 
-            if let Some(cols) = columns
-                && !cols.is_empty()
-            {
-                let mut sorted_cols: Vec<_> = cols.iter().collect();
+            if !table.columns.is_empty() {
+                let mut sorted_cols: Vec<_> = table.columns.iter().collect();
                 sorted_cols.sort();
                 html.push_str(
                     "<div class=\"join-line\">Columns:</div><div style=\"margin-top: 4px;\">",
@@ -336,7 +801,11 @@ pub fn generate_html(doc: &UsmlDocument) -> String {
                     if i > 0 {
                         html.push_str(", ");
                     }
-                    write!(&mut html, "<code style=\"background: #e5e7eb; padding: 2px 6px; border-radius: 4px; font-size: 0.85rem;\">{}</code>", escape_html(col)).unwrap();
+                    write!(
+                        html,
+                        "<code style=\"background: #e5e7eb; padding: 2px 6px; border-radius: 4px; font-size: 0.85rem;\">{}</code>",
+                        escape_html(col)
+                    )?;
                 }
                 html.push_str("</div>");
             } else {
@@ -344,278 +813,433 @@ pub fn generate_html(doc: &UsmlDocument) -> String {
             }
             html.push_str("</div>\n");
         }
+        html.push_str("</div>\n");
     }
-    html.push_str("</div>\n</div>\n</div>\n"); // column (Tables), grid, visual-view の終了
-
-    // テーブルビュー
-    html.push_str("<div id=\"table-view\" class=\"view active\">\n");
-    generate_table_view(&mut html, &entries, &table_ctx, doc);
     html.push_str("</div>\n");
+    Ok(())
+}
 
-    html.push_str("</div>\n"); // main-content の終了
-
-    // JavaScript for view switching
-    html.push_str(r#"<script>
-function switchView(viewName, event) {
+// 表示の切り替え・ハイライト・キーボード操作
+const SCRIPT: &str = r#"<script>
+function switchView(viewName) {
   document.querySelectorAll('.view').forEach(function(v) { v.classList.remove('active'); });
-  document.querySelectorAll('.tab').forEach(function(b) { b.classList.remove('active'); });
+  document.querySelectorAll('.tab').forEach(function(b) {
+    var selected = b.getAttribute('aria-controls') === viewName + '-view';
+    b.classList.toggle('active', selected);
+    b.setAttribute('aria-selected', selected ? 'true' : 'false');
+    b.tabIndex = selected ? 0 : -1;
+  });
   document.getElementById(viewName + '-view').classList.add('active');
-  if (event && event.target) {
-    event.target.classList.add('active');
-  }
+  // まとめた表示では、選択中の usecase が参照しないテーブルを薄くする
+  document.querySelectorAll('.table-card[data-usecases]').forEach(function(c) {
+    c.classList.toggle('inactive', c.dataset.usecases.split(',').indexOf(viewName) < 0);
+  });
 }
 
 (function() {
+  // マウスを乗せたときと同じハイライトを、キーボードでフォーカスしたときにも付ける
+  function onEnter(el, handler) {
+    el.addEventListener('mouseenter', handler);
+    el.addEventListener('focus', handler);
+  }
+  function onLeave(el, handler) {
+    el.addEventListener('mouseleave', handler);
+    el.addEventListener('blur', handler);
+  }
   function setupHover() {
     document.querySelectorAll('.response-card[data-field]').forEach(function(card) {
-      card.addEventListener('mouseenter', function() {
+      onEnter(card, function() {
         var field = card.dataset.field;
         var tables = (card.dataset.tables || '').split(',').filter(function(t) { return t.length > 0; });
         card.classList.add('highlighted');
-        document.querySelectorAll('.join-card[data-field="' + field + '"]').forEach(function(c) { c.classList.add('highlighted'); });
+        card.closest('.view').querySelectorAll('.join-card[data-field="' + field + '"]').forEach(function(c) { c.classList.add('highlighted'); });
         tables.forEach(function(t) {
           var tc = document.querySelector('.table-card[data-table="' + t + '"]');
           if (tc) tc.classList.add('highlighted');
         });
       });
-      card.addEventListener('mouseleave', function() {
+      onLeave(card, function() {
         document.querySelectorAll('.card').forEach(function(c) { c.classList.remove('highlighted'); });
       });
     });
   }
+  function setupLineage() {
+    var svg = document.querySelector('.lineage-svg');
+    if (!svg) return;
+    document.querySelectorAll('.lineage-filters input[data-kind]').forEach(function(input) {
+      input.addEventListener('change', function() {
+        svg.classList.toggle('hide-' + input.dataset.kind, !input.checked);
+      });
+    });
+    svg.querySelectorAll('.lineage-node').forEach(function(node) {
+      onEnter(node, function() {
+        var attr = node.dataset.field !== undefined ? 'data-field' : 'data-column';
+        var key = node.dataset.field !== undefined ? node.dataset.field : node.dataset.column;
+        svg.classList.add('focused');
+        node.classList.add('highlighted');
+        svg.querySelectorAll('.lineage-edge[' + attr + '="' + key + '"]').forEach(function(edge) {
+          edge.classList.add('highlighted');
+          svg.querySelectorAll('.lineage-node[data-field="' + edge.dataset.field + '"], .lineage-node[data-column="' + edge.dataset.column + '"]').forEach(function(n) { n.classList.add('highlighted'); });
+        });
+      });
+      onLeave(node, function() {
+        svg.classList.remove('focused');
+        svg.querySelectorAll('.highlighted').forEach(function(e) { e.classList.remove('highlighted'); });
+      });
+    });
+  }
+  // タブ: 左右の矢印・Home・End で移動して切り替える
+  function setupTabs() {
+    var tabs = Array.prototype.slice.call(document.querySelectorAll('.tab'));
+    tabs.forEach(function(tab, i) {
+      tab.addEventListener('keydown', function(e) {
+        var next = null;
+        if (e.key === 'ArrowRight') next = tabs[(i + 1) % tabs.length];
+        else if (e.key === 'ArrowLeft') next = tabs[(i + tabs.length - 1) % tabs.length];
+        else if (e.key === 'Home') next = tabs[0];
+        else if (e.key === 'End') next = tabs[tabs.length - 1];
+        if (!next) return;
+        e.preventDefault();
+        next.click();
+        next.focus();
+      });
+    });
+  }
+  // カード・ノードの一覧: Tab キーでは一覧ごとに 1 回だけ止まり、上下の矢印で一覧内を、
+  // 左右の矢印で隣の一覧（ハイライト中の要素があればそこ）へ移動する
+  function setupListNavigation() {
+    document.querySelectorAll('.nav-group').forEach(function(group) {
+      var items = Array.prototype.slice.call(group.querySelectorAll(':scope > [tabindex]'));
+      items.forEach(function(item, i) {
+        item.tabIndex = i === 0 ? 0 : -1;
+        item.addEventListener('focus', function() {
+          items.forEach(function(other) { other.tabIndex = other === item ? 0 : -1; });
+        });
+        item.addEventListener('keydown', function(e) {
+          var target = null;
+          if (e.key === 'ArrowDown') target = items[i + 1];
+          else if (e.key === 'ArrowUp') target = items[i - 1];
+          else if (e.key === 'Home') target = items[0];
+          else if (e.key === 'End') target = items[items.length - 1];
+          else if (e.key === 'ArrowRight' || e.key === 'ArrowLeft') {
+            var scope = group.closest('.nav-scope') || group.closest('.view');
+            var groups = Array.prototype.slice.call(scope.querySelectorAll('.nav-group')).filter(function(g) { return g.getClientRects().length > 0; });
+            var other = groups[groups.indexOf(group) + (e.key === 'ArrowRight' ? 1 : -1)];
+            if (other) target = other.querySelector(':scope > .highlighted[tabindex]') || other.querySelector(':scope > [tabindex]');
+          }
+          if (!target) return;
+          e.preventDefault();
+          target.focus();
+        });
+      });
+    });
+  }
   window.addEventListener('load', function() {
     setupHover();
+    setupLineage();
+    setupTabs();
+    setupListNavigation();
   });
 })();
 </script>
-"#);
-    html.push_str("</body>\n</html>\n");
-    html
-}
+"#;
+
+// リネージビューの 1 行の高さ・ノードの幅・図全体の幅
+const LINEAGE_ROW: usize = 32;
+const LINEAGE_NODE_WIDTH: usize = 240;
+const LINEAGE_WIDTH: usize = 880;
+
+/// 左にレスポンスフィールド、右にテーブル.カラムを並べ、依存を辺で結ぶ二部グラフ
+///
+/// 多対多の関係（複数カラムを合成するフィールド、複数フィールドが使うカラム）を一目で追えるように、
+/// 座標はここで決めて SVG に書き出す。辺は種類ごとに色分けし、チェックボックスで絞り込める
+fn write_lineage_view(html: &mut String, model: &Model) -> fmt::Result {
+    let edges = lineage::from_model(model);
+    if edges.is_empty() {
+        html.push_str("<div class=\"empty\">No lineage edges.</div>\n");
+        return Ok(());
+    }
+
+    let fields: Vec<&str> = model.fields.iter().map(|f| f.path.as_str()).collect();
+    let mut columns: Vec<String> = edges.iter().map(column_key).collect();
+    columns.sort();
+    columns.dedup();
 
-fn build_transform_map(transforms: &[Transform]) -> HashMap<String, Vec<String>> {
-    let mut map = HashMap::new();
-    for transform in transforms {
-        map.entry(transform.target.clone())
-            .or_insert_with(Vec::new)
-            .push(transform.r#type.clone());
+    html.push_str("<div class=\"lineage-filters\" role=\"group\" aria-label=\"表示する辺の種類\">");
+    for kind in [EdgeKind::Source, EdgeKind::Transform, EdgeKind::Aggregate] {
+        write!(
+            html,
+            "<label><input type=\"checkbox\" data-kind=\"{}\" checked><span class=\"legend-line {}\"></span>{}</label>",
+            edge_kind_name(kind),
+            edge_class(kind),
+            edge_kind_label(kind)
+        )?;
     }
-    map
+    html.push_str("</div>\n");
+
+    let height = fields.len().max(columns.len()) * LINEAGE_ROW + 8;
+    writeln!(
+        html,
+        "<svg class=\"lineage-svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\" role=\"group\" aria-label=\"フィールドとカラムの依存関係\">",
+        LINEAGE_WIDTH, height, LINEAGE_WIDTH, height
+    )?;
+    let left = LINEAGE_NODE_WIDTH;
+    let right = LINEAGE_WIDTH - LINEAGE_NODE_WIDTH;
+    let middle = (left + right) / 2;
+    for edge in &edges {
+        let Some(from) = fields.iter().position(|f| *f == edge.field) else {
+            continue;
+        };
+        let key = column_key(edge);
+        let Some(to) = columns.iter().position(|c| *c == key) else {
+            continue;
+        };
+        let (y1, y2) = (
+            from * LINEAGE_ROW + LINEAGE_ROW / 2 + 4,
+            to * LINEAGE_ROW + LINEAGE_ROW / 2 + 4,
+        );
+        writeln!(
+            html,
+            "<path class=\"lineage-edge {}\" data-field=\"{}\" data-column=\"{}\" d=\"M{},{} C{},{} {},{} {},{}\"><title>{} ← {} ({})</title></path>",
+            edge_class(edge.kind),
+            escape_html(&edge.field),
+            escape_html(&key),
+            left,
+            y1,
+            middle,
+            y1,
+            middle,
+            y2,
+            right,
+            y2,
+            escape_html(&edge.field),
+            escape_html(&key),
+            edge_kind_label(edge.kind)
+        )?;
+    }
+    // ノードは辺の上に描き、読み上げでは接続先を aria-label で伝える
+    html.push_str("<g class=\"nav-group\" role=\"list\" aria-label=\"Response Fields\">\n");
+    for (i, field) in fields.iter().enumerate() {
+        let mut sources: Vec<String> = edges
+            .iter()
+            .filter(|e| e.field == *field)
+            .map(column_key)
+            .collect();
+        sources.sort();
+        sources.dedup();
+        let description = if sources.is_empty() {
+            format!("{}（依存するカラムなし）", field)
+        } else {
+            format!("{} ← {}", field, sources.join(", "))
+        };
+        write_lineage_node(
+            html,
+            ("data-field", "field-node"),
+            field,
+            &description,
+            0,
+            i * LINEAGE_ROW + 8,
+        )?;
+    }
+    html.push_str("</g>\n<g class=\"nav-group\" role=\"list\" aria-label=\"Columns\">\n");
+    for (i, column) in columns.iter().enumerate() {
+        let mut fields: Vec<&str> = edges
+            .iter()
+            .filter(|e| column_key(e) == *column)
+            .map(|e| e.field.as_str())
+            .collect();
+        fields.sort();
+        fields.dedup();
+        write_lineage_node(
+            html,
+            ("data-column", "column-node"),
+            column,
+            &format!("{} → {}", column, fields.join(", ")),
+            right,
+            i * LINEAGE_ROW + 8,
+        )?;
+    }
+    html.push_str("</g>\n");
+    html.push_str("</svg>\n");
+    Ok(())
 }
 
-fn extract_import_tables(doc: &UsmlDocument) -> Vec<String> {
-    let mut tables = Vec::new();
-    let mut seen = HashSet::new();
-    if let Some(dbmls) = &doc.import.dbml {
-        for entry in dbmls {
-            if let Some(table) = extract_table_name(entry)
-                && seen.insert(table.clone())
-            {
-                tables.push(table);
-            }
-        }
+/// `(attr, class)` はノードを識別するデータ属性と種類のクラス
+fn write_lineage_node(
+    html: &mut String,
+    (attr, class): (&str, &str),
+    label: &str,
+    description: &str,
+    x: usize,
+    y: usize,
+) -> fmt::Result {
+    let label = escape_html(label);
+    writeln!(
+        html,
+        "<g class=\"lineage-node {}\" {}=\"{}\" role=\"listitem\" tabindex=\"0\" aria-label=\"{}\"><rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"24\" rx=\"6\"></rect><text x=\"{}\" y=\"{}\">{}</text></g>",
+        class,
+        attr,
+        label,
+        escape_html(description),
+        x,
+        y,
+        LINEAGE_NODE_WIDTH,
+        x + 10,
+        y + 16,
+        label
+    )
+}
+
+fn column_key(edge: &LineageEdge) -> String {
+    format!("{}.{}", edge.table, edge.column)
+}
+
+fn edge_kind_name(kind: EdgeKind) -> &'static str {
+    match kind {
+        EdgeKind::Source => "source",
+        EdgeKind::Transform => "transform",
+        EdgeKind::Aggregate => "aggregate",
     }
-    tables
 }
 
-fn extract_table_name(value: &str) -> Option<String> {
-    let marker = "#tables[\"";
-    let start = value.find(marker)? + marker.len();
-    let remainder = &value[start..];
-    let end = remainder.find("\"]")?;
-    Some(remainder[..end].to_string())
+fn edge_class(kind: EdgeKind) -> String {
+    format!("edge-{}", edge_kind_name(kind))
+}
+
+fn edge_kind_label(kind: EdgeKind) -> &'static str {
+    match kind {
+        EdgeKind::Source => "直接参照",
+        EdgeKind::Transform => "transform",
+        EdgeKind::Aggregate => "集約",
+    }
 }
 
-fn extract_table_identifier(value: &str) -> Option<String> {
-    let token = value.split_whitespace().next()?;
-    let table = token.split('.').next()?;
-    if table.is_empty() {
+/// エイリアスがあれば "a, b" の形で返す
+fn alias_label(table: &TableNode) -> Option<String> {
+    if table.aliases.is_empty() {
         None
     } else {
-        Some(table.to_string())
+        Some(table.aliases.join(", "))
     }
 }
 
-fn collect_entries(
-    mappings: &[ResponseMapping],
-    depth: usize,
-    parent_path: &str,
-    transform_map: &HashMap<String, Vec<String>>,
-    entries: &mut Vec<FieldEntry>,
-    table_ctx: &mut TableContext,
-) {
-    for mapping in mappings {
+fn collect_entries(model: &Model) -> Vec<FieldEntry> {
+    let mut entries = Vec::new();
+    for field in &model.fields {
         let mut badges = Vec::new();
-        if let Some(aggregate) = &mapping.aggregate {
-            badges.push(aggregate.r#type.clone());
+        if let Some(aggregate) = &field.aggregate {
+            badges.push(aggregate.clone());
+        }
+        if field.kind == FieldKind::Array {
+            // 要素を生成するテーブルが分かれば添える
+            badges.push(match &field.source_table {
+                Some(table) => format!("array: {}", table),
+                None => "array".to_string(),
+            });
         }
-        if mapping.r#type.as_deref() == Some("array") {
-            badges.push("array".to_string());
+        if field.collection {
+            badges.push("collection".to_string());
+        }
+        if let Some(envelope) = &field.envelope {
+            badges.push(envelope.clone());
         }
 
         let mut join_lines = Vec::new();
-        if let Some(join) = &mapping.join {
-            let join_type = join.r#type.as_deref().unwrap_or("JOIN");
-            let table_part = if let Some(alias) = &join.alias {
-                // エイリアスマッピングを記録
-                table_ctx
-                    .alias_map
-                    .insert(alias.clone(), join.table.clone());
-                format!("{} AS {}", join.table, alias)
-            } else {
-                join.table.clone()
+        let mut chain = Vec::new();
+        for join in model.joins_of(field) {
+            if join.chain_index.is_some() {
+                chain.push(format!("JOIN {} ON {}", join.table, join.on));
+                continue;
+            }
+            let table_part = match &join.alias {
+                Some(alias) => format!("{} AS {}", join.table, alias),
+                None => join.table.clone(),
             };
-            let line = format!("{} {} ON {}", join_type, table_part, join.on);
-            join_lines.push(line);
+            join_lines.push(format!(
+                "{} {} ON {}",
+                join.kind.as_sql(),
+                table_part,
+                join.on
+            ));
         }
-        if let Some(chain) = &mapping.join_chain
-            && !chain.is_empty()
-        {
-            let chain_line = chain
-                .iter()
-                .map(|entry| format!("JOIN {} ON {}", entry.table, entry.on))
-                .collect::<Vec<_>>()
-                .join(" → ");
-            join_lines.push(chain_line);
+        if !chain.is_empty() {
+            join_lines.push(chain.join(" → "));
         }
 
-        let join_type = if mapping.aggregate.is_some() {
-            "aggregate".to_string()
-        } else if mapping.join_chain.is_some() {
-            "join-chain".to_string()
-        } else if mapping.join.is_some() {
-            "join".to_string()
+        let join_type = if field.aggregate.is_some() {
+            "aggregate"
+        } else if field
+            .joins
+            .iter()
+            .any(|&i| model.joins[i].chain_index.is_some())
+        {
+            "join-chain"
+        } else if !field.joins.is_empty() {
+            "join"
         } else {
-            "simple".to_string()
+            "simple"
         };
 
-        let mut field_tables: Vec<String> = Vec::new();
-        if let Some(source) = &mapping.source
-            && let Some(table) = extract_table_identifier(source)
-            && !field_tables.contains(&table)
-        {
-            field_tables.push(table);
-        }
-        if let Some(join) = &mapping.join
-            && !field_tables.contains(&join.table)
-        {
-            field_tables.push(join.table.clone());
-        }
-        if let Some(chain) = &mapping.join_chain {
-            for entry in chain {
-                if !field_tables.contains(&entry.table) {
-                    field_tables.push(entry.table.clone());
-                }
+        let mut tables: Vec<String> = Vec::new();
+        let field_tables = field
+            .source
+            .iter()
+            .map(|source| &source.table)
+            .chain(model.joins_of(field).map(|join| &join.table));
+        for table in field_tables {
+            if !tables.contains(table) {
+                tables.push(table.clone());
             }
         }
 
-        let transforms = transform_map
-            .get(&mapping.field)
-            .cloned()
-            .unwrap_or_default();
-
-        // フルパスを構築（親がいる場合は "親.子" の形式）
-        let field_path = if parent_path.is_empty() {
-            mapping.field.clone()
-        } else {
-            format!("{}.{}", parent_path, mapping.field)
-        };
-
         entries.push(FieldEntry {
-            field: mapping.field.clone(),
-            field_path,
-            source: mapping.source.clone(),
+            field: field.name.clone(),
+            field_path: field.path.clone(),
+            source: field
+                .source
+                .as_ref()
+                .map(|s| format!("{}.{}", s.qualifier, s.column)),
+            value: field
+                .value
+                .as_ref()
+                .map(|v| v.to_string())
+                .or_else(|| field.param.as_ref().map(|p| format!(":{}", p))),
             badges,
             join_lines,
-            transforms,
-            depth,
-            tables: field_tables,
-            join_type,
+            transforms: field.transforms.iter().map(|t| t.kind.clone()).collect(),
+            depth: field.depth,
+            tables,
+            join_type: join_type.to_string(),
         });
-
-        // テーブルとカラムの情報を記録
-        if let Some(source) = &mapping.source
-            && let Some((table, column)) = source.split_once('.')
-        {
-            let table = table.to_string();
-            let column = column.to_string();
-
-            table_ctx
-                .columns
-                .entry(table.clone())
-                .or_default()
-                .insert(column);
-
-            if table_ctx.seen.insert(table.clone()) {
-                table_ctx.order.push(table);
-            }
-        }
-
-        // JOIN、JOIN chainのテーブルも記録（カラムなし）
-        if let Some(join) = &mapping.join {
-            let table = join.table.clone();
-            table_ctx.columns.entry(table.clone()).or_default();
-            if table_ctx.seen.insert(table.clone()) {
-                table_ctx.order.push(table);
-            }
-        }
-
-        if let Some(chain) = &mapping.join_chain {
-            for entry in chain {
-                let table = entry.table.clone();
-                table_ctx.columns.entry(table.clone()).or_default();
-                if table_ctx.seen.insert(table.clone()) {
-                    table_ctx.order.push(table);
-                }
-            }
-        }
-
-        if let Some(fields) = &mapping.fields {
-            // 親パスを現在のフィールドパスに更新して再帰
-            let current_field_path = if parent_path.is_empty() {
-                mapping.field.clone()
-            } else {
-                format!("{}.{}", parent_path, mapping.field)
-            };
-            collect_entries(
-                fields,
-                depth + 1,
-                &current_field_path,
-                transform_map,
-                entries,
-                table_ctx,
-            );
-        }
     }
+    entries
 }
 
 fn depth_class(depth: usize) -> String {
     if depth == 0 {
         String::new()
     } else {
-        format!(" depth-{}", depth.min(4))
+        format!(" depth-{}", depth)
     }
 }
 
 fn generate_table_view(
     html: &mut String,
     entries: &[FieldEntry],
-    table_ctx: &TableContext,
+    model: &Model,
     doc: &UsmlDocument,
-) {
+) -> fmt::Result {
     // Response Mapping Table
     html.push_str("<div class=\"table-section\"><h2>Response Mapping</h2>\n");
-    html.push_str("<table><thead><tr><th>Field</th><th>Source</th><th>Type</th><th>JOIN</th><th>Transforms</th></tr></thead><tbody>\n");
+    html.push_str("<table><thead><tr><th scope=\"col\">Field</th><th scope=\"col\">Source</th><th scope=\"col\">Type</th><th scope=\"col\">JOIN</th><th scope=\"col\">Transforms</th></tr></thead><tbody>\n");
 
     for entry in entries {
-        let indent_class = match entry.depth {
-            1 => " class=\"indent-1\"",
-            2 => " class=\"indent-2\"",
-            3 => " class=\"indent-3\"",
-            4 => " class=\"indent-4\"",
-            _ => "",
+        let indent_class = if entry.depth > 0 {
+            format!(" class=\"indent-{}\"", entry.depth)
+        } else {
+            String::new()
         };
-        write!(html, "<tr{}>", indent_class).unwrap();
+        write!(html, "<tr{}>", indent_class)?;
 
         // フィールド名にインデント表現を追加
         let field_display = if entry.depth > 0 {
@@ -628,18 +1252,19 @@ fn generate_table_view(
             html,
             "<td><code class=\"inline\">{}</code></td>",
             escape_html(&field_display)
-        )
-        .unwrap();
+        )?;
 
         // Source - mapping.sourceまたはtables列から推定
         let source = if let Some(src) = &entry.source {
             src.clone()
+        } else if let Some(value) = &entry.value {
+            format!("= {}", value)
         } else if !entry.tables.is_empty() {
             entry.tables.join(", ")
         } else {
             "-".to_string()
         };
-        write!(html, "<td>{}</td>", escape_html(&source)).unwrap();
+        write!(html, "<td>{}</td>", escape_html(&source))?;
 
         // Type - badges
         let type_str = if !entry.badges.is_empty() {
@@ -647,7 +1272,7 @@ fn generate_table_view(
         } else {
             "-".to_string()
         };
-        write!(html, "<td>{}</td>", escape_html(&type_str)).unwrap();
+        write!(html, "<td>{}</td>", escape_html(&type_str))?;
 
         // JOIN
         let join_str = if !entry.join_lines.is_empty() {
@@ -655,7 +1280,7 @@ fn generate_table_view(
         } else {
             "-".to_string()
         };
-        write!(html, "<td>{}</td>", join_str).unwrap();
+        write!(html, "<td>{}</td>", join_str)?;
 
         // Transforms
         let transform_str = if !entry.transforms.is_empty() {
@@ -668,7 +1293,7 @@ fn generate_table_view(
         } else {
             "-".to_string()
         };
-        write!(html, "<td>{}</td>", transform_str).unwrap();
+        write!(html, "<td>{}</td>", transform_str)?;
 
         html.push_str("</tr>\n");
     }
@@ -677,32 +1302,28 @@ fn generate_table_view(
 
     // Tables Summary
     html.push_str("<div class=\"table-section\"><h2>Tables Summary</h2>\n");
-    html.push_str("<table><thead><tr><th>Table</th><th>Columns</th></tr></thead><tbody>\n");
+    html.push_str("<table><thead><tr><th scope=\"col\">Table</th><th scope=\"col\">Columns</th></tr></thead><tbody>\n");
 
-    for table in &table_ctx.order {
-        // エイリアスかどうかを判定
-        let display_name = if let Some(actual_table) = table_ctx.alias_map.get(table) {
-            format!(
+    for table in &model.tables {
+        let display_name = match alias_label(table) {
+            Some(aliases) => format!(
                 "<strong>{}</strong> <span style=\"color: #6b7280; font-weight: 400;\">(as {})</span>",
-                escape_html(actual_table),
-                escape_html(table)
-            )
-        } else {
-            format!("<strong>{}</strong>", escape_html(table))
+                escape_html(&table.name),
+                escape_html(&aliases)
+            ),
+            None => format!("<strong>{}</strong>", escape_html(&table.name)),
         };
-        write!(html, "<tr><td>{}</td>", display_name).unwrap();
+        write!(html, "<tr><td>{}</td>", display_name)?;
 
-        if let Some(cols) = table_ctx.columns.get(table)
-            && !cols.is_empty()
-        {
-            let mut sorted_cols: Vec<_> = cols.iter().collect();
+        if !table.columns.is_empty() {
+            let mut sorted_cols: Vec<_> = table.columns.iter().collect();
             sorted_cols.sort();
             let cols_html = sorted_cols
                 .iter()
                 .map(|c| format!("<code class=\"inline\">{}</code>", escape_html(c)))
                 .collect::<Vec<_>>()
                 .join(", ");
-            write!(html, "<td>{}</td>", cols_html).unwrap();
+            write!(html, "<td>{}</td>", cols_html)?;
         } else {
             html.push_str("<td style=\"color: #9ca3af;\">No columns referenced</td>");
         }
@@ -715,21 +1336,20 @@ fn generate_table_view(
     // Filters Summary
     if !doc.usecase.filters.is_empty() {
         html.push_str("<div class=\"table-section\"><h2>Filters</h2>\n");
-        html.push_str("<table><thead><tr><th>Parameter</th><th>Maps To</th><th>Details</th></tr></thead><tbody>\n");
+        html.push_str("<table><thead><tr><th scope=\"col\">Parameter</th><th scope=\"col\">Maps To</th><th scope=\"col\">Details</th></tr></thead><tbody>\n");
 
         for filter in &doc.usecase.filters {
             write!(
                 html,
                 "<tr><td><code class=\"inline\">{}</code></td>",
                 escape_html(&filter.param)
-            )
-            .unwrap();
+            )?;
             write!(
                 html,
-                "<td><strong>{}</strong></td>",
-                escape_html(&filter.maps_to)
-            )
-            .unwrap();
+                "<td><strong>{}</strong>{}</td>",
+                escape_html(&filter.maps_to),
+                library_ref(filter.r#use.as_deref())
+            )?;
 
             let mut details = Vec::new();
             if let Some(condition) = &filter.condition {
@@ -756,7 +1376,7 @@ fn generate_table_view(
             } else {
                 details.join(", ")
             };
-            write!(html, "<td>{}</td>", details_html).unwrap();
+            write!(html, "<td>{}</td>", details_html)?;
 
             html.push_str("</tr>\n");
         }
@@ -767,21 +1387,20 @@ fn generate_table_view(
     // Transforms Summary
     if !doc.usecase.transforms.is_empty() {
         html.push_str("<div class=\"table-section\"><h2>Transforms</h2>\n");
-        html.push_str("<table><thead><tr><th>Target</th><th>Type</th><th>Sources</th><th>Details</th></tr></thead><tbody>\n");
+        html.push_str("<table><thead><tr><th scope=\"col\">Target</th><th scope=\"col\">Type</th><th scope=\"col\">Sources</th><th scope=\"col\">Details</th></tr></thead><tbody>\n");
 
         for transform in &doc.usecase.transforms {
             write!(
                 html,
                 "<tr><td><code class=\"inline\">{}</code></td>",
                 escape_html(&transform.target)
-            )
-            .unwrap();
+            )?;
             write!(
                 html,
-                "<td><strong>{}</strong></td>",
-                escape_html(&transform.r#type)
-            )
-            .unwrap();
+                "<td><strong>{}</strong>{}</td>",
+                escape_html(&transform.r#type),
+                library_ref(transform.r#use.as_deref())
+            )?;
 
             // Sources
             let sources_html = if let Some(sources) = &transform.sources {
@@ -795,7 +1414,7 @@ fn generate_table_view(
             } else {
                 "-".to_string()
             };
-            write!(html, "<td>{}</td>", sources_html).unwrap();
+            write!(html, "<td>{}</td>", sources_html)?;
 
             // Details
             let mut details = Vec::new();
@@ -816,19 +1435,55 @@ fn generate_table_view(
             {
                 details.push(format!("when: {} conditions", when.len()));
             }
+            if let Some(factor) = transform.factor {
+                details.push(format!("factor: <code class=\"inline\">{}</code>", factor));
+            }
+            if let Some(lookup) = &transform.lookup {
+                details.push(format!(
+                    "rate: <code class=\"inline\">{}</code>",
+                    escape_html(&lookup.rate)
+                ));
+            }
+            if let Some(scale) = transform.scale {
+                details.push(format!(
+                    "scale: {} ({})",
+                    scale,
+                    escape_html(transform.rounding.as_deref().unwrap_or("half_up"))
+                ));
+            }
+            if let Some(locale) = &transform.locale {
+                let mut chain = vec![format!(":{}", locale.param)];
+                chain.extend(locale.fallback.iter().cloned());
+                details.push(format!(
+                    "locale: <code class=\"inline\">{}</code>",
+                    escape_html(&chain.join(" → "))
+                ));
+            }
 
             let details_html = if details.is_empty() {
                 "-".to_string()
             } else {
                 details.join(", ")
             };
-            write!(html, "<td>{}</td>", details_html).unwrap();
+            write!(html, "<td>{}</td>", details_html)?;
 
             html.push_str("</tr>\n");
         }
 
         html.push_str("</tbody></table></div>\n");
     }
+    Ok(())
+}
+
+/// 共有ライブラリから展開した transform・filter に付ける参照元の表示
+fn library_ref(name: Option<&str>) -> String {
+    match name {
+        Some(name) => format!(
+            "<span class=\"library-ref\"><i class=\"fas fa-book\" aria-hidden=\"true\"></i> {}</span>",
+            escape_html(name)
+        ),
+        None => String::new(),
+    }
 }
 
 fn escape_html(value: &str) -> String {
@@ -843,7 +1498,10 @@ fn escape_html(value: &str) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::ast::{Aggregate, Import, Join, ResponseMapping, Transform, Usecase, UsmlDocument};
+    use crate::ast::{
+        Aggregate, Authorization, Cache, Import, Join, Literal, ResponseMapping, Transform,
+        Usecase, UsmlDocument,
+    };
 
     #[test]
     fn test_generate_html_contains_sections() {
@@ -852,30 +1510,76 @@ mod tests {
             import: Import {
                 openapi: None,
                 dbml: Some(vec!["./schema.dbml#tables[\"users\"]".to_string()]),
+                tables: Default::default(),
             },
             usecase: Usecase {
                 name: "Users".to_string(),
                 summary: None,
+                tags: Vec::new(),
+                owner: None,
+                reviewers: Vec::new(),
                 output: None,
-                response_mapping: vec![ResponseMapping {
-                    field: "id".to_string(),
-                    source: Some("users.id".to_string()),
-                    r#type: None,
-                    source_table: None,
-                    join: None,
-                    join_chain: None,
-                    aggregate: None,
-                    fields: None,
-                }],
+                response_mapping: vec![
+                    ResponseMapping {
+                        field: "id".to_string(),
+                        source: Some("users.id".to_string()),
+                        r#type: None,
+                        source_table: None,
+                        join: None,
+                        join_chain: None,
+                        aggregate: None,
+                        fields: None,
+                        collection: false,
+                        envelope: None,
+                        nullable: None,
+                        value: None,
+                        from_param: None,
+                        source_usecase: None,
+                        timezone: None,
+                    },
+                    ResponseMapping {
+                        field: "api_version".to_string(),
+                        source: None,
+                        r#type: None,
+                        source_table: None,
+                        join: None,
+                        join_chain: None,
+                        aggregate: None,
+                        fields: None,
+                        collection: false,
+                        envelope: None,
+                        nullable: None,
+                        value: Some(Literal::String("v2".to_string())),
+                        from_param: None,
+                        source_usecase: None,
+                        timezone: None,
+                    },
+                ],
                 filters: Vec::new(),
                 transforms: Vec::new(),
+                distinct: false,
+                limit: None,
+                default_limit: None,
+                authorization: None,
+                cache: None,
+                hydration: None,
             },
+            environments: Default::default(),
         };
 
         let html = generate_html(&doc);
         assert!(html.contains("Response Fields"));
         assert!(html.contains("Joins &amp; Transforms"));
         assert!(html.contains("Tables"));
+        assert!(html.contains("response-card const-card"));
+        assert!(html.contains("<td>= &quot;v2&quot;</td>"));
+        assert!(html.contains("<div class=\"tabs\" role=\"tablist\""));
+        assert!(html.contains(
+            "id=\"tab-table\" role=\"tab\" aria-selected=\"true\" aria-controls=\"table-view\" tabindex=\"0\""
+        ));
+        assert!(html.contains(
+            "<div class=\"card response-card\" role=\"listitem\" tabindex=\"0\" data-field=\"id\""
+        ));
     }
 
     #[test]
@@ -888,16 +1592,20 @@ mod tests {
                     "./schema.dbml#tables[\"users\"]".to_string(),
                     "./schema.dbml#tables[\"profiles\"]".to_string(),
                 ]),
+                tables: Default::default(),
             },
             usecase: Usecase {
                 name: "Profiles".to_string(),
                 summary: None,
-                output: None,
+                tags: vec!["profiles".to_string(), "team-core".to_string()],
+                owner: Some("core".to_string()),
+                reviewers: vec!["alice".to_string(), "bob".to_string()],
+                output: Some("profiles/list.html".to_string()),
                 response_mapping: vec![ResponseMapping {
                     field: "profile_count".to_string(),
                     source: Some("profiles.id".to_string()),
                     r#type: Some("array".to_string()),
-                    source_table: None,
+                    source_table: Some("profiles".to_string()),
                     join: Some(Join {
                         table: "profiles".to_string(),
                         on: "users.id = profiles.user_id".to_string(),
@@ -908,13 +1616,22 @@ mod tests {
                     aggregate: Some(Aggregate {
                         r#type: "COUNT".to_string(),
                         group_by: None,
+                        strategy: None,
                     }),
                     fields: None,
+                    collection: false,
+                    envelope: None,
+                    nullable: None,
+                    value: None,
+                    from_param: None,
+                    source_usecase: None,
+                    timezone: None,
                 }],
                 filters: Vec::new(),
                 transforms: vec![Transform {
                     target: "profile_count".to_string(),
                     r#type: "COALESCE".to_string(),
+                    r#use: Some("zero_if_null".to_string()),
                     source: None,
                     sources: None,
                     fallback: None,
@@ -925,15 +1642,220 @@ mod tests {
                     condition: None,
                     then_source: None,
                     else_source: None,
+                    factor: None,
+                    lookup: None,
+                    rounding: None,
+                    scale: None,
+                    locale: None,
                 }],
+                distinct: true,
+                limit: Some(100),
+                default_limit: None,
+                authorization: Some(Authorization {
+                    scopes: vec!["profiles:read".to_string()],
+                    roles: Vec::new(),
+                    row_filters: vec!["users.id = :current_user_id".to_string()],
+                }),
+                cache: Some(Cache {
+                    ttl: 300,
+                    key: vec!["user_id".to_string()],
+                    invalidated_by: vec!["profiles".to_string()],
+                }),
+                hydration: None,
             },
+            environments: Default::default(),
         };
 
         let html = generate_html(&doc);
         assert!(html.contains("LEFT JOIN profiles ON users.id = profiles.user_id"));
         assert!(html.contains("COUNT"));
-        assert!(html.contains("array"));
+        assert!(html.contains("array: profiles"));
+        assert!(html.contains("<span class=\"output-badge\">output: profiles/list.html</span>"));
         assert!(html.contains("COALESCE"));
+        assert!(
+            html.contains("<i class=\"fas fa-book\" aria-hidden=\"true\"></i> zero_if_null</span>")
+        );
         assert!(html.contains("profiles"));
+        assert!(html.contains("<span class=\"query-badge\">DISTINCT</span>"));
+        assert!(html.contains("<span class=\"query-badge\">LIMIT 100</span>"));
+        assert!(html.contains("<span class=\"auth-badge\">scope: profiles:read</span>"));
+        assert!(html.contains("<span class=\"cache-badge\">TTL 300 秒</span>"));
+        assert!(html.contains("<span class=\"tag-badge\">team-core</span>"));
+        assert!(html.contains("<span class=\"owner-badge\">owner: core</span>"));
+        assert!(html.contains("<span class=\"owner-badge reviewer\">review: alice, bob</span>"));
+        assert!(
+            html.contains(
+                "<span class=\"auth-badge row-filter\">users.id = :current_user_id</span>"
+            )
+        );
+    }
+
+    #[test]
+    fn test_generate_html_lineage_view() {
+        let yaml = r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["users"]
+    - ./schema.dbml#tables["profiles"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: id
+      source: users.id
+    - field: display_name
+      source: profiles.display_name
+      join:
+        table: profiles
+        on: users.id = profiles.user_id
+    - field: profile_count
+      source: profiles.id
+      aggregate:
+        type: COUNT
+  transforms:
+    - target: display_name
+      type: COALESCE
+      sources: [profiles.display_name, users.name]
+"#;
+        let doc = crate::parser::parse(yaml).unwrap();
+        let html = generate_html(&doc);
+        assert!(html.contains("switchView('lineage')"));
+        assert!(html.contains("<input type=\"checkbox\" data-kind=\"transform\" checked>"));
+        assert!(html.contains(
+            "class=\"lineage-edge edge-transform\" data-field=\"display_name\" data-column=\"users.name\""
+        ));
+        assert!(html.contains(
+            "class=\"lineage-edge edge-aggregate\" data-field=\"profile_count\" data-column=\"profiles.id\""
+        ));
+        // カラムは重複なしで並び、読み上げ用に接続先を aria-label に持つ
+        assert_eq!(
+            html.matches("data-column=\"profiles.display_name\" role=\"listitem\"")
+                .count(),
+            1
+        );
+        assert!(html.contains(
+            "tabindex=\"0\" aria-label=\"display_name ← profiles.display_name, users.name\""
+        ));
+        assert!(html.contains("aria-label=\"users.name → display_name\""));
+    }
+
+    #[test]
+    fn test_deep_nesting_keeps_indenting() {
+        let mut yaml = String::from(
+            "version: \"0.1\"\nimport:\n  dbml:\n    - ./schema.dbml#tables[\"t\"]\nusecase:\n  name: Deep\n  response_mapping:\n",
+        );
+        let mut indent = "    ".to_string();
+        for depth in 0..6 {
+            yaml.push_str(&format!(
+                "{0}- field: f{1}\n{0}  type: object\n{0}  fields:\n",
+                indent, depth
+            ));
+            indent.push_str("    ");
+        }
+        yaml.push_str(&format!(
+            "{}- field: leaf\n{}  source: t.id\n",
+            indent, indent
+        ));
+        let html = generate_html(&crate::parser::parse(&yaml).unwrap());
+
+        // 5 段目より深くても字下げを続け、色は先頭から繰り返す
+        assert!(html.contains(
+            ".depth-6 { margin-left: 144px; padding-left: 12px; border-left: 3px solid #8b5cf6;"
+        ));
+        assert!(html.contains(".indent-6 { padding-left: 112px; background: #f3e8ff; }"));
+        assert!(!html.contains(".depth-7"));
+        assert!(html.contains("data-field=\"f0.f1.f2.f3.f4.f5.leaf\""));
+        assert!(html.contains("<tr class=\"indent-6\">"));
+    }
+
+    #[test]
+    fn test_generate_merged_html_shares_tables() {
+        let list = crate::parser::parse(
+            r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: 一覧
+  response_mapping:
+    - field: id
+      source: users.id
+    - field: name
+      source: users.name
+    - field: email
+      source: users.email
+"#,
+        )
+        .unwrap();
+        let feed = crate::parser::parse(
+            r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["posts"]
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: フィード
+  response_mapping:
+    - field: title
+      source: posts.title
+    - field: author
+      source: users.name
+      join:
+        table: users
+        on: posts.user_id = users.id
+"#,
+        )
+        .unwrap();
+
+        let html = generate_merged_html(&[&list, &feed]);
+        // 両方が参照するテーブルを見出しにし、usecase ごとにタブを作る
+        assert!(html.contains("<h1>users</h1>"));
+        assert!(html.contains("aria-controls=\"uc0-view\" tabindex=\"0\""));
+        assert!(html.contains("aria-controls=\"uc1-view\" tabindex=\"-1\""));
+        assert!(html.contains("<div id=\"uc1-view\" class=\"view\""));
+        // テーブルは 1 つにまとめ、最初のタブが参照しないものは薄く表示する
+        assert_eq!(
+            html.matches("data-table=\"users\" data-usecases").count(),
+            1
+        );
+        assert!(html.contains("data-table=\"users\" data-usecases=\"uc0,uc1\""));
+        assert!(html.contains(
+            "<div class=\"card table-card inactive\" role=\"listitem\" tabindex=\"0\" data-table=\"posts\" data-usecases=\"uc1\""
+        ));
+        // カラムごとに参照する usecase に印を付ける
+        assert!(
+            html.contains(
+                "<tr><td><code class=\"inline\">email</code></td><td>✓</td><td></td></tr>"
+            )
+        );
+        assert!(
+            html.contains(
+                "<tr><td><code class=\"inline\">name</code></td><td>✓</td><td>✓</td></tr>"
+            )
+        );
+    }
+
+    #[test]
+    fn test_generate_merged_html_escapes_usecase_names() {
+        let doc = crate::parser::parse(
+            r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: "<script>alert(1)</script>"
+  response_mapping:
+    - field: id
+      source: users.id
+"#,
+        )
+        .unwrap();
+
+        let html = generate_merged_html(&[&doc, &doc]);
+        assert!(!html.contains("<script>alert(1)</script>"));
+        assert!(html.contains("</i> 1. &lt;script&gt;alert(1)&lt;/script&gt;</button>"));
     }
 }
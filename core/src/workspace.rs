@@ -0,0 +1,495 @@
+//! 複数の USML ファイルをまとめて扱うワークスペース
+//!
+//! `source_usecase` による usecase 間の参照を usecase.name で解決し、
+//! 参照先の存在・循環の確認と、参照先を含めたリネージの合成を行う。
+//! `library.usml.yaml` は共有ライブラリとして読み込み、各 usecase の `use` を展開する
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use crate::ast::{Library, UsmlDocument};
+use crate::diagnostic::{Diagnostic, Location};
+use crate::error::{Result, UsmlError};
+use crate::library::LIBRARY_FILE;
+use crate::lineage::{self, LineageEdge};
+use crate::model::Model;
+use crate::parser;
+use crate::resolver::Limits;
+use crate::rules;
+use crate::validator::ValidationError;
+
+/// ワークスペースとして読み込むファイルの拡張子
+pub const EXTENSION: &str = ".usml.yaml";
+
+/// ワークスペース内の 1 ファイル
+#[derive(Debug)]
+pub struct WorkspaceDocument {
+    pub path: String,
+    pub document: UsmlDocument,
+}
+
+/// usecase.name で互いに参照できるドキュメントの集まり
+#[derive(Debug, Default)]
+pub struct Workspace {
+    documents: Vec<WorkspaceDocument>,
+    library: Library,
+    limits: Limits,
+}
+
+impl Workspace {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `dir` 以下の `*.usml.yaml` をパス順に再帰的に読み込む
+    ///
+    /// `library.usml.yaml` は共有ライブラリとしてまとめ、他のドキュメントの `use` を展開する
+    pub fn load(dir: &Path) -> Result<Self> {
+        let mut paths = Vec::new();
+        collect_files(dir, &mut paths)?;
+        paths.sort();
+        let mut workspace = Workspace::new();
+        let (libraries, documents): (Vec<_>, Vec<_>) =
+            paths.into_iter().partition(|p| is_library(p));
+        for path in libraries {
+            workspace
+                .library
+                .merge(parser::parse_library_file(&path)?.library);
+        }
+        for path in documents {
+            let mut document = parser::parse_file(&path)?;
+            workspace.library.expand(&mut document);
+            workspace.add(path, document);
+        }
+        Ok(workspace)
+    }
+
+    /// 解決の上限を置き換える。source_usecase の参照は `max_reference_depth` 段までたどる
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    pub fn add(&mut self, path: impl Into<String>, document: UsmlDocument) {
+        self.documents.push(WorkspaceDocument {
+            path: path.into(),
+            document,
+        });
+    }
+
+    pub fn documents(&self) -> &[WorkspaceDocument] {
+        &self.documents
+    }
+
+    /// ワークスペースの共有ライブラリ
+    pub fn library(&self) -> &Library {
+        &self.library
+    }
+
+    /// usecase.name からドキュメントを探す（同名が複数あれば最初のもの）
+    pub fn usecase(&self, name: &str) -> Option<&WorkspaceDocument> {
+        self.documents
+            .iter()
+            .find(|d| d.document.usecase.name == name)
+    }
+
+    /// `tag` の付いたドキュメント（パス順）
+    pub fn tagged<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = &'a WorkspaceDocument> {
+        self.documents
+            .iter()
+            .filter(move |d| d.document.usecase.tags.iter().any(|t| t == tag))
+    }
+
+    /// タグごとのドキュメントの一覧（タグの名前順、タグの無いドキュメントは含まない）
+    pub fn tags(&self) -> BTreeMap<&str, Vec<&WorkspaceDocument>> {
+        let mut tags: BTreeMap<&str, Vec<&WorkspaceDocument>> = BTreeMap::new();
+        for entry in &self.documents {
+            for tag in &entry.document.usecase.tags {
+                let entries = tags.entry(tag.as_str()).or_default();
+                if !entries.iter().any(|e| std::ptr::eq(*e, entry)) {
+                    entries.push(entry);
+                }
+            }
+        }
+        tags
+    }
+
+    /// `doc` の source_usecase の参照（U037）と、共有ライブラリの `use`（U038）を検証する
+    ///
+    /// `doc` はワークスペースに含まれていなくてもよい
+    pub fn check(&self, doc: &UsmlDocument) -> Vec<ValidationError> {
+        let mut errors = self.library.check(doc);
+        for field in Model::build(doc).fields {
+            if let Some(target) = &field.source_usecase
+                && self.usecase(target).is_none()
+            {
+                errors.push(
+                    ValidationError::new(
+                        &rules::USECASE_REFERENCE,
+                        format!(
+                            "フィールド '{}' の source_usecase '{}' がワークスペースにありません",
+                            field.path, target
+                        ),
+                    )
+                    .at(format!("{}/source_usecase", field.pointer)),
+                );
+            }
+        }
+        let mut stack = vec![doc.usecase.name.clone()];
+        if let Some(cycle) = self.find_cycle(doc, &mut stack, &mut HashSet::new()) {
+            errors.push(ValidationError::new(
+                &rules::USECASE_REFERENCE,
+                format!("usecase の参照が循環しています（{}）", cycle.join(" → ")),
+            ));
+        } else if let Some(chain) = self.find_deep_chain(doc, &mut stack) {
+            errors.push(ValidationError::new(
+                &rules::IMPORT_LIMIT,
+                format!(
+                    "source_usecase の参照が {} 段を超えています（{}）",
+                    self.limits.max_reference_depth,
+                    chain.join(" → ")
+                ),
+            ));
+        }
+        errors
+    }
+
+    /// 全ドキュメントの参照と usecase.name の重複を検証し、ファイルの位置付きの診断にする
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        self.documents
+            .iter()
+            .flat_map(|entry| self.validate_document(entry))
+            .collect()
+    }
+
+    /// ワークスペース内の 1 ドキュメントについて `validate` と同じ検証をする
+    pub fn validate_document(&self, entry: &WorkspaceDocument) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        let name = &entry.document.usecase.name;
+        let location = Some(Location::file(entry.path.as_str()));
+        if let Some(first) = self
+            .documents
+            .iter()
+            .take_while(|d| !std::ptr::eq(*d, entry))
+            .find(|d| d.document.usecase.name == *name)
+        {
+            diagnostics.push(
+                Diagnostic::from(ValidationError::new(
+                    &rules::USECASE_REFERENCE,
+                    format!("usecase '{}' は {} でも定義されています", name, first.path),
+                ))
+                .with_location(location.clone()),
+            );
+        }
+        diagnostics.extend(
+            self.check(&entry.document)
+                .into_iter()
+                .map(|err| Diagnostic::from(err).with_location(location.clone())),
+        );
+        diagnostics
+    }
+
+    /// `doc` のリネージに、source_usecase の参照先のリネージを合成する
+    ///
+    /// 参照先の辺はフィールドのパスを参照元のフィールドの下に付け替え、`usecase` に参照先の名前を入れる
+    pub fn lineage(&self, doc: &UsmlDocument) -> Vec<LineageEdge> {
+        let mut edges = Vec::new();
+        let mut visiting = vec![doc.usecase.name.clone()];
+        self.collect_lineage(doc, None, None, &mut visiting, &mut edges);
+        edges
+    }
+
+    fn collect_lineage(
+        &self,
+        doc: &UsmlDocument,
+        prefix: Option<&str>,
+        via: Option<&str>,
+        visiting: &mut Vec<String>,
+        edges: &mut Vec<LineageEdge>,
+    ) {
+        let model = Model::build(doc);
+        for mut edge in lineage::from_model(&model) {
+            if let Some(prefix) = prefix {
+                edge.field = format!("{}.{}", prefix, edge.field);
+            }
+            if edge.usecase.is_none() {
+                edge.usecase = via.map(str::to_string);
+            }
+            if !edges.contains(&edge) {
+                edges.push(edge);
+            }
+        }
+        for field in &model.fields {
+            let Some(name) = &field.source_usecase else {
+                continue;
+            };
+            let Some(target) = self.usecase(name) else {
+                continue;
+            };
+            if visiting.contains(name) || visiting.len() > self.limits.max_reference_depth {
+                continue;
+            }
+            let path = match prefix {
+                Some(prefix) => format!("{}.{}", prefix, field.path),
+                None => field.path.clone(),
+            };
+            visiting.push(name.clone());
+            self.collect_lineage(&target.document, Some(&path), Some(name), visiting, edges);
+            visiting.pop();
+        }
+    }
+
+    /// `stack` の先頭の usecase に戻る参照の経路を深さ優先で探す
+    fn find_cycle(
+        &self,
+        doc: &UsmlDocument,
+        stack: &mut Vec<String>,
+        visited: &mut HashSet<String>,
+    ) -> Option<Vec<String>> {
+        for (_, target) in references(doc) {
+            if target == stack[0] {
+                let mut cycle = stack.clone();
+                cycle.push(target);
+                return Some(cycle);
+            }
+            if !visited.insert(target.clone()) {
+                continue;
+            }
+            if let Some(next) = self.usecase(&target) {
+                stack.push(target);
+                if let Some(cycle) = self.find_cycle(&next.document, stack, visited) {
+                    return Some(cycle);
+                }
+                stack.pop();
+            }
+        }
+        None
+    }
+
+    /// source_usecase の参照をたどって `max_reference_depth` 段を超える経路を深さ優先で探す
+    ///
+    /// `stack` の usecase の数は段数より 1 多い。経路上にすでにある usecase は循環なのでたどらない
+    fn find_deep_chain(&self, doc: &UsmlDocument, stack: &mut Vec<String>) -> Option<Vec<String>> {
+        if stack.len() > self.limits.max_reference_depth + 1 {
+            return Some(stack.clone());
+        }
+        for (_, target) in references(doc) {
+            if stack.contains(&target) {
+                continue;
+            }
+            if let Some(next) = self.usecase(&target) {
+                stack.push(target);
+                if let Some(chain) = self.find_deep_chain(&next.document, stack) {
+                    return Some(chain);
+                }
+                stack.pop();
+            }
+        }
+        None
+    }
+}
+
+/// (フィールドのフルパス, 参照先の usecase 名) の一覧
+fn references(doc: &UsmlDocument) -> Vec<(String, String)> {
+    Model::build(doc)
+        .fields
+        .into_iter()
+        .filter_map(|f| f.source_usecase.map(|target| (f.path, target)))
+        .collect()
+}
+
+fn is_library(path: &str) -> bool {
+    Path::new(path)
+        .file_name()
+        .is_some_and(|name| name == LIBRARY_FILE)
+}
+
+/// `dir` 以下の `*.usml.yaml`（共有ライブラリを除く）をパス順に返す
+pub fn find_documents(dir: &Path) -> Result<Vec<String>> {
+    let mut paths = Vec::new();
+    collect_files(dir, &mut paths)?;
+    paths.retain(|p| !is_library(p));
+    paths.sort();
+    Ok(paths)
+}
+
+fn collect_files(dir: &Path, paths: &mut Vec<String>) -> Result<()> {
+    let io_error = |source| UsmlError::Io {
+        path: dir.to_string_lossy().to_string(),
+        source,
+    };
+    for entry in fs::read_dir(dir).map_err(io_error)? {
+        let path = entry.map_err(io_error)?.path();
+        if path.is_dir() {
+            collect_files(&path, paths)?;
+        } else if path.to_string_lossy().ends_with(EXTENSION) {
+            paths.push(path.to_string_lossy().to_string());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lineage::EdgeKind;
+
+    fn doc(name: &str, mappings: &str) -> UsmlDocument {
+        parser::parse(&format!(
+            r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["users"]
+    - ./schema.dbml#tables["badges"]
+usecase:
+  name: {}
+  response_mapping:
+{}"#,
+            name, mappings
+        ))
+        .unwrap()
+    }
+
+    fn workspace() -> Workspace {
+        let mut workspace = Workspace::new();
+        workspace.add(
+            "user.usml.yaml",
+            doc(
+                "GetUser",
+                "    - field: name\n      source: users.name\n    - field: badges\n      source_usecase: GetUserBadges\n",
+            ),
+        );
+        workspace.add(
+            "badges.usml.yaml",
+            doc(
+                "GetUserBadges",
+                "    - field: items\n      type: array\n      fields:\n        - field: label\n          source: badges.label\n",
+            ),
+        );
+        workspace
+    }
+
+    #[test]
+    fn test_combined_lineage_follows_source_usecase() {
+        let workspace = workspace();
+        let edges = workspace.lineage(&workspace.documents()[0].document);
+        assert_eq!(edges.len(), 2);
+        assert_eq!(edges[1].field, "badges.items.label");
+        assert_eq!(edges[1].table, "badges");
+        assert_eq!(edges[1].kind, EdgeKind::Source);
+        assert_eq!(edges[1].usecase.as_deref(), Some("GetUserBadges"));
+        assert!(workspace.validate().is_empty());
+    }
+
+    #[test]
+    fn test_documents_by_tag() {
+        let mut workspace = workspace();
+        workspace.documents[0].document.usecase.tags = vec!["users".to_string()];
+        workspace.documents[1].document.usecase.tags =
+            vec!["users".to_string(), "badges".to_string()];
+
+        let names: Vec<_> = workspace
+            .tagged("badges")
+            .map(|d| d.document.usecase.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["GetUserBadges"]);
+        let tags = workspace.tags();
+        assert_eq!(
+            tags.keys().copied().collect::<Vec<_>>(),
+            vec!["badges", "users"]
+        );
+        assert_eq!(tags["users"].len(), 2);
+    }
+
+    #[test]
+    fn test_missing_and_cyclic_references() {
+        let mut workspace = workspace();
+        workspace.add(
+            "cycle.usml.yaml",
+            doc(
+                "GetUserBadges",
+                "    - field: owner\n      source_usecase: GetUser\n    - field: extra\n      source_usecase: Unknown\n",
+            ),
+        );
+        let cyclic = &workspace.documents()[2].document;
+        let messages: Vec<_> = workspace
+            .check(cyclic)
+            .iter()
+            .map(|e| e.to_string())
+            .collect();
+        assert_eq!(messages.len(), 2);
+        assert!(messages[0].contains("'Unknown' がワークスペースにありません"));
+        assert!(messages[1].contains("GetUserBadges → GetUser → GetUserBadges"));
+
+        let diagnostics = workspace.validate();
+        assert!(diagnostics.iter().any(|d| {
+            d.message.contains("badges.usml.yaml でも定義されています")
+                && d.location
+                    .as_ref()
+                    .is_some_and(|l| l.file == "cycle.usml.yaml")
+        }));
+    }
+
+    #[test]
+    fn test_reference_depth_limit() {
+        let mut workspace = workspace();
+        workspace.add(
+            "summary.usml.yaml",
+            doc(
+                "GetSummary",
+                "    - field: user\n      source_usecase: GetUser\n",
+            ),
+        );
+        let summary = &workspace.documents()[2].document;
+        assert!(workspace.check(summary).is_empty());
+
+        workspace.set_limits(Limits {
+            max_reference_depth: 1,
+            ..Limits::default()
+        });
+        let summary = &workspace.documents()[2].document;
+        let errors = workspace.check(summary);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].rule().code, "U042");
+        assert!(
+            errors[0]
+                .to_string()
+                .contains("1 段を超えています（GetSummary → GetUser → GetUserBadges）")
+        );
+        // リネージも上限の段数までしかたどらない
+        assert!(
+            workspace
+                .lineage(summary)
+                .iter()
+                .all(|edge| edge.usecase.as_deref() != Some("GetUserBadges"))
+        );
+    }
+
+    #[test]
+    fn test_find_documents_skips_library() {
+        let root = std::env::temp_dir().join("usml_test_find_documents");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("users")).unwrap();
+        for file in [
+            "users/list.usml.yaml",
+            "posts.usml.yaml",
+            LIBRARY_FILE,
+            "schema.dbml",
+        ] {
+            fs::write(root.join(file), "").unwrap();
+        }
+        let found: Vec<String> = find_documents(&root)
+            .unwrap()
+            .iter()
+            .map(|p| {
+                Path::new(p)
+                    .strip_prefix(&root)
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string()
+            })
+            .collect();
+        assert_eq!(found, ["posts.usml.yaml", "users/list.usml.yaml"]);
+    }
+}
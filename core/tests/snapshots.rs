@@ -0,0 +1,89 @@
+//! `examples/` のフィクスチャから生成物（解析 JSON・診断・HTML・SQL・Kotlin・テストケース）を作り、スナップショットと比較する
+//! 誤りを含むドキュメントは `tests/fixtures/` に置き、診断だけを比較する
+//!
+//! 生成器を変更した場合は `INSTA_UPDATE=always cargo test -p usml_core --all-features --test snapshots`
+//! （または `cargo insta review`）でスナップショットを更新し、差分をレビューする
+#![cfg(all(feature = "resolver-openapi", feature = "resolver-dbml"))]
+
+use std::fs;
+
+use usml_core::resolver::FsResolver;
+use usml_core::{api, parser};
+
+/// import の基準ディレクトリ。診断のパスがマシンに依存しないよう相対パスにする
+const EXAMPLES_DIR: &str = "../examples";
+
+#[test]
+fn test_analysis_snapshots() {
+    insta::glob!("../../examples", "*.usml.yaml", |path| {
+        let source = fs::read_to_string(path).unwrap();
+        let doc = parser::parse(&source).unwrap();
+        let analysis = api::analyze_with_resolver(doc, EXAMPLES_DIR, &FsResolver);
+
+        insta::assert_json_snapshot!("diagnostics", analysis.diagnostics);
+        insta::assert_json_snapshot!("analysis", analysis);
+    });
+}
+
+#[test]
+fn test_diagnostics_snapshots_for_invalid_fixtures() {
+    insta::glob!("fixtures", "*.usml.yaml", |path| {
+        let source = fs::read_to_string(path).unwrap();
+        let doc = parser::parse(&source).unwrap();
+        let analysis = api::analyze_with_resolver(doc, "tests/fixtures", &FsResolver);
+        insta::assert_json_snapshot!("fixture_diagnostics", analysis.diagnostics);
+    });
+}
+
+#[test]
+#[cfg(feature = "visualizer")]
+fn test_visualizer_snapshots() {
+    insta::glob!("../../examples", "*.usml.yaml", |path| {
+        let source = fs::read_to_string(path).unwrap();
+        let doc = parser::parse(&source).unwrap();
+        let html = usml_core::visualizer::generate_html(&doc);
+        insta::assert_snapshot!("html", html);
+    });
+}
+
+#[test]
+#[cfg(feature = "codegen")]
+fn test_codegen_snapshots() {
+    use usml_core::ast::Hydration;
+    use usml_core::codegen;
+
+    insta::glob!("../../examples", "*.usml.yaml", |path| {
+        let source = fs::read_to_string(path).unwrap();
+        let file = path.file_name().unwrap().to_str().unwrap();
+        let analyze = |doc| api::analyze_with_resolver(doc, EXAMPLES_DIR, &FsResolver);
+
+        let analysis = analyze(parser::parse(&source).unwrap());
+        insta::assert_snapshot!("sql", codegen::sql::generate(file, &analysis).unwrap());
+        insta::assert_json_snapshot!(
+            "sql_prepared",
+            codegen::sql::prepared(file, &analysis).unwrap()
+        );
+        insta::assert_snapshot!(
+            "kotlin",
+            codegen::kotlin::generate(file, &analysis, None).unwrap()
+        );
+        insta::assert_json_snapshot!(
+            "testcase",
+            codegen::testcase::test_case(file, &analysis, &Default::default()).unwrap()
+        );
+
+        // 配列を親の行のキーでまとめて取る場合も
+        let mut doc = parser::parse(&source).unwrap();
+        if doc.usecase.hydration.is_some() {
+            doc.usecase.hydration = Some(Hydration {
+                strategy: "batch".to_string(),
+                dialect: None,
+            });
+            let analysis = analyze(doc);
+            insta::assert_snapshot!(
+                "sql_batch",
+                codegen::sql::generate(file, &analysis).unwrap()
+            );
+        }
+    });
+}
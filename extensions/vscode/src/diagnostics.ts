@@ -3,9 +3,12 @@ import { execSync } from "child_process";
 import { getBinaryPath } from "./utils";
 
 interface DiagnosticEntry {
-  severity: "error" | "warning";
+  severity: "error" | "warning" | "info" | "hint";
+  code?: string;
   rule: string;
   message: string;
+  hints?: string[];
+  doc_url?: string;
 }
 
 interface ValidateResult {
@@ -14,6 +17,27 @@ interface ValidateResult {
   diagnostics: DiagnosticEntry[];
 }
 
+const SEVERITIES: Record<DiagnosticEntry["severity"], vscode.DiagnosticSeverity> = {
+  error: vscode.DiagnosticSeverity.Error,
+  warning: vscode.DiagnosticSeverity.Warning,
+  info: vscode.DiagnosticSeverity.Information,
+  hint: vscode.DiagnosticSeverity.Hint,
+};
+
+// 規則コードから規則のドキュメントに飛べるようにする。添えたヒントはメッセージの下に並べる
+function toDiagnostic(d: DiagnosticEntry): vscode.Diagnostic {
+  const hints = (d.hints ?? []).map((hint) => `\nヒント: ${hint}`).join("");
+  const diagnostic = new vscode.Diagnostic(
+    new vscode.Range(0, 0, 0, 0),
+    `[${d.code ? `${d.code} ` : ""}${d.rule}] ${d.message}${hints}`,
+    SEVERITIES[d.severity] ?? vscode.DiagnosticSeverity.Warning
+  );
+  if (d.code && d.doc_url) {
+    diagnostic.code = { value: d.code, target: vscode.Uri.parse(d.doc_url) };
+  }
+  return diagnostic;
+}
+
 export class UsmlDiagnosticsProvider {
   validate(
     document: vscode.TextDocument,
@@ -41,17 +65,7 @@ export class UsmlDiagnosticsProvider {
       });
 
       const result: ValidateResult = JSON.parse(output);
-      const diagnostics = result.diagnostics.map((d) => {
-        const severity =
-          d.severity === "error"
-            ? vscode.DiagnosticSeverity.Error
-            : vscode.DiagnosticSeverity.Warning;
-        return new vscode.Diagnostic(
-          new vscode.Range(0, 0, 0, 0),
-          `[${d.rule}] ${d.message}`,
-          severity
-        );
-      });
+      const diagnostics = result.diagnostics.map(toDiagnostic);
 
       collection.set(document.uri, diagnostics);
     } catch (e: unknown) {
@@ -61,17 +75,7 @@ export class UsmlDiagnosticsProvider {
           const result: ValidateResult = JSON.parse(
             (e as { stdout: string }).stdout
           );
-          const diagnostics = result.diagnostics.map((d) => {
-            const severity =
-              d.severity === "error"
-                ? vscode.DiagnosticSeverity.Error
-                : vscode.DiagnosticSeverity.Warning;
-            return new vscode.Diagnostic(
-              new vscode.Range(0, 0, 0, 0),
-              `[${d.rule}] ${d.message}`,
-              severity
-            );
-          });
+          const diagnostics = result.diagnostics.map(toDiagnostic);
           collection.set(document.uri, diagnostics);
           return;
         } catch {
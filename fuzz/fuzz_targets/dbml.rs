@@ -0,0 +1,10 @@
+//! 任意の DBML で parse_dbml_content が panic せずに Ok か Err を返すことを確かめる
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use usml_core::resolver::dbml;
+
+fuzz_target!(|input: &str| {
+    let _ = dbml::parse_dbml_content(input, "fuzz.dbml");
+});
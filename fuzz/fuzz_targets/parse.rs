@@ -0,0 +1,15 @@
+//! 任意の入力で parse し、パースできた文書に対して validate / lineage / analyze / visualize が panic しないことを確かめる
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use usml_core::{api, lineage, parser, validator, visualizer};
+
+fuzz_target!(|input: &str| {
+    if let Ok(doc) = parser::parse(input) {
+        let _ = validator::validate(&doc);
+        let _ = lineage::lineage(&doc);
+        let _ = visualizer::generate_html(&doc);
+        let _ = api::analyze(doc);
+    }
+});
@@ -0,0 +1,24 @@
+//! 任意の入力でソーステキストを扱う API（outline・hover・編集）が panic しないことを確かめる
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use usml_core::edit::Editor;
+use usml_core::syntax::Position;
+use usml_core::validator::ResolveContext;
+use usml_core::{hover, outline};
+
+fuzz_target!(|input: (&str, u8, u8)| {
+    let (source, line, column) = input;
+    let _ = outline::outline(source);
+    let position = Position {
+        line: usize::from(line) + 1,
+        column: usize::from(column) + 1,
+    };
+    let _ = hover::hover(source, &ResolveContext::default(), position);
+
+    if let Ok(mut editor) = Editor::new(source) {
+        let _ = editor.add_dbml_import("./schema.dbml#tables[\"users\"]");
+        let _ = editor.rename_field("id", "user_id");
+    }
+});
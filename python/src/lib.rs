@@ -0,0 +1,95 @@
+use pyo3::create_exception;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use serde::Serialize;
+
+use usml_core::ast::UsmlDocument;
+use usml_core::diagnostic;
+use usml_core::resolver::FsResolver;
+use usml_core::{api, lineage as core_lineage, parser, validator, visualizer};
+
+create_exception!(
+    usml,
+    UsmlParseError,
+    PyValueError,
+    "USML ドキュメントのパースに失敗した"
+);
+
+/// serde で直列化できる値を dict / list などの Python オブジェクトに変換する
+fn to_python<'py, T: Serialize>(py: Python<'py>, value: &T) -> PyResult<Bound<'py, PyAny>> {
+    Ok(pythonize::pythonize(py, value)?)
+}
+
+fn parse_source(source: &str) -> PyResult<UsmlDocument> {
+    parser::parse(source).map_err(|e| UsmlParseError::new_err(e.to_string()))
+}
+
+/// USML 文字列をパースして AST を dict で返す
+#[pyfunction]
+fn parse<'py>(py: Python<'py>, source: &str) -> PyResult<Bound<'py, PyAny>> {
+    to_python(py, &parse_source(source)?)
+}
+
+/// import を解決せずにバリデーションし、診断の list を返す
+#[pyfunction]
+fn validate<'py>(py: Python<'py>, source: &str) -> PyResult<Bound<'py, PyAny>> {
+    let doc = parse_source(source)?;
+    let diagnostics = diagnostic::from_errors(validator::validate(&doc));
+    to_python(py, &diagnostics)
+}
+
+/// base_dir を基準に import 先の OpenAPI / DBML を読み込んでバリデーションする
+#[pyfunction]
+fn validate_with_resolve<'py>(
+    py: Python<'py>,
+    source: &str,
+    base_dir: &str,
+) -> PyResult<Bound<'py, PyAny>> {
+    let doc = parse_source(source)?;
+    let diagnostics = py.detach(|| validator::validate_with_resolve(&doc, base_dir));
+    to_python(py, &diagnostics)
+}
+
+/// レスポンスフィールド → テーブル.カラム のリネージを list で返す
+#[pyfunction]
+fn lineage<'py>(py: Python<'py>, source: &str) -> PyResult<Bound<'py, PyAny>> {
+    let doc = parse_source(source)?;
+    to_python(py, &core_lineage::lineage(&doc))
+}
+
+/// AST・診断・リネージ・テーブル利用状況を dict で返す
+///
+/// base_dir を渡すと import 先のファイルも解決する
+#[pyfunction]
+#[pyo3(signature = (source, base_dir=None))]
+fn analyze<'py>(
+    py: Python<'py>,
+    source: &str,
+    base_dir: Option<&str>,
+) -> PyResult<Bound<'py, PyAny>> {
+    let doc = parse_source(source)?;
+    let analysis = match base_dir {
+        Some(base_dir) => py.detach(|| api::analyze_with_resolver(doc, base_dir, &FsResolver)),
+        None => api::analyze(doc),
+    };
+    to_python(py, &analysis)
+}
+
+/// HTML データフロー図を生成する
+#[pyfunction]
+fn generate_html(source: &str) -> PyResult<String> {
+    let doc = parse_source(source)?;
+    Ok(visualizer::generate_html(&doc))
+}
+
+#[pymodule]
+fn usml(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("UsmlParseError", m.py().get_type::<UsmlParseError>())?;
+    m.add_function(wrap_pyfunction!(parse, m)?)?;
+    m.add_function(wrap_pyfunction!(validate, m)?)?;
+    m.add_function(wrap_pyfunction!(validate_with_resolve, m)?)?;
+    m.add_function(wrap_pyfunction!(lineage, m)?)?;
+    m.add_function(wrap_pyfunction!(analyze, m)?)?;
+    m.add_function(wrap_pyfunction!(generate_html, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,49 @@
+import pytest
+
+import usml
+
+DOC = """
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: ユーザー取得
+  response_mapping:
+    - field: name
+      source: users.name
+    - field: display_name
+      source: users.nickname
+  transforms:
+    - target: display_name
+      type: COALESCE
+      sources:
+        - users.nickname
+        - users.name
+"""
+
+
+def test_parse_returns_dict():
+    doc = usml.parse(DOC)
+    assert doc["usecase"]["name"] == "ユーザー取得"
+
+
+def test_parse_error_is_value_error():
+    with pytest.raises(usml.UsmlParseError):
+        usml.parse("version: [")
+    assert issubclass(usml.UsmlParseError, ValueError)
+
+
+def test_validate_returns_diagnostics():
+    assert isinstance(usml.validate(DOC), list)
+
+
+def test_analyze():
+    analysis = usml.analyze(DOC)
+    assert set(analysis) == {"document", "schemas", "diagnostics", "lineage", "tables"}
+    assert analysis["tables"][0]["name"] == "users"
+
+
+def test_lineage():
+    edges = usml.lineage(DOC)
+    assert {"field": "display_name", "table": "users", "column": "name", "kind": "transform"} in edges
@@ -0,0 +1,161 @@
+use std::io;
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use usml_core::diagnostic::{self, Diagnostic};
+use usml_core::resolver::{Resolver, ResolverError};
+use usml_core::{
+    api, fix, outline as core_outline, parser, semantic_tokens as core_semantic_tokens, validator,
+    visualizer,
+};
+
+/// JS のコールバックからファイル内容を受け取る Resolver
+///
+/// コールバックは `(path: string) => string | null | undefined` の形で、
+/// 見つからないファイルには null / undefined を返す
+struct CallbackResolver<'a> {
+    provider: &'a js_sys::Function,
+}
+
+impl Resolver for CallbackResolver<'_> {
+    fn load(&self, path: &str) -> Result<String, ResolverError> {
+        let io_error = |message: String| ResolverError::Io {
+            file: path.to_string(),
+            source: io::Error::new(io::ErrorKind::NotFound, message),
+        };
+        let value = self
+            .provider
+            .call1(&JsValue::NULL, &JsValue::from_str(path))
+            .map_err(|e| io_error(format!("ファイルプロバイダでエラーが発生しました: {:?}", e)))?;
+        value
+            .as_string()
+            .ok_or_else(|| io_error("ファイルプロバイダが内容を返しませんでした".to_string()))
+    }
+}
+
+/// USML 文字列をバリデーションする。パースエラーも `parse` 規則の診断として返す
+fn validate_source(input: &str, resolve: Option<(&str, &dyn Resolver)>) -> Vec<Diagnostic> {
+    let doc = match parser::parse(input) {
+        Ok(doc) => doc,
+        Err(e) => return vec![Diagnostic::error("parse", e.to_string())],
+    };
+    match resolve {
+        Some((base_dir, resolver)) => validator::validate_with_resolver(&doc, base_dir, resolver),
+        None => diagnostic::from_errors(validator::validate(&doc)),
+    }
+}
+
+fn to_js<T: Serialize>(value: &T) -> Result<JsValue, JsError> {
+    value
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(|e| JsError::new(&e.to_string()))
+}
+
+/// USML 文字列をパースして AST を返す
+#[wasm_bindgen]
+pub fn parse(input: &str) -> Result<JsValue, JsError> {
+    let doc = parser::parse(input).map_err(|e| JsError::new(&e.to_string()))?;
+    to_js(&doc)
+}
+
+/// テーブル名・カラム参照・パラメータ・API パス・transform の種類のトークンを範囲付きで返す
+#[wasm_bindgen(js_name = semanticTokens)]
+pub fn semantic_tokens(input: &str) -> Result<JsValue, JsError> {
+    let tokens =
+        core_semantic_tokens::semantic_tokens(input).map_err(|e| JsError::new(&e.to_string()))?;
+    to_js(&tokens)
+}
+
+/// import を解決せずにバリデーションし、診断の配列を返す
+#[wasm_bindgen]
+pub fn validate(input: &str) -> Result<JsValue, JsError> {
+    to_js(&validate_source(input, None))
+}
+
+/// `provider` コールバックで import 先のファイルを読み込んでバリデーションする
+#[wasm_bindgen(js_name = validateWithFiles)]
+pub fn validate_with_files(
+    input: &str,
+    base_dir: &str,
+    provider: &js_sys::Function,
+) -> Result<JsValue, JsError> {
+    let resolver = CallbackResolver { provider };
+    to_js(&validate_source(input, Some((base_dir, &resolver))))
+}
+
+/// AST・診断・リネージ・テーブル利用状況をまとめて返す
+#[wasm_bindgen]
+pub fn analyze(input: &str) -> Result<JsValue, JsError> {
+    let doc = parser::parse(input).map_err(|e| JsError::new(&e.to_string()))?;
+    to_js(&api::analyze(doc))
+}
+
+/// エディタのアウトライン用にユースケース・フィールド・filters・transforms を範囲付きで返す
+#[wasm_bindgen]
+pub fn outline(input: &str) -> Result<JsValue, JsError> {
+    let symbols = core_outline::outline(input).map_err(|e| JsError::new(&e.to_string()))?;
+    to_js(&symbols)
+}
+
+/// import を解決せずにバリデーションし、直し方のある診断の直し方（置換と範囲）を返す
+#[wasm_bindgen(js_name = codeActions)]
+pub fn code_actions(input: &str) -> Result<JsValue, JsError> {
+    to_js(&fix::code_actions(input, &validate_source(input, None)))
+}
+
+/// USML 文字列から HTML データフロー図を生成する
+#[wasm_bindgen]
+pub fn visualize(input: &str) -> Result<String, JsError> {
+    let doc = parser::parse(input).map_err(|e| JsError::new(&e.to_string()))?;
+    Ok(visualizer::generate_html(&doc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct MapResolver(Vec<(&'static str, &'static str)>);
+
+    impl Resolver for MapResolver {
+        fn load(&self, path: &str) -> Result<String, ResolverError> {
+            self.0
+                .iter()
+                .find(|(p, _)| *p == path)
+                .map(|(_, content)| content.to_string())
+                .ok_or_else(|| ResolverError::NotFound(path.to_string()))
+        }
+    }
+
+    #[test]
+    fn test_validate_source_reports_parse_error() {
+        let diagnostics = validate_source("version: [", None);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].rule, "parse");
+    }
+
+    #[test]
+    fn test_validate_source_with_provided_files() {
+        let yaml = r#"
+version: "0.1"
+import:
+  dbml:
+    - ./schema.dbml#tables["users"]
+usecase:
+  name: テスト
+  response_mapping:
+    - field: phone
+      source: users.phone
+"#;
+        let resolver = MapResolver(vec![(
+            "playground/./schema.dbml",
+            "Table users {\n  id integer [pk]\n}\n",
+        )]);
+        let diagnostics = validate_source(yaml, Some(("playground", &resolver)));
+        assert!(
+            diagnostics
+                .iter()
+                .any(|d| d.rule == "response_mapping.source")
+        );
+    }
+}